@@ -0,0 +1,319 @@
+//! Instruction types
+
+use crate::{get_associated_token_address_with_program_id, id};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar,
+};
+
+/// Instructions supported by the AssociatedTokenAccount program
+#[derive(Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub enum AssociatedTokenAccountInstruction {
+    /// Creates an associated token account for the given wallet address and token mint.
+    /// Fails if the account exists.
+    ///
+    /// Empty instruction data is also read as `Create`, the only instruction of the program
+    /// before instruction data was introduced.
+    ///
+    ///   0. `[writeable,signer]` Funding account (must be a system account)
+    ///   1. `[writeable]` Associated token account address to be created
+    ///   2. `[]` Wallet address for the new associated token account
+    ///   3. `[]` The token mint for the new associated token account
+    ///   4. `[]` System program
+    ///   5. `[]` SPL Token program, or another program implementing its interface
+    ///   6. `[]` Rent sysvar
+    Create,
+    /// Creates an associated token account for the given wallet address and token mint,
+    /// doing nothing if the account already exists for that wallet and mint.  Fails if the
+    /// account exists with a different owner or mint.
+    ///
+    ///   0. `[writeable,signer]` Funding account (must be a system account)
+    ///   1. `[writeable]` Associated token account address to be created
+    ///   2. `[]` Wallet address for the new associated token account
+    ///   3. `[]` The token mint for the new associated token account
+    ///   4. `[]` System program
+    ///   5. `[]` SPL Token program, or another program implementing its interface
+    ///   6. `[]` Rent sysvar
+    CreateIdempotent,
+    /// Transfers all tokens out of a nested associated token account, one whose owner is
+    /// another associated token account of the wallet, into the wallet's associated token
+    /// account for the nested mint, and closes the nested account, returning its lamports to
+    /// the wallet.
+    ///
+    /// The owner associated token account signs for the nested account through its derived
+    /// address. Transfer fees withheld in the nested account are harvested to the nested mint
+    /// before it is closed.
+    ///
+    ///   0. `[writeable]` Nested associated token account, owned by the owner associated
+    ///      token account
+    ///   1. `[writeable]` Token mint for the nested associated token account
+    ///   2. `[writeable]` Wallet's associated token account for the nested mint
+    ///   3. `[]` Owner associated token account address, owned by the wallet
+    ///   4. `[]` Token mint for the owner associated token account
+    ///   5. `[writeable,signer]` Wallet address for the owner associated token account
+    ///   6. `[]` SPL Token program, or another program implementing its interface
+    RecoverNested,
+}
+
+impl AssociatedTokenAccountInstruction {
+    /// Unpacks a byte buffer into an
+    /// [AssociatedTokenAccountInstruction](enum.AssociatedTokenAccountInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.is_empty() {
+            return Ok(Self::Create);
+        }
+        Self::try_from_slice(input).map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+/// Creates a `Create` instruction
+pub fn create_associated_token_account(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    spl_token_mint_address: &Pubkey,
+) -> Instruction {
+    create_associated_token_account_with_program_id(
+        funding_address,
+        wallet_address,
+        spl_token_mint_address,
+        &spl_token::id(),
+    )
+}
+
+/// Creates a `Create` instruction for a mint of the given token program
+pub fn create_associated_token_account_with_program_id(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let mut instruction = build_create_associated_token_account(
+        funding_address,
+        wallet_address,
+        token_mint_address,
+        token_program_id,
+        &AssociatedTokenAccountInstruction::Create,
+    );
+    // Programs deployed before instructions carried data only accept empty data
+    instruction.data.clear();
+    instruction
+}
+
+/// Creates a `CreateIdempotent` instruction
+pub fn create_associated_token_account_idempotent(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    spl_token_mint_address: &Pubkey,
+) -> Instruction {
+    create_associated_token_account_idempotent_with_program_id(
+        funding_address,
+        wallet_address,
+        spl_token_mint_address,
+        &spl_token::id(),
+    )
+}
+
+/// Creates a `CreateIdempotent` instruction for a mint of the given token program
+pub fn create_associated_token_account_idempotent_with_program_id(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    build_create_associated_token_account(
+        funding_address,
+        wallet_address,
+        token_mint_address,
+        token_program_id,
+        &AssociatedTokenAccountInstruction::CreateIdempotent,
+    )
+}
+
+fn build_create_associated_token_account(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+    instruction: &AssociatedTokenAccountInstruction,
+) -> Instruction {
+    let associated_account_address = get_associated_token_address_with_program_id(
+        wallet_address,
+        token_mint_address,
+        token_program_id,
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        instruction,
+        vec![
+            AccountMeta::new(*funding_address, true),
+            AccountMeta::new(associated_account_address, false),
+            AccountMeta::new_readonly(*wallet_address, false),
+            AccountMeta::new_readonly(*token_mint_address, false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(*token_program_id, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+    )
+}
+
+/// Creates a `RecoverNested` instruction
+pub fn recover_nested(
+    wallet_address: &Pubkey,
+    owner_token_mint_address: &Pubkey,
+    nested_token_mint_address: &Pubkey,
+) -> Instruction {
+    recover_nested_with_program_id(
+        wallet_address,
+        owner_token_mint_address,
+        nested_token_mint_address,
+        &spl_token::id(),
+    )
+}
+
+/// Creates a `RecoverNested` instruction for mints of the given token program
+pub fn recover_nested_with_program_id(
+    wallet_address: &Pubkey,
+    owner_token_mint_address: &Pubkey,
+    nested_token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let owner_associated_account_address = get_associated_token_address_with_program_id(
+        wallet_address,
+        owner_token_mint_address,
+        token_program_id,
+    );
+    let destination_associated_account_address = get_associated_token_address_with_program_id(
+        wallet_address,
+        nested_token_mint_address,
+        token_program_id,
+    );
+    let nested_associated_account_address = get_associated_token_address_with_program_id(
+        &owner_associated_account_address,
+        nested_token_mint_address,
+        token_program_id,
+    );
+
+    Instruction::new_with_borsh(
+        id(),
+        &AssociatedTokenAccountInstruction::RecoverNested,
+        vec![
+            AccountMeta::new(nested_associated_account_address, false),
+            AccountMeta::new(*nested_token_mint_address, false),
+            AccountMeta::new(destination_associated_account_address, false),
+            AccountMeta::new_readonly(owner_associated_account_address, false),
+            AccountMeta::new_readonly(*owner_token_mint_address, false),
+            AccountMeta::new(*wallet_address, true),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_associated_token_address;
+
+    #[test]
+    fn test_unpack() {
+        // empty data is the original create instruction
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&[]),
+            Ok(AssociatedTokenAccountInstruction::Create)
+        );
+
+        let check = AssociatedTokenAccountInstruction::Create;
+        let packed = check.try_to_vec().unwrap();
+        assert_eq!(packed, vec![0]);
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&packed),
+            Ok(check)
+        );
+
+        let check = AssociatedTokenAccountInstruction::CreateIdempotent;
+        let packed = check.try_to_vec().unwrap();
+        assert_eq!(packed, vec![1]);
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&packed),
+            Ok(check)
+        );
+
+        let check = AssociatedTokenAccountInstruction::RecoverNested;
+        let packed = check.try_to_vec().unwrap();
+        assert_eq!(packed, vec![2]);
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&packed),
+            Ok(check)
+        );
+
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&[3]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&[0, 0]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_builders() {
+        let funding_address = Pubkey::new_unique();
+        let wallet_address = Pubkey::new_unique();
+        let mint_address = Pubkey::new_unique();
+
+        let instruction =
+            create_associated_token_account(&funding_address, &wallet_address, &mint_address);
+        assert!(instruction.data.is_empty());
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            get_associated_token_address(&wallet_address, &mint_address)
+        );
+
+        let instruction = create_associated_token_account_idempotent(
+            &funding_address,
+            &wallet_address,
+            &mint_address,
+        );
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&instruction.data),
+            Ok(AssociatedTokenAccountInstruction::CreateIdempotent)
+        );
+
+        let nested_mint_address = Pubkey::new_unique();
+        let instruction = recover_nested(&wallet_address, &mint_address, &nested_mint_address);
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&instruction.data),
+            Ok(AssociatedTokenAccountInstruction::RecoverNested)
+        );
+        assert_eq!(
+            instruction.accounts[0].pubkey,
+            get_associated_token_address(
+                &get_associated_token_address(&wallet_address, &mint_address),
+                &nested_mint_address
+            )
+        );
+
+        let token_program_id = Pubkey::new_unique();
+        let instruction = create_associated_token_account_with_program_id(
+            &funding_address,
+            &wallet_address,
+            &mint_address,
+            &token_program_id,
+        );
+        let associated_token_address = get_associated_token_address_with_program_id(
+            &wallet_address,
+            &mint_address,
+            &token_program_id,
+        );
+        assert_ne!(
+            associated_token_address,
+            get_associated_token_address(&wallet_address, &mint_address)
+        );
+        assert_eq!(instruction.accounts[1].pubkey, associated_token_address);
+        assert_eq!(instruction.accounts[5].pubkey, token_program_id);
+    }
+}
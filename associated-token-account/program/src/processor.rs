@@ -1,9 +1,10 @@
 //! Program state processor
 
-use crate::*;
+use crate::{instruction::AssociatedTokenAccountInstruction, *};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    instruction::Instruction,
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
@@ -12,12 +13,36 @@ use solana_program::{
     system_instruction,
     sysvar::Sysvar,
 };
+use spl_token::{
+    extension::{transfer_fee::TransferFeeAmount, ExtensionType, StateWithExtensions},
+    state::{Account, Mint},
+};
 
 /// Instruction processor
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _input: &[u8],
+    input: &[u8],
+) -> ProgramResult {
+    match AssociatedTokenAccountInstruction::unpack(input)? {
+        AssociatedTokenAccountInstruction::Create => {
+            process_create_associated_token_account(program_id, accounts, false)
+        }
+        AssociatedTokenAccountInstruction::CreateIdempotent => {
+            process_create_associated_token_account(program_id, accounts, true)
+        }
+        AssociatedTokenAccountInstruction::RecoverNested => {
+            process_recover_nested(program_id, accounts)
+        }
+    }
+}
+
+/// Processes CreateAssociatedTokenAccount, which succeeds without doing anything when
+/// `idempotent` is set and the account already exists for the wallet and mint
+fn process_create_associated_token_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    idempotent: bool,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
@@ -41,6 +66,21 @@ pub fn process_instruction(
         return Err(ProgramError::InvalidSeeds);
     }
 
+    if idempotent && associated_token_account_info.owner == spl_token_program_id {
+        let associated_token_account_data = associated_token_account_info.data.borrow();
+        let associated_token_account =
+            StateWithExtensions::<Account>::unpack(&associated_token_account_data)?;
+        // the address is derived from the wallet and mint, but the account owner may have been
+        // reassigned since
+        if associated_token_account.base.owner != *wallet_account_info.key
+            || associated_token_account.base.mint != *spl_token_mint_info.key
+        {
+            msg!("Error: Associated token account exists with a different owner or mint");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        return Ok(());
+    }
+
     let associated_token_account_signer_seeds: &[&[_]] = &[
         &wallet_account_info.key.to_bytes(),
         &spl_token_program_id.to_bytes(),
@@ -48,10 +88,21 @@ pub fn process_instruction(
         &[bump_seed],
     ];
 
+    // Size the account for any extensions required by the mint, an invalid mint is rejected by
+    // the token program when initializing the account
+    let account_len = StateWithExtensions::<Mint>::unpack(&spl_token_mint_info.data.borrow())
+        .and_then(|mint| mint.get_extension_types())
+        .map(|mint_extension_types| {
+            ExtensionType::get_account_len::<Account>(
+                &ExtensionType::get_required_init_account_extensions(&mint_extension_types),
+            )
+        })
+        .unwrap_or(Account::LEN);
+
     // Fund the associated token account with the minimum balance to be rent exempt
     let rent = &Rent::from_account_info(rent_sysvar_info)?;
     let required_lamports = rent
-        .minimum_balance(spl_token::state::Account::LEN)
+        .minimum_balance(account_len)
         .max(1)
         .saturating_sub(associated_token_account_info.lamports());
 
@@ -76,10 +127,7 @@ pub fn process_instruction(
 
     msg!("Allocate space for the associated token account");
     invoke_signed(
-        &system_instruction::allocate(
-            associated_token_account_info.key,
-            spl_token::state::Account::LEN as u64,
-        ),
+        &system_instruction::allocate(associated_token_account_info.key, account_len as u64),
         &[
             associated_token_account_info.clone(),
             system_program_info.clone(),
@@ -99,12 +147,14 @@ pub fn process_instruction(
 
     msg!("Initialize the associated token account");
     invoke(
-        &spl_token::instruction::initialize_account(
-            spl_token_program_id,
-            associated_token_account_info.key,
-            spl_token_mint_info.key,
-            wallet_account_info.key,
-        )?,
+        &token_program_instruction(spl_token_program_id, |token_program_id| {
+            spl_token::instruction::initialize_account(
+                token_program_id,
+                associated_token_account_info.key,
+                spl_token_mint_info.key,
+                wallet_account_info.key,
+            )
+        })?,
         &[
             associated_token_account_info.clone(),
             spl_token_mint_info.clone(),
@@ -114,3 +164,181 @@ pub fn process_instruction(
         ],
     )
 }
+
+/// Builds an spl-token instruction for `token_program_id`, which may be any program that
+/// implements the spl-token interface.  The spl-token builders only accept the SPL Token program
+/// id, so the instruction is built for it and then retargeted.
+fn token_program_instruction(
+    token_program_id: &Pubkey,
+    build: impl FnOnce(&Pubkey) -> Result<Instruction, ProgramError>,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = build(&spl_token::id())?;
+    instruction.program_id = *token_program_id;
+    Ok(instruction)
+}
+
+/// Processes RecoverNested, moving the tokens of an associated token account owned by another
+/// of the wallet's associated token accounts back to the wallet
+fn process_recover_nested(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let nested_associated_token_account_info = next_account_info(account_info_iter)?;
+    let nested_token_mint_info = next_account_info(account_info_iter)?;
+    let destination_associated_token_account_info = next_account_info(account_info_iter)?;
+    let owner_associated_token_account_info = next_account_info(account_info_iter)?;
+    let owner_token_mint_info = next_account_info(account_info_iter)?;
+    let wallet_account_info = next_account_info(account_info_iter)?;
+    let spl_token_program_info = next_account_info(account_info_iter)?;
+    let spl_token_program_id = spl_token_program_info.key;
+
+    // The owner associated token account signs for the nested account
+    let (owner_associated_token_address, bump_seed) =
+        get_associated_token_address_and_bump_seed_internal(
+            wallet_account_info.key,
+            owner_token_mint_info.key,
+            program_id,
+            spl_token_program_id,
+        );
+    if owner_associated_token_address != *owner_associated_token_account_info.key {
+        msg!("Error: Owner associated address does not match seed derivation");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let (nested_associated_token_address, _) = get_associated_token_address_and_bump_seed_internal(
+        owner_associated_token_account_info.key,
+        nested_token_mint_info.key,
+        program_id,
+        spl_token_program_id,
+    );
+    if nested_associated_token_address != *nested_associated_token_account_info.key {
+        msg!("Error: Nested associated address does not match seed derivation");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let (destination_associated_token_address, _) =
+        get_associated_token_address_and_bump_seed_internal(
+            wallet_account_info.key,
+            nested_token_mint_info.key,
+            program_id,
+            spl_token_program_id,
+        );
+    if destination_associated_token_address != *destination_associated_token_account_info.key {
+        msg!("Error: Destination associated address does not match seed derivation");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if !wallet_account_info.is_signer {
+        msg!("Error: Wallet of the owner associated token account must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if owner_token_mint_info.owner != spl_token_program_id {
+        msg!("Error: Owner mint not owned by the token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    // The wallet may have handed the owner associated token account over to someone else
+    if owner_associated_token_account_info.owner != spl_token_program_id {
+        msg!("Error: Owner associated token account not owned by the token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let owner_account =
+        StateWithExtensions::<Account>::unpack(&owner_associated_token_account_info.data.borrow())?
+            .base;
+    if owner_account.owner != *wallet_account_info.key {
+        msg!("Error: Owner associated token account not owned by the wallet");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if nested_associated_token_account_info.owner != spl_token_program_id {
+        msg!("Error: Nested associated token account not owned by the token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let (amount, withheld_amount) = {
+        let nested_data = nested_associated_token_account_info.data.borrow();
+        let nested_account = StateWithExtensions::<Account>::unpack(&nested_data)?;
+        let withheld_amount = nested_account
+            .get_extension::<TransferFeeAmount>()
+            .map(|transfer_fee_amount| transfer_fee_amount.withheld_amount)
+            .unwrap_or(0);
+        (nested_account.base.amount, withheld_amount)
+    };
+
+    if nested_token_mint_info.owner != spl_token_program_id {
+        msg!("Error: Nested mint not owned by the token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let decimals = StateWithExtensions::<Mint>::unpack(&nested_token_mint_info.data.borrow())?
+        .base
+        .decimals;
+
+    let owner_associated_token_account_signer_seeds: &[&[_]] = &[
+        &wallet_account_info.key.to_bytes(),
+        &spl_token_program_id.to_bytes(),
+        &owner_token_mint_info.key.to_bytes(),
+        &[bump_seed],
+    ];
+
+    msg!("Transfer the nested balance to the destination associated token account");
+    invoke_signed(
+        &token_program_instruction(spl_token_program_id, |token_program_id| {
+            spl_token::instruction::transfer_checked(
+                token_program_id,
+                nested_associated_token_account_info.key,
+                nested_token_mint_info.key,
+                destination_associated_token_account_info.key,
+                owner_associated_token_account_info.key,
+                &[],
+                amount,
+                decimals,
+            )
+        })?,
+        &[
+            nested_associated_token_account_info.clone(),
+            nested_token_mint_info.clone(),
+            destination_associated_token_account_info.clone(),
+            owner_associated_token_account_info.clone(),
+            spl_token_program_info.clone(),
+        ],
+        &[owner_associated_token_account_signer_seeds],
+    )?;
+
+    // The token program refuses to close an account with withheld transfer fees, and anyone
+    // may harvest them to the mint
+    if withheld_amount != 0 {
+        msg!("Harvest the withheld transfer fees of the nested account to the mint");
+        invoke(
+            &token_program_instruction(spl_token_program_id, |token_program_id| {
+                spl_token::instruction::harvest_withheld_tokens_to_mint(
+                    token_program_id,
+                    nested_token_mint_info.key,
+                    &[nested_associated_token_account_info.key],
+                )
+            })?,
+            &[
+                nested_token_mint_info.clone(),
+                nested_associated_token_account_info.clone(),
+                spl_token_program_info.clone(),
+            ],
+        )?;
+    }
+
+    msg!("Close the nested associated token account");
+    invoke_signed(
+        &token_program_instruction(spl_token_program_id, |token_program_id| {
+            spl_token::instruction::close_account(
+                token_program_id,
+                nested_associated_token_account_info.key,
+                wallet_account_info.key,
+                owner_associated_token_account_info.key,
+                &[],
+            )
+        })?,
+        &[
+            nested_associated_token_account_info.clone(),
+            wallet_account_info.clone(),
+            owner_associated_token_account_info.clone(),
+            spl_token_program_info.clone(),
+        ],
+        &[owner_associated_token_account_signer_seeds],
+    )
+}
@@ -4,10 +4,17 @@
 use solana_program::{instruction::*, program_pack::Pack, pubkey::Pubkey, system_instruction};
 use solana_program_test::*;
 use solana_sdk::{
-    signature::Signer,
+    signature::{Keypair, Signer},
     transaction::{Transaction, TransactionError},
 };
-use spl_associated_token_account::*;
+use spl_associated_token_account::{instruction::*, *};
+use spl_token::{
+    extension::{
+        transfer_fee::{TransferFeeAmount, TransferFeeConfig},
+        ExtensionType, StateWithExtensions,
+    },
+    state::{Account, Mint},
+};
 
 fn program_test(token_mint_address: Pubkey) -> ProgramTest {
     let mut pc = ProgramTest::new(
@@ -242,3 +249,481 @@ async fn test_create_account_mismatch() {
         TransactionError::InstructionError(0, InstructionError::InvalidSeeds)
     );
 }
+
+#[tokio::test]
+async fn test_create_idempotent() {
+    let wallet = Keypair::new();
+    let wallet_address = wallet.pubkey();
+    let token_mint_address = Pubkey::new_unique();
+    let associated_token_address =
+        get_associated_token_address(&wallet_address, &token_mint_address);
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test(token_mint_address).start().await;
+
+    // Creates the account when it does not exist
+    let mut transaction = Transaction::new_with_payer(
+        &[create_associated_token_account_idempotent(
+            &payer.pubkey(),
+            &wallet_address,
+            &token_mint_address,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let associated_account = banks_client
+        .get_account(associated_token_address)
+        .await
+        .expect("get_account")
+        .expect("associated_account not none");
+
+    // Plain create fails now that the account exists
+    let mut transaction = Transaction::new_with_payer(
+        &[create_associated_token_account(
+            &payer.pubkey(),
+            &wallet_address,
+            &token_mint_address,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Idempotent create succeeds and leaves the account alone, this time funded by the wallet
+    // so that the transaction differs from the first one
+    let mut transaction = Transaction::new_with_payer(
+        &[create_associated_token_account_idempotent(
+            &wallet_address,
+            &wallet_address,
+            &token_mint_address,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &wallet], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        banks_client
+            .get_account(associated_token_address)
+            .await
+            .expect("get_account")
+            .expect("associated_account not none"),
+        associated_account
+    );
+
+    // Idempotent create fails once the account owner has been reassigned
+    let mut transaction = Transaction::new_with_payer(
+        &[spl_token::instruction::set_authority(
+            &spl_token::id(),
+            &associated_token_address,
+            Some(&payer.pubkey()),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            &wallet_address,
+            &[],
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &wallet], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let funder = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[create_associated_token_account_idempotent(
+            &funder.pubkey(),
+            &wallet_address,
+            &token_mint_address,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &funder], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidAccountData)
+    );
+}
+
+#[tokio::test]
+async fn test_recover_nested() {
+    let wallet = Keypair::new();
+    let wallet_address = wallet.pubkey();
+    let owner_token_mint_address = Pubkey::new_unique();
+    let nested_token_mint = Keypair::new();
+    let nested_token_mint_address = nested_token_mint.pubkey();
+    let owner_associated_token_address =
+        get_associated_token_address(&wallet_address, &owner_token_mint_address);
+    let nested_associated_token_address =
+        get_associated_token_address(&owner_associated_token_address, &nested_token_mint_address);
+    let destination_associated_token_address =
+        get_associated_token_address(&wallet_address, &nested_token_mint_address);
+
+    let (mut banks_client, payer, recent_blockhash) =
+        program_test(owner_token_mint_address).start().await;
+    let rent = banks_client.get_rent().await.unwrap();
+
+    // Send tokens of a new mint to the associated token account of the owner associated token
+    // account
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &nested_token_mint_address,
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &nested_token_mint_address,
+                &payer.pubkey(),
+                None,
+                0,
+            )
+            .unwrap(),
+            create_associated_token_account(
+                &payer.pubkey(),
+                &wallet_address,
+                &owner_token_mint_address,
+            ),
+            create_associated_token_account(
+                &payer.pubkey(),
+                &owner_associated_token_address,
+                &nested_token_mint_address,
+            ),
+            create_associated_token_account(
+                &payer.pubkey(),
+                &wallet_address,
+                &nested_token_mint_address,
+            ),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &nested_token_mint_address,
+                &nested_associated_token_address,
+                &payer.pubkey(),
+                &[],
+                100,
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &nested_token_mint], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The wallet must sign
+    let mut instruction = recover_nested(
+        &wallet_address,
+        &owner_token_mint_address,
+        &nested_token_mint_address,
+    );
+    instruction.accounts[5] = AccountMeta::new(wallet_address, false);
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[recover_nested(
+            &wallet_address,
+            &owner_token_mint_address,
+            &nested_token_mint_address,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &wallet], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The tokens are back with the wallet and the nested account is closed
+    let destination_account = banks_client
+        .get_account(destination_associated_token_address)
+        .await
+        .expect("get_account")
+        .expect("destination_account not none");
+    assert_eq!(
+        spl_token::state::Account::unpack(&destination_account.data)
+            .unwrap()
+            .amount,
+        100
+    );
+    assert_eq!(
+        banks_client
+            .get_account(nested_associated_token_address)
+            .await
+            .expect("get_account"),
+        None,
+    );
+    assert_eq!(
+        banks_client.get_balance(wallet_address).await.unwrap(),
+        rent.minimum_balance(spl_token::state::Account::LEN)
+    );
+}
+
+/// Retargets an instruction built by the spl-token builders, which only accept the SPL Token
+/// program id, to another program implementing its interface
+fn with_program_id(mut instruction: Instruction, program_id: &Pubkey) -> Instruction {
+    instruction.program_id = *program_id;
+    instruction
+}
+
+#[tokio::test]
+async fn test_recover_nested_with_withheld_transfer_fees() {
+    let wallet = Keypair::new();
+    let wallet_address = wallet.pubkey();
+    let owner_token_mint_address = Pubkey::new_unique();
+    let nested_token_mint = Keypair::new();
+    let nested_token_mint_address = nested_token_mint.pubkey();
+    let token_program_id = Pubkey::new_unique();
+    let owner_associated_token_address = get_associated_token_address_with_program_id(
+        &wallet_address,
+        &owner_token_mint_address,
+        &token_program_id,
+    );
+    let nested_associated_token_address = get_associated_token_address_with_program_id(
+        &owner_associated_token_address,
+        &nested_token_mint_address,
+        &token_program_id,
+    );
+    let destination_associated_token_address = get_associated_token_address_with_program_id(
+        &wallet_address,
+        &nested_token_mint_address,
+        &token_program_id,
+    );
+
+    // The SPL Token program bundled with the test validator has no transfer fees, so run this
+    // tree's token program at another address
+    let mut pc = program_test(Pubkey::new_unique());
+    pc.add_program(
+        "spl_token_fork",
+        token_program_id,
+        processor!(spl_token::processor::Processor::process),
+    );
+    pc.add_account_with_file_data(
+        owner_token_mint_address,
+        1461600,
+        token_program_id,
+        "token-mint-data.bin",
+    );
+    let (mut banks_client, payer, recent_blockhash) = pc.start().await;
+    let rent = banks_client.get_rent().await.unwrap();
+
+    // Send tokens of a new mint with a 1% transfer fee to the associated token account of the
+    // owner associated token account, withholding a fee there
+    let mint_len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &nested_token_mint_address,
+                rent.minimum_balance(mint_len),
+                mint_len as u64,
+                &token_program_id,
+            ),
+            with_program_id(
+                spl_token::instruction::initialize_transfer_fee_config(
+                    &spl_token::id(),
+                    &nested_token_mint_address,
+                    None,
+                    None,
+                    100,
+                    u64::MAX,
+                )
+                .unwrap(),
+                &token_program_id,
+            ),
+            with_program_id(
+                spl_token::instruction::initialize_mint(
+                    &spl_token::id(),
+                    &nested_token_mint_address,
+                    &payer.pubkey(),
+                    None,
+                    0,
+                )
+                .unwrap(),
+                &token_program_id,
+            ),
+            create_associated_token_account_with_program_id(
+                &payer.pubkey(),
+                &wallet_address,
+                &owner_token_mint_address,
+                &token_program_id,
+            ),
+            create_associated_token_account_with_program_id(
+                &payer.pubkey(),
+                &owner_associated_token_address,
+                &nested_token_mint_address,
+                &token_program_id,
+            ),
+            create_associated_token_account_with_program_id(
+                &payer.pubkey(),
+                &wallet_address,
+                &nested_token_mint_address,
+                &token_program_id,
+            ),
+            with_program_id(
+                spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &nested_token_mint_address,
+                    &destination_associated_token_address,
+                    &payer.pubkey(),
+                    &[],
+                    1000,
+                )
+                .unwrap(),
+                &token_program_id,
+            ),
+            with_program_id(
+                spl_token::instruction::transfer_checked(
+                    &spl_token::id(),
+                    &destination_associated_token_address,
+                    &nested_token_mint_address,
+                    &nested_associated_token_address,
+                    &wallet_address,
+                    &[],
+                    1000,
+                    0,
+                )
+                .unwrap(),
+                &token_program_id,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &nested_token_mint, &wallet], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let nested_account = banks_client
+        .get_account(nested_associated_token_address)
+        .await
+        .expect("get_account")
+        .expect("nested_account not none");
+    let nested_account = StateWithExtensions::<Account>::unpack(&nested_account.data).unwrap();
+    assert_eq!(nested_account.base.amount, 990);
+    assert_eq!(
+        nested_account
+            .get_extension::<TransferFeeAmount>()
+            .unwrap()
+            .withheld_amount,
+        10
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[recover_nested_with_program_id(
+            &wallet_address,
+            &owner_token_mint_address,
+            &nested_token_mint_address,
+            &token_program_id,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &wallet], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The fees withheld in the nested account are harvested to the mint, the transfer back to the
+    // wallet withholds its own fee, and the nested account is closed
+    let nested_token_mint_account = banks_client
+        .get_account(nested_token_mint_address)
+        .await
+        .expect("get_account")
+        .expect("nested_token_mint_account not none");
+    assert_eq!(
+        StateWithExtensions::<Mint>::unpack(&nested_token_mint_account.data)
+            .unwrap()
+            .get_extension::<TransferFeeConfig>()
+            .unwrap()
+            .withheld_amount,
+        10
+    );
+    let destination_account = banks_client
+        .get_account(destination_associated_token_address)
+        .await
+        .expect("get_account")
+        .expect("destination_account not none");
+    let destination_account =
+        StateWithExtensions::<Account>::unpack(&destination_account.data).unwrap();
+    assert_eq!(destination_account.base.amount, 980);
+    assert_eq!(
+        destination_account
+            .get_extension::<TransferFeeAmount>()
+            .unwrap()
+            .withheld_amount,
+        10
+    );
+    assert_eq!(
+        banks_client
+            .get_account(nested_associated_token_address)
+            .await
+            .expect("get_account"),
+        None,
+    );
+    assert_eq!(
+        banks_client.get_balance(wallet_address).await.unwrap(),
+        rent.minimum_balance(ExtensionType::get_account_len::<Account>(&[
+            ExtensionType::TransferFeeAmount
+        ]))
+    );
+}
+
+#[tokio::test]
+async fn test_create_with_alternate_token_program() {
+    let wallet_address = Pubkey::new_unique();
+    let token_mint_address = Pubkey::new_unique();
+    let token_program_id = Pubkey::new_unique();
+    let associated_token_address = get_associated_token_address_with_program_id(
+        &wallet_address,
+        &token_mint_address,
+        &token_program_id,
+    );
+
+    // Run a copy of the token program at another address, with its own mint
+    let mut pc = program_test(Pubkey::new_unique());
+    pc.add_program(
+        "spl_token_fork",
+        token_program_id,
+        processor!(spl_token::processor::Processor::process),
+    );
+    pc.add_account_with_file_data(
+        token_mint_address,
+        1461600,
+        token_program_id,
+        "token-mint-data.bin",
+    );
+    let (mut banks_client, payer, recent_blockhash) = pc.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_associated_token_account_with_program_id(
+            &payer.pubkey(),
+            &wallet_address,
+            &token_mint_address,
+            &token_program_id,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let associated_account = banks_client
+        .get_account(associated_token_address)
+        .await
+        .expect("get_account")
+        .expect("associated_account not none");
+    assert_eq!(associated_account.owner, token_program_id);
+    let account = spl_token::state::Account::unpack(&associated_account.data).unwrap();
+    assert_eq!(account.owner, wallet_address);
+    assert_eq!(account.mint, token_mint_address);
+
+    // The SPL Token address of the same wallet and mint is a different account
+    assert_ne!(
+        associated_token_address,
+        get_associated_token_address(&wallet_address, &token_mint_address)
+    );
+}
@@ -234,11 +234,11 @@ impl PreciseNumber {
     /// t_k+1 = t_k * (x - a) * (n + 1 - k) / k
     ///
     /// where a = 1, n = power, x = precise_num
-    /// NOTE: this function is private because its accurate range and precision
-    /// have not been estbalished.
+    /// Returns `None` for bases outside of the range of convergence.
     fn checked_pow_approximation(&self, exponent: &Self, max_iterations: u128) -> Option<Self> {
-        assert!(self.value >= Self::min_pow_base());
-        assert!(self.value <= Self::max_pow_base());
+        if self.value < Self::min_pow_base() || self.value > Self::max_pow_base() {
+            return None;
+        }
         let one = Self::one();
         if *exponent == Self::zero() {
             return Some(one);
@@ -274,16 +274,20 @@ impl PreciseNumber {
 
     /// Get the power of a number, where the exponent is expressed as a fraction
     /// (numerator / denominator)
-    /// NOTE: this function is private because its accurate range and precision
-    /// have not been estbalished.
-    #[allow(dead_code)]
-    fn checked_pow_fraction(&self, exponent: &Self) -> Option<Self> {
-        assert!(self.value >= Self::min_pow_base());
-        assert!(self.value <= Self::max_pow_base());
+    /// The base must be between 0 and 2, exclusive of 0, since the fractional
+    /// part uses a Taylor Series around 1, which converges faster the closer
+    /// the base is to 1.  For bases between 0.5 and 1.5, results have a relative
+    /// error below 1e-9, or 9 significant digits.
+    pub fn checked_pow_fraction(&self, exponent: &Self) -> Option<Self> {
+        if self.value < Self::min_pow_base() || self.value > Self::max_pow_base() {
+            return None;
+        }
         let whole_exponent = exponent.floor()?;
         let precise_whole = self.checked_pow(whole_exponent.to_imprecise()?)?;
         let (remainder_exponent, negative) = exponent.unsigned_sub(&whole_exponent);
-        assert!(!negative);
+        if negative {
+            return None;
+        }
         if remainder_exponent.value == InnerUint::from(0) {
             return Some(precise_whole);
         }
@@ -441,6 +445,47 @@ mod tests {
             InnerUint::from(8_629769290500u128),
             less_precision,
         ); // 8.629769290
+
+        // bases outside of the series' range of convergence
+        let exponent = PreciseNumber { value: one / 2 };
+        let zero = PreciseNumber { value: zero() };
+        assert_eq!(zero.checked_pow_fraction(&exponent), None);
+        let too_large = PreciseNumber { value: one * 2 + 1 };
+        assert_eq!(too_large.checked_pow_fraction(&exponent), None);
+        assert_eq!(zero.checked_pow_approximation(&exponent, 10), None);
+        assert_eq!(too_large.checked_pow_approximation(&exponent, 10), None);
+    }
+
+    #[test]
+    fn test_pow_fraction_accuracy() {
+        // relative error below 1e-9 for bases between 0.5 and 1.5
+        let one = ONE as f64;
+        for base in (50..=150).step_by(5) {
+            for exponent in (1..=400).step_by(7) {
+                let precise_base = PreciseNumber {
+                    value: InnerUint::from(ONE * base / 100),
+                };
+                let precise_exponent = PreciseNumber {
+                    value: InnerUint::from(ONE * exponent / 100),
+                };
+                let power = precise_base
+                    .checked_pow_fraction(&precise_exponent)
+                    .unwrap()
+                    .value
+                    .as_u128() as f64
+                    / one;
+                let expected = (base as f64 / 100.0).powf(exponent as f64 / 100.0);
+                let relative_error = (power - expected).abs() / expected;
+                assert!(
+                    relative_error < 1e-9,
+                    "{}^{}: {} vs {}",
+                    base,
+                    exponent,
+                    power,
+                    expected
+                );
+            }
+        }
     }
 
     #[test]
@@ -75,12 +75,31 @@ export const TokenSwapLayout = BufferLayout.struct([
   Layout.uint64('hostFeeDenominator'),
   BufferLayout.u8('curveType'),
   BufferLayout.blob(32, 'curveParameters'),
+  BufferLayout.u32('pauseAuthorityOption'),
+  Layout.publicKey('pauseAuthority'),
+  BufferLayout.u8('isPaused'),
+  Layout.uint128('priceACumulative'),
+  Layout.uint128('priceBCumulative'),
+  Layout.int64('lastUpdateTimestamp'),
+  Layout.uint64('volatilityFeeNumerator'),
+  Layout.uint64('volatilityFeeDenominator'),
+  Layout.uint64('maxTradeFeeNumerator'),
+  Layout.uint64('volatilityDecayPeriod'),
+  Layout.uint64('volatility'),
+  Layout.int64('lastVolatilityUpdate'),
+  BufferLayout.u32('oracleOption'),
+  Layout.publicKey('oracle'),
+  Layout.publicKey('oracleProgramId'),
 ]);
 
 export const CurveType = Object.freeze({
   ConstantProduct: 0, // Constant product curve, Uniswap-style
   ConstantPrice: 1, // Constant price curve, always X amount of A token for 1 B token, where X is defined at init
   Offset: 3, // Offset curve, like Uniswap, but with an additional offset on the token B side
+  Weighted: 4, // Weighted curve, like Uniswap, but with configurable weights for token A and B, Balancer-style
+  ConcentratedLiquidity: 5, // Concentrated liquidity curve, like Uniswap, but only providing liquidity between a lower and upper price
+  ProactiveMarketMaking: 6, // Proactive market making curve, DODO-style, concentrating liquidity around the price of an oracle
+  StakePoolStable: 7, // Stable curve between SOL and stake pool tokens, with the 1:1 zone following the stake pool's exchange rate
 });
 
 /**
@@ -191,6 +210,7 @@ export class TokenSwap {
     hostFeeDenominator: number,
     curveType: number,
     curveParameters: Numberu64 = new Numberu64(0),
+    pauseAuthority: PublicKey | null = null,
   ): TransactionInstruction {
     const keys = [
       {pubkey: tokenSwapAccount.publicKey, isSigner: false, isWritable: true},
@@ -244,6 +264,13 @@ export class TokenSwap {
       );
       data = data.slice(0, encodeLength);
     }
+
+    // the pause authority is an optional public key, packed after the curve
+    const pauseAuthorityBuffer =
+      pauseAuthority === null
+        ? Buffer.from([0])
+        : Buffer.concat([Buffer.from([1]), pauseAuthority.toBuffer()]);
+    data = Buffer.concat([data, pauseAuthorityBuffer]);
     return new TransactionInstruction({
       keys,
       programId: swapProgramId,
@@ -343,6 +370,7 @@ export class TokenSwap {
    * @param swapProgramId The program ID of the token-swap program
    * @param feeNumerator Numerator of the fee ratio
    * @param feeDenominator Denominator of the fee ratio
+   * @param pauseAuthority Optional authority able to pause and unpause the swap
    * @return Token object for the newly minted token, Public key of the account holding the total supply of new tokens
    */
   static async createTokenSwap(
@@ -370,6 +398,7 @@ export class TokenSwap {
     hostFeeDenominator: number,
     curveType: number,
     curveParameters?: Numberu64,
+    pauseAuthority: PublicKey | null = null,
   ): Promise<TokenSwap> {
     let transaction;
     const tokenSwap = new TokenSwap(
@@ -432,6 +461,7 @@ export class TokenSwap {
       hostFeeDenominator,
       curveType,
       curveParameters,
+      pauseAuthority,
     );
 
     transaction.add(instruction);
@@ -527,7 +557,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: userSource, isSigner: false, isWritable: true},
@@ -628,7 +658,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: sourceA, isSigner: false, isWritable: true},
@@ -729,7 +759,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: poolMint, isSigner: false, isWritable: true},
@@ -820,7 +850,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: source, isSigner: false, isWritable: true},
@@ -914,7 +944,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: poolMint, isSigner: false, isWritable: true},
@@ -14,6 +14,20 @@ export const uint64 = (property: string = 'uint64'): Object => {
   return BufferLayout.blob(8, property);
 };
 
+/**
+ * Layout for a 64bit signed value
+ */
+export const int64 = (property: string = 'int64'): Object => {
+  return BufferLayout.blob(8, property);
+};
+
+/**
+ * Layout for a 128bit unsigned value
+ */
+export const uint128 = (property: string = 'uint128'): Object => {
+  return BufferLayout.blob(16, property);
+};
+
 /**
  * Layout for a Rust String type
  */
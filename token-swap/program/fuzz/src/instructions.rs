@@ -7,17 +7,22 @@ use {
         curve::{
             base::{CurveType, SwapCurve},
             calculator::TradeDirection,
+            concentrated_liquidity::{ConcentratedLiquidityCurve, PRICE_PRECISION},
             constant_price::ConstantPriceCurve,
             constant_product::ConstantProductCurve,
             fees::Fees,
             offset::OffsetCurve,
+            proactive_market_making::{ProactiveMarketMakingCurve, K_PRECISION},
             stable::StableCurve,
+            stake_pool_stable::StakePoolStableCurve,
+            weighted::WeightedCurve,
         },
         error::SwapError,
         instruction::{
             DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn, Swap, WithdrawAllTokenTypes,
             WithdrawSingleTokenTypeExactAmountOut,
         },
+        state::{SwapState, SwapVersion},
     },
     spl_token_swap_fuzz::{
         native_account_data::NativeAccountData,
@@ -105,10 +110,16 @@ fn run_fuzz(fuzz_data: FuzzData) {
     let swap_curve = get_swap_curve(fuzz_data.curve_type);
     let mut token_swap = NativeTokenSwap::new(
         fees,
-        swap_curve.clone(),
+        swap_curve,
         INITIAL_SWAP_TOKEN_A_AMOUNT,
         INITIAL_SWAP_TOKEN_B_AMOUNT,
     );
+    // pool values are measured with the curve as initialized, refreshed from
+    // the mock oracle for curves priced by one
+    let swap_curve = SwapVersion::unpack(&token_swap.swap_account.data)
+        .unwrap()
+        .swap_curve()
+        .clone();
 
     // keep track of all accounts, including swap accounts
     let mut token_a_accounts: HashMap<AccountId, NativeAccountData> = HashMap::new();
@@ -467,6 +478,22 @@ fn get_swap_curve(curve_type: CurveType) -> SwapCurve {
             CurveType::Offset => Box::new(OffsetCurve {
                 token_b_offset: 100_000_000_000,
             }),
+            CurveType::Weighted => Box::new(WeightedCurve {
+                token_a_weight: 80,
+                token_b_weight: 20,
+            }),
+            CurveType::ConcentratedLiquidity => Box::new(ConcentratedLiquidityCurve {
+                lower_price: 2 * PRICE_PRECISION,
+                upper_price: 5 * PRICE_PRECISION,
+            }),
+            CurveType::ProactiveMarketMaking => Box::new(ProactiveMarketMakingCurve {
+                k: K_PRECISION / 10,
+                ..ProactiveMarketMakingCurve::default()
+            }),
+            CurveType::StakePoolStable => Box::new(StakePoolStableCurve {
+                amp: 100,
+                ..StakePoolStableCurve::default()
+            }),
         },
     }
 }
@@ -5,17 +5,26 @@ use crate::native_processor::do_process_instruction;
 use crate::native_token;
 
 use spl_token_swap::{
-    curve::{base::SwapCurve, fees::Fees},
+    curve::{
+        base::{CurveType, SwapCurve},
+        fees::{DynamicFees, Fees},
+    },
     instruction::{
         self, DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn, Swap,
         WithdrawAllTokenTypes, WithdrawSingleTokenTypeExactAmountOut,
     },
+    oracle::{Price, PRICE_SCALE},
     state::SwapVersion,
 };
 
+use spl_stake_pool::state::{AccountType, StakePool};
 use spl_token::instruction::approve;
 
-use solana_program::{bpf_loader, entrypoint::ProgramResult, pubkey::Pubkey, system_program};
+use borsh::BorshSerialize;
+use solana_program::{
+    bpf_loader, entrypoint::ProgramResult, program_option::COption, program_pack::Pack,
+    pubkey::Pubkey, system_program,
+};
 
 pub struct NativeTokenSwap {
     pub user_account: NativeAccountData,
@@ -32,6 +41,7 @@ pub struct NativeTokenSwap {
     pub token_b_account: NativeAccountData,
     pub token_b_mint_account: NativeAccountData,
     pub token_program_account: NativeAccountData,
+    pub oracle_account: Option<NativeAccountData>,
 }
 
 pub fn create_program_account(program_id: Pubkey) -> NativeAccountData {
@@ -40,6 +50,41 @@ pub fn create_program_account(program_id: Pubkey) -> NativeAccountData {
     account_data
 }
 
+/// Creates a mock oracle account for curves priced by an oracle, giving a
+/// constant price matching the initial balances of the pool
+pub fn create_oracle_account(
+    swap_curve: &SwapCurve,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    token_b_mint: &Pubkey,
+) -> Option<NativeAccountData> {
+    match swap_curve.curve_type {
+        CurveType::ProactiveMarketMaking => {
+            let price = Price {
+                price: (token_b_amount as u128 * PRICE_SCALE as u128 / token_a_amount as u128)
+                    as u64,
+                publish_slot: 0,
+            };
+            let mut oracle_account = NativeAccountData::new(Price::LEN, Pubkey::new_unique());
+            price.pack_into_slice(&mut oracle_account.data);
+            Some(oracle_account)
+        }
+        CurveType::StakePoolStable => {
+            let stake_pool = StakePool {
+                account_type: AccountType::StakePool,
+                pool_mint: *token_b_mint,
+                total_stake_lamports: token_b_amount,
+                pool_token_supply: token_b_amount,
+                ..StakePool::default()
+            };
+            let mut oracle_account = NativeAccountData::new(0, spl_stake_pool::id());
+            oracle_account.data = stake_pool.try_to_vec().unwrap();
+            Some(oracle_account)
+        }
+        _ => None,
+    }
+}
+
 impl NativeTokenSwap {
     pub fn new(
         fees: Fees,
@@ -64,6 +109,10 @@ impl NativeTokenSwap {
         let mut pool_fee_account =
             native_token::create_token_account(&mut pool_mint_account, &user_account.key, 0);
         let mut token_a_mint_account = native_token::create_mint(&user_account.key);
+        // stake pool curves value token A as SOL
+        if swap_curve.curve_type == CurveType::StakePoolStable {
+            token_a_mint_account.key = spl_token::native_mint::id();
+        }
         let mut token_a_account = native_token::create_token_account(
             &mut token_a_mint_account,
             &authority_account.key,
@@ -75,6 +124,12 @@ impl NativeTokenSwap {
             &authority_account.key,
             token_b_amount,
         );
+        let mut oracle_account = create_oracle_account(
+            &swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &token_b_mint_account.key,
+        );
 
         let init_instruction = instruction::initialize(
             &spl_token_swap::id(),
@@ -86,26 +141,31 @@ impl NativeTokenSwap {
             &pool_mint_account.key,
             &pool_fee_account.key,
             &pool_token_account.key,
+            oracle_account
+                .as_ref()
+                .map(|oracle_account| &oracle_account.key),
             nonce,
             fees.clone(),
             swap_curve.clone(),
+            COption::None,
+            DynamicFees::default(),
         )
         .unwrap();
 
-        do_process_instruction(
-            init_instruction,
-            &[
-                swap_account.as_account_info(),
-                authority_account.as_account_info(),
-                token_a_account.as_account_info(),
-                token_b_account.as_account_info(),
-                pool_mint_account.as_account_info(),
-                pool_fee_account.as_account_info(),
-                pool_token_account.as_account_info(),
-                token_program_account.as_account_info(),
-            ],
-        )
-        .unwrap();
+        let mut account_infos = vec![
+            swap_account.as_account_info(),
+            authority_account.as_account_info(),
+            token_a_account.as_account_info(),
+            token_b_account.as_account_info(),
+            pool_mint_account.as_account_info(),
+            pool_fee_account.as_account_info(),
+            pool_token_account.as_account_info(),
+            token_program_account.as_account_info(),
+        ];
+        if let Some(oracle_account) = oracle_account.as_mut() {
+            account_infos.push(oracle_account.as_account_info());
+        }
+        do_process_instruction(init_instruction, &account_infos).unwrap();
 
         Self {
             user_account,
@@ -122,6 +182,7 @@ impl NativeTokenSwap {
             token_b_account,
             token_b_mint_account,
             token_program_account,
+            oracle_account,
         }
     }
 
@@ -182,27 +243,31 @@ impl NativeTokenSwap {
             &token_b_account.key,
             &self.pool_mint_account.key,
             &self.pool_fee_account.key,
+            self.oracle_account
+                .as_ref()
+                .map(|oracle_account| &oracle_account.key),
             Some(&self.pool_token_account.key),
             instruction,
         )
         .unwrap();
 
-        do_process_instruction(
-            swap_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                token_a_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                token_b_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                self.pool_fee_account.as_account_info(),
-                self.token_program_account.as_account_info(),
-                self.pool_token_account.as_account_info(),
-            ],
-        )
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            token_a_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            token_b_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            self.pool_fee_account.as_account_info(),
+            self.token_program_account.as_account_info(),
+        ];
+        if let Some(oracle_account) = self.oracle_account.as_mut() {
+            account_infos.push(oracle_account.as_account_info());
+        }
+        account_infos.push(self.pool_token_account.as_account_info());
+        do_process_instruction(swap_instruction, &account_infos)
     }
 
     pub fn swap_b_to_a(
@@ -243,27 +308,31 @@ impl NativeTokenSwap {
             &token_a_account.key,
             &self.pool_mint_account.key,
             &self.pool_fee_account.key,
+            self.oracle_account
+                .as_ref()
+                .map(|oracle_account| &oracle_account.key),
             Some(&self.pool_token_account.key),
             instruction,
         )
         .unwrap();
 
-        do_process_instruction(
-            swap_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                token_b_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                token_a_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                self.pool_fee_account.as_account_info(),
-                self.token_program_account.as_account_info(),
-                self.pool_token_account.as_account_info(),
-            ],
-        )
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            token_b_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            token_a_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            self.pool_fee_account.as_account_info(),
+            self.token_program_account.as_account_info(),
+        ];
+        if let Some(oracle_account) = self.oracle_account.as_mut() {
+            account_infos.push(oracle_account.as_account_info());
+        }
+        account_infos.push(self.pool_token_account.as_account_info());
+        do_process_instruction(swap_instruction, &account_infos)
     }
 
     pub fn deposit_all_token_types(
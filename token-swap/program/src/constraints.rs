@@ -3,12 +3,13 @@
 use crate::{
     curve::{
         base::{CurveType, SwapCurve},
+        concentrated_liquidity::ConcentratedLiquidityCurve,
         fees::Fees,
     },
     error::SwapError,
 };
 
-use solana_program::program_error::ProgramError;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
 #[cfg(feature = "production")]
 use std::env;
@@ -24,21 +25,48 @@ pub struct SwapConstraints<'a> {
     pub owner_key: &'a str,
     /// Valid curve types
     pub valid_curve_types: &'a [CurveType],
+    /// Valid programs owning the oracle accounts of curves priced by an
+    /// oracle, other than the stake pool program
+    pub valid_oracle_program_ids: &'a [&'a str],
     /// Valid fees
     pub fees: &'a Fees,
+    /// Valid price ranges of concentrated liquidity curves, if restricted
+    pub price_range: Option<&'a PriceRangeConstraints>,
 }
 
 impl<'a> SwapConstraints<'a> {
     /// Checks that the provided curve is valid for the given constraints
     pub fn validate_curve(&self, swap_curve: &SwapCurve) -> Result<(), ProgramError> {
+        self.validate_curve_type(swap_curve.curve_type)?;
+        match (swap_curve.curve_type, self.price_range) {
+            (CurveType::ConcentratedLiquidity, Some(price_range)) => {
+                let curve = ConcentratedLiquidityCurve::from_swap_curve(swap_curve)?;
+                price_range.validate(curve.lower_price, curve.upper_price)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the provided curve type is valid for the given constraints
+    pub fn validate_curve_type(&self, curve_type: CurveType) -> Result<(), ProgramError> {
+        if self.valid_curve_types.contains(&curve_type) {
+            Ok(())
+        } else {
+            Err(SwapError::UnsupportedCurveType.into())
+        }
+    }
+
+    /// Checks that the provided oracle program is valid for the given
+    /// constraints
+    pub fn validate_oracle_program_id(&self, program_id: &Pubkey) -> Result<(), ProgramError> {
         if self
-            .valid_curve_types
+            .valid_oracle_program_ids
             .iter()
-            .any(|x| *x == swap_curve.curve_type)
+            .any(|valid_program_id| valid_program_id.parse::<Pubkey>().as_ref() == Ok(program_id))
         {
             Ok(())
         } else {
-            Err(SwapError::UnsupportedCurveType.into())
+            Err(SwapError::IncorrectOracleAccount.into())
         }
     }
 
@@ -60,6 +88,40 @@ impl<'a> SwapConstraints<'a> {
     }
 }
 
+/// Encodes the price ranges allowed for concentrated liquidity curves, in
+/// units of 1 / PRICE_PRECISION token B per token A.  Aligning prices on ticks
+/// keeps pools over the same pair comparable, and lets frontends route between
+/// them.
+pub struct PriceRangeConstraints {
+    /// Lowest allowed lower price
+    pub min_lower_price: u64,
+    /// Highest allowed upper price
+    pub max_upper_price: u64,
+    /// Spacing between allowed prices, which must be multiples of it, or 0 to
+    /// allow any price
+    pub tick_spacing: u64,
+}
+
+impl PriceRangeConstraints {
+    /// Checks that the provided price range is valid for the given constraints
+    pub fn validate(&self, lower_price: u64, upper_price: u64) -> Result<(), ProgramError> {
+        let on_tick = |price: u64| {
+            price
+                .checked_rem(self.tick_spacing)
+                .map_or(true, |remainder| remainder == 0)
+        };
+        if lower_price >= self.min_lower_price
+            && upper_price <= self.max_upper_price
+            && on_tick(lower_price)
+            && on_tick(upper_price)
+        {
+            Ok(())
+        } else {
+            Err(SwapError::UnsupportedPriceRange.into())
+        }
+    }
+}
+
 #[cfg(feature = "production")]
 const OWNER_KEY: &str = env!("SWAP_PROGRAM_OWNER_FEE_ADDRESS");
 #[cfg(feature = "production")]
@@ -75,6 +137,8 @@ const FEES: &Fees = &Fees {
 };
 #[cfg(feature = "production")]
 const VALID_CURVE_TYPES: &[CurveType] = &[CurveType::ConstantPrice, CurveType::ConstantProduct];
+#[cfg(feature = "production")]
+const VALID_ORACLE_PROGRAM_IDS: &[&str] = &[];
 
 /// Fee structure defined by program creator in order to enforce certain
 /// fees when others use the program.  Adds checks on pool creation and
@@ -88,7 +152,9 @@ pub const SWAP_CONSTRAINTS: Option<SwapConstraints> = {
         Some(SwapConstraints {
             owner_key: OWNER_KEY,
             valid_curve_types: VALID_CURVE_TYPES,
+            valid_oracle_program_ids: VALID_ORACLE_PROGRAM_IDS,
             fees: FEES,
+            price_range: None,
         })
     }
     #[cfg(not(feature = "production"))]
@@ -101,7 +167,11 @@ pub const SWAP_CONSTRAINTS: Option<SwapConstraints> = {
 mod tests {
     use super::*;
 
-    use crate::curve::{base::CurveType, constant_product::ConstantProductCurve};
+    use crate::curve::{
+        base::CurveType,
+        concentrated_liquidity::{ConcentratedLiquidityCurve, PRICE_PRECISION},
+        constant_product::ConstantProductCurve,
+    };
 
     #[test]
     fn validate_fees() {
@@ -133,7 +203,9 @@ mod tests {
         let constraints = SwapConstraints {
             owner_key,
             valid_curve_types: &[curve_type],
+            valid_oracle_program_ids: &[],
             fees: &valid_fees,
+            price_range: None,
         };
 
         constraints.validate_curve(&swap_curve).unwrap();
@@ -194,4 +266,139 @@ mod tests {
             constraints.validate_curve(&swap_curve),
         );
     }
+    #[test]
+    fn validate_concentrated_liquidity_curve() {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10000,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 20,
+            host_fee_denominator: 100,
+        };
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConcentratedLiquidity,
+            calculator: Box::new(ConcentratedLiquidityCurve {
+                lower_price: PRICE_PRECISION / 2,
+                upper_price: 2 * PRICE_PRECISION,
+            }),
+        };
+
+        let constraints = SwapConstraints {
+            owner_key: "",
+            valid_curve_types: &[CurveType::ConstantProduct, CurveType::ConcentratedLiquidity],
+            valid_oracle_program_ids: &[],
+            fees: &fees,
+            price_range: None,
+        };
+        assert_eq!(constraints.validate_curve(&swap_curve), Ok(()));
+
+        let constraints = SwapConstraints {
+            owner_key: "",
+            valid_curve_types: &[CurveType::ConstantProduct],
+            valid_oracle_program_ids: &[],
+            fees: &fees,
+            price_range: None,
+        };
+        assert_eq!(
+            Err(SwapError::UnsupportedCurveType.into()),
+            constraints.validate_curve(&swap_curve),
+        );
+
+        let price_range = PriceRangeConstraints {
+            min_lower_price: PRICE_PRECISION / 4,
+            max_upper_price: 4 * PRICE_PRECISION,
+            tick_spacing: PRICE_PRECISION / 100,
+        };
+        let constraints = SwapConstraints {
+            owner_key: "",
+            valid_curve_types: &[CurveType::ConstantProduct, CurveType::ConcentratedLiquidity],
+            valid_oracle_program_ids: &[],
+            fees: &fees,
+            price_range: Some(&price_range),
+        };
+        assert_eq!(constraints.validate_curve(&swap_curve), Ok(()));
+
+        // ranges only apply to concentrated liquidity curves
+        let constant_product_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+        assert_eq!(constraints.validate_curve(&constant_product_curve), Ok(()));
+
+        let check_price_range = |lower_price: u64, upper_price: u64| {
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConcentratedLiquidity,
+                calculator: Box::new(ConcentratedLiquidityCurve {
+                    lower_price,
+                    upper_price,
+                }),
+            };
+            constraints.validate_curve(&swap_curve)
+        };
+        assert_eq!(
+            check_price_range(PRICE_PRECISION / 4, 4 * PRICE_PRECISION),
+            Ok(())
+        );
+        // lower price below the minimum
+        assert_eq!(
+            Err(SwapError::UnsupportedPriceRange.into()),
+            check_price_range(PRICE_PRECISION / 5, 2 * PRICE_PRECISION),
+        );
+        // upper price above the maximum
+        assert_eq!(
+            Err(SwapError::UnsupportedPriceRange.into()),
+            check_price_range(PRICE_PRECISION / 2, 5 * PRICE_PRECISION),
+        );
+        // prices off the ticks
+        assert_eq!(
+            Err(SwapError::UnsupportedPriceRange.into()),
+            check_price_range(PRICE_PRECISION / 2 + 1, 2 * PRICE_PRECISION),
+        );
+        assert_eq!(
+            Err(SwapError::UnsupportedPriceRange.into()),
+            check_price_range(PRICE_PRECISION / 2, 2 * PRICE_PRECISION - 1),
+        );
+
+        // without a tick spacing, any price within the bounds is allowed
+        let price_range = PriceRangeConstraints {
+            tick_spacing: 0,
+            ..price_range
+        };
+        let constraints = SwapConstraints {
+            price_range: Some(&price_range),
+            ..constraints
+        };
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConcentratedLiquidity,
+            calculator: Box::new(ConcentratedLiquidityCurve {
+                lower_price: PRICE_PRECISION / 2 + 1,
+                upper_price: 2 * PRICE_PRECISION - 1,
+            }),
+        };
+        assert_eq!(constraints.validate_curve(&swap_curve), Ok(()));
+    }
+
+    #[test]
+    fn validate_oracle_program_id() {
+        let oracle_program_id = Pubkey::new_unique();
+        let valid_oracle_program_id = &oracle_program_id.to_string();
+        let constraints = SwapConstraints {
+            owner_key: "",
+            valid_curve_types: &[CurveType::ProactiveMarketMaking],
+            valid_oracle_program_ids: &[valid_oracle_program_id],
+            fees: &Fees::default(),
+            price_range: None,
+        };
+        assert_eq!(
+            constraints.validate_oracle_program_id(&oracle_program_id),
+            Ok(())
+        );
+        assert_eq!(
+            Err(SwapError::IncorrectOracleAccount.into()),
+            constraints.validate_oracle_program_id(&Pubkey::new_unique()),
+        );
+    }
 }
@@ -7,11 +7,15 @@ use solana_program::{
 
 use crate::curve::{
     calculator::{CurveCalculator, SwapWithoutFeesResult, TradeDirection},
+    concentrated_liquidity::ConcentratedLiquidityCurve,
     constant_price::ConstantPriceCurve,
     constant_product::ConstantProductCurve,
     fees::Fees,
     offset::OffsetCurve,
+    proactive_market_making::ProactiveMarketMakingCurve,
     stable::StableCurve,
+    stake_pool_stable::StakePoolStableCurve,
+    weighted::WeightedCurve,
 };
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use std::convert::{TryFrom, TryInto};
@@ -33,6 +37,29 @@ pub enum CurveType {
     Stable,
     /// Offset curve, like Uniswap, but the token B side has a faked offset
     Offset,
+    /// Balancer-style curve, like Uniswap, but each token has a configurable
+    /// weight, invariant = token_a_amount ^ token_a_weight * token_b_amount ^ token_b_weight
+    Weighted,
+    /// Uniswap v3-style curve, like Uniswap, but liquidity is only provided
+    /// between a lower and an upper price
+    ConcentratedLiquidity,
+    /// DODO-style proactive market making curve, concentrating liquidity
+    /// around a price given by an oracle
+    ProactiveMarketMaking,
+    /// Stable curve between SOL and the pool tokens of a stake pool, with the
+    /// 1:1 zone following the stake pool's exchange rate
+    StakePoolStable,
+}
+
+impl CurveType {
+    /// Curves priced by an oracle need the oracle account on initialization
+    /// and on every swap
+    pub fn uses_oracle(&self) -> bool {
+        matches!(
+            self,
+            CurveType::ProactiveMarketMaking | CurveType::StakePoolStable
+        )
+    }
 }
 
 /// Encodes all results of swapping from a source token to a destination token
@@ -104,6 +131,43 @@ impl SwapCurve {
         })
     }
 
+    /// Calculate how much source token must be provided, fees included, to
+    /// receive exactly the given amount of destination token.
+    pub fn swap_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        fees: &Fees,
+    ) -> Option<SwapResult> {
+        let SwapWithoutFeesResult {
+            source_amount_swapped,
+            destination_amount_swapped,
+        } = self.calculator.swap_without_fees_exact_out(
+            destination_amount,
+            swap_source_amount,
+            swap_destination_amount,
+            trade_direction,
+        )?;
+
+        // gross up the source amount so that the curve still receives enough
+        // once the fees are debited
+        let source_amount_swapped = fees.pre_trading_fee_amount(source_amount_swapped)?;
+        let trade_fee = fees.trading_fee(source_amount_swapped)?;
+        let owner_fee = fees.owner_trading_fee(source_amount_swapped)?;
+
+        Some(SwapResult {
+            new_swap_source_amount: swap_source_amount.checked_add(source_amount_swapped)?,
+            new_swap_destination_amount: swap_destination_amount
+                .checked_sub(destination_amount_swapped)?,
+            source_amount_swapped,
+            destination_amount_swapped,
+            trade_fee,
+            owner_fee,
+        })
+    }
+
     /// Get the amount of pool tokens for the deposited amount of token A or B
     pub fn deposit_single_token_type(
         &self,
@@ -223,6 +287,16 @@ impl Pack for SwapCurve {
                 }
                 CurveType::Stable => Box::new(StableCurve::unpack_from_slice(calculator)?),
                 CurveType::Offset => Box::new(OffsetCurve::unpack_from_slice(calculator)?),
+                CurveType::Weighted => Box::new(WeightedCurve::unpack_from_slice(calculator)?),
+                CurveType::ConcentratedLiquidity => {
+                    Box::new(ConcentratedLiquidityCurve::unpack_from_slice(calculator)?)
+                }
+                CurveType::ProactiveMarketMaking => {
+                    Box::new(ProactiveMarketMakingCurve::unpack_from_slice(calculator)?)
+                }
+                CurveType::StakePoolStable => {
+                    Box::new(StakePoolStableCurve::unpack_from_slice(calculator)?)
+                }
             },
         })
     }
@@ -253,6 +327,10 @@ impl TryFrom<u8> for CurveType {
             1 => Ok(CurveType::ConstantPrice),
             2 => Ok(CurveType::Stable),
             3 => Ok(CurveType::Offset),
+            4 => Ok(CurveType::Weighted),
+            5 => Ok(CurveType::ConcentratedLiquidity),
+            6 => Ok(CurveType::ProactiveMarketMaking),
+            7 => Ok(CurveType::StakePoolStable),
             _ => Err(ProgramError::InvalidAccountData),
         }
     }
@@ -397,4 +475,60 @@ mod tests {
         assert_eq!(result.destination_amount_swapped, 4545);
         assert_eq!(result.new_swap_destination_amount, 45455);
     }
+
+    #[test]
+    fn constant_product_exact_out_trade_fee() {
+        // inverse of constant_product_trade_fee
+        let swap_source_amount = 1000;
+        let swap_destination_amount = 50000;
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 100,
+            ..Fees::default()
+        };
+        let destination_amount = 4504;
+        let curve = ConstantProductCurve {};
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(curve),
+        };
+        let result = swap_curve
+            .swap_exact_out(
+                destination_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB,
+                &fees,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 100);
+        assert_eq!(result.new_swap_source_amount, 1100);
+        assert_eq!(result.destination_amount_swapped, 4504);
+        assert_eq!(result.new_swap_destination_amount, 45496);
+        assert_eq!(result.trade_fee, 1);
+        assert_eq!(result.owner_fee, 0);
+
+        // the calculated source amount gives at least the same in a regular swap
+        let result = swap_curve
+            .swap(
+                result.source_amount_swapped,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB,
+                &fees,
+            )
+            .unwrap();
+        assert!(result.destination_amount_swapped >= destination_amount);
+
+        // the whole destination side can't be bought
+        assert!(swap_curve
+            .swap_exact_out(
+                swap_destination_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB,
+                &fees,
+            )
+            .is_none());
+    }
 }
@@ -1,6 +1,10 @@
 //! Swap calculations
 
-use {crate::error::SwapError, spl_math::precise_number::PreciseNumber, std::fmt::Debug};
+use {
+    crate::error::SwapError,
+    spl_math::{precise_number::PreciseNumber, uint::U256},
+    std::fmt::Debug,
+};
 
 #[cfg(feature = "fuzz")]
 use arbitrary::Arbitrary;
@@ -24,6 +28,21 @@ pub fn map_zero_to_none(x: u128) -> Option<u128> {
     }
 }
 
+/// Number of fractional bits of spot prices, which are Q64.64 fixed point
+/// numbers
+pub const SPOT_PRICE_SHIFT: usize = 64;
+
+/// Helper function calculating `numerator / denominator` as a Q64.64 fixed
+/// point number, rounded down.  There is no price if either side is zero.
+pub fn checked_spot_price(numerator: U256, denominator: U256) -> Option<U256> {
+    if numerator.is_zero() || denominator.is_zero() {
+        return None;
+    }
+    numerator
+        .checked_mul(U256::one() << SPOT_PRICE_SHIFT)?
+        .checked_div(denominator)
+}
+
 /// The direction of a trade, since curves can be specialized to treat each
 /// token differently (by adding offsets or weights)
 #[cfg_attr(feature = "fuzz", derive(Arbitrary))]
@@ -95,6 +114,30 @@ pub trait CurveCalculator: Debug + DynPack {
         trade_direction: TradeDirection,
     ) -> Option<SwapWithoutFeesResult>;
 
+    /// Calculate how much source token must be provided to receive exactly
+    /// `destination_amount` of destination token, the inverse of
+    /// `swap_without_fees`.  The source amount is rounded up, so that the
+    /// pool never loses value on the trade.
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult>;
+
+    /// Calculate the spot price of the source token in the destination token
+    /// for the given balances, as a Q64.64 fixed point number: the amount of
+    /// destination token given per source token, before fees, by a swap too
+    /// small to move the price.  Returns `None` if the curve has no price for
+    /// these balances, for example if the pool is empty.
+    fn spot_price(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128>;
+
     /// Get the supply for a new pool
     /// The default implementation is a Balancer-style fixed initial supply
     fn new_pool_supply(&self) -> u128 {
@@ -196,7 +239,6 @@ pub trait CurveCalculator: Debug + DynPack {
 pub mod test {
     use super::*;
     use proptest::prelude::*;
-    use spl_math::uint::U256;
 
     /// The epsilon for most curves when performing the conversion test,
     /// comparing a one-sided deposit to a swap + deposit.
@@ -436,6 +478,93 @@ pub mod test {
         assert!(difference <= epsilon);
     }
 
+    /// Test function checking that the spot price is the limit of the rate of
+    /// swaps as they get smaller: no swap gets a better rate, beyond the
+    /// rounding of the price and one token of rounding of the swap, and a swap
+    /// of a ten-thousandth of the source balance gets within 0.1% of it.
+    pub fn check_spot_price(
+        curve: &dyn CurveCalculator,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) {
+        let spot_price = U256::from(
+            curve
+                .spot_price(swap_source_amount, swap_destination_amount, trade_direction)
+                .unwrap(),
+        );
+        let results = curve
+            .swap_without_fees(
+                swap_source_amount / 10_000,
+                swap_source_amount,
+                swap_destination_amount,
+                trade_direction,
+            )
+            .unwrap();
+        let source_amount = U256::from(results.source_amount_swapped);
+        let destination_amount = U256::from(results.destination_amount_swapped) << SPOT_PRICE_SHIFT;
+        assert!(
+            destination_amount
+                <= (spot_price + 1) * source_amount + (U256::one() << SPOT_PRICE_SHIFT)
+        );
+        assert!(destination_amount * 1_000 >= spot_price * source_amount * 999);
+    }
+
+    /// Test function checking that an exact output swap matches the regular
+    /// swap, so that providing the calculated source amount gives at least the
+    /// requested destination amount, and that it never reduces the overall
+    /// value of the pool.
+    pub fn check_swap_exact_out(
+        curve: &dyn CurveCalculator,
+        destination_token_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) {
+        let results = curve
+            .swap_without_fees_exact_out(
+                destination_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                trade_direction,
+            )
+            .unwrap();
+        assert_eq!(results.destination_amount_swapped, destination_token_amount);
+
+        let forward_results = curve
+            .swap_without_fees(
+                results.source_amount_swapped,
+                swap_source_amount,
+                swap_destination_amount,
+                trade_direction,
+            )
+            .unwrap();
+        assert!(forward_results.destination_amount_swapped >= destination_token_amount);
+
+        let (swap_token_a_amount, swap_token_b_amount) = match trade_direction {
+            TradeDirection::AtoB => (swap_source_amount, swap_destination_amount),
+            TradeDirection::BtoA => (swap_destination_amount, swap_source_amount),
+        };
+        let previous_value = curve
+            .normalized_value(swap_token_a_amount, swap_token_b_amount)
+            .unwrap();
+
+        let new_swap_source_amount = swap_source_amount
+            .checked_add(results.source_amount_swapped)
+            .unwrap();
+        let new_swap_destination_amount = swap_destination_amount
+            .checked_sub(results.destination_amount_swapped)
+            .unwrap();
+        let (swap_token_a_amount, swap_token_b_amount) = match trade_direction {
+            TradeDirection::AtoB => (new_swap_source_amount, new_swap_destination_amount),
+            TradeDirection::BtoA => (new_swap_destination_amount, new_swap_source_amount),
+        };
+        let new_value = curve
+            .normalized_value(swap_token_a_amount, swap_token_b_amount)
+            .unwrap();
+        assert!(new_value.greater_than_or_equal(&previous_value));
+    }
+
     /// Test function checking that a deposit never reduces the value of pool
     /// tokens.
     ///
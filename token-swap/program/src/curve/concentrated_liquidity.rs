@@ -0,0 +1,920 @@
+//! The Uniswap v3-style invariant calculator, providing liquidity only within
+//! a price range
+
+use {
+    crate::{
+        curve::{
+            base::{CurveType, SwapCurve},
+            calculator::{
+                checked_spot_price, map_zero_to_none, CurveCalculator, DynPack, RoundDirection,
+                SwapWithoutFeesResult, TradeDirection, TradingTokenResult,
+            },
+            constant_product::pool_tokens_to_trading_tokens,
+        },
+        error::SwapError,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+    },
+    spl_math::{precise_number::PreciseNumber, uint::U256},
+    std::convert::TryFrom,
+};
+
+/// Prices are expressed as an amount of token B per token A, in units of
+/// 1 / PRICE_PRECISION, so a price of 1_000_000_000 means 1 token B per token A
+pub const PRICE_PRECISION: u64 = 1_000_000_000;
+
+/// Square root prices are kept as Q64.64 fixed point numbers
+const SQRT_PRICE_SHIFT: usize = 64;
+
+/// Liquidity is kept as a fixed point number with 32 fractional bits, so that
+/// its rounding stays far below a single token
+const LIQUIDITY_SHIFT: usize = 32;
+
+/// Divide, rounding up
+fn checked_div_ceiling(numerator: U256, denominator: U256) -> Option<U256> {
+    let quotient = numerator.checked_div(denominator)?;
+    if quotient.checked_mul(denominator)? == numerator {
+        Some(quotient)
+    } else {
+        quotient.checked_add(U256::one())
+    }
+}
+
+/// Calculate `(numerator << shift) / denominator`, rounding in the given
+/// direction, without shifting the whole numerator
+fn checked_shifted_div(
+    numerator: U256,
+    denominator: U256,
+    shift: usize,
+    round_direction: RoundDirection,
+) -> Option<U256> {
+    let quotient = numerator.checked_div(denominator)?;
+    let remainder = numerator.checked_rem(denominator)?;
+    let shifted_remainder = remainder.checked_mul(U256::one() << shift)?;
+    let remainder_quotient = shifted_remainder.checked_div(denominator)?;
+    let result = quotient
+        .checked_mul(U256::one() << shift)?
+        .checked_add(remainder_quotient)?;
+    match round_direction {
+        RoundDirection::Floor => Some(result),
+        RoundDirection::Ceiling => {
+            if remainder_quotient.checked_mul(denominator)? == shifted_remainder {
+                Some(result)
+            } else {
+                result.checked_add(U256::one())
+            }
+        }
+    }
+}
+
+/// Square root, rounding in the given direction
+fn checked_sqrt_round(radicand: U256, round_direction: RoundDirection) -> Option<U256> {
+    let root = radicand.integer_sqrt();
+    match round_direction {
+        RoundDirection::Floor => Some(root),
+        RoundDirection::Ceiling => {
+            if root.checked_mul(root)? == radicand {
+                Some(root)
+            } else {
+                root.checked_add(U256::one())
+            }
+        }
+    }
+}
+
+/// ConcentratedLiquidityCurve struct implementing CurveCalculator
+///
+/// The pool behaves like a constant product pool on virtual reserves, which
+/// are the real reserves plus just enough liquidity to run out of token B at
+/// the lower price, and out of token A at the upper price:
+///
+/// (token_a + L / sqrt(upper_price)) * (token_b + L * sqrt(lower_price)) = L ^ 2
+///
+/// The liquidity L is derived from the real reserves on every calculation, so
+/// the curve needs no extra state, and proportional deposits and withdrawals
+/// scale it proportionally.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConcentratedLiquidityCurve {
+    /// Lowest price at which the pool provides liquidity, in units of
+    /// 1 / PRICE_PRECISION token B per token A
+    pub lower_price: u64,
+    /// Highest price at which the pool provides liquidity, in units of
+    /// 1 / PRICE_PRECISION token B per token A
+    pub upper_price: u64,
+}
+
+impl ConcentratedLiquidityCurve {
+    /// Copy the calculator out of a swap curve of the concentrated liquidity
+    /// type
+    pub fn from_swap_curve(swap_curve: &SwapCurve) -> Result<Self, ProgramError> {
+        if swap_curve.curve_type != CurveType::ConcentratedLiquidity {
+            return Err(SwapError::UnsupportedCurveOperation.into());
+        }
+        let mut packed = [0u8; Self::LEN];
+        swap_curve.calculator.pack_into_slice(&mut packed);
+        Self::unpack_from_slice(&packed)
+    }
+
+    /// Square roots of the lower and upper prices, as Q64.64 fixed point
+    /// numbers, rounded down.  The curve is defined by these rounded values.
+    fn sqrt_prices(&self) -> Option<(U256, U256)> {
+        let sqrt_price = |price: u64| -> Option<U256> {
+            let scaled_price = (U256::from(price) << (SQRT_PRICE_SHIFT * 2))
+                .checked_div(U256::from(PRICE_PRECISION))?;
+            Some(scaled_price.integer_sqrt())
+        };
+        Some((sqrt_price(self.lower_price)?, sqrt_price(self.upper_price)?))
+    }
+
+    /// Calculate the liquidity L of the given real reserves, which is the
+    /// positive root of:
+    ///
+    /// L ^ 2 * (1 - sqrt(lower_price / upper_price))
+    ///   - L * (token_a * sqrt(lower_price) + token_b / sqrt(upper_price))
+    ///   - token_a * token_b = 0
+    ///
+    /// Written as L = b + sqrt(b ^ 2 + c), each step is rounded in the given
+    /// direction, so that the result is a bound on the exact liquidity.  The
+    /// result has LIQUIDITY_SHIFT fractional bits.
+    fn liquidity(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        round_direction: RoundDirection,
+    ) -> Option<U256> {
+        let (sqrt_lower_price, sqrt_upper_price) = self.sqrt_prices()?;
+        let token_a = U256::from(swap_token_a_amount);
+        let token_b = U256::from(swap_token_b_amount);
+        let sqrt_price_difference = sqrt_upper_price.checked_sub(sqrt_lower_price)?;
+
+        let b = checked_shifted_div(
+            token_a
+                .checked_mul(sqrt_lower_price)?
+                .checked_mul(sqrt_upper_price)?
+                .checked_add(token_b.checked_mul(U256::one() << (SQRT_PRICE_SHIFT * 2))?)?,
+            sqrt_price_difference.checked_mul(U256::from(2) << SQRT_PRICE_SHIFT)?,
+            LIQUIDITY_SHIFT,
+            round_direction,
+        )?;
+        let c = checked_shifted_div(
+            token_a
+                .checked_mul(token_b)?
+                .checked_mul(sqrt_upper_price)?,
+            sqrt_price_difference,
+            LIQUIDITY_SHIFT * 2,
+            round_direction,
+        )?;
+        let root = checked_sqrt_round(b.checked_mul(b)?.checked_add(c)?, round_direction)?;
+        b.checked_add(root)
+    }
+
+    /// Virtual amounts added to the real source and destination reserves of a
+    /// swap for the given liquidity, rounded down, with LIQUIDITY_SHIFT
+    /// fractional bits
+    fn swap_offsets(
+        &self,
+        liquidity: U256,
+        trade_direction: TradeDirection,
+    ) -> Option<(U256, U256)> {
+        let (sqrt_lower_price, sqrt_upper_price) = self.sqrt_prices()?;
+        let token_a_offset = liquidity
+            .checked_mul(U256::one() << SQRT_PRICE_SHIFT)?
+            .checked_div(sqrt_upper_price)?;
+        let token_b_offset = liquidity.checked_mul(sqrt_lower_price)? >> SQRT_PRICE_SHIFT;
+        match trade_direction {
+            TradeDirection::AtoB => Some((token_a_offset, token_b_offset)),
+            TradeDirection::BtoA => Some((token_b_offset, token_a_offset)),
+        }
+    }
+
+    /// Rounded-up liquidity of the reserves before a swap
+    fn swap_liquidity(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<U256> {
+        match trade_direction {
+            TradeDirection::AtoB => self.liquidity(
+                swap_source_amount,
+                swap_destination_amount,
+                RoundDirection::Ceiling,
+            ),
+            TradeDirection::BtoA => self.liquidity(
+                swap_destination_amount,
+                swap_source_amount,
+                RoundDirection::Ceiling,
+            ),
+        }
+    }
+
+    /// Calculate the real reserve on one side of a swap which keeps the
+    /// invariant for the given real reserve on the other side, rounded up
+    fn reserve_for_invariant(
+        liquidity: U256,
+        other_reserve: u128,
+        other_offset: U256,
+        offset: U256,
+    ) -> Option<U256> {
+        let other_virtual_reserve = U256::from(other_reserve)
+            .checked_mul(U256::one() << LIQUIDITY_SHIFT)?
+            .checked_add(other_offset)?;
+        let virtual_reserve =
+            checked_div_ceiling(liquidity.checked_mul(liquidity)?, other_virtual_reserve)?;
+        checked_div_ceiling(
+            virtual_reserve.checked_sub(offset)?,
+            U256::one() << LIQUIDITY_SHIFT,
+        )
+    }
+}
+
+impl CurveCalculator for ConcentratedLiquidityCurve {
+    /// Constant product swap on the virtual reserves.  The liquidity is
+    /// rounded up and the offsets down, so that the liquidity of the real
+    /// reserves never decreases, at the expense of the trader.
+    /// Swaps that would move the price out of the range fail.
+    fn swap_without_fees(
+        &self,
+        source_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let liquidity =
+            self.swap_liquidity(swap_source_amount, swap_destination_amount, trade_direction)?;
+        let (source_offset, destination_offset) = self.swap_offsets(liquidity, trade_direction)?;
+
+        let new_swap_destination_amount = Self::reserve_for_invariant(
+            liquidity,
+            swap_source_amount.checked_add(source_amount)?,
+            source_offset,
+            destination_offset,
+        )?;
+
+        let destination_amount_swapped = U256::from(swap_destination_amount)
+            .checked_sub(new_swap_destination_amount)?
+            .as_u128();
+        Some(SwapWithoutFeesResult {
+            source_amount_swapped: source_amount,
+            destination_amount_swapped: map_zero_to_none(destination_amount_swapped)?,
+        })
+    }
+
+    /// The inverse of the swap, on the same virtual reserves
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let liquidity =
+            self.swap_liquidity(swap_source_amount, swap_destination_amount, trade_direction)?;
+        let (source_offset, destination_offset) = self.swap_offsets(liquidity, trade_direction)?;
+
+        let source_amount_swapped = Self::reserve_for_invariant(
+            liquidity,
+            swap_destination_amount.checked_sub(destination_amount)?,
+            destination_offset,
+            source_offset,
+        )?
+        .checked_sub(U256::from(swap_source_amount))?;
+        let source_amount_swapped = u128::try_from(source_amount_swapped).ok()?;
+        Some(SwapWithoutFeesResult {
+            source_amount_swapped: map_zero_to_none(source_amount_swapped)?,
+            destination_amount_swapped: destination_amount,
+        })
+    }
+
+    /// The spot price is the ratio of the virtual reserves, which is the
+    /// square of the current square root price
+    fn spot_price(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let liquidity =
+            self.swap_liquidity(swap_source_amount, swap_destination_amount, trade_direction)?;
+        let (source_offset, destination_offset) = self.swap_offsets(liquidity, trade_direction)?;
+        let virtual_source_amount = U256::from(swap_source_amount)
+            .checked_mul(U256::one() << LIQUIDITY_SHIFT)?
+            .checked_add(source_offset)?;
+        let virtual_destination_amount = U256::from(swap_destination_amount)
+            .checked_mul(U256::one() << LIQUIDITY_SHIFT)?
+            .checked_add(destination_offset)?;
+        u128::try_from(checked_spot_price(
+            virtual_destination_amount,
+            virtual_source_amount,
+        )?)
+        .ok()
+    }
+
+    /// Liquidity scales with the real reserves, so pool tokens are a simple
+    /// ratio of them, like the constant product curve
+    fn pool_tokens_to_trading_tokens(
+        &self,
+        pool_tokens: u128,
+        pool_token_supply: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        round_direction: RoundDirection,
+    ) -> Option<TradingTokenResult> {
+        pool_tokens_to_trading_tokens(
+            pool_tokens,
+            pool_token_supply,
+            swap_token_a_amount,
+            swap_token_b_amount,
+            round_direction,
+        )
+    }
+
+    /// Pool tokens are minted in proportion to the liquidity added:
+    ///
+    /// pool_tokens = pool_supply * (new_liquidity - liquidity) / liquidity
+    fn deposit_single_token_type(
+        &self,
+        source_amount: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        pool_supply: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let (new_swap_token_a_amount, new_swap_token_b_amount) = match trade_direction {
+            TradeDirection::AtoB => (
+                swap_token_a_amount.checked_add(source_amount)?,
+                swap_token_b_amount,
+            ),
+            TradeDirection::BtoA => (
+                swap_token_a_amount,
+                swap_token_b_amount.checked_add(source_amount)?,
+            ),
+        };
+        // rounded against the depositor
+        let liquidity = self.liquidity(
+            swap_token_a_amount,
+            swap_token_b_amount,
+            RoundDirection::Ceiling,
+        )?;
+        let new_liquidity = self.liquidity(
+            new_swap_token_a_amount,
+            new_swap_token_b_amount,
+            RoundDirection::Floor,
+        )?;
+        if new_liquidity <= liquidity {
+            return Some(0);
+        }
+        let pool_tokens = U256::from(pool_supply)
+            .checked_mul(new_liquidity.checked_sub(liquidity)?)?
+            .checked_div(liquidity)?;
+        u128::try_from(pool_tokens).ok()
+    }
+
+    /// Pool tokens are burned in proportion to the liquidity removed:
+    ///
+    /// pool_tokens = pool_supply * (liquidity - new_liquidity) / liquidity
+    fn withdraw_single_token_type_exact_out(
+        &self,
+        source_amount: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        pool_supply: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let (new_swap_token_a_amount, new_swap_token_b_amount) = match trade_direction {
+            TradeDirection::AtoB => (
+                swap_token_a_amount.checked_sub(source_amount)?,
+                swap_token_b_amount,
+            ),
+            TradeDirection::BtoA => (
+                swap_token_a_amount,
+                swap_token_b_amount.checked_sub(source_amount)?,
+            ),
+        };
+        // rounded against the withdrawer
+        let liquidity_floor = self.liquidity(
+            swap_token_a_amount,
+            swap_token_b_amount,
+            RoundDirection::Floor,
+        )?;
+        let liquidity_ceiling = self.liquidity(
+            swap_token_a_amount,
+            swap_token_b_amount,
+            RoundDirection::Ceiling,
+        )?;
+        let new_liquidity = self.liquidity(
+            new_swap_token_a_amount,
+            new_swap_token_b_amount,
+            RoundDirection::Floor,
+        )?;
+        let pool_tokens = checked_div_ceiling(
+            U256::from(pool_supply).checked_mul(liquidity_ceiling.checked_sub(new_liquidity)?)?,
+            liquidity_floor,
+        )?;
+        u128::try_from(pool_tokens).ok()
+    }
+
+    fn validate(&self) -> Result<(), SwapError> {
+        if self.lower_price == 0 || self.lower_price >= self.upper_price {
+            return Err(SwapError::InvalidCurve);
+        }
+        match self.sqrt_prices() {
+            Some((sqrt_lower_price, sqrt_upper_price)) if sqrt_lower_price < sqrt_upper_price => {
+                Ok(())
+            }
+            _ => Err(SwapError::InvalidCurve),
+        }
+    }
+
+    /// The normalized value of the concentrated liquidity curve is its
+    /// liquidity, which is the square root of the invariant on the virtual
+    /// reserves
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+    ) -> Option<PreciseNumber> {
+        let liquidity = self.liquidity(
+            swap_token_a_amount,
+            swap_token_b_amount,
+            RoundDirection::Floor,
+        )?;
+        PreciseNumber::new(u128::try_from(liquidity).ok()?)?
+            .checked_div(&PreciseNumber::new(1u128 << LIQUIDITY_SHIFT)?)
+    }
+}
+
+/// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
+impl IsInitialized for ConcentratedLiquidityCurve {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+impl Sealed for ConcentratedLiquidityCurve {}
+impl Pack for ConcentratedLiquidityCurve {
+    const LEN: usize = 16;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        (self as &dyn DynPack).pack_into_slice(output);
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<ConcentratedLiquidityCurve, ProgramError> {
+        let input = array_ref![input, 0, 16];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (lower_price, upper_price) = array_refs![input, 8, 8];
+        Ok(Self {
+            lower_price: u64::from_le_bytes(*lower_price),
+            upper_price: u64::from_le_bytes(*upper_price),
+        })
+    }
+}
+
+impl DynPack for ConcentratedLiquidityCurve {
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 16];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (lower_price, upper_price) = mut_array_refs![output, 8, 8];
+        *lower_price = self.lower_price.to_le_bytes();
+        *upper_price = self.upper_price.to_le_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::calculator::{
+        test::{
+            check_curve_value_from_swap, check_deposit_token_conversion,
+            check_pool_value_from_deposit, check_pool_value_from_withdraw, check_spot_price,
+            check_swap_exact_out, check_withdraw_token_conversion, total_and_intermediate,
+            CONVERSION_BASIS_POINTS_GUARANTEE,
+        },
+        INITIAL_SWAP_POOL_AMOUNT,
+    };
+    use proptest::prelude::*;
+
+    // reserves holding a liquidity of 1_000_000 at a price of 2, in a range
+    // from 1 to 4
+    const SWAP_TOKEN_A_AMOUNT: u128 = 207_106;
+    const SWAP_TOKEN_B_AMOUNT: u128 = 414_213;
+
+    fn test_curve() -> ConcentratedLiquidityCurve {
+        ConcentratedLiquidityCurve {
+            lower_price: PRICE_PRECISION,
+            upper_price: 4 * PRICE_PRECISION,
+        }
+    }
+
+    #[test]
+    fn pack_curve() {
+        let lower_price = u64::MAX - 1;
+        let upper_price = u64::MAX;
+        let curve = ConcentratedLiquidityCurve {
+            lower_price,
+            upper_price,
+        };
+
+        let mut packed = [0u8; ConcentratedLiquidityCurve::LEN];
+        Pack::pack_into_slice(&curve, &mut packed[..]);
+        let unpacked = ConcentratedLiquidityCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+
+        let mut packed = vec![];
+        packed.extend_from_slice(&lower_price.to_le_bytes());
+        packed.extend_from_slice(&upper_price.to_le_bytes());
+        let unpacked = ConcentratedLiquidityCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+    }
+
+    #[test]
+    fn validate_price_range() {
+        let valid_ranges: &[(u64, u64)] = &[
+            (1, 2),
+            (PRICE_PRECISION - 1, PRICE_PRECISION + 1),
+            (PRICE_PRECISION, 4 * PRICE_PRECISION),
+            (u64::MAX - 1, u64::MAX),
+        ];
+        for (lower_price, upper_price) in valid_ranges.iter() {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: *lower_price,
+                upper_price: *upper_price,
+            };
+            assert_eq!(curve.validate(), Ok(()));
+        }
+        let invalid_ranges: &[(u64, u64)] = &[(0, 0), (0, 1), (1, 1), (2, 1), (u64::MAX, 1)];
+        for (lower_price, upper_price) in invalid_ranges.iter() {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: *lower_price,
+                upper_price: *upper_price,
+            };
+            assert_eq!(curve.validate(), Err(SwapError::InvalidCurve));
+        }
+    }
+
+    #[test]
+    fn liquidity_rounding() {
+        let curve = test_curve();
+        // exact liquidity is 999_997.44
+        let floor = curve
+            .liquidity(
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                RoundDirection::Floor,
+            )
+            .unwrap();
+        let ceiling = curve
+            .liquidity(
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                RoundDirection::Ceiling,
+            )
+            .unwrap();
+        assert_eq!(floor >> LIQUIDITY_SHIFT, U256::from(999_997));
+        assert_eq!(ceiling >> LIQUIDITY_SHIFT, U256::from(999_997));
+        assert!(ceiling - floor <= U256::from(2));
+    }
+
+    #[test]
+    fn swap_calculation() {
+        let curve = test_curve();
+
+        // exact amount out is 19_721.11
+        let result = curve
+            .swap_without_fees(
+                10_000,
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 10_000);
+        assert_eq!(result.destination_amount_swapped, 19_721);
+
+        // exact amount out is 4_964.89
+        let result = curve
+            .swap_without_fees(
+                10_000,
+                SWAP_TOKEN_B_AMOUNT,
+                SWAP_TOKEN_A_AMOUNT,
+                TradeDirection::BtoA,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 10_000);
+        assert_eq!(result.destination_amount_swapped, 4_964);
+    }
+
+    #[test]
+    fn swap_out_of_range() {
+        let curve = test_curve();
+
+        // the lower price is reached after swapping in 292_892.7 token A
+        assert!(curve
+            .swap_without_fees(
+                292_000,
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                TradeDirection::AtoB,
+            )
+            .is_some());
+        assert!(curve
+            .swap_without_fees(
+                293_000,
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                TradeDirection::AtoB,
+            )
+            .is_none());
+
+        // the upper price is reached after swapping in 585_784.4 token B
+        assert!(curve
+            .swap_without_fees(
+                585_000,
+                SWAP_TOKEN_B_AMOUNT,
+                SWAP_TOKEN_A_AMOUNT,
+                TradeDirection::BtoA,
+            )
+            .is_some());
+        assert!(curve
+            .swap_without_fees(
+                586_000,
+                SWAP_TOKEN_B_AMOUNT,
+                SWAP_TOKEN_A_AMOUNT,
+                TradeDirection::BtoA,
+            )
+            .is_none());
+
+        // buying out the whole reserve moves the price to the lower price
+        let result = curve
+            .swap_without_fees_exact_out(
+                SWAP_TOKEN_B_AMOUNT,
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 292_893);
+        assert!(curve
+            .swap_without_fees_exact_out(
+                SWAP_TOKEN_B_AMOUNT + 1,
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                TradeDirection::AtoB,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn single_sided_reserves() {
+        let curve = test_curve();
+        // below the range, the pool only holds token A, which is sold as the
+        // price rises
+        let liquidity = curve.liquidity(500_000, 0, RoundDirection::Floor).unwrap();
+        assert_eq!(liquidity, U256::from(1_000_000) << LIQUIDITY_SHIFT);
+        assert!(curve
+            .swap_without_fees(1_000, 500_000, 0, TradeDirection::AtoB)
+            .is_none());
+        let result = curve
+            .swap_without_fees(1_000, 0, 500_000, TradeDirection::BtoA)
+            .unwrap();
+        assert_eq!(result.destination_amount_swapped, 999);
+    }
+
+    #[test]
+    fn spot_price() {
+        let curve = test_curve();
+        let spot_price = curve
+            .spot_price(
+                SWAP_TOKEN_A_AMOUNT,
+                SWAP_TOKEN_B_AMOUNT,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        assert!((spot_price as i128 - (2 << 64)).abs() < 1 << 50);
+        let spot_price = curve
+            .spot_price(
+                SWAP_TOKEN_B_AMOUNT,
+                SWAP_TOKEN_A_AMOUNT,
+                TradeDirection::BtoA,
+            )
+            .unwrap();
+        assert!((spot_price as i128 - (1 << 63)).abs() < 1 << 50);
+
+        // at the edges of the range, the price is the bound of the range
+        let spot_price = curve.spot_price(500_000, 0, TradeDirection::AtoB).unwrap();
+        assert!((spot_price as i128 - (1 << 64)).abs() < 1 << 50);
+        assert_eq!(curve.spot_price(0, 0, TradeDirection::AtoB), None);
+    }
+
+    // The narrower the range, and the closer the price is to its edges, the
+    // more liquidity a single token of rounding is worth.  To stay within the
+    // normalized token allowed by the value check, the pools are balanced in
+    // the middle of a wide range, and the swaps are kept small compared to the
+    // reserves.
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_swap(
+            swap_token_amount in 1_000..u64::MAX,
+            source_per_mille in 1..100u64,
+            range_multiplier in 10..1_000u64,
+        ) {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: PRICE_PRECISION / range_multiplier,
+                upper_price: PRICE_PRECISION * range_multiplier,
+            };
+            let source_token_amount = swap_token_amount / 1_000 * source_per_mille;
+            check_curve_value_from_swap(
+                &curve,
+                source_token_amount as u128,
+                swap_token_amount as u128,
+                swap_token_amount as u128,
+                TradeDirection::AtoB
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_swap_b_to_a(
+            swap_token_amount in 1_000..u64::MAX,
+            source_per_mille in 1..100u64,
+            range_multiplier in 10..1_000u64,
+        ) {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: PRICE_PRECISION / range_multiplier,
+                upper_price: PRICE_PRECISION * range_multiplier,
+            };
+            let source_token_amount = swap_token_amount / 1_000 * source_per_mille;
+            check_curve_value_from_swap(
+                &curve,
+                source_token_amount as u128,
+                swap_token_amount as u128,
+                swap_token_amount as u128,
+                TradeDirection::BtoA
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn spot_price_matches_small_swap(
+            swap_token_amount in 1_000_000_000..u64::MAX,
+            range_multiplier in 10..1_000u64,
+        ) {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: PRICE_PRECISION / range_multiplier,
+                upper_price: PRICE_PRECISION * range_multiplier,
+            };
+            check_spot_price(
+                &curve,
+                swap_token_amount as u128,
+                swap_token_amount as u128,
+                TradeDirection::AtoB,
+            );
+            check_spot_price(
+                &curve,
+                swap_token_amount as u128,
+                swap_token_amount as u128,
+                TradeDirection::BtoA,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            lower_price in PRICE_PRECISION / 100..PRICE_PRECISION,
+            range_multiplier in 2..100u64,
+        ) {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price,
+                upper_price: lower_price * range_multiplier,
+            };
+            // the swap must stay within the price range
+            prop_assume!(curve.swap_without_fees_exact_out(
+                destination_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB
+            ).is_some());
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_deposit(
+            pool_token_amount in 1..u64::MAX,
+            pool_token_supply in 1..u64::MAX,
+            swap_token_a_amount in 1..u64::MAX,
+            swap_token_b_amount in 1..u64::MAX,
+        ) {
+            let curve = test_curve();
+            let pool_token_amount = pool_token_amount as u128;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_a_amount = swap_token_a_amount as u128;
+            let swap_token_b_amount = swap_token_b_amount as u128;
+            // Make sure we will get at least one trading token out for each
+            // side, otherwise the calculation fails
+            prop_assume!(pool_token_amount * swap_token_a_amount / pool_token_supply >= 1);
+            prop_assume!(pool_token_amount * swap_token_b_amount / pool_token_supply >= 1);
+            check_pool_value_from_deposit(
+                &curve,
+                pool_token_amount,
+                pool_token_supply,
+                swap_token_a_amount,
+                swap_token_b_amount,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_withdraw(
+            (pool_token_supply, pool_token_amount) in total_and_intermediate(),
+            swap_token_a_amount in 1..u64::MAX,
+            swap_token_b_amount in 1..u64::MAX,
+        ) {
+            let curve = test_curve();
+            let pool_token_amount = pool_token_amount as u128;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_a_amount = swap_token_a_amount as u128;
+            let swap_token_b_amount = swap_token_b_amount as u128;
+            // Make sure we will get at least one trading token out for each
+            // side, otherwise the calculation fails
+            prop_assume!(pool_token_amount * swap_token_a_amount / pool_token_supply >= 1);
+            prop_assume!(pool_token_amount * swap_token_b_amount / pool_token_supply >= 1);
+            check_pool_value_from_withdraw(
+                &curve,
+                pool_token_amount,
+                pool_token_supply,
+                swap_token_a_amount,
+                swap_token_b_amount,
+            );
+        }
+    }
+
+    // The conversion checks chain a swap with a single-sided deposit or
+    // withdrawal, so the amounts are kept to a small share of the pool to
+    // stay within the price range.
+
+    proptest! {
+        #[test]
+        fn deposit_token_conversion(
+            swap_source_amount in 100_000..u64::MAX,
+            swap_destination_amount in 100_000..u64::MAX,
+            deposit_per_mille in 1..100u64,
+            pool_supply in INITIAL_SWAP_POOL_AMOUNT..u64::MAX as u128,
+        ) {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: 1,
+                upper_price: u64::MAX,
+            };
+            let source_token_amount = swap_source_amount / 1_000 * deposit_per_mille;
+            check_deposit_token_conversion(
+                &curve,
+                source_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB,
+                pool_supply,
+                CONVERSION_BASIS_POINTS_GUARANTEE,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn withdraw_token_conversion(
+            pool_token_supply in INITIAL_SWAP_POOL_AMOUNT..u64::MAX as u128,
+            withdraw_per_mille in 1..50u128,
+            swap_token_a_amount in 100_000..u64::MAX,
+            swap_token_b_amount in 100_000..u64::MAX,
+        ) {
+            let curve = ConcentratedLiquidityCurve {
+                lower_price: 1,
+                upper_price: u64::MAX,
+            };
+            let pool_token_amount = pool_token_supply / 1_000 * withdraw_per_mille;
+            check_withdraw_token_conversion(
+                &curve,
+                pool_token_amount,
+                pool_token_supply,
+                swap_token_a_amount as u128,
+                swap_token_b_amount as u128,
+                TradeDirection::AtoB,
+                CONVERSION_BASIS_POINTS_GUARANTEE
+            );
+        }
+    }
+}
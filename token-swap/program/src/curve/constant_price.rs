@@ -2,8 +2,8 @@
 use {
     crate::{
         curve::calculator::{
-            map_zero_to_none, CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult,
-            TradeDirection, TradingTokenResult,
+            checked_spot_price, map_zero_to_none, CurveCalculator, DynPack, RoundDirection,
+            SwapWithoutFeesResult, TradeDirection, TradingTokenResult,
         },
         error::SwapError,
     },
@@ -13,6 +13,7 @@ use {
         program_pack::{IsInitialized, Pack, Sealed},
     },
     spl_math::{checked_ceil_div::CheckedCeilDiv, precise_number::PreciseNumber, uint::U256},
+    std::convert::TryFrom,
 };
 
 /// Get the amount of pool tokens for the given amount of token A or B.
@@ -98,6 +99,47 @@ impl CurveCalculator for ConstantPriceCurve {
         })
     }
 
+    /// Constant price curve charges the price of the destination amount,
+    /// rounding up when buying token A with token B
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        _swap_source_amount: u128,
+        _swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let token_b_price = self.token_b_price as u128;
+
+        let source_amount_swapped = match trade_direction {
+            TradeDirection::AtoB => destination_amount.checked_mul(token_b_price)?,
+            TradeDirection::BtoA => destination_amount
+                .checked_add(token_b_price.checked_sub(1)?)?
+                .checked_div(token_b_price)?,
+        };
+        let source_amount_swapped = map_zero_to_none(source_amount_swapped)?;
+        let destination_amount_swapped = map_zero_to_none(destination_amount)?;
+        Some(SwapWithoutFeesResult {
+            source_amount_swapped,
+            destination_amount_swapped,
+        })
+    }
+
+    /// The constant price curve always trades at the set price, whatever the
+    /// balances
+    fn spot_price(
+        &self,
+        _swap_source_amount: u128,
+        _swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let token_b_price = U256::from(self.token_b_price);
+        let spot_price = match trade_direction {
+            TradeDirection::AtoB => checked_spot_price(U256::one(), token_b_price)?,
+            TradeDirection::BtoA => checked_spot_price(token_b_price, U256::one())?,
+        };
+        u128::try_from(spot_price).ok()
+    }
+
     /// Get the amount of trading tokens for the given amount of pool tokens,
     /// provided the total trading tokens and supply of pool tokens.
     /// For the constant price curve, the total value of the pool is weighted
@@ -264,7 +306,7 @@ mod tests {
     use super::*;
     use crate::curve::calculator::{
         test::{
-            check_curve_value_from_swap, check_deposit_token_conversion,
+            check_curve_value_from_swap, check_deposit_token_conversion, check_swap_exact_out,
             check_withdraw_token_conversion, total_and_intermediate,
             CONVERSION_BASIS_POINTS_GUARANTEE,
         },
@@ -385,6 +427,109 @@ mod tests {
         assert_eq!(result.destination_amount_swapped, 1u128);
     }
 
+    #[test]
+    fn swap_exact_out_calculation() {
+        let token_b_price = 1_000;
+        let curve = ConstantPriceCurve { token_b_price };
+        let swap_token_a_amount = 1_000_000;
+        let swap_token_b_amount = 1_000;
+
+        let result = curve
+            .swap_without_fees_exact_out(
+                5,
+                swap_token_a_amount,
+                swap_token_b_amount,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 5_000);
+        assert_eq!(result.destination_amount_swapped, 5);
+
+        // partial token B is rounded up
+        let result = curve
+            .swap_without_fees_exact_out(
+                1_500,
+                swap_token_b_amount,
+                swap_token_a_amount,
+                TradeDirection::BtoA,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 2);
+        assert_eq!(result.destination_amount_swapped, 1_500);
+
+        assert!(curve
+            .swap_without_fees_exact_out(
+                0,
+                swap_token_b_amount,
+                swap_token_a_amount,
+                TradeDirection::BtoA,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn constant_price_spot_price() {
+        let token_b_price = 4;
+        let curve = ConstantPriceCurve { token_b_price };
+        assert_eq!(
+            curve.spot_price(1_000, 1_000, TradeDirection::AtoB),
+            Some(1 << 62)
+        );
+        assert_eq!(
+            curve.spot_price(1_000, 1_000, TradeDirection::BtoA),
+            Some(4 << 64)
+        );
+        assert_eq!(curve.spot_price(0, 0, TradeDirection::BtoA), Some(4 << 64));
+
+        let curve = ConstantPriceCurve {
+            token_b_price: u64::MAX,
+        };
+        assert_eq!(
+            curve.spot_price(1_000, 1_000, TradeDirection::BtoA),
+            Some((u64::MAX as u128) << 64)
+        );
+        assert_eq!(
+            curve.spot_price(1_000, 1_000, TradeDirection::AtoB),
+            Some(1)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap_a_to_b(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            token_b_price in 1..u64::MAX,
+        ) {
+            let curve = ConstantPriceCurve { token_b_price };
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap_b_to_a(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            token_b_price in 1..u64::MAX,
+        ) {
+            let curve = ConstantPriceCurve { token_b_price };
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::BtoA,
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn deposit_token_conversion_a_to_b(
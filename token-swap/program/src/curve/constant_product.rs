@@ -3,8 +3,8 @@
 use {
     crate::{
         curve::calculator::{
-            map_zero_to_none, CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult,
-            TradeDirection, TradingTokenResult,
+            checked_spot_price, map_zero_to_none, CurveCalculator, DynPack, RoundDirection,
+            SwapWithoutFeesResult, TradeDirection, TradingTokenResult,
         },
         error::SwapError,
     },
@@ -12,7 +12,8 @@ use {
         program_error::ProgramError,
         program_pack::{IsInitialized, Pack, Sealed},
     },
-    spl_math::{checked_ceil_div::CheckedCeilDiv, precise_number::PreciseNumber},
+    spl_math::{checked_ceil_div::CheckedCeilDiv, precise_number::PreciseNumber, uint::U256},
+    std::convert::TryFrom,
 };
 
 /// ConstantProductCurve struct implementing CurveCalculator
@@ -45,6 +46,32 @@ pub fn swap(
     })
 }
 
+/// The inverse of the constant product swap calculation, giving the source
+/// amount needed for an exact destination amount, factored out of its class
+/// for reuse.
+///
+/// The source amount is rounded up so that the invariant never decreases.
+pub fn swap_exact_out(
+    destination_amount: u128,
+    swap_source_amount: u128,
+    swap_destination_amount: u128,
+) -> Option<SwapWithoutFeesResult> {
+    let invariant = swap_source_amount.checked_mul(swap_destination_amount)?;
+
+    let new_swap_destination_amount =
+        map_zero_to_none(swap_destination_amount.checked_sub(destination_amount)?)?;
+    let (new_swap_source_amount, _) = invariant.checked_ceil_div(new_swap_destination_amount)?;
+
+    let source_amount_swapped =
+        map_zero_to_none(new_swap_source_amount.checked_sub(swap_source_amount)?)?;
+    let destination_amount_swapped = map_zero_to_none(destination_amount)?;
+
+    Some(SwapWithoutFeesResult {
+        source_amount_swapped,
+        destination_amount_swapped,
+    })
+}
+
 /// Get the amount of trading tokens for the given amount of pool tokens,
 /// provided the total trading tokens and supply of pool tokens.
 ///
@@ -154,6 +181,18 @@ pub fn withdraw_single_token_type_exact_out(
     }
 }
 
+/// The constant product spot price, factored out of its class for reuse.
+///
+/// The marginal price of x * y = constant is the ratio of the destination
+/// balance to the source balance.
+pub fn spot_price(swap_source_amount: u128, swap_destination_amount: u128) -> Option<u128> {
+    u128::try_from(checked_spot_price(
+        U256::from(swap_destination_amount),
+        U256::from(swap_source_amount),
+    )?)
+    .ok()
+}
+
 /// Calculates the total normalized value of the curve given the liquidity
 /// parameters.
 ///
@@ -182,6 +221,29 @@ impl CurveCalculator for ConstantProductCurve {
         swap(source_amount, swap_source_amount, swap_destination_amount)
     }
 
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        _trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        swap_exact_out(
+            destination_amount,
+            swap_source_amount,
+            swap_destination_amount,
+        )
+    }
+
+    fn spot_price(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        _trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        spot_price(swap_source_amount, swap_destination_amount)
+    }
+
     /// The constant product implementation is a simple ratio calculation for how many
     /// trading tokens correspond to a certain number of pool tokens
     fn pool_tokens_to_trading_tokens(
@@ -279,8 +341,8 @@ mod tests {
     use crate::curve::calculator::{
         test::{
             check_curve_value_from_swap, check_deposit_token_conversion,
-            check_pool_value_from_deposit, check_pool_value_from_withdraw,
-            check_withdraw_token_conversion, total_and_intermediate,
+            check_pool_value_from_deposit, check_pool_value_from_withdraw, check_spot_price,
+            check_swap_exact_out, check_withdraw_token_conversion, total_and_intermediate,
             CONVERSION_BASIS_POINTS_GUARANTEE,
         },
         RoundDirection, INITIAL_SWAP_POOL_AMOUNT,
@@ -414,6 +476,93 @@ mod tests {
         }
     }
 
+    #[test]
+    fn constant_product_swap_exact_out_rounding() {
+        let curve = ConstantProductCurve::default();
+
+        // the whole destination side can never be bought
+        assert!(curve
+            .swap_without_fees_exact_out(10_000, 20_000, 10_000, TradeDirection::AtoB)
+            .is_none());
+
+        let tests: &[(u128, u128, u128, u128)] = &[
+            (15, 20_000, 30_000, 11), // spot: 15 * 2 / 3 = 10, rounded up by the invariant
+            (14, 20_000, 30_000, 10), // spot: 14 * 2 / 2.9986 = 9.34
+            (49, 60_000, 30_000, 99), // spot: 49 * 6 / 2.9951 = 98.16
+            (6, 30_000 - 20, 10_000, 18), // spot: 6 * 2.998 / 0.9994 = 17.99
+        ];
+        for (
+            destination_amount,
+            swap_source_amount,
+            swap_destination_amount,
+            expected_source_amount,
+        ) in tests.iter()
+        {
+            let result = curve
+                .swap_without_fees_exact_out(
+                    *destination_amount,
+                    *swap_source_amount,
+                    *swap_destination_amount,
+                    TradeDirection::AtoB,
+                )
+                .unwrap();
+            assert_eq!(result.source_amount_swapped, *expected_source_amount);
+            assert_eq!(result.destination_amount_swapped, *destination_amount);
+            let invariant = swap_source_amount * swap_destination_amount;
+            let new_invariant = (swap_source_amount + result.source_amount_swapped)
+                * (swap_destination_amount - result.destination_amount_swapped);
+            assert!(new_invariant >= invariant);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+        ) {
+            let curve = ConstantProductCurve {};
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB,
+            );
+        }
+    }
+
+    #[test]
+    fn constant_product_spot_price() {
+        let curve = ConstantProductCurve {};
+        assert_eq!(
+            curve.spot_price(1_000, 2_000, TradeDirection::AtoB),
+            Some(2 << 64)
+        );
+        assert_eq!(
+            curve.spot_price(2_000, 1_000, TradeDirection::BtoA),
+            Some(1 << 63)
+        );
+        assert_eq!(curve.spot_price(0, 1_000, TradeDirection::AtoB), None);
+        assert_eq!(curve.spot_price(1_000, 0, TradeDirection::AtoB), None);
+    }
+
+    proptest! {
+        #[test]
+        fn spot_price_matches_small_swap(
+            swap_source_amount in 1_000_000_000..u64::MAX,
+            swap_destination_amount in 1_000_000_000..u64::MAX,
+        ) {
+            let curve = ConstantProductCurve {};
+            check_spot_price(
+                &curve,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB,
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn deposit_token_conversion(
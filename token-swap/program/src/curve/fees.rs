@@ -3,10 +3,15 @@
 use crate::error::SwapError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
+    clock::UnixTimestamp,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
 };
-use std::convert::TryFrom;
+use spl_math::{checked_ceil_div::CheckedCeilDiv, uint::U256};
+use std::convert::{TryFrom, TryInto};
+
+/// Number of basis points in 100%, used to express volatility
+pub const BASIS_POINTS: u64 = 10_000;
 
 /// Encapsulates all fee information and calculations for swap operations
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -61,6 +66,15 @@ pub fn calculate_fee(
     }
 }
 
+/// Fee fraction as `u128`, with a zero fee expressed as `0 / 1`
+fn fee_fraction(numerator: u64, denominator: u64) -> (u128, u128) {
+    if numerator == 0 || denominator == 0 {
+        (0, 1)
+    } else {
+        (numerator as u128, denominator as u128)
+    }
+}
+
 fn validate_fraction(numerator: u64, denominator: u64) -> Result<(), SwapError> {
     if denominator == 0 && numerator == 0 {
         Ok(())
@@ -99,6 +113,48 @@ impl Fees {
         )
     }
 
+    /// Calculate the amount of trading tokens which, once the trading and
+    /// owner trading fees are taken out, leaves at least `post_fee_amount`.
+    /// This is the inverse of subtracting both fees, used for swaps with an
+    /// exact output.
+    pub fn pre_trading_fee_amount(&self, post_fee_amount: u128) -> Option<u128> {
+        if post_fee_amount == 0 {
+            return Some(0);
+        }
+        let (trade_fee_numerator, trade_fee_denominator) =
+            fee_fraction(self.trade_fee_numerator, self.trade_fee_denominator);
+        let (owner_fee_numerator, owner_fee_denominator) = fee_fraction(
+            self.owner_trade_fee_numerator,
+            self.owner_trade_fee_denominator,
+        );
+        // combine both fees into a single fraction of the pre-fee amount
+        let fee_denominator = trade_fee_denominator.checked_mul(owner_fee_denominator)?;
+        let fee_numerator = trade_fee_numerator
+            .checked_mul(owner_fee_denominator)?
+            .checked_add(owner_fee_numerator.checked_mul(trade_fee_denominator)?)?;
+        let (mut pre_fee_amount, _) = post_fee_amount
+            .checked_mul(fee_denominator)?
+            .checked_ceil_div(fee_denominator.checked_sub(fee_numerator)?)?;
+
+        // each fee is rounded down, or up to a minimum of one token, so the
+        // estimate can be off by a few tokens either way
+        let amount_less_fees = |amount: u128| -> Option<u128> {
+            let total_fees = self
+                .trading_fee(amount)?
+                .checked_add(self.owner_trading_fee(amount)?)?;
+            Some(amount.saturating_sub(total_fees))
+        };
+        while pre_fee_amount > post_fee_amount
+            && amount_less_fees(pre_fee_amount.checked_sub(1)?)? >= post_fee_amount
+        {
+            pre_fee_amount = pre_fee_amount.checked_sub(1)?;
+        }
+        while amount_less_fees(pre_fee_amount)? < post_fee_amount {
+            pre_fee_amount = pre_fee_amount.checked_add(1)?;
+        }
+        Some(pre_fee_amount)
+    }
+
     /// Calculate the host fee based on the owner fee, only used in production
     /// situations where a program is hosted by multiple frontends
     pub fn host_fee(&self, owner_fee: u128) -> Option<u128> {
@@ -125,6 +181,122 @@ impl Fees {
     }
 }
 
+/// Encapsulates the configuration of a trade fee which rises with the recent
+/// volatility of the pool price.
+///
+/// Volatility is the sum of the price movements caused by recent swaps, in
+/// basis points, decaying linearly back to zero over `decay_period` seconds.
+/// On top of the base trade fee of [Fees](struct.Fees.html), every swap pays
+/// `volatility_fee_numerator / volatility_fee_denominator` of the volatility
+/// at the time of the swap, up to `max_trade_fee_numerator`.
+///
+/// All fields set to zero disable dynamic fees.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynamicFees {
+    /// Share of the volatility added to the trade fee numerator
+    pub volatility_fee_numerator: u64,
+    /// Share of the volatility added to the trade fee denominator
+    pub volatility_fee_denominator: u64,
+    /// Maximum trade fee numerator, over the trade fee denominator of
+    /// [Fees](struct.Fees.html)
+    pub max_trade_fee_numerator: u64,
+    /// Number of seconds for the volatility to decay back to zero
+    pub decay_period: u64,
+}
+
+impl DynamicFees {
+    /// Are the trade fees raised with volatility
+    pub fn is_enabled(&self) -> bool {
+        self.volatility_fee_numerator > 0
+    }
+
+    /// Calculate the volatility left after decaying for `time_elapsed` seconds
+    pub fn decayed_volatility(&self, volatility: u64, time_elapsed: UnixTimestamp) -> u64 {
+        let time_elapsed = u64::try_from(time_elapsed).unwrap_or(0);
+        if time_elapsed >= self.decay_period {
+            0
+        } else {
+            // cannot overflow, the decay period is a u64
+            (volatility as u128 * (self.decay_period - time_elapsed) as u128
+                / self.decay_period as u128) as u64
+        }
+    }
+
+    /// Calculate the fees of a trade at the given volatility, with the trade
+    /// fee raised from its base value, up to the maximum
+    pub fn trade_fees(&self, fees: &Fees, volatility: u64) -> Option<Fees> {
+        if !self.is_enabled() || volatility == 0 {
+            return Some(fees.clone());
+        }
+        let volatility_fee_numerator = U256::from(volatility)
+            .checked_mul(U256::from(self.volatility_fee_numerator))?
+            .checked_mul(U256::from(fees.trade_fee_denominator))?
+            .checked_div(
+                U256::from(self.volatility_fee_denominator)
+                    .checked_mul(U256::from(BASIS_POINTS))?,
+            )?;
+        let max_volatility_fee_numerator = self
+            .max_trade_fee_numerator
+            .saturating_sub(fees.trade_fee_numerator);
+        let volatility_fee_numerator =
+            if volatility_fee_numerator > U256::from(max_volatility_fee_numerator) {
+                max_volatility_fee_numerator
+            } else {
+                volatility_fee_numerator.as_u64()
+            };
+        Some(Fees {
+            trade_fee_numerator: fees
+                .trade_fee_numerator
+                .checked_add(volatility_fee_numerator)?,
+            ..fees.clone()
+        })
+    }
+
+    /// Validate that the dynamic fees are reasonable for the base fees
+    pub fn validate(&self, fees: &Fees) -> Result<(), SwapError> {
+        if !self.is_enabled() {
+            return if *self == Self::default() {
+                Ok(())
+            } else {
+                Err(SwapError::InvalidFee)
+            };
+        }
+        if self.volatility_fee_denominator == 0
+            || self.decay_period == 0
+            || self.max_trade_fee_numerator <= fees.trade_fee_numerator
+            || self.max_trade_fee_numerator >= fees.trade_fee_denominator
+        {
+            return Err(SwapError::InvalidFee);
+        }
+        Ok(())
+    }
+}
+
+/// Calculate the movement of the pool price, in basis points, between two
+/// sets of token balances, saturating on extreme movements
+pub fn price_movement(
+    token_a_amount: u128,
+    token_b_amount: u128,
+    new_token_a_amount: u128,
+    new_token_b_amount: u128,
+) -> Option<u64> {
+    // price of A in B is b / a, so the movement is |b' * a - b * a'| / (b * a')
+    let price = U256::from(token_b_amount).checked_mul(U256::from(new_token_a_amount))?;
+    let new_price = U256::from(new_token_b_amount).checked_mul(U256::from(token_a_amount))?;
+    if price.is_zero() {
+        return Some(0);
+    }
+    let difference = if new_price > price {
+        new_price.checked_sub(price)?
+    } else {
+        price.checked_sub(new_price)?
+    };
+    let movement = difference
+        .checked_mul(U256::from(BASIS_POINTS))?
+        .checked_div(price)?;
+    Some(movement.try_into().unwrap_or(u64::MAX))
+}
+
 /// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
 impl IsInitialized for Fees {
     fn is_initialized(&self) -> bool {
@@ -183,6 +355,48 @@ impl Pack for Fees {
     }
 }
 
+/// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
+impl IsInitialized for DynamicFees {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Sealed for DynamicFees {}
+impl Pack for DynamicFees {
+    const LEN: usize = 32;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 32];
+        let (
+            volatility_fee_numerator,
+            volatility_fee_denominator,
+            max_trade_fee_numerator,
+            decay_period,
+        ) = mut_array_refs![output, 8, 8, 8, 8];
+        *volatility_fee_numerator = self.volatility_fee_numerator.to_le_bytes();
+        *volatility_fee_denominator = self.volatility_fee_denominator.to_le_bytes();
+        *max_trade_fee_numerator = self.max_trade_fee_numerator.to_le_bytes();
+        *decay_period = self.decay_period.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<DynamicFees, ProgramError> {
+        let input = array_ref![input, 0, 32];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            volatility_fee_numerator,
+            volatility_fee_denominator,
+            max_trade_fee_numerator,
+            decay_period,
+        ) = array_refs![input, 8, 8, 8, 8];
+        Ok(Self {
+            volatility_fee_numerator: u64::from_le_bytes(*volatility_fee_numerator),
+            volatility_fee_denominator: u64::from_le_bytes(*volatility_fee_denominator),
+            max_trade_fee_numerator: u64::from_le_bytes(*max_trade_fee_numerator),
+            decay_period: u64::from_le_bytes(*decay_period),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,4 +439,176 @@ mod tests {
         let unpacked = Fees::unpack_from_slice(&packed).unwrap();
         assert_eq!(fees, unpacked);
     }
+
+    #[test]
+    fn pre_trading_fee_amount() {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        for &post_fee_amount in &[1, 2, 99, 100, 1_000, 123_456_789] {
+            let pre_fee_amount = fees.pre_trading_fee_amount(post_fee_amount).unwrap();
+            let total_fees = fees.trading_fee(pre_fee_amount).unwrap()
+                + fees.owner_trading_fee(pre_fee_amount).unwrap();
+            assert!(pre_fee_amount - total_fees >= post_fee_amount);
+            // one token less would not be enough
+            let smaller_amount = pre_fee_amount - 1;
+            let total_fees = fees.trading_fee(smaller_amount).unwrap()
+                + fees.owner_trading_fee(smaller_amount).unwrap();
+            assert!(smaller_amount - total_fees.min(smaller_amount) < post_fee_amount);
+        }
+        assert_eq!(fees.pre_trading_fee_amount(0), Some(0));
+
+        // no fees
+        let fees = Fees::default();
+        assert_eq!(fees.pre_trading_fee_amount(1_000), Some(1_000));
+
+        // fees taking the whole amount
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 2,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 2,
+            ..Fees::default()
+        };
+        assert_eq!(fees.pre_trading_fee_amount(1_000), None);
+    }
+
+    #[test]
+    fn pack_dynamic_fees() {
+        let dynamic_fees = DynamicFees {
+            volatility_fee_numerator: 1,
+            volatility_fee_denominator: 10,
+            max_trade_fee_numerator: 100,
+            decay_period: 600,
+        };
+
+        let mut packed = [0u8; DynamicFees::LEN];
+        Pack::pack_into_slice(&dynamic_fees, &mut packed[..]);
+        let unpacked = DynamicFees::unpack_from_slice(&packed).unwrap();
+        assert_eq!(dynamic_fees, unpacked);
+
+        let mut packed = vec![];
+        packed.extend_from_slice(&1u64.to_le_bytes());
+        packed.extend_from_slice(&10u64.to_le_bytes());
+        packed.extend_from_slice(&100u64.to_le_bytes());
+        packed.extend_from_slice(&600u64.to_le_bytes());
+        let unpacked = DynamicFees::unpack_from_slice(&packed).unwrap();
+        assert_eq!(dynamic_fees, unpacked);
+    }
+
+    #[test]
+    fn dynamic_trade_fees() {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let dynamic_fees = DynamicFees {
+            volatility_fee_numerator: 1,
+            volatility_fee_denominator: 10,
+            max_trade_fee_numerator: 100,
+            decay_period: 600,
+        };
+        dynamic_fees.validate(&fees).unwrap();
+
+        // no volatility, base fee
+        assert_eq!(dynamic_fees.trade_fees(&fees, 0).unwrap(), fees);
+
+        // 2% volatility adds 0.2%
+        let trade_fees = dynamic_fees.trade_fees(&fees, 200).unwrap();
+        assert_eq!(trade_fees.trade_fee_numerator, 45);
+        assert_eq!(trade_fees.owner_trade_fee_numerator, 5);
+
+        // capped at the maximum
+        let trade_fees = dynamic_fees.trade_fees(&fees, u64::MAX).unwrap();
+        assert_eq!(trade_fees.trade_fee_numerator, 100);
+
+        // disabled, base fee whatever the volatility
+        let trade_fees = DynamicFees::default().trade_fees(&fees, 200).unwrap();
+        assert_eq!(trade_fees, fees);
+    }
+
+    #[test]
+    fn decayed_volatility() {
+        let dynamic_fees = DynamicFees {
+            volatility_fee_numerator: 1,
+            volatility_fee_denominator: 10,
+            max_trade_fee_numerator: 100,
+            decay_period: 600,
+        };
+        assert_eq!(dynamic_fees.decayed_volatility(1_000, 0), 1_000);
+        assert_eq!(dynamic_fees.decayed_volatility(1_000, 150), 750);
+        assert_eq!(dynamic_fees.decayed_volatility(1_000, 600), 0);
+        assert_eq!(dynamic_fees.decayed_volatility(1_000, 6_000), 0);
+        assert_eq!(dynamic_fees.decayed_volatility(1_000, -10), 1_000);
+    }
+
+    #[test]
+    fn validate_dynamic_fees() {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let dynamic_fees = DynamicFees {
+            volatility_fee_numerator: 1,
+            volatility_fee_denominator: 10,
+            max_trade_fee_numerator: 100,
+            decay_period: 600,
+        };
+        assert_eq!(dynamic_fees.validate(&fees), Ok(()));
+        assert_eq!(DynamicFees::default().validate(&fees), Ok(()));
+
+        let invalid_dynamic_fees = [
+            DynamicFees {
+                volatility_fee_numerator: 0,
+                ..dynamic_fees.clone()
+            },
+            DynamicFees {
+                volatility_fee_denominator: 0,
+                ..dynamic_fees.clone()
+            },
+            DynamicFees {
+                decay_period: 0,
+                ..dynamic_fees.clone()
+            },
+            DynamicFees {
+                max_trade_fee_numerator: 25,
+                ..dynamic_fees.clone()
+            },
+            DynamicFees {
+                max_trade_fee_numerator: 10_000,
+                ..dynamic_fees.clone()
+            },
+        ];
+        for invalid_dynamic_fees in invalid_dynamic_fees.iter() {
+            assert_eq!(
+                invalid_dynamic_fees.validate(&fees),
+                Err(SwapError::InvalidFee)
+            );
+        }
+    }
+
+    #[test]
+    fn price_movement_basis_points() {
+        // no movement
+        assert_eq!(price_movement(1_000, 2_000, 2_000, 4_000), Some(0));
+        // price of A up 10%
+        assert_eq!(price_movement(1_000, 2_000, 1_000, 2_200), Some(1_000));
+        // price of A down 50%
+        assert_eq!(price_movement(1_000, 2_000, 2_000, 2_000), Some(5_000));
+        // empty pool
+        assert_eq!(price_movement(1_000, 0, 1_000, 2_000), Some(0));
+        // extreme movement saturates
+        assert_eq!(
+            price_movement(u64::MAX as u128, 1, 1, u64::MAX as u128),
+            Some(u64::MAX)
+        );
+    }
 }
@@ -2,8 +2,13 @@
 
 pub mod base;
 pub mod calculator;
+pub mod concentrated_liquidity;
 pub mod constant_price;
 pub mod constant_product;
 pub mod fees;
+pub mod multi_stable;
 pub mod offset;
+pub mod proactive_market_making;
 pub mod stable;
+pub mod stake_pool_stable;
+pub mod weighted;
@@ -0,0 +1,571 @@
+//! The curve.fi invariant calculator, generalized to pools of more than two tokens.
+
+use {
+    crate::{
+        curve::{
+            base::SwapResult,
+            calculator::{map_zero_to_none, RoundDirection, INITIAL_SWAP_POOL_AMOUNT},
+            fees::Fees,
+            stable::compute_d,
+        },
+        error::SwapError,
+    },
+    arrayref::{array_mut_ref, array_ref},
+    solana_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+    },
+    spl_math::{precise_number::PreciseNumber, uint::U256},
+    std::convert::TryFrom,
+};
+
+const ITERATIONS: u8 = 32;
+
+/// Compute the new amount of the destination token, given the amounts of all
+/// the other tokens in the pool after the trade
+/// Solve for y:
+/// y**2 + y * (sum' - (A*n**n - 1) * D / (A * n**n)) = D ** (n + 1) / (n ** (2 * n) * prod' * A)
+/// y**2 + b*y = c
+fn compute_new_destination_amount(
+    leverage: u64,
+    other_amounts: &[u128],
+    d_val: u128,
+) -> Option<u128> {
+    // Upscale to U256
+    let n_coins = U256::from(other_amounts.len().checked_add(1)?);
+    let leverage: U256 = leverage.into();
+    let d_val: U256 = d_val.into();
+
+    // c =  D ** (n + 1) / (n ** (2 * n) * prod' * A), dividing at every step
+    // to stay within bounds for large numbers of tokens
+    let mut c = d_val;
+    let mut sum = U256::zero();
+    for amount in other_amounts {
+        let amount = U256::from(*amount);
+        sum = sum.checked_add(amount)?;
+        c = c
+            .checked_mul(d_val)?
+            .checked_div(amount.checked_mul(n_coins)?)?;
+    }
+    c = c
+        .checked_mul(d_val)?
+        .checked_div(leverage.checked_mul(n_coins)?)?;
+
+    // b = sum' - (A*n**n - 1) * D / (A * n**n), with D subtracted below
+    let b = sum.checked_add(d_val.checked_div(leverage)?)?;
+
+    // Solve for y by approximating: y**2 + b*y = c
+    let mut y_prev: U256;
+    let mut y = d_val;
+    for _ in 0..ITERATIONS {
+        y_prev = y;
+        y = (y.checked_mul(y)?.checked_add(c)?).checked_div(
+            y.checked_mul(U256::from(2))?
+                .checked_add(b)?
+                .checked_sub(d_val)?,
+        )?;
+        if y == y_prev {
+            break;
+        }
+    }
+    u128::try_from(y).ok()
+}
+
+/// StableSwap curve for pools of any number of tokens.  Amounts are given
+/// for all of the pool's tokens, and tokens are referred to by their index
+/// in the pool.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MultiStableCurve {
+    /// Amplifier constant
+    pub amp: u64,
+}
+
+impl MultiStableCurve {
+    /// Leverage used by the invariant, A * n
+    fn leverage(&self, n_coins: usize) -> Option<u64> {
+        self.amp.checked_mul(u64::try_from(n_coins).ok()?)
+    }
+
+    /// Compute the stable swap invariant (D) for the given token amounts
+    pub fn compute_d(&self, swap_token_amounts: &[u128]) -> Option<u128> {
+        compute_d(self.leverage(swap_token_amounts.len())?, swap_token_amounts)
+    }
+
+    /// Calculate how much destination token will be provided given an amount
+    /// of source token, without any fees
+    pub fn swap_without_fees(
+        &self,
+        source_amount: u128,
+        source_index: usize,
+        destination_index: usize,
+        swap_token_amounts: &[u128],
+    ) -> Option<u128> {
+        if source_index == destination_index || source_index >= swap_token_amounts.len() {
+            return None;
+        }
+        let leverage = self.leverage(swap_token_amounts.len())?;
+        let d_val = compute_d(leverage, swap_token_amounts)?;
+
+        let other_amounts = swap_token_amounts
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != destination_index)
+            .map(|(index, amount)| {
+                if index == source_index {
+                    amount.checked_add(source_amount)
+                } else {
+                    Some(*amount)
+                }
+            })
+            .collect::<Option<Vec<u128>>>()?;
+        let new_destination_amount =
+            compute_new_destination_amount(leverage, &other_amounts, d_val)?;
+
+        // The invariant is only approximated, so take one more token to
+        // round in favor of the pool, like curve.fi
+        map_zero_to_none(
+            swap_token_amounts
+                .get(destination_index)?
+                .checked_sub(new_destination_amount)?
+                .saturating_sub(1),
+        )
+    }
+
+    /// Subtract fees and calculate how much destination token will be
+    /// provided given an amount of source token.
+    pub fn swap(
+        &self,
+        source_amount: u128,
+        source_index: usize,
+        destination_index: usize,
+        swap_token_amounts: &[u128],
+        fees: &Fees,
+    ) -> Option<SwapResult> {
+        // debit the fee to calculate the amount swapped
+        let trade_fee = fees.trading_fee(source_amount)?;
+        let owner_fee = fees.owner_trading_fee(source_amount)?;
+
+        let total_fees = trade_fee.checked_add(owner_fee)?;
+        let source_amount_less_fees = source_amount.checked_sub(total_fees)?;
+
+        let destination_amount_swapped = self.swap_without_fees(
+            source_amount_less_fees,
+            source_index,
+            destination_index,
+            swap_token_amounts,
+        )?;
+
+        Some(SwapResult {
+            new_swap_source_amount: swap_token_amounts
+                .get(source_index)?
+                .checked_add(source_amount)?,
+            new_swap_destination_amount: swap_token_amounts
+                .get(destination_index)?
+                .checked_sub(destination_amount_swapped)?,
+            source_amount_swapped: source_amount,
+            destination_amount_swapped,
+            trade_fee,
+            owner_fee,
+        })
+    }
+
+    /// Get the amount of each trading token for the given amount of pool
+    /// tokens, provided the total number of pool tokens in existence.
+    pub fn pool_tokens_to_trading_tokens(
+        &self,
+        pool_tokens: u128,
+        pool_token_supply: u128,
+        swap_token_amounts: &[u128],
+        round_direction: RoundDirection,
+    ) -> Option<Vec<u128>> {
+        swap_token_amounts
+            .iter()
+            .map(|swap_token_amount| {
+                let token_amount = pool_tokens
+                    .checked_mul(*swap_token_amount)?
+                    .checked_div(pool_token_supply)?;
+                match round_direction {
+                    RoundDirection::Floor => Some(token_amount),
+                    RoundDirection::Ceiling => {
+                        let token_remainder = pool_tokens
+                            .checked_mul(*swap_token_amount)?
+                            .checked_rem(pool_token_supply)?;
+                        if token_remainder > 0 {
+                            token_amount.checked_add(1)
+                        } else {
+                            Some(token_amount)
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Get the amount of pool tokens worth the given amount of one of the
+    /// pool's tokens, if it were withdrawn from the pool
+    pub fn withdraw_single_token_type_exact_out(
+        &self,
+        source_amount: u128,
+        source_index: usize,
+        swap_token_amounts: &[u128],
+        pool_supply: u128,
+        round_direction: RoundDirection,
+    ) -> Option<u128> {
+        if source_amount == 0 {
+            return Some(0);
+        }
+        let d0 = PreciseNumber::new(self.compute_d(swap_token_amounts)?)?;
+        let mut updated_token_amounts = swap_token_amounts.to_vec();
+        let updated_token_amount = updated_token_amounts.get_mut(source_index)?;
+        *updated_token_amount = updated_token_amount.checked_sub(source_amount)?;
+        let d1 = PreciseNumber::new(self.compute_d(&updated_token_amounts)?)?;
+        let diff = d0.checked_sub(&d1)?;
+        let final_amount =
+            (diff.checked_mul(&PreciseNumber::new(pool_supply)?))?.checked_div(&d0)?;
+        match round_direction {
+            RoundDirection::Floor => final_amount.floor()?.to_imprecise(),
+            RoundDirection::Ceiling => final_amount.ceiling()?.to_imprecise(),
+        }
+    }
+
+    /// Get the supply for a new pool
+    pub fn new_pool_supply(&self) -> u128 {
+        INITIAL_SWAP_POOL_AMOUNT
+    }
+
+    /// Validate the given curve
+    pub fn validate(&self) -> Result<(), SwapError> {
+        if self.amp == 0 {
+            Err(SwapError::InvalidCurve)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
+impl IsInitialized for MultiStableCurve {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+impl Sealed for MultiStableCurve {}
+impl Pack for MultiStableCurve {
+    const LEN: usize = 8;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let amp = array_mut_ref![output, 0, 8];
+        *amp = self.amp.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<MultiStableCurve, ProgramError> {
+        let amp = array_ref![input, 0, 8];
+        Ok(Self {
+            amp: u64::from_le_bytes(*amp),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use sim::StableSwapModel;
+    use spl_math::uint::U256;
+
+    #[test]
+    fn initial_pool_amount() {
+        let amp = 1;
+        let calculator = MultiStableCurve { amp };
+        assert_eq!(calculator.new_pool_supply(), INITIAL_SWAP_POOL_AMOUNT);
+    }
+
+    #[test]
+    fn pack_curve() {
+        let amp = 100;
+        let curve = MultiStableCurve { amp };
+
+        let mut packed = [0u8; MultiStableCurve::LEN];
+        Pack::pack_into_slice(&curve, &mut packed[..]);
+        let unpacked = MultiStableCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+
+        let mut packed = vec![];
+        packed.extend_from_slice(&amp.to_le_bytes());
+        let unpacked = MultiStableCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+    }
+
+    #[test]
+    fn validate_amp() {
+        assert_eq!(
+            MultiStableCurve { amp: 0 }.validate(),
+            Err(SwapError::InvalidCurve)
+        );
+        assert_eq!(MultiStableCurve { amp: 1 }.validate(), Ok(()));
+    }
+
+    #[test]
+    fn balanced_invariant() {
+        let curve = MultiStableCurve { amp: 100 };
+        assert_eq!(curve.compute_d(&[1_000_000_000; 3]).unwrap(), 3_000_000_000);
+        assert_eq!(curve.compute_d(&[0; 3]).unwrap(), 0);
+    }
+
+    #[test]
+    fn swap_calculation() {
+        let curve = MultiStableCurve { amp: 100 };
+        let swap_token_amounts = [1_000_000_000, 1_000_000_000, 1_000_000_000];
+        assert_eq!(
+            curve
+                .swap_without_fees(1_000_000, 0, 2, &swap_token_amounts)
+                .unwrap(),
+            999_990
+        );
+
+        let swap_token_amounts = [1_000_000_000, 1_200_000_000, 800_000_000];
+        assert_eq!(
+            curve
+                .swap_without_fees(100_000_000, 0, 2, &swap_token_amounts)
+                .unwrap(),
+            99_600_547
+        );
+
+        let curve = MultiStableCurve { amp: 10 };
+        let swap_token_amounts = [5_000_000; 8];
+        assert_eq!(
+            curve
+                .swap_without_fees(1_000_000, 3, 7, &swap_token_amounts)
+                .unwrap(),
+            981_557
+        );
+
+        // invalid token indexes
+        assert!(curve
+            .swap_without_fees(1_000_000, 3, 3, &swap_token_amounts)
+            .is_none());
+        assert!(curve
+            .swap_without_fees(1_000_000, 8, 3, &swap_token_amounts)
+            .is_none());
+        assert!(curve
+            .swap_without_fees(1_000_000, 3, 8, &swap_token_amounts)
+            .is_none());
+    }
+
+    #[test]
+    fn swap_with_fees() {
+        let curve = MultiStableCurve { amp: 100 };
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let swap_token_amounts = [1_000_000_000, 1_000_000_000, 1_000_000_000];
+        let result = curve
+            .swap(1_000_000, 1, 0, &swap_token_amounts, &fees)
+            .unwrap();
+        assert_eq!(result.trade_fee, 2_500);
+        assert_eq!(result.owner_fee, 500);
+        assert_eq!(result.source_amount_swapped, 1_000_000);
+        assert_eq!(result.new_swap_source_amount, 1_001_000_000);
+        assert_eq!(
+            result.destination_amount_swapped,
+            curve
+                .swap_without_fees(997_000, 1, 0, &swap_token_amounts)
+                .unwrap()
+        );
+        assert_eq!(
+            result.new_swap_destination_amount,
+            1_000_000_000 - result.destination_amount_swapped
+        );
+    }
+
+    #[test]
+    fn trading_token_conversion() {
+        let curve = MultiStableCurve { amp: 1 };
+        let swap_token_amounts = [2, 49, 100];
+        assert_eq!(
+            curve
+                .pool_tokens_to_trading_tokens(5, 10, &swap_token_amounts, RoundDirection::Floor)
+                .unwrap(),
+            vec![1, 24, 50]
+        );
+        assert_eq!(
+            curve
+                .pool_tokens_to_trading_tokens(5, 10, &swap_token_amounts, RoundDirection::Ceiling)
+                .unwrap(),
+            vec![1, 25, 50]
+        );
+    }
+
+    #[test]
+    fn withdraw_single_token_rounding() {
+        let curve = MultiStableCurve { amp: 100 };
+        let swap_token_amounts = [1_000_000_000, 1_000_000_000, 1_000_000_000];
+        let floor = curve
+            .withdraw_single_token_type_exact_out(
+                1_000,
+                1,
+                &swap_token_amounts,
+                INITIAL_SWAP_POOL_AMOUNT,
+                RoundDirection::Floor,
+            )
+            .unwrap();
+        let ceiling = curve
+            .withdraw_single_token_type_exact_out(
+                1_000,
+                1,
+                &swap_token_amounts,
+                INITIAL_SWAP_POOL_AMOUNT,
+                RoundDirection::Ceiling,
+            )
+            .unwrap();
+        assert!(floor > 0);
+        assert!(floor <= ceiling && ceiling <= floor + 1);
+        assert_eq!(
+            curve
+                .withdraw_single_token_type_exact_out(
+                    0,
+                    1,
+                    &swap_token_amounts,
+                    INITIAL_SWAP_POOL_AMOUNT,
+                    RoundDirection::Ceiling,
+                )
+                .unwrap(),
+            0
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn multi_stable_swap_no_fee(
+            n_coins in 3..=8u8,
+            swap_token_amount in 100..1_000_000_000_000_000_000u128,
+            source_amount in 100..100_000_000_000u128,
+            amp in 1..150u64
+        ) {
+            prop_assume!(source_amount < swap_token_amount);
+
+            let curve = MultiStableCurve { amp };
+            let swap_token_amounts = vec![swap_token_amount; n_coins as usize];
+
+            let model: StableSwapModel = StableSwapModel::new(
+                curve.amp.into(),
+                swap_token_amounts.clone(),
+                n_coins,
+            );
+
+            let result = curve
+                .swap_without_fees(source_amount, 0, 1, &swap_token_amounts)
+                .unwrap();
+            let sim_result = model.sim_exchange(0, 1, source_amount);
+
+            // the curve takes one extra token in favor of the pool
+            let diff = (sim_result as i128 - result as i128 - 1).abs();
+
+            let tolerance = std::cmp::max(1, sim_result as i128 / 1_000_000_000);
+
+            assert!(
+                diff <= tolerance,
+                "result={}, sim_result={}, amp={}, source_amount={}, swap_token_amount={}, diff={}",
+                result,
+                sim_result,
+                amp,
+                source_amount,
+                swap_token_amount,
+                diff
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_swap(
+            swap_token_amounts in prop::collection::vec(1_000..u64::MAX, 3..=8),
+            source_per_mille in 1..1_000u128,
+            source_index in 0..8usize,
+            destination_index in 0..8usize,
+            amp in 1..150u64,
+        ) {
+            let n_coins = swap_token_amounts.len();
+            let source_index = source_index % n_coins;
+            let destination_index = destination_index % n_coins;
+            prop_assume!(source_index != destination_index);
+            let swap_token_amounts: Vec<u128> =
+                swap_token_amounts.into_iter().map(u128::from).collect();
+            let source_amount = swap_token_amounts[source_index] * source_per_mille / 1_000;
+
+            let curve = MultiStableCurve { amp };
+            if let Some(destination_amount) = curve.swap_without_fees(
+                source_amount,
+                source_index,
+                destination_index,
+                &swap_token_amounts,
+            ) {
+                let mut new_swap_token_amounts = swap_token_amounts.clone();
+                new_swap_token_amounts[source_index] += source_amount;
+                new_swap_token_amounts[destination_index] -= destination_amount;
+
+                let previous_value = curve.compute_d(&swap_token_amounts).unwrap();
+                let new_value = curve.compute_d(&new_swap_token_amounts).unwrap();
+                assert!(new_value >= previous_value);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_deposit_and_withdraw(
+            pool_token_per_mille in 1..1_000u128,
+            pool_token_supply in 1_000..u32::MAX,
+            swap_token_amounts in prop::collection::vec(u32::MAX as u64..u64::MAX, 3..=8),
+            amp in 1..150u64,
+        ) {
+            // Every swap token amount is at least the pool token supply, so
+            // each token gets at least one trading token out
+            let pool_token_amount = pool_token_supply as u128 * pool_token_per_mille / 1_000;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_amounts: Vec<u128> =
+                swap_token_amounts.into_iter().map(u128::from).collect();
+            let curve = MultiStableCurve { amp };
+
+            // deposit, the following inequality must hold for every token:
+            // new_token / new_pool_token_supply >= token / pool_token_supply
+            let deposit_amounts = curve
+                .pool_tokens_to_trading_tokens(
+                    pool_token_amount,
+                    pool_token_supply,
+                    &swap_token_amounts,
+                    RoundDirection::Ceiling,
+                )
+                .unwrap();
+            let new_pool_token_supply = U256::from(pool_token_supply + pool_token_amount);
+            for (swap_token_amount, amount) in swap_token_amounts.iter().zip(deposit_amounts.iter()) {
+                let new_swap_token_amount = U256::from(swap_token_amount + amount);
+                assert!(
+                    new_swap_token_amount * U256::from(pool_token_supply)
+                        >= U256::from(*swap_token_amount) * new_pool_token_supply
+                );
+            }
+
+            // withdraw, same inequality
+            let withdraw_amounts = curve
+                .pool_tokens_to_trading_tokens(
+                    pool_token_amount,
+                    pool_token_supply,
+                    &swap_token_amounts,
+                    RoundDirection::Floor,
+                )
+                .unwrap();
+            let new_pool_token_supply = U256::from(pool_token_supply - pool_token_amount);
+            for (swap_token_amount, amount) in swap_token_amounts.iter().zip(withdraw_amounts.iter()) {
+                let new_swap_token_amount = U256::from(swap_token_amount - amount);
+                assert!(
+                    new_swap_token_amount * U256::from(pool_token_supply)
+                        >= U256::from(*swap_token_amount) * new_pool_token_supply
+                );
+            }
+        }
+    }
+}
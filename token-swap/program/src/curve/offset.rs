@@ -8,8 +8,8 @@ use {
                 TradingTokenResult,
             },
             constant_product::{
-                deposit_single_token_type, normalized_value, pool_tokens_to_trading_tokens, swap,
-                withdraw_single_token_type_exact_out,
+                deposit_single_token_type, normalized_value, pool_tokens_to_trading_tokens,
+                spot_price, swap, swap_exact_out, withdraw_single_token_type_exact_out,
             },
         },
         error::SwapError,
@@ -56,6 +56,52 @@ impl CurveCalculator for OffsetCurve {
         swap(source_amount, swap_source_amount, swap_destination_amount)
     }
 
+    /// The inverse of the offset swap, with the same offset applied to the
+    /// token B side
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let token_b_offset = self.token_b_offset as u128;
+        let swap_source_amount = match trade_direction {
+            TradeDirection::AtoB => swap_source_amount,
+            TradeDirection::BtoA => swap_source_amount.checked_add(token_b_offset)?,
+        };
+        let swap_destination_amount = match trade_direction {
+            TradeDirection::AtoB => swap_destination_amount.checked_add(token_b_offset)?,
+            TradeDirection::BtoA => swap_destination_amount,
+        };
+        swap_exact_out(
+            destination_amount,
+            swap_source_amount,
+            swap_destination_amount,
+        )
+    }
+
+    /// The constant product spot price, with the offset applied to the token
+    /// B side
+    fn spot_price(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let token_b_offset = self.token_b_offset as u128;
+        match trade_direction {
+            TradeDirection::AtoB => spot_price(
+                swap_source_amount,
+                swap_destination_amount.checked_add(token_b_offset)?,
+            ),
+            TradeDirection::BtoA => spot_price(
+                swap_source_amount.checked_add(token_b_offset)?,
+                swap_destination_amount,
+            ),
+        }
+    }
+
     /// The conversion for the offset curve needs to take into account the
     /// offset
     fn pool_tokens_to_trading_tokens(
@@ -189,8 +235,8 @@ mod tests {
     use crate::curve::calculator::{
         test::{
             check_curve_value_from_swap, check_deposit_token_conversion,
-            check_pool_value_from_deposit, check_pool_value_from_withdraw,
-            check_withdraw_token_conversion, total_and_intermediate,
+            check_pool_value_from_deposit, check_pool_value_from_withdraw, check_spot_price,
+            check_swap_exact_out, check_withdraw_token_conversion, total_and_intermediate,
             CONVERSION_BASIS_POINTS_GUARANTEE,
         },
         INITIAL_SWAP_POOL_AMOUNT,
@@ -314,6 +360,32 @@ mod tests {
        }
     }
 
+    #[test]
+    fn offset_spot_price() {
+        let token_b_offset = 1_000;
+        let curve = OffsetCurve { token_b_offset };
+        assert_eq!(
+            curve.spot_price(1_000, 1_000, TradeDirection::AtoB),
+            Some(2 << 64)
+        );
+        assert_eq!(
+            curve.spot_price(1_000, 1_000, TradeDirection::BtoA),
+            Some(1 << 63)
+        );
+        // no token B deposited yet, but the offset still gives a price
+        assert_eq!(
+            curve.spot_price(1_000, 0, TradeDirection::AtoB),
+            Some(1 << 64)
+        );
+        assert_eq!(
+            curve.spot_price(0, 1_000, TradeDirection::BtoA),
+            Some(1 << 64)
+        );
+        assert_eq!(curve.spot_price(1_000, 0, TradeDirection::BtoA), None);
+        check_spot_price(&curve, 1_000_000_000, 1_000_000_000, TradeDirection::AtoB);
+        check_spot_price(&curve, 1_000_000_000, 1_000_000_000, TradeDirection::BtoA);
+    }
+
     proptest! {
         #[test]
         fn deposit_token_conversion_a_to_b(
@@ -509,6 +581,56 @@ mod tests {
         }
     }
 
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap_a_to_b(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            token_b_offset in 1..u64::MAX,
+        ) {
+            let curve = OffsetCurve { token_b_offset };
+
+            let swap_source_amount = swap_source_amount as u128;
+            let swap_destination_amount = swap_destination_amount as u128;
+            let token_b_offset = token_b_offset as u128;
+
+            // The invariant needs to fit in a u128
+            prop_assume!(!(swap_destination_amount + token_b_offset).overflowing_mul(swap_source_amount).1);
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap_b_to_a(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            token_b_offset in 1..u64::MAX,
+        ) {
+            let curve = OffsetCurve { token_b_offset };
+
+            let swap_source_amount = swap_source_amount as u128;
+            let swap_destination_amount = swap_destination_amount as u128;
+            let token_b_offset = token_b_offset as u128;
+
+            // The invariant needs to fit in a u128
+            prop_assume!(!(swap_source_amount + token_b_offset).overflowing_mul(swap_destination_amount).1);
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::BtoA
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn curve_value_does_not_decrease_from_deposit(
@@ -3,7 +3,7 @@ use {
     crate::{
         curve::calculator::{
             CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult, TradeDirection,
-            TradingTokenResult,
+            TradingTokenResult, SPOT_PRICE_SHIFT,
         },
         error::SwapError,
     },
@@ -46,29 +46,39 @@ pub struct StableCurve {
 }
 
 /// d = (leverage * sum_x + d_product * n_coins) * initial_d / ((leverage - 1) * initial_d + (n_coins + 1) * d_product)
-fn calculate_step(initial_d: &U256, leverage: u64, sum_x: u128, d_product: &U256) -> Option<U256> {
+fn calculate_step(
+    initial_d: &U256,
+    leverage: u64,
+    sum_x: u128,
+    d_product: &U256,
+    n_coins: u8,
+) -> Option<U256> {
     let leverage_mul = U256::from(leverage).checked_mul(sum_x.into())?;
-    let d_p_mul = checked_u8_mul(d_product, N_COINS)?;
+    let d_p_mul = checked_u8_mul(d_product, n_coins)?;
 
     let l_val = leverage_mul.checked_add(d_p_mul)?.checked_mul(*initial_d)?;
 
     let leverage_sub = initial_d.checked_mul((leverage.checked_sub(1)?).into())?;
-    let n_coins_sum = checked_u8_mul(d_product, N_COINS.checked_add(1)?)?;
+    let n_coins_sum = checked_u8_mul(d_product, n_coins.checked_add(1)?)?;
 
     let r_val = leverage_sub.checked_add(n_coins_sum)?;
 
     l_val.checked_div(r_val)
 }
 
-/// Compute stable swap invariant (D)
+/// Compute stable swap invariant (D) for any number of coins, where
+/// `leverage` is `A * n`
 /// Equation:
 /// A * sum(x_i) * n**n + D = A * D * n**n + D**(n+1) / (n**n * prod(x_i))
-fn compute_d(leverage: u64, amount_a: u128, amount_b: u128) -> Option<u128> {
-    let amount_a_times_coins =
-        checked_u8_mul(&U256::from(amount_a), N_COINS)?.checked_add(U256::one())?;
-    let amount_b_times_coins =
-        checked_u8_mul(&U256::from(amount_b), N_COINS)?.checked_add(U256::one())?;
-    let sum_x = amount_a.checked_add(amount_b)?; // sum(x_i), a.k.a S
+pub(crate) fn compute_d(leverage: u64, amounts: &[u128]) -> Option<u128> {
+    let n_coins = u8::try_from(amounts.len()).ok()?;
+    let amounts_times_coins = amounts
+        .iter()
+        .map(|amount| checked_u8_mul(&U256::from(*amount), n_coins)?.checked_add(U256::one()))
+        .collect::<Option<Vec<U256>>>()?;
+    let sum_x = amounts
+        .iter()
+        .try_fold(0u128, |sum, amount| sum.checked_add(*amount))?; // sum(x_i), a.k.a S
     if sum_x == 0 {
         Some(0)
     } else {
@@ -78,15 +88,12 @@ fn compute_d(leverage: u64, amount_a: u128, amount_b: u128) -> Option<u128> {
         // Newton's method to approximate D
         for _ in 0..ITERATIONS {
             let mut d_product = d;
-            d_product = d_product
-                .checked_mul(d)?
-                .checked_div(amount_a_times_coins)?;
-            d_product = d_product
-                .checked_mul(d)?
-                .checked_div(amount_b_times_coins)?;
+            for amount_times_coins in amounts_times_coins.iter() {
+                d_product = d_product.checked_mul(d)?.checked_div(*amount_times_coins)?;
+            }
             d_previous = d;
             //d = (leverage * sum_x + d_p * n_coins) * d / ((leverage - 1) * d + (n_coins + 1) * d_p);
-            d = calculate_step(&d, leverage, sum_x, &d_product)?;
+            d = calculate_step(&d, leverage, sum_x, &d_product, n_coins)?;
             // Equality with the precision of 1
             if d == d_previous {
                 break;
@@ -147,7 +154,7 @@ impl CurveCalculator for StableCurve {
         let new_destination_amount = compute_new_destination_amount(
             leverage,
             new_source_amount,
-            compute_d(leverage, swap_source_amount, swap_destination_amount)?,
+            compute_d(leverage, &[swap_source_amount, swap_destination_amount])?,
         )?;
 
         let amount_swapped = swap_destination_amount.checked_sub(new_destination_amount)?;
@@ -158,6 +165,90 @@ impl CurveCalculator for StableCurve {
         })
     }
 
+    /// Stable curve, solving the invariant for the source side.  Since the
+    /// invariant is only approximated, the source amount is bumped until the
+    /// regular swap gives at least the destination amount.
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        _trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        if destination_amount == 0 {
+            return None;
+        }
+        let leverage = self.amp.checked_mul(N_COINS as u64)?;
+        let d_val = compute_d(leverage, &[swap_source_amount, swap_destination_amount])?;
+
+        let new_destination_amount = swap_destination_amount.checked_sub(destination_amount)?;
+        if new_destination_amount == 0 {
+            return None;
+        }
+        let new_source_amount =
+            compute_new_destination_amount(leverage, new_destination_amount, d_val)?;
+        let mut source_amount = new_source_amount.saturating_sub(swap_source_amount).max(1);
+
+        for _ in 0..ITERATIONS {
+            let new_destination_amount = compute_new_destination_amount(
+                leverage,
+                swap_source_amount.checked_add(source_amount)?,
+                d_val,
+            )?;
+            if swap_destination_amount.saturating_sub(new_destination_amount) >= destination_amount
+            {
+                return Some(SwapWithoutFeesResult {
+                    source_amount_swapped: source_amount,
+                    destination_amount_swapped: destination_amount,
+                });
+            }
+            source_amount = source_amount.checked_add(1)?;
+        }
+        None
+    }
+
+    /// Marginal price of the stable invariant, the ratio of its partial
+    /// derivatives, with `D ** (n + 1) / (n ** n * prod(x_i))` replaced by
+    /// the other side of the invariant:
+    ///
+    /// price = y * (A * (2x + y) + D - A * D) / (x * (A * (x + 2y) + D - A * D))
+    ///
+    /// where `A` is the leverage, x the source and y the destination amount.
+    fn spot_price(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        _trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        if swap_source_amount == 0 || swap_destination_amount == 0 {
+            return None;
+        }
+        let leverage = self.amp.checked_mul(N_COINS as u64)?;
+        let d_val = U256::from(compute_d(
+            leverage,
+            &[swap_source_amount, swap_destination_amount],
+        )?);
+        let leverage = U256::from(leverage);
+        let x = U256::from(swap_source_amount);
+        let y = U256::from(swap_destination_amount);
+        let leverage_d = leverage.checked_mul(d_val)?;
+        let numerator = leverage
+            .checked_mul(checked_u8_mul(&x, 2)?.checked_add(y)?)?
+            .checked_add(d_val)?
+            .checked_sub(leverage_d)?;
+        let denominator = leverage
+            .checked_mul(x.checked_add(checked_u8_mul(&y, 2)?)?)?
+            .checked_add(d_val)?
+            .checked_sub(leverage_d)?;
+        // divide in two steps to stay within U256
+        let spot_price = numerator
+            .checked_mul(U256::one() << SPOT_PRICE_SHIFT)?
+            .checked_div(denominator)?
+            .checked_mul(y)?
+            .checked_div(x)?;
+        u128::try_from(spot_price).ok()
+    }
+
     /// Re-implementation of `remove_liquidty`:
     ///
     /// <https://github.com/curvefi/curve-contract/blob/80bbe179083c9a7062e4c482b0be3bfb7501f2bd/contracts/pool-templates/base/SwapTemplateBase.vy#L513>
@@ -218,8 +309,7 @@ impl CurveCalculator for StableCurve {
         let leverage = self.amp.checked_mul(N_COINS as u64)?;
         let d0 = PreciseNumber::new(compute_d(
             leverage,
-            swap_token_a_amount,
-            swap_token_b_amount,
+            &[swap_token_a_amount, swap_token_b_amount],
         )?)?;
         let (deposit_token_amount, other_token_amount) = match trade_direction {
             TradeDirection::AtoB => (swap_token_a_amount, swap_token_b_amount),
@@ -228,8 +318,7 @@ impl CurveCalculator for StableCurve {
         let updated_deposit_token_amount = deposit_token_amount.checked_add(source_amount)?;
         let d1 = PreciseNumber::new(compute_d(
             leverage,
-            updated_deposit_token_amount,
-            other_token_amount,
+            &[updated_deposit_token_amount, other_token_amount],
         )?)?;
         let diff = d1.checked_sub(&d0)?;
         let final_amount =
@@ -251,8 +340,7 @@ impl CurveCalculator for StableCurve {
         let leverage = self.amp.checked_mul(N_COINS as u64)?;
         let d0 = PreciseNumber::new(compute_d(
             leverage,
-            swap_token_a_amount,
-            swap_token_b_amount,
+            &[swap_token_a_amount, swap_token_b_amount],
         )?)?;
         let (withdraw_token_amount, other_token_amount) = match trade_direction {
             TradeDirection::AtoB => (swap_token_a_amount, swap_token_b_amount),
@@ -261,8 +349,7 @@ impl CurveCalculator for StableCurve {
         let updated_deposit_token_amount = withdraw_token_amount.checked_sub(source_amount)?;
         let d1 = PreciseNumber::new(compute_d(
             leverage,
-            updated_deposit_token_amount,
-            other_token_amount,
+            &[updated_deposit_token_amount, other_token_amount],
         )?)?;
         let diff = d0.checked_sub(&d1)?;
         let final_amount =
@@ -280,8 +367,7 @@ impl CurveCalculator for StableCurve {
             let leverage = self.amp.checked_mul(N_COINS as u64)?;
             PreciseNumber::new(compute_d(
                 leverage,
-                swap_token_a_amount,
-                swap_token_b_amount,
+                &[swap_token_a_amount, swap_token_b_amount],
             )?)
         }
         #[cfg(any(test, feature = "fuzz"))]
@@ -349,8 +435,8 @@ mod tests {
     use crate::curve::calculator::{
         test::{
             check_curve_value_from_swap, check_deposit_token_conversion,
-            check_pool_value_from_deposit, check_pool_value_from_withdraw,
-            check_withdraw_token_conversion, total_and_intermediate,
+            check_pool_value_from_deposit, check_pool_value_from_withdraw, check_spot_price,
+            check_swap_exact_out, check_withdraw_token_conversion, total_and_intermediate,
             CONVERSION_BASIS_POINTS_GUARANTEE,
         },
         RoundDirection, INITIAL_SWAP_POOL_AMOUNT,
@@ -536,6 +622,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn spot_price() {
+        let curve = StableCurve { amp: 100 };
+        // balanced pools trade at par
+        assert_eq!(
+            curve.spot_price(1_000_000, 1_000_000, TradeDirection::AtoB),
+            Some(1 << 64)
+        );
+        assert_eq!(curve.spot_price(0, 1_000_000, TradeDirection::AtoB), None);
+        assert_eq!(curve.spot_price(1_000_000, 0, TradeDirection::AtoB), None);
+
+        // the price moves much less than the balance ratio
+        let spot_price = curve
+            .spot_price(2_000_000, 1_000_000, TradeDirection::AtoB)
+            .unwrap();
+        assert!(spot_price < 1 << 64);
+        assert!(spot_price > 99 << 57);
+    }
+
+    proptest! {
+        #[test]
+        fn spot_price_matches_small_swap(
+            swap_source_amount in 1_000_000_000..1_000_000_000_000u64,
+            swap_destination_amount in 1_000_000_000..1_000_000_000_000u64,
+            amp in 1..100,
+        ) {
+            let curve = StableCurve { amp: amp as u64 };
+            check_spot_price(
+                &curve,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap(
+            swap_source_amount in 1..u64::MAX,
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            amp in 1..100,
+        ) {
+            let curve = StableCurve { amp: amp as u64 };
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount as u128,
+                swap_source_amount as u128,
+                swap_destination_amount as u128,
+                TradeDirection::AtoB
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn deposit_token_conversion(
@@ -0,0 +1,637 @@
+//! The curve.fi invariant calculator between SOL and the pool tokens of a
+//! stake pool, with the 1:1 zone following the stake pool's exchange rate
+//!
+//! Token A is SOL, in lamports, and token B is the stake pool's pool token.
+//! Amounts of token B are converted into their value in lamports at the
+//! exchange rate of the stake pool before running the stable invariant, so
+//! that the curve stays flat around the stake pool's price instead of a
+//! fixed 1:1 peg.
+
+use {
+    crate::{
+        curve::{
+            base::{CurveType, SwapCurve},
+            calculator::{
+                CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult, TradeDirection,
+                TradingTokenResult,
+            },
+            stable::StableCurve,
+        },
+        error::SwapError,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+    },
+    spl_math::precise_number::PreciseNumber,
+};
+
+/// Returns `amount * numerator / denominator`, rounded in the given direction
+fn checked_convert(
+    amount: u128,
+    numerator: u64,
+    denominator: u64,
+    round_direction: RoundDirection,
+) -> Option<u128> {
+    let product = amount.checked_mul(numerator as u128)?;
+    let quotient = product.checked_div(denominator as u128)?;
+    match round_direction {
+        RoundDirection::Floor => Some(quotient),
+        RoundDirection::Ceiling => {
+            if product.checked_rem(denominator as u128)? > 0 {
+                quotient.checked_add(1)
+            } else {
+                Some(quotient)
+            }
+        }
+    }
+}
+
+/// StakePoolStableCurve struct implementing CurveCalculator
+///
+/// The exchange rate is curve state, refreshed by the processor from the
+/// stake pool account before every swap.  It is only as accurate as the
+/// stake pool, which updates its total stake once per epoch.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StakePoolStableCurve {
+    /// Amplifier constant of the stable invariant
+    pub amp: u64,
+    /// Total lamports under the stake pool's management as of the last refresh
+    pub total_stake_lamports: u64,
+    /// Pool token supply of the stake pool as of the last refresh
+    pub pool_token_supply: u64,
+}
+
+impl StakePoolStableCurve {
+    /// Copy the calculator out of a swap curve of the stake pool stable type
+    pub fn from_swap_curve(swap_curve: &SwapCurve) -> Result<Self, ProgramError> {
+        if swap_curve.curve_type != CurveType::StakePoolStable {
+            return Err(SwapError::UnsupportedCurveOperation.into());
+        }
+        let mut packed = [0u8; Self::LEN];
+        swap_curve.calculator.pack_into_slice(&mut packed);
+        Self::unpack_from_slice(&packed)
+    }
+
+    /// Update the exchange rate from the stake pool's totals
+    pub fn refresh(&mut self, total_stake_lamports: u64, pool_token_supply: u64) {
+        self.total_stake_lamports = total_stake_lamports;
+        self.pool_token_supply = pool_token_supply;
+    }
+
+    /// Value of an amount of token B in lamports
+    fn token_b_value(&self, amount: u128, round_direction: RoundDirection) -> Option<u128> {
+        checked_convert(
+            amount,
+            self.total_stake_lamports,
+            self.pool_token_supply,
+            round_direction,
+        )
+    }
+
+    /// Amount of token B worth a value in lamports
+    fn token_b_amount(&self, value: u128, round_direction: RoundDirection) -> Option<u128> {
+        checked_convert(
+            value,
+            self.pool_token_supply,
+            self.total_stake_lamports,
+            round_direction,
+        )
+    }
+
+    fn stable_curve(&self) -> StableCurve {
+        StableCurve { amp: self.amp }
+    }
+}
+
+impl CurveCalculator for StakePoolStableCurve {
+    /// Stable curve on the value of the balances, rounding the value of
+    /// token B down on both sides of the trade
+    fn swap_without_fees(
+        &self,
+        source_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let stable_curve = self.stable_curve();
+        match trade_direction {
+            TradeDirection::AtoB => {
+                let result = stable_curve.swap_without_fees(
+                    source_amount,
+                    swap_source_amount,
+                    self.token_b_value(swap_destination_amount, RoundDirection::Floor)?,
+                    trade_direction,
+                )?;
+                Some(SwapWithoutFeesResult {
+                    source_amount_swapped: source_amount,
+                    destination_amount_swapped: self
+                        .token_b_amount(result.destination_amount_swapped, RoundDirection::Floor)?,
+                })
+            }
+            TradeDirection::BtoA => {
+                let result = stable_curve.swap_without_fees(
+                    self.token_b_value(source_amount, RoundDirection::Floor)?,
+                    self.token_b_value(swap_source_amount, RoundDirection::Floor)?,
+                    swap_destination_amount,
+                    trade_direction,
+                )?;
+                Some(SwapWithoutFeesResult {
+                    source_amount_swapped: source_amount,
+                    destination_amount_swapped: result.destination_amount_swapped,
+                })
+            }
+        }
+    }
+
+    /// Stable curve on the value of the balances, rounding the value owed
+    /// to the pool up
+    fn swap_without_fees_exact_out(
+        &self,
+        destination_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let stable_curve = self.stable_curve();
+        let source_amount = match trade_direction {
+            TradeDirection::AtoB => {
+                stable_curve
+                    .swap_without_fees_exact_out(
+                        self.token_b_value(destination_amount, RoundDirection::Ceiling)?,
+                        swap_source_amount,
+                        self.token_b_value(swap_destination_amount, RoundDirection::Floor)?,
+                        trade_direction,
+                    )?
+                    .source_amount_swapped
+            }
+            TradeDirection::BtoA => {
+                let result = stable_curve.swap_without_fees_exact_out(
+                    destination_amount,
+                    self.token_b_value(swap_source_amount, RoundDirection::Floor)?,
+                    swap_destination_amount,
+                    trade_direction,
+                )?;
+                self.token_b_amount(result.source_amount_swapped, RoundDirection::Ceiling)?
+            }
+        };
+        Some(SwapWithoutFeesResult {
+            source_amount_swapped: source_amount,
+            destination_amount_swapped: destination_amount,
+        })
+    }
+
+    /// Spot price of the stable curve on the value of the balances,
+    /// converted back into token B at the exchange rate
+    fn spot_price(
+        &self,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let stable_curve = self.stable_curve();
+        match trade_direction {
+            TradeDirection::AtoB => {
+                let spot_price = stable_curve.spot_price(
+                    swap_source_amount,
+                    self.token_b_value(swap_destination_amount, RoundDirection::Floor)?,
+                    trade_direction,
+                )?;
+                self.token_b_amount(spot_price, RoundDirection::Floor)
+            }
+            TradeDirection::BtoA => {
+                let spot_price = stable_curve.spot_price(
+                    self.token_b_value(swap_source_amount, RoundDirection::Floor)?,
+                    swap_destination_amount,
+                    trade_direction,
+                )?;
+                self.token_b_value(spot_price, RoundDirection::Floor)
+            }
+        }
+    }
+
+    fn pool_tokens_to_trading_tokens(
+        &self,
+        pool_tokens: u128,
+        pool_token_supply: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        round_direction: RoundDirection,
+    ) -> Option<TradingTokenResult> {
+        self.stable_curve().pool_tokens_to_trading_tokens(
+            pool_tokens,
+            pool_token_supply,
+            swap_token_a_amount,
+            swap_token_b_amount,
+            round_direction,
+        )
+    }
+
+    fn deposit_single_token_type(
+        &self,
+        source_amount: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        pool_supply: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let source_value = match trade_direction {
+            TradeDirection::AtoB => source_amount,
+            TradeDirection::BtoA => self.token_b_value(source_amount, RoundDirection::Floor)?,
+        };
+        self.stable_curve().deposit_single_token_type(
+            source_value,
+            swap_token_a_amount,
+            self.token_b_value(swap_token_b_amount, RoundDirection::Floor)?,
+            pool_supply,
+            trade_direction,
+        )
+    }
+
+    fn withdraw_single_token_type_exact_out(
+        &self,
+        source_amount: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        pool_supply: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let source_value = match trade_direction {
+            TradeDirection::AtoB => source_amount,
+            TradeDirection::BtoA => self.token_b_value(source_amount, RoundDirection::Ceiling)?,
+        };
+        self.stable_curve().withdraw_single_token_type_exact_out(
+            source_value,
+            swap_token_a_amount,
+            self.token_b_value(swap_token_b_amount, RoundDirection::Floor)?,
+            pool_supply,
+            trade_direction,
+        )
+    }
+
+    /// The normalized value of the stable invariant on the value of the
+    /// balances, in lamports
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+    ) -> Option<PreciseNumber> {
+        self.stable_curve().normalized_value(
+            swap_token_a_amount,
+            self.token_b_value(swap_token_b_amount, RoundDirection::Floor)?,
+        )
+    }
+
+    fn validate(&self) -> Result<(), SwapError> {
+        if self.total_stake_lamports == 0 || self.pool_token_supply == 0 {
+            Err(SwapError::InvalidCurve)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
+impl IsInitialized for StakePoolStableCurve {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+impl Sealed for StakePoolStableCurve {}
+impl Pack for StakePoolStableCurve {
+    const LEN: usize = 24;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        (self as &dyn DynPack).pack_into_slice(output);
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<StakePoolStableCurve, ProgramError> {
+        let input = array_ref![input, 0, 24];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (amp, total_stake_lamports, pool_token_supply) = array_refs![input, 8, 8, 8];
+        Ok(Self {
+            amp: u64::from_le_bytes(*amp),
+            total_stake_lamports: u64::from_le_bytes(*total_stake_lamports),
+            pool_token_supply: u64::from_le_bytes(*pool_token_supply),
+        })
+    }
+}
+
+impl DynPack for StakePoolStableCurve {
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 24];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (amp, total_stake_lamports, pool_token_supply) = mut_array_refs![output, 8, 8, 8];
+        *amp = self.amp.to_le_bytes();
+        *total_stake_lamports = self.total_stake_lamports.to_le_bytes();
+        *pool_token_supply = self.pool_token_supply.to_le_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::calculator::{
+        test::{
+            check_curve_value_from_swap, check_pool_value_from_deposit,
+            check_pool_value_from_withdraw, check_spot_price, check_swap_exact_out,
+            total_and_intermediate,
+        },
+        INITIAL_SWAP_POOL_AMOUNT,
+    };
+    use proptest::prelude::*;
+
+    // a stake pool worth 1.1 SOL per pool token
+    fn test_curve(amp: u64) -> StakePoolStableCurve {
+        StakePoolStableCurve {
+            amp,
+            total_stake_lamports: 1_100_000_000_000,
+            pool_token_supply: 1_000_000_000_000,
+        }
+    }
+
+    #[test]
+    fn initial_pool_amount() {
+        let calculator = test_curve(100);
+        assert_eq!(calculator.new_pool_supply(), INITIAL_SWAP_POOL_AMOUNT);
+    }
+
+    #[test]
+    fn pack_curve() {
+        let amp = 100;
+        let total_stake_lamports = u64::MAX - 1;
+        let pool_token_supply = u64::MAX;
+        let curve = StakePoolStableCurve {
+            amp,
+            total_stake_lamports,
+            pool_token_supply,
+        };
+
+        let mut packed = [0u8; StakePoolStableCurve::LEN];
+        Pack::pack_into_slice(&curve, &mut packed[..]);
+        let unpacked = StakePoolStableCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+
+        let mut packed = vec![];
+        packed.extend_from_slice(&amp.to_le_bytes());
+        packed.extend_from_slice(&total_stake_lamports.to_le_bytes());
+        packed.extend_from_slice(&pool_token_supply.to_le_bytes());
+        let unpacked = StakePoolStableCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::StakePoolStable,
+            calculator: Box::new(curve.clone()),
+        };
+        assert_eq!(
+            StakePoolStableCurve::from_swap_curve(&swap_curve).unwrap(),
+            curve
+        );
+        assert_eq!(
+            StakePoolStableCurve::from_swap_curve(&SwapCurve::default()),
+            Err(SwapError::UnsupportedCurveOperation.into())
+        );
+    }
+
+    #[test]
+    fn validate_exchange_rate() {
+        assert_eq!(test_curve(100).validate(), Ok(()));
+        for (total_stake_lamports, pool_token_supply) in [(0, 1), (1, 0), (0, 0)].iter() {
+            let curve = StakePoolStableCurve {
+                amp: 100,
+                total_stake_lamports: *total_stake_lamports,
+                pool_token_supply: *pool_token_supply,
+            };
+            assert_eq!(curve.validate(), Err(SwapError::InvalidCurve));
+        }
+    }
+
+    #[test]
+    fn swap_at_exchange_rate() {
+        let curve = test_curve(100);
+        // balanced in value: 1.1M lamports against 1M pool tokens
+        let swap_token_a_amount = 1_100_000_000;
+        let swap_token_b_amount = 1_000_000_000;
+
+        // small trades get the exchange rate
+        let result = curve
+            .swap_without_fees(
+                1_100,
+                swap_token_a_amount,
+                swap_token_b_amount,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        assert_eq!(result.destination_amount_swapped, 1_000);
+        let result = curve
+            .swap_without_fees(
+                1_000,
+                swap_token_b_amount,
+                swap_token_a_amount,
+                TradeDirection::BtoA,
+            )
+            .unwrap();
+        assert_eq!(result.destination_amount_swapped, 1_100);
+
+        // a fixed 1:1 peg would have given close to 1 SOL per pool token
+        let stable_curve = StableCurve { amp: 100 };
+        let result = stable_curve
+            .swap_without_fees(
+                1_000,
+                swap_token_b_amount,
+                swap_token_a_amount,
+                TradeDirection::BtoA,
+            )
+            .unwrap();
+        assert!(result.destination_amount_swapped < 1_010);
+    }
+
+    #[test]
+    fn spot_price_at_exchange_rate() {
+        let curve = test_curve(100);
+        // balanced in value: 1.1M lamports against 1M pool tokens
+        let swap_token_a_amount = 1_100_000_000;
+        let swap_token_b_amount = 1_000_000_000;
+
+        assert_eq!(
+            curve.spot_price(
+                swap_token_a_amount,
+                swap_token_b_amount,
+                TradeDirection::AtoB
+            ),
+            Some((1 << 64) * 10 / 11)
+        );
+        assert_eq!(
+            curve.spot_price(
+                swap_token_b_amount,
+                swap_token_a_amount,
+                TradeDirection::BtoA
+            ),
+            Some((1 << 64) * 11 / 10)
+        );
+        check_spot_price(
+            &curve,
+            swap_token_a_amount,
+            2 * swap_token_b_amount,
+            TradeDirection::AtoB,
+        );
+        check_spot_price(
+            &curve,
+            2 * swap_token_b_amount,
+            swap_token_a_amount,
+            TradeDirection::BtoA,
+        );
+    }
+
+    #[test]
+    fn refresh_exchange_rate() {
+        let mut curve = test_curve(100);
+        curve.refresh(1_200_000_000_000, 1_000_000_000_000);
+        assert_eq!(curve.total_stake_lamports, 1_200_000_000_000);
+        assert_eq!(curve.pool_token_supply, 1_000_000_000_000);
+
+        // balanced in value at the new rate
+        let result = curve
+            .swap_without_fees(1_000, 1_000_000_000, 1_200_000_000, TradeDirection::BtoA)
+            .unwrap();
+        assert_eq!(result.destination_amount_swapped, 1_200);
+    }
+
+    prop_compose! {
+        // exchange rates between 1 and 2 SOL per pool token
+        fn exchange_rate()(pool_token_supply in 1_000_000_000..u32::MAX as u64)
+                        (total_stake_lamports in pool_token_supply..2 * pool_token_supply,
+                         pool_token_supply in Just(pool_token_supply))
+                        -> (u64, u64) {
+           (total_stake_lamports, pool_token_supply)
+       }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_deposit(
+            pool_token_amount in 1..u64::MAX,
+            pool_token_supply in 1..u64::MAX,
+            swap_token_a_amount in 1..u64::MAX,
+            swap_token_b_amount in 1..u64::MAX / 2,
+            amp in 1..100u64,
+            (total_stake_lamports, stake_pool_token_supply) in exchange_rate(),
+        ) {
+            let pool_token_amount = pool_token_amount as u128;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_a_amount = swap_token_a_amount as u128;
+            let swap_token_b_amount = swap_token_b_amount as u128;
+            // Make sure we will get at least one trading token out for each
+            // side, otherwise the calculation fails
+            prop_assume!(pool_token_amount * swap_token_a_amount / pool_token_supply >= 1);
+            prop_assume!(pool_token_amount * swap_token_b_amount / pool_token_supply >= 1);
+            let curve = StakePoolStableCurve {
+                amp,
+                total_stake_lamports,
+                pool_token_supply: stake_pool_token_supply,
+            };
+            check_pool_value_from_deposit(
+                &curve,
+                pool_token_amount,
+                pool_token_supply,
+                swap_token_a_amount,
+                swap_token_b_amount,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_withdraw(
+            (pool_token_supply, pool_token_amount) in total_and_intermediate(),
+            swap_token_a_amount in 1..u64::MAX,
+            swap_token_b_amount in 1..u64::MAX / 2,
+            amp in 1..100u64,
+            (total_stake_lamports, stake_pool_token_supply) in exchange_rate(),
+        ) {
+            let pool_token_amount = pool_token_amount as u128;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_a_amount = swap_token_a_amount as u128;
+            let swap_token_b_amount = swap_token_b_amount as u128;
+            // Make sure we will get at least one trading token out for each
+            // side, otherwise the calculation fails
+            prop_assume!(pool_token_amount * swap_token_a_amount / pool_token_supply >= 1);
+            prop_assume!(pool_token_amount * swap_token_b_amount / pool_token_supply >= 1);
+            let curve = StakePoolStableCurve {
+                amp,
+                total_stake_lamports,
+                pool_token_supply: stake_pool_token_supply,
+            };
+            check_pool_value_from_withdraw(
+                &curve,
+                pool_token_amount,
+                pool_token_supply,
+                swap_token_a_amount,
+                swap_token_b_amount,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_swap(
+            source_token_amount in 1..u32::MAX as u128,
+            swap_token_a_amount in 1_000_000..u64::MAX as u128 / 4,
+            swap_token_b_amount in 1_000_000..u64::MAX as u128 / 4,
+            amp in 1..100u64,
+            (total_stake_lamports, pool_token_supply) in exchange_rate(),
+        ) {
+            let curve = StakePoolStableCurve {
+                amp,
+                total_stake_lamports,
+                pool_token_supply,
+            };
+            check_curve_value_from_swap(
+                &curve,
+                source_token_amount,
+                swap_token_a_amount,
+                swap_token_b_amount,
+                TradeDirection::AtoB
+            );
+            check_curve_value_from_swap(
+                &curve,
+                source_token_amount,
+                swap_token_b_amount,
+                swap_token_a_amount,
+                TradeDirection::BtoA
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_exact_out_matches_swap(
+            swap_source_amount in 1_000_000..u64::MAX as u128 / 4,
+            swap_destination_amount in 1_000_000..u64::MAX as u128 / 4,
+            destination_token_amount in 1..u32::MAX as u128,
+            amp in 1..100u64,
+            (total_stake_lamports, pool_token_supply) in exchange_rate(),
+        ) {
+            prop_assume!(destination_token_amount < swap_destination_amount / 2);
+            let curve = StakePoolStableCurve {
+                amp,
+                total_stake_lamports,
+                pool_token_supply,
+            };
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB
+            );
+            check_swap_exact_out(
+                &curve,
+                destination_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::BtoA
+            );
+        }
+    }
+}
@@ -102,6 +102,33 @@ pub enum SwapError {
     /// The operation cannot be performed on the given curve
     #[error("The operation cannot be performed on the given curve")]
     UnsupportedCurveOperation,
+    /// The route is empty, has too many pools, or is missing pool accounts
+    #[error("The route is empty, has too many pools, or is missing pool accounts")]
+    InvalidRoute,
+    /// The pause authority is missing, incorrect, or did not sign
+    #[error("The pause authority is missing, incorrect, or did not sign")]
+    InvalidPauseAuthority,
+
+    // 30.
+    /// The swap is paused, so swaps and deposits are not allowed
+    #[error("The swap is paused, so swaps and deposits are not allowed")]
+    SwapPaused,
+    /// The swap is not paused
+    #[error("The swap is not paused")]
+    SwapNotPaused,
+    /// The number of tokens does not match the swap, or is outside of the
+    /// range supported by multi-token swaps
+    #[error("The number of tokens does not match the swap or is not supported")]
+    InvalidTokenCount,
+    /// The oracle account is missing or does not match the swap
+    #[error("The oracle account is missing or does not match the swap")]
+    IncorrectOracleAccount,
+    /// The price range of the curve is not allowed by the program owner
+    #[error("The price range of the curve is not allowed by the program owner")]
+    UnsupportedPriceRange,
+    /// The price given by the oracle is too old to be used
+    #[error("The price given by the oracle is too old to be used")]
+    StaleOraclePrice,
 }
 impl From<SwapError> for ProgramError {
     fn from(e: SwapError) -> Self {
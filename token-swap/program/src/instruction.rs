@@ -2,11 +2,17 @@
 
 #![allow(clippy::too_many_arguments)]
 
-use crate::curve::{base::SwapCurve, fees::Fees};
+use crate::curve::{
+    base::SwapCurve,
+    fees::{DynamicFees, Fees},
+    multi_stable::MultiStableCurve,
+};
 use crate::error::SwapError;
+use crate::state::{MAX_MULTI_TOKENS, MIN_MULTI_TOKENS};
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
+    program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
 };
@@ -27,6 +33,10 @@ pub struct Initialize {
     /// swap curve info for pool, including CurveType and anything
     /// else that may be required
     pub swap_curve: SwapCurve,
+    /// authority allowed to pause and unpause the swap, if any
+    pub pause_authority: COption<Pubkey>,
+    /// trade fee rising with volatility, only packed if enabled
+    pub dynamic_fees: DynamicFees,
 }
 
 /// Swap instruction data
@@ -92,6 +102,90 @@ pub struct WithdrawSingleTokenTypeExactAmountOut {
     pub maximum_pool_token_amount: u64,
 }
 
+/// RouteSwap instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteSwap {
+    /// SOURCE amount to transfer into the first pool of the route
+    pub amount_in: u64,
+    /// Minimum amount of DESTINATION token to output from the last pool of the route,
+    /// prevents excessive slippage
+    pub minimum_amount_out: u64,
+}
+
+/// SwapExactAmountOut instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapExactAmountOut {
+    /// DESTINATION amount to receive, input from SOURCE is based on the exchange rate
+    pub amount_out: u64,
+    /// Maximum amount of SOURCE token to input, prevents excessive slippage
+    pub maximum_amount_in: u64,
+}
+
+/// InitializeMultiToken instruction data
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct InitializeMultiToken {
+    /// nonce used to create valid program address
+    pub nonce: u8,
+    /// all swap fees
+    pub fees: Fees,
+    /// stable curve used between all tokens of the pool
+    pub swap_curve: MultiStableCurve,
+    /// authority allowed to pause and unpause the swap, if any
+    pub pause_authority: COption<Pubkey>,
+}
+
+/// SwapMultiToken instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapMultiToken {
+    /// Index of the SOURCE token in the swap's tokens
+    pub source_index: u8,
+    /// Index of the DESTINATION token in the swap's tokens
+    pub destination_index: u8,
+    /// SOURCE amount to transfer, output to DESTINATION is based on the exchange rate
+    pub amount_in: u64,
+    /// Minimum amount of DESTINATION token to output, prevents excessive slippage
+    pub minimum_amount_out: u64,
+}
+
+/// DepositAllMultiToken instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepositAllMultiToken {
+    /// Pool token amount to transfer. Token amounts are set by the current
+    /// exchange rate and size of the pool
+    pub pool_token_amount: u64,
+    /// Maximum amount of each token to deposit, in the order of the swap's
+    /// tokens, prevents excessive slippage
+    pub maximum_token_amounts: Vec<u64>,
+}
+
+/// WithdrawAllMultiToken instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct WithdrawAllMultiToken {
+    /// Amount of pool tokens to burn. User receives an output of each token
+    /// based on the percentage of the pool tokens that are returned.
+    pub pool_token_amount: u64,
+    /// Minimum amount of each token to receive, in the order of the swap's
+    /// tokens, prevents excessive slippage
+    pub minimum_token_amounts: Vec<u64>,
+}
+
+/// Maximum number of pools a RouteSwap may go through
+pub const MAX_ROUTE_SWAP_HOPS: usize = 4;
+
+/// Number of accounts given for each pool of a RouteSwap
+pub const ROUTE_SWAP_HOP_ACCOUNTS_LEN: usize = 6;
+
 /// Instructions supported by the token swap program.
 #[repr(C)]
 #[derive(Debug, PartialEq)]
@@ -108,11 +202,19 @@ pub enum SwapInstruction {
     ///   6. `[writable]` Pool Token Account to deposit the initial pool token
     ///   supply.  Must be empty, not owned by swap authority.
     ///   7. '[]` Token program id
+    ///   8. `[]` Oracle account, only for curves priced by an oracle.  For stake
+    ///   pool stable curves, the stake pool of token B.  Its owner is recorded,
+    ///   and swaps fail if it is later owned by another program.
+    ///
+    ///   The pause authority, able to pause and unpause the swap, is given in
+    ///   the instruction data rather than as an account.  Without one, the
+    ///   swap can never be paused.  Dynamic fees are only present in the
+    ///   instruction data when enabled.
     Initialize(Initialize),
 
     ///   Swap the tokens in the pool.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
@@ -122,14 +224,17 @@ pub enum SwapInstruction {
     ///   7. `[writable]` Pool token mint, to generate trading fees
     ///   8. `[writable]` Fee account, to receive trading fees
     ///   9. '[]` Token program id
-    ///   10 `[optional, writable]` Host fee account to receive additional trading fees
+    ///   10 `[]` Oracle account, only for pools priced by an oracle.  For stake
+    ///   pool stable curves, the stake pool of token B.  For other curves, the
+    ///   price account, rejected if published more than `MAX_PRICE_AGE` slots ago.
+    ///   10 or 11 `[optional, writable]` Host fee account to receive additional trading fees
     Swap(Swap),
 
     ///   Deposit both types of tokens into the pool.  The output is a "pool"
     ///   token representing ownership in the pool. Inputs are converted to
     ///   the current ratio.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_a user transfer authority can transfer amount,
@@ -145,7 +250,7 @@ pub enum SwapInstruction {
     ///   pool tokens.  The pool tokens are burned in exchange for an equivalent
     ///   amount of token A and B.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` Pool mint account, swap authority is the owner
@@ -162,7 +267,7 @@ pub enum SwapInstruction {
     ///   representing ownership into the pool. Input token is converted as if
     ///   a swap and deposit all token types were performed.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
@@ -176,7 +281,7 @@ pub enum SwapInstruction {
     ///   Withdraw one token type from the pool at the current ratio given the
     ///   exact amount out expected.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` Pool mint account, swap authority is the owner
@@ -187,6 +292,133 @@ pub enum SwapInstruction {
     ///   8. `[writable]` Fee account, to receive withdrawal fees
     ///   9. '[]` Token program id
     WithdrawSingleTokenTypeExactAmountOut(WithdrawSingleTokenTypeExactAmountOut),
+
+    ///   Swap tokens through an ordered list of pools, such as A to B in a
+    ///   first pool and B to C in a second, as a single operation.  The output
+    ///   of each pool is transferred directly into the source account of the
+    ///   next pool, and only the output of the last pool is checked against
+    ///   `minimum_amount_out`.  Any input a pool's curve cannot use, such as a
+    ///   remainder lost to rounding, stays in that pool.  Pools priced by an
+    ///   oracle can't be part of a route.
+    ///
+    ///   0. `[]` user transfer authority
+    ///   1. `[writable]` token SOURCE Account, amount is transferable by user transfer authority,
+    ///   2. `[writable]` token DESTINATION Account assigned to USER as the owner.
+    ///   3. '[]` Token program id
+    ///
+    ///   Then, for each of the 1 to `MAX_ROUTE_SWAP_HOPS` pools of the route, in order:
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token
+    ///   for the first pool, or the DESTINATION token of the previous pool.
+    ///   3. `[writable]` token_(A|B) Base Account to swap FROM.
+    ///   4. `[writable]` Pool token mint, to generate trading fees
+    ///   5. `[writable]` Fee account, to receive trading fees
+    RouteSwap(RouteSwap),
+
+    ///   Swap the tokens in the pool, receiving an exact amount of the
+    ///   DESTINATION token.  The SOURCE amount, fees included, is calculated
+    ///   from the exchange rate and rounded up.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
+    ///   4. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token.
+    ///   5. `[writable]` token_(A|B) Base Account to swap FROM.  Must be the DESTINATION token.
+    ///   6. `[writable]` token_(A|B) DESTINATION Account assigned to USER as the owner.
+    ///   7. `[writable]` Pool token mint, to generate trading fees
+    ///   8. `[writable]` Fee account, to receive trading fees
+    ///   9. '[]` Token program id
+    ///   10 `[]` Oracle account, only for pools priced by an oracle.  For stake
+    ///   pool stable curves, the stake pool of token B.  For other curves, the
+    ///   price account, rejected if published more than `MAX_PRICE_AGE` slots ago.
+    ///   10 or 11 `[optional, writable]` Host fee account to receive additional trading fees
+    SwapExactAmountOut(SwapExactAmountOut),
+
+    ///   Pause the swap.  While paused, swaps and deposits fail, but
+    ///   withdrawals are still allowed.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Pause authority
+    Pause,
+
+    ///   Unpause the swap, allowing swaps and deposits again.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Pause authority
+    Unpause,
+
+    ///   Initializes a new swap holding between `MIN_MULTI_TOKENS` and
+    ///   `MAX_MULTI_TOKENS` tokens, priced with the StableSwap invariant.
+    ///
+    ///   0. `[writable, signer]` New Token-swap to create.
+    ///   1. `[]` swap authority derived from `create_program_address(&[Token-swap account])`
+    ///   2. `[writable]` Pool Token Mint. Must be empty, owned by swap authority.
+    ///   3. `[]` Pool Token Account to deposit trading and withdraw fees.
+    ///   Must be empty, not owned by swap authority
+    ///   4. `[writable]` Pool Token Account to deposit the initial pool token
+    ///   supply.  Must be empty, not owned by swap authority.
+    ///   5. '[]` Token program id
+    ///   6. ..6+N `[]` token Accounts, one per token. Must be non zero, owned by
+    ///   swap authority.
+    ///
+    ///   The pause authority, able to pause and unpause the swap, is given in
+    ///   the instruction data rather than as an account.  Without one, the
+    ///   swap can never be paused.
+    InitializeMultiToken(InitializeMultiToken),
+
+    ///   Swap between any two tokens of a multi-token pool.  The price depends
+    ///   on the balances of all tokens, so every token account of the swap
+    ///   must be provided.
+    ///
+    ///   0. `[]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` token SOURCE Account, amount is transferable by user transfer authority,
+    ///   4. `[writable]` token DESTINATION Account assigned to USER as the owner.
+    ///   5. `[writable]` Pool token mint, to generate trading fees
+    ///   6. `[writable]` Fee account, to receive trading fees
+    ///   7. '[]` Token program id
+    ///   8. ..8+N `[writable]` token Base Accounts of the swap, in order
+    ///   8+N `[optional, writable]` Host fee account to receive additional trading fees
+    SwapMultiToken(SwapMultiToken),
+
+    ///   Deposit all tokens into a multi-token pool.  The output is a "pool"
+    ///   token representing ownership in the pool. Inputs are converted to
+    ///   the current ratio.
+    ///
+    ///   0. `[]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` Pool MINT account, swap authority is the owner.
+    ///   4. `[writable]` Pool Account to deposit the generated tokens, user is the owner.
+    ///   5. '[]` Token program id
+    ///
+    ///   Then, for each token of the swap, in order:
+    ///
+    ///   0. `[writable]` token user transfer authority can transfer amount,
+    ///   1. `[writable]` token Base Account to deposit into.
+    DepositAllMultiToken(DepositAllMultiToken),
+
+    ///   Withdraw all tokens from a multi-token pool at the current ratio,
+    ///   given pool tokens.  The pool tokens are burned in exchange for an
+    ///   equivalent amount of each token.
+    ///
+    ///   0. `[]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` Pool mint account, swap authority is the owner
+    ///   4. `[writable]` SOURCE Pool account, amount is transferable by user transfer authority.
+    ///   5. `[writable]` Fee account, to receive withdrawal fees
+    ///   6. '[]` Token program id
+    ///
+    ///   Then, for each token of the swap, in order:
+    ///
+    ///   0. `[writable]` token Swap Account to withdraw FROM.
+    ///   1. `[writable]` token user Account to credit.
+    WithdrawAllMultiToken(WithdrawAllMultiToken),
 }
 
 impl SwapInstruction {
@@ -199,11 +431,28 @@ impl SwapInstruction {
                 if rest.len() >= Fees::LEN {
                     let (fees, rest) = rest.split_at(Fees::LEN);
                     let fees = Fees::unpack_unchecked(fees)?;
-                    let swap_curve = SwapCurve::unpack_unchecked(rest)?;
+                    // the pause authority and dynamic fees are optional, for
+                    // compatibility with previous clients
+                    let (swap_curve, pause_authority, dynamic_fees) = if rest.len() > SwapCurve::LEN
+                    {
+                        let (swap_curve, rest) = rest.split_at(SwapCurve::LEN);
+                        let (pause_authority, rest) = Self::unpack_pubkey_option(rest)?;
+                        let dynamic_fees = if rest.is_empty() {
+                            DynamicFees::default()
+                        } else {
+                            DynamicFees::unpack_unchecked(rest)?
+                        };
+                        (swap_curve, pause_authority, dynamic_fees)
+                    } else {
+                        (rest, COption::None, DynamicFees::default())
+                    };
+                    let swap_curve = SwapCurve::unpack_unchecked(swap_curve)?;
                     Self::Initialize(Initialize {
                         nonce,
                         fees,
                         swap_curve,
+                        pause_authority,
+                        dynamic_fees,
                     })
                 } else {
                     return Err(SwapError::InvalidInstruction.into());
@@ -253,6 +502,72 @@ impl SwapInstruction {
                     maximum_pool_token_amount,
                 })
             }
+            6 => {
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = Self::unpack_u64(rest)?;
+                Self::RouteSwap(RouteSwap {
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            7 => {
+                let (amount_out, rest) = Self::unpack_u64(rest)?;
+                let (maximum_amount_in, _rest) = Self::unpack_u64(rest)?;
+                Self::SwapExactAmountOut(SwapExactAmountOut {
+                    amount_out,
+                    maximum_amount_in,
+                })
+            }
+            8 => Self::Pause,
+            9 => Self::Unpause,
+            10 => {
+                let (&nonce, rest) = rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                if rest.len() >= Fees::LEN + MultiStableCurve::LEN {
+                    let (fees, rest) = rest.split_at(Fees::LEN);
+                    let fees = Fees::unpack_unchecked(fees)?;
+                    let (swap_curve, rest) = rest.split_at(MultiStableCurve::LEN);
+                    let swap_curve = MultiStableCurve::unpack_unchecked(swap_curve)?;
+                    let (pause_authority, _rest) = Self::unpack_pubkey_option(rest)?;
+                    Self::InitializeMultiToken(InitializeMultiToken {
+                        nonce,
+                        fees,
+                        swap_curve,
+                        pause_authority,
+                    })
+                } else {
+                    return Err(SwapError::InvalidInstruction.into());
+                }
+            }
+            11 => {
+                let (&source_index, rest) =
+                    rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                let (&destination_index, rest) =
+                    rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = Self::unpack_u64(rest)?;
+                Self::SwapMultiToken(SwapMultiToken {
+                    source_index,
+                    destination_index,
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            12 => {
+                let (pool_token_amount, rest) = Self::unpack_u64(rest)?;
+                let (maximum_token_amounts, _rest) = Self::unpack_u64_vec(rest)?;
+                Self::DepositAllMultiToken(DepositAllMultiToken {
+                    pool_token_amount,
+                    maximum_token_amounts,
+                })
+            }
+            13 => {
+                let (pool_token_amount, rest) = Self::unpack_u64(rest)?;
+                let (minimum_token_amounts, _rest) = Self::unpack_u64_vec(rest)?;
+                Self::WithdrawAllMultiToken(WithdrawAllMultiToken {
+                    pool_token_amount,
+                    minimum_token_amounts,
+                })
+            }
             _ => return Err(SwapError::InvalidInstruction.into()),
         })
     }
@@ -271,6 +586,49 @@ impl SwapInstruction {
         }
     }
 
+    fn unpack_u64_vec(input: &[u8]) -> Result<(Vec<u64>, &[u8]), ProgramError> {
+        let (&count, mut rest) = input.split_first().ok_or(SwapError::InvalidInstruction)?;
+        let mut amounts = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (amount, next) = Self::unpack_u64(rest)?;
+            amounts.push(amount);
+            rest = next;
+        }
+        Ok((amounts, rest))
+    }
+
+    fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::Some((&0, rest)) => Ok((COption::None, rest)),
+            Option::Some((&1, rest)) if rest.len() >= 32 => {
+                let (key, rest) = rest.split_at(32);
+                let key = key
+                    .try_into()
+                    .map(Pubkey::new_from_array)
+                    .map_err(|_| SwapError::InvalidInstruction)?;
+                Ok((COption::Some(key), rest))
+            }
+            _ => Err(SwapError::InvalidInstruction.into()),
+        }
+    }
+
+    fn pack_u64_vec(amounts: &[u64], buf: &mut Vec<u8>) {
+        buf.push(amounts.len() as u8);
+        for amount in amounts {
+            buf.extend_from_slice(&amount.to_le_bytes());
+        }
+    }
+
+    fn pack_pubkey_option(value: &COption<Pubkey>, buf: &mut Vec<u8>) {
+        match *value {
+            COption::Some(ref key) => {
+                buf.push(1);
+                buf.extend_from_slice(&key.to_bytes());
+            }
+            COption::None => buf.push(0),
+        }
+    }
+
     /// Packs a [SwapInstruction](enum.SwapInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
@@ -279,6 +637,8 @@ impl SwapInstruction {
                 nonce,
                 fees,
                 swap_curve,
+                pause_authority,
+                dynamic_fees,
             }) => {
                 buf.push(0);
                 buf.push(*nonce);
@@ -288,6 +648,12 @@ impl SwapInstruction {
                 let mut swap_curve_slice = [0u8; SwapCurve::LEN];
                 Pack::pack_into_slice(swap_curve, &mut swap_curve_slice[..]);
                 buf.extend_from_slice(&swap_curve_slice);
+                Self::pack_pubkey_option(pause_authority, &mut buf);
+                if dynamic_fees.is_enabled() {
+                    let mut dynamic_fees_slice = [0u8; DynamicFees::LEN];
+                    Pack::pack_into_slice(dynamic_fees, &mut dynamic_fees_slice[..]);
+                    buf.extend_from_slice(&dynamic_fees_slice);
+                }
             }
             Self::Swap(Swap {
                 amount_in,
@@ -335,6 +701,68 @@ impl SwapInstruction {
                 buf.extend_from_slice(&destination_token_amount.to_le_bytes());
                 buf.extend_from_slice(&maximum_pool_token_amount.to_le_bytes());
             }
+            Self::RouteSwap(RouteSwap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(6);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::SwapExactAmountOut(SwapExactAmountOut {
+                amount_out,
+                maximum_amount_in,
+            }) => {
+                buf.push(7);
+                buf.extend_from_slice(&amount_out.to_le_bytes());
+                buf.extend_from_slice(&maximum_amount_in.to_le_bytes());
+            }
+            Self::Pause => buf.push(8),
+            Self::Unpause => buf.push(9),
+            Self::InitializeMultiToken(InitializeMultiToken {
+                nonce,
+                fees,
+                swap_curve,
+                pause_authority,
+            }) => {
+                buf.push(10);
+                buf.push(*nonce);
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+                let mut swap_curve_slice = [0u8; MultiStableCurve::LEN];
+                Pack::pack_into_slice(swap_curve, &mut swap_curve_slice[..]);
+                buf.extend_from_slice(&swap_curve_slice);
+                Self::pack_pubkey_option(pause_authority, &mut buf);
+            }
+            Self::SwapMultiToken(SwapMultiToken {
+                source_index,
+                destination_index,
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(11);
+                buf.push(*source_index);
+                buf.push(*destination_index);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::DepositAllMultiToken(DepositAllMultiToken {
+                pool_token_amount,
+                maximum_token_amounts,
+            }) => {
+                buf.push(12);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+                Self::pack_u64_vec(maximum_token_amounts, &mut buf);
+            }
+            Self::WithdrawAllMultiToken(WithdrawAllMultiToken {
+                pool_token_amount,
+                minimum_token_amounts,
+            }) => {
+                buf.push(13);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+                Self::pack_u64_vec(minimum_token_amounts, &mut buf);
+            }
         }
         buf
     }
@@ -351,18 +779,23 @@ pub fn initialize(
     pool_pubkey: &Pubkey,
     fee_pubkey: &Pubkey,
     destination_pubkey: &Pubkey,
+    oracle_pubkey: Option<&Pubkey>,
     nonce: u8,
     fees: Fees,
     swap_curve: SwapCurve,
+    pause_authority: COption<Pubkey>,
+    dynamic_fees: DynamicFees,
 ) -> Result<Instruction, ProgramError> {
     let init_data = SwapInstruction::Initialize(Initialize {
         nonce,
         fees,
         swap_curve,
+        pause_authority,
+        dynamic_fees,
     });
     let data = init_data.pack();
 
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new(*swap_pubkey, true),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*token_a_pubkey, false),
@@ -372,6 +805,9 @@ pub fn initialize(
         AccountMeta::new(*destination_pubkey, false),
         AccountMeta::new_readonly(*token_program_id, false),
     ];
+    if let Some(oracle_pubkey) = oracle_pubkey {
+        accounts.push(AccountMeta::new_readonly(*oracle_pubkey, false));
+    }
 
     Ok(Instruction {
         program_id: *program_id,
@@ -398,7 +834,7 @@ pub fn deposit_all_token_types(
     let data = SwapInstruction::DepositAllTokenTypes(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*deposit_token_a_pubkey, false),
@@ -436,7 +872,7 @@ pub fn withdraw_all_token_types(
     let data = SwapInstruction::WithdrawAllTokenTypes(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*pool_mint_pubkey, false),
@@ -473,7 +909,7 @@ pub fn deposit_single_token_type_exact_amount_in(
     let data = SwapInstruction::DepositSingleTokenTypeExactAmountIn(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*source_token_pubkey, false),
@@ -509,7 +945,7 @@ pub fn withdraw_single_token_type_exact_amount_out(
     let data = SwapInstruction::WithdrawSingleTokenTypeExactAmountOut(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*pool_mint_pubkey, false),
@@ -541,13 +977,14 @@ pub fn swap(
     destination_pubkey: &Pubkey,
     pool_mint_pubkey: &Pubkey,
     pool_fee_pubkey: &Pubkey,
+    oracle_pubkey: Option<&Pubkey>,
     host_fee_pubkey: Option<&Pubkey>,
     instruction: Swap,
 ) -> Result<Instruction, ProgramError> {
     let data = SwapInstruction::Swap(instruction).pack();
 
     let mut accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*source_pubkey, false),
@@ -558,6 +995,9 @@ pub fn swap(
         AccountMeta::new(*pool_fee_pubkey, false),
         AccountMeta::new_readonly(*token_program_id, false),
     ];
+    if let Some(oracle_pubkey) = oracle_pubkey {
+        accounts.push(AccountMeta::new_readonly(*oracle_pubkey, false));
+    }
     if let Some(host_fee_pubkey) = host_fee_pubkey {
         accounts.push(AccountMeta::new(*host_fee_pubkey, false));
     }
@@ -569,32 +1009,355 @@ pub fn swap(
     })
 }
 
-/// Unpacks a reference from a bytes buffer.
-/// TODO actually pack / unpack instead of relying on normal memory layout.
-pub fn unpack<T>(input: &[u8]) -> Result<&T, ProgramError> {
-    if input.len() < size_of::<u8>() + size_of::<T>() {
-        return Err(ProgramError::InvalidAccountData);
+/// Creates a 'swap_exact_amount_out' instruction.
+pub fn swap_exact_amount_out(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    pool_fee_pubkey: &Pubkey,
+    oracle_pubkey: Option<&Pubkey>,
+    host_fee_pubkey: Option<&Pubkey>,
+    instruction: SwapExactAmountOut,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SwapExactAmountOut(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*swap_source_pubkey, false),
+        AccountMeta::new(*swap_destination_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*pool_fee_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    if let Some(oracle_pubkey) = oracle_pubkey {
+        accounts.push(AccountMeta::new_readonly(*oracle_pubkey, false));
     }
-    #[allow(clippy::cast_ptr_alignment)]
-    let val: &T = unsafe { &*(&input[1] as *const u8 as *const T) };
-    Ok(val)
+    if let Some(host_fee_pubkey) = host_fee_pubkey {
+        accounts.push(AccountMeta::new(*host_fee_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Pool accounts for one hop of a 'route_swap' instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteSwapHop {
+    /// Token-swap account of the pool
+    pub swap_pubkey: Pubkey,
+    /// Swap authority of the pool
+    pub authority_pubkey: Pubkey,
+    /// Pool token account receiving the input of the hop
+    pub swap_source_pubkey: Pubkey,
+    /// Pool token account providing the output of the hop
+    pub swap_destination_pubkey: Pubkey,
+    /// Pool token mint
+    pub pool_mint_pubkey: Pubkey,
+    /// Pool fee account
+    pub pool_fee_pubkey: Pubkey,
+}
 
-    use crate::curve::{base::CurveType, stable::StableCurve};
+/// Creates a 'route_swap' instruction.
+pub fn route_swap(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    hops: &[RouteSwapHop],
+    instruction: RouteSwap,
+) -> Result<Instruction, ProgramError> {
+    if hops.is_empty() || hops.len() > MAX_ROUTE_SWAP_HOPS {
+        return Err(SwapError::InvalidRoute.into());
+    }
+    let data = SwapInstruction::RouteSwap(instruction).pack();
 
-    #[test]
-    fn pack_intialize() {
-        let trade_fee_numerator: u64 = 1;
-        let trade_fee_denominator: u64 = 4;
-        let owner_trade_fee_numerator: u64 = 2;
-        let owner_trade_fee_denominator: u64 = 5;
-        let owner_withdraw_fee_numerator: u64 = 1;
-        let owner_withdraw_fee_denominator: u64 = 3;
-        let host_fee_numerator: u64 = 5;
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    for hop in hops {
+        accounts.extend_from_slice(&[
+            AccountMeta::new(hop.swap_pubkey, false),
+            AccountMeta::new_readonly(hop.authority_pubkey, false),
+            AccountMeta::new(hop.swap_source_pubkey, false),
+            AccountMeta::new(hop.swap_destination_pubkey, false),
+            AccountMeta::new(hop.pool_mint_pubkey, false),
+            AccountMeta::new(hop.pool_fee_pubkey, false),
+        ]);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'pause' instruction.
+pub fn pause(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    pause_authority_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Pause.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*pause_authority_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'unpause' instruction.
+pub fn unpause(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    pause_authority_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Unpause.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*pause_authority_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+fn check_multi_token_count(token_count: usize) -> Result<(), ProgramError> {
+    if (MIN_MULTI_TOKENS..=MAX_MULTI_TOKENS).contains(&token_count) {
+        Ok(())
+    } else {
+        Err(SwapError::InvalidTokenCount.into())
+    }
+}
+
+/// Creates an 'initialize_multi_token' instruction.
+pub fn initialize_multi_token(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    pool_pubkey: &Pubkey,
+    fee_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    nonce: u8,
+    fees: Fees,
+    swap_curve: MultiStableCurve,
+    pause_authority: COption<Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_multi_token_count(token_pubkeys.len())?;
+    let init_data = SwapInstruction::InitializeMultiToken(InitializeMultiToken {
+        nonce,
+        fees,
+        swap_curve,
+        pause_authority,
+    });
+    let data = init_data.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, true),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new(*pool_pubkey, false),
+        AccountMeta::new_readonly(*fee_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    for token_pubkey in token_pubkeys {
+        accounts.push(AccountMeta::new_readonly(*token_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'swap_multi_token' instruction.
+pub fn swap_multi_token(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    pool_fee_pubkey: &Pubkey,
+    swap_token_pubkeys: &[Pubkey],
+    host_fee_pubkey: Option<&Pubkey>,
+    instruction: SwapMultiToken,
+) -> Result<Instruction, ProgramError> {
+    check_multi_token_count(swap_token_pubkeys.len())?;
+    let data = SwapInstruction::SwapMultiToken(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*pool_fee_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    for swap_token_pubkey in swap_token_pubkeys {
+        accounts.push(AccountMeta::new(*swap_token_pubkey, false));
+    }
+    if let Some(host_fee_pubkey) = host_fee_pubkey {
+        accounts.push(AccountMeta::new(*host_fee_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'deposit_all_multi_token' instruction.
+pub fn deposit_all_multi_token(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    deposit_token_pubkeys: &[Pubkey],
+    swap_token_pubkeys: &[Pubkey],
+    instruction: DepositAllMultiToken,
+) -> Result<Instruction, ProgramError> {
+    check_multi_token_count(deposit_token_pubkeys.len())?;
+    if swap_token_pubkeys.len() != deposit_token_pubkeys.len()
+        || instruction.maximum_token_amounts.len() != deposit_token_pubkeys.len()
+    {
+        return Err(SwapError::InvalidTokenCount.into());
+    }
+    let data = SwapInstruction::DepositAllMultiToken(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    for (deposit_token_pubkey, swap_token_pubkey) in
+        deposit_token_pubkeys.iter().zip(swap_token_pubkeys.iter())
+    {
+        accounts.push(AccountMeta::new(*deposit_token_pubkey, false));
+        accounts.push(AccountMeta::new(*swap_token_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'withdraw_all_multi_token' instruction.
+pub fn withdraw_all_multi_token(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    fee_account_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_token_pubkeys: &[Pubkey],
+    destination_token_pubkeys: &[Pubkey],
+    instruction: WithdrawAllMultiToken,
+) -> Result<Instruction, ProgramError> {
+    check_multi_token_count(swap_token_pubkeys.len())?;
+    if destination_token_pubkeys.len() != swap_token_pubkeys.len()
+        || instruction.minimum_token_amounts.len() != swap_token_pubkeys.len()
+    {
+        return Err(SwapError::InvalidTokenCount.into());
+    }
+    let data = SwapInstruction::WithdrawAllMultiToken(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*fee_account_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    for (swap_token_pubkey, destination_token_pubkey) in swap_token_pubkeys
+        .iter()
+        .zip(destination_token_pubkeys.iter())
+    {
+        accounts.push(AccountMeta::new(*swap_token_pubkey, false));
+        accounts.push(AccountMeta::new(*destination_token_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Unpacks a reference from a bytes buffer.
+/// TODO actually pack / unpack instead of relying on normal memory layout.
+pub fn unpack<T>(input: &[u8]) -> Result<&T, ProgramError> {
+    if input.len() < size_of::<u8>() + size_of::<T>() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    #[allow(clippy::cast_ptr_alignment)]
+    let val: &T = unsafe { &*(&input[1] as *const u8 as *const T) };
+    Ok(val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::curve::{base::CurveType, stable::StableCurve};
+
+    #[test]
+    fn pack_intialize() {
+        let trade_fee_numerator: u64 = 1;
+        let trade_fee_denominator: u64 = 4;
+        let owner_trade_fee_numerator: u64 = 2;
+        let owner_trade_fee_denominator: u64 = 5;
+        let owner_withdraw_fee_numerator: u64 = 1;
+        let owner_withdraw_fee_denominator: u64 = 3;
+        let host_fee_numerator: u64 = 5;
         let host_fee_denominator: u64 = 20;
         let fees = Fees {
             trade_fee_numerator,
@@ -616,8 +1379,10 @@ mod tests {
         };
         let check = SwapInstruction::Initialize(Initialize {
             nonce,
-            fees,
-            swap_curve,
+            fees: fees.clone(),
+            swap_curve: swap_curve.clone(),
+            pause_authority: COption::None,
+            dynamic_fees: DynamicFees::default(),
         });
         let packed = check.pack();
         let mut expect = vec![0u8, nonce];
@@ -632,6 +1397,40 @@ mod tests {
         expect.push(curve_type as u8);
         expect.extend_from_slice(&amp.to_le_bytes());
         expect.extend_from_slice(&[0u8; 24]);
+        // previous clients don't provide a pause authority
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+        expect.push(0);
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        // with a pause authority and dynamic fees
+        let volatility_fee_numerator: u64 = 1;
+        let volatility_fee_denominator: u64 = 10;
+        let max_trade_fee_numerator: u64 = 2;
+        let decay_period: u64 = 600;
+        let pause_authority = Pubkey::new_from_array([8u8; 32]);
+        let check = SwapInstruction::Initialize(Initialize {
+            nonce,
+            fees,
+            swap_curve,
+            pause_authority: COption::Some(pause_authority),
+            dynamic_fees: DynamicFees {
+                volatility_fee_numerator,
+                volatility_fee_denominator,
+                max_trade_fee_numerator,
+                decay_period,
+            },
+        });
+        let packed = check.pack();
+        expect.pop();
+        expect.push(1);
+        expect.extend_from_slice(&pause_authority.to_bytes());
+        expect.extend_from_slice(&volatility_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&volatility_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&max_trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&decay_period.to_le_bytes());
         assert_eq!(packed, expect);
         let unpacked = SwapInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
@@ -731,4 +1530,242 @@ mod tests {
         let unpacked = SwapInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
     }
+
+    #[test]
+    fn pack_route_swap() {
+        let amount_in: u64 = 1_000;
+        let minimum_amount_out: u64 = 950;
+        let check = SwapInstruction::RouteSwap(RouteSwap {
+            amount_in,
+            minimum_amount_out,
+        });
+        let packed = check.pack();
+        let mut expect = vec![6];
+        expect.extend_from_slice(&amount_in.to_le_bytes());
+        expect.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_swap_exact_amount_out() {
+        let amount_out: u64 = 10;
+        let maximum_amount_in: u64 = 2;
+        let check = SwapInstruction::SwapExactAmountOut(SwapExactAmountOut {
+            amount_out,
+            maximum_amount_in,
+        });
+        let packed = check.pack();
+        let mut expect = vec![7];
+        expect.extend_from_slice(&amount_out.to_le_bytes());
+        expect.extend_from_slice(&maximum_amount_in.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_pause() {
+        let check = SwapInstruction::Pause;
+        let packed = check.pack();
+        let expect = vec![8];
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_unpause() {
+        let check = SwapInstruction::Unpause;
+        let packed = check.pack();
+        let expect = vec![9];
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_initialize_multi_token() {
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 4,
+            owner_trade_fee_numerator: 2,
+            owner_trade_fee_denominator: 5,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 3,
+            host_fee_numerator: 5,
+            host_fee_denominator: 20,
+        };
+        let nonce: u8 = 255;
+        let amp: u64 = 100;
+        let pause_authority = Pubkey::new_from_array([8u8; 32]);
+        let check = SwapInstruction::InitializeMultiToken(InitializeMultiToken {
+            nonce,
+            fees: fees.clone(),
+            swap_curve: MultiStableCurve { amp },
+            pause_authority: COption::Some(pause_authority),
+        });
+        let packed = check.pack();
+        let mut expect = vec![10u8, nonce];
+        expect.extend_from_slice(&fees.trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.trade_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_trade_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_withdraw_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_withdraw_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&fees.host_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.host_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&amp.to_le_bytes());
+        expect.push(1);
+        expect.extend_from_slice(&pause_authority.to_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = SwapInstruction::InitializeMultiToken(InitializeMultiToken {
+            nonce,
+            fees,
+            swap_curve: MultiStableCurve { amp },
+            pause_authority: COption::None,
+        });
+        let packed = check.pack();
+        assert_eq!(packed.len(), 3 + Fees::LEN + MultiStableCurve::LEN);
+        assert_eq!(*packed.last().unwrap(), 0);
+        let unpacked = SwapInstruction::unpack(&packed).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_swap_multi_token() {
+        let source_index: u8 = 0;
+        let destination_index: u8 = 2;
+        let amount_in: u64 = 2;
+        let minimum_amount_out: u64 = 10;
+        let check = SwapInstruction::SwapMultiToken(SwapMultiToken {
+            source_index,
+            destination_index,
+            amount_in,
+            minimum_amount_out,
+        });
+        let packed = check.pack();
+        let mut expect = vec![11, source_index, destination_index];
+        expect.extend_from_slice(&amount_in.to_le_bytes());
+        expect.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_deposit_all_multi_token() {
+        let pool_token_amount: u64 = 5;
+        let maximum_token_amounts = vec![10, 20, 30];
+        let check = SwapInstruction::DepositAllMultiToken(DepositAllMultiToken {
+            pool_token_amount,
+            maximum_token_amounts: maximum_token_amounts.clone(),
+        });
+        let packed = check.pack();
+        let mut expect = vec![12];
+        expect.extend_from_slice(&pool_token_amount.to_le_bytes());
+        expect.push(3);
+        for amount in maximum_token_amounts {
+            expect.extend_from_slice(&amount.to_le_bytes());
+        }
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        // missing amounts
+        expect.truncate(expect.len() - 8);
+        assert_eq!(
+            SwapInstruction::unpack(&expect),
+            Err(SwapError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn pack_withdraw_all_multi_token() {
+        let pool_token_amount: u64 = 1212438012089;
+        let minimum_token_amounts = vec![102198761982612, 1, 2, 3];
+        let check = SwapInstruction::WithdrawAllMultiToken(WithdrawAllMultiToken {
+            pool_token_amount,
+            minimum_token_amounts: minimum_token_amounts.clone(),
+        });
+        let packed = check.pack();
+        let mut expect = vec![13];
+        expect.extend_from_slice(&pool_token_amount.to_le_bytes());
+        expect.push(4);
+        for amount in minimum_token_amounts {
+            expect.extend_from_slice(&amount.to_le_bytes());
+        }
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn multi_token_count() {
+        let program_id = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let fees = Fees::default();
+        let swap_curve = MultiStableCurve { amp: 100 };
+        for token_count in [2, 9].iter() {
+            let token_pubkeys = vec![key; *token_count];
+            assert_eq!(
+                initialize_multi_token(
+                    &program_id,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &key,
+                    &token_pubkeys,
+                    0,
+                    fees.clone(),
+                    swap_curve.clone(),
+                    COption::None,
+                ),
+                Err(SwapError::InvalidTokenCount.into())
+            );
+        }
+        let token_pubkeys = vec![key; 3];
+        let instruction = initialize_multi_token(
+            &program_id,
+            &key,
+            &key,
+            &key,
+            &key,
+            &key,
+            &key,
+            &token_pubkeys,
+            0,
+            fees,
+            swap_curve,
+            COption::None,
+        )
+        .unwrap();
+        assert_eq!(instruction.accounts.len(), 9);
+
+        // amounts must match the number of tokens
+        assert_eq!(
+            deposit_all_multi_token(
+                &program_id,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &key,
+                &token_pubkeys,
+                &token_pubkeys,
+                DepositAllMultiToken {
+                    pool_token_amount: 1,
+                    maximum_token_amounts: vec![1, 1],
+                },
+            ),
+            Err(SwapError::InvalidTokenCount.into())
+        );
+    }
 }
@@ -6,6 +6,7 @@ pub mod constraints;
 pub mod curve;
 pub mod error;
 pub mod instruction;
+pub mod oracle;
 pub mod processor;
 pub mod state;
 
@@ -0,0 +1,107 @@
+//! Price accounts read by curves priced by an oracle
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    clock::Slot,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+};
+
+/// Prices given by an oracle are expressed as an amount of token B per token
+/// A, in units of 1 / PRICE_SCALE, so a price of 1_000_000_000 means 1 token B
+/// per token A
+pub const PRICE_SCALE: u64 = 1_000_000_000;
+
+/// Maximum number of slots between the publication of a price and its use by
+/// a swap.  Older prices are considered stale, and rejected.
+pub const MAX_PRICE_AGE: Slot = 25;
+
+/// Price account kept up to date by an oracle, read on every swap of the
+/// pools it was given to on initialization
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Price {
+    /// Price of token A of the pool, in units of 1 / PRICE_SCALE token B per
+    /// token A.  A price of 0 means that no price is available.
+    pub price: u64,
+    /// Slot in which the oracle published the price
+    pub publish_slot: Slot,
+}
+
+impl Price {
+    /// Returns the price, unless it was published more than MAX_PRICE_AGE
+    /// slots before `current_slot`
+    pub fn current_price(&self, current_slot: Slot) -> Option<u64> {
+        if current_slot.saturating_sub(self.publish_slot) <= MAX_PRICE_AGE {
+            Some(self.price)
+        } else {
+            None
+        }
+    }
+}
+
+impl Sealed for Price {}
+impl IsInitialized for Price {
+    fn is_initialized(&self) -> bool {
+        self.price != 0
+    }
+}
+
+impl Pack for Price {
+    const LEN: usize = 16;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 16];
+        let (price, publish_slot) = mut_array_refs![output, 8, 8];
+        *price = self.price.to_le_bytes();
+        *publish_slot = self.publish_slot.to_le_bytes();
+    }
+
+    /// Unpacks a byte buffer into a [Price](struct.Price.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 16];
+        let (price, publish_slot) = array_refs![input, 8, 8];
+        Ok(Self {
+            price: u64::from_le_bytes(*price),
+            publish_slot: Slot::from_le_bytes(*publish_slot),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_pack() {
+        let price = Price {
+            price: 1_234_567,
+            publish_slot: 89,
+        };
+        let mut packed = [0u8; Price::LEN];
+        Price::pack(price, &mut packed).unwrap();
+        let mut expect = 1_234_567u64.to_le_bytes().to_vec();
+        expect.extend_from_slice(&89u64.to_le_bytes());
+        assert_eq!(packed.to_vec(), expect);
+        assert_eq!(Price::unpack(&packed).unwrap(), price);
+
+        let packed = [0u8; Price::LEN];
+        assert_eq!(
+            Price::unpack(&packed),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn current_price() {
+        let price = Price {
+            price: 1_234_567,
+            publish_slot: 100,
+        };
+        assert_eq!(price.current_price(100), Some(1_234_567));
+        assert_eq!(price.current_price(100 + MAX_PRICE_AGE), Some(1_234_567));
+        assert_eq!(price.current_price(101 + MAX_PRICE_AGE), None);
+        // a clock behind the oracle doesn't make the price stale
+        assert_eq!(price.current_price(90), Some(1_234_567));
+    }
+}
@@ -3,20 +3,30 @@
 use crate::constraints::{SwapConstraints, SWAP_CONSTRAINTS};
 use crate::{
     curve::{
-        base::SwapCurve,
+        base::{CurveType, SwapCurve},
         calculator::{RoundDirection, TradeDirection},
-        fees::Fees,
+        fees::{price_movement, DynamicFees, Fees},
+        multi_stable::MultiStableCurve,
+        proactive_market_making::ProactiveMarketMakingCurve,
+        stake_pool_stable::StakePoolStableCurve,
     },
     error::SwapError,
     instruction::{
-        DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn, Initialize, Swap,
-        SwapInstruction, WithdrawAllTokenTypes, WithdrawSingleTokenTypeExactAmountOut,
+        DepositAllMultiToken, DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn,
+        Initialize, InitializeMultiToken, RouteSwap, Swap, SwapExactAmountOut, SwapInstruction,
+        SwapMultiToken, WithdrawAllMultiToken, WithdrawAllTokenTypes,
+        WithdrawSingleTokenTypeExactAmountOut, MAX_ROUTE_SWAP_HOPS, ROUTE_SWAP_HOP_ACCOUNTS_LEN,
+    },
+    oracle::Price,
+    state::{
+        MultiTokenSwapV1, MultiTokenSwapVersion, SwapState, SwapV3, SwapVersion, MAX_MULTI_TOKENS,
+        MIN_MULTI_TOKENS,
     },
-    state::{SwapState, SwapV1, SwapVersion},
 };
 use num_traits::FromPrimitive;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    borsh::try_from_slice_unchecked,
     decode_error::DecodeError,
     entrypoint::ProgramResult,
     msg,
@@ -25,9 +35,41 @@ use solana_program::{
     program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
 };
+use spl_stake_pool::state::StakePool;
 use std::convert::TryInto;
 
+/// Source of the tokens swapped into a pool
+#[derive(Clone, Copy)]
+enum SwapSource<'b, 'a> {
+    /// Transferred from a user account by the user transfer authority
+    User {
+        source_info: &'b AccountInfo<'a>,
+        user_transfer_authority_info: &'b AccountInfo<'a>,
+    },
+    /// Already transferred into the pool's source account by the previous pool of a route
+    PreviousHop {
+        /// Amount sent by the previous pool
+        amount: u64,
+    },
+}
+
+/// Amounts requested from a swap, fixing either side of the trade
+#[derive(Clone, Copy)]
+enum SwapAmount {
+    /// Swap an exact SOURCE amount for at least `minimum_amount_out`
+    ExactIn {
+        amount_in: u64,
+        minimum_amount_out: u64,
+    },
+    /// Swap at most `maximum_amount_in` for an exact DESTINATION amount
+    ExactOut {
+        amount_out: u64,
+        maximum_amount_in: u64,
+    },
+}
+
 /// Program state handler.
 pub struct Processor {}
 impl Processor {
@@ -203,11 +245,14 @@ impl Processor {
     }
 
     /// Processes an [Initialize](enum.Instruction.html).
+    #[allow(clippy::too_many_arguments)]
     pub fn process_initialize(
         program_id: &Pubkey,
         nonce: u8,
         fees: Fees,
         swap_curve: SwapCurve,
+        pause_authority: COption<Pubkey>,
+        dynamic_fees: DynamicFees,
         accounts: &[AccountInfo],
         swap_constraints: &Option<SwapConstraints>,
     ) -> ProgramResult {
@@ -220,6 +265,11 @@ impl Processor {
         let fee_account_info = next_account_info(account_info_iter)?;
         let destination_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
+        let oracle_info = if swap_curve.curve_type.uses_oracle() {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
 
         let token_program_id = *token_program_info.key;
         if SwapVersion::is_initialized(&swap_info.data.borrow()) {
@@ -253,6 +303,12 @@ impl Processor {
         if token_a.mint == token_b.mint {
             return Err(SwapError::RepeatedMint.into());
         }
+        // stake pool curves value token A as SOL
+        if swap_curve.curve_type == CurveType::StakePoolStable
+            && token_a.mint != spl_token::native_mint::id()
+        {
+            return Err(SwapError::InvalidCurve.into());
+        }
         swap_curve
             .calculator
             .validate_supply(token_a.amount, token_b.amount)?;
@@ -291,6 +347,23 @@ impl Processor {
             swap_constraints.validate_fees(&fees)?;
         }
         fees.validate()?;
+        dynamic_fees.validate(&fees)?;
+        let oracle_program_id = oracle_info
+            .map(|oracle_info| {
+                Self::oracle_program_id(swap_curve.curve_type, oracle_info, swap_constraints)
+            })
+            .transpose()?;
+        // curves priced by an oracle start from its current price
+        let swap_curve = match oracle_info {
+            Some(oracle_info) => Self::refresh_swap_curve(
+                &swap_curve,
+                oracle_info,
+                &token_b.mint,
+                token_a.amount,
+                token_b.amount,
+            )?,
+            None => swap_curve,
+        };
         swap_curve.calculator.validate()?;
 
         let initial_amount = swap_curve.calculator.new_pool_supply();
@@ -305,7 +378,8 @@ impl Processor {
             to_u64(initial_amount)?,
         )?;
 
-        let obj = SwapVersion::SwapV1(SwapV1 {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let obj = SwapVersion::SwapV3(SwapV3 {
             is_initialized: true,
             nonce,
             token_program_id,
@@ -317,6 +391,19 @@ impl Processor {
             pool_fee_account: *fee_account_info.key,
             fees,
             swap_curve,
+            pause_authority,
+            is_paused: false,
+            price_a_cumulative: 0,
+            price_b_cumulative: 0,
+            last_update_timestamp: timestamp,
+            dynamic_fees,
+            volatility: 0,
+            last_volatility_update: timestamp,
+            oracle: match oracle_info {
+                Some(oracle_info) => COption::Some(*oracle_info.key),
+                None => COption::None,
+            },
+            oracle_program_id: oracle_program_id.unwrap_or_default(),
         });
         SwapVersion::pack(obj, &mut swap_info.data.borrow_mut())?;
         Ok(())
@@ -328,6 +415,39 @@ impl Processor {
         amount_in: u64,
         minimum_amount_out: u64,
         accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::process_user_swap(
+            program_id,
+            SwapAmount::ExactIn {
+                amount_in,
+                minimum_amount_out,
+            },
+            accounts,
+        )
+    }
+
+    /// Processes a [SwapExactAmountOut](enum.Instruction.html).
+    pub fn process_swap_exact_amount_out(
+        program_id: &Pubkey,
+        amount_out: u64,
+        maximum_amount_in: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::process_user_swap(
+            program_id,
+            SwapAmount::ExactOut {
+                amount_out,
+                maximum_amount_in,
+            },
+            accounts,
+        )
+    }
+
+    /// Processes a swap through a single pool from the user's source account
+    fn process_user_swap(
+        program_id: &Pubkey,
+        swap_amount: SwapAmount,
+        accounts: &[AccountInfo],
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let swap_info = next_account_info(account_info_iter)?;
@@ -340,11 +460,120 @@ impl Processor {
         let pool_mint_info = next_account_info(account_info_iter)?;
         let pool_fee_account_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
+        // pools priced by an oracle take it before the optional host fee
+        // account, any other swap state is rejected when swapping below
+        let oracle_info = match SwapVersion::unpack(&swap_info.data.borrow()) {
+            Ok(token_swap) if token_swap.oracle().is_some() => {
+                Some(next_account_info(account_info_iter)?)
+            }
+            _ => None,
+        };
+        let host_fee_account_info = next_account_info(account_info_iter).ok();
+
+        Self::swap_in_pool(
+            program_id,
+            swap_amount,
+            SwapSource::User {
+                source_info,
+                user_transfer_authority_info,
+            },
+            swap_info,
+            authority_info,
+            swap_source_info,
+            swap_destination_info,
+            destination_info,
+            pool_mint_info,
+            pool_fee_account_info,
+            token_program_info,
+            oracle_info,
+            host_fee_account_info,
+        )?;
+        Ok(())
+    }
+
+    /// Processes a [RouteSwap](enum.Instruction.html).
+    pub fn process_route_swap(
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let hop_accounts = account_info_iter.as_slice();
+        if hop_accounts.is_empty()
+            || hop_accounts.len() % ROUTE_SWAP_HOP_ACCOUNTS_LEN != 0
+            || hop_accounts.len() / ROUTE_SWAP_HOP_ACCOUNTS_LEN > MAX_ROUTE_SWAP_HOPS
+        {
+            return Err(SwapError::InvalidRoute.into());
+        }
+
+        let mut hops = hop_accounts.chunks(ROUTE_SWAP_HOP_ACCOUNTS_LEN).peekable();
+        let mut amount = amount_in;
+        let mut swap_source = SwapSource::User {
+            source_info,
+            user_transfer_authority_info,
+        };
+        while let Some(hop) = hops.next() {
+            // Each pool sends its output straight into the source account of the next pool,
+            // and only the last pool pays out to the user
+            let (hop_destination_info, hop_minimum_amount_out) = match hops.peek() {
+                Some(next_hop) => (&next_hop[2], 0),
+                None => (destination_info, minimum_amount_out),
+            };
+            amount = Self::swap_in_pool(
+                program_id,
+                SwapAmount::ExactIn {
+                    amount_in: amount,
+                    minimum_amount_out: hop_minimum_amount_out,
+                },
+                swap_source,
+                &hop[0],
+                &hop[1],
+                &hop[2],
+                &hop[3],
+                hop_destination_info,
+                &hop[4],
+                &hop[5],
+                token_program_info,
+                // pools priced by an oracle can't be part of a route
+                None,
+                None,
+            )?;
+            swap_source = SwapSource::PreviousHop { amount };
+        }
+        Ok(())
+    }
 
+    /// Swaps tokens through a pool for the requested amounts, returning the
+    /// amount sent to `destination_info`
+    #[allow(clippy::too_many_arguments)]
+    fn swap_in_pool<'a>(
+        program_id: &Pubkey,
+        swap_amount: SwapAmount,
+        swap_source: SwapSource<'_, 'a>,
+        swap_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        swap_source_info: &AccountInfo<'a>,
+        swap_destination_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        pool_mint_info: &AccountInfo<'a>,
+        pool_fee_account_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        oracle_info: Option<&AccountInfo<'a>>,
+        host_fee_account_info: Option<&AccountInfo<'a>>,
+    ) -> Result<u64, ProgramError> {
         if swap_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
+        if token_swap.is_paused() {
+            return Err(SwapError::SwapPaused.into());
+        }
 
         if *authority_info.key != Self::authority_id(program_id, swap_info.key, token_swap.nonce())?
         {
@@ -363,8 +592,10 @@ impl Processor {
         if *swap_source_info.key == *swap_destination_info.key {
             return Err(SwapError::InvalidInput.into());
         }
-        if swap_source_info.key == source_info.key {
-            return Err(SwapError::InvalidInput.into());
+        if let SwapSource::User { source_info, .. } = swap_source {
+            if swap_source_info.key == source_info.key {
+                return Err(SwapError::InvalidInput.into());
+            }
         }
         if swap_destination_info.key == destination_info.key {
             return Err(SwapError::InvalidInput.into());
@@ -385,24 +616,103 @@ impl Processor {
             Self::unpack_token_account(swap_destination_info, token_swap.token_program_id())?;
         let pool_mint = Self::unpack_mint(pool_mint_info, token_swap.token_program_id())?;
 
+        // Tokens sent by a previous hop are already in the pool, but must not count
+        // towards its reserve when pricing the swap
+        let swap_source_amount = match swap_source {
+            SwapSource::User { .. } => source_account.amount,
+            SwapSource::PreviousHop { amount } => source_account
+                .amount
+                .checked_sub(amount)
+                .ok_or(SwapError::CalculationFailure)?,
+        };
+
         let trade_direction = if *swap_source_info.key == *token_swap.token_a_account() {
             TradeDirection::AtoB
         } else {
             TradeDirection::BtoA
         };
-        let result = token_swap
-            .swap_curve()
-            .swap(
-                to_u128(amount_in)?,
-                to_u128(source_account.amount)?,
-                to_u128(dest_account.amount)?,
-                trade_direction,
-                token_swap.fees(),
-            )
-            .ok_or(SwapError::ZeroTradingTokens)?;
-        if result.destination_amount_swapped < to_u128(minimum_amount_out)? {
-            return Err(SwapError::ExceededSlippage.into());
-        }
+        let (token_a_amount, token_b_amount) = match trade_direction {
+            TradeDirection::AtoB => (swap_source_amount, dest_account.amount),
+            TradeDirection::BtoA => (dest_account.amount, swap_source_amount),
+        };
+        Self::update_price_cumulative(swap_info, token_a_amount, token_b_amount)?;
+
+        // curves priced by an oracle follow its latest price, and keep the
+        // refreshed state for later operations on the pool
+        let refreshed_swap_curve = match token_swap.oracle() {
+            COption::Some(oracle) => {
+                let oracle_info = oracle_info
+                    .filter(|oracle_info| {
+                        *oracle_info.key == oracle
+                            && oracle_info.owner == token_swap.oracle_program_id()
+                    })
+                    .ok_or(SwapError::IncorrectOracleAccount)?;
+                Some(Self::refresh_swap_curve(
+                    token_swap.swap_curve(),
+                    oracle_info,
+                    token_swap.token_b_mint(),
+                    token_a_amount,
+                    token_b_amount,
+                )?)
+            }
+            COption::None => None,
+        };
+        let swap_curve = refreshed_swap_curve
+            .as_ref()
+            .unwrap_or_else(|| token_swap.swap_curve());
+
+        // the trade fee rises with the volatility left by previous swaps
+        let dynamic_fees = token_swap.dynamic_fees();
+        let fees = if dynamic_fees.is_enabled() {
+            let time_elapsed = Clock::get()?
+                .unix_timestamp
+                .saturating_sub(token_swap.last_volatility_update());
+            let volatility = dynamic_fees.decayed_volatility(token_swap.volatility(), time_elapsed);
+            dynamic_fees
+                .trade_fees(token_swap.fees(), volatility)
+                .ok_or(SwapError::FeeCalculationFailure)?
+        } else {
+            token_swap.fees().clone()
+        };
+
+        let result = match swap_amount {
+            SwapAmount::ExactIn {
+                amount_in,
+                minimum_amount_out,
+            } => {
+                let result = swap_curve
+                    .swap(
+                        to_u128(amount_in)?,
+                        to_u128(swap_source_amount)?,
+                        to_u128(dest_account.amount)?,
+                        trade_direction,
+                        &fees,
+                    )
+                    .ok_or(SwapError::ZeroTradingTokens)?;
+                if result.destination_amount_swapped < to_u128(minimum_amount_out)? {
+                    return Err(SwapError::ExceededSlippage.into());
+                }
+                result
+            }
+            SwapAmount::ExactOut {
+                amount_out,
+                maximum_amount_in,
+            } => {
+                let result = swap_curve
+                    .swap_exact_out(
+                        to_u128(amount_out)?,
+                        to_u128(swap_source_amount)?,
+                        to_u128(dest_account.amount)?,
+                        trade_direction,
+                        &fees,
+                    )
+                    .ok_or(SwapError::ZeroTradingTokens)?;
+                if result.source_amount_swapped > to_u128(maximum_amount_in)? {
+                    return Err(SwapError::ExceededSlippage.into());
+                }
+                result
+            }
+        };
 
         let (swap_token_a_amount, swap_token_b_amount) = match trade_direction {
             TradeDirection::AtoB => (
@@ -414,32 +724,47 @@ impl Processor {
                 result.new_swap_source_amount,
             ),
         };
+        if dynamic_fees.is_enabled() {
+            let price_movement = price_movement(
+                to_u128(token_a_amount)?,
+                to_u128(token_b_amount)?,
+                swap_token_a_amount,
+                swap_token_b_amount,
+            )
+            .ok_or(SwapError::CalculationFailure)?;
+            Self::update_volatility(swap_info, price_movement)?;
+        }
 
-        Self::token_transfer(
-            swap_info.key,
-            token_program_info.clone(),
-            source_info.clone(),
-            swap_source_info.clone(),
-            user_transfer_authority_info.clone(),
-            token_swap.nonce(),
-            to_u64(result.source_amount_swapped)?,
-        )?;
+        if let SwapSource::User {
+            source_info,
+            user_transfer_authority_info,
+        } = swap_source
+        {
+            Self::token_transfer(
+                swap_info.key,
+                token_program_info.clone(),
+                source_info.clone(),
+                swap_source_info.clone(),
+                user_transfer_authority_info.clone(),
+                token_swap.nonce(),
+                to_u64(result.source_amount_swapped)?,
+            )?;
+        }
 
-        let mut pool_token_amount = token_swap
-            .swap_curve()
+        let mut pool_token_amount = swap_curve
             .withdraw_single_token_type_exact_out(
                 result.owner_fee,
                 swap_token_a_amount,
                 swap_token_b_amount,
                 to_u128(pool_mint.supply)?,
                 trade_direction,
-                token_swap.fees(),
+                &fees,
             )
             .ok_or(SwapError::FeeCalculationFailure)?;
 
         if pool_token_amount > 0 {
             // Allow error to fall through
-            if let Ok(host_fee_account_info) = next_account_info(account_info_iter) {
+            if let Some(host_fee_account_info) = host_fee_account_info {
                 let host_fee_account = Self::unpack_token_account(
                     host_fee_account_info,
                     token_swap.token_program_id(),
@@ -447,8 +772,7 @@ impl Processor {
                 if *pool_mint_info.key != host_fee_account.mint {
                     return Err(SwapError::IncorrectPoolMint.into());
                 }
-                let host_fee = token_swap
-                    .fees()
+                let host_fee = fees
                     .host_fee(pool_token_amount)
                     .ok_or(SwapError::FeeCalculationFailure)?;
                 if host_fee > 0 {
@@ -476,7 +800,11 @@ impl Processor {
                 to_u64(pool_token_amount)?,
             )?;
         }
+        if let Some(swap_curve) = refreshed_swap_curve {
+            Self::update_swap_curve(swap_info, swap_curve)?;
+        }
 
+        let amount_out = to_u64(result.destination_amount_swapped)?;
         Self::token_transfer(
             swap_info.key,
             token_program_info.clone(),
@@ -484,10 +812,10 @@ impl Processor {
             destination_info.clone(),
             authority_info.clone(),
             token_swap.nonce(),
-            to_u64(result.destination_amount_swapped)?,
+            amount_out,
         )?;
 
-        Ok(())
+        Ok(amount_out)
     }
 
     /// Processes an [DepositAllTokenTypes](enum.Instruction.html).
@@ -511,6 +839,9 @@ impl Processor {
         let token_program_info = next_account_info(account_info_iter)?;
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
+        if token_swap.is_paused() {
+            return Err(SwapError::SwapPaused.into());
+        }
         let calculator = &token_swap.swap_curve().calculator;
         if !calculator.allows_deposits() {
             return Err(SwapError::UnsupportedCurveOperation.into());
@@ -532,6 +863,7 @@ impl Processor {
         let token_a = Self::unpack_token_account(token_a_info, token_swap.token_program_id())?;
         let token_b = Self::unpack_token_account(token_b_info, token_swap.token_program_id())?;
         let pool_mint = Self::unpack_mint(pool_mint_info, token_swap.token_program_id())?;
+        Self::update_price_cumulative(swap_info, token_a.amount, token_b.amount)?;
         let current_pool_mint_supply = to_u128(pool_mint.supply)?;
         let (pool_token_amount, pool_mint_supply) = if current_pool_mint_supply > 0 {
             (to_u128(pool_token_amount)?, current_pool_mint_supply)
@@ -563,6 +895,14 @@ impl Processor {
             return Err(SwapError::ZeroTradingTokens.into());
         }
 
+        Self::scale_swap_curve_targets(
+            swap_info,
+            token_swap.swap_curve(),
+            current_pool_mint_supply
+                .checked_add(pool_token_amount)
+                .ok_or(SwapError::CalculationFailure)?,
+            current_pool_mint_supply,
+        )?;
         let pool_token_amount = to_u64(pool_token_amount)?;
 
         Self::token_transfer(
@@ -635,6 +975,7 @@ impl Processor {
         let token_a = Self::unpack_token_account(token_a_info, token_swap.token_program_id())?;
         let token_b = Self::unpack_token_account(token_b_info, token_swap.token_program_id())?;
         let pool_mint = Self::unpack_mint(pool_mint_info, token_swap.token_program_id())?;
+        Self::update_price_cumulative(swap_info, token_a.amount, token_b.amount)?;
 
         let calculator = &token_swap.swap_curve().calculator;
 
@@ -676,6 +1017,15 @@ impl Processor {
         if token_b_amount == 0 && token_b.amount != 0 {
             return Err(SwapError::ZeroTradingTokens.into());
         }
+        let pool_mint_supply = to_u128(pool_mint.supply)?;
+        Self::scale_swap_curve_targets(
+            swap_info,
+            token_swap.swap_curve(),
+            pool_mint_supply
+                .checked_sub(pool_token_amount)
+                .ok_or(SwapError::CalculationFailure)?,
+            pool_mint_supply,
+        )?;
 
         if withdraw_fee > 0 {
             Self::token_transfer(
@@ -742,6 +1092,14 @@ impl Processor {
         let token_program_info = next_account_info(account_info_iter)?;
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
+        if token_swap.is_paused() {
+            return Err(SwapError::SwapPaused.into());
+        }
+        // the state of curves priced by an oracle is only kept up to date by
+        // swaps and by deposits and withdrawals of all token types
+        if token_swap.swap_curve().curve_type.uses_oracle() {
+            return Err(SwapError::UnsupportedCurveOperation.into());
+        }
         let source_account =
             Self::unpack_token_account(source_info, token_swap.token_program_id())?;
         let swap_token_a =
@@ -775,6 +1133,7 @@ impl Processor {
             source_b_info,
             None,
         )?;
+        Self::update_price_cumulative(swap_info, swap_token_a.amount, swap_token_b.amount)?;
 
         let pool_mint = Self::unpack_mint(pool_mint_info, token_swap.token_program_id())?;
         let pool_mint_supply = to_u128(pool_mint.supply)?;
@@ -859,6 +1218,11 @@ impl Processor {
         let token_program_info = next_account_info(account_info_iter)?;
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
+        // the state of curves priced by an oracle is only kept up to date by
+        // swaps and by deposits and withdrawals of all token types
+        if token_swap.swap_curve().curve_type.uses_oracle() {
+            return Err(SwapError::UnsupportedCurveOperation.into());
+        }
         let destination_account =
             Self::unpack_token_account(destination_info, token_swap.token_program_id())?;
         let swap_token_a =
@@ -891,6 +1255,7 @@ impl Processor {
             destination_b_info,
             Some(pool_fee_account_info),
         )?;
+        Self::update_price_cumulative(swap_info, swap_token_a.amount, swap_token_b.amount)?;
 
         let pool_mint = Self::unpack_mint(pool_mint_info, token_swap.token_program_id())?;
         let pool_mint_supply = to_u128(pool_mint.supply)?;
@@ -978,2939 +1343,3330 @@ impl Processor {
         Ok(())
     }
 
-    /// Processes an [Instruction](enum.Instruction.html).
-    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
-        Self::process_with_constraints(program_id, accounts, input, &SWAP_CONSTRAINTS)
-    }
-
-    /// Processes an instruction given extra constraint
-    pub fn process_with_constraints(
+    /// Processes a [Pause](enum.Instruction.html) or [Unpause](enum.Instruction.html).
+    pub fn process_set_paused(
         program_id: &Pubkey,
+        is_paused: bool,
         accounts: &[AccountInfo],
-        input: &[u8],
-        swap_constraints: &Option<SwapConstraints>,
     ) -> ProgramResult {
-        let instruction = SwapInstruction::unpack(input)?;
-        match instruction {
-            SwapInstruction::Initialize(Initialize {
-                nonce,
-                fees,
-                swap_curve,
-            }) => {
-                msg!("Instruction: Init");
-                Self::process_initialize(
-                    program_id,
-                    nonce,
-                    fees,
-                    swap_curve,
-                    accounts,
-                    swap_constraints,
-                )
-            }
-            SwapInstruction::Swap(Swap {
-                amount_in,
-                minimum_amount_out,
-            }) => {
-                msg!("Instruction: Swap");
-                Self::process_swap(program_id, amount_in, minimum_amount_out, accounts)
-            }
-            SwapInstruction::DepositAllTokenTypes(DepositAllTokenTypes {
-                pool_token_amount,
-                maximum_token_a_amount,
-                maximum_token_b_amount,
-            }) => {
-                msg!("Instruction: DepositAllTokenTypes");
-                Self::process_deposit_all_token_types(
-                    program_id,
-                    pool_token_amount,
-                    maximum_token_a_amount,
-                    maximum_token_b_amount,
-                    accounts,
-                )
-            }
-            SwapInstruction::WithdrawAllTokenTypes(WithdrawAllTokenTypes {
-                pool_token_amount,
-                minimum_token_a_amount,
-                minimum_token_b_amount,
-            }) => {
-                msg!("Instruction: WithdrawAllTokenTypes");
-                Self::process_withdraw_all_token_types(
-                    program_id,
-                    pool_token_amount,
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                    accounts,
-                )
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let pause_authority_info = next_account_info(account_info_iter)?;
+
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if MultiTokenSwapVersion::is_initialized(&swap_info.data.borrow()) {
+            let mut token_swap = MultiTokenSwapVersion::unpack(&swap_info.data.borrow())?;
+            Self::check_set_paused(
+                token_swap.pause_authority,
+                token_swap.is_paused,
+                is_paused,
+                pause_authority_info,
+            )?;
+            token_swap.is_paused = is_paused;
+            MultiTokenSwapVersion::pack(
+                MultiTokenSwapVersion::MultiTokenSwapV1(token_swap),
+                &mut swap_info.data.borrow_mut(),
+            )?;
+            return Ok(());
+        }
+        let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
+        Self::check_set_paused(
+            token_swap.pause_authority(),
+            token_swap.is_paused(),
+            is_paused,
+            pause_authority_info,
+        )?;
+
+        // Only versions with a pause authority can get here
+        let token_swap = match SwapVersion::unpack_versioned(&swap_info.data.borrow())? {
+            SwapVersion::SwapV1(_) => return Err(SwapError::InvalidPauseAuthority.into()),
+            SwapVersion::SwapV2(mut token_swap) => {
+                token_swap.is_paused = is_paused;
+                SwapVersion::SwapV2(token_swap)
             }
-            SwapInstruction::DepositSingleTokenTypeExactAmountIn(
-                DepositSingleTokenTypeExactAmountIn {
-                    source_token_amount,
-                    minimum_pool_token_amount,
-                },
-            ) => {
-                msg!("Instruction: DepositSingleTokenTypeExactAmountIn");
-                Self::process_deposit_single_token_type_exact_amount_in(
-                    program_id,
-                    source_token_amount,
-                    minimum_pool_token_amount,
-                    accounts,
-                )
+            SwapVersion::SwapV3(mut token_swap) => {
+                token_swap.is_paused = is_paused;
+                SwapVersion::SwapV3(token_swap)
             }
-            SwapInstruction::WithdrawSingleTokenTypeExactAmountOut(
-                WithdrawSingleTokenTypeExactAmountOut {
-                    destination_token_amount,
-                    maximum_pool_token_amount,
-                },
-            ) => {
-                msg!("Instruction: WithdrawSingleTokenTypeExactAmountOut");
-                Self::process_withdraw_single_token_type_exact_amount_out(
-                    program_id,
-                    destination_token_amount,
-                    maximum_pool_token_amount,
-                    accounts,
-                )
+        };
+        SwapVersion::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Adds the prices held since the last update to the swap's price
+    /// accumulators, given the pool balances before the current operation.
+    /// Older swap versions have no accumulators and are left untouched.
+    fn update_price_cumulative(
+        swap_info: &AccountInfo,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> ProgramResult {
+        let token_swap = match SwapVersion::unpack_versioned(&swap_info.data.borrow())? {
+            SwapVersion::SwapV3(mut token_swap) => {
+                token_swap.update_price_cumulative(
+                    token_a_amount,
+                    token_b_amount,
+                    Clock::get()?.unix_timestamp,
+                );
+                SwapVersion::SwapV3(token_swap)
             }
-        }
+            _ => return Ok(()),
+        };
+        SwapVersion::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+        Ok(())
     }
-}
 
-impl PrintProgramError for SwapError {
-    fn print<E>(&self)
-    where
-        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
-    {
-        match self {
-            SwapError::AlreadyInUse => msg!("Error: Swap account already in use"),
-            SwapError::InvalidProgramAddress => {
-                msg!("Error: Invalid program address generated from nonce and key")
-            }
-            SwapError::InvalidOwner => {
-                msg!("Error: The input account owner is not the program address")
-            }
-            SwapError::InvalidOutputOwner => {
-                msg!("Error: Output pool account owner cannot be the program address")
-            }
-            SwapError::ExpectedMint => msg!("Error: Deserialized account is not an SPL Token mint"),
-            SwapError::ExpectedAccount => {
-                msg!("Error: Deserialized account is not an SPL Token account")
-            }
-            SwapError::EmptySupply => msg!("Error: Input token account empty"),
-            SwapError::InvalidSupply => msg!("Error: Pool token mint has a non-zero supply"),
-            SwapError::RepeatedMint => msg!("Error: Swap input token accounts have the same mint"),
-            SwapError::InvalidDelegate => msg!("Error: Token account has a delegate"),
-            SwapError::InvalidInput => msg!("Error: InvalidInput"),
-            SwapError::IncorrectSwapAccount => {
-                msg!("Error: Address of the provided swap token account is incorrect")
-            }
-            SwapError::IncorrectPoolMint => {
-                msg!("Error: Address of the provided pool token mint is incorrect")
-            }
-            SwapError::InvalidOutput => msg!("Error: InvalidOutput"),
-            SwapError::CalculationFailure => msg!("Error: CalculationFailure"),
-            SwapError::InvalidInstruction => msg!("Error: InvalidInstruction"),
-            SwapError::ExceededSlippage => {
-                msg!("Error: Swap instruction exceeds desired slippage limit")
-            }
-            SwapError::InvalidCloseAuthority => msg!("Error: Token account has a close authority"),
-            SwapError::InvalidFreezeAuthority => {
-                msg!("Error: Pool token mint has a freeze authority")
-            }
-            SwapError::IncorrectFeeAccount => msg!("Error: Pool fee token account incorrect"),
-            SwapError::ZeroTradingTokens => {
-                msg!("Error: Given pool token amount results in zero trading tokens")
-            }
-            SwapError::FeeCalculationFailure => msg!(
-                "Error: The fee calculation failed due to overflow, underflow, or unexpected 0"
-            ),
-            SwapError::ConversionFailure => msg!("Error: Conversion to or from u64 failed."),
-            SwapError::InvalidFee => {
-                msg!("Error: The provided fee does not match the program owner's constraints")
+    /// Adds the price movement of a swap, in basis points, to the swap's
+    /// volatility.  Older swap versions have no dynamic fees.
+    fn update_volatility(swap_info: &AccountInfo, price_movement: u64) -> ProgramResult {
+        let token_swap = match SwapVersion::unpack_versioned(&swap_info.data.borrow())? {
+            SwapVersion::SwapV3(mut token_swap) => {
+                token_swap.update_volatility(price_movement, Clock::get()?.unix_timestamp);
+                SwapVersion::SwapV3(token_swap)
             }
-            SwapError::IncorrectTokenProgramId => {
-                msg!("Error: The provided token program does not match the token program expected by the swap")
+            _ => return Ok(()),
+        };
+        SwapVersion::pack(token_swap, &mut swap_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Returns the program trusted to own the oracle account of a curve priced
+    /// by an oracle, which is the stake pool program for stake pool curves,
+    /// and one of the oracle programs allowed by the swap constraints for
+    /// other curves.  Without constraints, any oracle program is allowed, like
+    /// any curve type or fee.
+    fn oracle_program_id(
+        curve_type: CurveType,
+        oracle_info: &AccountInfo,
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> Result<Pubkey, ProgramError> {
+        match (curve_type, swap_constraints) {
+            (CurveType::StakePoolStable, _) => {
+                if *oracle_info.owner != spl_stake_pool::id() {
+                    return Err(SwapError::IncorrectOracleAccount.into());
+                }
+                Ok(spl_stake_pool::id())
             }
-            SwapError::UnsupportedCurveType => {
-                msg!("Error: The provided curve type is not supported by the program owner")
+            (_, Some(swap_constraints)) => {
+                swap_constraints.validate_oracle_program_id(oracle_info.owner)?;
+                Ok(*oracle_info.owner)
             }
-            SwapError::InvalidCurve => {
-                msg!("Error: The provided curve parameters are invalid")
+            (_, None) => Ok(*oracle_info.owner),
+        }
+    }
+
+    /// Reads the price of a curve priced by an oracle from the oracle
+    /// account, or from the stake pool account for stake pool curves,
+    /// returning the curve refreshed for the given pool balances
+    fn refresh_swap_curve(
+        swap_curve: &SwapCurve,
+        oracle_info: &AccountInfo,
+        token_b_mint: &Pubkey,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<SwapCurve, ProgramError> {
+        match swap_curve.curve_type {
+            CurveType::StakePoolStable => {
+                if *oracle_info.owner != spl_stake_pool::id() {
+                    return Err(SwapError::IncorrectOracleAccount.into());
+                }
+                let stake_pool = try_from_slice_unchecked::<StakePool>(&oracle_info.data.borrow())?;
+                if !stake_pool.is_valid() || stake_pool.pool_mint != *token_b_mint {
+                    return Err(SwapError::IncorrectOracleAccount.into());
+                }
+                let mut calculator = StakePoolStableCurve::from_swap_curve(swap_curve)?;
+                calculator.refresh(
+                    stake_pool.total_stake_lamports,
+                    stake_pool.pool_token_supply,
+                );
+                Ok(SwapCurve {
+                    curve_type: swap_curve.curve_type,
+                    calculator: Box::new(calculator),
+                })
             }
-            SwapError::UnsupportedCurveOperation => {
-                msg!("Error: The operation cannot be performed on the given curve")
+            CurveType::ProactiveMarketMaking => {
+                let price = Price::unpack(&oracle_info.data.borrow())?
+                    .current_price(Clock::get()?.slot)
+                    .ok_or(SwapError::StaleOraclePrice)?;
+                let mut calculator = ProactiveMarketMakingCurve::from_swap_curve(swap_curve)?;
+                calculator
+                    .refresh(price, to_u128(token_a_amount)?, to_u128(token_b_amount)?)
+                    .ok_or(SwapError::CalculationFailure)?;
+                Ok(SwapCurve {
+                    curve_type: swap_curve.curve_type,
+                    calculator: Box::new(calculator),
+                })
             }
+            _ => Err(SwapError::UnsupportedCurveOperation.into()),
         }
     }
-}
-
-fn to_u128(val: u64) -> Result<u128, SwapError> {
-    val.try_into().map_err(|_| SwapError::ConversionFailure)
-}
 
-fn to_u64(val: u128) -> Result<u64, SwapError> {
-    val.try_into().map_err(|_| SwapError::ConversionFailure)
-}
+    /// Scales the targets of a proactive market making curve with the pool
+    /// token supply, since depositing or withdrawing all token types keeps the
+    /// pool's balances in proportion.  Other curves are left untouched.
+    fn scale_swap_curve_targets(
+        swap_info: &AccountInfo,
+        swap_curve: &SwapCurve,
+        new_pool_mint_supply: u128,
+        pool_mint_supply: u128,
+    ) -> ProgramResult {
+        if swap_curve.curve_type != CurveType::ProactiveMarketMaking || pool_mint_supply == 0 {
+            return Ok(());
+        }
+        let mut calculator = ProactiveMarketMakingCurve::from_swap_curve(swap_curve)?;
+        calculator
+            .scale_targets(new_pool_mint_supply, pool_mint_supply)
+            .ok_or(SwapError::CalculationFailure)?;
+        Self::update_swap_curve(
+            swap_info,
+            SwapCurve {
+                curve_type: swap_curve.curve_type,
+                calculator: Box::new(calculator),
+            },
+        )
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        curve::calculator::{CurveCalculator, INITIAL_SWAP_POOL_AMOUNT},
-        curve::{
-            base::CurveType, constant_price::ConstantPriceCurve,
-            constant_product::ConstantProductCurve, offset::OffsetCurve,
-        },
-        instruction::{
-            deposit_all_token_types, deposit_single_token_type_exact_amount_in, initialize, swap,
-            withdraw_all_token_types, withdraw_single_token_type_exact_amount_out,
-        },
-    };
-    use solana_program::{instruction::Instruction, program_stubs, rent::Rent};
-    use solana_sdk::account::{create_account_for_test, create_is_signer_account_infos, Account};
-    use spl_token::{
-        error::TokenError,
-        instruction::{
-            approve, initialize_account, initialize_mint, mint_to, revoke, set_authority,
-            AuthorityType,
-        },
-    };
+    /// Stores the state of a curve priced by an oracle.  Only the latest swap
+    /// version has oracles.
+    fn update_swap_curve(swap_info: &AccountInfo, swap_curve: SwapCurve) -> ProgramResult {
+        let token_swap = SwapVersion::unpack_versioned(&swap_info.data.borrow())?;
+        if let SwapVersion::SwapV3(mut token_swap) = token_swap {
+            token_swap.swap_curve = swap_curve;
+            SwapVersion::pack(
+                SwapVersion::SwapV3(token_swap),
+                &mut swap_info.data.borrow_mut(),
+            )?;
+        }
+        Ok(())
+    }
 
-    // Test program id for the swap program.
-    const SWAP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([2u8; 32]);
+    /// Checks that the pause authority signed, and that the swap is not
+    /// already in the requested state
+    fn check_set_paused(
+        pause_authority: COption<Pubkey>,
+        current_is_paused: bool,
+        is_paused: bool,
+        pause_authority_info: &AccountInfo,
+    ) -> ProgramResult {
+        if pause_authority != COption::Some(*pause_authority_info.key)
+            || !pause_authority_info.is_signer
+        {
+            return Err(SwapError::InvalidPauseAuthority.into());
+        }
+        if current_is_paused == is_paused {
+            return Err(if is_paused {
+                SwapError::SwapPaused
+            } else {
+                SwapError::SwapNotPaused
+            }
+            .into());
+        }
+        Ok(())
+    }
 
-    struct TestSyscallStubs {}
-    impl program_stubs::SyscallStubs for TestSyscallStubs {
-        fn sol_invoke_signed(
-            &self,
-            instruction: &Instruction,
-            account_infos: &[AccountInfo],
-            signers_seeds: &[&[&[u8]]],
-        ) -> ProgramResult {
-            msg!("TestSyscallStubs::sol_invoke_signed()");
+    /// Processes an [InitializeMultiToken](enum.Instruction.html).
+    pub fn process_initialize_multi_token(
+        program_id: &Pubkey,
+        nonce: u8,
+        fees: Fees,
+        swap_curve: MultiStableCurve,
+        pause_authority: COption<Pubkey>,
+        accounts: &[AccountInfo],
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let fee_account_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let token_infos = account_info_iter.as_slice();
 
-            let mut new_account_infos = vec![];
+        let token_program_id = *token_program_info.key;
+        if MultiTokenSwapVersion::is_initialized(&swap_info.data.borrow())
+            || SwapVersion::is_initialized(&swap_info.data.borrow())
+        {
+            return Err(SwapError::AlreadyInUse.into());
+        }
+        if !(MIN_MULTI_TOKENS..=MAX_MULTI_TOKENS).contains(&token_infos.len()) {
+            return Err(SwapError::InvalidTokenCount.into());
+        }
 
-            // mimic check for token program in accounts
-            if !account_infos.iter().any(|x| *x.key == spl_token::id()) {
-                return Err(ProgramError::InvalidAccountData);
+        if *authority_info.key != Self::authority_id(program_id, swap_info.key, nonce)? {
+            return Err(SwapError::InvalidProgramAddress.into());
+        }
+        let fee_account = Self::unpack_token_account(fee_account_info, &token_program_id)?;
+        let destination = Self::unpack_token_account(destination_info, &token_program_id)?;
+        let pool_mint = Self::unpack_mint(pool_mint_info, &token_program_id)?;
+        let mut token_mints: Vec<Pubkey> = Vec::with_capacity(token_infos.len());
+        for token_info in token_infos {
+            let token = Self::unpack_token_account(token_info, &token_program_id)?;
+            if *authority_info.key != token.owner {
+                return Err(SwapError::InvalidOwner.into());
             }
-
-            for meta in instruction.accounts.iter() {
-                for account_info in account_infos.iter() {
-                    if meta.pubkey == *account_info.key {
-                        let mut new_account_info = account_info.clone();
-                        for seeds in signers_seeds.iter() {
-                            let signer =
-                                Pubkey::create_program_address(seeds, &SWAP_PROGRAM_ID).unwrap();
-                            if *account_info.key == signer {
-                                new_account_info.is_signer = true;
-                            }
-                        }
-                        new_account_infos.push(new_account_info);
-                    }
-                }
+            if token_mints.contains(&token.mint) {
+                return Err(SwapError::RepeatedMint.into());
+            }
+            if token.amount == 0 {
+                return Err(SwapError::EmptySupply.into());
+            }
+            if token.delegate.is_some() {
+                return Err(SwapError::InvalidDelegate.into());
             }
+            if token.close_authority.is_some() {
+                return Err(SwapError::InvalidCloseAuthority.into());
+            }
+            token_mints.push(token.mint);
+        }
+        if *authority_info.key == destination.owner {
+            return Err(SwapError::InvalidOutputOwner.into());
+        }
+        if *authority_info.key == fee_account.owner {
+            return Err(SwapError::InvalidOutputOwner.into());
+        }
+        if COption::Some(*authority_info.key) != pool_mint.mint_authority {
+            return Err(SwapError::InvalidOwner.into());
+        }
 
-            spl_token::processor::Processor::process(
-                &instruction.program_id,
-                &new_account_infos,
-                &instruction.data,
-            )
+        if pool_mint.supply != 0 {
+            return Err(SwapError::InvalidSupply.into());
+        }
+        if pool_mint.freeze_authority.is_some() {
+            return Err(SwapError::InvalidFreezeAuthority.into());
+        }
+        if *pool_mint_info.key != fee_account.mint {
+            return Err(SwapError::IncorrectPoolMint.into());
         }
-    }
 
-    fn test_syscall_stubs() {
-        use std::sync::Once;
-        static ONCE: Once = Once::new();
+        if let Some(swap_constraints) = swap_constraints {
+            let owner_key = swap_constraints
+                .owner_key
+                .parse::<Pubkey>()
+                .map_err(|_| SwapError::InvalidOwner)?;
+            if fee_account.owner != owner_key {
+                return Err(SwapError::InvalidOwner.into());
+            }
+            swap_constraints.validate_curve_type(CurveType::Stable)?;
+            swap_constraints.validate_fees(&fees)?;
+        }
+        fees.validate()?;
+        swap_curve.validate()?;
 
-        ONCE.call_once(|| {
-            program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs {}));
-        });
-    }
+        let initial_amount = swap_curve.new_pool_supply();
 
-    struct SwapAccountInfo {
-        nonce: u8,
-        authority_key: Pubkey,
-        fees: Fees,
-        swap_curve: SwapCurve,
-        swap_key: Pubkey,
-        swap_account: Account,
-        pool_mint_key: Pubkey,
-        pool_mint_account: Account,
-        pool_fee_key: Pubkey,
-        pool_fee_account: Account,
-        pool_token_key: Pubkey,
-        pool_token_account: Account,
-        token_a_key: Pubkey,
-        token_a_account: Account,
-        token_a_mint_key: Pubkey,
-        token_a_mint_account: Account,
-        token_b_key: Pubkey,
-        token_b_account: Account,
-        token_b_mint_key: Pubkey,
-        token_b_mint_account: Account,
+        Self::token_mint_to(
+            swap_info.key,
+            token_program_info.clone(),
+            pool_mint_info.clone(),
+            destination_info.clone(),
+            authority_info.clone(),
+            nonce,
+            to_u64(initial_amount)?,
+        )?;
+
+        let obj = MultiTokenSwapVersion::MultiTokenSwapV1(MultiTokenSwapV1 {
+            is_initialized: true,
+            nonce,
+            token_program_id,
+            pool_mint: *pool_mint_info.key,
+            pool_fee_account: *fee_account_info.key,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused: false,
+            tokens: token_infos.iter().map(|info| *info.key).collect(),
+            token_mints,
+        });
+        MultiTokenSwapVersion::pack(obj, &mut swap_info.data.borrow_mut())?;
+        Ok(())
     }
 
-    impl SwapAccountInfo {
-        pub fn new(
-            user_key: &Pubkey,
-            fees: Fees,
-            swap_curve: SwapCurve,
-            token_a_amount: u64,
-            token_b_amount: u64,
-        ) -> Self {
-            let swap_key = Pubkey::new_unique();
-            let swap_account = Account::new(0, SwapVersion::LATEST_LEN, &SWAP_PROGRAM_ID);
-            let (authority_key, nonce) =
-                Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+    /// Unpacks a multi-token swap, checking the accounts used by all of its
+    /// instructions, along with the swap's token accounts, given in order.
+    /// Returns the swap and the balance of each of its token accounts.
+    fn unpack_multi_token_swap(
+        program_id: &Pubkey,
+        swap_info: &AccountInfo,
+        authority_info: &AccountInfo,
+        pool_mint_info: &AccountInfo,
+        token_program_info: &AccountInfo,
+        swap_token_infos: &[&AccountInfo],
+    ) -> Result<(MultiTokenSwapV1, Vec<u128>), ProgramError> {
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let token_swap = MultiTokenSwapVersion::unpack(&swap_info.data.borrow())?;
+        if *authority_info.key != Self::authority_id(program_id, swap_info.key, token_swap.nonce)? {
+            return Err(SwapError::InvalidProgramAddress.into());
+        }
+        if *pool_mint_info.key != token_swap.pool_mint {
+            return Err(SwapError::IncorrectPoolMint.into());
+        }
+        if *token_program_info.key != token_swap.token_program_id {
+            return Err(SwapError::IncorrectTokenProgramId.into());
+        }
+        if swap_token_infos.len() != token_swap.tokens.len() {
+            return Err(SwapError::InvalidTokenCount.into());
+        }
+        let mut swap_token_amounts = Vec::with_capacity(swap_token_infos.len());
+        for (swap_token_info, token_key) in swap_token_infos.iter().zip(token_swap.tokens.iter()) {
+            if swap_token_info.key != token_key {
+                return Err(SwapError::IncorrectSwapAccount.into());
+            }
+            let token = Self::unpack_token_account(swap_token_info, &token_swap.token_program_id)?;
+            swap_token_amounts.push(to_u128(token.amount)?);
+        }
+        Ok((token_swap, swap_token_amounts))
+    }
 
-            let (pool_mint_key, mut pool_mint_account) =
-                create_mint(&spl_token::id(), &authority_key, None);
-            let (pool_token_key, pool_token_account) = mint_token(
-                &spl_token::id(),
-                &pool_mint_key,
-                &mut pool_mint_account,
-                &authority_key,
-                user_key,
-                0,
-            );
-            let (pool_fee_key, pool_fee_account) = mint_token(
-                &spl_token::id(),
-                &pool_mint_key,
-                &mut pool_mint_account,
-                &authority_key,
-                user_key,
-                0,
-            );
-            let (token_a_mint_key, mut token_a_mint_account) =
-                create_mint(&spl_token::id(), user_key, None);
-            let (token_a_key, token_a_account) = mint_token(
-                &spl_token::id(),
-                &token_a_mint_key,
-                &mut token_a_mint_account,
-                user_key,
-                &authority_key,
-                token_a_amount,
-            );
-            let (token_b_mint_key, mut token_b_mint_account) =
-                create_mint(&spl_token::id(), user_key, None);
-            let (token_b_key, token_b_account) = mint_token(
-                &spl_token::id(),
-                &token_b_mint_key,
-                &mut token_b_mint_account,
-                user_key,
-                &authority_key,
-                token_b_amount,
-            );
+    /// Processes a [SwapMultiToken](enum.Instruction.html).
+    pub fn process_swap_multi_token(
+        program_id: &Pubkey,
+        source_index: u8,
+        destination_index: u8,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let pool_fee_account_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let remaining_infos = account_info_iter.as_slice();
 
-            SwapAccountInfo {
-                nonce,
-                authority_key,
-                fees,
-                swap_curve,
-                swap_key,
-                swap_account,
-                pool_mint_key,
-                pool_mint_account,
-                pool_fee_key,
-                pool_fee_account,
-                pool_token_key,
-                pool_token_account,
-                token_a_key,
-                token_a_account,
-                token_a_mint_key,
-                token_a_mint_account,
-                token_b_key,
-                token_b_account,
-                token_b_mint_key,
-                token_b_mint_account,
-            }
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
         }
+        let token_count = MultiTokenSwapVersion::unpack(&swap_info.data.borrow())?
+            .tokens
+            .len();
+        if remaining_infos.len() < token_count {
+            return Err(SwapError::InvalidTokenCount.into());
+        }
+        let (swap_token_infos, host_fee_account_info) = remaining_infos.split_at(token_count);
+        let host_fee_account_info = host_fee_account_info.first();
+        let swap_token_infos: Vec<&AccountInfo> = swap_token_infos.iter().collect();
 
-        pub fn initialize_swap(&mut self) -> ProgramResult {
-            do_process_instruction(
-                initialize(
-                    &SWAP_PROGRAM_ID,
-                    &spl_token::id(),
-                    &self.swap_key,
-                    &self.authority_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    &self.pool_token_key,
-                    self.nonce,
-                    self.fees.clone(),
-                    self.swap_curve.clone(),
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut Account::default(),
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut self.pool_mint_account,
-                    &mut self.pool_fee_account,
-                    &mut self.pool_token_account,
-                    &mut Account::default(),
-                ],
-            )
+        let (token_swap, swap_token_amounts) = Self::unpack_multi_token_swap(
+            program_id,
+            swap_info,
+            authority_info,
+            pool_mint_info,
+            token_program_info,
+            &swap_token_infos,
+        )?;
+        if token_swap.is_paused {
+            return Err(SwapError::SwapPaused.into());
+        }
+        let source_index = source_index as usize;
+        let destination_index = destination_index as usize;
+        let swap_source_info = swap_token_infos
+            .get(source_index)
+            .ok_or(SwapError::IncorrectSwapAccount)?;
+        let swap_destination_info = swap_token_infos
+            .get(destination_index)
+            .ok_or(SwapError::IncorrectSwapAccount)?;
+        if source_index == destination_index {
+            return Err(SwapError::InvalidInput.into());
+        }
+        if swap_source_info.key == source_info.key {
+            return Err(SwapError::InvalidInput.into());
+        }
+        if swap_destination_info.key == destination_info.key {
+            return Err(SwapError::InvalidInput.into());
+        }
+        if *pool_fee_account_info.key != token_swap.pool_fee_account {
+            return Err(SwapError::IncorrectFeeAccount.into());
         }
 
-        pub fn setup_token_accounts(
-            &mut self,
-            mint_owner: &Pubkey,
-            account_owner: &Pubkey,
-            a_amount: u64,
-            b_amount: u64,
-            pool_amount: u64,
-        ) -> (Pubkey, Account, Pubkey, Account, Pubkey, Account) {
-            let (token_a_key, token_a_account) = mint_token(
-                &spl_token::id(),
-                &self.token_a_mint_key,
-                &mut self.token_a_mint_account,
-                mint_owner,
-                account_owner,
-                a_amount,
-            );
-            let (token_b_key, token_b_account) = mint_token(
-                &spl_token::id(),
-                &self.token_b_mint_key,
-                &mut self.token_b_mint_account,
-                mint_owner,
-                account_owner,
-                b_amount,
-            );
-            let (pool_key, pool_account) = mint_token(
-                &spl_token::id(),
-                &self.pool_mint_key,
-                &mut self.pool_mint_account,
-                &self.authority_key,
-                account_owner,
-                pool_amount,
-            );
-            (
-                token_a_key,
-                token_a_account,
-                token_b_key,
-                token_b_account,
-                pool_key,
-                pool_account,
+        let pool_mint = Self::unpack_mint(pool_mint_info, &token_swap.token_program_id)?;
+        let result = token_swap
+            .swap_curve
+            .swap(
+                to_u128(amount_in)?,
+                source_index,
+                destination_index,
+                &swap_token_amounts,
+                &token_swap.fees,
             )
+            .ok_or(SwapError::ZeroTradingTokens)?;
+        if result.destination_amount_swapped < to_u128(minimum_amount_out)? {
+            return Err(SwapError::ExceededSlippage.into());
         }
 
-        fn get_token_account(&self, account_key: &Pubkey) -> &Account {
-            if *account_key == self.token_a_key {
-                return &self.token_a_account;
-            } else if *account_key == self.token_b_key {
-                return &self.token_b_account;
-            }
-            panic!("Could not find matching swap token account");
-        }
+        let mut new_swap_token_amounts = swap_token_amounts;
+        new_swap_token_amounts[source_index] = result.new_swap_source_amount;
+        new_swap_token_amounts[destination_index] = result.new_swap_destination_amount;
 
-        fn set_token_account(&mut self, account_key: &Pubkey, account: Account) {
-            if *account_key == self.token_a_key {
-                self.token_a_account = account;
-                return;
-            } else if *account_key == self.token_b_key {
-                self.token_b_account = account;
-                return;
-            }
-            panic!("Could not find matching swap token account");
-        }
+        Self::token_transfer(
+            swap_info.key,
+            token_program_info.clone(),
+            source_info.clone(),
+            (*swap_source_info).clone(),
+            user_transfer_authority_info.clone(),
+            token_swap.nonce,
+            to_u64(result.source_amount_swapped)?,
+        )?;
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn swap(
-            &mut self,
-            user_key: &Pubkey,
-            user_source_key: &Pubkey,
-            mut user_source_account: &mut Account,
-            swap_source_key: &Pubkey,
-            swap_destination_key: &Pubkey,
-            user_destination_key: &Pubkey,
-            mut user_destination_account: &mut Account,
-            amount_in: u64,
-            minimum_amount_out: u64,
-        ) -> ProgramResult {
-            let user_transfer_key = Pubkey::new_unique();
-            // approve moving from user source account
-            do_process_instruction(
-                approve(
-                    &spl_token::id(),
-                    user_source_key,
-                    &user_transfer_key,
-                    user_key,
-                    &[],
-                    amount_in,
-                )
-                .unwrap(),
-                vec![
-                    &mut user_source_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                ],
+        let mut pool_token_amount = token_swap
+            .swap_curve
+            .withdraw_single_token_type_exact_out(
+                result.owner_fee,
+                source_index,
+                &new_swap_token_amounts,
+                to_u128(pool_mint.supply)?,
+                RoundDirection::Floor,
             )
-            .unwrap();
+            .ok_or(SwapError::FeeCalculationFailure)?;
 
-            let mut swap_source_account = self.get_token_account(swap_source_key).clone();
-            let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
-
-            // perform the swap
-            do_process_instruction(
-                swap(
-                    &SWAP_PROGRAM_ID,
-                    &spl_token::id(),
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_key,
-                    user_source_key,
-                    swap_source_key,
-                    swap_destination_key,
-                    user_destination_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    None,
-                    Swap {
-                        amount_in,
-                        minimum_amount_out,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                    &mut user_source_account,
-                    &mut swap_source_account,
-                    &mut swap_destination_account,
-                    &mut user_destination_account,
-                    &mut self.pool_mint_account,
-                    &mut self.pool_fee_account,
-                    &mut Account::default(),
-                ],
+        if pool_token_amount > 0 {
+            // Allow error to fall through
+            if let Some(host_fee_account_info) = host_fee_account_info {
+                let host_fee_account = Self::unpack_token_account(
+                    host_fee_account_info,
+                    &token_swap.token_program_id,
+                )?;
+                if *pool_mint_info.key != host_fee_account.mint {
+                    return Err(SwapError::IncorrectPoolMint.into());
+                }
+                let host_fee = token_swap
+                    .fees
+                    .host_fee(pool_token_amount)
+                    .ok_or(SwapError::FeeCalculationFailure)?;
+                if host_fee > 0 {
+                    pool_token_amount = pool_token_amount
+                        .checked_sub(host_fee)
+                        .ok_or(SwapError::FeeCalculationFailure)?;
+                    Self::token_mint_to(
+                        swap_info.key,
+                        token_program_info.clone(),
+                        pool_mint_info.clone(),
+                        host_fee_account_info.clone(),
+                        authority_info.clone(),
+                        token_swap.nonce,
+                        to_u64(host_fee)?,
+                    )?;
+                }
+            }
+            Self::token_mint_to(
+                swap_info.key,
+                token_program_info.clone(),
+                pool_mint_info.clone(),
+                pool_fee_account_info.clone(),
+                authority_info.clone(),
+                token_swap.nonce,
+                to_u64(pool_token_amount)?,
             )?;
+        }
 
-            self.set_token_account(swap_source_key, swap_source_account);
-            self.set_token_account(swap_destination_key, swap_destination_account);
+        Self::token_transfer(
+            swap_info.key,
+            token_program_info.clone(),
+            (*swap_destination_info).clone(),
+            destination_info.clone(),
+            authority_info.clone(),
+            token_swap.nonce,
+            to_u64(result.destination_amount_swapped)?,
+        )?;
+        Ok(())
+    }
 
-            Ok(())
+    /// Processes a [DepositAllMultiToken](enum.Instruction.html).
+    pub fn process_deposit_all_multi_token(
+        program_id: &Pubkey,
+        pool_token_amount: u64,
+        maximum_token_amounts: &[u64],
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let dest_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let token_infos = account_info_iter.as_slice();
+        if token_infos.len() % 2 != 0 {
+            return Err(SwapError::InvalidTokenCount.into());
         }
+        let source_infos: Vec<&AccountInfo> = token_infos.iter().step_by(2).collect();
+        let swap_token_infos: Vec<&AccountInfo> = token_infos.iter().skip(1).step_by(2).collect();
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn deposit_all_token_types(
-            &mut self,
-            depositor_key: &Pubkey,
-            depositor_token_a_key: &Pubkey,
-            mut depositor_token_a_account: &mut Account,
-            depositor_token_b_key: &Pubkey,
-            mut depositor_token_b_account: &mut Account,
-            depositor_pool_key: &Pubkey,
-            mut depositor_pool_account: &mut Account,
-            pool_token_amount: u64,
-            maximum_token_a_amount: u64,
-            maximum_token_b_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority = Pubkey::new_unique();
-            do_process_instruction(
-                approve(
-                    &spl_token::id(),
-                    depositor_token_a_key,
-                    &user_transfer_authority,
-                    depositor_key,
-                    &[],
-                    maximum_token_a_amount,
-                )
-                .unwrap(),
-                vec![
-                    &mut depositor_token_a_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                ],
-            )
-            .unwrap();
+        let (token_swap, swap_token_amounts) = Self::unpack_multi_token_swap(
+            program_id,
+            swap_info,
+            authority_info,
+            pool_mint_info,
+            token_program_info,
+            &swap_token_infos,
+        )?;
+        if token_swap.is_paused {
+            return Err(SwapError::SwapPaused.into());
+        }
+        if maximum_token_amounts.len() != swap_token_amounts.len() {
+            return Err(SwapError::InvalidTokenCount.into());
+        }
+        for (source_info, swap_token_info) in source_infos.iter().zip(swap_token_infos.iter()) {
+            if source_info.key == swap_token_info.key {
+                return Err(SwapError::InvalidInput.into());
+            }
+        }
 
-            do_process_instruction(
-                approve(
-                    &spl_token::id(),
-                    depositor_token_b_key,
-                    &user_transfer_authority,
-                    depositor_key,
-                    &[],
-                    maximum_token_b_amount,
-                )
-                .unwrap(),
-                vec![
-                    &mut depositor_token_b_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                ],
-            )
-            .unwrap();
+        let calculator = &token_swap.swap_curve;
+        let pool_mint = Self::unpack_mint(pool_mint_info, &token_swap.token_program_id)?;
+        let current_pool_mint_supply = to_u128(pool_mint.supply)?;
+        let (pool_token_amount, pool_mint_supply) = if current_pool_mint_supply > 0 {
+            (to_u128(pool_token_amount)?, current_pool_mint_supply)
+        } else {
+            (calculator.new_pool_supply(), calculator.new_pool_supply())
+        };
 
-            do_process_instruction(
-                deposit_all_token_types(
-                    &SWAP_PROGRAM_ID,
-                    &spl_token::id(),
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority,
-                    depositor_token_a_key,
-                    depositor_token_b_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    &self.pool_mint_key,
-                    depositor_pool_key,
-                    DepositAllTokenTypes {
-                        pool_token_amount,
-                        maximum_token_a_amount,
-                        maximum_token_b_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                    &mut depositor_token_a_account,
-                    &mut depositor_token_b_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut self.pool_mint_account,
-                    &mut depositor_pool_account,
-                    &mut Account::default(),
-                ],
+        let results = calculator
+            .pool_tokens_to_trading_tokens(
+                pool_token_amount,
+                pool_mint_supply,
+                &swap_token_amounts,
+                RoundDirection::Ceiling,
             )
+            .ok_or(SwapError::ZeroTradingTokens)?;
+        let mut token_amounts = Vec::with_capacity(results.len());
+        for (token_amount, maximum_token_amount) in results.iter().zip(maximum_token_amounts) {
+            let token_amount = to_u64(*token_amount)?;
+            if token_amount > *maximum_token_amount {
+                return Err(SwapError::ExceededSlippage.into());
+            }
+            if token_amount == 0 {
+                return Err(SwapError::ZeroTradingTokens.into());
+            }
+            token_amounts.push(token_amount);
         }
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn withdraw_all_token_types(
-            &mut self,
-            user_key: &Pubkey,
-            pool_key: &Pubkey,
-            mut pool_account: &mut Account,
-            token_a_key: &Pubkey,
-            mut token_a_account: &mut Account,
-            token_b_key: &Pubkey,
-            mut token_b_account: &mut Account,
-            pool_token_amount: u64,
-            minimum_token_a_amount: u64,
-            minimum_token_b_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority_key = Pubkey::new_unique();
-            // approve user transfer authority to take out pool tokens
-            do_process_instruction(
-                approve(
-                    &spl_token::id(),
-                    pool_key,
-                    &user_transfer_authority_key,
-                    user_key,
-                    &[],
-                    pool_token_amount,
-                )
-                .unwrap(),
-                vec![
-                    &mut pool_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                ],
-            )
-            .unwrap();
+        let pool_token_amount = to_u64(pool_token_amount)?;
 
-            // withdraw token a and b correctly
-            do_process_instruction(
-                withdraw_all_token_types(
-                    &SWAP_PROGRAM_ID,
-                    &spl_token::id(),
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    pool_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    token_a_key,
-                    token_b_key,
-                    WithdrawAllTokenTypes {
-                        pool_token_amount,
-                        minimum_token_a_amount,
-                        minimum_token_b_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                    &mut self.pool_mint_account,
-                    &mut pool_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut token_a_account,
-                    &mut token_b_account,
-                    &mut self.pool_fee_account,
-                    &mut Account::default(),
-                ],
-            )
+        for ((source_info, swap_token_info), token_amount) in source_infos
+            .iter()
+            .zip(swap_token_infos.iter())
+            .zip(token_amounts)
+        {
+            Self::token_transfer(
+                swap_info.key,
+                token_program_info.clone(),
+                (*source_info).clone(),
+                (*swap_token_info).clone(),
+                user_transfer_authority_info.clone(),
+                token_swap.nonce,
+                token_amount,
+            )?;
         }
+        Self::token_mint_to(
+            swap_info.key,
+            token_program_info.clone(),
+            pool_mint_info.clone(),
+            dest_info.clone(),
+            authority_info.clone(),
+            token_swap.nonce,
+            pool_token_amount,
+        )?;
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn deposit_single_token_type_exact_amount_in(
-            &mut self,
-            depositor_key: &Pubkey,
-            deposit_account_key: &Pubkey,
-            mut deposit_token_account: &mut Account,
-            deposit_pool_key: &Pubkey,
-            mut deposit_pool_account: &mut Account,
-            source_token_amount: u64,
-            minimum_pool_token_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority_key = Pubkey::new_unique();
-            do_process_instruction(
-                approve(
-                    &spl_token::id(),
-                    deposit_account_key,
-                    &user_transfer_authority_key,
-                    depositor_key,
-                    &[],
-                    source_token_amount,
-                )
-                .unwrap(),
-                vec![
-                    &mut deposit_token_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                ],
-            )
-            .unwrap();
+        Ok(())
+    }
 
-            do_process_instruction(
-                deposit_single_token_type_exact_amount_in(
-                    &SWAP_PROGRAM_ID,
-                    &spl_token::id(),
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority_key,
-                    deposit_account_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    &self.pool_mint_key,
-                    deposit_pool_key,
-                    DepositSingleTokenTypeExactAmountIn {
-                        source_token_amount,
-                        minimum_pool_token_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                    &mut deposit_token_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut self.pool_mint_account,
-                    &mut deposit_pool_account,
-                    &mut Account::default(),
-                ],
-            )
+    /// Processes a [WithdrawAllMultiToken](enum.Instruction.html).
+    pub fn process_withdraw_all_multi_token(
+        program_id: &Pubkey,
+        pool_token_amount: u64,
+        minimum_token_amounts: &[u64],
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let pool_fee_account_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let token_infos = account_info_iter.as_slice();
+        if token_infos.len() % 2 != 0 {
+            return Err(SwapError::InvalidTokenCount.into());
         }
+        let swap_token_infos: Vec<&AccountInfo> = token_infos.iter().step_by(2).collect();
+        let dest_infos: Vec<&AccountInfo> = token_infos.iter().skip(1).step_by(2).collect();
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn withdraw_single_token_type_exact_amount_out(
-            &mut self,
-            user_key: &Pubkey,
-            pool_key: &Pubkey,
-            mut pool_account: &mut Account,
-            destination_key: &Pubkey,
-            mut destination_account: &mut Account,
-            destination_token_amount: u64,
-            maximum_pool_token_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority_key = Pubkey::new_unique();
-            // approve user transfer authority to take out pool tokens
-            do_process_instruction(
-                approve(
-                    &spl_token::id(),
-                    pool_key,
-                    &user_transfer_authority_key,
-                    user_key,
-                    &[],
-                    maximum_pool_token_amount,
-                )
-                .unwrap(),
-                vec![
-                    &mut pool_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                ],
-            )
-            .unwrap();
+        let (token_swap, swap_token_amounts) = Self::unpack_multi_token_swap(
+            program_id,
+            swap_info,
+            authority_info,
+            pool_mint_info,
+            token_program_info,
+            &swap_token_infos,
+        )?;
+        if minimum_token_amounts.len() != swap_token_amounts.len() {
+            return Err(SwapError::InvalidTokenCount.into());
+        }
+        for (swap_token_info, dest_info) in swap_token_infos.iter().zip(dest_infos.iter()) {
+            if swap_token_info.key == dest_info.key {
+                return Err(SwapError::InvalidInput.into());
+            }
+        }
+        if *pool_fee_account_info.key != token_swap.pool_fee_account {
+            return Err(SwapError::IncorrectFeeAccount.into());
+        }
 
-            do_process_instruction(
-                withdraw_single_token_type_exact_amount_out(
-                    &SWAP_PROGRAM_ID,
-                    &spl_token::id(),
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    pool_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    destination_key,
-                    WithdrawSingleTokenTypeExactAmountOut {
-                        destination_token_amount,
-                        maximum_pool_token_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut Account::default(),
-                    &mut Account::default(),
-                    &mut self.pool_mint_account,
-                    &mut pool_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut destination_account,
-                    &mut self.pool_fee_account,
-                    &mut Account::default(),
-                ],
+        let pool_mint = Self::unpack_mint(pool_mint_info, &token_swap.token_program_id)?;
+
+        let withdraw_fee: u128 = if *pool_fee_account_info.key == *source_info.key {
+            // withdrawing from the fee account, don't assess withdraw fee
+            0
+        } else {
+            token_swap
+                .fees
+                .owner_withdraw_fee(to_u128(pool_token_amount)?)
+                .ok_or(SwapError::FeeCalculationFailure)?
+        };
+        let pool_token_amount = to_u128(pool_token_amount)?
+            .checked_sub(withdraw_fee)
+            .ok_or(SwapError::CalculationFailure)?;
+
+        let results = token_swap
+            .swap_curve
+            .pool_tokens_to_trading_tokens(
+                pool_token_amount,
+                to_u128(pool_mint.supply)?,
+                &swap_token_amounts,
+                RoundDirection::Floor,
             )
+            .ok_or(SwapError::ZeroTradingTokens)?;
+        let mut token_amounts = Vec::with_capacity(results.len());
+        for ((token_amount, swap_token_amount), minimum_token_amount) in results
+            .iter()
+            .zip(swap_token_amounts.iter())
+            .zip(minimum_token_amounts)
+        {
+            let swap_token_amount = to_u64(*swap_token_amount)?;
+            let token_amount = std::cmp::min(swap_token_amount, to_u64(*token_amount)?);
+            if token_amount < *minimum_token_amount {
+                return Err(SwapError::ExceededSlippage.into());
+            }
+            if token_amount == 0 && swap_token_amount != 0 {
+                return Err(SwapError::ZeroTradingTokens.into());
+            }
+            token_amounts.push(token_amount);
         }
-    }
 
-    fn mint_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len())
+        if withdraw_fee > 0 {
+            Self::token_transfer(
+                swap_info.key,
+                token_program_info.clone(),
+                source_info.clone(),
+                pool_fee_account_info.clone(),
+                user_transfer_authority_info.clone(),
+                token_swap.nonce,
+                to_u64(withdraw_fee)?,
+            )?;
+        }
+        Self::token_burn(
+            swap_info.key,
+            token_program_info.clone(),
+            source_info.clone(),
+            pool_mint_info.clone(),
+            user_transfer_authority_info.clone(),
+            token_swap.nonce,
+            to_u64(pool_token_amount)?,
+        )?;
+
+        for ((swap_token_info, dest_info), token_amount) in swap_token_infos
+            .iter()
+            .zip(dest_infos.iter())
+            .zip(token_amounts)
+        {
+            if token_amount > 0 {
+                Self::token_transfer(
+                    swap_info.key,
+                    token_program_info.clone(),
+                    (*swap_token_info).clone(),
+                    (*dest_info).clone(),
+                    authority_info.clone(),
+                    token_swap.nonce,
+                    token_amount,
+                )?;
+            }
+        }
+        Ok(())
     }
 
-    fn account_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(spl_token::state::Account::get_packed_len())
+    /// Processes an [Instruction](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        Self::process_with_constraints(program_id, accounts, input, &SWAP_CONSTRAINTS)
     }
 
-    fn do_process_instruction_with_fee_constraints(
-        instruction: Instruction,
-        accounts: Vec<&mut Account>,
+    /// Processes an instruction given extra constraint
+    pub fn process_with_constraints(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        input: &[u8],
         swap_constraints: &Option<SwapConstraints>,
     ) -> ProgramResult {
-        test_syscall_stubs();
-
-        // approximate the logic in the actual runtime which runs the instruction
-        // and only updates accounts if the instruction is successful
-        let mut account_clones = accounts.iter().map(|x| (*x).clone()).collect::<Vec<_>>();
-        let mut meta = instruction
-            .accounts
-            .iter()
-            .zip(account_clones.iter_mut())
-            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
-            .collect::<Vec<_>>();
-        let mut account_infos = create_is_signer_account_infos(&mut meta);
-        let res = if instruction.program_id == SWAP_PROGRAM_ID {
-            Processor::process_with_constraints(
-                &instruction.program_id,
-                &account_infos,
-                &instruction.data,
-                swap_constraints,
-            )
-        } else {
-            spl_token::processor::Processor::process(
-                &instruction.program_id,
-                &account_infos,
-                &instruction.data,
-            )
-        };
-
-        if res.is_ok() {
-            let mut account_metas = instruction
-                .accounts
-                .iter()
-                .zip(accounts)
-                .map(|(account_meta, account)| (&account_meta.pubkey, account))
-                .collect::<Vec<_>>();
-            for account_info in account_infos.iter_mut() {
-                for account_meta in account_metas.iter_mut() {
-                    if account_info.key == account_meta.0 {
-                        let account = &mut account_meta.1;
-                        account.owner = *account_info.owner;
-                        account.lamports = **account_info.lamports.borrow();
-                        account.data = account_info.data.borrow().to_vec();
-                    }
-                }
+        let instruction = SwapInstruction::unpack(input)?;
+        match instruction {
+            SwapInstruction::Initialize(Initialize {
+                nonce,
+                fees,
+                swap_curve,
+                pause_authority,
+                dynamic_fees,
+            }) => {
+                msg!("Instruction: Init");
+                Self::process_initialize(
+                    program_id,
+                    nonce,
+                    fees,
+                    swap_curve,
+                    pause_authority,
+                    dynamic_fees,
+                    accounts,
+                    swap_constraints,
+                )
+            }
+            SwapInstruction::Swap(Swap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                msg!("Instruction: Swap");
+                Self::process_swap(program_id, amount_in, minimum_amount_out, accounts)
+            }
+            SwapInstruction::RouteSwap(RouteSwap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                msg!("Instruction: RouteSwap");
+                Self::process_route_swap(program_id, amount_in, minimum_amount_out, accounts)
+            }
+            SwapInstruction::SwapExactAmountOut(SwapExactAmountOut {
+                amount_out,
+                maximum_amount_in,
+            }) => {
+                msg!("Instruction: SwapExactAmountOut");
+                Self::process_swap_exact_amount_out(
+                    program_id,
+                    amount_out,
+                    maximum_amount_in,
+                    accounts,
+                )
+            }
+            SwapInstruction::DepositAllTokenTypes(DepositAllTokenTypes {
+                pool_token_amount,
+                maximum_token_a_amount,
+                maximum_token_b_amount,
+            }) => {
+                msg!("Instruction: DepositAllTokenTypes");
+                Self::process_deposit_all_token_types(
+                    program_id,
+                    pool_token_amount,
+                    maximum_token_a_amount,
+                    maximum_token_b_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::WithdrawAllTokenTypes(WithdrawAllTokenTypes {
+                pool_token_amount,
+                minimum_token_a_amount,
+                minimum_token_b_amount,
+            }) => {
+                msg!("Instruction: WithdrawAllTokenTypes");
+                Self::process_withdraw_all_token_types(
+                    program_id,
+                    pool_token_amount,
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::DepositSingleTokenTypeExactAmountIn(
+                DepositSingleTokenTypeExactAmountIn {
+                    source_token_amount,
+                    minimum_pool_token_amount,
+                },
+            ) => {
+                msg!("Instruction: DepositSingleTokenTypeExactAmountIn");
+                Self::process_deposit_single_token_type_exact_amount_in(
+                    program_id,
+                    source_token_amount,
+                    minimum_pool_token_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::WithdrawSingleTokenTypeExactAmountOut(
+                WithdrawSingleTokenTypeExactAmountOut {
+                    destination_token_amount,
+                    maximum_pool_token_amount,
+                },
+            ) => {
+                msg!("Instruction: WithdrawSingleTokenTypeExactAmountOut");
+                Self::process_withdraw_single_token_type_exact_amount_out(
+                    program_id,
+                    destination_token_amount,
+                    maximum_pool_token_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::Pause => {
+                msg!("Instruction: Pause");
+                Self::process_set_paused(program_id, true, accounts)
+            }
+            SwapInstruction::Unpause => {
+                msg!("Instruction: Unpause");
+                Self::process_set_paused(program_id, false, accounts)
+            }
+            SwapInstruction::InitializeMultiToken(InitializeMultiToken {
+                nonce,
+                fees,
+                swap_curve,
+                pause_authority,
+            }) => {
+                msg!("Instruction: InitializeMultiToken");
+                Self::process_initialize_multi_token(
+                    program_id,
+                    nonce,
+                    fees,
+                    swap_curve,
+                    pause_authority,
+                    accounts,
+                    swap_constraints,
+                )
+            }
+            SwapInstruction::SwapMultiToken(SwapMultiToken {
+                source_index,
+                destination_index,
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                msg!("Instruction: SwapMultiToken");
+                Self::process_swap_multi_token(
+                    program_id,
+                    source_index,
+                    destination_index,
+                    amount_in,
+                    minimum_amount_out,
+                    accounts,
+                )
+            }
+            SwapInstruction::DepositAllMultiToken(DepositAllMultiToken {
+                pool_token_amount,
+                maximum_token_amounts,
+            }) => {
+                msg!("Instruction: DepositAllMultiToken");
+                Self::process_deposit_all_multi_token(
+                    program_id,
+                    pool_token_amount,
+                    &maximum_token_amounts,
+                    accounts,
+                )
+            }
+            SwapInstruction::WithdrawAllMultiToken(WithdrawAllMultiToken {
+                pool_token_amount,
+                minimum_token_amounts,
+            }) => {
+                msg!("Instruction: WithdrawAllMultiToken");
+                Self::process_withdraw_all_multi_token(
+                    program_id,
+                    pool_token_amount,
+                    &minimum_token_amounts,
+                    accounts,
+                )
             }
         }
-        res
     }
+}
 
-    fn do_process_instruction(
-        instruction: Instruction,
-        accounts: Vec<&mut Account>,
-    ) -> ProgramResult {
-        do_process_instruction_with_fee_constraints(instruction, accounts, &SWAP_CONSTRAINTS)
+impl PrintProgramError for SwapError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        match self {
+            SwapError::AlreadyInUse => msg!("Error: Swap account already in use"),
+            SwapError::InvalidProgramAddress => {
+                msg!("Error: Invalid program address generated from nonce and key")
+            }
+            SwapError::InvalidOwner => {
+                msg!("Error: The input account owner is not the program address")
+            }
+            SwapError::InvalidOutputOwner => {
+                msg!("Error: Output pool account owner cannot be the program address")
+            }
+            SwapError::ExpectedMint => msg!("Error: Deserialized account is not an SPL Token mint"),
+            SwapError::ExpectedAccount => {
+                msg!("Error: Deserialized account is not an SPL Token account")
+            }
+            SwapError::EmptySupply => msg!("Error: Input token account empty"),
+            SwapError::InvalidSupply => msg!("Error: Pool token mint has a non-zero supply"),
+            SwapError::RepeatedMint => msg!("Error: Swap input token accounts have the same mint"),
+            SwapError::InvalidDelegate => msg!("Error: Token account has a delegate"),
+            SwapError::InvalidInput => msg!("Error: InvalidInput"),
+            SwapError::IncorrectSwapAccount => {
+                msg!("Error: Address of the provided swap token account is incorrect")
+            }
+            SwapError::IncorrectPoolMint => {
+                msg!("Error: Address of the provided pool token mint is incorrect")
+            }
+            SwapError::InvalidOutput => msg!("Error: InvalidOutput"),
+            SwapError::CalculationFailure => msg!("Error: CalculationFailure"),
+            SwapError::InvalidInstruction => msg!("Error: InvalidInstruction"),
+            SwapError::ExceededSlippage => {
+                msg!("Error: Swap instruction exceeds desired slippage limit")
+            }
+            SwapError::InvalidCloseAuthority => msg!("Error: Token account has a close authority"),
+            SwapError::InvalidFreezeAuthority => {
+                msg!("Error: Pool token mint has a freeze authority")
+            }
+            SwapError::IncorrectFeeAccount => msg!("Error: Pool fee token account incorrect"),
+            SwapError::ZeroTradingTokens => {
+                msg!("Error: Given pool token amount results in zero trading tokens")
+            }
+            SwapError::FeeCalculationFailure => msg!(
+                "Error: The fee calculation failed due to overflow, underflow, or unexpected 0"
+            ),
+            SwapError::ConversionFailure => msg!("Error: Conversion to or from u64 failed."),
+            SwapError::InvalidFee => {
+                msg!("Error: The provided fee does not match the program owner's constraints")
+            }
+            SwapError::IncorrectTokenProgramId => {
+                msg!("Error: The provided token program does not match the token program expected by the swap")
+            }
+            SwapError::UnsupportedCurveType => {
+                msg!("Error: The provided curve type is not supported by the program owner")
+            }
+            SwapError::InvalidCurve => {
+                msg!("Error: The provided curve parameters are invalid")
+            }
+            SwapError::UnsupportedCurveOperation => {
+                msg!("Error: The operation cannot be performed on the given curve")
+            }
+            SwapError::InvalidRoute => {
+                msg!("Error: The route is empty, has too many pools, or is missing pool accounts")
+            }
+            SwapError::InvalidPauseAuthority => {
+                msg!("Error: The pause authority is missing, incorrect, or did not sign")
+            }
+            SwapError::SwapPaused => {
+                msg!("Error: The swap is paused, so swaps and deposits are not allowed")
+            }
+            SwapError::SwapNotPaused => msg!("Error: The swap is not paused"),
+            SwapError::InvalidTokenCount => {
+                msg!("Error: The number of tokens does not match the swap or is not supported")
+            }
+            SwapError::IncorrectOracleAccount => {
+                msg!("Error: The oracle account is missing or does not match the swap")
+            }
+            SwapError::UnsupportedPriceRange => {
+                msg!("Error: The price range of the curve is not allowed by the program owner")
+            }
+            SwapError::StaleOraclePrice => {
+                msg!("Error: The price given by the oracle is too old to be used")
+            }
+        }
     }
+}
 
-    fn mint_token(
-        program_id: &Pubkey,
-        mint_key: &Pubkey,
-        mut mint_account: &mut Account,
-        mint_authority_key: &Pubkey,
-        account_owner_key: &Pubkey,
-        amount: u64,
-    ) -> (Pubkey, Account) {
-        let account_key = Pubkey::new_unique();
-        let mut account_account = Account::new(
-            account_minimum_balance(),
-            spl_token::state::Account::get_packed_len(),
-            program_id,
-        );
-        let mut mint_authority_account = Account::default();
-        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
-
-        do_process_instruction(
-            initialize_account(program_id, &account_key, mint_key, account_owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut mint_authority_account,
-                &mut rent_sysvar_account,
-            ],
-        )
-        .unwrap();
+fn to_u128(val: u64) -> Result<u128, SwapError> {
+    val.try_into().map_err(|_| SwapError::ConversionFailure)
+}
 
-        if amount > 0 {
-            do_process_instruction(
-                mint_to(
-                    program_id,
-                    mint_key,
-                    &account_key,
-                    mint_authority_key,
-                    &[],
-                    amount,
-                )
-                .unwrap(),
-                vec![
-                    &mut mint_account,
-                    &mut account_account,
-                    &mut mint_authority_account,
-                ],
-            )
-            .unwrap();
-        }
+fn to_u64(val: u128) -> Result<u64, SwapError> {
+    val.try_into().map_err(|_| SwapError::ConversionFailure)
+}
 
-        (account_key, account_account)
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        curve::calculator::{CurveCalculator, INITIAL_SWAP_POOL_AMOUNT},
+        curve::{
+            concentrated_liquidity::{ConcentratedLiquidityCurve, PRICE_PRECISION},
+            constant_price::ConstantPriceCurve,
+            constant_product::ConstantProductCurve,
+            offset::OffsetCurve,
+            proactive_market_making::K_PRECISION,
+        },
+        instruction::{
+            deposit_all_multi_token, deposit_all_token_types,
+            deposit_single_token_type_exact_amount_in, initialize, initialize_multi_token, pause,
+            route_swap, swap, swap_exact_amount_out, swap_multi_token, unpause,
+            withdraw_all_multi_token, withdraw_all_token_types,
+            withdraw_single_token_type_exact_amount_out, RouteSwapHop,
+        },
+        oracle::{MAX_PRICE_AGE, PRICE_SCALE},
+    };
+    use borsh::BorshSerialize;
+    use solana_program::{
+        clock::{Slot, UnixTimestamp},
+        entrypoint::SUCCESS,
+        instruction::Instruction,
+        program_stubs,
+        rent::Rent,
+    };
+    use solana_sdk::account::{create_account_for_test, create_is_signer_account_infos, Account};
+    use spl_stake_pool::state::AccountType;
+    use spl_token::{
+        error::TokenError,
+        instruction::{
+            approve, initialize_account, initialize_mint, mint_to, revoke, set_authority,
+            AuthorityType,
+        },
+    };
+    use std::cell::Cell;
 
-    fn create_mint(
-        program_id: &Pubkey,
-        authority_key: &Pubkey,
-        freeze_authority: Option<&Pubkey>,
-    ) -> (Pubkey, Account) {
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account = Account::new(
-            mint_minimum_balance(),
-            spl_token::state::Mint::get_packed_len(),
-            program_id,
-        );
-        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
+    // Test program id for the swap program.
+    const SWAP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([2u8; 32]);
 
-        do_process_instruction(
-            initialize_mint(program_id, &mint_key, authority_key, freeze_authority, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar_account],
-        )
-        .unwrap();
+    // Test program id for the oracle program.
+    const ORACLE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([3u8; 32]);
 
-        (mint_key, mint_account)
+    thread_local! {
+        // Unix timestamp of the clock sysvar seen by the current test
+        static UNIX_TIMESTAMP: Cell<UnixTimestamp> = Cell::new(UnixTimestamp::default());
+        // Slot of the clock sysvar seen by the current test
+        static SLOT: Cell<Slot> = Cell::new(Slot::default());
     }
 
-    #[test]
-    fn test_token_program_id_error() {
-        test_syscall_stubs();
-        let swap_key = Pubkey::new_unique();
-        let mut mint = (Pubkey::new_unique(), Account::default());
-        let mut destination = (Pubkey::new_unique(), Account::default());
-        let token_program = (spl_token::id(), Account::default());
-        let (authority_key, nonce) =
-            Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
-        let mut authority = (authority_key, Account::default());
-        let swap_bytes = swap_key.to_bytes();
-        let authority_signature_seeds = [&swap_bytes[..32], &[nonce]];
-        let signers = &[&authority_signature_seeds[..]];
-        let ix = mint_to(
-            &token_program.0,
-            &mint.0,
-            &destination.0,
-            &authority.0,
-            &[],
-            10,
-        )
-        .unwrap();
-        let mint = (&mut mint).into();
-        let destination = (&mut destination).into();
-        let authority = (&mut authority).into();
+    fn set_unix_timestamp(unix_timestamp: UnixTimestamp) {
+        UNIX_TIMESTAMP.with(|t| t.set(unix_timestamp));
+    }
 
-        let err = invoke_signed(&ix, &[mint, destination, authority], signers).unwrap_err();
-        assert_eq!(err, ProgramError::InvalidAccountData);
+    fn set_slot(slot: Slot) {
+        SLOT.with(|s| s.set(slot));
     }
 
-    #[test]
-    fn test_initialize() {
-        let user_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+    struct TestSyscallStubs {}
+    impl program_stubs::SyscallStubs for TestSyscallStubs {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe {
+                *(var_addr as *mut Clock) = Clock {
+                    slot: SLOT.with(|s| s.get()),
+                    unix_timestamp: UNIX_TIMESTAMP.with(|t| t.get()),
+                    ..Clock::default()
+                };
+            }
+            SUCCESS
+        }
 
-        let token_a_amount = 1000;
-        let token_b_amount = 2000;
-        let pool_token_amount = 10;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Box::new(ConstantProductCurve {}),
-        };
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            msg!("TestSyscallStubs::sol_invoke_signed()");
 
-        let mut accounts =
-            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+            let mut new_account_infos = vec![];
 
-        // wrong nonce for authority_key
-        {
-            let old_nonce = accounts.nonce;
-            accounts.nonce = old_nonce - 1;
-            assert_eq!(
-                Err(SwapError::InvalidProgramAddress.into()),
-                accounts.initialize_swap()
-            );
-            accounts.nonce = old_nonce;
-        }
+            // mimic check for token program in accounts
+            if !account_infos.iter().any(|x| *x.key == spl_token::id()) {
+                return Err(ProgramError::InvalidAccountData);
+            }
 
-        // uninitialized token a account
-        {
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = Account::new(0, 0, &spl_token::id());
-            assert_eq!(
-                Err(SwapError::ExpectedAccount.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_a_account = old_account;
-        }
+            for meta in instruction.accounts.iter() {
+                for account_info in account_infos.iter() {
+                    if meta.pubkey == *account_info.key {
+                        let mut new_account_info = account_info.clone();
+                        for seeds in signers_seeds.iter() {
+                            let signer =
+                                Pubkey::create_program_address(seeds, &SWAP_PROGRAM_ID).unwrap();
+                            if *account_info.key == signer {
+                                new_account_info.is_signer = true;
+                            }
+                        }
+                        new_account_infos.push(new_account_info);
+                    }
+                }
+            }
 
-        // uninitialized token b account
-        {
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = Account::new(0, 0, &spl_token::id());
-            assert_eq!(
-                Err(SwapError::ExpectedAccount.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
+            spl_token::processor::Processor::process(
+                &instruction.program_id,
+                &new_account_infos,
+                &instruction.data,
+            )
         }
+    }
 
-        // uninitialized pool mint
-        {
-            let old_account = accounts.pool_mint_account;
-            accounts.pool_mint_account = Account::new(0, 0, &spl_token::id());
-            assert_eq!(
-                Err(SwapError::ExpectedMint.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_mint_account = old_account;
-        }
+    fn test_syscall_stubs() {
+        use std::sync::Once;
+        static ONCE: Once = Once::new();
 
-        // token A account owner is not swap authority
-        {
-            let (_token_a_key, token_a_account) = mint_token(
+        ONCE.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs {}));
+        });
+    }
+
+    struct SwapAccountInfo {
+        nonce: u8,
+        authority_key: Pubkey,
+        fees: Fees,
+        swap_curve: SwapCurve,
+        swap_key: Pubkey,
+        swap_account: Account,
+        pool_mint_key: Pubkey,
+        pool_mint_account: Account,
+        pool_fee_key: Pubkey,
+        pool_fee_account: Account,
+        pool_token_key: Pubkey,
+        pool_token_account: Account,
+        token_a_key: Pubkey,
+        token_a_account: Account,
+        token_a_mint_key: Pubkey,
+        token_a_mint_account: Account,
+        token_b_key: Pubkey,
+        token_b_account: Account,
+        token_b_mint_key: Pubkey,
+        token_b_mint_account: Account,
+        pause_authority_key: Pubkey,
+        dynamic_fees: DynamicFees,
+        oracle_key: Pubkey,
+        oracle_account: Account,
+    }
+
+    impl SwapAccountInfo {
+        pub fn new(
+            user_key: &Pubkey,
+            fees: Fees,
+            swap_curve: SwapCurve,
+            token_a_amount: u64,
+            token_b_amount: u64,
+        ) -> Self {
+            let swap_key = Pubkey::new_unique();
+            let swap_account = Account::new(0, SwapVersion::LATEST_LEN, &SWAP_PROGRAM_ID);
+            let (authority_key, nonce) =
+                Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+
+            let (pool_mint_key, mut pool_mint_account) =
+                create_mint(&spl_token::id(), &authority_key, None);
+            let (pool_token_key, pool_token_account) = mint_token(
                 &spl_token::id(),
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &user_key,
+                &pool_mint_key,
+                &mut pool_mint_account,
+                &authority_key,
+                user_key,
                 0,
             );
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = token_a_account;
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_a_account = old_account;
-        }
-
-        // token B account owner is not swap authority
-        {
-            let (_token_b_key, token_b_account) = mint_token(
+            let (pool_fee_key, pool_fee_account) = mint_token(
                 &spl_token::id(),
-                &accounts.token_b_mint_key,
-                &mut accounts.token_b_mint_account,
-                &user_key,
-                &user_key,
+                &pool_mint_key,
+                &mut pool_mint_account,
+                &authority_key,
+                user_key,
                 0,
             );
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_b_account;
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
-        }
-
-        // pool token account owner is swap authority
-        {
-            let (_pool_token_key, pool_token_account) = mint_token(
+            let (token_a_mint_key, mut token_a_mint_account) =
+                create_mint(&spl_token::id(), user_key, None);
+            let (token_a_key, token_a_account) = mint_token(
                 &spl_token::id(),
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.pool_token_account;
-            accounts.pool_token_account = pool_token_account;
-            assert_eq!(
-                Err(SwapError::InvalidOutputOwner.into()),
-                accounts.initialize_swap()
+                &token_a_mint_key,
+                &mut token_a_mint_account,
+                user_key,
+                &authority_key,
+                token_a_amount,
             );
-            accounts.pool_token_account = old_account;
-        }
-
-        // pool fee account owner is swap authority
-        {
-            let (_pool_fee_key, pool_fee_account) = mint_token(
+            let (token_b_mint_key, mut token_b_mint_account) =
+                create_mint(&spl_token::id(), user_key, None);
+            let (token_b_key, token_b_account) = mint_token(
                 &spl_token::id(),
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.pool_fee_account;
-            accounts.pool_fee_account = pool_fee_account;
-            assert_eq!(
-                Err(SwapError::InvalidOutputOwner.into()),
-                accounts.initialize_swap()
+                &token_b_mint_key,
+                &mut token_b_mint_account,
+                user_key,
+                &authority_key,
+                token_b_amount,
             );
-            accounts.pool_fee_account = old_account;
+
+            SwapAccountInfo {
+                nonce,
+                authority_key,
+                fees,
+                swap_curve,
+                swap_key,
+                swap_account,
+                pool_mint_key,
+                pool_mint_account,
+                pool_fee_key,
+                pool_fee_account,
+                pool_token_key,
+                pool_token_account,
+                token_a_key,
+                token_a_account,
+                token_a_mint_key,
+                token_a_mint_account,
+                token_b_key,
+                token_b_account,
+                token_b_mint_key,
+                token_b_mint_account,
+                pause_authority_key: Pubkey::new_unique(),
+                dynamic_fees: DynamicFees::default(),
+                oracle_key: Pubkey::new_unique(),
+                oracle_account: create_oracle(PRICE_PRECISION),
+            }
         }
 
-        // pool mint authority is not swap authority
-        {
-            let (_pool_mint_key, pool_mint_account) =
-                create_mint(&spl_token::id(), &user_key, None);
-            let old_mint = accounts.pool_mint_account;
-            accounts.pool_mint_account = pool_mint_account;
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_mint_account = old_mint;
+        fn oracle_key(&self) -> Option<&Pubkey> {
+            if self.swap_curve.curve_type.uses_oracle() {
+                Some(&self.oracle_key)
+            } else {
+                None
+            }
         }
 
-        // pool mint token has freeze authority
-        {
-            let (_pool_mint_key, pool_mint_account) =
-                create_mint(&spl_token::id(), &accounts.authority_key, Some(&user_key));
-            let old_mint = accounts.pool_mint_account;
-            accounts.pool_mint_account = pool_mint_account;
-            assert_eq!(
-                Err(SwapError::InvalidFreezeAuthority.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_mint_account = old_mint;
+        pub fn initialize_swap(&mut self) -> ProgramResult {
+            self.initialize_swap_with_constraints(&SWAP_CONSTRAINTS)
         }
 
-        // token A account owned by wrong program
-        {
-            let (_token_a_key, mut token_a_account) = mint_token(
+        pub fn initialize_swap_with_constraints(
+            &mut self,
+            swap_constraints: &Option<SwapConstraints>,
+        ) -> ProgramResult {
+            let instruction = initialize(
+                &SWAP_PROGRAM_ID,
                 &spl_token::id(),
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                token_a_amount,
-            );
-            token_a_account.owner = SWAP_PROGRAM_ID;
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = token_a_account;
-            assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_a_account = old_account;
+                &self.swap_key,
+                &self.authority_key,
+                &self.token_a_key,
+                &self.token_b_key,
+                &self.pool_mint_key,
+                &self.pool_fee_key,
+                &self.pool_token_key,
+                self.oracle_key(),
+                self.nonce,
+                self.fees.clone(),
+                self.swap_curve.clone(),
+                COption::Some(self.pause_authority_key),
+                self.dynamic_fees.clone(),
+            )
+            .unwrap();
+            let uses_oracle = self.swap_curve.curve_type.uses_oracle();
+            let mut authority_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.pool_mint_account,
+                &mut self.pool_fee_account,
+                &mut self.pool_token_account,
+                &mut token_program_account,
+            ];
+            if uses_oracle {
+                accounts.push(&mut self.oracle_account);
+            }
+            do_process_instruction_with_fee_constraints(instruction, accounts, swap_constraints)
         }
 
-        // token B account owned by wrong program
-        {
-            let (_token_b_key, mut token_b_account) = mint_token(
+        pub fn setup_token_accounts(
+            &mut self,
+            mint_owner: &Pubkey,
+            account_owner: &Pubkey,
+            a_amount: u64,
+            b_amount: u64,
+            pool_amount: u64,
+        ) -> (Pubkey, Account, Pubkey, Account, Pubkey, Account) {
+            let (token_a_key, token_a_account) = mint_token(
                 &spl_token::id(),
-                &accounts.token_b_mint_key,
-                &mut accounts.token_b_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                token_b_amount,
-            );
-            token_b_account.owner = SWAP_PROGRAM_ID;
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_b_account;
-            assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                accounts.initialize_swap()
+                &self.token_a_mint_key,
+                &mut self.token_a_mint_account,
+                mint_owner,
+                account_owner,
+                a_amount,
             );
-            accounts.token_b_account = old_account;
-        }
-
-        // empty token A account
-        {
-            let (_token_a_key, token_a_account) = mint_token(
+            let (token_b_key, token_b_account) = mint_token(
                 &spl_token::id(),
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                0,
+                &self.token_b_mint_key,
+                &mut self.token_b_mint_account,
+                mint_owner,
+                account_owner,
+                b_amount,
             );
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = token_a_account;
-            assert_eq!(
-                Err(SwapError::EmptySupply.into()),
-                accounts.initialize_swap()
+            let (pool_key, pool_account) = mint_token(
+                &spl_token::id(),
+                &self.pool_mint_key,
+                &mut self.pool_mint_account,
+                &self.authority_key,
+                account_owner,
+                pool_amount,
             );
-            accounts.token_a_account = old_account;
+            (
+                token_a_key,
+                token_a_account,
+                token_b_key,
+                token_b_account,
+                pool_key,
+                pool_account,
+            )
         }
 
-        // empty token B account
-        {
-            let (_token_b_key, token_b_account) = mint_token(
-                &spl_token::id(),
-                &accounts.token_b_mint_key,
-                &mut accounts.token_b_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_b_account;
-            assert_eq!(
-                Err(SwapError::EmptySupply.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
+        fn get_token_account(&self, account_key: &Pubkey) -> &Account {
+            if *account_key == self.token_a_key {
+                return &self.token_a_account;
+            } else if *account_key == self.token_b_key {
+                return &self.token_b_account;
+            }
+            panic!("Could not find matching swap token account");
         }
 
-        // invalid pool tokens
-        {
-            let old_mint = accounts.pool_mint_account;
-            let old_pool_account = accounts.pool_token_account;
+        fn set_token_account(&mut self, account_key: &Pubkey, account: Account) {
+            if *account_key == self.token_a_key {
+                self.token_a_account = account;
+                return;
+            } else if *account_key == self.token_b_key {
+                self.token_b_account = account;
+                return;
+            }
+            panic!("Could not find matching swap token account");
+        }
 
-            let (_pool_mint_key, pool_mint_account) =
-                create_mint(&spl_token::id(), &accounts.authority_key, None);
-            accounts.pool_mint_account = pool_mint_account;
-
-            let (_empty_pool_token_key, empty_pool_token_account) = mint_token(
-                &spl_token::id(),
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &user_key,
-                0,
-            );
-
-            let (_pool_token_key, pool_token_account) = mint_token(
-                &spl_token::id(),
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &user_key,
-                pool_token_amount,
-            );
-
-            // non-empty pool token account
-            accounts.pool_token_account = pool_token_account;
-            assert_eq!(
-                Err(SwapError::InvalidSupply.into()),
-                accounts.initialize_swap()
-            );
-
-            // pool tokens already in circulation
-            accounts.pool_token_account = empty_pool_token_account;
-            assert_eq!(
-                Err(SwapError::InvalidSupply.into()),
-                accounts.initialize_swap()
-            );
-
-            accounts.pool_mint_account = old_mint;
-            accounts.pool_token_account = old_pool_account;
-        }
-
-        // pool fee account has wrong mint
-        {
-            let (_pool_fee_key, pool_fee_account) = mint_token(
-                &spl_token::id(),
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &user_key,
-                0,
-            );
-            let old_account = accounts.pool_fee_account;
-            accounts.pool_fee_account = pool_fee_account;
-            assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_fee_account = old_account;
-        }
-
-        // token A account is delegated
-        {
+        #[allow(clippy::too_many_arguments)]
+        pub fn swap(
+            &mut self,
+            user_key: &Pubkey,
+            user_source_key: &Pubkey,
+            mut user_source_account: &mut Account,
+            swap_source_key: &Pubkey,
+            swap_destination_key: &Pubkey,
+            user_destination_key: &Pubkey,
+            mut user_destination_account: &mut Account,
+            amount_in: u64,
+            minimum_amount_out: u64,
+        ) -> ProgramResult {
+            let user_transfer_key = Pubkey::new_unique();
+            // approve moving from user source account
             do_process_instruction(
                 approve(
                     &spl_token::id(),
-                    &accounts.token_a_key,
-                    &user_key,
-                    &accounts.authority_key,
+                    user_source_key,
+                    &user_transfer_key,
+                    user_key,
                     &[],
-                    1,
+                    amount_in,
                 )
                 .unwrap(),
                 vec![
-                    &mut accounts.token_a_account,
+                    &mut user_source_account,
                     &mut Account::default(),
                     &mut Account::default(),
                 ],
             )
             .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidDelegate.into()),
-                accounts.initialize_swap()
-            );
 
-            do_process_instruction(
-                revoke(
-                    &spl_token::id(),
-                    &accounts.token_a_key,
-                    &accounts.authority_key,
-                    &[],
-                )
-                .unwrap(),
-                vec![&mut accounts.token_a_account, &mut Account::default()],
+            let mut swap_source_account = self.get_token_account(swap_source_key).clone();
+            let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
+
+            // perform the swap
+            let instruction = swap(
+                &SWAP_PROGRAM_ID,
+                &spl_token::id(),
+                &self.swap_key,
+                &self.authority_key,
+                &user_transfer_key,
+                user_source_key,
+                swap_source_key,
+                swap_destination_key,
+                user_destination_key,
+                &self.pool_mint_key,
+                &self.pool_fee_key,
+                self.oracle_key(),
+                None,
+                Swap {
+                    amount_in,
+                    minimum_amount_out,
+                },
             )
             .unwrap();
+            let uses_oracle = self.swap_curve.curve_type.uses_oracle();
+            let mut authority_account = Account::default();
+            let mut user_transfer_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut user_transfer_account,
+                &mut user_source_account,
+                &mut swap_source_account,
+                &mut swap_destination_account,
+                &mut user_destination_account,
+                &mut self.pool_mint_account,
+                &mut self.pool_fee_account,
+                &mut token_program_account,
+            ];
+            if uses_oracle {
+                accounts.push(&mut self.oracle_account);
+            }
+            do_process_instruction(instruction, accounts)?;
+
+            self.set_token_account(swap_source_key, swap_source_account);
+            self.set_token_account(swap_destination_key, swap_destination_account);
+
+            Ok(())
         }
 
-        // token B account is delegated
-        {
+        #[allow(clippy::too_many_arguments)]
+        pub fn deposit_all_token_types(
+            &mut self,
+            depositor_key: &Pubkey,
+            depositor_token_a_key: &Pubkey,
+            mut depositor_token_a_account: &mut Account,
+            depositor_token_b_key: &Pubkey,
+            mut depositor_token_b_account: &mut Account,
+            depositor_pool_key: &Pubkey,
+            mut depositor_pool_account: &mut Account,
+            pool_token_amount: u64,
+            maximum_token_a_amount: u64,
+            maximum_token_b_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority = Pubkey::new_unique();
             do_process_instruction(
                 approve(
                     &spl_token::id(),
-                    &accounts.token_b_key,
-                    &user_key,
-                    &accounts.authority_key,
+                    depositor_token_a_key,
+                    &user_transfer_authority,
+                    depositor_key,
                     &[],
-                    1,
+                    maximum_token_a_amount,
                 )
                 .unwrap(),
                 vec![
-                    &mut accounts.token_b_account,
+                    &mut depositor_token_a_account,
                     &mut Account::default(),
                     &mut Account::default(),
                 ],
             )
             .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidDelegate.into()),
-                accounts.initialize_swap()
-            );
 
             do_process_instruction(
-                revoke(
+                approve(
                     &spl_token::id(),
-                    &accounts.token_b_key,
-                    &accounts.authority_key,
+                    depositor_token_b_key,
+                    &user_transfer_authority,
+                    depositor_key,
                     &[],
+                    maximum_token_b_amount,
                 )
                 .unwrap(),
-                vec![&mut accounts.token_b_account, &mut Account::default()],
+                vec![
+                    &mut depositor_token_b_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
             )
             .unwrap();
-        }
 
-        // token A account has close authority
-        {
             do_process_instruction(
-                set_authority(
+                deposit_all_token_types(
+                    &SWAP_PROGRAM_ID,
                     &spl_token::id(),
-                    &accounts.token_a_key,
-                    Some(&user_key),
-                    AuthorityType::CloseAccount,
-                    &accounts.authority_key,
-                    &[],
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority,
+                    depositor_token_a_key,
+                    depositor_token_b_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    &self.pool_mint_key,
+                    depositor_pool_key,
+                    DepositAllTokenTypes {
+                        pool_token_amount,
+                        maximum_token_a_amount,
+                        maximum_token_b_amount,
+                    },
                 )
                 .unwrap(),
-                vec![&mut accounts.token_a_account, &mut Account::default()],
+                vec![
+                    &mut self.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut depositor_token_a_account,
+                    &mut depositor_token_b_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    &mut self.pool_mint_account,
+                    &mut depositor_pool_account,
+                    &mut Account::default(),
+                ],
             )
-            .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidCloseAuthority.into()),
-                accounts.initialize_swap()
-            );
+        }
 
+        #[allow(clippy::too_many_arguments)]
+        pub fn withdraw_all_token_types(
+            &mut self,
+            user_key: &Pubkey,
+            pool_key: &Pubkey,
+            mut pool_account: &mut Account,
+            token_a_key: &Pubkey,
+            mut token_a_account: &mut Account,
+            token_b_key: &Pubkey,
+            mut token_b_account: &mut Account,
+            pool_token_amount: u64,
+            minimum_token_a_amount: u64,
+            minimum_token_b_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
+            // approve user transfer authority to take out pool tokens
             do_process_instruction(
-                set_authority(
+                approve(
                     &spl_token::id(),
-                    &accounts.token_a_key,
-                    None,
-                    AuthorityType::CloseAccount,
-                    &user_key,
+                    pool_key,
+                    &user_transfer_authority_key,
+                    user_key,
                     &[],
+                    pool_token_amount,
                 )
                 .unwrap(),
-                vec![&mut accounts.token_a_account, &mut Account::default()],
+                vec![
+                    &mut pool_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
             )
             .unwrap();
-        }
 
-        // token B account has close authority
-        {
+            // withdraw token a and b correctly
             do_process_instruction(
-                set_authority(
+                withdraw_all_token_types(
+                    &SWAP_PROGRAM_ID,
                     &spl_token::id(),
-                    &accounts.token_b_key,
-                    Some(&user_key),
-                    AuthorityType::CloseAccount,
-                    &accounts.authority_key,
-                    &[],
-                )
-                .unwrap(),
-                vec![&mut accounts.token_b_account, &mut Account::default()],
-            )
-            .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidCloseAuthority.into()),
-                accounts.initialize_swap()
-            );
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    pool_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    token_a_key,
+                    token_b_key,
+                    WithdrawAllTokenTypes {
+                        pool_token_amount,
+                        minimum_token_a_amount,
+                        minimum_token_b_amount,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut self.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut self.pool_mint_account,
+                    &mut pool_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    &mut token_a_account,
+                    &mut token_b_account,
+                    &mut self.pool_fee_account,
+                    &mut Account::default(),
+                ],
+            )
+        }
 
+        #[allow(clippy::too_many_arguments)]
+        pub fn deposit_single_token_type_exact_amount_in(
+            &mut self,
+            depositor_key: &Pubkey,
+            deposit_account_key: &Pubkey,
+            mut deposit_token_account: &mut Account,
+            deposit_pool_key: &Pubkey,
+            mut deposit_pool_account: &mut Account,
+            source_token_amount: u64,
+            minimum_pool_token_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
             do_process_instruction(
-                set_authority(
+                approve(
                     &spl_token::id(),
-                    &accounts.token_b_key,
-                    None,
-                    AuthorityType::CloseAccount,
-                    &user_key,
+                    deposit_account_key,
+                    &user_transfer_authority_key,
+                    depositor_key,
                     &[],
+                    source_token_amount,
                 )
                 .unwrap(),
-                vec![&mut accounts.token_b_account, &mut Account::default()],
+                vec![
+                    &mut deposit_token_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
             )
             .unwrap();
+
+            do_process_instruction(
+                deposit_single_token_type_exact_amount_in(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    deposit_account_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    &self.pool_mint_key,
+                    deposit_pool_key,
+                    DepositSingleTokenTypeExactAmountIn {
+                        source_token_amount,
+                        minimum_pool_token_amount,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut self.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut deposit_token_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    &mut self.pool_mint_account,
+                    &mut deposit_pool_account,
+                    &mut Account::default(),
+                ],
+            )
         }
 
-        // wrong token program id
-        {
-            let wrong_program_id = Pubkey::new_unique();
-            assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                do_process_instruction(
-                    initialize(
-                        &SWAP_PROGRAM_ID,
-                        &wrong_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.pool_token_key,
-                        accounts.nonce,
-                        accounts.fees.clone(),
-                        accounts.swap_curve.clone(),
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut Account::default(),
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.pool_token_account,
-                        &mut Account::default(),
-                    ],
+        #[allow(clippy::too_many_arguments)]
+        pub fn withdraw_single_token_type_exact_amount_out(
+            &mut self,
+            user_key: &Pubkey,
+            pool_key: &Pubkey,
+            mut pool_account: &mut Account,
+            destination_key: &Pubkey,
+            mut destination_account: &mut Account,
+            destination_token_amount: u64,
+            maximum_pool_token_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
+            // approve user transfer authority to take out pool tokens
+            do_process_instruction(
+                approve(
+                    &spl_token::id(),
+                    pool_key,
+                    &user_transfer_authority_key,
+                    user_key,
+                    &[],
+                    maximum_pool_token_amount,
                 )
-            );
+                .unwrap(),
+                vec![
+                    &mut pool_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
+            )
+            .unwrap();
+
+            do_process_instruction(
+                withdraw_single_token_type_exact_amount_out(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    pool_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    destination_key,
+                    WithdrawSingleTokenTypeExactAmountOut {
+                        destination_token_amount,
+                        maximum_pool_token_amount,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut self.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut self.pool_mint_account,
+                    &mut pool_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    &mut destination_account,
+                    &mut self.pool_fee_account,
+                    &mut Account::default(),
+                ],
+            )
         }
+    }
 
-        // create swap with same token A and B
-        {
-            let (_token_a_repeat_key, token_a_repeat_account) = mint_token(
+    struct MultiTokenSwapAccountInfo {
+        nonce: u8,
+        authority_key: Pubkey,
+        fees: Fees,
+        swap_curve: MultiStableCurve,
+        swap_key: Pubkey,
+        swap_account: Account,
+        pool_mint_key: Pubkey,
+        pool_mint_account: Account,
+        pool_fee_key: Pubkey,
+        pool_fee_account: Account,
+        pool_token_key: Pubkey,
+        pool_token_account: Account,
+        token_keys: Vec<Pubkey>,
+        token_accounts: Vec<Account>,
+        token_mint_keys: Vec<Pubkey>,
+        token_mint_accounts: Vec<Account>,
+        pause_authority_key: Pubkey,
+    }
+
+    impl MultiTokenSwapAccountInfo {
+        pub fn new(
+            user_key: &Pubkey,
+            fees: Fees,
+            swap_curve: MultiStableCurve,
+            token_amounts: &[u64],
+        ) -> Self {
+            let swap_key = Pubkey::new_unique();
+            let swap_account = Account::new(0, MultiTokenSwapVersion::LATEST_LEN, &SWAP_PROGRAM_ID);
+            let (authority_key, nonce) =
+                Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+
+            let (pool_mint_key, mut pool_mint_account) =
+                create_mint(&spl_token::id(), &authority_key, None);
+            let (pool_token_key, pool_token_account) = mint_token(
                 &spl_token::id(),
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                10,
+                &pool_mint_key,
+                &mut pool_mint_account,
+                &authority_key,
+                user_key,
+                0,
             );
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_a_repeat_account;
-            assert_eq!(
-                Err(SwapError::RepeatedMint.into()),
-                accounts.initialize_swap()
+            let (pool_fee_key, pool_fee_account) = mint_token(
+                &spl_token::id(),
+                &pool_mint_key,
+                &mut pool_mint_account,
+                &authority_key,
+                user_key,
+                0,
             );
-            accounts.token_b_account = old_account;
-        }
-
-        // create valid swap
-        accounts.initialize_swap().unwrap();
+            let mut token_keys = vec![];
+            let mut token_accounts = vec![];
+            let mut token_mint_keys = vec![];
+            let mut token_mint_accounts = vec![];
+            for token_amount in token_amounts {
+                let (token_mint_key, mut token_mint_account) =
+                    create_mint(&spl_token::id(), user_key, None);
+                let (token_key, token_account) = mint_token(
+                    &spl_token::id(),
+                    &token_mint_key,
+                    &mut token_mint_account,
+                    user_key,
+                    &authority_key,
+                    *token_amount,
+                );
+                token_keys.push(token_key);
+                token_accounts.push(token_account);
+                token_mint_keys.push(token_mint_key);
+                token_mint_accounts.push(token_mint_account);
+            }
 
-        // create invalid flat swap
-        {
-            let token_b_price = 0;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantPrice,
-                calculator: Box::new(ConstantPriceCurve { token_b_price }),
-            };
-            let mut accounts =
-                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-            assert_eq!(
-                Err(SwapError::InvalidCurve.into()),
-                accounts.initialize_swap()
-            );
+            MultiTokenSwapAccountInfo {
+                nonce,
+                authority_key,
+                fees,
+                swap_curve,
+                swap_key,
+                swap_account,
+                pool_mint_key,
+                pool_mint_account,
+                pool_fee_key,
+                pool_fee_account,
+                pool_token_key,
+                pool_token_account,
+                token_keys,
+                token_accounts,
+                token_mint_keys,
+                token_mint_accounts,
+                pause_authority_key: Pubkey::new_unique(),
+            }
         }
 
-        // create valid flat swap
-        {
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let token_b_price = 10_000;
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantPrice,
-                calculator: Box::new(ConstantPriceCurve { token_b_price }),
-            };
-            let mut accounts =
-                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-            accounts.initialize_swap().unwrap();
+        pub fn initialize_swap(&mut self) -> ProgramResult {
+            let mut authority_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut self.pool_mint_account,
+                &mut self.pool_fee_account,
+                &mut self.pool_token_account,
+                &mut token_program_account,
+            ];
+            accounts.extend(self.token_accounts.iter_mut());
+            do_process_instruction(
+                initialize_multi_token(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &self.swap_key,
+                    &self.authority_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    &self.pool_token_key,
+                    &self.token_keys,
+                    self.nonce,
+                    self.fees.clone(),
+                    self.swap_curve.clone(),
+                    COption::Some(self.pause_authority_key),
+                )
+                .unwrap(),
+                accounts,
+            )
         }
 
-        // create invalid offset swap
-        {
-            let token_b_offset = 0;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::Offset,
-                calculator: Box::new(OffsetCurve { token_b_offset }),
-            };
-            let mut accounts =
-                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-            assert_eq!(
-                Err(SwapError::InvalidCurve.into()),
-                accounts.initialize_swap()
+        pub fn setup_token_accounts(
+            &mut self,
+            mint_owner: &Pubkey,
+            account_owner: &Pubkey,
+            token_amounts: &[u64],
+            pool_amount: u64,
+        ) -> (Vec<Pubkey>, Vec<Account>, Pubkey, Account) {
+            let mut token_keys = vec![];
+            let mut token_accounts = vec![];
+            for ((token_mint_key, token_mint_account), token_amount) in self
+                .token_mint_keys
+                .iter()
+                .zip(self.token_mint_accounts.iter_mut())
+                .zip(token_amounts)
+            {
+                let (token_key, token_account) = mint_token(
+                    &spl_token::id(),
+                    token_mint_key,
+                    token_mint_account,
+                    mint_owner,
+                    account_owner,
+                    *token_amount,
+                );
+                token_keys.push(token_key);
+                token_accounts.push(token_account);
+            }
+            let (pool_key, pool_account) = mint_token(
+                &spl_token::id(),
+                &self.pool_mint_key,
+                &mut self.pool_mint_account,
+                &self.authority_key,
+                account_owner,
+                pool_amount,
             );
+            (token_keys, token_accounts, pool_key, pool_account)
         }
 
-        // create valid offset swap
-        {
-            let token_b_offset = 10;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::Offset,
-                calculator: Box::new(OffsetCurve { token_b_offset }),
-            };
-            let mut accounts =
-                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-            accounts.initialize_swap().unwrap();
+        #[allow(clippy::too_many_arguments)]
+        pub fn swap(
+            &mut self,
+            user_key: &Pubkey,
+            user_source_key: &Pubkey,
+            mut user_source_account: &mut Account,
+            user_destination_key: &Pubkey,
+            user_destination_account: &mut Account,
+            source_index: u8,
+            destination_index: u8,
+            amount_in: u64,
+            minimum_amount_out: u64,
+        ) -> ProgramResult {
+            let user_transfer_key = Pubkey::new_unique();
+            // approve moving from user source account
+            do_process_instruction(
+                approve(
+                    &spl_token::id(),
+                    user_source_key,
+                    &user_transfer_key,
+                    user_key,
+                    &[],
+                    amount_in,
+                )
+                .unwrap(),
+                vec![
+                    &mut user_source_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
+            )
+            .unwrap();
+
+            // perform the swap
+            let mut authority_account = Account::default();
+            let mut user_transfer_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut user_transfer_account,
+                user_source_account,
+                user_destination_account,
+                &mut self.pool_mint_account,
+                &mut self.pool_fee_account,
+                &mut token_program_account,
+            ];
+            accounts.extend(self.token_accounts.iter_mut());
+            do_process_instruction(
+                swap_multi_token(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_key,
+                    user_source_key,
+                    user_destination_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    &self.token_keys,
+                    None,
+                    SwapMultiToken {
+                        source_index,
+                        destination_index,
+                        amount_in,
+                        minimum_amount_out,
+                    },
+                )
+                .unwrap(),
+                accounts,
+            )
         }
 
-        // wrong owner key in constraint
-        {
-            let new_key = Pubkey::new_unique();
-            let trade_fee_numerator = 25;
-            let trade_fee_denominator = 10000;
-            let owner_trade_fee_numerator = 5;
-            let owner_trade_fee_denominator = 10000;
-            let host_fee_numerator = 20;
-            let host_fee_denominator = 100;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let curve = ConstantProductCurve {};
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantProduct,
-                calculator: Box::new(curve),
-            };
-            let owner_key = &new_key.to_string();
-            let valid_curve_types = &[CurveType::ConstantProduct];
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types,
-                fees: &fees,
-            });
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees.clone(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-            );
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                do_process_instruction_with_fee_constraints(
-                    initialize(
-                        &SWAP_PROGRAM_ID,
+        #[allow(clippy::too_many_arguments)]
+        pub fn deposit_all_multi_token(
+            &mut self,
+            user_key: &Pubkey,
+            user_token_keys: &[Pubkey],
+            user_token_accounts: &mut [Account],
+            pool_key: &Pubkey,
+            pool_account: &mut Account,
+            pool_token_amount: u64,
+            maximum_token_amounts: Vec<u64>,
+        ) -> ProgramResult {
+            let user_transfer_authority = Pubkey::new_unique();
+            for ((user_token_key, user_token_account), maximum_token_amount) in user_token_keys
+                .iter()
+                .zip(user_token_accounts.iter_mut())
+                .zip(maximum_token_amounts.iter())
+            {
+                do_process_instruction(
+                    approve(
                         &spl_token::id(),
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.pool_token_key,
-                        accounts.nonce,
-                        accounts.fees.clone(),
-                        accounts.swap_curve.clone(),
+                        user_token_key,
+                        &user_transfer_authority,
+                        user_key,
+                        &[],
+                        *maximum_token_amount,
                     )
                     .unwrap(),
                     vec![
-                        &mut accounts.swap_account,
+                        user_token_account,
                         &mut Account::default(),
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.pool_token_account,
                         &mut Account::default(),
                     ],
-                    &constraints,
                 )
-            );
-        }
+                .unwrap();
+            }
 
-        // wrong fee in constraint
-        {
-            let trade_fee_numerator = 25;
-            let trade_fee_denominator = 10000;
-            let owner_trade_fee_numerator = 5;
-            let owner_trade_fee_denominator = 10000;
-            let host_fee_numerator = 20;
-            let host_fee_denominator = 100;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let curve = ConstantProductCurve {};
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantProduct,
-                calculator: Box::new(curve),
-            };
-            let owner_key = &user_key.to_string();
-            let valid_curve_types = &[CurveType::ConstantProduct];
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types,
-                fees: &fees,
-            });
-            let mut bad_fees = fees.clone();
-            bad_fees.trade_fee_numerator = trade_fee_numerator - 1;
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                bad_fees,
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-            );
-            assert_eq!(
-                Err(SwapError::InvalidFee.into()),
-                do_process_instruction_with_fee_constraints(
-                    initialize(
-                        &SWAP_PROGRAM_ID,
-                        &spl_token::id(),
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.pool_token_key,
-                        accounts.nonce,
-                        accounts.fees.clone(),
-                        accounts.swap_curve.clone(),
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut Account::default(),
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.pool_token_account,
-                        &mut Account::default(),
-                    ],
-                    &constraints,
+            let mut authority_account = Account::default();
+            let mut user_transfer_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut user_transfer_account,
+                &mut self.pool_mint_account,
+                pool_account,
+                &mut token_program_account,
+            ];
+            for (user_token_account, swap_token_account) in user_token_accounts
+                .iter_mut()
+                .zip(self.token_accounts.iter_mut())
+            {
+                accounts.push(user_token_account);
+                accounts.push(swap_token_account);
+            }
+            do_process_instruction(
+                deposit_all_multi_token(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority,
+                    &self.pool_mint_key,
+                    pool_key,
+                    user_token_keys,
+                    &self.token_keys,
+                    DepositAllMultiToken {
+                        pool_token_amount,
+                        maximum_token_amounts,
+                    },
                 )
-            );
+                .unwrap(),
+                accounts,
+            )
         }
 
-        // create valid swap with constraints
-        {
-            let trade_fee_numerator = 25;
-            let trade_fee_denominator = 10000;
-            let owner_trade_fee_numerator = 5;
-            let owner_trade_fee_denominator = 10000;
-            let host_fee_numerator = 20;
-            let host_fee_denominator = 100;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let curve = ConstantProductCurve {};
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantProduct,
-                calculator: Box::new(curve),
-            };
-            let owner_key = &user_key.to_string();
-            let valid_curve_types = &[CurveType::ConstantProduct];
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types,
-                fees: &fees,
-            });
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees.clone(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-            );
-            do_process_instruction_with_fee_constraints(
-                initialize(
-                    &SWAP_PROGRAM_ID,
+        #[allow(clippy::too_many_arguments)]
+        pub fn withdraw_all_multi_token(
+            &mut self,
+            user_key: &Pubkey,
+            pool_key: &Pubkey,
+            mut pool_account: &mut Account,
+            user_token_keys: &[Pubkey],
+            user_token_accounts: &mut [Account],
+            pool_token_amount: u64,
+            minimum_token_amounts: Vec<u64>,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
+            // approve user transfer authority to take out pool tokens
+            do_process_instruction(
+                approve(
                     &spl_token::id(),
-                    &accounts.swap_key,
-                    &accounts.authority_key,
-                    &accounts.token_a_key,
-                    &accounts.token_b_key,
-                    &accounts.pool_mint_key,
-                    &accounts.pool_fee_key,
-                    &accounts.pool_token_key,
-                    accounts.nonce,
-                    accounts.fees,
-                    accounts.swap_curve.clone(),
+                    pool_key,
+                    &user_transfer_authority_key,
+                    user_key,
+                    &[],
+                    pool_token_amount,
                 )
                 .unwrap(),
                 vec![
-                    &mut accounts.swap_account,
+                    &mut pool_account,
                     &mut Account::default(),
-                    &mut accounts.token_a_account,
-                    &mut accounts.token_b_account,
-                    &mut accounts.pool_mint_account,
-                    &mut accounts.pool_fee_account,
-                    &mut accounts.pool_token_account,
                     &mut Account::default(),
                 ],
-                &constraints,
             )
             .unwrap();
-        }
 
-        // create again
-        {
-            assert_eq!(
-                Err(SwapError::AlreadyInUse.into()),
-                accounts.initialize_swap()
-            );
+            let mut authority_account = Account::default();
+            let mut user_transfer_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut user_transfer_account,
+                &mut self.pool_mint_account,
+                pool_account,
+                &mut self.pool_fee_account,
+                &mut token_program_account,
+            ];
+            for (swap_token_account, user_token_account) in self
+                .token_accounts
+                .iter_mut()
+                .zip(user_token_accounts.iter_mut())
+            {
+                accounts.push(swap_token_account);
+                accounts.push(user_token_account);
+            }
+            do_process_instruction(
+                withdraw_all_multi_token(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    pool_key,
+                    &self.token_keys,
+                    user_token_keys,
+                    WithdrawAllMultiToken {
+                        pool_token_amount,
+                        minimum_token_amounts,
+                    },
+                )
+                .unwrap(),
+                accounts,
+            )
         }
-        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
-        assert!(swap_state.is_initialized());
-        assert_eq!(swap_state.nonce(), accounts.nonce);
-        assert_eq!(
-            swap_state.swap_curve().curve_type,
-            accounts.swap_curve.curve_type
-        );
-        assert_eq!(*swap_state.token_a_account(), accounts.token_a_key);
-        assert_eq!(*swap_state.token_b_account(), accounts.token_b_key);
-        assert_eq!(*swap_state.pool_mint(), accounts.pool_mint_key);
-        assert_eq!(*swap_state.token_a_mint(), accounts.token_a_mint_key);
-        assert_eq!(*swap_state.token_b_mint(), accounts.token_b_mint_key);
-        assert_eq!(*swap_state.pool_fee_account(), accounts.pool_fee_key);
-        let token_a = spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-        assert_eq!(token_a.amount, token_a_amount);
-        let token_b = spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-        assert_eq!(token_b.amount, token_b_amount);
-        let pool_account =
-            spl_token::state::Account::unpack(&accounts.pool_token_account.data).unwrap();
-        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
-        assert_eq!(pool_mint.supply, pool_account.amount);
     }
 
-    #[test]
-    fn test_deposit() {
-        let user_key = Pubkey::new_unique();
-        let depositor_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
+    fn mint_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len())
+    }
 
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+    fn account_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(spl_token::state::Account::get_packed_len())
+    }
 
-        let token_a_amount = 1000;
-        let token_b_amount = 9000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Box::new(ConstantProductCurve {}),
-        };
+    fn do_process_instruction_with_fee_constraints(
+        instruction: Instruction,
+        accounts: Vec<&mut Account>,
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        test_syscall_stubs();
 
-        let mut accounts =
-            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+        // approximate the logic in the actual runtime which runs the instruction
+        // and only updates accounts if the instruction is successful
+        let mut account_clones = accounts.iter().map(|x| (*x).clone()).collect::<Vec<_>>();
+        let mut meta = instruction
+            .accounts
+            .iter()
+            .zip(account_clones.iter_mut())
+            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
+            .collect::<Vec<_>>();
+        let mut account_infos = create_is_signer_account_infos(&mut meta);
+        let res = if instruction.program_id == SWAP_PROGRAM_ID {
+            Processor::process_with_constraints(
+                &instruction.program_id,
+                &account_infos,
+                &instruction.data,
+                swap_constraints,
+            )
+        } else {
+            spl_token::processor::Processor::process(
+                &instruction.program_id,
+                &account_infos,
+                &instruction.data,
+            )
+        };
 
-        // depositing 10% of the current pool amount in token A and B means
-        // that our pool tokens will be worth 1 / 10 of the current pool amount
-        let pool_amount = INITIAL_SWAP_POOL_AMOUNT / 10;
-        let deposit_a = token_a_amount / 10;
-        let deposit_b = token_b_amount / 10;
+        if res.is_ok() {
+            let mut account_metas = instruction
+                .accounts
+                .iter()
+                .zip(accounts)
+                .map(|(account_meta, account)| (&account_meta.pubkey, account))
+                .collect::<Vec<_>>();
+            for account_info in account_infos.iter_mut() {
+                for account_meta in account_metas.iter_mut() {
+                    if account_info.key == account_meta.0 {
+                        let account = &mut account_meta.1;
+                        account.owner = *account_info.owner;
+                        account.lamports = **account_info.lamports.borrow();
+                        account.data = account_info.data.borrow().to_vec();
+                    }
+                }
+            }
+        }
+        res
+    }
 
-        // swap not initialized
+    fn do_process_instruction(
+        instruction: Instruction,
+        accounts: Vec<&mut Account>,
+    ) -> ProgramResult {
+        do_process_instruction_with_fee_constraints(instruction, accounts, &SWAP_CONSTRAINTS)
+    }
+
+    fn mint_token(
+        program_id: &Pubkey,
+        mint_key: &Pubkey,
+        mut mint_account: &mut Account,
+        mint_authority_key: &Pubkey,
+        account_owner_key: &Pubkey,
+        amount: u64,
+    ) -> (Pubkey, Account) {
+        let account_key = Pubkey::new_unique();
+        let mut account_account = Account::new(
+            account_minimum_balance(),
+            spl_token::state::Account::get_packed_len(),
+            program_id,
+        );
+        let mut mint_authority_account = Account::default();
+        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
+
+        do_process_instruction(
+            initialize_account(program_id, &account_key, mint_key, account_owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut mint_authority_account,
+                &mut rent_sysvar_account,
+            ],
+        )
+        .unwrap();
+
+        if amount > 0 {
+            do_process_instruction(
+                mint_to(
+                    program_id,
+                    mint_key,
+                    &account_key,
+                    mint_authority_key,
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+                vec![
+                    &mut mint_account,
+                    &mut account_account,
+                    &mut mint_authority_account,
+                ],
+            )
+            .unwrap();
+        }
+
+        (account_key, account_account)
+    }
+
+    fn create_mint(
+        program_id: &Pubkey,
+        authority_key: &Pubkey,
+        freeze_authority: Option<&Pubkey>,
+    ) -> (Pubkey, Account) {
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account = Account::new(
+            mint_minimum_balance(),
+            spl_token::state::Mint::get_packed_len(),
+            program_id,
+        );
+        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
+
+        do_process_instruction(
+            initialize_mint(program_id, &mint_key, authority_key, freeze_authority, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar_account],
+        )
+        .unwrap();
+
+        (mint_key, mint_account)
+    }
+
+    // Points a token account to another mint, without going through the token
+    // program, for mints like the native mint which can't be minted from
+    fn set_token_account_mint(account: &mut Account, mint_key: &Pubkey) {
+        let mut token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+        token_account.mint = *mint_key;
+        spl_token::state::Account::pack(token_account, &mut account.data).unwrap();
+    }
+
+    fn create_oracle(price: u64) -> Account {
+        let mut oracle_account = Account::new(0, Price::LEN, &ORACLE_PROGRAM_ID);
+        Price {
+            price,
+            publish_slot: 0,
+        }
+        .pack_into_slice(&mut oracle_account.data);
+        oracle_account
+    }
+
+    fn create_stake_pool(
+        pool_mint: &Pubkey,
+        total_stake_lamports: u64,
+        pool_token_supply: u64,
+    ) -> Account {
+        let stake_pool = StakePool {
+            account_type: AccountType::StakePool,
+            pool_mint: *pool_mint,
+            total_stake_lamports,
+            pool_token_supply,
+            ..StakePool::default()
+        };
+        let data = stake_pool.try_to_vec().unwrap();
+        let mut stake_pool_account = Account::new(0, data.len(), &spl_stake_pool::id());
+        stake_pool_account.data = data;
+        stake_pool_account
+    }
+
+    #[test]
+    fn test_token_program_id_error() {
+        test_syscall_stubs();
+        let swap_key = Pubkey::new_unique();
+        let mut mint = (Pubkey::new_unique(), Account::default());
+        let mut destination = (Pubkey::new_unique(), Account::default());
+        let token_program = (spl_token::id(), Account::default());
+        let (authority_key, nonce) =
+            Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+        let mut authority = (authority_key, Account::default());
+        let swap_bytes = swap_key.to_bytes();
+        let authority_signature_seeds = [&swap_bytes[..32], &[nonce]];
+        let signers = &[&authority_signature_seeds[..]];
+        let ix = mint_to(
+            &token_program.0,
+            &mint.0,
+            &destination.0,
+            &authority.0,
+            &[],
+            10,
+        )
+        .unwrap();
+        let mint = (&mut mint).into();
+        let destination = (&mut destination).into();
+        let authority = (&mut authority).into();
+
+        let err = invoke_signed(&ix, &[mint, destination, authority], signers).unwrap_err();
+        assert_eq!(err, ProgramError::InvalidAccountData);
+    }
+
+    #[test]
+    fn test_initialize() {
+        let user_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 20;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
+        let pool_token_amount = 10;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+
+        // wrong nonce for authority_key
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let old_nonce = accounts.nonce;
+            accounts.nonce = old_nonce - 1;
             assert_eq!(
-                Err(ProgramError::UninitializedAccount),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidProgramAddress.into()),
+                accounts.initialize_swap()
             );
+            accounts.nonce = old_nonce;
         }
 
-        accounts.initialize_swap().unwrap();
+        // uninitialized token a account
+        {
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = Account::new(0, 0, &spl_token::id());
+            assert_eq!(
+                Err(SwapError::ExpectedAccount.into()),
+                accounts.initialize_swap()
+            );
+            accounts.token_a_account = old_account;
+        }
 
-        // wrong owner for swap account
+        // uninitialized token b account
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let old_swap_account = accounts.swap_account;
-            let mut wrong_swap_account = old_swap_account.clone();
-            wrong_swap_account.owner = spl_token::id();
-            accounts.swap_account = wrong_swap_account;
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = Account::new(0, 0, &spl_token::id());
             assert_eq!(
-                Err(ProgramError::IncorrectProgramId),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::ExpectedAccount.into()),
+                accounts.initialize_swap()
             );
-            accounts.swap_account = old_swap_account;
+            accounts.token_b_account = old_account;
         }
 
-        // wrong nonce for authority_key
+        // uninitialized pool mint
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let old_authority = accounts.authority_key;
-            let (bad_authority_key, _nonce) = Pubkey::find_program_address(
-                &[&accounts.swap_key.to_bytes()[..]],
-                &spl_token::id(),
-            );
-            accounts.authority_key = bad_authority_key;
+            let old_account = accounts.pool_mint_account;
+            accounts.pool_mint_account = Account::new(0, 0, &spl_token::id());
             assert_eq!(
-                Err(SwapError::InvalidProgramAddress.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::ExpectedMint.into()),
+                accounts.initialize_swap()
             );
-            accounts.authority_key = old_authority;
+            accounts.pool_mint_account = old_account;
         }
 
-        // not enough token A
+        // token A account owner is not swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let (_token_a_key, token_a_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
                 &user_key,
-                &depositor_key,
-                deposit_a / 2,
-                deposit_b,
                 0,
             );
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = token_a_account;
             assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_a_account = old_account;
         }
 
-        // not enough token B
+        // token B account owner is not swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let (_token_b_key, token_b_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_b_mint_key,
+                &mut accounts.token_b_mint_account,
+                &user_key,
                 &user_key,
-                &depositor_key,
-                deposit_a,
-                deposit_b / 2,
                 0,
             );
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_b_account;
             assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_b_account = old_account;
         }
 
-        // wrong swap token accounts
+        // pool token account owner is swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (_pool_token_key, pool_token_account) = mint_token(
+                &spl_token::id(),
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.pool_token_account;
+            accounts.pool_token_account = pool_token_account;
             assert_eq!(
-                Err(TokenError::MintMismatch.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOutputOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_token_account = old_account;
         }
 
-        // wrong pool token account
+        // pool fee account owner is swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                mut _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let (
-                wrong_token_key,
-                mut wrong_token_account,
-                _token_b_key,
-                mut _token_b_account,
-                _pool_key,
-                mut _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (_pool_fee_key, pool_fee_account) = mint_token(
+                &spl_token::id(),
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.pool_fee_account;
+            accounts.pool_fee_account = pool_fee_account;
             assert_eq!(
-                Err(TokenError::MintMismatch.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &wrong_token_key,
-                    &mut wrong_token_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOutputOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_fee_account = old_account;
         }
 
-        // no approval
+        // pool mint authority is not swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let user_transfer_authority_key = Pubkey::new_unique();
+            let (_pool_mint_key, pool_mint_account) =
+                create_mint(&spl_token::id(), &user_key, None);
+            let old_mint = accounts.pool_mint_account;
+            accounts.pool_mint_account = pool_mint_account;
             assert_eq!(
-                Err(TokenError::OwnerMismatch.into()),
-                do_process_instruction(
-                    deposit_all_token_types(
-                        &SWAP_PROGRAM_ID,
-                        &spl_token::id(),
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &user_transfer_authority_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &pool_key,
-                        DepositAllTokenTypes {
-                            pool_token_amount: pool_amount.try_into().unwrap(),
-                            maximum_token_a_amount: deposit_a,
-                            maximum_token_b_amount: deposit_b,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut Account::default(),
-                        &mut Account::default(),
-                        &mut token_a_account,
-                        &mut token_b_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
-                        &mut Account::default(),
-                    ],
-                )
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_mint_account = old_mint;
         }
 
-        // wrong token program id
+        // pool mint token has freeze authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let wrong_key = Pubkey::new_unique();
+            let (_pool_mint_key, pool_mint_account) =
+                create_mint(&spl_token::id(), &accounts.authority_key, Some(&user_key));
+            let old_mint = accounts.pool_mint_account;
+            accounts.pool_mint_account = pool_mint_account;
             assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                do_process_instruction(
-                    deposit_all_token_types(
-                        &SWAP_PROGRAM_ID,
-                        &wrong_key,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.authority_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &pool_key,
-                        DepositAllTokenTypes {
-                            pool_token_amount: pool_amount.try_into().unwrap(),
-                            maximum_token_a_amount: deposit_a,
-                            maximum_token_b_amount: deposit_b,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut Account::default(),
-                        &mut Account::default(),
-                        &mut token_a_account,
-                        &mut token_b_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
-                        &mut Account::default(),
-                    ],
-                )
+                Err(SwapError::InvalidFreezeAuthority.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_mint_account = old_mint;
         }
 
-        // wrong swap token accounts
+        // token A account owned by wrong program
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-
-            let old_a_key = accounts.token_a_key;
-            let old_a_account = accounts.token_a_account;
-
-            accounts.token_a_key = token_a_key;
-            accounts.token_a_account = token_a_account.clone();
-
-            // wrong swap token a account
+            let (_token_a_key, mut token_a_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                token_a_amount,
+            );
+            token_a_account.owner = SWAP_PROGRAM_ID;
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = token_a_account;
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_a_account = old_account;
+        }
 
-            accounts.token_a_key = old_a_key;
-            accounts.token_a_account = old_a_account;
-
-            let old_b_key = accounts.token_b_key;
-            let old_b_account = accounts.token_b_account;
-
-            accounts.token_b_key = token_b_key;
-            accounts.token_b_account = token_b_account.clone();
+        // token B account owned by wrong program
+        {
+            let (_token_b_key, mut token_b_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_b_mint_key,
+                &mut accounts.token_b_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                token_b_amount,
+            );
+            token_b_account.owner = SWAP_PROGRAM_ID;
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_b_account;
+            assert_eq!(
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                accounts.initialize_swap()
+            );
+            accounts.token_b_account = old_account;
+        }
 
-            // wrong swap token b account
+        // empty token A account
+        {
+            let (_token_a_key, token_a_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = token_a_account;
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::EmptySupply.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_a_account = old_account;
+        }
 
-            accounts.token_b_key = old_b_key;
-            accounts.token_b_account = old_b_account;
+        // empty token B account
+        {
+            let (_token_b_key, token_b_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_b_mint_key,
+                &mut accounts.token_b_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_b_account;
+            assert_eq!(
+                Err(SwapError::EmptySupply.into()),
+                accounts.initialize_swap()
+            );
+            accounts.token_b_account = old_account;
         }
 
-        // wrong mint
+        // invalid pool tokens
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let (pool_mint_key, pool_mint_account) =
+            let old_mint = accounts.pool_mint_account;
+            let old_pool_account = accounts.pool_token_account;
+
+            let (_pool_mint_key, pool_mint_account) =
                 create_mint(&spl_token::id(), &accounts.authority_key, None);
-            let old_pool_key = accounts.pool_mint_key;
-            let old_pool_account = accounts.pool_mint_account;
-            accounts.pool_mint_key = pool_mint_key;
             accounts.pool_mint_account = pool_mint_account;
 
+            let (_empty_pool_token_key, empty_pool_token_account) = mint_token(
+                &spl_token::id(),
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &user_key,
+                0,
+            );
+
+            let (_pool_token_key, pool_token_account) = mint_token(
+                &spl_token::id(),
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &user_key,
+                pool_token_amount,
+            );
+
+            // non-empty pool token account
+            accounts.pool_token_account = pool_token_account;
             assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidSupply.into()),
+                accounts.initialize_swap()
             );
 
-            accounts.pool_mint_key = old_pool_key;
-            accounts.pool_mint_account = old_pool_account;
+            // pool tokens already in circulation
+            accounts.pool_token_account = empty_pool_token_account;
+            assert_eq!(
+                Err(SwapError::InvalidSupply.into()),
+                accounts.initialize_swap()
+            );
+
+            accounts.pool_mint_account = old_mint;
+            accounts.pool_token_account = old_pool_account;
         }
 
-        // deposit 1 pool token fails beacuse it equates to 0 swap tokens
+        // pool fee account has wrong mint
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (_pool_fee_key, pool_fee_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
+                &user_key,
+                0,
+            );
+            let old_account = accounts.pool_fee_account;
+            accounts.pool_fee_account = pool_fee_account;
             assert_eq!(
-                Err(SwapError::ZeroTradingTokens.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    1,
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_fee_account = old_account;
         }
 
-        // slippage exceeded
+        // token A account is delegated
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            // maximum A amount in too low
-            assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a / 10,
-                    deposit_b,
+            do_process_instruction(
+                approve(
+                    &spl_token::id(),
+                    &accounts.token_a_key,
+                    &user_key,
+                    &accounts.authority_key,
+                    &[],
+                    1,
                 )
-            );
-            // maximum B amount in too low
+                .unwrap(),
+                vec![
+                    &mut accounts.token_a_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
+            )
+            .unwrap();
             assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b / 10,
-                )
+                Err(SwapError::InvalidDelegate.into()),
+                accounts.initialize_swap()
             );
+
+            do_process_instruction(
+                revoke(
+                    &spl_token::id(),
+                    &accounts.token_a_key,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_a_account, &mut Account::default()],
+            )
+            .unwrap();
         }
 
-        // invalid input: can't use swap pool tokens as source
+        // token B account is delegated
         {
-            let (
-                _token_a_key,
-                _token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let swap_token_a_key = accounts.token_a_key;
-            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
-            let swap_token_b_key = accounts.token_b_key;
-            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
-            let authority_key = accounts.authority_key;
-            assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.deposit_all_token_types(
-                    &authority_key,
-                    &swap_token_a_key,
-                    &mut swap_token_a_account,
-                    &swap_token_b_key,
-                    &mut swap_token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
+            do_process_instruction(
+                approve(
+                    &spl_token::id(),
+                    &accounts.token_b_key,
+                    &user_key,
+                    &accounts.authority_key,
+                    &[],
+                    1,
                 )
+                .unwrap(),
+                vec![
+                    &mut accounts.token_b_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                ],
+            )
+            .unwrap();
+            assert_eq!(
+                Err(SwapError::InvalidDelegate.into()),
+                accounts.initialize_swap()
             );
+
+            do_process_instruction(
+                revoke(
+                    &spl_token::id(),
+                    &accounts.token_b_key,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_b_account, &mut Account::default()],
+            )
+            .unwrap();
         }
 
-        // correctly deposit
+        // token A account has close authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            accounts
-                .deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
+            do_process_instruction(
+                set_authority(
+                    &spl_token::id(),
+                    &accounts.token_a_key,
+                    Some(&user_key),
+                    AuthorityType::CloseAccount,
+                    &accounts.authority_key,
+                    &[],
                 )
-                .unwrap();
-
-            let swap_token_a =
-                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-            assert_eq!(swap_token_a.amount, deposit_a + token_a_amount);
-            let swap_token_b =
-                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-            assert_eq!(swap_token_b.amount, deposit_b + token_b_amount);
-            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.amount, 0);
-            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-            assert_eq!(token_b.amount, 0);
-            let pool_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
-            let swap_pool_account =
-                spl_token::state::Account::unpack(&accounts.pool_token_account.data).unwrap();
-            let pool_mint =
-                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+                .unwrap(),
+                vec![&mut accounts.token_a_account, &mut Account::default()],
+            )
+            .unwrap();
             assert_eq!(
-                pool_mint.supply,
-                pool_account.amount + swap_pool_account.amount
+                Err(SwapError::InvalidCloseAuthority.into()),
+                accounts.initialize_swap()
             );
-        }
-    }
 
-    #[test]
-    fn test_withdraw() {
-        let user_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 7;
-        let host_fee_denominator = 100;
+            do_process_instruction(
+                set_authority(
+                    &spl_token::id(),
+                    &accounts.token_a_key,
+                    None,
+                    AuthorityType::CloseAccount,
+                    &user_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_a_account, &mut Account::default()],
+            )
+            .unwrap();
+        }
 
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+        // token B account has close authority
+        {
+            do_process_instruction(
+                set_authority(
+                    &spl_token::id(),
+                    &accounts.token_b_key,
+                    Some(&user_key),
+                    AuthorityType::CloseAccount,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_b_account, &mut Account::default()],
+            )
+            .unwrap();
+            assert_eq!(
+                Err(SwapError::InvalidCloseAuthority.into()),
+                accounts.initialize_swap()
+            );
 
-        let token_a_amount = 1000;
-        let token_b_amount = 2000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Box::new(ConstantProductCurve {}),
-        };
+            do_process_instruction(
+                set_authority(
+                    &spl_token::id(),
+                    &accounts.token_b_key,
+                    None,
+                    AuthorityType::CloseAccount,
+                    &user_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_b_account, &mut Account::default()],
+            )
+            .unwrap();
+        }
 
-        let withdrawer_key = Pubkey::new_unique();
-        let initial_a = token_a_amount / 10;
-        let initial_b = token_b_amount / 10;
-        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
-        let withdraw_amount = initial_pool / 4;
-        let minimum_token_a_amount = initial_a / 40;
-        let minimum_token_b_amount = initial_b / 40;
-
-        let mut accounts =
-            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-
-        // swap not initialized
+        // wrong token program id
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+            let wrong_program_id = Pubkey::new_unique();
             assert_eq!(
-                Err(ProgramError::UninitializedAccount),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                do_process_instruction(
+                    initialize(
+                        &SWAP_PROGRAM_ID,
+                        &wrong_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.pool_token_key,
+                        None,
+                        accounts.nonce,
+                        accounts.fees.clone(),
+                        accounts.swap_curve.clone(),
+                        COption::None,
+                        DynamicFees::default(),
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.pool_token_account,
+                        &mut Account::default(),
+                    ],
                 )
             );
         }
 
-        accounts.initialize_swap().unwrap();
-
-        // wrong owner for swap account
+        // create swap with same token A and B
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
-            let old_swap_account = accounts.swap_account;
-            let mut wrong_swap_account = old_swap_account.clone();
-            wrong_swap_account.owner = spl_token::id();
-            accounts.swap_account = wrong_swap_account;
+            let (_token_a_repeat_key, token_a_repeat_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                10,
+            );
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_a_repeat_account;
             assert_eq!(
-                Err(ProgramError::IncorrectProgramId),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::RepeatedMint.into()),
+                accounts.initialize_swap()
             );
-            accounts.swap_account = old_swap_account;
+            accounts.token_b_account = old_account;
         }
 
-        // wrong nonce for authority_key
+        // create valid swap
+        accounts.initialize_swap().unwrap();
+
+        // create invalid flat swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
-            let old_authority = accounts.authority_key;
-            let (bad_authority_key, _nonce) = Pubkey::find_program_address(
-                &[&accounts.swap_key.to_bytes()[..]],
-                &spl_token::id(),
-            );
-            accounts.authority_key = bad_authority_key;
+            let token_b_price = 0;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantPrice,
+                calculator: Box::new(ConstantPriceCurve { token_b_price }),
+            };
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
             assert_eq!(
-                Err(SwapError::InvalidProgramAddress.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
             );
-            accounts.authority_key = old_authority;
         }
 
-        // not enough pool tokens
+        // create valid flat swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                to_u64(withdraw_amount).unwrap() / 2u64,
-            );
-            assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount / 2,
-                    minimum_token_b_amount / 2,
-                )
-            );
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let token_b_price = 10_000;
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantPrice,
+                calculator: Box::new(ConstantPriceCurve { token_b_price }),
+            };
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+            accounts.initialize_swap().unwrap();
         }
 
-        // wrong token a / b accounts
+        // create invalid offset swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
+            let token_b_offset = 0;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::Offset,
+                calculator: Box::new(OffsetCurve { token_b_offset }),
+            };
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
             assert_eq!(
-                Err(TokenError::MintMismatch.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
             );
         }
 
-        // wrong pool token account
+        // create valid offset swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let (
-                wrong_token_a_key,
-                mut wrong_token_a_account,
-                _token_b_key,
-                _token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                withdraw_amount.try_into().unwrap(),
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            assert_eq!(
-                Err(TokenError::MintMismatch.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &wrong_token_a_key,
-                    &mut wrong_token_a_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
-            );
+            let token_b_offset = 10;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::Offset,
+                calculator: Box::new(OffsetCurve { token_b_offset }),
+            };
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+            accounts.initialize_swap().unwrap();
         }
 
-        // wrong pool fee account
+        // create invalid concentrated liquidity swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                wrong_pool_key,
-                wrong_pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let (
-                _token_a_key,
-                _token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let old_pool_fee_account = accounts.pool_fee_account;
-            let old_pool_fee_key = accounts.pool_fee_key;
-            accounts.pool_fee_account = wrong_pool_account;
-            accounts.pool_fee_key = wrong_pool_key;
+            let lower_price = 2 * PRICE_PRECISION;
+            let upper_price = PRICE_PRECISION;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConcentratedLiquidity,
+                calculator: Box::new(ConcentratedLiquidityCurve {
+                    lower_price,
+                    upper_price,
+                }),
+            };
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
             assert_eq!(
-                Err(SwapError::IncorrectFeeAccount.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                ),
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
             );
-            accounts.pool_fee_account = old_pool_fee_account;
-            accounts.pool_fee_key = old_pool_fee_key;
         }
 
-        // no approval
+        // create valid concentrated liquidity swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let lower_price = PRICE_PRECISION;
+            let upper_price = 2 * PRICE_PRECISION;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConcentratedLiquidity,
+                calculator: Box::new(ConcentratedLiquidityCurve {
+                    lower_price,
+                    upper_price,
+                }),
+            };
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+            accounts.initialize_swap().unwrap();
+        }
+
+        // wrong owner key in constraint
+        {
+            let new_key = Pubkey::new_unique();
+            let trade_fee_numerator = 25;
+            let trade_fee_denominator = 10000;
+            let owner_trade_fee_numerator = 5;
+            let owner_trade_fee_denominator = 10000;
+            let host_fee_numerator = 20;
+            let host_fee_denominator = 100;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let curve = ConstantProductCurve {};
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantProduct,
+                calculator: Box::new(curve),
+            };
+            let owner_key = &new_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                valid_oracle_program_ids: &[],
+                fees: &fees,
+                price_range: None,
+            });
+            let mut accounts = SwapAccountInfo::new(
                 &user_key,
-                &withdrawer_key,
-                0,
-                0,
-                withdraw_amount.try_into().unwrap(),
+                fees.clone(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
             );
-            let user_transfer_authority_key = Pubkey::new_unique();
             assert_eq!(
-                Err(TokenError::OwnerMismatch.into()),
-                do_process_instruction(
-                    withdraw_all_token_types(
+                Err(SwapError::InvalidOwner.into()),
+                do_process_instruction_with_fee_constraints(
+                    initialize(
                         &SWAP_PROGRAM_ID,
                         &spl_token::id(),
                         &accounts.swap_key,
                         &accounts.authority_key,
-                        &user_transfer_authority_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &pool_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
-                        &token_a_key,
-                        &token_b_key,
-                        WithdrawAllTokenTypes {
-                            pool_token_amount: withdraw_amount.try_into().unwrap(),
-                            minimum_token_a_amount,
-                            minimum_token_b_amount,
-                        }
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.pool_token_key,
+                        None,
+                        accounts.nonce,
+                        accounts.fees.clone(),
+                        accounts.swap_curve.clone(),
+                        COption::None,
+                        DynamicFees::default(),
                     )
                     .unwrap(),
                     vec![
                         &mut accounts.swap_account,
                         &mut Account::default(),
-                        &mut Account::default(),
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
-                        &mut token_a_account,
-                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
                         &mut accounts.pool_fee_account,
+                        &mut accounts.pool_token_account,
                         &mut Account::default(),
                     ],
+                    &constraints,
                 )
             );
         }
 
-        // wrong token program id
+        // wrong fee in constraint
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let wrong_key = Pubkey::new_unique();
-            assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                do_process_instruction(
-                    withdraw_all_token_types(
-                        &SWAP_PROGRAM_ID,
-                        &wrong_key,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.authority_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &pool_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_a_key,
-                        &token_b_key,
-                        WithdrawAllTokenTypes {
-                            pool_token_amount: withdraw_amount.try_into().unwrap(),
-                            minimum_token_a_amount,
-                            minimum_token_b_amount,
-                        },
+            let trade_fee_numerator = 25;
+            let trade_fee_denominator = 10000;
+            let owner_trade_fee_numerator = 5;
+            let owner_trade_fee_denominator = 10000;
+            let host_fee_numerator = 20;
+            let host_fee_denominator = 100;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let curve = ConstantProductCurve {};
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantProduct,
+                calculator: Box::new(curve),
+            };
+            let owner_key = &user_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                valid_oracle_program_ids: &[],
+                fees: &fees,
+                price_range: None,
+            });
+            let mut bad_fees = fees.clone();
+            bad_fees.trade_fee_numerator = trade_fee_numerator - 1;
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                bad_fees,
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+            );
+            assert_eq!(
+                Err(SwapError::InvalidFee.into()),
+                do_process_instruction_with_fee_constraints(
+                    initialize(
+                        &SWAP_PROGRAM_ID,
+                        &spl_token::id(),
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.pool_token_key,
+                        None,
+                        accounts.nonce,
+                        accounts.fees.clone(),
+                        accounts.swap_curve.clone(),
+                        COption::None,
+                        DynamicFees::default(),
                     )
                     .unwrap(),
                     vec![
                         &mut accounts.swap_account,
                         &mut Account::default(),
-                        &mut Account::default(),
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
-                        &mut token_a_account,
-                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
                         &mut accounts.pool_fee_account,
+                        &mut accounts.pool_token_account,
                         &mut Account::default(),
                     ],
+                    &constraints,
                 )
             );
         }
 
-        // wrong swap token accounts
+        // create valid swap with constraints
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let trade_fee_numerator = 25;
+            let trade_fee_denominator = 10000;
+            let owner_trade_fee_numerator = 5;
+            let owner_trade_fee_denominator = 10000;
+            let host_fee_numerator = 20;
+            let host_fee_denominator = 100;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let curve = ConstantProductCurve {};
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantProduct,
+                calculator: Box::new(curve),
+            };
+            let owner_key = &user_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                valid_oracle_program_ids: &[],
+                fees: &fees,
+                price_range: None,
+            });
+            let mut accounts = SwapAccountInfo::new(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
+                fees.clone(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
             );
+            do_process_instruction_with_fee_constraints(
+                initialize(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &accounts.swap_key,
+                    &accounts.authority_key,
+                    &accounts.token_a_key,
+                    &accounts.token_b_key,
+                    &accounts.pool_mint_key,
+                    &accounts.pool_fee_key,
+                    &accounts.pool_token_key,
+                    None,
+                    accounts.nonce,
+                    accounts.fees,
+                    accounts.swap_curve.clone(),
+                    COption::None,
+                    DynamicFees::default(),
+                )
+                .unwrap(),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut Account::default(),
+                    &mut accounts.token_a_account,
+                    &mut accounts.token_b_account,
+                    &mut accounts.pool_mint_account,
+                    &mut accounts.pool_fee_account,
+                    &mut accounts.pool_token_account,
+                    &mut Account::default(),
+                ],
+                &constraints,
+            )
+            .unwrap();
+        }
 
-            let old_a_key = accounts.token_a_key;
-            let old_a_account = accounts.token_a_account;
-
-            accounts.token_a_key = token_a_key;
-            accounts.token_a_account = token_a_account.clone();
-
-            // wrong swap token a account
+        // create again
+        {
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::AlreadyInUse.into()),
+                accounts.initialize_swap()
             );
+        }
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_state.is_initialized());
+        assert_eq!(swap_state.nonce(), accounts.nonce);
+        assert_eq!(
+            swap_state.swap_curve().curve_type,
+            accounts.swap_curve.curve_type
+        );
+        assert_eq!(*swap_state.token_a_account(), accounts.token_a_key);
+        assert_eq!(*swap_state.token_b_account(), accounts.token_b_key);
+        assert_eq!(*swap_state.pool_mint(), accounts.pool_mint_key);
+        assert_eq!(*swap_state.token_a_mint(), accounts.token_a_mint_key);
+        assert_eq!(*swap_state.token_b_mint(), accounts.token_b_mint_key);
+        assert_eq!(*swap_state.pool_fee_account(), accounts.pool_fee_key);
+        assert_eq!(
+            swap_state.pause_authority(),
+            COption::Some(accounts.pause_authority_key)
+        );
+        assert!(!swap_state.is_paused());
+        let token_a = spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(token_a.amount, token_a_amount);
+        let token_b = spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(token_b.amount, token_b_amount);
+        let pool_account =
+            spl_token::state::Account::unpack(&accounts.pool_token_account.data).unwrap();
+        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+        assert_eq!(pool_mint.supply, pool_account.amount);
+    }
 
-            accounts.token_a_key = old_a_key;
-            accounts.token_a_account = old_a_account;
+    #[test]
+    fn test_deposit() {
+        let user_key = Pubkey::new_unique();
+        let depositor_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 20;
+        let host_fee_denominator = 100;
 
-            let old_b_key = accounts.token_b_key;
-            let old_b_account = accounts.token_b_account;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
 
-            accounts.token_b_key = token_b_key;
-            accounts.token_b_account = token_b_account.clone();
+        let token_a_amount = 1000;
+        let token_b_amount = 9000;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
 
-            // wrong swap token b account
-            assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
-            );
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
 
-            accounts.token_b_key = old_b_key;
-            accounts.token_b_account = old_b_account;
-        }
+        // depositing 10% of the current pool amount in token A and B means
+        // that our pool tokens will be worth 1 / 10 of the current pool amount
+        let pool_amount = INITIAL_SWAP_POOL_AMOUNT / 10;
+        let deposit_a = token_a_amount / 10;
+        let deposit_b = token_b_amount / 10;
 
-        // wrong mint
+        // swap not initialized
         {
             let (
                 token_a_key,
@@ -3919,41 +4675,27 @@ mod tests {
                 mut token_b_account,
                 pool_key,
                 mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
-            );
-            let (pool_mint_key, pool_mint_account) =
-                create_mint(&spl_token::id(), &accounts.authority_key, None);
-            let old_pool_key = accounts.pool_mint_key;
-            let old_pool_account = accounts.pool_mint_account;
-            accounts.pool_mint_key = pool_mint_key;
-            accounts.pool_mint_account = pool_mint_account;
-
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
+                Err(ProgramError::UninitializedAccount),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
                 )
             );
-
-            accounts.pool_mint_key = old_pool_key;
-            accounts.pool_mint_account = old_pool_account;
         }
 
-        // withdrawing 1 pool token fails because it equates to 0 output tokens
+        accounts.initialize_swap().unwrap();
+
+        // wrong owner for swap account
         {
             let (
                 token_a_key,
@@ -3962,31 +4704,30 @@ mod tests {
                 mut token_b_account,
                 pool_key,
                 mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
-            );
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let old_swap_account = accounts.swap_account;
+            let mut wrong_swap_account = old_swap_account.clone();
+            wrong_swap_account.owner = spl_token::id();
+            accounts.swap_account = wrong_swap_account;
             assert_eq!(
-                Err(SwapError::ZeroTradingTokens.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
+                Err(ProgramError::IncorrectProgramId),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    1,
-                    0,
-                    0,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
                 )
             );
+            accounts.swap_account = old_swap_account;
         }
 
-        // slippage exceeded
+        // wrong nonce for authority_key
         {
             let (
                 token_a_key,
@@ -3995,48 +4736,32 @@ mod tests {
                 mut token_b_account,
                 pool_key,
                 mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let old_authority = accounts.authority_key;
+            let (bad_authority_key, _nonce) = Pubkey::find_program_address(
+                &[&accounts.swap_key.to_bytes()[..]],
+                &spl_token::id(),
             );
-            // minimum A amount out too high
+            accounts.authority_key = bad_authority_key;
             assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
+                Err(SwapError::InvalidProgramAddress.into()),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount * 10,
-                    minimum_token_b_amount,
-                )
-            );
-            // minimum B amount out too high
-            assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount * 10,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
                 )
             );
+            accounts.authority_key = old_authority;
         }
 
-        // invalid input: can't use swap pool tokens as destination
+        // not enough token A
         {
             let (
                 token_a_key,
@@ -4047,48 +4772,29 @@ mod tests {
                 mut pool_account,
             ) = accounts.setup_token_accounts(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
+                &depositor_key,
+                deposit_a / 2,
+                deposit_b,
+                0,
             );
-            let swap_token_a_key = accounts.token_a_key;
-            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &swap_token_a_key,
-                    &mut swap_token_a_account,
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
-            );
-            let swap_token_b_key = accounts.token_b_key;
-            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
-            assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_b_key,
-                    &mut swap_token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
                 )
             );
         }
 
-        // correct withdrawal
+        // not enough token B
         {
             let (
                 token_a_key,
@@ -4099,303 +4805,54 @@ mod tests {
                 mut pool_account,
             ) = accounts.setup_token_accounts(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
+                &depositor_key,
+                deposit_a,
+                deposit_b / 2,
+                0,
             );
-
-            accounts
-                .withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
-                .unwrap();
-
-            let swap_token_a =
-                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-            let swap_token_b =
-                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-            let pool_mint =
-                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
-            let withdraw_fee = accounts.fees.owner_withdraw_fee(withdraw_amount).unwrap();
-            let results = accounts
-                .swap_curve
-                .calculator
-                .pool_tokens_to_trading_tokens(
-                    withdraw_amount - withdraw_fee,
-                    pool_mint.supply.try_into().unwrap(),
-                    swap_token_a.amount.try_into().unwrap(),
-                    swap_token_b.amount.try_into().unwrap(),
-                    RoundDirection::Floor,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
                 )
-                .unwrap();
-            assert_eq!(
-                swap_token_a.amount,
-                token_a_amount - to_u64(results.token_a_amount).unwrap()
-            );
-            assert_eq!(
-                swap_token_b.amount,
-                token_b_amount - to_u64(results.token_b_amount).unwrap()
-            );
-            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-            assert_eq!(
-                token_a.amount,
-                initial_a + to_u64(results.token_a_amount).unwrap()
-            );
-            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-            assert_eq!(
-                token_b.amount,
-                initial_b + to_u64(results.token_b_amount).unwrap()
-            );
-            let pool_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
-            assert_eq!(
-                pool_account.amount,
-                to_u64(initial_pool - withdraw_amount).unwrap()
-            );
-            let fee_account =
-                spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
-            assert_eq!(
-                fee_account.amount,
-                TryInto::<u64>::try_into(withdraw_fee).unwrap()
             );
         }
 
-        // correct withdrawal from fee account
+        // wrong swap token accounts
         {
             let (
                 token_a_key,
                 mut token_a_account,
                 token_b_key,
                 mut token_b_account,
-                _pool_key,
-                mut _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
-
-            let pool_fee_key = accounts.pool_fee_key;
-            let mut pool_fee_account = accounts.pool_fee_account.clone();
-            let fee_account = spl_token::state::Account::unpack(&pool_fee_account.data).unwrap();
-            let pool_fee_amount = fee_account.amount;
-
-            accounts
-                .withdraw_all_token_types(
-                    &user_key,
-                    &pool_fee_key,
-                    &mut pool_fee_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    pool_fee_amount,
-                    0,
-                    0,
-                )
-                .unwrap();
-
-            let swap_token_a =
-                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-            let swap_token_b =
-                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-            let pool_mint =
-                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
-            let results = accounts
-                .swap_curve
-                .calculator
-                .pool_tokens_to_trading_tokens(
-                    pool_fee_amount.try_into().unwrap(),
-                    pool_mint.supply.try_into().unwrap(),
-                    swap_token_a.amount.try_into().unwrap(),
-                    swap_token_b.amount.try_into().unwrap(),
-                    RoundDirection::Floor,
-                )
-                .unwrap();
-            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-            assert_eq!(
-                token_a.amount,
-                TryInto::<u64>::try_into(results.token_a_amount).unwrap()
-            );
-            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-            assert_eq!(
-                token_b.amount,
-                TryInto::<u64>::try_into(results.token_b_amount).unwrap()
-            );
-        }
-    }
-
-    #[test]
-    fn test_deposit_one_exact_in() {
-        let user_key = Pubkey::new_unique();
-        let depositor_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
-
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 9000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Box::new(ConstantProductCurve {}),
-        };
-
-        let mut accounts =
-            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-
-        let deposit_a = token_a_amount / 10;
-        let deposit_b = token_b_amount / 10;
-        let pool_amount = to_u64(INITIAL_SWAP_POOL_AMOUNT / 100).unwrap();
-
-        // swap not initialized
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            assert_eq!(
-                Err(ProgramError::UninitializedAccount),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_a,
-                    pool_amount,
-                )
-            );
-        }
-
-        accounts.initialize_swap().unwrap();
-
-        // wrong owner for swap account
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let old_swap_account = accounts.swap_account;
-            let mut wrong_swap_account = old_swap_account.clone();
-            wrong_swap_account.owner = spl_token::id();
-            accounts.swap_account = wrong_swap_account;
-            assert_eq!(
-                Err(ProgramError::IncorrectProgramId),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_a,
-                    pool_amount,
-                )
-            );
-            accounts.swap_account = old_swap_account;
-        }
-
-        // wrong nonce for authority_key
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let old_authority = accounts.authority_key;
-            let (bad_authority_key, _nonce) = Pubkey::find_program_address(
-                &[&accounts.swap_key.to_bytes()[..]],
-                &spl_token::id(),
-            );
-            accounts.authority_key = bad_authority_key;
-            assert_eq!(
-                Err(SwapError::InvalidProgramAddress.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_a,
-                    pool_amount,
-                )
-            );
-            accounts.authority_key = old_authority;
-        }
-
-        // not enough token A / B
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &depositor_key,
-                deposit_a / 2,
-                deposit_b / 2,
-                0,
-            );
-            assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_a,
-                    0,
-                )
-            );
-            assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_b,
-                    0,
-                )
-            );
-        }
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            assert_eq!(
+                Err(TokenError::MintMismatch.into()),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
+                )
+            );
+        }
 
         // wrong pool token account
         {
@@ -4407,16 +4864,27 @@ mod tests {
                 _pool_key,
                 mut _pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (
+                wrong_token_key,
+                mut wrong_token_account,
+                _token_b_key,
+                mut _token_b_account,
+                _pool_key,
+                mut _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             assert_eq!(
                 Err(TokenError::MintMismatch.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
+                    &wrong_token_key,
+                    &mut wrong_token_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
         }
@@ -4426,8 +4894,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -4435,20 +4903,22 @@ mod tests {
             assert_eq!(
                 Err(TokenError::OwnerMismatch.into()),
                 do_process_instruction(
-                    deposit_single_token_type_exact_amount_in(
+                    deposit_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &spl_token::id(),
                         &accounts.swap_key,
                         &accounts.authority_key,
                         &user_transfer_authority_key,
                         &token_a_key,
+                        &token_b_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &accounts.pool_mint_key,
                         &pool_key,
-                        DepositSingleTokenTypeExactAmountIn {
-                            source_token_amount: deposit_a,
-                            minimum_pool_token_amount: pool_amount,
+                        DepositAllTokenTypes {
+                            pool_token_amount: pool_amount.try_into().unwrap(),
+                            maximum_token_a_amount: deposit_a,
+                            maximum_token_b_amount: deposit_b,
                         },
                     )
                     .unwrap(),
@@ -4457,6 +4927,7 @@ mod tests {
                         &mut Account::default(),
                         &mut Account::default(),
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut accounts.pool_mint_account,
@@ -4472,8 +4943,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -4481,20 +4952,22 @@ mod tests {
             assert_eq!(
                 Err(SwapError::IncorrectTokenProgramId.into()),
                 do_process_instruction(
-                    deposit_single_token_type_exact_amount_in(
+                    deposit_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &wrong_key,
                         &accounts.swap_key,
                         &accounts.authority_key,
                         &accounts.authority_key,
                         &token_a_key,
+                        &token_b_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &accounts.pool_mint_key,
                         &pool_key,
-                        DepositSingleTokenTypeExactAmountIn {
-                            source_token_amount: deposit_a,
-                            minimum_pool_token_amount: pool_amount,
+                        DepositAllTokenTypes {
+                            pool_token_amount: pool_amount.try_into().unwrap(),
+                            maximum_token_a_amount: deposit_a,
+                            maximum_token_b_amount: deposit_b,
                         },
                     )
                     .unwrap(),
@@ -4503,6 +4976,7 @@ mod tests {
                         &mut Account::default(),
                         &mut Account::default(),
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut accounts.pool_mint_account,
@@ -4519,7 +4993,7 @@ mod tests {
                 token_a_key,
                 mut token_a_account,
                 token_b_key,
-                token_b_account,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -4533,14 +5007,17 @@ mod tests {
             // wrong swap token a account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
 
@@ -4551,19 +5028,22 @@ mod tests {
             let old_b_account = accounts.token_b_account;
 
             accounts.token_b_key = token_b_key;
-            accounts.token_b_account = token_b_account;
+            accounts.token_b_account = token_b_account.clone();
 
             // wrong swap token b account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
 
@@ -4576,8 +5056,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -4590,14 +5070,17 @@ mod tests {
 
             assert_eq!(
                 Err(SwapError::IncorrectPoolMint.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
 
@@ -4605,6 +5088,33 @@ mod tests {
             accounts.pool_mint_account = old_pool_account;
         }
 
+        // deposit 1 pool token fails beacuse it equates to 0 swap tokens
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    1,
+                    deposit_a,
+                    deposit_b,
+                )
+            );
+        }
+
         // slippage exceeded
         {
             let (
@@ -4615,30 +5125,36 @@ mod tests {
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            // minimum pool amount too high
+            // maximum A amount in too low
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a / 10,
-                    pool_amount,
+                    deposit_b,
                 )
             );
-            // minimum pool amount too high
+            // maximum B amount in too low
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
                     deposit_b / 10,
-                    pool_amount,
                 )
             );
         }
@@ -4660,26 +5176,17 @@ mod tests {
             let authority_key = accounts.authority_key;
             assert_eq!(
                 Err(SwapError::InvalidInput.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &authority_key,
                     &swap_token_a_key,
                     &mut swap_token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_a,
-                    pool_amount,
-                )
-            );
-            assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &authority_key,
                     &swap_token_b_key,
                     &mut swap_token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
                     deposit_b,
-                    pool_amount,
                 )
             );
         }
@@ -4695,42 +5202,30 @@ mod tests {
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             accounts
-                .deposit_single_token_type_exact_amount_in(
+                .deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
                 .unwrap();
 
             let swap_token_a =
                 spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
             assert_eq!(swap_token_a.amount, deposit_a + token_a_amount);
-
-            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.amount, 0);
-
-            accounts
-                .deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_b,
-                    pool_amount,
-                )
-                .unwrap();
             let swap_token_b =
                 spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
             assert_eq!(swap_token_b.amount, deposit_b + token_b_amount);
-
+            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.amount, 0);
             let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
             assert_eq!(token_b.amount, 0);
-
             let pool_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
             let swap_pool_account =
                 spl_token::state::Account::unpack(&accounts.pool_token_account.data).unwrap();
@@ -4744,7 +5239,7 @@ mod tests {
     }
 
     #[test]
-    fn test_withdraw_one_exact_out() {
+    fn test_withdraw() {
         let user_key = Pubkey::new_unique();
         let trade_fee_numerator = 1;
         let trade_fee_denominator = 2;
@@ -4766,8 +5261,8 @@ mod tests {
             host_fee_denominator,
         };
 
-        let token_a_amount = 100_000;
-        let token_b_amount = 200_000;
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
         let curve_type = CurveType::ConstantProduct;
         let swap_curve = SwapCurve {
             curve_type,
@@ -4778,9 +5273,9 @@ mod tests {
         let initial_a = token_a_amount / 10;
         let initial_b = token_b_amount / 10;
         let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
-        let maximum_pool_token_amount = to_u64(initial_pool / 4).unwrap();
-        let destination_a_amount = initial_a / 40;
-        let destination_b_amount = initial_b / 40;
+        let withdraw_amount = initial_pool / 4;
+        let minimum_token_a_amount = initial_a / 40;
+        let minimum_token_b_amount = initial_b / 40;
 
         let mut accounts =
             SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
@@ -4790,21 +5285,24 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
             assert_eq!(
                 Err(ProgramError::UninitializedAccount),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
@@ -4816,8 +5314,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
@@ -4827,14 +5325,17 @@ mod tests {
             accounts.swap_account = wrong_swap_account;
             assert_eq!(
                 Err(ProgramError::IncorrectProgramId),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
             accounts.swap_account = old_swap_account;
@@ -4843,8 +5344,8 @@ mod tests {
         // wrong nonce for authority_key
         {
             let (
-                _token_a_key,
-                _token_a_account,
+                token_a_key,
+                mut token_a_account,
                 token_b_key,
                 mut token_b_account,
                 pool_key,
@@ -4858,14 +5359,17 @@ mod tests {
             accounts.authority_key = bad_authority_key;
             assert_eq!(
                 Err(SwapError::InvalidProgramAddress.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
             accounts.authority_key = old_authority;
@@ -4874,8 +5378,8 @@ mod tests {
         // not enough pool tokens
         {
             let (
-                _token_a_key,
-                _token_a_account,
+                token_a_key,
+                mut token_a_account,
                 token_b_key,
                 mut token_b_account,
                 pool_key,
@@ -4885,18 +5389,54 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount / 1000,
+                to_u64(withdraw_amount).unwrap() / 2u64,
             );
             assert_eq!(
                 Err(TokenError::InsufficientFunds.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount / 2,
+                    minimum_token_b_amount / 2,
+                )
+            );
+        }
+
+        // wrong token a / b accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                withdraw_amount.try_into().unwrap(),
+            );
+            assert_eq!(
+                Err(TokenError::MintMismatch.into()),
+                accounts.withdraw_all_token_types(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
@@ -4913,20 +5453,37 @@ mod tests {
             ) = accounts.setup_token_accounts(
                 &user_key,
                 &withdrawer_key,
-                maximum_pool_token_amount,
+                initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
+            );
+            let (
+                wrong_token_a_key,
+                mut wrong_token_a_account,
+                _token_b_key,
+                _token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                withdraw_amount.try_into().unwrap(),
+                initial_b,
+                withdraw_amount.try_into().unwrap(),
             );
             assert_eq!(
                 Err(TokenError::MintMismatch.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
+                    &wrong_token_a_key,
+                    &mut wrong_token_a_account,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
@@ -4936,8 +5493,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 wrong_pool_key,
                 wrong_pool_account,
             ) = accounts.setup_token_accounts(
@@ -4945,7 +5502,7 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let (
                 _token_a_key,
@@ -4959,7 +5516,7 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let old_pool_fee_account = accounts.pool_fee_account;
             let old_pool_fee_key = accounts.pool_fee_key;
@@ -4967,15 +5524,18 @@ mod tests {
             accounts.pool_fee_key = wrong_pool_key;
             assert_eq!(
                 Err(SwapError::IncorrectFeeAccount.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
-                )
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                ),
             );
             accounts.pool_fee_account = old_pool_fee_account;
             accounts.pool_fee_key = old_pool_fee_key;
@@ -4986,8 +5546,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -4995,13 +5555,13 @@ mod tests {
                 &withdrawer_key,
                 0,
                 0,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let user_transfer_authority_key = Pubkey::new_unique();
             assert_eq!(
                 Err(TokenError::OwnerMismatch.into()),
                 do_process_instruction(
-                    withdraw_single_token_type_exact_amount_out(
+                    withdraw_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &spl_token::id(),
                         &accounts.swap_key,
@@ -5013,9 +5573,11 @@ mod tests {
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &token_a_key,
-                        WithdrawSingleTokenTypeExactAmountOut {
-                            destination_token_amount: destination_a_amount,
-                            maximum_pool_token_amount,
+                        &token_b_key,
+                        WithdrawAllTokenTypes {
+                            pool_token_amount: withdraw_amount.try_into().unwrap(),
+                            minimum_token_a_amount,
+                            minimum_token_b_amount,
                         }
                     )
                     .unwrap(),
@@ -5028,6 +5590,7 @@ mod tests {
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.pool_fee_account,
                         &mut Account::default(),
                     ],
@@ -5040,8 +5603,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5049,13 +5612,13 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let wrong_key = Pubkey::new_unique();
             assert_eq!(
                 Err(SwapError::IncorrectTokenProgramId.into()),
                 do_process_instruction(
-                    withdraw_single_token_type_exact_amount_out(
+                    withdraw_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &wrong_key,
                         &accounts.swap_key,
@@ -5067,10 +5630,12 @@ mod tests {
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &token_a_key,
-                        WithdrawSingleTokenTypeExactAmountOut {
-                            destination_token_amount: destination_a_amount,
-                            maximum_pool_token_amount,
-                        }
+                        &token_b_key,
+                        WithdrawAllTokenTypes {
+                            pool_token_amount: withdraw_amount.try_into().unwrap(),
+                            minimum_token_a_amount,
+                            minimum_token_b_amount,
+                        },
                     )
                     .unwrap(),
                     vec![
@@ -5082,6 +5647,7 @@ mod tests {
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.pool_fee_account,
                         &mut Account::default(),
                     ],
@@ -5115,14 +5681,17 @@ mod tests {
             // wrong swap token a account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
 
@@ -5138,14 +5707,17 @@ mod tests {
             // wrong swap token b account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
 
@@ -5158,8 +5730,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5178,14 +5750,17 @@ mod tests {
 
             assert_eq!(
                 Err(SwapError::IncorrectPoolMint.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
 
@@ -5193,7 +5768,7 @@ mod tests {
             accounts.pool_mint_account = old_pool_account;
         }
 
-        // slippage exceeded
+        // withdrawing 1 pool token fails because it equates to 0 output tokens
         {
             let (
                 token_a_key,
@@ -5207,32 +5782,71 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                initial_pool.try_into().unwrap(),
+            );
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                accounts.withdraw_all_token_types(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    1,
+                    0,
+                    0,
+                )
             );
+        }
 
-            // maximum pool token amount too low
+        // slippage exceeded
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+            // minimum A amount out too high
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount / 1000,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount * 10,
+                    minimum_token_b_amount,
                 )
             );
+            // minimum B amount out too high
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount / 1000,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount * 10,
                 )
             );
         }
@@ -5240,10 +5854,10 @@ mod tests {
         // invalid input: can't use swap pool tokens as destination
         {
             let (
-                _token_a_key,
-                _token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5251,34 +5865,40 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                initial_pool.try_into().unwrap(),
             );
             let swap_token_a_key = accounts.token_a_key;
             let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
             assert_eq!(
                 Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &swap_token_a_key,
                     &mut swap_token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
             let swap_token_b_key = accounts.token_b_key;
             let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
             assert_eq!(
                 Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &swap_token_b_key,
                     &mut swap_token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
@@ -5288,8 +5908,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5300,53 +5920,68 @@ mod tests {
                 initial_pool.try_into().unwrap(),
             );
 
-            let swap_token_a =
-                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-            let swap_token_b =
-                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-            let pool_mint =
-                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
-
-            let pool_token_amount = accounts
-                .swap_curve
-                .withdraw_single_token_type_exact_out(
-                    destination_a_amount.try_into().unwrap(),
-                    swap_token_a.amount.try_into().unwrap(),
-                    swap_token_b.amount.try_into().unwrap(),
-                    pool_mint.supply.try_into().unwrap(),
-                    TradeDirection::AtoB,
-                    &accounts.fees,
-                )
-                .unwrap();
-            let withdraw_fee = accounts.fees.owner_withdraw_fee(pool_token_amount).unwrap();
-
             accounts
-                .withdraw_single_token_type_exact_amount_out(
+                .withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
                 .unwrap();
 
             let swap_token_a =
                 spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-
-            assert_eq!(swap_token_a.amount, token_a_amount - destination_a_amount);
+            let swap_token_b =
+                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+            let pool_mint =
+                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+            let withdraw_fee = accounts.fees.owner_withdraw_fee(withdraw_amount).unwrap();
+            let results = accounts
+                .swap_curve
+                .calculator
+                .pool_tokens_to_trading_tokens(
+                    withdraw_amount - withdraw_fee,
+                    pool_mint.supply.try_into().unwrap(),
+                    swap_token_a.amount.try_into().unwrap(),
+                    swap_token_b.amount.try_into().unwrap(),
+                    RoundDirection::Floor,
+                )
+                .unwrap();
+            assert_eq!(
+                swap_token_a.amount,
+                token_a_amount - to_u64(results.token_a_amount).unwrap()
+            );
+            assert_eq!(
+                swap_token_b.amount,
+                token_b_amount - to_u64(results.token_b_amount).unwrap()
+            );
             let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.amount, initial_a + destination_a_amount);
-
+            assert_eq!(
+                token_a.amount,
+                initial_a + to_u64(results.token_a_amount).unwrap()
+            );
+            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            assert_eq!(
+                token_b.amount,
+                initial_b + to_u64(results.token_b_amount).unwrap()
+            );
             let pool_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
             assert_eq!(
                 pool_account.amount,
-                to_u64(initial_pool - pool_token_amount - withdraw_fee).unwrap()
+                to_u64(initial_pool - withdraw_amount).unwrap()
             );
             let fee_account =
                 spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
-            assert_eq!(fee_account.amount, to_u64(withdraw_fee).unwrap());
+            assert_eq!(
+                fee_account.amount,
+                TryInto::<u64>::try_into(withdraw_fee).unwrap()
+            );
         }
 
         // correct withdrawal from fee account
@@ -5354,234 +5989,75 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+                mut _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
 
-            let fee_a_amount = 2;
             let pool_fee_key = accounts.pool_fee_key;
             let mut pool_fee_account = accounts.pool_fee_account.clone();
             let fee_account = spl_token::state::Account::unpack(&pool_fee_account.data).unwrap();
             let pool_fee_amount = fee_account.amount;
 
-            let swap_token_a =
-                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-
-            let token_a_amount = swap_token_a.amount;
             accounts
-                .withdraw_single_token_type_exact_amount_out(
+                .withdraw_all_token_types(
                     &user_key,
                     &pool_fee_key,
                     &mut pool_fee_account,
                     &token_a_key,
                     &mut token_a_account,
-                    fee_a_amount,
-                    pool_fee_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    pool_fee_amount,
+                    0,
+                    0,
                 )
                 .unwrap();
 
             let swap_token_a =
                 spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-
-            assert_eq!(swap_token_a.amount, token_a_amount - fee_a_amount);
+            let swap_token_b =
+                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+            let pool_mint =
+                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+            let results = accounts
+                .swap_curve
+                .calculator
+                .pool_tokens_to_trading_tokens(
+                    pool_fee_amount.try_into().unwrap(),
+                    pool_mint.supply.try_into().unwrap(),
+                    swap_token_a.amount.try_into().unwrap(),
+                    swap_token_b.amount.try_into().unwrap(),
+                    RoundDirection::Floor,
+                )
+                .unwrap();
             let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.amount, initial_a + fee_a_amount);
+            assert_eq!(
+                token_a.amount,
+                TryInto::<u64>::try_into(results.token_a_amount).unwrap()
+            );
+            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            assert_eq!(
+                token_b.amount,
+                TryInto::<u64>::try_into(results.token_b_amount).unwrap()
+            );
         }
     }
 
-    fn check_valid_swap_curve(
-        fees: Fees,
-        curve_type: CurveType,
-        calculator: Box<dyn CurveCalculator>,
-        token_a_amount: u64,
-        token_b_amount: u64,
-    ) {
-        let user_key = Pubkey::new_unique();
-        let swapper_key = Pubkey::new_unique();
-
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator,
-        };
-
-        let mut accounts = SwapAccountInfo::new(
-            &user_key,
-            fees.clone(),
-            swap_curve.clone(),
-            token_a_amount,
-            token_b_amount,
-        );
-        let initial_a = token_a_amount / 5;
-        let initial_b = token_b_amount / 5;
-        accounts.initialize_swap().unwrap();
-
-        let swap_token_a_key = accounts.token_a_key;
-        let swap_token_b_key = accounts.token_b_key;
-
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            _pool_key,
-            _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-        // swap one way
-        let a_to_b_amount = initial_a / 10;
-        let minimum_token_b_amount = 0;
-        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
-        let initial_supply = pool_mint.supply;
-        accounts
-            .swap(
-                &swapper_key,
-                &token_a_key,
-                &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
-                &token_b_key,
-                &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
-            )
-            .unwrap();
-
-        let results = swap_curve
-            .swap(
-                a_to_b_amount.try_into().unwrap(),
-                token_a_amount.try_into().unwrap(),
-                token_b_amount.try_into().unwrap(),
-                TradeDirection::AtoB,
-                &fees,
-            )
-            .unwrap();
-
-        let swap_token_a =
-            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-        let token_a_amount = swap_token_a.amount;
-        assert_eq!(
-            token_a_amount,
-            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
-        );
-        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-        assert_eq!(token_a.amount, initial_a - a_to_b_amount);
-
-        let swap_token_b =
-            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-        let token_b_amount = swap_token_b.amount;
-        assert_eq!(
-            token_b_amount,
-            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
-        );
-        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-        assert_eq!(
-            token_b.amount,
-            initial_b + to_u64(results.destination_amount_swapped).unwrap()
-        );
-
-        let first_fee = swap_curve
-            .withdraw_single_token_type_exact_out(
-                results.owner_fee,
-                token_a_amount.try_into().unwrap(),
-                token_b_amount.try_into().unwrap(),
-                initial_supply.try_into().unwrap(),
-                TradeDirection::AtoB,
-                &fees,
-            )
-            .unwrap();
-        let fee_account =
-            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
-        assert_eq!(
-            fee_account.amount,
-            TryInto::<u64>::try_into(first_fee).unwrap()
-        );
-
-        let first_swap_amount = results.destination_amount_swapped;
-
-        // swap the other way
-        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
-        let initial_supply = pool_mint.supply;
-
-        let b_to_a_amount = initial_b / 10;
-        let minimum_a_amount = 0;
-        accounts
-            .swap(
-                &swapper_key,
-                &token_b_key,
-                &mut token_b_account,
-                &swap_token_b_key,
-                &swap_token_a_key,
-                &token_a_key,
-                &mut token_a_account,
-                b_to_a_amount,
-                minimum_a_amount,
-            )
-            .unwrap();
-
-        let results = swap_curve
-            .swap(
-                b_to_a_amount.try_into().unwrap(),
-                token_b_amount.try_into().unwrap(),
-                token_a_amount.try_into().unwrap(),
-                TradeDirection::BtoA,
-                &fees,
-            )
-            .unwrap();
-
-        let swap_token_a =
-            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-        let token_a_amount = swap_token_a.amount;
-        assert_eq!(
-            token_a_amount,
-            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
-        );
-        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-        assert_eq!(
-            token_a.amount,
-            initial_a - a_to_b_amount + to_u64(results.destination_amount_swapped).unwrap()
-        );
-
-        let swap_token_b =
-            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-        let token_b_amount = swap_token_b.amount;
-        assert_eq!(
-            token_b_amount,
-            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
-        );
-        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-        assert_eq!(
-            token_b.amount,
-            initial_b + to_u64(first_swap_amount).unwrap()
-                - to_u64(results.source_amount_swapped).unwrap()
-        );
-
-        let second_fee = swap_curve
-            .withdraw_single_token_type_exact_out(
-                results.owner_fee,
-                token_a_amount.try_into().unwrap(),
-                token_b_amount.try_into().unwrap(),
-                initial_supply.try_into().unwrap(),
-                TradeDirection::BtoA,
-                &fees,
-            )
-            .unwrap();
-        let fee_account =
-            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
-        assert_eq!(fee_account.amount, to_u64(first_fee + second_fee).unwrap());
-    }
-
     #[test]
-    fn test_valid_swap_curves_all_fees() {
-        // All fees
+    fn test_deposit_one_exact_in() {
+        let user_key = Pubkey::new_unique();
+        let depositor_key = Pubkey::new_unique();
         let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
+        let trade_fee_denominator = 2;
         let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
+        let owner_trade_fee_denominator = 10;
         let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
+        let owner_withdraw_fee_denominator = 5;
         let host_fee_numerator = 20;
         let host_fee_denominator = 100;
+
         let fees = Fees {
             trade_fee_numerator,
             trade_fee_denominator,
@@ -5593,341 +6069,86 @@ mod tests {
             host_fee_denominator,
         };
 
-        let token_a_amount = 10_000_000_000;
-        let token_b_amount = 50_000_000_000;
+        let token_a_amount = 1000;
+        let token_b_amount = 9000;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
 
-        check_valid_swap_curve(
-            fees.clone(),
-            CurveType::ConstantProduct,
-            Box::new(ConstantProductCurve {}),
-            token_a_amount,
-            token_b_amount,
-        );
-        let token_b_price = 1;
-        check_valid_swap_curve(
-            fees.clone(),
-            CurveType::ConstantPrice,
-            Box::new(ConstantPriceCurve { token_b_price }),
-            token_a_amount,
-            token_b_amount,
-        );
-        let token_b_offset = 10_000_000_000;
-        check_valid_swap_curve(
-            fees,
-            CurveType::Offset,
-            Box::new(OffsetCurve { token_b_offset }),
-            token_a_amount,
-            token_b_amount,
-        );
-    }
-
-    #[test]
-    fn test_valid_swap_curves_trade_fee_only() {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 0;
-        let owner_trade_fee_denominator = 0;
-        let owner_withdraw_fee_numerator = 0;
-        let owner_withdraw_fee_denominator = 0;
-        let host_fee_numerator = 0;
-        let host_fee_denominator = 0;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 10_000_000_000;
-        let token_b_amount = 50_000_000_000;
-
-        check_valid_swap_curve(
-            fees.clone(),
-            CurveType::ConstantProduct,
-            Box::new(ConstantProductCurve {}),
-            token_a_amount,
-            token_b_amount,
-        );
-        let token_b_price = 10_000;
-        check_valid_swap_curve(
-            fees.clone(),
-            CurveType::ConstantPrice,
-            Box::new(ConstantPriceCurve { token_b_price }),
-            token_a_amount,
-            token_b_amount / token_b_price,
-        );
-        let token_b_offset = 1;
-        check_valid_swap_curve(
-            fees,
-            CurveType::Offset,
-            Box::new(OffsetCurve { token_b_offset }),
-            token_a_amount,
-            token_b_amount,
-        );
-    }
-
-    #[test]
-    fn test_valid_swap_with_fee_constraints() {
-        let owner_key = Pubkey::new_unique();
-
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
-
-        let token_a_amount = 1_000_000;
-        let token_b_amount = 5_000_000;
-
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let curve = ConstantProductCurve {};
-        let swap_curve = SwapCurve {
-            curve_type: CurveType::ConstantProduct,
-            calculator: Box::new(curve),
-        };
-
-        let owner_key_str = &owner_key.to_string();
-        let valid_curve_types = &[CurveType::ConstantProduct];
-        let constraints = Some(SwapConstraints {
-            owner_key: owner_key_str,
-            valid_curve_types,
-            fees: &fees,
-        });
-        let mut accounts = SwapAccountInfo::new(
-            &owner_key,
-            fees.clone(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-        );
-
-        // initialize swap
-        do_process_instruction_with_fee_constraints(
-            initialize(
-                &SWAP_PROGRAM_ID,
-                &spl_token::id(),
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &accounts.pool_token_key,
-                accounts.nonce,
-                accounts.fees.clone(),
-                accounts.swap_curve.clone(),
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut Account::default(),
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut accounts.pool_mint_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.pool_token_account,
-                &mut Account::default(),
-            ],
-            &constraints,
-        )
-        .unwrap();
-
-        let authority_key = accounts.authority_key;
-
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &owner_key,
-            &authority_key,
-            token_a_amount,
-            token_b_amount,
-            0,
-        );
-
-        let amount_in = token_a_amount / 2;
-        let minimum_amount_out = 0;
-
-        // perform the swap
-        do_process_instruction_with_fee_constraints(
-            swap(
-                &SWAP_PROGRAM_ID,
-                &spl_token::id(),
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                &token_a_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &token_b_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                Some(&pool_key),
-                Swap {
-                    amount_in,
-                    minimum_amount_out,
-                },
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut Account::default(),
-                &mut Account::default(),
-                &mut token_a_account,
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut token_b_account,
-                &mut accounts.pool_mint_account,
-                &mut accounts.pool_fee_account,
-                &mut Account::default(),
-                &mut pool_account,
-            ],
-            &constraints,
-        )
-        .unwrap();
-
-        // check that fees were taken in the host fee account
-        let host_fee_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
-        let owner_fee_account =
-            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
-        let total_fee = owner_fee_account.amount * host_fee_denominator
-            / (host_fee_denominator - host_fee_numerator);
-        assert_eq!(
-            total_fee,
-            host_fee_account.amount + owner_fee_account.amount
-        );
-    }
-
-    #[test]
-    fn test_invalid_swap() {
-        let user_key = Pubkey::new_unique();
-        let swapper_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 4;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 9;
-        let host_fee_denominator = 100;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 5000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Box::new(ConstantProductCurve {}),
-        };
         let mut accounts =
             SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
 
-        let initial_a = token_a_amount / 5;
-        let initial_b = token_b_amount / 5;
-        let minimum_token_b_amount = initial_b / 2;
-
-        let swap_token_a_key = accounts.token_a_key;
-        let swap_token_b_key = accounts.token_b_key;
+        let deposit_a = token_a_amount / 10;
+        let deposit_b = token_b_amount / 10;
+        let pool_amount = to_u64(INITIAL_SWAP_POOL_AMOUNT / 100).unwrap();
 
         // swap not initialized
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             assert_eq!(
                 Err(ProgramError::UninitializedAccount),
-                accounts.swap(
-                    &swapper_key,
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
                 )
             );
         }
 
         accounts.initialize_swap().unwrap();
 
-        // wrong swap account program id
+        // wrong owner for swap account
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             let old_swap_account = accounts.swap_account;
             let mut wrong_swap_account = old_swap_account.clone();
             wrong_swap_account.owner = spl_token::id();
             accounts.swap_account = wrong_swap_account;
             assert_eq!(
                 Err(ProgramError::IncorrectProgramId),
-                accounts.swap(
-                    &swapper_key,
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
                 )
             );
             accounts.swap_account = old_swap_account;
         }
 
-        // wrong nonce
+        // wrong nonce for authority_key
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             let old_authority = accounts.authority_key;
             let (bad_authority_key, _nonce) = Pubkey::find_program_address(
                 &[&accounts.swap_key.to_bytes()[..]],
@@ -5936,22 +6157,62 @@ mod tests {
             accounts.authority_key = bad_authority_key;
             assert_eq!(
                 Err(SwapError::InvalidProgramAddress.into()),
-                accounts.swap(
-                    &swapper_key,
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+            accounts.authority_key = old_authority;
+        }
+
+        // not enough token A / B
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &depositor_key,
+                deposit_a / 2,
+                deposit_b / 2,
+                0,
+            );
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    0,
+                )
+            );
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
                     &token_b_key,
                     &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b,
+                    0,
                 )
             );
-            accounts.authority_key = old_authority;
         }
 
-        // wrong token program id
+        // wrong pool token account
         {
             let (
                 token_a_key,
@@ -5959,28 +6220,50 @@ mod tests {
                 token_b_key,
                 mut token_b_account,
                 _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let wrong_program_id = Pubkey::new_unique();
+                mut _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
+                Err(TokenError::MintMismatch.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+        }
+
+        // no approval
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let user_transfer_authority_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(TokenError::OwnerMismatch.into()),
                 do_process_instruction(
-                    swap(
+                    deposit_single_token_type_exact_amount_in(
                         &SWAP_PROGRAM_ID,
-                        &wrong_program_id,
+                        &spl_token::id(),
                         &accounts.swap_key,
                         &accounts.authority_key,
-                        &accounts.authority_key,
+                        &user_transfer_authority_key,
                         &token_a_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
-                        &token_b_key,
                         &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        None,
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: minimum_token_b_amount,
+                        &pool_key,
+                        DepositSingleTokenTypeExactAmountIn {
+                            source_token_amount: deposit_a,
+                            minimum_pool_token_amount: pool_amount,
                         },
                     )
                     .unwrap(),
@@ -5991,71 +6274,42 @@ mod tests {
                         &mut token_a_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
-                        &mut token_b_account,
                         &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
+                        &mut pool_account,
                         &mut Account::default(),
                     ],
-                ),
-            );
-        }
-
-        // not enough token a to swap
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a * 2,
-                    minimum_token_b_amount * 2,
                 )
             );
         }
 
-        // wrong swap token A / B accounts
+        // wrong token program id
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let user_transfer_key = Pubkey::new_unique();
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let wrong_key = Pubkey::new_unique();
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
+                Err(SwapError::IncorrectTokenProgramId.into()),
                 do_process_instruction(
-                    swap(
+                    deposit_single_token_type_exact_amount_in(
                         &SWAP_PROGRAM_ID,
-                        &spl_token::id(),
+                        &wrong_key,
                         &accounts.swap_key,
                         &accounts.authority_key,
-                        &user_transfer_key,
-                        &token_a_key,
+                        &accounts.authority_key,
                         &token_a_key,
-                        &token_b_key,
-                        &token_b_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
                         &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        None,
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: minimum_token_b_amount,
+                        &pool_key,
+                        DepositSingleTokenTypeExactAmountIn {
+                            source_token_amount: deposit_a,
+                            minimum_pool_token_amount: pool_amount,
                         },
                     )
                     .unwrap(),
@@ -6063,610 +6317,3733 @@ mod tests {
                         &mut accounts.swap_account,
                         &mut Account::default(),
                         &mut Account::default(),
-                        &mut token_a_account.clone(),
                         &mut token_a_account,
-                        &mut token_b_account.clone(),
-                        &mut token_b_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
                         &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
+                        &mut pool_account,
                         &mut Account::default(),
                     ],
-                ),
+                )
             );
         }
 
-        // wrong user token A / B accounts
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            assert_eq!(
-                Err(TokenError::MintMismatch.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    initial_a,
-                    minimum_token_b_amount,
-                )
-            );
-        }
+        // wrong swap token accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+
+            let old_a_key = accounts.token_a_key;
+            let old_a_account = accounts.token_a_account;
+
+            accounts.token_a_key = token_a_key;
+            accounts.token_a_account = token_a_account.clone();
+
+            // wrong swap token a account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+
+            accounts.token_a_key = old_a_key;
+            accounts.token_a_account = old_a_account;
+
+            let old_b_key = accounts.token_b_key;
+            let old_b_account = accounts.token_b_account;
+
+            accounts.token_b_key = token_b_key;
+            accounts.token_b_account = token_b_account;
+
+            // wrong swap token b account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+
+            accounts.token_b_key = old_b_key;
+            accounts.token_b_account = old_b_account;
+        }
+
+        // wrong mint
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (pool_mint_key, pool_mint_account) =
+                create_mint(&spl_token::id(), &accounts.authority_key, None);
+            let old_pool_key = accounts.pool_mint_key;
+            let old_pool_account = accounts.pool_mint_account;
+            accounts.pool_mint_key = pool_mint_key;
+            accounts.pool_mint_account = pool_mint_account;
+
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+
+            accounts.pool_mint_key = old_pool_key;
+            accounts.pool_mint_account = old_pool_account;
+        }
+
+        // slippage exceeded
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            // minimum pool amount too high
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a / 10,
+                    pool_amount,
+                )
+            );
+            // minimum pool amount too high
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b / 10,
+                    pool_amount,
+                )
+            );
+        }
+
+        // invalid input: can't use swap pool tokens as source
+        {
+            let (
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let swap_token_a_key = accounts.token_a_key;
+            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
+            let swap_token_b_key = accounts.token_b_key;
+            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
+            let authority_key = accounts.authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &authority_key,
+                    &swap_token_a_key,
+                    &mut swap_token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &authority_key,
+                    &swap_token_b_key,
+                    &mut swap_token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b,
+                    pool_amount,
+                )
+            );
+        }
+
+        // correctly deposit
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            accounts
+                .deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+                .unwrap();
+
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+            assert_eq!(swap_token_a.amount, deposit_a + token_a_amount);
+
+            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.amount, 0);
+
+            accounts
+                .deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b,
+                    pool_amount,
+                )
+                .unwrap();
+            let swap_token_b =
+                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+            assert_eq!(swap_token_b.amount, deposit_b + token_b_amount);
+
+            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            assert_eq!(token_b.amount, 0);
+
+            let pool_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
+            let swap_pool_account =
+                spl_token::state::Account::unpack(&accounts.pool_token_account.data).unwrap();
+            let pool_mint =
+                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+            assert_eq!(
+                pool_mint.supply,
+                pool_account.amount + swap_pool_account.amount
+            );
+        }
+    }
+
+    #[test]
+    fn test_withdraw_one_exact_out() {
+        let user_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 7;
+        let host_fee_denominator = 100;
+
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 100_000;
+        let token_b_amount = 200_000;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+
+        let withdrawer_key = Pubkey::new_unique();
+        let initial_a = token_a_amount / 10;
+        let initial_b = token_b_amount / 10;
+        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
+        let maximum_pool_token_amount = to_u64(initial_pool / 4).unwrap();
+        let destination_a_amount = initial_a / 40;
+        let destination_b_amount = initial_b / 40;
+
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+
+        // swap not initialized
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // wrong owner for swap account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+            let old_swap_account = accounts.swap_account;
+            let mut wrong_swap_account = old_swap_account.clone();
+            wrong_swap_account.owner = spl_token::id();
+            accounts.swap_account = wrong_swap_account;
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+            accounts.swap_account = old_swap_account;
+        }
+
+        // wrong nonce for authority_key
+        {
+            let (
+                _token_a_key,
+                _token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+            let old_authority = accounts.authority_key;
+            let (bad_authority_key, _nonce) = Pubkey::find_program_address(
+                &[&accounts.swap_key.to_bytes()[..]],
+                &spl_token::id(),
+            );
+            accounts.authority_key = bad_authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidProgramAddress.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+            accounts.authority_key = old_authority;
+        }
+
+        // not enough pool tokens
+        {
+            let (
+                _token_a_key,
+                _token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount / 1000,
+            );
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+        }
+
+        // wrong pool token account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                maximum_pool_token_amount,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            assert_eq!(
+                Err(TokenError::MintMismatch.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+        }
+
+        // wrong pool fee account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                wrong_pool_key,
+                wrong_pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let (
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let old_pool_fee_account = accounts.pool_fee_account;
+            let old_pool_fee_key = accounts.pool_fee_key;
+            accounts.pool_fee_account = wrong_pool_account;
+            accounts.pool_fee_key = wrong_pool_key;
+            assert_eq!(
+                Err(SwapError::IncorrectFeeAccount.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+            accounts.pool_fee_account = old_pool_fee_account;
+            accounts.pool_fee_key = old_pool_fee_key;
+        }
+
+        // no approval
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                0,
+                0,
+                maximum_pool_token_amount,
+            );
+            let user_transfer_authority_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(TokenError::OwnerMismatch.into()),
+                do_process_instruction(
+                    withdraw_single_token_type_exact_amount_out(
+                        &SWAP_PROGRAM_ID,
+                        &spl_token::id(),
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &user_transfer_authority_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &pool_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_a_key,
+                        WithdrawSingleTokenTypeExactAmountOut {
+                            destination_token_amount: destination_a_amount,
+                            maximum_pool_token_amount,
+                        }
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut accounts.pool_mint_account,
+                        &mut pool_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_a_account,
+                        &mut accounts.pool_fee_account,
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+
+        // wrong token program id
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let wrong_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                do_process_instruction(
+                    withdraw_single_token_type_exact_amount_out(
+                        &SWAP_PROGRAM_ID,
+                        &wrong_key,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &pool_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_a_key,
+                        WithdrawSingleTokenTypeExactAmountOut {
+                            destination_token_amount: destination_a_amount,
+                            maximum_pool_token_amount,
+                        }
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut accounts.pool_mint_account,
+                        &mut pool_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_a_account,
+                        &mut accounts.pool_fee_account,
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+
+        // wrong swap token accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+
+            let old_a_key = accounts.token_a_key;
+            let old_a_account = accounts.token_a_account;
+
+            accounts.token_a_key = token_a_key;
+            accounts.token_a_account = token_a_account.clone();
+
+            // wrong swap token a account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+
+            accounts.token_a_key = old_a_key;
+            accounts.token_a_account = old_a_account;
+
+            let old_b_key = accounts.token_b_key;
+            let old_b_account = accounts.token_b_account;
+
+            accounts.token_b_key = token_b_key;
+            accounts.token_b_account = token_b_account.clone();
+
+            // wrong swap token b account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+
+            accounts.token_b_key = old_b_key;
+            accounts.token_b_account = old_b_account;
+        }
+
+        // wrong mint
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+            let (pool_mint_key, pool_mint_account) =
+                create_mint(&spl_token::id(), &accounts.authority_key, None);
+            let old_pool_key = accounts.pool_mint_key;
+            let old_pool_account = accounts.pool_mint_account;
+            accounts.pool_mint_key = pool_mint_key;
+            accounts.pool_mint_account = pool_mint_account;
+
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+
+            accounts.pool_mint_key = old_pool_key;
+            accounts.pool_mint_account = old_pool_account;
+        }
+
+        // slippage exceeded
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+
+            // maximum pool token amount too low
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount / 1000,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount / 1000,
+                )
+            );
+        }
+
+        // invalid input: can't use swap pool tokens as destination
+        {
+            let (
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let swap_token_a_key = accounts.token_a_key;
+            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &swap_token_a_key,
+                    &mut swap_token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+            let swap_token_b_key = accounts.token_b_key;
+            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &swap_token_b_key,
+                    &mut swap_token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+        }
+
+        // correct withdrawal
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+            let swap_token_b =
+                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+            let pool_mint =
+                spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+
+            let pool_token_amount = accounts
+                .swap_curve
+                .withdraw_single_token_type_exact_out(
+                    destination_a_amount.try_into().unwrap(),
+                    swap_token_a.amount.try_into().unwrap(),
+                    swap_token_b.amount.try_into().unwrap(),
+                    pool_mint.supply.try_into().unwrap(),
+                    TradeDirection::AtoB,
+                    &accounts.fees,
+                )
+                .unwrap();
+            let withdraw_fee = accounts.fees.owner_withdraw_fee(pool_token_amount).unwrap();
+
+            accounts
+                .withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+                .unwrap();
+
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+
+            assert_eq!(swap_token_a.amount, token_a_amount - destination_a_amount);
+            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.amount, initial_a + destination_a_amount);
+
+            let pool_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
+            assert_eq!(
+                pool_account.amount,
+                to_u64(initial_pool - pool_token_amount - withdraw_fee).unwrap()
+            );
+            let fee_account =
+                spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+            assert_eq!(fee_account.amount, to_u64(withdraw_fee).unwrap());
+        }
+
+        // correct withdrawal from fee account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+
+            let fee_a_amount = 2;
+            let pool_fee_key = accounts.pool_fee_key;
+            let mut pool_fee_account = accounts.pool_fee_account.clone();
+            let fee_account = spl_token::state::Account::unpack(&pool_fee_account.data).unwrap();
+            let pool_fee_amount = fee_account.amount;
+
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+
+            let token_a_amount = swap_token_a.amount;
+            accounts
+                .withdraw_single_token_type_exact_amount_out(
+                    &user_key,
+                    &pool_fee_key,
+                    &mut pool_fee_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    fee_a_amount,
+                    pool_fee_amount,
+                )
+                .unwrap();
+
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+
+            assert_eq!(swap_token_a.amount, token_a_amount - fee_a_amount);
+            let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.amount, initial_a + fee_a_amount);
+        }
+    }
+
+    fn check_valid_swap_curve(
+        fees: Fees,
+        curve_type: CurveType,
+        calculator: Box<dyn CurveCalculator>,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            swap_curve.clone(),
+            token_a_amount,
+            token_b_amount,
+        );
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        accounts.initialize_swap().unwrap();
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+        // swap one way
+        let a_to_b_amount = initial_a / 10;
+        let minimum_token_b_amount = 0;
+        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+        let initial_supply = pool_mint.supply;
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+            .unwrap();
+
+        let results = swap_curve
+            .swap(
+                a_to_b_amount.try_into().unwrap(),
+                token_a_amount.try_into().unwrap(),
+                token_b_amount.try_into().unwrap(),
+                TradeDirection::AtoB,
+                &fees,
+            )
+            .unwrap();
+
+        let swap_token_a =
+            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        let token_a_amount = swap_token_a.amount;
+        assert_eq!(
+            token_a_amount,
+            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
+        );
+        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.amount, initial_a - a_to_b_amount);
+
+        let swap_token_b =
+            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        let token_b_amount = swap_token_b.amount;
+        assert_eq!(
+            token_b_amount,
+            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
+        );
+        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+        assert_eq!(
+            token_b.amount,
+            initial_b + to_u64(results.destination_amount_swapped).unwrap()
+        );
+
+        let first_fee = swap_curve
+            .withdraw_single_token_type_exact_out(
+                results.owner_fee,
+                token_a_amount.try_into().unwrap(),
+                token_b_amount.try_into().unwrap(),
+                initial_supply.try_into().unwrap(),
+                TradeDirection::AtoB,
+                &fees,
+            )
+            .unwrap();
+        let fee_account =
+            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert_eq!(
+            fee_account.amount,
+            TryInto::<u64>::try_into(first_fee).unwrap()
+        );
+
+        let first_swap_amount = results.destination_amount_swapped;
+
+        // swap the other way
+        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+        let initial_supply = pool_mint.supply;
+
+        let b_to_a_amount = initial_b / 10;
+        let minimum_a_amount = 0;
+        accounts
+            .swap(
+                &swapper_key,
+                &token_b_key,
+                &mut token_b_account,
+                &swap_token_b_key,
+                &swap_token_a_key,
+                &token_a_key,
+                &mut token_a_account,
+                b_to_a_amount,
+                minimum_a_amount,
+            )
+            .unwrap();
+
+        let results = swap_curve
+            .swap(
+                b_to_a_amount.try_into().unwrap(),
+                token_b_amount.try_into().unwrap(),
+                token_a_amount.try_into().unwrap(),
+                TradeDirection::BtoA,
+                &fees,
+            )
+            .unwrap();
+
+        let swap_token_a =
+            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        let token_a_amount = swap_token_a.amount;
+        assert_eq!(
+            token_a_amount,
+            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
+        );
+        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+        assert_eq!(
+            token_a.amount,
+            initial_a - a_to_b_amount + to_u64(results.destination_amount_swapped).unwrap()
+        );
+
+        let swap_token_b =
+            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        let token_b_amount = swap_token_b.amount;
+        assert_eq!(
+            token_b_amount,
+            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
+        );
+        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+        assert_eq!(
+            token_b.amount,
+            initial_b + to_u64(first_swap_amount).unwrap()
+                - to_u64(results.source_amount_swapped).unwrap()
+        );
+
+        let second_fee = swap_curve
+            .withdraw_single_token_type_exact_out(
+                results.owner_fee,
+                token_a_amount.try_into().unwrap(),
+                token_b_amount.try_into().unwrap(),
+                initial_supply.try_into().unwrap(),
+                TradeDirection::BtoA,
+                &fees,
+            )
+            .unwrap();
+        let fee_account =
+            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert_eq!(fee_account.amount, to_u64(first_fee + second_fee).unwrap());
+    }
+
+    #[test]
+    fn test_valid_swap_curves_all_fees() {
+        // All fees
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 20;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 10_000_000_000;
+        let token_b_amount = 50_000_000_000;
+
+        check_valid_swap_curve(
+            fees.clone(),
+            CurveType::ConstantProduct,
+            Box::new(ConstantProductCurve {}),
+            token_a_amount,
+            token_b_amount,
+        );
+        let token_b_price = 1;
+        check_valid_swap_curve(
+            fees.clone(),
+            CurveType::ConstantPrice,
+            Box::new(ConstantPriceCurve { token_b_price }),
+            token_a_amount,
+            token_b_amount,
+        );
+        let token_b_offset = 10_000_000_000;
+        check_valid_swap_curve(
+            fees,
+            CurveType::Offset,
+            Box::new(OffsetCurve { token_b_offset }),
+            token_a_amount,
+            token_b_amount,
+        );
+    }
+
+    #[test]
+    fn test_valid_swap_curves_trade_fee_only() {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 0;
+        let owner_trade_fee_denominator = 0;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 0;
+        let host_fee_numerator = 0;
+        let host_fee_denominator = 0;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 10_000_000_000;
+        let token_b_amount = 50_000_000_000;
+
+        check_valid_swap_curve(
+            fees.clone(),
+            CurveType::ConstantProduct,
+            Box::new(ConstantProductCurve {}),
+            token_a_amount,
+            token_b_amount,
+        );
+        let token_b_price = 10_000;
+        check_valid_swap_curve(
+            fees.clone(),
+            CurveType::ConstantPrice,
+            Box::new(ConstantPriceCurve { token_b_price }),
+            token_a_amount,
+            token_b_amount / token_b_price,
+        );
+        let token_b_offset = 1;
+        check_valid_swap_curve(
+            fees,
+            CurveType::Offset,
+            Box::new(OffsetCurve { token_b_offset }),
+            token_a_amount,
+            token_b_amount,
+        );
+    }
+
+    #[test]
+    fn test_valid_swap_with_fee_constraints() {
+        let owner_key = Pubkey::new_unique();
+
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 5_000_000;
+
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let curve = ConstantProductCurve {};
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(curve),
+        };
+
+        let owner_key_str = &owner_key.to_string();
+        let valid_curve_types = &[CurveType::ConstantProduct];
+        let constraints = Some(SwapConstraints {
+            owner_key: owner_key_str,
+            valid_curve_types,
+            valid_oracle_program_ids: &[],
+            fees: &fees,
+            price_range: None,
+        });
+        let mut accounts = SwapAccountInfo::new(
+            &owner_key,
+            fees.clone(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+        );
+
+        // initialize swap
+        do_process_instruction_with_fee_constraints(
+            initialize(
+                &SWAP_PROGRAM_ID,
+                &spl_token::id(),
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.pool_token_key,
+                None,
+                accounts.nonce,
+                accounts.fees.clone(),
+                accounts.swap_curve.clone(),
+                COption::None,
+                DynamicFees::default(),
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut Account::default(),
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.pool_token_account,
+                &mut Account::default(),
+            ],
+            &constraints,
+        )
+        .unwrap();
+
+        let authority_key = accounts.authority_key;
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &owner_key,
+            &authority_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
+
+        let amount_in = token_a_amount / 2;
+        let minimum_amount_out = 0;
+
+        // perform the swap
+        do_process_instruction_with_fee_constraints(
+            swap(
+                &SWAP_PROGRAM_ID,
+                &spl_token::id(),
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.authority_key,
+                &token_a_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                None,
+                Some(&pool_key),
+                Swap {
+                    amount_in,
+                    minimum_amount_out,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut Account::default(),
+                &mut Account::default(),
+                &mut token_a_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut Account::default(),
+                &mut pool_account,
+            ],
+            &constraints,
+        )
+        .unwrap();
+
+        // check that fees were taken in the host fee account
+        let host_fee_account = spl_token::state::Account::unpack(&pool_account.data).unwrap();
+        let owner_fee_account =
+            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+        let total_fee = owner_fee_account.amount * host_fee_denominator
+            / (host_fee_denominator - host_fee_numerator);
+        assert_eq!(
+            total_fee,
+            host_fee_account.amount + owner_fee_account.amount
+        );
+    }
+
+    #[test]
+    fn test_invalid_swap() {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 4;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 9;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 1000;
+        let token_b_amount = 5000;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let minimum_token_b_amount = initial_b / 2;
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // swap not initialized
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // wrong swap account program id
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let old_swap_account = accounts.swap_account;
+            let mut wrong_swap_account = old_swap_account.clone();
+            wrong_swap_account.owner = spl_token::id();
+            accounts.swap_account = wrong_swap_account;
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            accounts.swap_account = old_swap_account;
+        }
+
+        // wrong nonce
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let old_authority = accounts.authority_key;
+            let (bad_authority_key, _nonce) = Pubkey::find_program_address(
+                &[&accounts.swap_key.to_bytes()[..]],
+                &spl_token::id(),
+            );
+            accounts.authority_key = bad_authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidProgramAddress.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            accounts.authority_key = old_authority;
+        }
+
+        // wrong token program id
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let wrong_program_id = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                do_process_instruction(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &wrong_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        None,
+                        None,
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: minimum_token_b_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut Account::default(),
+                    ],
+                ),
+            );
+        }
+
+        // not enough token a to swap
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a * 2,
+                    minimum_token_b_amount * 2,
+                )
+            );
+        }
+
+        // wrong swap token A / B accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let user_transfer_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                do_process_instruction(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &spl_token::id(),
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &user_transfer_key,
+                        &token_a_key,
+                        &token_a_key,
+                        &token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        None,
+                        None,
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: minimum_token_b_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account.clone(),
+                        &mut token_a_account,
+                        &mut token_b_account.clone(),
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut Account::default(),
+                    ],
+                ),
+            );
+        }
+
+        // wrong user token A / B accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(TokenError::MintMismatch.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // swap from a to a
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account.clone(),
+                    &swap_token_a_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // incorrect mint provided
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let (pool_mint_key, pool_mint_account) =
+                create_mint(&spl_token::id(), &accounts.authority_key, None);
+            let old_pool_key = accounts.pool_mint_key;
+            let old_pool_account = accounts.pool_mint_account;
+            accounts.pool_mint_key = pool_mint_key;
+            accounts.pool_mint_account = pool_mint_account;
+
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+
+            accounts.pool_mint_key = old_pool_key;
+            accounts.pool_mint_account = old_pool_account;
+        }
+
+        // incorrect fee account provided
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                wrong_pool_key,
+                wrong_pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let old_pool_fee_account = accounts.pool_fee_account;
+            let old_pool_fee_key = accounts.pool_fee_key;
+            accounts.pool_fee_account = wrong_pool_account;
+            accounts.pool_fee_key = wrong_pool_key;
+            assert_eq!(
+                Err(SwapError::IncorrectFeeAccount.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            accounts.pool_fee_account = old_pool_fee_account;
+            accounts.pool_fee_key = old_pool_fee_key;
+        }
+
+        // no approval
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let user_transfer_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(TokenError::OwnerMismatch.into()),
+                do_process_instruction(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &spl_token::id(),
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &user_transfer_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        None,
+                        None,
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: minimum_token_b_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut Account::default(),
+                    ],
+                ),
+            );
+        }
+
+        // output token value 0
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &swap_token_b_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    1,
+                    1,
+                )
+            );
+        }
+
+        // slippage exceeded: minimum out amount too high
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount * 2,
+                )
+            );
+        }
+
+        // invalid input: can't use swap pool as user source / dest
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
+            let authority_key = accounts.authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &authority_key,
+                    &swap_token_a_key,
+                    &mut swap_token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &swap_token_b_key,
+                    &mut swap_token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // still correct: constraint specified, no host fee account
+        {
+            let authority_key = accounts.authority_key;
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
+            let owner_key = &swapper_key.to_string();
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types: &[],
+                valid_oracle_program_ids: &[],
+                fees: &fees,
+                price_range: None,
+            });
+            do_process_instruction_with_fee_constraints(
+                swap(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &accounts.swap_key,
+                    &accounts.authority_key,
+                    &accounts.authority_key,
+                    &token_a_key,
+                    &accounts.token_a_key,
+                    &accounts.token_b_key,
+                    &token_b_key,
+                    &accounts.pool_mint_key,
+                    &accounts.pool_fee_key,
+                    None,
+                    None,
+                    Swap {
+                        amount_in: initial_a,
+                        minimum_amount_out: minimum_token_b_amount,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut Account::default(),
+                    &mut Account::default(),
+                    &mut token_a_account,
+                    &mut accounts.token_a_account,
+                    &mut accounts.token_b_account,
+                    &mut token_b_account,
+                    &mut accounts.pool_mint_account,
+                    &mut accounts.pool_fee_account,
+                    &mut Account::default(),
+                ],
+                &constraints,
+            )
+            .unwrap();
+        }
+
+        // invalid mint for host fee account
+        {
+            let authority_key = accounts.authority_key;
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
+            let (
+                bad_token_a_key,
+                mut bad_token_a_account,
+                _token_b_key,
+                mut _token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
+            let owner_key = &swapper_key.to_string();
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types: &[],
+                valid_oracle_program_ids: &[],
+                fees: &fees,
+                price_range: None,
+            });
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                do_process_instruction_with_fee_constraints(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &spl_token::id(),
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        None,
+                        Some(&bad_token_a_key),
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: 0,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut Account::default(),
+                        &mut bad_token_a_account,
+                    ],
+                    &constraints,
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn test_route_swap() {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 100,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 200,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        };
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+
+        // first pool trades A for B, second pool trades B for C
+        let mut first_pool = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            swap_curve.clone(),
+            1_000_000,
+            2_000_000,
+        );
+        first_pool.initialize_swap().unwrap();
+        let mut second_pool =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, 3_000_000, 1_500_000);
+        let (token_b_key, token_b_account) = mint_token(
+            &spl_token::id(),
+            &first_pool.token_b_mint_key,
+            &mut first_pool.token_b_mint_account,
+            &user_key,
+            &second_pool.authority_key,
+            3_000_000,
+        );
+        second_pool.token_a_key = token_b_key;
+        second_pool.token_a_account = token_b_account;
+        second_pool.token_a_mint_key = first_pool.token_b_mint_key;
+        second_pool.token_a_mint_account = first_pool.token_b_mint_account.clone();
+        second_pool.initialize_swap().unwrap();
+
+        let initial_a = 10_000;
+        let (token_a_key, mut token_a_account, _, _, _, _) =
+            first_pool.setup_token_accounts(&user_key, &swapper_key, initial_a, 0, 0);
+        let (_, _, token_c_key, mut token_c_account, _, _) =
+            second_pool.setup_token_accounts(&user_key, &swapper_key, 0, 0, 0);
+
+        let amount_in = initial_a / 2;
+        let first_result = first_pool
+            .swap_curve
+            .swap(
+                amount_in.into(),
+                1_000_000,
+                2_000_000,
+                TradeDirection::AtoB,
+                &first_pool.fees,
+            )
+            .unwrap();
+        let second_result = second_pool
+            .swap_curve
+            .swap(
+                first_result.destination_amount_swapped,
+                3_000_000,
+                1_500_000,
+                TradeDirection::AtoB,
+                &second_pool.fees,
+            )
+            .unwrap();
+
+        let user_transfer_key = Pubkey::new_unique();
+        do_process_instruction(
+            approve(
+                &spl_token::id(),
+                &token_a_key,
+                &user_transfer_key,
+                &swapper_key,
+                &[],
+                amount_in,
+            )
+            .unwrap(),
+            vec![
+                &mut token_a_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+        .unwrap();
+
+        let hops = [
+            RouteSwapHop {
+                swap_pubkey: first_pool.swap_key,
+                authority_pubkey: first_pool.authority_key,
+                swap_source_pubkey: first_pool.token_a_key,
+                swap_destination_pubkey: first_pool.token_b_key,
+                pool_mint_pubkey: first_pool.pool_mint_key,
+                pool_fee_pubkey: first_pool.pool_fee_key,
+            },
+            RouteSwapHop {
+                swap_pubkey: second_pool.swap_key,
+                authority_pubkey: second_pool.authority_key,
+                swap_source_pubkey: second_pool.token_a_key,
+                swap_destination_pubkey: second_pool.token_b_key,
+                pool_mint_pubkey: second_pool.pool_mint_key,
+                pool_fee_pubkey: second_pool.pool_fee_key,
+            },
+        ];
+        let route_swap_instruction = |minimum_amount_out| {
+            route_swap(
+                &SWAP_PROGRAM_ID,
+                &spl_token::id(),
+                &user_transfer_key,
+                &token_a_key,
+                &token_c_key,
+                &hops,
+                RouteSwap {
+                    amount_in,
+                    minimum_amount_out,
+                },
+            )
+            .unwrap()
+        };
+
+        // route exceeds slippage on the last pool
+        {
+            let minimum_amount_out = to_u64(second_result.destination_amount_swapped).unwrap() + 1;
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                do_process_instruction(
+                    route_swap_instruction(minimum_amount_out),
+                    vec![
+                        &mut Account::default(),
+                        &mut token_a_account.clone(),
+                        &mut token_c_account.clone(),
+                        &mut Account::default(),
+                        &mut first_pool.swap_account.clone(),
+                        &mut Account::default(),
+                        &mut first_pool.token_a_account.clone(),
+                        &mut first_pool.token_b_account.clone(),
+                        &mut first_pool.pool_mint_account.clone(),
+                        &mut first_pool.pool_fee_account.clone(),
+                        &mut second_pool.swap_account.clone(),
+                        &mut Account::default(),
+                        &mut second_pool.token_a_account.clone(),
+                        &mut second_pool.token_b_account.clone(),
+                        &mut second_pool.pool_mint_account.clone(),
+                        &mut second_pool.pool_fee_account.clone(),
+                    ],
+                )
+            );
+        }
+
+        // route missing the accounts of its second pool
+        {
+            let mut instruction = route_swap_instruction(0);
+            instruction
+                .accounts
+                .truncate(4 + ROUTE_SWAP_HOP_ACCOUNTS_LEN + 1);
+            assert_eq!(
+                Err(SwapError::InvalidRoute.into()),
+                do_process_instruction(
+                    instruction,
+                    vec![
+                        &mut Account::default(),
+                        &mut token_a_account.clone(),
+                        &mut token_c_account.clone(),
+                        &mut Account::default(),
+                        &mut first_pool.swap_account.clone(),
+                        &mut Account::default(),
+                        &mut first_pool.token_a_account.clone(),
+                        &mut first_pool.token_b_account.clone(),
+                        &mut first_pool.pool_mint_account.clone(),
+                        &mut first_pool.pool_fee_account.clone(),
+                        &mut second_pool.swap_account.clone(),
+                    ],
+                )
+            );
+        }
+
+        // too many pools
+        {
+            assert_eq!(
+                Err(SwapError::InvalidRoute.into()),
+                route_swap(
+                    &SWAP_PROGRAM_ID,
+                    &spl_token::id(),
+                    &user_transfer_key,
+                    &token_a_key,
+                    &token_c_key,
+                    &vec![hops[0].clone(); MAX_ROUTE_SWAP_HOPS + 1],
+                    RouteSwap {
+                        amount_in,
+                        minimum_amount_out: 0,
+                    },
+                )
+            );
+        }
+
+        // route through both pools
+        let minimum_amount_out = to_u64(second_result.destination_amount_swapped).unwrap();
+        do_process_instruction(
+            route_swap_instruction(minimum_amount_out),
+            vec![
+                &mut Account::default(),
+                &mut token_a_account,
+                &mut token_c_account,
+                &mut Account::default(),
+                &mut first_pool.swap_account,
+                &mut Account::default(),
+                &mut first_pool.token_a_account,
+                &mut first_pool.token_b_account,
+                &mut first_pool.pool_mint_account,
+                &mut first_pool.pool_fee_account,
+                &mut second_pool.swap_account,
+                &mut Account::default(),
+                &mut second_pool.token_a_account,
+                &mut second_pool.token_b_account,
+                &mut second_pool.pool_mint_account,
+                &mut second_pool.pool_fee_account,
+            ],
+        )
+        .unwrap();
+
+        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+        assert_eq!(
+            token_a.amount,
+            initial_a - to_u64(first_result.source_amount_swapped).unwrap()
+        );
+        let token_c = spl_token::state::Account::unpack(&token_c_account.data).unwrap();
+        assert_eq!(token_c.amount, minimum_amount_out);
+
+        // the intermediate tokens moved from the first pool to the second
+        let first_pool_token_b =
+            spl_token::state::Account::unpack(&first_pool.token_b_account.data).unwrap();
+        assert_eq!(
+            first_pool_token_b.amount,
+            2_000_000 - to_u64(first_result.destination_amount_swapped).unwrap()
+        );
+        let second_pool_token_b =
+            spl_token::state::Account::unpack(&second_pool.token_a_account.data).unwrap();
+        assert_eq!(
+            second_pool_token_b.amount,
+            3_000_000 + to_u64(first_result.destination_amount_swapped).unwrap()
+        );
+
+        // both pools collected owner trading fees
+        let first_pool_fee =
+            spl_token::state::Account::unpack(&first_pool.pool_fee_account.data).unwrap();
+        assert!(first_pool_fee.amount > 0);
+        let second_pool_fee =
+            spl_token::state::Account::unpack(&second_pool.pool_fee_account.data).unwrap();
+        assert!(second_pool_fee.amount > 0);
+    }
+
+    #[test]
+    fn test_swap_exact_amount_out() {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 100,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 200,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        };
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 2_000_000;
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+        accounts.initialize_swap().unwrap();
+
+        let initial_a = 10_000;
+        let (token_a_key, mut token_a_account, token_b_key, mut token_b_account, _, _) =
+            accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, 0, 0);
+
+        let amount_out = 5_000;
+        let result = accounts
+            .swap_curve
+            .swap_exact_out(
+                amount_out.into(),
+                token_a_amount.into(),
+                token_b_amount.into(),
+                TradeDirection::AtoB,
+                &accounts.fees,
+            )
+            .unwrap();
+        let amount_in = to_u64(result.source_amount_swapped).unwrap();
+
+        let user_transfer_key = Pubkey::new_unique();
+        do_process_instruction(
+            approve(
+                &spl_token::id(),
+                &token_a_key,
+                &user_transfer_key,
+                &swapper_key,
+                &[],
+                initial_a,
+            )
+            .unwrap(),
+            vec![
+                &mut token_a_account,
+                &mut Account::default(),
+                &mut Account::default(),
+            ],
+        )
+        .unwrap();
+
+        let swap_exact_amount_out_instruction = |maximum_amount_in| {
+            swap_exact_amount_out(
+                &SWAP_PROGRAM_ID,
+                &spl_token::id(),
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &user_transfer_key,
+                &token_a_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                None,
+                None,
+                SwapExactAmountOut {
+                    amount_out,
+                    maximum_amount_in,
+                },
+            )
+            .unwrap()
+        };
+
+        // required source amount exceeds slippage
+        {
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                do_process_instruction(
+                    swap_exact_amount_out_instruction(amount_in - 1),
+                    vec![
+                        &mut accounts.swap_account.clone(),
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account.clone(),
+                        &mut accounts.token_a_account.clone(),
+                        &mut accounts.token_b_account.clone(),
+                        &mut token_b_account.clone(),
+                        &mut accounts.pool_mint_account.clone(),
+                        &mut accounts.pool_fee_account.clone(),
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+
+        // whole destination side of the pool requested
+        {
+            let mut instruction = swap_exact_amount_out_instruction(initial_a);
+            instruction.data = SwapInstruction::SwapExactAmountOut(SwapExactAmountOut {
+                amount_out: token_b_amount,
+                maximum_amount_in: initial_a,
+            })
+            .pack();
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                do_process_instruction(
+                    instruction,
+                    vec![
+                        &mut accounts.swap_account.clone(),
+                        &mut Account::default(),
+                        &mut Account::default(),
+                        &mut token_a_account.clone(),
+                        &mut accounts.token_a_account.clone(),
+                        &mut accounts.token_b_account.clone(),
+                        &mut token_b_account.clone(),
+                        &mut accounts.pool_mint_account.clone(),
+                        &mut accounts.pool_fee_account.clone(),
+                        &mut Account::default(),
+                    ],
+                )
+            );
+        }
+
+        // receive exactly the requested amount
+        do_process_instruction(
+            swap_exact_amount_out_instruction(amount_in),
+            vec![
+                &mut accounts.swap_account,
+                &mut Account::default(),
+                &mut Account::default(),
+                &mut token_a_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut Account::default(),
+            ],
+        )
+        .unwrap();
+
+        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.amount, initial_a - amount_in);
+        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+        assert_eq!(token_b.amount, amount_out);
+        let swap_token_a =
+            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(swap_token_a.amount, token_a_amount + amount_in);
+        let swap_token_b =
+            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.amount, token_b_amount - amount_out);
+
+        // a regular swap of the same source amount gives at least as much
+        let swap_result = accounts
+            .swap_curve
+            .swap(
+                amount_in.into(),
+                token_a_amount.into(),
+                token_b_amount.into(),
+                TradeDirection::AtoB,
+                &accounts.fees,
+            )
+            .unwrap();
+        assert!(swap_result.destination_amount_swapped >= amount_out.into());
+
+        let pool_fee = spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert!(pool_fee.amount > 0);
+    }
+
+    #[test]
+    fn test_overdraw_offset_curve() {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000_000;
+        let token_b_amount = 0;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_b_offset = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::Offset,
+            calculator: Box::new(OffsetCurve { token_b_offset }),
+        };
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+
+        accounts.initialize_swap().unwrap();
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let initial_a = 500_000;
+        let initial_b = 1_000;
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+
+        // swap a to b way, fails, there's no liquidity
+        let a_to_b_amount = initial_a;
+        let minimum_token_b_amount = 0;
+
+        assert_eq!(
+            Err(SwapError::ZeroTradingTokens.into()),
+            accounts.swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+        );
+
+        // swap b to a, succeeds at offset price
+        let b_to_a_amount = initial_b;
+        let minimum_token_a_amount = 0;
+        accounts
+            .swap(
+                &swapper_key,
+                &token_b_key,
+                &mut token_b_account,
+                &swap_token_b_key,
+                &swap_token_a_key,
+                &token_a_key,
+                &mut token_a_account,
+                b_to_a_amount,
+                minimum_token_a_amount,
+            )
+            .unwrap();
+
+        // try a to b again, succeeds due to new liquidity
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+            .unwrap();
+
+        // try a to b again, fails due to no more liquidity
+        assert_eq!(
+            Err(SwapError::ZeroTradingTokens.into()),
+            accounts.swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+        );
+
+        // Try to deposit, fails because deposits are not allowed for offset
+        // curve swaps
+        {
+            let initial_a = 100;
+            let initial_b = 100;
+            let pool_amount = 100;
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::UnsupportedCurveOperation.into()),
+                accounts.deposit_all_token_types(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount,
+                    initial_a,
+                    initial_b,
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_withdraw_all_offset_curve() {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000_000;
+        let token_b_amount = 10;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_b_offset = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::Offset,
+            calculator: Box::new(OffsetCurve { token_b_offset }),
+        };
+        let total_pool = swap_curve.calculator.new_pool_supply();
+        let user_key = Pubkey::new_unique();
+        let withdrawer_key = Pubkey::new_unique();
+
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+
+        accounts.initialize_swap().unwrap();
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
+
+        let pool_key = accounts.pool_token_key;
+        let mut pool_account = accounts.pool_token_account.clone();
+
+        // WithdrawAllTokenTypes takes all tokens for A and B.
+        // The curve's calculation for token B will say to transfer
+        // `token_b_offset + token_b_amount`, but only `token_b_amount` will be
+        // moved.
+        accounts
+            .withdraw_all_token_types(
+                &user_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                total_pool.try_into().unwrap(),
+                0,
+                0,
+            )
+            .unwrap();
+
+        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.amount, token_a_amount);
+        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+        assert_eq!(token_b.amount, token_b_amount);
+        let swap_token_a =
+            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(swap_token_a.amount, 0);
+        let swap_token_b =
+            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.amount, 0);
+    }
+
+    #[test]
+    fn test_withdraw_all_constant_price_curve() {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        // initialize "unbalanced", so that withdrawing all will have some issues
+        // A: 1_000_000_000
+        // B: 2_000_000_000 (1_000 * 2_000_000)
+        let swap_token_a_amount = 1_000_000_000;
+        let swap_token_b_amount = 1_000;
+        let token_b_price = 2_000_000;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantPrice,
+            calculator: Box::new(ConstantPriceCurve { token_b_price }),
+        };
+        let total_pool = swap_curve.calculator.new_pool_supply();
+        let user_key = Pubkey::new_unique();
+        let withdrawer_key = Pubkey::new_unique();
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            swap_curve,
+            swap_token_a_amount,
+            swap_token_b_amount,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
+
+        let pool_key = accounts.pool_token_key;
+        let mut pool_account = accounts.pool_token_account.clone();
+
+        // WithdrawAllTokenTypes will not take all token A and B, since their
+        // ratio is unbalanced.  It will try to take 1_500_000_000 worth of
+        // each token, which means 1_500_000_000 token A, and 750 token B.
+        // With no slippage, this will leave 250 token B in the pool.
+        assert_eq!(
+            Err(SwapError::ExceededSlippage.into()),
+            accounts.withdraw_all_token_types(
+                &user_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                total_pool.try_into().unwrap(),
+                swap_token_a_amount,
+                swap_token_b_amount,
+            )
+        );
+
+        accounts
+            .withdraw_all_token_types(
+                &user_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                total_pool.try_into().unwrap(),
+                0,
+                0,
+            )
+            .unwrap();
+
+        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.amount, swap_token_a_amount);
+        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+        assert_eq!(token_b.amount, 750);
+        let swap_token_a =
+            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(swap_token_a.amount, 0);
+        let swap_token_b =
+            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.amount, 250);
+
+        // deposit now, not enough to cover the tokens already in there
+        let token_b_amount = 10;
+        let token_a_amount = token_b_amount * token_b_price;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &user_key,
+            &withdrawer_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
+
+        assert_eq!(
+            Err(SwapError::ExceededSlippage.into()),
+            accounts.deposit_all_token_types(
+                &withdrawer_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                1, // doesn't matter
+                token_a_amount,
+                token_b_amount,
+            )
+        );
+
+        // deposit enough tokens, success!
+        let token_b_amount = 125;
+        let token_a_amount = token_b_amount * token_b_price;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &user_key,
+            &withdrawer_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
 
-        // swap from a to a
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                _token_b_key,
-                _token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account.clone(),
-                    &swap_token_a_key,
-                    &swap_token_a_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    initial_a,
-                    minimum_token_b_amount,
-                )
-            );
-        }
+        accounts
+            .deposit_all_token_types(
+                &withdrawer_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                1, // doesn't matter
+                token_a_amount,
+                token_b_amount,
+            )
+            .unwrap();
+    }
+    #[test]
+    fn test_pause() {
+        let user_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
 
-        // incorrect mint provided
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let (pool_mint_key, pool_mint_account) =
-                create_mint(&spl_token::id(), &accounts.authority_key, None);
-            let old_pool_key = accounts.pool_mint_key;
-            let old_pool_account = accounts.pool_mint_account;
-            accounts.pool_mint_key = pool_mint_key;
-            accounts.pool_mint_account = pool_mint_account;
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
 
-            assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
-                )
-            );
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            swap_curve.clone(),
+            token_a_amount,
+            token_b_amount,
+        );
+        accounts.initialize_swap().unwrap();
+        let pause_authority_key = accounts.pause_authority_key;
 
-            accounts.pool_mint_key = old_pool_key;
-            accounts.pool_mint_account = old_pool_account;
-        }
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let initial_pool = 1_000_000;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &user_key, initial_a, initial_b, initial_pool);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
 
-        // incorrect fee account provided
+        // wrong pause authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                wrong_pool_key,
-                wrong_pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let old_pool_fee_account = accounts.pool_fee_account;
-            let old_pool_fee_key = accounts.pool_fee_key;
-            accounts.pool_fee_account = wrong_pool_account;
-            accounts.pool_fee_key = wrong_pool_key;
+            let wrong_key = Pubkey::new_unique();
             assert_eq!(
-                Err(SwapError::IncorrectFeeAccount.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                Err(SwapError::InvalidPauseAuthority.into()),
+                do_process_instruction(
+                    pause(&SWAP_PROGRAM_ID, &accounts.swap_key, &wrong_key).unwrap(),
+                    vec![&mut accounts.swap_account, &mut Account::default()],
                 )
             );
-            accounts.pool_fee_account = old_pool_fee_account;
-            accounts.pool_fee_key = old_pool_fee_key;
         }
 
-        // no approval
+        // pause authority not signing
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let user_transfer_key = Pubkey::new_unique();
+            let mut instruction =
+                pause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap();
+            instruction.accounts[1].is_signer = false;
             assert_eq!(
-                Err(TokenError::OwnerMismatch.into()),
+                Err(SwapError::InvalidPauseAuthority.into()),
                 do_process_instruction(
-                    swap(
-                        &SWAP_PROGRAM_ID,
-                        &spl_token::id(),
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &user_transfer_key,
-                        &token_a_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        None,
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: minimum_token_b_amount,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut Account::default(),
-                        &mut Account::default(),
-                        &mut token_a_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut Account::default(),
-                    ],
-                ),
+                    instruction,
+                    vec![&mut accounts.swap_account, &mut Account::default()],
+                )
             );
         }
 
-        // output token value 0
+        // unpause before pausing
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
             assert_eq!(
-                Err(SwapError::ZeroTradingTokens.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &swap_token_b_key,
-                    &swap_token_a_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    1,
-                    1,
+                Err(SwapError::SwapNotPaused.into()),
+                do_process_instruction(
+                    unpause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+                    vec![&mut accounts.swap_account, &mut Account::default()],
+                )
+            );
+        }
+
+        // pause
+        do_process_instruction(
+            pause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+            vec![&mut accounts.swap_account, &mut Account::default()],
+        )
+        .unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_state.is_paused());
+
+        // pause again
+        {
+            assert_eq!(
+                Err(SwapError::SwapPaused.into()),
+                do_process_instruction(
+                    pause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+                    vec![&mut accounts.swap_account, &mut Account::default()],
                 )
             );
         }
 
-        // slippage exceeded: minimum out amount too high
+        // swaps and deposits are blocked
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
             assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
+                Err(SwapError::SwapPaused.into()),
                 accounts.swap(
-                    &swapper_key,
+                    &user_key,
                     &token_a_key,
                     &mut token_a_account,
                     &swap_token_a_key,
                     &swap_token_b_key,
                     &token_b_key,
                     &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount * 2,
+                    initial_a / 10,
+                    0,
                 )
             );
-        }
-
-        // invalid input: can't use swap pool as user source / dest
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
-            let authority_key = accounts.authority_key;
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.swap(
-                    &authority_key,
-                    &swap_token_a_key,
-                    &mut swap_token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
+                Err(SwapError::SwapPaused.into()),
+                accounts.deposit_all_token_types(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    initial_pool / 10,
                     initial_a,
-                    minimum_token_b_amount,
+                    initial_b,
                 )
             );
-            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.swap(
-                    &swapper_key,
+                Err(SwapError::SwapPaused.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &user_key,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &swap_token_b_key,
-                    &mut swap_token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    &pool_key,
+                    &mut pool_account,
+                    initial_a / 10,
+                    0,
                 )
             );
         }
 
-        // still correct: constraint specified, no host fee account
+        // withdrawals are still allowed
         {
-            let authority_key = accounts.authority_key;
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
-            let owner_key = &swapper_key.to_string();
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types: &[],
-                fees: &fees,
-            });
-            do_process_instruction_with_fee_constraints(
-                swap(
+            accounts
+                .withdraw_all_token_types(
+                    &user_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_pool / 10,
+                    0,
+                    0,
+                )
+                .unwrap();
+            accounts
+                .withdraw_single_token_type_exact_amount_out(
+                    &user_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    initial_a / 1_000,
+                    initial_pool,
+                )
+                .unwrap();
+        }
+
+        // unpause, and swaps work again
+        do_process_instruction(
+            unpause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+            vec![&mut accounts.swap_account, &mut Account::default()],
+        )
+        .unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(!swap_state.is_paused());
+        accounts
+            .swap(
+                &user_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                initial_a / 10,
+                0,
+            )
+            .unwrap();
+
+        // swap created without a pause authority can never be paused
+        {
+            let mut accounts =
+                SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+            do_process_instruction(
+                initialize(
                     &SWAP_PROGRAM_ID,
                     &spl_token::id(),
                     &accounts.swap_key,
                     &accounts.authority_key,
-                    &accounts.authority_key,
-                    &token_a_key,
                     &accounts.token_a_key,
                     &accounts.token_b_key,
-                    &token_b_key,
                     &accounts.pool_mint_key,
                     &accounts.pool_fee_key,
+                    &accounts.pool_token_key,
                     None,
-                    Swap {
-                        amount_in: initial_a,
-                        minimum_amount_out: minimum_token_b_amount,
-                    },
+                    accounts.nonce,
+                    accounts.fees.clone(),
+                    accounts.swap_curve.clone(),
+                    COption::None,
+                    DynamicFees::default(),
                 )
                 .unwrap(),
                 vec![
                     &mut accounts.swap_account,
                     &mut Account::default(),
-                    &mut Account::default(),
-                    &mut token_a_account,
                     &mut accounts.token_a_account,
                     &mut accounts.token_b_account,
-                    &mut token_b_account,
                     &mut accounts.pool_mint_account,
                     &mut accounts.pool_fee_account,
+                    &mut accounts.pool_token_account,
                     &mut Account::default(),
                 ],
-                &constraints,
             )
             .unwrap();
-        }
-
-        // invalid mint for host fee account
-        {
-            let authority_key = accounts.authority_key;
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
-            let (
-                bad_token_a_key,
-                mut bad_token_a_account,
-                _token_b_key,
-                mut _token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
-            let owner_key = &swapper_key.to_string();
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types: &[],
-                fees: &fees,
-            });
+            let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(swap_state.pause_authority(), COption::None);
             assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                do_process_instruction_with_fee_constraints(
-                    swap(
-                        &SWAP_PROGRAM_ID,
-                        &spl_token::id(),
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.authority_key,
-                        &token_a_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        Some(&bad_token_a_key),
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: 0,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut Account::default(),
-                        &mut Account::default(),
-                        &mut token_a_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut Account::default(),
-                        &mut bad_token_a_account,
-                    ],
-                    &constraints,
-                ),
+                Err(SwapError::InvalidPauseAuthority.into()),
+                do_process_instruction(
+                    pause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+                    vec![&mut accounts.swap_account, &mut Account::default()],
+                )
             );
         }
     }
 
     #[test]
-    fn test_overdraw_offset_curve() {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
-
-        let token_a_amount = 1_000_000_000;
-        let token_b_amount = 0;
+    fn test_price_cumulative() {
+        let user_key = Pubkey::new_unique();
         let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 10,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 30,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 30,
+            host_fee_numerator: 10,
+            host_fee_denominator: 100,
         };
 
-        let token_b_offset = 2_000_000;
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 2_000_000;
         let swap_curve = SwapCurve {
-            curve_type: CurveType::Offset,
-            calculator: Box::new(OffsetCurve { token_b_offset }),
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(ConstantProductCurve {}),
         };
-        let user_key = Pubkey::new_unique();
-        let swapper_key = Pubkey::new_unique();
 
+        set_unix_timestamp(1_000);
         let mut accounts =
             SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
-
         accounts.initialize_swap().unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.price_a_cumulative(), 0);
+        assert_eq!(swap_state.price_b_cumulative(), 0);
+        assert_eq!(swap_state.last_update_timestamp(), 1_000);
 
-        let swap_token_a_key = accounts.token_a_key;
-        let swap_token_b_key = accounts.token_b_key;
-        let initial_a = 500_000;
-        let initial_b = 1_000;
-
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let initial_pool = 1_000_000;
         let (
             token_a_key,
             mut token_a_account,
             token_b_key,
             mut token_b_account,
-            _pool_key,
-            _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-
-        // swap a to b way, fails, there's no liquidity
-        let a_to_b_amount = initial_a;
-        let minimum_token_b_amount = 0;
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &user_key, initial_a, initial_b, initial_pool);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
 
-        assert_eq!(
-            Err(SwapError::ZeroTradingTokens.into()),
-            accounts.swap(
-                &swapper_key,
+        // the price before the swap is accumulated for the elapsed time
+        set_unix_timestamp(1_010);
+        accounts
+            .swap(
+                &user_key,
                 &token_a_key,
                 &mut token_a_account,
                 &swap_token_a_key,
                 &swap_token_b_key,
                 &token_b_key,
                 &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
+                initial_a / 10,
+                0,
             )
-        );
+            .unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        let price_a_cumulative = 20u128 << 64;
+        let price_b_cumulative = 5u128 << 64;
+        assert_eq!(swap_state.price_a_cumulative(), price_a_cumulative);
+        assert_eq!(swap_state.price_b_cumulative(), price_b_cumulative);
+        assert_eq!(swap_state.last_update_timestamp(), 1_010);
 
-        // swap b to a, succeeds at offset price
-        let b_to_a_amount = initial_b;
-        let minimum_token_a_amount = 0;
+        // deposits accumulate the price left by the swap
+        let swap_token_a =
+            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+        let swap_token_b =
+            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+        set_unix_timestamp(1_030);
         accounts
-            .swap(
-                &swapper_key,
+            .deposit_all_token_types(
+                &user_key,
+                &token_a_key,
+                &mut token_a_account,
                 &token_b_key,
                 &mut token_b_account,
-                &swap_token_b_key,
-                &swap_token_a_key,
+                &pool_key,
+                &mut pool_account,
+                initial_pool / 10,
+                initial_a,
+                initial_b,
+            )
+            .unwrap();
+        let price_a_cumulative = price_a_cumulative
+            + ((swap_token_b.amount as u128) << 64) / swap_token_a.amount as u128 * 20;
+        let price_b_cumulative = price_b_cumulative
+            + ((swap_token_a.amount as u128) << 64) / swap_token_b.amount as u128 * 20;
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.price_a_cumulative(), price_a_cumulative);
+        assert_eq!(swap_state.price_b_cumulative(), price_b_cumulative);
+        assert_eq!(swap_state.last_update_timestamp(), 1_030);
+
+        // nothing is accumulated within the same second
+        accounts
+            .deposit_single_token_type_exact_amount_in(
+                &user_key,
+                &token_a_key,
+                &mut token_a_account,
+                &pool_key,
+                &mut pool_account,
+                initial_a / 10,
+                0,
+            )
+            .unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.price_a_cumulative(), price_a_cumulative);
+        assert_eq!(swap_state.price_b_cumulative(), price_b_cumulative);
+        assert_eq!(swap_state.last_update_timestamp(), 1_030);
+    }
+
+    #[test]
+    fn test_dynamic_fees() {
+        let user_key = Pubkey::new_unique();
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        };
+        let dynamic_fees = DynamicFees {
+            volatility_fee_numerator: 1,
+            volatility_fee_denominator: 2,
+            max_trade_fee_numerator: 500,
+            decay_period: 600,
+        };
+
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Box::new(ConstantProductCurve {}),
+        };
+
+        set_unix_timestamp(1_000);
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            swap_curve.clone(),
+            token_a_amount,
+            token_b_amount,
+        );
+
+        // maximum trade fee below the base trade fee
+        {
+            accounts.dynamic_fees = DynamicFees {
+                max_trade_fee_numerator: 20,
+                ..dynamic_fees.clone()
+            };
+            assert_eq!(
+                Err(SwapError::InvalidFee.into()),
+                accounts.initialize_swap()
+            );
+        }
+
+        accounts.dynamic_fees = dynamic_fees.clone();
+        accounts.initialize_swap().unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(*swap_state.dynamic_fees(), dynamic_fees);
+        assert_eq!(swap_state.volatility(), 0);
+        assert_eq!(swap_state.last_volatility_update(), 1_000);
+
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &user_key, initial_a, initial_b, 0);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let amount_in = initial_a / 4;
+
+        // swaps the test amount, checking the fees charged for the given
+        // volatility, and returns the volatility left by the swap
+        let mut check_swap = |accounts: &mut SwapAccountInfo, volatility: u64| -> u64 {
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+            let swap_token_b =
+                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            let trade_fees = dynamic_fees.trade_fees(&fees, volatility).unwrap();
+            let results = swap_curve
+                .swap(
+                    amount_in.into(),
+                    swap_token_a.amount.into(),
+                    swap_token_b.amount.into(),
+                    TradeDirection::AtoB,
+                    &trade_fees,
+                )
+                .unwrap();
+            accounts
+                .swap(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+                .unwrap();
+            let new_token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            assert_eq!(
+                (new_token_b.amount - token_b.amount) as u128,
+                results.destination_amount_swapped
+            );
+            price_movement(
+                swap_token_a.amount.into(),
+                swap_token_b.amount.into(),
+                results.new_swap_source_amount,
+                results.new_swap_destination_amount,
+            )
+            .unwrap()
+        };
+
+        // no volatility yet, only the base trade fee
+        let volatility = check_swap(&mut accounts, 0);
+        assert!(volatility > 0);
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.volatility(), volatility);
+
+        // the next swap pays for the volatility, decayed by a quarter
+        set_unix_timestamp(1_150);
+        let decayed_volatility = volatility * 3 / 4;
+        assert!(
+            dynamic_fees
+                .trade_fees(&fees, decayed_volatility)
+                .unwrap()
+                .trade_fee_numerator
+                > fees.trade_fee_numerator
+        );
+        let price_movement = check_swap(&mut accounts, decayed_volatility);
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.volatility(), decayed_volatility + price_movement);
+        assert_eq!(swap_state.last_volatility_update(), 1_150);
+
+        // back to the base trade fee once the volatility has decayed
+        set_unix_timestamp(1_750);
+        let price_movement = check_swap(&mut accounts, 0);
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.volatility(), price_movement);
+    }
+
+    #[test]
+    fn test_proactive_market_making() {
+        let user_key = Pubkey::new_unique();
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        };
+
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ProactiveMarketMaking,
+            calculator: Box::new(ProactiveMarketMakingCurve {
+                k: K_PRECISION / 10,
+                ..ProactiveMarketMakingCurve::default()
+            }),
+        };
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+        );
+
+        // no price available yet
+        {
+            accounts.oracle_account = create_oracle(0);
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.initialize_swap()
+            );
+        }
+
+        // the curve starts from the oracle price, with the balances as targets
+        accounts.oracle_account = create_oracle(2 * PRICE_SCALE);
+        accounts.initialize_swap().unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.oracle(), COption::Some(accounts.oracle_key));
+        let calculator =
+            ProactiveMarketMakingCurve::from_swap_curve(swap_state.swap_curve()).unwrap();
+        assert_eq!(calculator.price, 2 * PRICE_SCALE);
+        assert_eq!(calculator.token_a_target, token_a_amount);
+        assert_eq!(calculator.token_b_target, token_b_amount);
+
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let initial_pool = 1_000_000;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &user_key, initial_a, initial_b, initial_pool);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let amount_in = initial_a / 10;
+
+        // wrong oracle account
+        {
+            let oracle_key = accounts.oracle_key;
+            accounts.oracle_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectOracleAccount.into()),
+                accounts.swap(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+            );
+            accounts.oracle_key = oracle_key;
+        }
+
+        // oracle account owned by another program
+        {
+            accounts.oracle_account.owner = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectOracleAccount.into()),
+                accounts.swap(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+            );
+            accounts.oracle_account.owner = ORACLE_PROGRAM_ID;
+        }
+
+        // stale price
+        {
+            set_slot(MAX_PRICE_AGE + 1);
+            assert_eq!(
+                Err(SwapError::StaleOraclePrice.into()),
+                accounts.swap(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+            );
+            set_slot(0);
+        }
+
+        // swaps the test amount of token A after refreshing the curve at the
+        // given price, returning the amount of token B received
+        let mut check_swap = |accounts: &mut SwapAccountInfo, price: u64| -> u64 {
+            let swap_token_a =
+                spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+            let swap_token_b =
+                spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+            let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+            let mut calculator =
+                ProactiveMarketMakingCurve::from_swap_curve(swap_state.swap_curve()).unwrap();
+            calculator
+                .refresh(
+                    price,
+                    swap_token_a.amount.into(),
+                    swap_token_b.amount.into(),
+                )
+                .unwrap();
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ProactiveMarketMaking,
+                calculator: Box::new(calculator.clone()),
+            };
+            let results = swap_curve
+                .swap(
+                    amount_in.into(),
+                    swap_token_a.amount.into(),
+                    swap_token_b.amount.into(),
+                    TradeDirection::AtoB,
+                    &fees,
+                )
+                .unwrap();
+            accounts
+                .swap(
+                    &user_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+                .unwrap();
+            let new_token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+            let amount_out = new_token_b.amount - token_b.amount;
+            assert_eq!(amount_out as u128, results.destination_amount_swapped);
+            let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+            assert_eq!(*swap_state.swap_curve(), swap_curve);
+            amount_out
+        };
+
+        // close to the oracle price, below it by the fees and slippage
+        let amount_out = check_swap(&mut accounts, 2 * PRICE_SCALE);
+        assert!(amount_out < 2 * amount_in);
+        assert!(amount_out > 2 * amount_in * 99 / 100);
+
+        // the next swap follows the new oracle price, with more slippage as
+        // the pool is already short of token B
+        accounts.oracle_account = create_oracle(3 * PRICE_SCALE);
+        let amount_out = check_swap(&mut accounts, 3 * PRICE_SCALE);
+        assert!(amount_out < 3 * amount_in);
+        assert!(amount_out > 3 * amount_in * 98 / 100);
+
+        // single token type deposits would leave the targets behind
+        assert_eq!(
+            Err(SwapError::UnsupportedCurveOperation.into()),
+            accounts.deposit_single_token_type_exact_amount_in(
+                &user_key,
                 &token_a_key,
                 &mut token_a_account,
-                b_to_a_amount,
-                minimum_token_a_amount,
+                &pool_key,
+                &mut pool_account,
+                amount_in,
+                0,
             )
-            .unwrap();
+        );
 
-        // try a to b again, succeeds due to new liquidity
+        // the targets follow deposits of all token types
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        let calculator =
+            ProactiveMarketMakingCurve::from_swap_curve(swap_state.swap_curve()).unwrap();
+        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+        let pool_token_amount = pool_mint.supply / 10;
         accounts
-            .swap(
-                &swapper_key,
+            .deposit_all_token_types(
+                &user_key,
                 &token_a_key,
                 &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
                 &token_b_key,
                 &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
+                &pool_key,
+                &mut pool_account,
+                pool_token_amount,
+                initial_a,
+                initial_b,
             )
             .unwrap();
-
-        // try a to b again, fails due to no more liquidity
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        let new_calculator =
+            ProactiveMarketMakingCurve::from_swap_curve(swap_state.swap_curve()).unwrap();
+        assert_eq!(new_calculator.price, calculator.price);
         assert_eq!(
-            Err(SwapError::ZeroTradingTokens.into()),
-            accounts.swap(
-                &swapper_key,
-                &token_a_key,
-                &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
-                &token_b_key,
-                &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
-            )
+            new_calculator.token_a_target,
+            calculator.token_a_target * 11 / 10
         );
+        assert_eq!(
+            new_calculator.token_b_target,
+            calculator.token_b_target * 11 / 10
+        );
+    }
 
-        // Try to deposit, fails because deposits are not allowed for offset
-        // curve swaps
+    #[test]
+    fn test_oracle_program_constraints() {
+        let user_key = Pubkey::new_unique();
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
+        };
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ProactiveMarketMaking,
+            calculator: Box::new(ProactiveMarketMakingCurve {
+                k: K_PRECISION / 10,
+                ..ProactiveMarketMakingCurve::default()
+            }),
+        };
+        let mut accounts =
+            SwapAccountInfo::new(&user_key, fees.clone(), swap_curve, 1_000_000, 2_000_000);
+        accounts.oracle_account = create_oracle(2 * PRICE_SCALE);
+
+        let owner_key = &user_key.to_string();
+        let valid_curve_types = &[CurveType::ProactiveMarketMaking];
+        let other_program_id = &Pubkey::new_unique().to_string();
+        let oracle_program_id = &ORACLE_PROGRAM_ID.to_string();
+
+        // oracle program not allowed by the constraints
         {
-            let initial_a = 100;
-            let initial_b = 100;
-            let pool_amount = 100;
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                valid_oracle_program_ids: &[other_program_id],
+                fees: &fees,
+                price_range: None,
+            });
             assert_eq!(
-                Err(SwapError::UnsupportedCurveOperation.into()),
-                accounts.deposit_all_token_types(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount,
-                    initial_a,
-                    initial_b,
-                )
+                Err(SwapError::IncorrectOracleAccount.into()),
+                accounts.initialize_swap_with_constraints(&constraints)
             );
         }
+
+        // the allowed oracle program is pinned for later swaps
+        let constraints = Some(SwapConstraints {
+            owner_key,
+            valid_curve_types,
+            valid_oracle_program_ids: &[other_program_id, oracle_program_id],
+            fees: &fees,
+            price_range: None,
+        });
+        accounts
+            .initialize_swap_with_constraints(&constraints)
+            .unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.oracle_program_id(), &ORACLE_PROGRAM_ID);
     }
 
     #[test]
-    fn test_withdraw_all_offset_curve() {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 0;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
-
-        let token_a_amount = 1_000_000_000;
-        let token_b_amount = 10;
+    fn test_stake_pool_stable() {
+        let user_key = Pubkey::new_unique();
         let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10_000,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
         };
 
-        let token_b_offset = 2_000_000;
+        // balanced in value at 1.1 SOL per pool token
+        let token_a_amount = 1_100_000_000;
+        let token_b_amount = 1_000_000_000;
+        let total_stake_lamports = 110_000_000_000;
+        let pool_token_supply = 100_000_000_000;
         let swap_curve = SwapCurve {
-            curve_type: CurveType::Offset,
-            calculator: Box::new(OffsetCurve { token_b_offset }),
+            curve_type: CurveType::StakePoolStable,
+            calculator: Box::new(StakePoolStableCurve {
+                amp: 100,
+                ..StakePoolStableCurve::default()
+            }),
         };
-        let total_pool = swap_curve.calculator.new_pool_supply();
-        let user_key = Pubkey::new_unique();
-        let withdrawer_key = Pubkey::new_unique();
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+        );
+        let token_b_mint_key = accounts.token_b_mint_key;
+        accounts.oracle_account =
+            create_stake_pool(&token_b_mint_key, total_stake_lamports, pool_token_supply);
 
-        let mut accounts =
-            SwapAccountInfo::new(&user_key, fees, swap_curve, token_a_amount, token_b_amount);
+        // token A is not SOL
+        assert_eq!(
+            Err(SwapError::InvalidCurve.into()),
+            accounts.initialize_swap()
+        );
+        let native_mint_key = spl_token::native_mint::id();
+        set_token_account_mint(&mut accounts.token_a_account, &native_mint_key);
+
+        // stake pool not owned by the stake pool program
+        {
+            let mut stake_pool_account =
+                create_stake_pool(&token_b_mint_key, total_stake_lamports, pool_token_supply);
+            stake_pool_account.owner = Pubkey::new_unique();
+            accounts.oracle_account = stake_pool_account;
+            assert_eq!(
+                Err(SwapError::IncorrectOracleAccount.into()),
+                accounts.initialize_swap()
+            );
+        }
+
+        // stake pool of another pool token
+        {
+            accounts.oracle_account = create_stake_pool(
+                &Pubkey::new_unique(),
+                total_stake_lamports,
+                pool_token_supply,
+            );
+            assert_eq!(
+                Err(SwapError::IncorrectOracleAccount.into()),
+                accounts.initialize_swap()
+            );
+        }
+
+        // empty stake pool, without an exchange rate
+        {
+            accounts.oracle_account = create_stake_pool(&token_b_mint_key, 0, 0);
+            assert_eq!(
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
+            );
+        }
 
+        // the curve starts from the stake pool's exchange rate
+        accounts.oracle_account =
+            create_stake_pool(&token_b_mint_key, total_stake_lamports, pool_token_supply);
         accounts.initialize_swap().unwrap();
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(swap_state.oracle(), COption::Some(accounts.oracle_key));
+        assert_eq!(swap_state.oracle_program_id(), &spl_stake_pool::id());
+        let calculator = StakePoolStableCurve::from_swap_curve(swap_state.swap_curve()).unwrap();
+        assert_eq!(calculator.total_stake_lamports, total_stake_lamports);
+        assert_eq!(calculator.pool_token_supply, pool_token_supply);
 
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
         let (
             token_a_key,
             mut token_a_account,
@@ -6674,211 +10051,500 @@ mod tests {
             mut token_b_account,
             _pool_key,
             _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
+        ) = accounts.setup_token_accounts(&user_key, &user_key, initial_a, initial_b, 0);
+        set_token_account_mint(&mut token_a_account, &native_mint_key);
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let amount_in = initial_a / 100;
+
+        // swaps the test amount of SOL for pool tokens after refreshing the
+        // curve at the given exchange rate, returning the amount received
+        let mut check_swap =
+            |accounts: &mut SwapAccountInfo, total_stake_lamports: u64, pool_token_supply: u64| {
+                let swap_token_a =
+                    spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
+                let swap_token_b =
+                    spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
+                let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+                let swap_curve = SwapCurve {
+                    curve_type: CurveType::StakePoolStable,
+                    calculator: Box::new(StakePoolStableCurve {
+                        amp: 100,
+                        total_stake_lamports,
+                        pool_token_supply,
+                    }),
+                };
+                let results = swap_curve
+                    .swap(
+                        amount_in.into(),
+                        swap_token_a.amount.into(),
+                        swap_token_b.amount.into(),
+                        TradeDirection::AtoB,
+                        &fees,
+                    )
+                    .unwrap();
+                accounts
+                    .swap(
+                        &user_key,
+                        &token_a_key,
+                        &mut token_a_account,
+                        &swap_token_a_key,
+                        &swap_token_b_key,
+                        &token_b_key,
+                        &mut token_b_account,
+                        amount_in,
+                        0,
+                    )
+                    .unwrap();
+                let new_token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
+                let amount_out = new_token_b.amount - token_b.amount;
+                assert_eq!(amount_out as u128, results.destination_amount_swapped);
+                let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+                assert_eq!(*swap_state.swap_curve(), swap_curve);
+                amount_out
+            };
 
-        let pool_key = accounts.pool_token_key;
-        let mut pool_account = accounts.pool_token_account.clone();
+        // close to the exchange rate, below it by the fees
+        let amount_out = check_swap(&mut accounts, total_stake_lamports, pool_token_supply);
+        assert!(amount_out < amount_in * 10 / 11);
+        assert!(amount_out > amount_in * 10 / 11 * 99 / 100);
+
+        // the next swap follows the stake pool's rewards
+        let total_stake_lamports = 120_000_000_000;
+        accounts.oracle_account =
+            create_stake_pool(&token_b_mint_key, total_stake_lamports, pool_token_supply);
+        let amount_out = check_swap(&mut accounts, total_stake_lamports, pool_token_supply);
+        assert!(amount_out < amount_in * 10 / 12);
+        assert!(amount_out > amount_in * 10 / 12 * 99 / 100);
+    }
+    #[test]
+    fn test_multi_token_swap() {
+        let user_key = Pubkey::new_unique();
+        let fees = Fees {
+            trade_fee_numerator: 4,
+            trade_fee_denominator: 10_000,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 10_000,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 1_000,
+            host_fee_numerator: 20,
+            host_fee_denominator: 100,
+        };
+        let swap_curve = MultiStableCurve { amp: 100 };
+        let token_amounts = [1_000_000_000, 1_000_000_000, 1_000_000_000];
 
-        // WithdrawAllTokenTypes takes all tokens for A and B.
-        // The curve's calculation for token B will say to transfer
-        // `token_b_offset + token_b_amount`, but only `token_b_amount` will be
-        // moved.
-        accounts
-            .withdraw_all_token_types(
+        // too few tokens
+        {
+            let mut accounts = MultiTokenSwapAccountInfo::new(
                 &user_key,
-                &pool_key,
-                &mut pool_account,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
-                total_pool.try_into().unwrap(),
-                0,
-                0,
+                fees.clone(),
+                swap_curve.clone(),
+                &token_amounts,
+            );
+            let mut instruction = initialize_multi_token(
+                &SWAP_PROGRAM_ID,
+                &spl_token::id(),
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.pool_token_key,
+                &accounts.token_keys,
+                accounts.nonce,
+                fees.clone(),
+                swap_curve.clone(),
+                COption::None,
             )
             .unwrap();
+            instruction.accounts.pop();
+            let mut token_accounts = accounts.token_accounts.clone();
+            token_accounts.pop();
+            let mut authority_account = Account::default();
+            let mut token_program_account = Account::default();
+            let mut account_list = vec![
+                &mut accounts.swap_account,
+                &mut authority_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.pool_token_account,
+                &mut token_program_account,
+            ];
+            account_list.extend(token_accounts.iter_mut());
+            assert_eq!(
+                Err(SwapError::InvalidTokenCount.into()),
+                do_process_instruction(instruction, account_list)
+            );
+        }
 
-        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-        assert_eq!(token_a.amount, token_a_amount);
-        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-        assert_eq!(token_b.amount, token_b_amount);
-        let swap_token_a =
-            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-        assert_eq!(swap_token_a.amount, 0);
-        let swap_token_b =
-            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-        assert_eq!(swap_token_b.amount, 0);
-    }
+        // repeated mint
+        {
+            let mut accounts = MultiTokenSwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                swap_curve.clone(),
+                &token_amounts,
+            );
+            let (token_key, token_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_mint_keys[0],
+                &mut accounts.token_mint_accounts[0],
+                &user_key,
+                &accounts.authority_key,
+                token_amounts[2],
+            );
+            accounts.token_keys[2] = token_key;
+            accounts.token_accounts[2] = token_account;
+            assert_eq!(
+                Err(SwapError::RepeatedMint.into()),
+                accounts.initialize_swap()
+            );
+        }
 
-    #[test]
-    fn test_withdraw_all_constant_price_curve() {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 0;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
+        // token account not owned by the swap authority
+        {
+            let mut accounts = MultiTokenSwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                swap_curve.clone(),
+                &token_amounts,
+            );
+            let (token_key, token_account) = mint_token(
+                &spl_token::id(),
+                &accounts.token_mint_keys[1],
+                &mut accounts.token_mint_accounts[1],
+                &user_key,
+                &user_key,
+                token_amounts[1],
+            );
+            accounts.token_keys[1] = token_key;
+            accounts.token_accounts[1] = token_account;
+            assert_eq!(
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
+            );
+        }
 
-        // initialize "unbalanced", so that withdrawing all will have some issues
-        // A: 1_000_000_000
-        // B: 2_000_000_000 (1_000 * 2_000_000)
-        let swap_token_a_amount = 1_000_000_000;
-        let swap_token_b_amount = 1_000;
-        let token_b_price = 2_000_000;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+        // empty token account
+        {
+            let mut accounts = MultiTokenSwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                swap_curve.clone(),
+                &[1_000, 0, 1_000],
+            );
+            assert_eq!(
+                Err(SwapError::EmptySupply.into()),
+                accounts.initialize_swap()
+            );
+        }
 
-        let swap_curve = SwapCurve {
-            curve_type: CurveType::ConstantPrice,
-            calculator: Box::new(ConstantPriceCurve { token_b_price }),
-        };
-        let total_pool = swap_curve.calculator.new_pool_supply();
-        let user_key = Pubkey::new_unique();
-        let withdrawer_key = Pubkey::new_unique();
+        // invalid curve
+        {
+            let mut accounts = MultiTokenSwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                MultiStableCurve { amp: 0 },
+                &token_amounts,
+            );
+            assert_eq!(
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
+            );
+        }
 
-        let mut accounts = SwapAccountInfo::new(
+        let mut accounts = MultiTokenSwapAccountInfo::new(
             &user_key,
-            fees,
-            swap_curve,
-            swap_token_a_amount,
-            swap_token_b_amount,
+            fees.clone(),
+            swap_curve.clone(),
+            &token_amounts,
         );
-
         accounts.initialize_swap().unwrap();
+        let swap_state = MultiTokenSwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_state.is_initialized);
+        assert_eq!(swap_state.nonce, accounts.nonce);
+        assert_eq!(swap_state.pool_mint, accounts.pool_mint_key);
+        assert_eq!(swap_state.pool_fee_account, accounts.pool_fee_key);
+        assert_eq!(swap_state.fees, fees);
+        assert_eq!(swap_state.swap_curve, swap_curve);
+        assert_eq!(
+            swap_state.pause_authority,
+            COption::Some(accounts.pause_authority_key)
+        );
+        assert_eq!(swap_state.tokens, accounts.token_keys);
+        assert_eq!(swap_state.token_mints, accounts.token_mint_keys);
+        let pool_token_account =
+            spl_token::state::Account::unpack(&accounts.pool_token_account.data).unwrap();
+        assert_eq!(
+            pool_token_account.amount,
+            to_u64(swap_curve.new_pool_supply()).unwrap()
+        );
 
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            _pool_key,
-            _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
-
-        let pool_key = accounts.pool_token_key;
-        let mut pool_account = accounts.pool_token_account.clone();
+        // already initialized
+        assert_eq!(
+            Err(SwapError::AlreadyInUse.into()),
+            accounts.initialize_swap()
+        );
 
-        // WithdrawAllTokenTypes will not take all token A and B, since their
-        // ratio is unbalanced.  It will try to take 1_500_000_000 worth of
-        // each token, which means 1_500_000_000 token A, and 750 token B.
-        // With no slippage, this will leave 250 token B in the pool.
+        // two-token instructions cannot use the swap
         assert_eq!(
-            Err(SwapError::ExceededSlippage.into()),
-            accounts.withdraw_all_token_types(
-                &user_key,
-                &pool_key,
-                &mut pool_account,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
-                total_pool.try_into().unwrap(),
-                swap_token_a_amount,
-                swap_token_b_amount,
-            )
+            SwapVersion::unpack(&accounts.swap_account.data).err(),
+            Some(ProgramError::InvalidAccountData)
         );
 
+        // deposit
+        let pool_token_amount = to_u64(INITIAL_SWAP_POOL_AMOUNT / 10).unwrap();
+        let deposit_amounts: Vec<u64> = token_amounts.iter().map(|amount| amount / 10).collect();
+        let (token_keys, mut token_accounts, pool_key, mut pool_account) =
+            accounts.setup_token_accounts(&user_key, &user_key, &deposit_amounts, 0);
+        {
+            let mut maximum_token_amounts = deposit_amounts.clone();
+            maximum_token_amounts[1] -= 1;
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.deposit_all_multi_token(
+                    &user_key,
+                    &token_keys,
+                    &mut token_accounts,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_token_amount,
+                    maximum_token_amounts,
+                )
+            );
+        }
         accounts
-            .withdraw_all_token_types(
+            .deposit_all_multi_token(
                 &user_key,
+                &token_keys,
+                &mut token_accounts,
                 &pool_key,
                 &mut pool_account,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
-                total_pool.try_into().unwrap(),
-                0,
+                pool_token_amount,
+                deposit_amounts.clone(),
+            )
+            .unwrap();
+        let pool_account_state = spl_token::state::Account::unpack(&pool_account.data).unwrap();
+        assert_eq!(pool_account_state.amount, pool_token_amount);
+        for ((token_account, swap_token_account), (token_amount, deposit_amount)) in token_accounts
+            .iter()
+            .zip(accounts.token_accounts.iter())
+            .zip(token_amounts.iter().zip(deposit_amounts.iter()))
+        {
+            let token_account = spl_token::state::Account::unpack(&token_account.data).unwrap();
+            assert_eq!(token_account.amount, 0);
+            let swap_token_account =
+                spl_token::state::Account::unpack(&swap_token_account.data).unwrap();
+            assert_eq!(swap_token_account.amount, token_amount + deposit_amount);
+        }
+
+        // swap from the first token to the last
+        let amount_in = 10_000_000;
+        let (user_keys, mut user_accounts, _pool_key, _pool_account) =
+            accounts.setup_token_accounts(&user_key, &user_key, &[amount_in, 0, 0], 0);
+        let swap_token_amounts: Vec<u128> = accounts
+            .token_accounts
+            .iter()
+            .map(|account| {
+                spl_token::state::Account::unpack(&account.data)
+                    .unwrap()
+                    .amount as u128
+            })
+            .collect();
+        let results = swap_curve
+            .swap(amount_in as u128, 0, 2, &swap_token_amounts, &fees)
+            .unwrap();
+        let (source_account, rest) = user_accounts.split_at_mut(1);
+        let destination_account = &mut rest[1];
+        {
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.swap(
+                    &user_key,
+                    &user_keys[0],
+                    &mut source_account[0],
+                    &user_keys[2],
+                    destination_account,
+                    0,
+                    2,
+                    amount_in,
+                    to_u64(results.destination_amount_swapped).unwrap() + 1,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &user_key,
+                    &user_keys[0],
+                    &mut source_account[0],
+                    &user_keys[2],
+                    destination_account,
+                    0,
+                    0,
+                    amount_in,
+                    0,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.swap(
+                    &user_key,
+                    &user_keys[0],
+                    &mut source_account[0],
+                    &user_keys[2],
+                    destination_account,
+                    0,
+                    3,
+                    amount_in,
+                    0,
+                )
+            );
+        }
+        accounts
+            .swap(
+                &user_key,
+                &user_keys[0],
+                &mut source_account[0],
+                &user_keys[2],
+                destination_account,
                 0,
+                2,
+                amount_in,
+                to_u64(results.destination_amount_swapped).unwrap(),
             )
             .unwrap();
+        let destination_account_state =
+            spl_token::state::Account::unpack(&destination_account.data).unwrap();
+        assert_eq!(
+            destination_account_state.amount as u128,
+            results.destination_amount_swapped
+        );
+        let swap_source_account =
+            spl_token::state::Account::unpack(&accounts.token_accounts[0].data).unwrap();
+        assert_eq!(
+            swap_source_account.amount as u128,
+            results.new_swap_source_amount
+        );
+        let swap_destination_account =
+            spl_token::state::Account::unpack(&accounts.token_accounts[2].data).unwrap();
+        assert_eq!(
+            swap_destination_account.amount as u128,
+            results.new_swap_destination_amount
+        );
+        let pool_fee_account =
+            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert!(pool_fee_account.amount > 0);
 
-        let token_a = spl_token::state::Account::unpack(&token_a_account.data).unwrap();
-        assert_eq!(token_a.amount, swap_token_a_amount);
-        let token_b = spl_token::state::Account::unpack(&token_b_account.data).unwrap();
-        assert_eq!(token_b.amount, 750);
-        let swap_token_a =
-            spl_token::state::Account::unpack(&accounts.token_a_account.data).unwrap();
-        assert_eq!(swap_token_a.amount, 0);
-        let swap_token_b =
-            spl_token::state::Account::unpack(&accounts.token_b_account.data).unwrap();
-        assert_eq!(swap_token_b.amount, 250);
+        // withdraw everything deposited, minus the withdraw fee
+        let pool_mint = spl_token::state::Mint::unpack(&accounts.pool_mint_account.data).unwrap();
+        let swap_token_amounts: Vec<u128> = accounts
+            .token_accounts
+            .iter()
+            .map(|account| {
+                spl_token::state::Account::unpack(&account.data)
+                    .unwrap()
+                    .amount as u128
+            })
+            .collect();
+        let withdraw_fee = fees.owner_withdraw_fee(pool_token_amount as u128).unwrap();
+        let withdraw_amounts = swap_curve
+            .pool_tokens_to_trading_tokens(
+                pool_token_amount as u128 - withdraw_fee,
+                pool_mint.supply as u128,
+                &swap_token_amounts,
+                RoundDirection::Floor,
+            )
+            .unwrap();
 
-        // deposit now, not enough to cover the tokens already in there
-        let token_b_amount = 10;
-        let token_a_amount = token_b_amount * token_b_price;
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &user_key,
-            &withdrawer_key,
-            token_a_amount,
-            token_b_amount,
-            0,
+        // pause, only withdrawals are allowed
+        let pause_authority_key = accounts.pause_authority_key;
+        do_process_instruction(
+            pause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+            vec![&mut accounts.swap_account, &mut Account::default()],
+        )
+        .unwrap();
+        let swap_state = MultiTokenSwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_state.is_paused);
+        assert_eq!(
+            Err(SwapError::SwapPaused.into()),
+            accounts.swap(
+                &user_key,
+                &user_keys[0],
+                &mut source_account[0],
+                &user_keys[2],
+                destination_account,
+                0,
+                2,
+                1,
+                0,
+            )
         );
-
         assert_eq!(
-            Err(SwapError::ExceededSlippage.into()),
-            accounts.deposit_all_token_types(
-                &withdrawer_key,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
+            Err(SwapError::SwapPaused.into()),
+            accounts.deposit_all_multi_token(
+                &user_key,
+                &token_keys,
+                &mut token_accounts,
                 &pool_key,
                 &mut pool_account,
-                1, // doesn't matter
-                token_a_amount,
-                token_b_amount,
+                1,
+                vec![u64::MAX; 3],
             )
         );
-
-        // deposit enough tokens, success!
-        let token_b_amount = 125;
-        let token_a_amount = token_b_amount * token_b_price;
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &user_key,
-            &withdrawer_key,
-            token_a_amount,
-            token_b_amount,
-            0,
-        );
-
+        {
+            let minimum_token_amounts: Vec<u64> = withdraw_amounts
+                .iter()
+                .map(|amount| to_u64(*amount).unwrap() + 1)
+                .collect();
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.withdraw_all_multi_token(
+                    &user_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_keys,
+                    &mut token_accounts,
+                    pool_token_amount,
+                    minimum_token_amounts,
+                )
+            );
+        }
+        let minimum_token_amounts: Vec<u64> = withdraw_amounts
+            .iter()
+            .map(|amount| to_u64(*amount).unwrap())
+            .collect();
         accounts
-            .deposit_all_token_types(
-                &withdrawer_key,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
+            .withdraw_all_multi_token(
+                &user_key,
                 &pool_key,
                 &mut pool_account,
-                1, // doesn't matter
-                token_a_amount,
-                token_b_amount,
+                &token_keys,
+                &mut token_accounts,
+                pool_token_amount,
+                minimum_token_amounts.clone(),
             )
             .unwrap();
+        for (token_account, minimum_token_amount) in
+            token_accounts.iter().zip(minimum_token_amounts.iter())
+        {
+            let token_account = spl_token::state::Account::unpack(&token_account.data).unwrap();
+            assert_eq!(token_account.amount, *minimum_token_amount);
+        }
+        let pool_account_state = spl_token::state::Account::unpack(&pool_account.data).unwrap();
+        assert_eq!(pool_account_state.amount, 0);
+        let pool_fee_account_state =
+            spl_token::state::Account::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert_eq!(
+            pool_fee_account_state.amount,
+            pool_fee_account.amount + to_u64(withdraw_fee).unwrap()
+        );
+
+        // unpause
+        do_process_instruction(
+            unpause(&SWAP_PROGRAM_ID, &accounts.swap_key, &pause_authority_key).unwrap(),
+            vec![&mut accounts.swap_account, &mut Account::default()],
+        )
+        .unwrap();
+        let swap_state = MultiTokenSwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(!swap_state.is_paused);
     }
 }
@@ -1,10 +1,17 @@
 //! State transition types
 
-use crate::curve::{base::SwapCurve, fees::Fees};
+use crate::curve::{
+    base::SwapCurve,
+    calculator::TradeDirection,
+    fees::{DynamicFees, Fees},
+    multi_stable::MultiStableCurve,
+};
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use enum_dispatch::enum_dispatch;
 use solana_program::{
+    clock::UnixTimestamp,
     program_error::ProgramError,
+    program_option::COption,
     program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
@@ -37,13 +44,55 @@ pub trait SwapState {
     fn fees(&self) -> &Fees;
     /// Curve associated with swap
     fn swap_curve(&self) -> &SwapCurve;
+
+    /// Authority allowed to pause and unpause the swap, if any
+    fn pause_authority(&self) -> COption<Pubkey>;
+    /// Are swaps and deposits currently blocked
+    fn is_paused(&self) -> bool;
+
+    /// Time-weighted sum of the price of token A in token B, see
+    /// [SwapV3](struct.SwapV3.html)
+    fn price_a_cumulative(&self) -> u128;
+    /// Time-weighted sum of the price of token B in token A, see
+    /// [SwapV3](struct.SwapV3.html)
+    fn price_b_cumulative(&self) -> u128;
+    /// Unix timestamp of the last update to the price accumulators
+    fn last_update_timestamp(&self) -> UnixTimestamp;
+
+    /// Configuration of the trade fee rising with volatility
+    fn dynamic_fees(&self) -> &DynamicFees;
+    /// Volatility of the pool price in basis points, as of the last update
+    fn volatility(&self) -> u64;
+    /// Unix timestamp of the last update to the volatility
+    fn last_volatility_update(&self) -> UnixTimestamp;
+
+    /// Account giving the price used by the curve, if the curve is priced by
+    /// an oracle
+    fn oracle(&self) -> COption<Pubkey>;
+    /// Program owning the oracle account, checked on every swap
+    fn oracle_program_id(&self) -> &Pubkey;
 }
 
+/// Dynamic fees of the versions without volatility, always disabled
+const NO_DYNAMIC_FEES: DynamicFees = DynamicFees {
+    volatility_fee_numerator: 0,
+    volatility_fee_denominator: 0,
+    max_trade_fee_numerator: 0,
+    decay_period: 0,
+};
+
+/// Oracle program of the versions without an oracle
+const NO_ORACLE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([0u8; 32]);
+
 /// All versions of SwapState
 #[enum_dispatch(SwapState)]
 pub enum SwapVersion {
-    /// Latest version, used for all new swaps
+    /// Original version, without a pause authority
     SwapV1,
+    /// Version with a pause authority, without price accumulators
+    SwapV2,
+    /// Latest version, used for all new swaps
+    SwapV3,
 }
 
 /// SwapVersion does not implement program_pack::Pack because there are size
@@ -51,7 +100,7 @@ pub enum SwapVersion {
 /// special implementations are provided here
 impl SwapVersion {
     /// Size of the latest version of the SwapState
-    pub const LATEST_LEN: usize = 1 + SwapV1::LEN; // add one for the version enum
+    pub const LATEST_LEN: usize = 1 + SwapV3::LEN; // add one for the version enum
 
     /// Pack a swap into a byte array, based on its version
     pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
@@ -60,6 +109,15 @@ impl SwapVersion {
                 dst[0] = 1;
                 SwapV1::pack(swap_info, &mut dst[1..])
             }
+            Self::SwapV2(swap_info) => {
+                dst[0] = 2;
+                SwapV2::pack(swap_info, &mut dst[1..])
+            }
+            // MULTI_TOKEN_SWAP_VERSION is skipped
+            Self::SwapV3(swap_info) => {
+                dst[0] = 4;
+                SwapV3::pack(swap_info, &mut dst[1..])
+            }
         }
     }
 
@@ -71,16 +129,40 @@ impl SwapVersion {
             .ok_or(ProgramError::InvalidAccountData)?;
         match version {
             1 => Ok(Box::new(SwapV1::unpack(rest)?)),
+            2 => Ok(Box::new(SwapV2::unpack(rest)?)),
+            // multi-token swaps must be read with MultiTokenSwapVersion
+            MULTI_TOKEN_SWAP_VERSION => Err(ProgramError::InvalidAccountData),
+            4 => Ok(Box::new(SwapV3::unpack(rest)?)),
+            _ => Err(ProgramError::UninitializedAccount),
+        }
+    }
+
+    /// Unpack the swap account based on its version, returning the concrete
+    /// version so that it can be modified and packed again
+    pub fn unpack_versioned(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&version, rest) = input
+            .split_first()
+            .ok_or(ProgramError::InvalidAccountData)?;
+        match version {
+            1 => Ok(Self::SwapV1(SwapV1::unpack(rest)?)),
+            2 => Ok(Self::SwapV2(SwapV2::unpack(rest)?)),
+            // multi-token swaps must be read with MultiTokenSwapVersion
+            MULTI_TOKEN_SWAP_VERSION => Err(ProgramError::InvalidAccountData),
+            4 => Ok(Self::SwapV3(SwapV3::unpack(rest)?)),
             _ => Err(ProgramError::UninitializedAccount),
         }
     }
 
     /// Special check to be done before any instruction processing, works for
-    /// all versions
+    /// all versions.  Accounts holding a multi-token swap are in use, even
+    /// though they can't be unpacked as a two-token swap.
     pub fn is_initialized(input: &[u8]) -> bool {
-        match Self::unpack(input) {
-            Ok(swap) => swap.is_initialized(),
-            Err(_) => false,
+        match input.first() {
+            Some(&MULTI_TOKEN_SWAP_VERSION) => MultiTokenSwapVersion::is_initialized(input),
+            _ => match Self::unpack(input) {
+                Ok(swap) => swap.is_initialized(),
+                Err(_) => false,
+            },
         }
     }
 }
@@ -170,6 +252,46 @@ impl SwapState for SwapV1 {
     fn swap_curve(&self) -> &SwapCurve {
         &self.swap_curve
     }
+
+    fn pause_authority(&self) -> COption<Pubkey> {
+        COption::None
+    }
+
+    fn is_paused(&self) -> bool {
+        false
+    }
+
+    fn price_a_cumulative(&self) -> u128 {
+        0
+    }
+
+    fn price_b_cumulative(&self) -> u128 {
+        0
+    }
+
+    fn last_update_timestamp(&self) -> UnixTimestamp {
+        0
+    }
+
+    fn dynamic_fees(&self) -> &DynamicFees {
+        &NO_DYNAMIC_FEES
+    }
+
+    fn volatility(&self) -> u64 {
+        0
+    }
+
+    fn last_volatility_update(&self) -> UnixTimestamp {
+        0
+    }
+
+    fn oracle(&self) -> COption<Pubkey> {
+        COption::None
+    }
+
+    fn oracle_program_id(&self) -> &Pubkey {
+        &NO_ORACLE_PROGRAM_ID
+    }
 }
 
 impl Sealed for SwapV1 {}
@@ -247,130 +369,1290 @@ impl Pack for SwapV1 {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::curve::stable::StableCurve;
+/// Program states, with an optional authority able to pause the swap.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct SwapV2 {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Nonce used in program address.
+    /// The program address is created deterministically with the nonce,
+    /// swap program id, and swap account pubkey.  This program address has
+    /// authority over the swap's token A account, token B account, and pool
+    /// token mint.
+    pub nonce: u8,
 
-    use std::convert::TryInto;
+    /// Program ID of the tokens being exchanged.
+    pub token_program_id: Pubkey,
 
-    const TEST_FEES: Fees = Fees {
-        trade_fee_numerator: 1,
-        trade_fee_denominator: 4,
-        owner_trade_fee_numerator: 3,
-        owner_trade_fee_denominator: 10,
-        owner_withdraw_fee_numerator: 2,
-        owner_withdraw_fee_denominator: 7,
-        host_fee_numerator: 5,
-        host_fee_denominator: 20,
-    };
+    /// Token A
+    pub token_a: Pubkey,
+    /// Token B
+    pub token_b: Pubkey,
 
-    const TEST_NONCE: u8 = 255;
-    const TEST_TOKEN_PROGRAM_ID: Pubkey = Pubkey::new_from_array([1u8; 32]);
-    const TEST_TOKEN_A: Pubkey = Pubkey::new_from_array([2u8; 32]);
-    const TEST_TOKEN_B: Pubkey = Pubkey::new_from_array([3u8; 32]);
-    const TEST_POOL_MINT: Pubkey = Pubkey::new_from_array([4u8; 32]);
-    const TEST_TOKEN_A_MINT: Pubkey = Pubkey::new_from_array([5u8; 32]);
-    const TEST_TOKEN_B_MINT: Pubkey = Pubkey::new_from_array([6u8; 32]);
-    const TEST_POOL_FEE_ACCOUNT: Pubkey = Pubkey::new_from_array([7u8; 32]);
+    /// Pool tokens are issued when A or B tokens are deposited.
+    /// Pool tokens can be withdrawn back to the original A or B token.
+    pub pool_mint: Pubkey,
 
-    const TEST_CURVE_TYPE: u8 = 2;
-    const TEST_AMP: u64 = 1;
-    const TEST_CURVE: StableCurve = StableCurve { amp: TEST_AMP };
+    /// Mint information for token A
+    pub token_a_mint: Pubkey,
+    /// Mint information for token B
+    pub token_b_mint: Pubkey,
 
-    #[test]
-    fn swap_version_pack() {
-        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
-        let calculator = Box::new(TEST_CURVE);
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator,
-        };
-        let swap_info = SwapVersion::SwapV1(SwapV1 {
-            is_initialized: true,
-            nonce: TEST_NONCE,
-            token_program_id: TEST_TOKEN_PROGRAM_ID,
-            token_a: TEST_TOKEN_A,
-            token_b: TEST_TOKEN_B,
-            pool_mint: TEST_POOL_MINT,
-            token_a_mint: TEST_TOKEN_A_MINT,
-            token_b_mint: TEST_TOKEN_B_MINT,
-            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
-            fees: TEST_FEES,
-            swap_curve: swap_curve.clone(),
-        });
+    /// Pool token account to receive trading and / or withdrawal fees
+    pub pool_fee_account: Pubkey,
 
-        let mut packed = [0u8; SwapVersion::LATEST_LEN];
-        SwapVersion::pack(swap_info, &mut packed).unwrap();
-        let unpacked = SwapVersion::unpack(&packed).unwrap();
+    /// All fee information
+    pub fees: Fees,
 
-        assert!(unpacked.is_initialized());
-        assert_eq!(unpacked.nonce(), TEST_NONCE);
-        assert_eq!(*unpacked.token_program_id(), TEST_TOKEN_PROGRAM_ID);
-        assert_eq!(*unpacked.token_a_account(), TEST_TOKEN_A);
-        assert_eq!(*unpacked.token_b_account(), TEST_TOKEN_B);
-        assert_eq!(*unpacked.pool_mint(), TEST_POOL_MINT);
-        assert_eq!(*unpacked.token_a_mint(), TEST_TOKEN_A_MINT);
-        assert_eq!(*unpacked.token_b_mint(), TEST_TOKEN_B_MINT);
-        assert_eq!(*unpacked.pool_fee_account(), TEST_POOL_FEE_ACCOUNT);
-        assert_eq!(*unpacked.fees(), TEST_FEES);
-        assert_eq!(*unpacked.swap_curve(), swap_curve);
+    /// Swap curve parameters, to be unpacked and used by the SwapCurve, which
+    /// calculates swaps, deposits, and withdrawals
+    pub swap_curve: SwapCurve,
+
+    /// Authority allowed to pause and unpause the swap.  If not present,
+    /// the swap can never be paused.
+    pub pause_authority: COption<Pubkey>,
+    /// Paused state.  While paused, swaps and deposits are rejected, but
+    /// withdrawals are still allowed.
+    pub is_paused: bool,
+}
+
+impl SwapState for SwapV2 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
     }
 
-    #[test]
-    fn swap_v1_pack() {
-        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
-        let calculator = Box::new(TEST_CURVE);
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator,
-        };
-        let swap_info = SwapV1 {
-            is_initialized: true,
-            nonce: TEST_NONCE,
-            token_program_id: TEST_TOKEN_PROGRAM_ID,
-            token_a: TEST_TOKEN_A,
-            token_b: TEST_TOKEN_B,
-            pool_mint: TEST_POOL_MINT,
-            token_a_mint: TEST_TOKEN_A_MINT,
-            token_b_mint: TEST_TOKEN_B_MINT,
-            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
-            fees: TEST_FEES,
-            swap_curve,
-        };
+    fn nonce(&self) -> u8 {
+        self.nonce
+    }
 
-        let mut packed = [0u8; SwapV1::LEN];
-        SwapV1::pack_into_slice(&swap_info, &mut packed);
-        let unpacked = SwapV1::unpack(&packed).unwrap();
-        assert_eq!(swap_info, unpacked);
+    fn token_program_id(&self) -> &Pubkey {
+        &self.token_program_id
+    }
 
-        let mut packed = vec![1u8, TEST_NONCE];
-        packed.extend_from_slice(&TEST_TOKEN_PROGRAM_ID.to_bytes());
-        packed.extend_from_slice(&TEST_TOKEN_A.to_bytes());
-        packed.extend_from_slice(&TEST_TOKEN_B.to_bytes());
-        packed.extend_from_slice(&TEST_POOL_MINT.to_bytes());
-        packed.extend_from_slice(&TEST_TOKEN_A_MINT.to_bytes());
-        packed.extend_from_slice(&TEST_TOKEN_B_MINT.to_bytes());
-        packed.extend_from_slice(&TEST_POOL_FEE_ACCOUNT.to_bytes());
-        packed.extend_from_slice(&TEST_FEES.trade_fee_numerator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.trade_fee_denominator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_numerator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_denominator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_numerator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_denominator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.host_fee_numerator.to_le_bytes());
-        packed.extend_from_slice(&TEST_FEES.host_fee_denominator.to_le_bytes());
-        packed.push(TEST_CURVE_TYPE);
-        packed.extend_from_slice(&TEST_AMP.to_le_bytes());
-        packed.extend_from_slice(&[0u8; 24]);
-        let unpacked = SwapV1::unpack(&packed).unwrap();
-        assert_eq!(swap_info, unpacked);
+    fn token_a_account(&self) -> &Pubkey {
+        &self.token_a
+    }
 
-        let packed = [0u8; SwapV1::LEN];
-        let swap_info: SwapV1 = Default::default();
-        let unpack_unchecked = SwapV1::unpack_unchecked(&packed).unwrap();
-        assert_eq!(unpack_unchecked, swap_info);
-        let err = SwapV1::unpack(&packed).unwrap_err();
+    fn token_b_account(&self) -> &Pubkey {
+        &self.token_b
+    }
+
+    fn pool_mint(&self) -> &Pubkey {
+        &self.pool_mint
+    }
+
+    fn token_a_mint(&self) -> &Pubkey {
+        &self.token_a_mint
+    }
+
+    fn token_b_mint(&self) -> &Pubkey {
+        &self.token_b_mint
+    }
+
+    fn pool_fee_account(&self) -> &Pubkey {
+        &self.pool_fee_account
+    }
+
+    fn fees(&self) -> &Fees {
+        &self.fees
+    }
+
+    fn swap_curve(&self) -> &SwapCurve {
+        &self.swap_curve
+    }
+
+    fn pause_authority(&self) -> COption<Pubkey> {
+        self.pause_authority
+    }
+
+    fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    fn price_a_cumulative(&self) -> u128 {
+        0
+    }
+
+    fn price_b_cumulative(&self) -> u128 {
+        0
+    }
+
+    fn last_update_timestamp(&self) -> UnixTimestamp {
+        0
+    }
+
+    fn dynamic_fees(&self) -> &DynamicFees {
+        &NO_DYNAMIC_FEES
+    }
+
+    fn volatility(&self) -> u64 {
+        0
+    }
+
+    fn last_volatility_update(&self) -> UnixTimestamp {
+        0
+    }
+
+    fn oracle(&self) -> COption<Pubkey> {
+        COption::None
+    }
+
+    fn oracle_program_id(&self) -> &Pubkey {
+        &NO_ORACLE_PROGRAM_ID
+    }
+}
+
+impl Sealed for SwapV2 {}
+impl IsInitialized for SwapV2 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for SwapV2 {
+    const LEN: usize = 360;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 360];
+        let (
+            is_initialized,
+            nonce,
+            token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused,
+        ) = mut_array_refs![output, 1, 1, 32, 32, 32, 32, 32, 32, 32, 64, 33, 36, 1];
+        is_initialized[0] = self.is_initialized as u8;
+        nonce[0] = self.nonce;
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        token_a.copy_from_slice(self.token_a.as_ref());
+        token_b.copy_from_slice(self.token_b.as_ref());
+        pool_mint.copy_from_slice(self.pool_mint.as_ref());
+        token_a_mint.copy_from_slice(self.token_a_mint.as_ref());
+        token_b_mint.copy_from_slice(self.token_b_mint.as_ref());
+        pool_fee_account.copy_from_slice(self.pool_fee_account.as_ref());
+        self.fees.pack_into_slice(&mut fees[..]);
+        self.swap_curve.pack_into_slice(&mut swap_curve[..]);
+        pack_coption_key(&self.pause_authority, pause_authority);
+        is_paused[0] = self.is_paused as u8;
+    }
+
+    /// Unpacks a byte buffer into a [SwapV2](struct.SwapV2.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 360];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            is_initialized,
+            nonce,
+            token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused,
+        ) = array_refs![input, 1, 1, 32, 32, 32, 32, 32, 32, 32, 64, 33, 36, 1];
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            nonce: nonce[0],
+            token_program_id: Pubkey::new_from_array(*token_program_id),
+            token_a: Pubkey::new_from_array(*token_a),
+            token_b: Pubkey::new_from_array(*token_b),
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            token_a_mint: Pubkey::new_from_array(*token_a_mint),
+            token_b_mint: Pubkey::new_from_array(*token_b_mint),
+            pool_fee_account: Pubkey::new_from_array(*pool_fee_account),
+            fees: Fees::unpack_from_slice(fees)?,
+            swap_curve: SwapCurve::unpack_from_slice(swap_curve)?,
+            pause_authority: unpack_coption_key(pause_authority)?,
+            is_paused: match is_paused {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+        })
+    }
+}
+
+/// Program states, with price accumulators used to derive time-weighted
+/// average prices (TWAPs), a trade fee rising with the recent volatility of
+/// the pool price, see [DynamicFees](../curve/fees/struct.DynamicFees.html),
+/// and an oracle account giving the price used by the curve, for curves
+/// priced by an oracle.
+///
+/// Prices are the Q64.64 fixed-point spot prices given by the swap curve for
+/// the pool's token balances, of token A in token B and of token B in token A.
+/// On every swap, deposit, and withdrawal, the prices held since the last
+/// update are added to the accumulators, weighted by the number of seconds
+/// elapsed.  The accumulators wrap on overflow, so the TWAP between two
+/// observations is the wrapping difference of their accumulators, divided by
+/// the difference of their timestamps.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct SwapV3 {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Nonce used in program address.
+    /// The program address is created deterministically with the nonce,
+    /// swap program id, and swap account pubkey.  This program address has
+    /// authority over the swap's token A account, token B account, and pool
+    /// token mint.
+    pub nonce: u8,
+
+    /// Program ID of the tokens being exchanged.
+    pub token_program_id: Pubkey,
+
+    /// Token A
+    pub token_a: Pubkey,
+    /// Token B
+    pub token_b: Pubkey,
+
+    /// Pool tokens are issued when A or B tokens are deposited.
+    /// Pool tokens can be withdrawn back to the original A or B token.
+    pub pool_mint: Pubkey,
+
+    /// Mint information for token A
+    pub token_a_mint: Pubkey,
+    /// Mint information for token B
+    pub token_b_mint: Pubkey,
+
+    /// Pool token account to receive trading and / or withdrawal fees
+    pub pool_fee_account: Pubkey,
+
+    /// All fee information
+    pub fees: Fees,
+
+    /// Swap curve parameters, to be unpacked and used by the SwapCurve, which
+    /// calculates swaps, deposits, and withdrawals
+    pub swap_curve: SwapCurve,
+
+    /// Authority allowed to pause and unpause the swap.  If not present,
+    /// the swap can never be paused.
+    pub pause_authority: COption<Pubkey>,
+    /// Paused state.  While paused, swaps and deposits are rejected, but
+    /// withdrawals are still allowed.
+    pub is_paused: bool,
+
+    /// Sum of the Q64.64 price of token A in token B, weighted by time
+    pub price_a_cumulative: u128,
+    /// Sum of the Q64.64 price of token B in token A, weighted by time
+    pub price_b_cumulative: u128,
+    /// Unix timestamp of the last update to the price accumulators
+    pub last_update_timestamp: UnixTimestamp,
+
+    /// Configuration of the trade fee rising with volatility
+    pub dynamic_fees: DynamicFees,
+    /// Sum of the price movements of recent swaps, in basis points, as of
+    /// the last volatility update
+    pub volatility: u64,
+    /// Unix timestamp of the last update to the volatility
+    pub last_volatility_update: UnixTimestamp,
+
+    /// Account giving the price used by the curve, required by every swap.
+    /// Only present for curves priced by an oracle.
+    pub oracle: COption<Pubkey>,
+    /// Program trusted to own the oracle account, validated on initialization
+    /// against the swap constraints.  Swaps are rejected if the oracle account
+    /// is owned by any other program.
+    pub oracle_program_id: Pubkey,
+}
+
+impl SwapV3 {
+    /// Adds the spot prices given by the swap curve for the pool balances,
+    /// held since the last update, to the accumulators.  The balances must be
+    /// the ones held before the current operation changes them.  Nothing is
+    /// accumulated while the curve gives no price, for example while the pool
+    /// is empty.
+    pub fn update_price_cumulative(
+        &mut self,
+        token_a_amount: u64,
+        token_b_amount: u64,
+        timestamp: UnixTimestamp,
+    ) {
+        let time_elapsed = timestamp.saturating_sub(self.last_update_timestamp);
+        if time_elapsed <= 0 {
+            return;
+        }
+        let calculator = &self.swap_curve.calculator;
+        let token_a_amount = token_a_amount as u128;
+        let token_b_amount = token_b_amount as u128;
+        if let (Some(price_a), Some(price_b)) = (
+            calculator.spot_price(token_a_amount, token_b_amount, TradeDirection::AtoB),
+            calculator.spot_price(token_b_amount, token_a_amount, TradeDirection::BtoA),
+        ) {
+            let time_elapsed = time_elapsed as u128;
+            self.price_a_cumulative = self
+                .price_a_cumulative
+                .wrapping_add(price_a.wrapping_mul(time_elapsed));
+            self.price_b_cumulative = self
+                .price_b_cumulative
+                .wrapping_add(price_b.wrapping_mul(time_elapsed));
+        }
+        self.last_update_timestamp = timestamp;
+    }
+
+    /// Adds the price movement of a swap, in basis points, to the volatility
+    /// decayed until `timestamp`
+    pub fn update_volatility(&mut self, price_movement: u64, timestamp: UnixTimestamp) {
+        self.volatility = self
+            .dynamic_fees
+            .decayed_volatility(
+                self.volatility,
+                timestamp.saturating_sub(self.last_volatility_update),
+            )
+            .saturating_add(price_movement);
+        self.last_volatility_update = timestamp;
+    }
+}
+
+impl SwapState for SwapV3 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+
+    fn nonce(&self) -> u8 {
+        self.nonce
+    }
+
+    fn token_program_id(&self) -> &Pubkey {
+        &self.token_program_id
+    }
+
+    fn token_a_account(&self) -> &Pubkey {
+        &self.token_a
+    }
+
+    fn token_b_account(&self) -> &Pubkey {
+        &self.token_b
+    }
+
+    fn pool_mint(&self) -> &Pubkey {
+        &self.pool_mint
+    }
+
+    fn token_a_mint(&self) -> &Pubkey {
+        &self.token_a_mint
+    }
+
+    fn token_b_mint(&self) -> &Pubkey {
+        &self.token_b_mint
+    }
+
+    fn pool_fee_account(&self) -> &Pubkey {
+        &self.pool_fee_account
+    }
+
+    fn fees(&self) -> &Fees {
+        &self.fees
+    }
+
+    fn swap_curve(&self) -> &SwapCurve {
+        &self.swap_curve
+    }
+
+    fn pause_authority(&self) -> COption<Pubkey> {
+        self.pause_authority
+    }
+
+    fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    fn price_a_cumulative(&self) -> u128 {
+        self.price_a_cumulative
+    }
+
+    fn price_b_cumulative(&self) -> u128 {
+        self.price_b_cumulative
+    }
+
+    fn last_update_timestamp(&self) -> UnixTimestamp {
+        self.last_update_timestamp
+    }
+
+    fn dynamic_fees(&self) -> &DynamicFees {
+        &self.dynamic_fees
+    }
+
+    fn volatility(&self) -> u64 {
+        self.volatility
+    }
+
+    fn last_volatility_update(&self) -> UnixTimestamp {
+        self.last_volatility_update
+    }
+
+    fn oracle(&self) -> COption<Pubkey> {
+        self.oracle
+    }
+
+    fn oracle_program_id(&self) -> &Pubkey {
+        &self.oracle_program_id
+    }
+}
+
+impl Sealed for SwapV3 {}
+impl IsInitialized for SwapV3 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for SwapV3 {
+    const LEN: usize = 516;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 516];
+        let (
+            is_initialized,
+            nonce,
+            token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused,
+            price_a_cumulative,
+            price_b_cumulative,
+            last_update_timestamp,
+            dynamic_fees,
+            volatility,
+            last_volatility_update,
+            oracle,
+            oracle_program_id,
+        ) = mut_array_refs![
+            output, 1, 1, 32, 32, 32, 32, 32, 32, 32, 64, 33, 36, 1, 16, 16, 8, 32, 8, 8, 36, 32
+        ];
+        is_initialized[0] = self.is_initialized as u8;
+        nonce[0] = self.nonce;
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        token_a.copy_from_slice(self.token_a.as_ref());
+        token_b.copy_from_slice(self.token_b.as_ref());
+        pool_mint.copy_from_slice(self.pool_mint.as_ref());
+        token_a_mint.copy_from_slice(self.token_a_mint.as_ref());
+        token_b_mint.copy_from_slice(self.token_b_mint.as_ref());
+        pool_fee_account.copy_from_slice(self.pool_fee_account.as_ref());
+        self.fees.pack_into_slice(&mut fees[..]);
+        self.swap_curve.pack_into_slice(&mut swap_curve[..]);
+        pack_coption_key(&self.pause_authority, pause_authority);
+        is_paused[0] = self.is_paused as u8;
+        *price_a_cumulative = self.price_a_cumulative.to_le_bytes();
+        *price_b_cumulative = self.price_b_cumulative.to_le_bytes();
+        *last_update_timestamp = self.last_update_timestamp.to_le_bytes();
+        self.dynamic_fees.pack_into_slice(&mut dynamic_fees[..]);
+        *volatility = self.volatility.to_le_bytes();
+        *last_volatility_update = self.last_volatility_update.to_le_bytes();
+        pack_coption_key(&self.oracle, oracle);
+        oracle_program_id.copy_from_slice(self.oracle_program_id.as_ref());
+    }
+
+    /// Unpacks a byte buffer into a [SwapV3](struct.SwapV3.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 516];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            is_initialized,
+            nonce,
+            token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused,
+            price_a_cumulative,
+            price_b_cumulative,
+            last_update_timestamp,
+            dynamic_fees,
+            volatility,
+            last_volatility_update,
+            oracle,
+            oracle_program_id,
+        ) = array_refs![
+            input, 1, 1, 32, 32, 32, 32, 32, 32, 32, 64, 33, 36, 1, 16, 16, 8, 32, 8, 8, 36, 32
+        ];
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            nonce: nonce[0],
+            token_program_id: Pubkey::new_from_array(*token_program_id),
+            token_a: Pubkey::new_from_array(*token_a),
+            token_b: Pubkey::new_from_array(*token_b),
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            token_a_mint: Pubkey::new_from_array(*token_a_mint),
+            token_b_mint: Pubkey::new_from_array(*token_b_mint),
+            pool_fee_account: Pubkey::new_from_array(*pool_fee_account),
+            fees: Fees::unpack_from_slice(fees)?,
+            swap_curve: SwapCurve::unpack_from_slice(swap_curve)?,
+            pause_authority: unpack_coption_key(pause_authority)?,
+            is_paused: match is_paused {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            price_a_cumulative: u128::from_le_bytes(*price_a_cumulative),
+            price_b_cumulative: u128::from_le_bytes(*price_b_cumulative),
+            last_update_timestamp: UnixTimestamp::from_le_bytes(*last_update_timestamp),
+            dynamic_fees: DynamicFees::unpack_from_slice(dynamic_fees)?,
+            volatility: u64::from_le_bytes(*volatility),
+            last_volatility_update: UnixTimestamp::from_le_bytes(*last_volatility_update),
+            oracle: unpack_coption_key(oracle)?,
+            oracle_program_id: Pubkey::new_from_array(*oracle_program_id),
+        })
+    }
+}
+
+/// Minimum number of tokens held by a multi-token swap
+pub const MIN_MULTI_TOKENS: usize = 3;
+/// Maximum number of tokens held by a multi-token swap
+pub const MAX_MULTI_TOKENS: usize = 8;
+
+/// Version number of the multi-token swap state, never used by SwapVersion
+const MULTI_TOKEN_SWAP_VERSION: u8 = 3;
+
+/// All versions of the multi-token swap state.  Multi-token swaps are stored
+/// with their own version numbers, distinct from the ones used by
+/// SwapVersion, so that a multi-token swap can never be read as a two-token
+/// swap, and vice versa.
+pub enum MultiTokenSwapVersion {
+    /// Original version of the multi-token swap
+    MultiTokenSwapV1(MultiTokenSwapV1),
+}
+
+impl MultiTokenSwapVersion {
+    /// Size of the latest version of the multi-token swap state
+    pub const LATEST_LEN: usize = 1 + MultiTokenSwapV1::LEN; // add one for the version enum
+
+    /// Pack a multi-token swap into a byte array, based on its version
+    pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        match src {
+            Self::MultiTokenSwapV1(swap_info) => {
+                dst[0] = MULTI_TOKEN_SWAP_VERSION;
+                MultiTokenSwapV1::pack(swap_info, &mut dst[1..])
+            }
+        }
+    }
+
+    /// Unpack the multi-token swap account based on its version
+    pub fn unpack(input: &[u8]) -> Result<MultiTokenSwapV1, ProgramError> {
+        let (&version, rest) = input
+            .split_first()
+            .ok_or(ProgramError::InvalidAccountData)?;
+        match version {
+            MULTI_TOKEN_SWAP_VERSION => MultiTokenSwapV1::unpack(rest),
+            _ => Err(ProgramError::UninitializedAccount),
+        }
+    }
+
+    /// Special check to be done before any instruction processing, works for
+    /// all versions
+    pub fn is_initialized(input: &[u8]) -> bool {
+        match Self::unpack(input) {
+            Ok(swap) => swap.is_initialized,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Program state for a swap holding between 3 and 8 tokens.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct MultiTokenSwapV1 {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Nonce used in program address.
+    /// The program address is created deterministically with the nonce,
+    /// swap program id, and swap account pubkey.  This program address has
+    /// authority over all of the swap's token accounts, and the pool token
+    /// mint.
+    pub nonce: u8,
+
+    /// Program ID of the tokens being exchanged.
+    pub token_program_id: Pubkey,
+
+    /// Pool tokens are issued when tokens are deposited.
+    /// Pool tokens can be withdrawn back to the original tokens.
+    pub pool_mint: Pubkey,
+
+    /// Pool token account to receive trading and / or withdrawal fees
+    pub pool_fee_account: Pubkey,
+
+    /// All fee information
+    pub fees: Fees,
+
+    /// Swap curve parameters, used to calculate swaps between any two tokens
+    pub swap_curve: MultiStableCurve,
+
+    /// Authority allowed to pause and unpause the swap.  If not present,
+    /// the swap can never be paused.
+    pub pause_authority: COption<Pubkey>,
+    /// Paused state.  While paused, swaps and deposits are rejected, but
+    /// withdrawals are still allowed.
+    pub is_paused: bool,
+
+    /// Token accounts held by the swap, in the order used by instructions
+    pub tokens: Vec<Pubkey>,
+    /// Mint information for each token, in the same order as `tokens`
+    pub token_mints: Vec<Pubkey>,
+}
+
+impl Sealed for MultiTokenSwapV1 {}
+impl IsInitialized for MultiTokenSwapV1 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for MultiTokenSwapV1 {
+    const LEN: usize = 720;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 720];
+        let (
+            is_initialized,
+            nonce,
+            token_program_id,
+            pool_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused,
+            token_count,
+            tokens,
+            token_mints,
+        ) = mut_array_refs![output, 1, 1, 32, 32, 32, 64, 8, 36, 1, 1, 256, 256];
+        is_initialized[0] = self.is_initialized as u8;
+        nonce[0] = self.nonce;
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        pool_mint.copy_from_slice(self.pool_mint.as_ref());
+        pool_fee_account.copy_from_slice(self.pool_fee_account.as_ref());
+        self.fees.pack_into_slice(&mut fees[..]);
+        self.swap_curve.pack_into_slice(&mut swap_curve[..]);
+        pack_coption_key(&self.pause_authority, pause_authority);
+        is_paused[0] = self.is_paused as u8;
+        token_count[0] = self.tokens.len() as u8;
+        pack_keys(&self.tokens, tokens);
+        pack_keys(&self.token_mints, token_mints);
+    }
+
+    /// Unpacks a byte buffer into a [MultiTokenSwapV1](struct.MultiTokenSwapV1.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 720];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            is_initialized,
+            nonce,
+            token_program_id,
+            pool_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            pause_authority,
+            is_paused,
+            token_count,
+            tokens,
+            token_mints,
+        ) = array_refs![input, 1, 1, 32, 32, 32, 64, 8, 36, 1, 1, 256, 256];
+        let token_count = token_count[0] as usize;
+        if token_count > MAX_MULTI_TOKENS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            nonce: nonce[0],
+            token_program_id: Pubkey::new_from_array(*token_program_id),
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            pool_fee_account: Pubkey::new_from_array(*pool_fee_account),
+            fees: Fees::unpack_from_slice(fees)?,
+            swap_curve: MultiStableCurve::unpack_from_slice(swap_curve)?,
+            pause_authority: unpack_coption_key(pause_authority)?,
+            is_paused: match is_paused {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            tokens: unpack_keys(tokens, token_count),
+            token_mints: unpack_keys(token_mints, token_count),
+        })
+    }
+}
+
+// Helpers
+fn pack_coption_key(src: &COption<Pubkey>, dst: &mut [u8; 36]) {
+    let (tag, body) = mut_array_refs![dst, 4, 32];
+    match src {
+        COption::Some(key) => {
+            *tag = [1, 0, 0, 0];
+            body.copy_from_slice(key.as_ref());
+        }
+        COption::None => {
+            *tag = [0; 4];
+        }
+    }
+}
+fn unpack_coption_key(src: &[u8; 36]) -> Result<COption<Pubkey>, ProgramError> {
+    let (tag, body) = array_refs![src, 4, 32];
+    match *tag {
+        [0, 0, 0, 0] => Ok(COption::None),
+        [1, 0, 0, 0] => Ok(COption::Some(Pubkey::new_from_array(*body))),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+fn pack_keys(src: &[Pubkey], dst: &mut [u8; 256]) {
+    for (key, dst) in src.iter().zip(dst.chunks_exact_mut(32)) {
+        dst.copy_from_slice(key.as_ref());
+    }
+}
+fn unpack_keys(src: &[u8; 256], count: usize) -> Vec<Pubkey> {
+    src.chunks_exact(32)
+        .take(count)
+        .map(|key| Pubkey::new_from_array(*array_ref![key, 0, 32]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{base::CurveType, constant_price::ConstantPriceCurve, stable::StableCurve};
+
+    use std::convert::TryInto;
+
+    const TEST_FEES: Fees = Fees {
+        trade_fee_numerator: 1,
+        trade_fee_denominator: 4,
+        owner_trade_fee_numerator: 3,
+        owner_trade_fee_denominator: 10,
+        owner_withdraw_fee_numerator: 2,
+        owner_withdraw_fee_denominator: 7,
+        host_fee_numerator: 5,
+        host_fee_denominator: 20,
+    };
+
+    const TEST_NONCE: u8 = 255;
+    const TEST_TOKEN_PROGRAM_ID: Pubkey = Pubkey::new_from_array([1u8; 32]);
+    const TEST_TOKEN_A: Pubkey = Pubkey::new_from_array([2u8; 32]);
+    const TEST_TOKEN_B: Pubkey = Pubkey::new_from_array([3u8; 32]);
+    const TEST_POOL_MINT: Pubkey = Pubkey::new_from_array([4u8; 32]);
+    const TEST_TOKEN_A_MINT: Pubkey = Pubkey::new_from_array([5u8; 32]);
+    const TEST_TOKEN_B_MINT: Pubkey = Pubkey::new_from_array([6u8; 32]);
+    const TEST_POOL_FEE_ACCOUNT: Pubkey = Pubkey::new_from_array([7u8; 32]);
+    const TEST_PAUSE_AUTHORITY: Pubkey = Pubkey::new_from_array([8u8; 32]);
+    const TEST_PRICE_A_CUMULATIVE: u128 = 0x1234_5678_9abc_def0_1122_3344_5566_7788;
+    const TEST_PRICE_B_CUMULATIVE: u128 = 0x0fed_cba9_8765_4321_8877_6655_4433_2211;
+    const TEST_LAST_UPDATE_TIMESTAMP: UnixTimestamp = 1_620_000_000;
+    const TEST_DYNAMIC_FEES: DynamicFees = DynamicFees {
+        volatility_fee_numerator: 1,
+        volatility_fee_denominator: 10,
+        max_trade_fee_numerator: 100,
+        decay_period: 600,
+    };
+    const TEST_VOLATILITY: u64 = 250;
+    const TEST_LAST_VOLATILITY_UPDATE: UnixTimestamp = 1_620_000_300;
+    const TEST_ORACLE: Pubkey = Pubkey::new_from_array([11u8; 32]);
+    const TEST_ORACLE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([12u8; 32]);
+    const TEST_TOKEN_C: Pubkey = Pubkey::new_from_array([9u8; 32]);
+    const TEST_TOKEN_C_MINT: Pubkey = Pubkey::new_from_array([10u8; 32]);
+
+    const TEST_CURVE_TYPE: u8 = 2;
+    const TEST_AMP: u64 = 1;
+    const TEST_CURVE: StableCurve = StableCurve { amp: TEST_AMP };
+
+    #[test]
+    fn swap_version_pack() {
+        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
+        let calculator = Box::new(TEST_CURVE);
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+        let swap_info = SwapVersion::SwapV3(SwapV3 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve: swap_curve.clone(),
+            pause_authority: COption::Some(TEST_PAUSE_AUTHORITY),
+            is_paused: true,
+            price_a_cumulative: TEST_PRICE_A_CUMULATIVE,
+            price_b_cumulative: TEST_PRICE_B_CUMULATIVE,
+            last_update_timestamp: TEST_LAST_UPDATE_TIMESTAMP,
+            dynamic_fees: TEST_DYNAMIC_FEES,
+            volatility: TEST_VOLATILITY,
+            last_volatility_update: TEST_LAST_VOLATILITY_UPDATE,
+            oracle: COption::Some(TEST_ORACLE),
+            oracle_program_id: TEST_ORACLE_PROGRAM_ID,
+        });
+
+        let mut packed = [0u8; SwapVersion::LATEST_LEN];
+        SwapVersion::pack(swap_info, &mut packed).unwrap();
+        let unpacked = SwapVersion::unpack(&packed).unwrap();
+
+        assert!(unpacked.is_initialized());
+        assert_eq!(unpacked.nonce(), TEST_NONCE);
+        assert_eq!(*unpacked.token_program_id(), TEST_TOKEN_PROGRAM_ID);
+        assert_eq!(*unpacked.token_a_account(), TEST_TOKEN_A);
+        assert_eq!(*unpacked.token_b_account(), TEST_TOKEN_B);
+        assert_eq!(*unpacked.pool_mint(), TEST_POOL_MINT);
+        assert_eq!(*unpacked.token_a_mint(), TEST_TOKEN_A_MINT);
+        assert_eq!(*unpacked.token_b_mint(), TEST_TOKEN_B_MINT);
+        assert_eq!(*unpacked.pool_fee_account(), TEST_POOL_FEE_ACCOUNT);
+        assert_eq!(*unpacked.fees(), TEST_FEES);
+        assert_eq!(*unpacked.swap_curve(), swap_curve);
+        assert_eq!(
+            unpacked.pause_authority(),
+            COption::Some(TEST_PAUSE_AUTHORITY)
+        );
+        assert!(unpacked.is_paused());
+        assert_eq!(unpacked.price_a_cumulative(), TEST_PRICE_A_CUMULATIVE);
+        assert_eq!(unpacked.price_b_cumulative(), TEST_PRICE_B_CUMULATIVE);
+        assert_eq!(unpacked.last_update_timestamp(), TEST_LAST_UPDATE_TIMESTAMP);
+        assert_eq!(*unpacked.dynamic_fees(), TEST_DYNAMIC_FEES);
+        assert_eq!(unpacked.volatility(), TEST_VOLATILITY);
+        assert_eq!(
+            unpacked.last_volatility_update(),
+            TEST_LAST_VOLATILITY_UPDATE
+        );
+        assert_eq!(unpacked.oracle(), COption::Some(TEST_ORACLE));
+        assert_eq!(*unpacked.oracle_program_id(), TEST_ORACLE_PROGRAM_ID);
+
+        // previously created swaps can still be read
+        let swap_info = SwapVersion::SwapV2(SwapV2 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve: swap_curve.clone(),
+            pause_authority: COption::Some(TEST_PAUSE_AUTHORITY),
+            is_paused: true,
+        });
+
+        let mut packed = [0u8; 1 + SwapV2::LEN];
+        SwapVersion::pack(swap_info, &mut packed).unwrap();
+        let unpacked = SwapVersion::unpack(&packed).unwrap();
+
+        assert!(unpacked.is_initialized());
+        assert_eq!(*unpacked.token_a_account(), TEST_TOKEN_A);
+        assert_eq!(
+            unpacked.pause_authority(),
+            COption::Some(TEST_PAUSE_AUTHORITY)
+        );
+        assert!(unpacked.is_paused());
+        assert_eq!(unpacked.price_a_cumulative(), 0);
+        assert_eq!(unpacked.price_b_cumulative(), 0);
+        assert_eq!(unpacked.last_update_timestamp(), 0);
+
+        let swap_info = SwapVersion::SwapV1(SwapV1 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve: swap_curve.clone(),
+        });
+
+        let mut packed = [0u8; 1 + SwapV1::LEN];
+        SwapVersion::pack(swap_info, &mut packed).unwrap();
+        let unpacked = SwapVersion::unpack(&packed).unwrap();
+
+        assert!(unpacked.is_initialized());
+        assert_eq!(*unpacked.token_a_account(), TEST_TOKEN_A);
+        assert_eq!(*unpacked.swap_curve(), swap_curve);
+        assert_eq!(unpacked.pause_authority(), COption::None);
+        assert!(!unpacked.is_paused());
+        assert_eq!(unpacked.last_update_timestamp(), 0);
+    }
+
+    #[test]
+    fn swap_v1_pack() {
+        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
+        let calculator = Box::new(TEST_CURVE);
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+        let swap_info = SwapV1 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve,
+        };
+
+        let mut packed = [0u8; SwapV1::LEN];
+        SwapV1::pack_into_slice(&swap_info, &mut packed);
+        let unpacked = SwapV1::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let mut packed = vec![1u8, TEST_NONCE];
+        packed.extend_from_slice(&TEST_TOKEN_PROGRAM_ID.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_A.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_B.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_A_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_B_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_FEE_ACCOUNT.to_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_denominator.to_le_bytes());
+        packed.push(TEST_CURVE_TYPE);
+        packed.extend_from_slice(&TEST_AMP.to_le_bytes());
+        packed.extend_from_slice(&[0u8; 24]);
+        let unpacked = SwapV1::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let packed = [0u8; SwapV1::LEN];
+        let swap_info: SwapV1 = Default::default();
+        let unpack_unchecked = SwapV1::unpack_unchecked(&packed).unwrap();
+        assert_eq!(unpack_unchecked, swap_info);
+        let err = SwapV1::unpack(&packed).unwrap_err();
+        assert_eq!(err, ProgramError::UninitializedAccount);
+    }
+
+    #[test]
+    fn swap_v2_pack() {
+        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
+        let calculator = Box::new(TEST_CURVE);
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+        let swap_info = SwapV2 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve,
+            pause_authority: COption::Some(TEST_PAUSE_AUTHORITY),
+            is_paused: false,
+        };
+
+        let mut packed = [0u8; SwapV2::LEN];
+        SwapV2::pack_into_slice(&swap_info, &mut packed);
+        let unpacked = SwapV2::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let mut packed = vec![1u8, TEST_NONCE];
+        packed.extend_from_slice(&TEST_TOKEN_PROGRAM_ID.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_A.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_B.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_A_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_B_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_FEE_ACCOUNT.to_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_denominator.to_le_bytes());
+        packed.push(TEST_CURVE_TYPE);
+        packed.extend_from_slice(&TEST_AMP.to_le_bytes());
+        packed.extend_from_slice(&[0u8; 24]);
+        packed.extend_from_slice(&[1u8, 0, 0, 0]);
+        packed.extend_from_slice(&TEST_PAUSE_AUTHORITY.to_bytes());
+        packed.push(0);
+        let unpacked = SwapV2::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let packed = [0u8; SwapV2::LEN];
+        let swap_info: SwapV2 = Default::default();
+        let unpack_unchecked = SwapV2::unpack_unchecked(&packed).unwrap();
+        assert_eq!(unpack_unchecked, swap_info);
+        let err = SwapV2::unpack(&packed).unwrap_err();
+        assert_eq!(err, ProgramError::UninitializedAccount);
+    }
+
+    #[test]
+    fn swap_v3_pack() {
+        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
+        let calculator = Box::new(TEST_CURVE);
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+        let swap_info = SwapV3 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve,
+            pause_authority: COption::None,
+            is_paused: false,
+            price_a_cumulative: TEST_PRICE_A_CUMULATIVE,
+            price_b_cumulative: TEST_PRICE_B_CUMULATIVE,
+            last_update_timestamp: TEST_LAST_UPDATE_TIMESTAMP,
+            dynamic_fees: TEST_DYNAMIC_FEES,
+            volatility: TEST_VOLATILITY,
+            last_volatility_update: TEST_LAST_VOLATILITY_UPDATE,
+            oracle: COption::Some(TEST_ORACLE),
+            oracle_program_id: TEST_ORACLE_PROGRAM_ID,
+        };
+
+        let mut packed = [0u8; SwapV3::LEN];
+        SwapV3::pack_into_slice(&swap_info, &mut packed);
+        let unpacked = SwapV3::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let mut packed = vec![1u8, TEST_NONCE];
+        packed.extend_from_slice(&TEST_TOKEN_PROGRAM_ID.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_A.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_B.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_A_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_TOKEN_B_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_FEE_ACCOUNT.to_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_denominator.to_le_bytes());
+        packed.push(TEST_CURVE_TYPE);
+        packed.extend_from_slice(&TEST_AMP.to_le_bytes());
+        packed.extend_from_slice(&[0u8; 24]);
+        packed.extend_from_slice(&[0u8; 36]);
+        packed.push(0);
+        packed.extend_from_slice(&TEST_PRICE_A_CUMULATIVE.to_le_bytes());
+        packed.extend_from_slice(&TEST_PRICE_B_CUMULATIVE.to_le_bytes());
+        packed.extend_from_slice(&TEST_LAST_UPDATE_TIMESTAMP.to_le_bytes());
+        packed.extend_from_slice(&TEST_DYNAMIC_FEES.volatility_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_DYNAMIC_FEES.volatility_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_DYNAMIC_FEES.max_trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_DYNAMIC_FEES.decay_period.to_le_bytes());
+        packed.extend_from_slice(&TEST_VOLATILITY.to_le_bytes());
+        packed.extend_from_slice(&TEST_LAST_VOLATILITY_UPDATE.to_le_bytes());
+        packed.extend_from_slice(&[1, 0, 0, 0]);
+        packed.extend_from_slice(&TEST_ORACLE.to_bytes());
+        packed.extend_from_slice(&TEST_ORACLE_PROGRAM_ID.to_bytes());
+        let unpacked = SwapV3::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let packed = [0u8; SwapV3::LEN];
+        let swap_info: SwapV3 = Default::default();
+        let unpack_unchecked = SwapV3::unpack_unchecked(&packed).unwrap();
+        assert_eq!(unpack_unchecked, swap_info);
+        let err = SwapV3::unpack(&packed).unwrap_err();
+        assert_eq!(err, ProgramError::UninitializedAccount);
+    }
+
+    #[test]
+    fn swap_v3_update_volatility() {
+        let mut swap_info = SwapV3 {
+            dynamic_fees: TEST_DYNAMIC_FEES,
+            last_volatility_update: 1_000,
+            ..Default::default()
+        };
+        swap_info.update_volatility(400, 1_000);
+        assert_eq!(swap_info.volatility, 400);
+
+        // a quarter of the decay period later, a quarter has decayed
+        swap_info.update_volatility(100, 1_150);
+        assert_eq!(swap_info.volatility, 400);
+        assert_eq!(swap_info.last_volatility_update, 1_150);
+
+        // fully decayed after the decay period
+        swap_info.update_volatility(50, 1_750);
+        assert_eq!(swap_info.volatility, 50);
+    }
+
+    #[test]
+    fn swap_v3_update_price_cumulative() {
+        let mut swap_info = SwapV3 {
+            last_update_timestamp: 100,
+            ..Default::default()
+        };
+
+        // price of A is 2 B, price of B is 0.5 A, held for 10 seconds
+        swap_info.update_price_cumulative(1_000, 2_000, 110);
+        assert_eq!(swap_info.price_a_cumulative, 20 << 64);
+        assert_eq!(swap_info.price_b_cumulative, 5 << 64);
+        assert_eq!(swap_info.last_update_timestamp, 110);
+
+        // nothing accumulates within the same second, or if time goes back
+        swap_info.update_price_cumulative(1_000, 4_000, 110);
+        swap_info.update_price_cumulative(1_000, 4_000, 105);
+        assert_eq!(swap_info.price_a_cumulative, 20 << 64);
+        assert_eq!(swap_info.price_b_cumulative, 5 << 64);
+        assert_eq!(swap_info.last_update_timestamp, 110);
+
+        // an empty pool has no price, but the timestamp moves on
+        swap_info.update_price_cumulative(0, 4_000, 120);
+        assert_eq!(swap_info.price_a_cumulative, 20 << 64);
+        assert_eq!(swap_info.price_b_cumulative, 5 << 64);
+        assert_eq!(swap_info.last_update_timestamp, 120);
+
+        // TWAP between two observations, with the accumulators wrapping
+        swap_info.price_a_cumulative = u128::MAX;
+        let start = swap_info.price_a_cumulative;
+        swap_info.update_price_cumulative(1_000, 4_000, 124);
+        let twap = swap_info.price_a_cumulative.wrapping_sub(start) / 4;
+        assert_eq!(twap, 4 << 64);
+
+        // other curves accumulate their own spot price, not the balance ratio
+        let mut swap_info = SwapV3 {
+            swap_curve: SwapCurve {
+                curve_type: CurveType::ConstantPrice,
+                calculator: Box::new(ConstantPriceCurve { token_b_price: 4 }),
+            },
+            last_update_timestamp: 100,
+            ..Default::default()
+        };
+        swap_info.update_price_cumulative(1_000, 1_000, 110);
+        assert_eq!(swap_info.price_a_cumulative, 10 << 62);
+        assert_eq!(swap_info.price_b_cumulative, 40 << 64);
+    }
+
+    #[test]
+    fn multi_token_swap_v1_pack() {
+        let tokens = vec![TEST_TOKEN_A, TEST_TOKEN_B, TEST_TOKEN_C];
+        let token_mints = vec![TEST_TOKEN_A_MINT, TEST_TOKEN_B_MINT, TEST_TOKEN_C_MINT];
+        let swap_info = MultiTokenSwapV1 {
+            is_initialized: true,
+            nonce: TEST_NONCE,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            pool_mint: TEST_POOL_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve: MultiStableCurve { amp: TEST_AMP },
+            pause_authority: COption::None,
+            is_paused: false,
+            tokens: tokens.clone(),
+            token_mints: token_mints.clone(),
+        };
+
+        let mut packed = [0u8; MultiTokenSwapV1::LEN];
+        MultiTokenSwapV1::pack_into_slice(&swap_info, &mut packed);
+        let unpacked = MultiTokenSwapV1::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let mut packed = vec![1u8, TEST_NONCE];
+        packed.extend_from_slice(&TEST_TOKEN_PROGRAM_ID.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_MINT.to_bytes());
+        packed.extend_from_slice(&TEST_POOL_FEE_ACCOUNT.to_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_trade_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.owner_withdraw_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_numerator.to_le_bytes());
+        packed.extend_from_slice(&TEST_FEES.host_fee_denominator.to_le_bytes());
+        packed.extend_from_slice(&TEST_AMP.to_le_bytes());
+        packed.extend_from_slice(&[0u8; 36]);
+        packed.push(0);
+        packed.push(3);
+        for token in tokens.iter() {
+            packed.extend_from_slice(&token.to_bytes());
+        }
+        packed.extend_from_slice(&[0u8; 5 * 32]);
+        for token_mint in token_mints.iter() {
+            packed.extend_from_slice(&token_mint.to_bytes());
+        }
+        packed.extend_from_slice(&[0u8; 5 * 32]);
+        let unpacked = MultiTokenSwapV1::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        // multi-token swaps are not readable as two-token swaps
+        let mut packed = [0u8; MultiTokenSwapVersion::LATEST_LEN];
+        MultiTokenSwapVersion::pack(
+            MultiTokenSwapVersion::MultiTokenSwapV1(swap_info),
+            &mut packed,
+        )
+        .unwrap();
+        assert!(MultiTokenSwapVersion::is_initialized(&packed));
+        // but are in use, so they can't be initialized as one either
+        assert!(SwapVersion::is_initialized(&packed));
+        assert_eq!(
+            SwapVersion::unpack(&packed).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            SwapVersion::unpack_versioned(&packed).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+
+        let packed = [0u8; MultiTokenSwapV1::LEN];
+        let swap_info: MultiTokenSwapV1 = Default::default();
+        let unpack_unchecked = MultiTokenSwapV1::unpack_unchecked(&packed).unwrap();
+        assert_eq!(unpack_unchecked, swap_info);
+        let err = MultiTokenSwapV1::unpack(&packed).unwrap_err();
         assert_eq!(err, ProgramError::UninitializedAccount);
     }
 }
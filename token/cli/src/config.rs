@@ -20,6 +20,7 @@ pub(crate) struct Config<'a> {
     pub(crate) blockhash_query: BlockhashQuery,
     pub(crate) sign_only: bool,
     pub(crate) multisigner_pubkeys: Vec<&'a Pubkey>,
+    pub(crate) export_transaction: Option<String>,
 }
 
 impl<'a> Config<'a> {
@@ -95,9 +96,11 @@ impl<'a> Config<'a> {
         wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
     ) -> (Box<dyn Signer>, Pubkey) {
         // If there are `--multisig-signers` on the command line, allow `NullSigner`s to
-        // be returned for multisig account addresses
+        // be returned for multisig account addresses.  Exported transactions may be signed
+        // by any signer later on.
         let config = SignerFromPathConfig {
-            allow_null_signer: !self.multisigner_pubkeys.is_empty(),
+            allow_null_signer: !self.multisigner_pubkeys.is_empty()
+                || self.export_transaction.is_some(),
         };
         let mut load_authority = move || {
             // fallback handled in default_signer() for backward compatibility
@@ -0,0 +1,149 @@
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    str::FromStr,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+/// A row of a distribution CSV
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DistributionRow {
+    /// Index of the row in the CSV, not counting blank lines or the header
+    pub index: usize,
+    /// Wallet or token account address of the recipient
+    pub recipient: Pubkey,
+    /// Amount to send, in tokens
+    pub ui_amount: f64,
+}
+
+/// Reads `RECIPIENT,AMOUNT` rows from a CSV file.  A header row, blank lines and lines
+/// starting with `#` are skipped.
+pub(crate) fn read_distribution_csv(path: &str) -> Result<Vec<DistributionRow>, Error> {
+    let file =
+        File::open(path).map_err(|err| format!("Unable to open CSV file {}: {}", path, err))?;
+    let mut rows = vec![];
+    for (line_index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(',').map(str::trim);
+        let recipient = fields.next().unwrap_or_default();
+        let ui_amount = fields.next().unwrap_or_default();
+        if fields.next().is_some() {
+            return Err(format!("Line {}: expected RECIPIENT,AMOUNT", line_index + 1).into());
+        }
+        let recipient = match Pubkey::from_str(recipient) {
+            Ok(recipient) => recipient,
+            Err(_) if rows.is_empty() && ui_amount.parse::<f64>().is_err() => {
+                // header
+                continue;
+            }
+            Err(err) => {
+                return Err(format!(
+                    "Line {}: invalid recipient `{}`: {}",
+                    line_index + 1,
+                    recipient,
+                    err
+                )
+                .into())
+            }
+        };
+        let ui_amount = ui_amount
+            .parse::<f64>()
+            .ok()
+            .filter(|ui_amount| *ui_amount > 0.)
+            .ok_or_else(|| format!("Line {}: invalid amount `{}`", line_index + 1, ui_amount))?;
+        rows.push(DistributionRow {
+            index: rows.len(),
+            recipient,
+            ui_amount,
+        });
+    }
+    Ok(rows)
+}
+
+/// Append-only log of the distribution rows that have already been sent, used to resume
+/// interrupted distributions.  Each line holds `INDEX,RECIPIENT,AMOUNT,SIGNATURE`.
+pub(crate) struct DistributionLog {
+    file: File,
+    completed: HashMap<usize, (Pubkey, f64)>,
+}
+
+impl DistributionLog {
+    /// Opens or creates the log at `path`, loading the rows completed by previous runs
+    pub(crate) fn open(path: &str) -> Result<Self, Error> {
+        let mut completed = HashMap::new();
+        if Path::new(path).exists() {
+            let file = File::open(path)?;
+            for (line_index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let parsed = match fields.as_slice() {
+                    [index, recipient, ui_amount, _signature] => index
+                        .parse::<usize>()
+                        .ok()
+                        .zip(Pubkey::from_str(recipient).ok())
+                        .zip(ui_amount.parse::<f64>().ok()),
+                    _ => None,
+                };
+                let ((index, recipient), ui_amount) = parsed.ok_or_else(|| {
+                    format!(
+                        "Log file {}, line {}: unreadable entry",
+                        path,
+                        line_index + 1
+                    )
+                })?;
+                completed.insert(index, (recipient, ui_amount));
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file, completed })
+    }
+
+    /// Returns whether `row` was sent by a previous run, failing if the log disagrees with
+    /// the CSV
+    pub(crate) fn is_completed(&self, row: &DistributionRow) -> Result<bool, Error> {
+        match self.completed.get(&row.index) {
+            Some((recipient, ui_amount))
+                if *recipient == row.recipient && *ui_amount == row.ui_amount =>
+            {
+                Ok(true)
+            }
+            Some(_) => Err(format!(
+                "Row {} of the CSV does not match the log file; \
+                 the CSV may have changed since the last run",
+                row.index
+            )
+            .into()),
+            None => Ok(false),
+        }
+    }
+
+    /// Records rows sent in the transaction with the given signature
+    pub(crate) fn record(
+        &mut self,
+        rows: &[DistributionRow],
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        for row in rows {
+            writeln!(
+                self.file,
+                "{},{},{},{}",
+                row.index, row.recipient, row.ui_amount, signature
+            )?;
+            self.completed
+                .insert(row.index, (row.recipient, row.ui_amount));
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
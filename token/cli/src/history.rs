@@ -0,0 +1,85 @@
+use solana_sdk::{instruction::CompiledInstruction, pubkey::Pubkey};
+use spl_token::instruction::TokenInstruction;
+
+/// A debit or credit of a token account found in a transaction
+pub(crate) struct BalanceChange {
+    /// Kind of instruction that moved the tokens
+    pub(crate) description: &'static str,
+    /// Other token account involved, if any
+    pub(crate) counterparty: Option<Pubkey>,
+    /// Change in the account balance, positive for credits
+    pub(crate) amount: i128,
+}
+
+/// Decodes the spl-token instructions of a transaction, in execution order, and collects the
+/// balance changes they made to `account`
+pub(crate) fn decode_balance_changes<'a>(
+    account: &Pubkey,
+    account_keys: &[Pubkey],
+    instructions: impl IntoIterator<Item = &'a CompiledInstruction>,
+) -> Vec<BalanceChange> {
+    let mut changes = vec![];
+    for instruction in instructions {
+        if account_keys.get(instruction.program_id_index as usize) != Some(&spl_token::id()) {
+            continue;
+        }
+        let instruction_accounts = instruction
+            .accounts
+            .iter()
+            .filter_map(|index| account_keys.get(*index as usize).copied())
+            .collect::<Vec<_>>();
+        if let Ok(token_instruction) = TokenInstruction::unpack(&instruction.data) {
+            push_balance_changes(
+                account,
+                &instruction_accounts,
+                token_instruction,
+                &mut changes,
+            );
+        }
+    }
+    changes
+}
+
+fn push_balance_changes(
+    account: &Pubkey,
+    accounts: &[Pubkey],
+    instruction: TokenInstruction,
+    changes: &mut Vec<BalanceChange>,
+) {
+    let key = |index: usize| accounts.get(index).copied();
+    let (description, source, destination, amount) = match instruction {
+        TokenInstruction::Transfer { amount } => ("transfer", key(0), key(1), amount),
+        TokenInstruction::TransferChecked { amount, .. } => ("transfer", key(0), key(2), amount),
+        TokenInstruction::MintTo { amount } | TokenInstruction::MintToChecked { amount, .. } => {
+            ("mint", None, key(1), amount)
+        }
+        TokenInstruction::Burn { amount } | TokenInstruction::BurnChecked { amount, .. } => {
+            ("burn", key(0), None, amount)
+        }
+        TokenInstruction::Batch { instructions } => {
+            let mut remaining_accounts = accounts;
+            for batched in instructions {
+                let num_accounts = (batched.num_accounts as usize).min(remaining_accounts.len());
+                let (batched_accounts, rest) = remaining_accounts.split_at(num_accounts);
+                push_balance_changes(account, batched_accounts, batched.instruction, changes);
+                remaining_accounts = rest;
+            }
+            return;
+        }
+        _ => return,
+    };
+    if source.as_ref() == Some(account) {
+        changes.push(BalanceChange {
+            description,
+            counterparty: destination,
+            amount: -(amount as i128),
+        });
+    }
+    if destination.as_ref() == Some(account) {
+        changes.push(BalanceChange {
+            description,
+            counterparty: source,
+            amount: amount as i128,
+        });
+    }
+}
@@ -3,7 +3,7 @@ use clap::{
     ArgMatches, SubCommand,
 };
 use solana_account_decoder::{
-    parse_token::{TokenAccountType, UiAccountState},
+    parse_token::{token_amount_to_ui_amount, TokenAccountType, UiAccountState},
     UiAccountData,
 };
 use solana_clap_utils::{
@@ -13,34 +13,49 @@ use solana_clap_utils::{
         is_amount, is_amount_or_all, is_parsable, is_url_or_moniker, is_valid_pubkey,
         is_valid_signer, normalize_to_url_if_moniker,
     },
-    keypair::{signer_from_path, CliSignerInfo},
+    keypair::{
+        signer_from_path, signer_from_path_with_config, CliSignerInfo, SignerFromPathConfig,
+    },
     nonce::*,
     offline::{self, *},
     ArgConstant,
 };
 use solana_cli_output::{return_signers, CliSignature, OutputFormat};
 use solana_client::{
-    blockhash_query::BlockhashQuery, rpc_client::RpcClient, rpc_request::TokenAccountsFilter,
+    blockhash_query::BlockhashQuery,
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_request::{TokenAccountsFilter, MAX_MULTIPLE_ACCOUNTS},
 };
 use solana_remote_wallet::remote_wallet::RemoteWalletManager;
 use solana_sdk::{
+    account::from_account,
+    clock::Clock,
     commitment_config::CommitmentConfig,
-    instruction::Instruction,
+    instruction::{AccountMeta, CompiledInstruction, Instruction},
     message::Message,
     native_token::*,
+    packet::PACKET_DATA_SIZE,
     program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    system_instruction, system_program,
+    rent::Rent,
+    signature::{Keypair, Signature, Signer},
+    system_instruction, system_program, sysvar,
     transaction::Transaction,
 };
+use solana_transaction_status::{UiInstruction, UiTransactionEncoding, UiTransactionTokenBalance};
 use spl_associated_token_account::*;
 use spl_token::{
     self,
+    extension::{
+        interest_bearing_mint::InterestBearingConfig,
+        permanent_delegate::PermanentDelegate,
+        transfer_fee::{TransferFeeConfig, MAX_FEE_BASIS_POINTS},
+        ExtensionType, StateWithExtensions,
+    },
     instruction::*,
     native_mint,
-    state::{Account, Mint, Multisig},
+    state::{Account, AccountState, Mint, Multisig},
 };
 use std::{collections::HashMap, process::exit, str::FromStr, sync::Arc};
 
@@ -53,6 +68,15 @@ use output::*;
 mod sort;
 use sort::sort_and_parse_token_accounts;
 
+mod distribute;
+use distribute::{read_distribution_csv, DistributionLog};
+
+mod history;
+use history::{decode_balance_changes, BalanceChange};
+
+mod transaction_file;
+use transaction_file::{absent_signers, merge_signatures, read_transactions, write_transactions};
+
 pub const OWNER_ADDRESS_ARG: ArgConstant<'static> = ArgConstant {
     name: "owner",
     long: "owner",
@@ -171,6 +195,15 @@ pub fn multisig_signer_arg<'a, 'b>() -> Arg<'a, 'b> {
         .help(MULTISIG_SIGNER_ARG.help)
 }
 
+fn is_transfer_fee_basis_points(string: String) -> Result<(), String> {
+    let v = u16::from_str(&string).map_err(|e| e.to_string())?;
+    if v > MAX_FEE_BASIS_POINTS {
+        Err(format!("must be at most {}", MAX_FEE_BASIS_POINTS))
+    } else {
+        Ok(())
+    }
+}
+
 fn is_multisig_minimum_signers(string: String) -> Result<(), String> {
     let v = u8::from_str(&string).map_err(|e| e.to_string())? as usize;
     if v < MIN_SIGNERS {
@@ -222,6 +255,18 @@ fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(),
     }
 }
 
+// Offline signers have no cluster to ask, so they assume the default rent parameters; every
+// signer must arrive at the same lamports for the transaction message to match
+fn get_minimum_balance_for_rent_exemption(config: &Config, data_len: usize) -> Result<u64, Error> {
+    if config.sign_only {
+        Ok(Rent::default().minimum_balance(data_len))
+    } else {
+        Ok(config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(data_len)?)
+    }
+}
+
 fn check_wallet_balance(
     config: &Config,
     wallet: &Pubkey,
@@ -246,12 +291,14 @@ pub fn signers_of(
     matches: &ArgMatches<'_>,
     name: &str,
     wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+    config: &SignerFromPathConfig,
 ) -> Result<Option<SignersOf>, Box<dyn std::error::Error>> {
     if let Some(values) = matches.values_of(name) {
         let mut results = Vec::new();
         for (i, value) in values.enumerate() {
             let name = format!("{}-{}", name, i + 1);
-            let signer = signer_from_path(matches, value, &name, wallet_manager)?;
+            let signer =
+                signer_from_path_with_config(matches, value, &name, wallet_manager, config)?;
             let signer_pubkey = signer.pubkey();
             results.push((signer, signer_pubkey));
         }
@@ -261,41 +308,104 @@ pub fn signers_of(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn command_create_token(
     config: &Config,
     decimals: u8,
     token: Pubkey,
     authority: Pubkey,
     enable_freeze: bool,
+    transfer_fee: Option<(u16, f64)>,
+    interest_rate: Option<i16>,
+    non_transferable: bool,
+    permanent_delegate: Option<Pubkey>,
+    default_frozen: bool,
     memo: Option<String>,
 ) -> CommandResult {
     println_display(config, format!("Creating token {}", token));
 
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Mint::LEN)?
-    } else {
-        0
-    };
+    let mut extension_types = vec![];
+    if transfer_fee.is_some() {
+        extension_types.push(ExtensionType::TransferFeeConfig);
+    }
+    if interest_rate.is_some() {
+        extension_types.push(ExtensionType::InterestBearingConfig);
+    }
+    if non_transferable {
+        extension_types.push(ExtensionType::NonTransferable);
+    }
+    if permanent_delegate.is_some() {
+        extension_types.push(ExtensionType::PermanentDelegate);
+    }
+    if default_frozen {
+        extension_types.push(ExtensionType::DefaultAccountState);
+    }
+    let mint_len = ExtensionType::get_account_len::<Mint>(&extension_types);
+
+    let minimum_balance_for_rent_exemption =
+        get_minimum_balance_for_rent_exemption(config, mint_len)?;
     let freeze_authority_pubkey = if enable_freeze { Some(authority) } else { None };
 
-    let mut instructions = vec![
-        system_instruction::create_account(
-            &config.fee_payer,
+    let mut instructions = vec![system_instruction::create_account(
+        &config.fee_payer,
+        &token,
+        minimum_balance_for_rent_exemption,
+        mint_len as u64,
+        &spl_token::id(),
+    )];
+    if let Some((transfer_fee_basis_points, ui_maximum_fee)) = transfer_fee {
+        println_display(
+            config,
+            format!(
+                "  Transfer fee: {} basis points, maximum {}",
+                transfer_fee_basis_points, ui_maximum_fee
+            ),
+        );
+        instructions.push(initialize_transfer_fee_config(
+            &spl_token::id(),
             &token,
-            minimum_balance_for_rent_exemption,
-            Mint::LEN as u64,
+            Some(&authority),
+            Some(&authority),
+            transfer_fee_basis_points,
+            spl_token::ui_amount_to_amount(ui_maximum_fee, decimals),
+        )?);
+    }
+    if let Some(rate) = interest_rate {
+        println_display(config, format!("  Interest rate: {} basis points", rate));
+        instructions.push(initialize_interest_bearing_mint(
             &spl_token::id(),
-        ),
-        initialize_mint(
+            &token,
+            Some(&authority),
+            rate,
+        )?);
+    }
+    if non_transferable {
+        println_display(config, "  Non-transferable".to_string());
+        instructions.push(initialize_non_transferable_mint(&spl_token::id(), &token)?);
+    }
+    if let Some(delegate) = permanent_delegate {
+        println_display(config, format!("  Permanent delegate: {}", delegate));
+        instructions.push(initialize_permanent_delegate(
             &spl_token::id(),
             &token,
-            &authority,
-            freeze_authority_pubkey.as_ref(),
-            decimals,
-        )?,
-    ];
+            &delegate,
+        )?);
+    }
+    if default_frozen {
+        println_display(config, "  New accounts start frozen".to_string());
+        instructions.push(initialize_default_account_state(
+            &spl_token::id(),
+            &token,
+            &AccountState::Frozen,
+        )?);
+    }
+    instructions.push(initialize_mint(
+        &spl_token::id(),
+        &token,
+        &authority,
+        freeze_authority_pubkey.as_ref(),
+        decimals,
+    )?);
     if let Some(text) = memo {
         instructions.push(spl_memo::build_memo(text.as_bytes(), &[&config.fee_payer]));
     }
@@ -311,13 +421,9 @@ fn command_create_account(
     owner: Pubkey,
     maybe_account: Option<Pubkey>,
 ) -> CommandResult {
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Account::LEN)?
-    } else {
-        0
-    };
+    let account_len = get_account_len_for_mint(config, &token)?;
+    let minimum_balance_for_rent_exemption =
+        get_minimum_balance_for_rent_exemption(config, account_len)?;
 
     let (account, system_account_ok, instructions) = if let Some(account) = maybe_account {
         println_display(config, format!("Creating account {}", account));
@@ -329,7 +435,7 @@ fn command_create_account(
                     &config.fee_payer,
                     &account,
                     minimum_balance_for_rent_exemption,
-                    Account::LEN as u64,
+                    account_len as u64,
                     &spl_token::id(),
                 ),
                 initialize_account(&spl_token::id(), &account, &token, &owner)?,
@@ -383,13 +489,8 @@ fn command_create_multisig(
         ),
     );
 
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Multisig::LEN)?
-    } else {
-        0
-    };
+    let minimum_balance_for_rent_exemption =
+        get_minimum_balance_for_rent_exemption(config, Multisig::LEN)?;
 
     let instructions = vec![
         system_instruction::create_account(
@@ -425,19 +526,61 @@ fn command_authorize(
         AuthorityType::FreezeAccount => "freeze authority",
         AuthorityType::AccountOwner => "owner",
         AuthorityType::CloseAccount => "close authority",
+        AuthorityType::TransferFeeConfig => "transfer fee authority",
+        AuthorityType::WithheldWithdraw => "withdraw withheld authority",
+        AuthorityType::InterestRate => "rate authority",
+        AuthorityType::PermanentDelegate => "permanent delegate",
     };
     let previous_authority = if !config.sign_only {
         let target_account = config.rpc_client.get_account(&account)?;
-        if let Ok(mint) = Mint::unpack(&target_account.data) {
+        if let Ok(mint) = StateWithExtensions::<Mint>::unpack(&target_account.data) {
             match authority_type {
                 AuthorityType::AccountOwner | AuthorityType::CloseAccount => Err(format!(
                     "Authority type `{}` not supported for SPL Token mints",
                     auth_str
                 )),
-                AuthorityType::MintTokens => Ok(mint.mint_authority),
-                AuthorityType::FreezeAccount => Ok(mint.freeze_authority),
+                AuthorityType::MintTokens => Ok(mint.base.mint_authority),
+                AuthorityType::FreezeAccount => Ok(mint.base.freeze_authority),
+                AuthorityType::TransferFeeConfig | AuthorityType::WithheldWithdraw => {
+                    if let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() {
+                        if authority_type == AuthorityType::TransferFeeConfig {
+                            Ok(transfer_fee_config.transfer_fee_config_authority)
+                        } else {
+                            Ok(transfer_fee_config.withdraw_withheld_authority)
+                        }
+                    } else {
+                        Err(format!(
+                            "Mint `{}` does not support the `{}`",
+                            account, auth_str
+                        ))
+                    }
+                }
+                AuthorityType::InterestRate => {
+                    if let Ok(interest_bearing_config) =
+                        mint.get_extension::<InterestBearingConfig>()
+                    {
+                        Ok(interest_bearing_config.rate_authority)
+                    } else {
+                        Err(format!(
+                            "Mint `{}` does not support the `{}`",
+                            account, auth_str
+                        ))
+                    }
+                }
+                AuthorityType::PermanentDelegate => {
+                    if let Ok(permanent_delegate) = mint.get_extension::<PermanentDelegate>() {
+                        Ok(permanent_delegate.delegate)
+                    } else {
+                        Err(format!(
+                            "Mint `{}` does not support the `{}`",
+                            account, auth_str
+                        ))
+                    }
+                }
             }
-        } else if let Ok(token_account) = Account::unpack(&target_account.data) {
+        } else if let Ok(token_account) =
+            StateWithExtensions::<Account>::unpack(&target_account.data).map(|a| a.base)
+        {
             let check_associated_token_account = || -> Result<(), Error> {
                 let maybe_associated_token_account =
                     get_associated_token_address(&token_account.owner, &token_account.mint);
@@ -456,7 +599,12 @@ fn command_authorize(
             };
 
             match authority_type {
-                AuthorityType::MintTokens | AuthorityType::FreezeAccount => Err(format!(
+                AuthorityType::MintTokens
+                | AuthorityType::FreezeAccount
+                | AuthorityType::TransferFeeConfig
+                | AuthorityType::WithheldWithdraw
+                | AuthorityType::InterestRate
+                | AuthorityType::PermanentDelegate => Err(format!(
                     "Authority type `{}` not supported for SPL Token accounts",
                     auth_str
                 )),
@@ -511,11 +659,8 @@ fn resolve_mint_info(
     mint_decimals: Option<u8>,
 ) -> Result<(Pubkey, u8), Error> {
     if !config.sign_only {
-        let source_account = config
-            .rpc_client
-            .get_token_account(token_account)?
-            .ok_or_else(|| format!("Could not find token account {}", token_account))?;
-        let source_mint = Pubkey::from_str(&source_account.mint)?;
+        let source_account = get_token_account(config, token_account)?;
+        let source_mint = source_account.mint;
         if let Some(mint) = mint_address {
             if source_mint != mint {
                 return Err(format!(
@@ -525,7 +670,8 @@ fn resolve_mint_info(
                 .into());
             }
         }
-        Ok((source_mint, source_account.token_amount.decimals))
+        let mint = get_mint(config, &source_mint)?;
+        Ok((source_mint, mint.decimals))
     } else {
         Ok((
             mint_address.unwrap_or_default(),
@@ -535,11 +681,43 @@ fn resolve_mint_info(
 }
 
 fn validate_mint(config: &Config, token: Pubkey) -> Result<(), Error> {
-    let mint = config.rpc_client.get_account(&token);
-    if mint.is_err() || Mint::unpack(&mint.unwrap().data).is_err() {
-        return Err(format!("Invalid mint account {:?}", token).into());
+    get_mint(config, &token)
+        .map(|_| ())
+        .map_err(|_| format!("Invalid mint account {:?}", token).into())
+}
+
+// The RPC token parsers only understand the original account layouts, so accounts that may
+// carry extensions are decoded locally
+fn get_mint(config: &Config, token: &Pubkey) -> Result<Mint, Error> {
+    let mint_account = config.rpc_client.get_account(token)?;
+    Ok(StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .map_err(|_| format!("Invalid mint account {:?}", token))?
+        .base)
+}
+
+fn get_token_account(config: &Config, address: &Pubkey) -> Result<Account, Error> {
+    let account = config
+        .rpc_client
+        .get_account(address)
+        .map_err(|_| format!("Could not find token account {}", address))?;
+    Ok(StateWithExtensions::<Account>::unpack(&account.data)
+        .map_err(|_| format!("Invalid token account {}", address))?
+        .base)
+}
+
+// Size of a new token account, including any extensions required by its mint
+fn get_account_len_for_mint(config: &Config, token: &Pubkey) -> Result<usize, Error> {
+    if config.sign_only {
+        return Ok(Account::LEN);
     }
-    Ok(())
+    let mint_account = config.rpc_client.get_account(token)?;
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .map_err(|_| format!("Invalid mint account {:?}", token))?;
+    let account_extension_types =
+        ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+    Ok(ExtensionType::get_account_len::<Account>(
+        &account_extension_types,
+    ))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -554,6 +732,7 @@ fn command_transfer(
     fund_recipient: bool,
     mint_decimals: Option<u8>,
     recipient_is_ata_owner: bool,
+    memo: Option<String>,
 ) -> CommandResult {
     let sender = if let Some(sender) = sender {
         sender
@@ -564,21 +743,14 @@ fn command_transfer(
     let maybe_transfer_balance =
         ui_amount.map(|ui_amount| spl_token::ui_amount_to_amount(ui_amount, decimals));
     let transfer_balance = if !config.sign_only {
-        let sender_token_amount = config
-            .rpc_client
-            .get_token_account_balance(&sender)
+        let sender_balance = get_token_account(config, &sender)
             .map_err(|err| {
                 format!(
                     "Error: Failed to get token balance of sender address {}: {}",
                     sender, err
                 )
-            })?;
-        let sender_balance = sender_token_amount.amount.parse::<u64>().map_err(|err| {
-            format!(
-                "Token account {} balance could not be parsed: {}",
-                sender, err
-            )
-        })?;
+            })?
+            .amount;
 
         let transfer_balance = maybe_transfer_balance.unwrap_or(sender_balance);
         println_display(
@@ -594,7 +766,7 @@ fn command_transfer(
         if transfer_balance > sender_balance {
             return Err(format!(
                 "Error: Sender has insufficient funds, current balance is {}",
-                sender_token_amount.real_number_string_trimmed()
+                spl_token::amount_to_ui_amount(sender_balance, decimals)
             )
             .into());
         }
@@ -613,7 +785,10 @@ fn command_transfer(
             .rpc_client
             .get_account_with_commitment(&recipient, config.rpc_client.commitment())?
             .value
-            .map(|account| account.owner == spl_token::id() && account.data.len() == Account::LEN);
+            .map(|account| {
+                account.owner == spl_token::id()
+                    && StateWithExtensions::<Account>::unpack(&account.data).is_ok()
+            });
 
         if recipient_account_info.is_none() && !allow_unfunded_recipient {
             return Err("Error: The recipient address is not funded. \
@@ -665,9 +840,10 @@ fn command_transfer(
         if needs_funding {
             if fund_recipient {
                 if !config.sign_only {
-                    minimum_balance_for_rent_exemption += config
-                        .rpc_client
-                        .get_minimum_balance_for_rent_exemption(Account::LEN)?;
+                    minimum_balance_for_rent_exemption +=
+                        config.rpc_client.get_minimum_balance_for_rent_exemption(
+                            get_account_len_for_mint(config, &mint_pubkey)?,
+                        )?;
                     println_display(
                         config,
                         format!(
@@ -692,7 +868,7 @@ fn command_transfer(
         }
     }
 
-    instructions.push(transfer_checked(
+    let mut transfer_instruction = transfer_checked(
         &spl_token::id(),
         &sender,
         &mint_pubkey,
@@ -701,13 +877,227 @@ fn command_transfer(
         &config.multisigner_pubkeys,
         transfer_balance,
         decimals,
-    )?);
+    )?;
+    if let Some(text) = memo {
+        // recipients that require memos check the preceding instruction through the
+        // instructions sysvar
+        instructions.push(spl_memo::build_memo(text.as_bytes(), &[&config.fee_payer]));
+        transfer_instruction
+            .accounts
+            .push(AccountMeta::new_readonly(sysvar::instructions::id(), false));
+    }
+    instructions.push(transfer_instruction);
     Ok(Some((
         minimum_balance_for_rent_exemption,
         vec![instructions],
     )))
 }
 
+#[allow(clippy::too_many_arguments)]
+fn command_distribute(
+    config: &Config,
+    token: Pubkey,
+    csv_path: &str,
+    state_path: &str,
+    sender: Option<Pubkey>,
+    sender_owner: Pubkey,
+    fund_recipient: bool,
+    mint_decimals: Option<u8>,
+    bulk_signers: &[Box<dyn Signer>],
+) -> CommandResult {
+    let sender = sender.unwrap_or_else(|| get_associated_token_address(&sender_owner, &token));
+    let (mint_pubkey, decimals) = resolve_mint_info(config, &sender, Some(token), mint_decimals)?;
+
+    let rows = read_distribution_csv(csv_path)?;
+    let mut log = DistributionLog::open(state_path)?;
+    let mut pending = vec![];
+    for row in rows.iter() {
+        if !log.is_completed(row)? {
+            pending.push(row.clone());
+        }
+    }
+    println_display(
+        config,
+        format!(
+            "Distribute {}\n  Sender: {}\n  Rows: {} ({} already sent)",
+            token,
+            sender,
+            rows.len(),
+            rows.len() - pending.len()
+        ),
+    );
+    if pending.is_empty() {
+        return Ok(None);
+    }
+
+    let total_amount = pending.iter().try_fold(0u64, |total, row| {
+        total.checked_add(spl_token::ui_amount_to_amount(row.ui_amount, decimals))
+    });
+    let sender_balance = get_token_account(config, &sender)?.amount;
+    if total_amount.map_or(true, |total_amount| total_amount > sender_balance) {
+        return Err(format!(
+            "Error: Sender has insufficient funds, current balance is {}",
+            spl_token::amount_to_ui_amount(sender_balance, decimals)
+        )
+        .into());
+    }
+
+    // Resolve every recipient to a token account before sending anything, so that a bad row
+    // fails the whole run rather than leaving it half done
+    let mut recipient_token_accounts = HashMap::new();
+    let mut wallets = vec![];
+    let recipients: Vec<Pubkey> = pending.iter().map(|row| row.recipient).collect();
+    for (recipient, account) in recipients
+        .iter()
+        .zip(get_multiple_accounts(config, &recipients)?)
+    {
+        let is_token_account = account.map_or(false, |account| {
+            account.owner == spl_token::id()
+                && StateWithExtensions::<Account>::unpack(&account.data).is_ok()
+        });
+        if is_token_account {
+            recipient_token_accounts.insert(*recipient, (*recipient, false));
+        } else {
+            wallets.push(*recipient);
+        }
+    }
+    let associated_token_accounts: Vec<Pubkey> = wallets
+        .iter()
+        .map(|wallet| get_associated_token_address(wallet, &mint_pubkey))
+        .collect();
+    for ((wallet, address), account) in wallets
+        .iter()
+        .zip(associated_token_accounts.iter())
+        .zip(get_multiple_accounts(config, &associated_token_accounts)?)
+    {
+        let needs_funding = match account {
+            Some(account) if account.owner == spl_token::id() => false,
+            Some(account) if account.owner != system_program::id() => {
+                return Err(format!("Error: Unsupported recipient address: {}", wallet).into());
+            }
+            _ => true,
+        };
+        if needs_funding && !fund_recipient {
+            return Err(format!(
+                "Error: Associated token account of recipient {} does not exist. \
+                 Add `--fund-recipient` to fund their account",
+                wallet
+            )
+            .into());
+        }
+        recipient_token_accounts.insert(*wallet, (*address, needs_funding));
+    }
+    let account_rent = if recipient_token_accounts
+        .values()
+        .any(|(_, needs_funding)| *needs_funding)
+    {
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(get_account_len_for_mint(
+                config,
+                &mint_pubkey,
+            )?)?
+    } else {
+        0
+    };
+
+    let mut rows = pending.into_iter().peekable();
+    while rows.peek().is_some() {
+        // Pack as many rows as fit into a single transaction
+        let mut batch_rows = vec![];
+        let mut instructions = vec![];
+        let mut minimum_balance_for_rent_exemption = 0;
+        while let Some(row) = rows.peek() {
+            let (recipient_token_account, needs_funding) = recipient_token_accounts[&row.recipient];
+            let mut row_instructions = vec![];
+            if needs_funding {
+                row_instructions.push(create_associated_token_account(
+                    &config.fee_payer,
+                    &row.recipient,
+                    &mint_pubkey,
+                ));
+            }
+            row_instructions.push(transfer_checked(
+                &spl_token::id(),
+                &sender,
+                &mint_pubkey,
+                &recipient_token_account,
+                &sender_owner,
+                &config.multisigner_pubkeys,
+                spl_token::ui_amount_to_amount(row.ui_amount, decimals),
+                decimals,
+            )?);
+            let candidate = [instructions.as_slice(), row_instructions.as_slice()].concat();
+            let message = Message::new(&candidate, Some(&config.fee_payer));
+            let transaction_len = 1
+                + message.header.num_required_signatures as usize * 64
+                + message.serialize().len();
+            if transaction_len > PACKET_DATA_SIZE {
+                if batch_rows.is_empty() {
+                    return Err(format!(
+                        "Error: Transfer to {} does not fit in a transaction",
+                        row.recipient
+                    )
+                    .into());
+                }
+                break;
+            }
+            instructions = candidate;
+            if needs_funding {
+                minimum_balance_for_rent_exemption += account_rent;
+                // later rows to the same recipient find the account already created
+                recipient_token_accounts.insert(row.recipient, (recipient_token_account, false));
+            }
+            batch_rows.push(rows.next().unwrap());
+        }
+
+        let message = Message::new(&instructions, Some(&config.fee_payer));
+        let (recent_blockhash, fee_calculator, _) = config
+            .rpc_client
+            .get_recent_blockhash_with_commitment(config.rpc_client.commitment())?
+            .value;
+        check_fee_payer_balance(
+            config,
+            minimum_balance_for_rent_exemption + fee_calculator.calculate_fee(&message),
+        )?;
+        let signer_keys = &message.account_keys[..message.header.num_required_signatures as usize];
+        let mut signers: Vec<&dyn Signer> = vec![];
+        for signer in bulk_signers {
+            let pubkey = signer.pubkey();
+            if signer_keys.contains(&pubkey) && !signers.iter().any(|s| s.pubkey() == pubkey) {
+                signers.push(signer.as_ref());
+            }
+        }
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.try_sign(&signers, recent_blockhash)?;
+        let signature = config
+            .rpc_client
+            .send_and_confirm_transaction_with_spinner(&transaction)?;
+        log.record(&batch_rows, &signature)?;
+        println_display(
+            config,
+            format!(
+                "  Sent rows {}-{}: {}",
+                batch_rows[0].index,
+                batch_rows[batch_rows.len() - 1].index,
+                signature
+            ),
+        );
+    }
+    Ok(None)
+}
+
+fn get_multiple_accounts(
+    config: &Config,
+    addresses: &[Pubkey],
+) -> Result<Vec<Option<solana_sdk::account::Account>>, Error> {
+    let mut accounts = vec![];
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(config.rpc_client.get_multiple_accounts(chunk)?);
+    }
+    Ok(accounts)
+}
+
 fn command_burn(
     config: &Config,
     source: Pubkey,
@@ -767,6 +1157,125 @@ fn command_mint(
     Ok(Some((0, vec![instructions])))
 }
 
+fn command_set_transfer_fee(
+    config: &Config,
+    token: Pubkey,
+    transfer_fee_basis_points: u16,
+    ui_maximum_fee: f64,
+    mint_decimals: Option<u8>,
+    transfer_fee_authority: Pubkey,
+) -> CommandResult {
+    let decimals = if !config.sign_only {
+        get_mint(config, &token)?.decimals
+    } else {
+        mint_decimals.unwrap_or_default()
+    };
+    println_display(
+        config,
+        format!(
+            "Setting transfer fee for {} to {} basis points, maximum {}",
+            token, transfer_fee_basis_points, ui_maximum_fee
+        ),
+    );
+
+    let instructions = vec![set_transfer_fee(
+        &spl_token::id(),
+        &token,
+        &transfer_fee_authority,
+        &config.multisigner_pubkeys,
+        transfer_fee_basis_points,
+        spl_token::ui_amount_to_amount(ui_maximum_fee, decimals),
+    )?];
+    Ok(Some((0, vec![instructions])))
+}
+
+fn command_set_interest_rate(
+    config: &Config,
+    token: Pubkey,
+    rate: i16,
+    rate_authority: Pubkey,
+) -> CommandResult {
+    println_display(
+        config,
+        format!(
+            "Setting interest rate for {} to {} basis points",
+            token, rate
+        ),
+    );
+
+    let instructions = vec![update_interest_rate(
+        &spl_token::id(),
+        &token,
+        &rate_authority,
+        &config.multisigner_pubkeys,
+        rate,
+    )?];
+    Ok(Some((0, vec![instructions])))
+}
+
+fn command_withdraw_withheld_tokens(
+    config: &Config,
+    token: Pubkey,
+    destination: Pubkey,
+    sources: Vec<Pubkey>,
+    include_mint: bool,
+    withdraw_withheld_authority: Pubkey,
+) -> CommandResult {
+    println_display(
+        config,
+        format!(
+            "Withdrawing withheld tokens from {} accounts{}\n  Destination: {}",
+            sources.len(),
+            if include_mint { " and the mint" } else { "" },
+            destination
+        ),
+    );
+
+    let mut instructions = vec![];
+    if include_mint {
+        instructions.push(withdraw_withheld_tokens_from_mint(
+            &spl_token::id(),
+            &token,
+            &destination,
+            &withdraw_withheld_authority,
+            &config.multisigner_pubkeys,
+        )?);
+    }
+    if !sources.is_empty() {
+        instructions.push(withdraw_withheld_tokens_from_accounts(
+            &spl_token::id(),
+            &token,
+            &destination,
+            &withdraw_withheld_authority,
+            &config.multisigner_pubkeys,
+            sources.iter().collect::<Vec<_>>().as_slice(),
+        )?);
+    }
+    Ok(Some((0, vec![instructions])))
+}
+
+fn command_harvest_withheld_tokens(
+    config: &Config,
+    token: Pubkey,
+    sources: Vec<Pubkey>,
+) -> CommandResult {
+    println_display(
+        config,
+        format!(
+            "Harvesting withheld tokens from {} accounts\n  Token: {}",
+            sources.len(),
+            token
+        ),
+    );
+
+    let instructions = vec![harvest_withheld_tokens_to_mint(
+        &spl_token::id(),
+        &token,
+        sources.iter().collect::<Vec<_>>().as_slice(),
+    )?];
+    Ok(Some((0, vec![instructions])))
+}
+
 fn command_freeze(
     config: &Config,
     account: Pubkey,
@@ -936,25 +1445,69 @@ fn command_approve(
     Ok(Some((0, vec![instructions])))
 }
 
-fn command_revoke(
+fn command_required_transfer_memos(
     config: &Config,
-    account: Pubkey,
+    token_account: Pubkey,
     owner: Pubkey,
-    delegate: Option<Pubkey>,
+    enable: bool,
 ) -> CommandResult {
-    let delegate = if !config.sign_only {
-        let source_account = config
-            .rpc_client
-            .get_token_account(&account)?
-            .ok_or_else(|| format!("Could not find token account {}", account))?;
+    println_display(
+        config,
+        format!(
+            "{} required transfer memos for {}",
+            if enable { "Enabling" } else { "Disabling" },
+            token_account
+        ),
+    );
 
-        if let Some(string) = source_account.delegate {
-            Some(Pubkey::from_str(&string)?)
-        } else {
-            None
-        }
-    } else {
-        delegate
+    let instructions = if enable {
+        // legacy accounts are first grown to fit the extension, a no-op for accounts with room
+        vec![
+            reallocate(
+                &spl_token::id(),
+                &token_account,
+                &config.fee_payer,
+                &owner,
+                &config.multisigner_pubkeys,
+                &[ExtensionType::MemoTransfer],
+            )?,
+            enable_required_transfer_memos(
+                &spl_token::id(),
+                &token_account,
+                &owner,
+                &config.multisigner_pubkeys,
+            )?,
+        ]
+    } else {
+        vec![disable_required_transfer_memos(
+            &spl_token::id(),
+            &token_account,
+            &owner,
+            &config.multisigner_pubkeys,
+        )?]
+    };
+    Ok(Some((0, vec![instructions])))
+}
+
+fn command_revoke(
+    config: &Config,
+    account: Pubkey,
+    owner: Pubkey,
+    delegate: Option<Pubkey>,
+) -> CommandResult {
+    let delegate = if !config.sign_only {
+        let source_account = config
+            .rpc_client
+            .get_token_account(&account)?
+            .ok_or_else(|| format!("Could not find token account {}", account))?;
+
+        if let Some(string) = source_account.delegate {
+            Some(Pubkey::from_str(&string)?)
+        } else {
+            None
+        }
+    } else {
+        delegate
     };
 
     if let Some(delegate) = delegate {
@@ -1020,12 +1573,47 @@ fn command_close(
     Ok(Some((0, vec![instructions])))
 }
 
+// Builds the displayed amount, including the interest accrued so far for interest-bearing mints
+fn get_cli_token_amount(
+    config: &Config,
+    amount: u64,
+    mint: &StateWithExtensions<Mint>,
+) -> Result<CliTokenAmount, Error> {
+    let decimals = mint.base.decimals;
+    let ui_amount_with_interest =
+        if let Ok(interest_bearing_config) = mint.get_extension::<InterestBearingConfig>() {
+            let clock_account = config.rpc_client.get_account(&sysvar::clock::id())?;
+            let clock: Clock =
+                from_account(&clock_account).ok_or("Failed to deserialize the cluster clock")?;
+            let ui_amount = interest_bearing_config
+                .amount_to_ui_amount(amount, decimals, clock.unix_timestamp)
+                .ok_or("Failed to calculate the accrued interest")?;
+            let ui_amount = format!("{:.*}", decimals as usize, ui_amount);
+            if ui_amount.contains('.') {
+                Some(
+                    ui_amount
+                        .trim_end_matches('0')
+                        .trim_end_matches('.')
+                        .to_string(),
+                )
+            } else {
+                Some(ui_amount)
+            }
+        } else {
+            None
+        };
+    Ok(CliTokenAmount {
+        amount: token_amount_to_ui_amount(amount, decimals),
+        ui_amount_with_interest,
+    })
+}
+
 fn command_balance(config: &Config, address: Pubkey) -> CommandResult {
-    let balance = config
-        .rpc_client
-        .get_token_account_balance(&address)
-        .map_err(|_| format!("Could not find token account {}", address))?;
-    let cli_token_amount = CliTokenAmount { amount: balance };
+    let account = get_token_account(config, &address)?;
+    let mint_account = config.rpc_client.get_account(&account.mint)?;
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .map_err(|_| format!("Invalid mint account {:?}", account.mint))?;
+    let cli_token_amount = get_cli_token_amount(config, account.amount, &mint)?;
     println!(
         "{}",
         config.output_format.formatted_string(&cli_token_amount)
@@ -1035,8 +1623,10 @@ fn command_balance(config: &Config, address: Pubkey) -> CommandResult {
 }
 
 fn command_supply(config: &Config, address: Pubkey) -> CommandResult {
-    let supply = config.rpc_client.get_token_supply(&address)?;
-    let cli_token_amount = CliTokenAmount { amount: supply };
+    let mint_account = config.rpc_client.get_account(&address)?;
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .map_err(|_| format!("Invalid mint account {:?}", address))?;
+    let cli_token_amount = get_cli_token_amount(config, mint.base.supply, &mint)?;
     println!(
         "{}",
         config.output_format.formatted_string(&cli_token_amount)
@@ -1157,13 +1747,8 @@ fn command_gc(config: &Config, owner: Pubkey) -> CommandResult {
         return Ok(None);
     }
 
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Account::LEN)?
-    } else {
-        0
-    };
+    let minimum_balance_for_rent_exemption =
+        get_minimum_balance_for_rent_exemption(config, Account::LEN)?;
 
     let mut accounts_by_token = HashMap::new();
 
@@ -1278,6 +1863,321 @@ fn command_sync_native(native_account_address: Pubkey) -> CommandResult {
     Ok(Some((0, vec![instructions])))
 }
 
+fn format_ui_amount(amount: i128, decimals: u8) -> String {
+    let ui_amount = token_amount_to_ui_amount(amount.unsigned_abs() as u64, decimals)
+        .real_number_string_trimmed();
+    if amount < 0 {
+        format!("-{}", ui_amount)
+    } else {
+        ui_amount
+    }
+}
+
+fn get_token_account_history(
+    config: &Config,
+    address: &Pubkey,
+    token_account: &Account,
+    decimals: u8,
+    limit: Option<usize>,
+) -> Result<CliAccountHistory, Error> {
+    const MAX_SIGNATURES_PER_PAGE: usize = 1000;
+
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page_limit = limit.map_or(MAX_SIGNATURES_PER_PAGE, |limit| {
+            (limit - signatures.len()).min(MAX_SIGNATURES_PER_PAGE)
+        });
+        let page = config.rpc_client.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(page_limit),
+                commitment: Some(config.rpc_client.commitment()),
+            },
+        )?;
+        let page_len = page.len();
+        for status in page {
+            let signature = status.signature.parse::<Signature>()?;
+            before = Some(signature);
+            if status.err.is_none() {
+                signatures.push(signature);
+            }
+        }
+        if page_len < page_limit || limit.map_or(false, |limit| signatures.len() >= limit) {
+            break;
+        }
+    }
+
+    // Signatures come newest first, so walk back from the current balance
+    let mut balance = token_account.amount as i128;
+    let mut transaction_entries = vec![];
+    for signature in signatures {
+        let confirmed_transaction = config
+            .rpc_client
+            .get_transaction(&signature, UiTransactionEncoding::Base64)?;
+        let transaction = confirmed_transaction
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| format!("Unable to decode transaction {}", signature))?;
+        let meta = confirmed_transaction.transaction.meta;
+        let account_keys = &transaction.message.account_keys;
+
+        // Instructions invoked by other programs are reported separately from the top-level
+        // instruction that invoked them
+        let mut inner_instructions: HashMap<u8, Vec<CompiledInstruction>> = HashMap::new();
+        if let Some(inner) = meta
+            .as_ref()
+            .and_then(|meta| meta.inner_instructions.as_ref())
+        {
+            for inner in inner {
+                let instructions = inner.instructions.iter().filter_map(|instruction| {
+                    if let UiInstruction::Compiled(instruction) = instruction {
+                        Some(CompiledInstruction {
+                            program_id_index: instruction.program_id_index,
+                            accounts: instruction.accounts.clone(),
+                            data: bs58::decode(&instruction.data).into_vec().ok()?,
+                        })
+                    } else {
+                        None
+                    }
+                });
+                inner_instructions
+                    .entry(inner.index)
+                    .or_default()
+                    .extend(instructions);
+            }
+        }
+        let instructions = transaction
+            .message
+            .instructions
+            .iter()
+            .enumerate()
+            .flat_map(|(index, instruction)| {
+                std::iter::once(instruction)
+                    .chain(inner_instructions.get(&(index as u8)).into_iter().flatten())
+            });
+        let mut changes = decode_balance_changes(address, account_keys, instructions);
+
+        // Transfer fees, withdrawn fees and synced native balances aren't visible in the
+        // instruction data; the cluster's record of the balances accounts for them
+        let account_index = account_keys.iter().position(|key| key == address);
+        let token_balance = |balances: Option<&Vec<UiTransactionTokenBalance>>| {
+            balances?
+                .iter()
+                .find(|balance| Some(balance.account_index as usize) == account_index)
+                .and_then(|balance| balance.ui_token_amount.amount.parse::<i128>().ok())
+        };
+        let pre_balance = token_balance(
+            meta.as_ref()
+                .and_then(|meta| meta.pre_token_balances.as_ref()),
+        );
+        let post_balance = token_balance(
+            meta.as_ref()
+                .and_then(|meta| meta.post_token_balances.as_ref()),
+        );
+        if pre_balance.is_some() || post_balance.is_some() {
+            let actual_change = post_balance.unwrap_or(0) - pre_balance.unwrap_or(0);
+            let decoded_change: i128 = changes.iter().map(|change| change.amount).sum();
+            if actual_change != decoded_change {
+                changes.push(BalanceChange {
+                    description: "other",
+                    counterparty: None,
+                    amount: actual_change - decoded_change,
+                });
+            }
+        }
+        if changes.is_empty() {
+            continue;
+        }
+
+        let total_change: i128 = changes.iter().map(|change| change.amount).sum();
+        let mut running_balance = balance - total_change;
+        balance = running_balance;
+        let entries = changes
+            .into_iter()
+            .map(|change| {
+                running_balance += change.amount;
+                CliHistoryEntry {
+                    signature: signature.to_string(),
+                    slot: confirmed_transaction.slot,
+                    block_time: confirmed_transaction.block_time,
+                    description: change.description.to_string(),
+                    counterparty: change.counterparty.map(|pubkey| pubkey.to_string()),
+                    debit: (change.amount < 0).then(|| format_ui_amount(-change.amount, decimals)),
+                    credit: (change.amount > 0).then(|| format_ui_amount(change.amount, decimals)),
+                    balance: format_ui_amount(running_balance, decimals),
+                }
+            })
+            .collect::<Vec<_>>();
+        transaction_entries.push(entries);
+    }
+
+    Ok(CliAccountHistory {
+        address: address.to_string(),
+        mint: token_account.mint.to_string(),
+        entries: transaction_entries.into_iter().rev().flatten().collect(),
+    })
+}
+
+fn command_history(
+    config: &Config,
+    address: Pubkey,
+    token: Option<Pubkey>,
+    limit: Option<usize>,
+    csv: bool,
+) -> CommandResult {
+    let account = config
+        .rpc_client
+        .get_account_with_commitment(&address, config.rpc_client.commitment())?
+        .value;
+    let token_accounts = match account {
+        Some(account) if account.owner == spl_token::id() => {
+            let token_account = StateWithExtensions::<Account>::unpack(&account.data)
+                .map_err(|_| format!("Invalid token account {}", address))?
+                .base;
+            vec![(address, token_account)]
+        }
+        _ => {
+            let filter = token.map_or(
+                TokenAccountsFilter::ProgramId(spl_token::id()),
+                TokenAccountsFilter::Mint,
+            );
+            let mut token_accounts = vec![];
+            for keyed_account in config
+                .rpc_client
+                .get_token_accounts_by_owner(&address, filter)?
+            {
+                let token_account_address = keyed_account.pubkey.parse::<Pubkey>()?;
+                token_accounts.push((
+                    token_account_address,
+                    get_token_account(config, &token_account_address)?,
+                ));
+            }
+            token_accounts
+        }
+    };
+
+    let mut decimals_by_mint = HashMap::new();
+    let mut accounts = vec![];
+    for (token_account_address, token_account) in token_accounts {
+        if token.map_or(false, |token| token != token_account.mint) {
+            continue;
+        }
+        let decimals = match decimals_by_mint.get(&token_account.mint) {
+            Some(decimals) => *decimals,
+            None => {
+                let decimals = get_mint(config, &token_account.mint)?.decimals;
+                decimals_by_mint.insert(token_account.mint, decimals);
+                decimals
+            }
+        };
+        accounts.push(get_token_account_history(
+            config,
+            &token_account_address,
+            &token_account,
+            decimals,
+            limit,
+        )?);
+    }
+
+    let history = CliTokenHistory { accounts };
+    if csv {
+        print!("{}", history.to_csv());
+    } else {
+        println!("{}", config.output_format.formatted_string(&history));
+    }
+    Ok(None)
+}
+
+fn print_transaction_signers(config: &Config, transactions: &[Transaction]) -> Result<(), Error> {
+    for transaction in transactions {
+        println!("{}", return_signers(transaction, &config.output_format)?);
+    }
+    Ok(())
+}
+
+fn command_sign_transaction(
+    config: &Config,
+    path: &str,
+    signers: Vec<Box<dyn Signer>>,
+) -> CommandResult {
+    let mut transactions = read_transactions(path)?;
+    for transaction in transactions.iter_mut() {
+        let signer_keys = transaction.message.signer_keys();
+        let signers = signers
+            .iter()
+            .filter(|signer| signer_keys.contains(&&signer.pubkey()))
+            .map(|signer| signer.as_ref())
+            .collect::<Vec<_>>();
+        let recent_blockhash = transaction.message.recent_blockhash;
+        transaction.try_partial_sign(&signers, recent_blockhash)?;
+    }
+    write_transactions(path, &transactions)?;
+    print_transaction_signers(config, &transactions)?;
+    Ok(None)
+}
+
+fn command_merge_transactions(config: &Config, output: &str, inputs: Vec<&str>) -> CommandResult {
+    let mut inputs = inputs.into_iter();
+    let mut transactions = read_transactions(inputs.next().unwrap())?;
+    for input in inputs {
+        let others = read_transactions(input)?;
+        if others.len() != transactions.len() {
+            return Err(format!(
+                "Transaction file {} holds {} transactions, expected {}",
+                input,
+                others.len(),
+                transactions.len()
+            )
+            .into());
+        }
+        for (transaction, other) in transactions.iter_mut().zip(others.iter()) {
+            merge_signatures(transaction, other)
+                .map_err(|err| format!("Transaction file {}: {}", input, err))?;
+        }
+    }
+    write_transactions(output, &transactions)?;
+    print_transaction_signers(config, &transactions)?;
+    Ok(None)
+}
+
+fn command_submit_transaction(config: &Config, path: &str, no_wait: bool) -> CommandResult {
+    let transactions = read_transactions(path)?;
+    for (index, transaction) in transactions.iter().enumerate() {
+        let absent = absent_signers(transaction);
+        if !absent.is_empty() {
+            return Err(format!(
+                "Transaction {} is missing signatures from: {}",
+                index,
+                absent
+                    .iter()
+                    .map(|pubkey| pubkey.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into());
+        }
+        transaction.verify()?;
+    }
+    for transaction in transactions.iter() {
+        let signature = if no_wait {
+            config.rpc_client.send_transaction(transaction)?
+        } else {
+            config
+                .rpc_client
+                .send_and_confirm_transaction_with_spinner(transaction)?
+        };
+        let signature = CliSignature {
+            signature: signature.to_string(),
+        };
+        println!("{}", config.output_format.formatted_string(&signature));
+    }
+    Ok(None)
+}
+
 struct SignOnlyNeedsFullMintSpec {}
 impl offline::ArgsConfig for SignOnlyNeedsFullMintSpec {
     fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
@@ -1359,6 +2259,19 @@ fn main() {
                 ),
         )
         .arg(fee_payer_arg().global(true))
+        .arg(
+            Arg::with_name("export_transaction")
+                .long("export-transaction")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help(
+                    "Write the transactions, signed by the available signers, to a file \
+                     instead of sending them. Collect the remaining signatures with \
+                     `sign-transaction` and `merge-transactions`, then send them with \
+                     `submit-transaction`."
+                ),
+        )
         .subcommand(SubCommand::with_name("create-token").about("Create a new token")
                 .arg(
                     Arg::with_name("token_keypair")
@@ -1401,6 +2314,71 @@ fn main() {
                             "Enable the mint authority to freeze associated token accounts."
                         ),
                 )
+                .arg(
+                    Arg::with_name("transfer_fee_basis_points")
+                        .long("transfer-fee-basis-points")
+                        .value_name("BASIS_POINTS")
+                        .validator(is_transfer_fee_basis_points)
+                        .takes_value(true)
+                        .requires("transfer_fee_maximum")
+                        .help(
+                            "Withhold a fee from every transfer, in basis points of the \
+                             transferred amount. The mint authority may update the fee and \
+                             withdraw withheld tokens."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("transfer_fee_maximum")
+                        .long("transfer-fee-maximum")
+                        .value_name("TOKEN_AMOUNT")
+                        .validator(is_amount)
+                        .takes_value(true)
+                        .requires("transfer_fee_basis_points")
+                        .help("Maximum fee withheld from a single transfer"),
+                )
+                .arg(
+                    Arg::with_name("interest_rate")
+                        .long("interest-rate")
+                        .value_name("BASIS_POINTS")
+                        .validator(is_parsable::<i16>)
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help(
+                            "Display balances with interest accrued continuously at this annual \
+                             rate, in basis points. Raw balances and the supply never change. \
+                             The mint authority may update the rate."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("non_transferable")
+                        .long("non-transferable")
+                        .takes_value(false)
+                        .help(
+                            "Prevent tokens from ever being transferred between accounts. \
+                             Tokens may still be minted, burned, and their accounts closed."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("default_frozen")
+                        .long("default-frozen")
+                        .takes_value(false)
+                        .requires("enable_freeze")
+                        .help(
+                            "Start every new token account frozen, so that the freeze \
+                             authority must thaw it before it can be used."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("permanent_delegate")
+                        .long("permanent-delegate")
+                        .value_name("ADDRESS")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help(
+                            "Specify a permanent delegate address, which may transfer or burn \
+                             tokens from any account of the mint without the owner's approval."
+                        ),
+                )
                 .arg(
                     Arg::with_name("memo")
                         .long("memo")
@@ -1498,11 +2476,24 @@ fn main() {
                     Arg::with_name("authority_type")
                         .value_name("AUTHORITY_TYPE")
                         .takes_value(true)
-                        .possible_values(&["mint", "freeze", "owner", "close"])
+                        .possible_values(&[
+                            "mint",
+                            "freeze",
+                            "owner",
+                            "close",
+                            "transfer-fee-config",
+                            "withheld-withdraw",
+                            "interest-rate",
+                            "permanent-delegate",
+                        ])
                         .index(2)
                         .required(true)
                         .help("The new authority type. \
-                            Token mints support `mint` and `freeze` authorities;\
+                            Token mints support `mint` and `freeze` authorities, and \
+                            `transfer-fee-config` and `withheld-withdraw` authorities if they \
+                            have a transfer fee, an `interest-rate` authority if they \
+                            bear interest, and a `permanent-delegate` authority if they have \
+                            one;\
                             Token accounts support `owner` and `close` authorities."),
                 )
                 .arg(
@@ -1622,47 +2613,241 @@ fn main() {
                         .requires("sign_only")
                         .help("In sign-only mode, specifies that the recipient is the owner of the associated token account rather than an actual token account"),
                 )
+                .arg(
+                    Arg::with_name("memo")
+                        .long("with-memo")
+                        .value_name("MEMO")
+                        .takes_value(true)
+                        .help("Precede the transfer with a memo, as required by recipients that enabled required transfer memos"),
+                )
                 .arg(multisig_signer_arg())
                 .arg(mint_decimals_arg())
                 .nonce_args(true)
                 .offline_args_config(&SignOnlyNeedsMintDecimals{}),
         )
         .subcommand(
-            SubCommand::with_name("burn")
-                .about("Burn tokens from an account")
+            SubCommand::with_name("distribute")
+                .about("Transfer tokens to the recipients listed in a CSV file")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Token to distribute"),
+                )
+                .arg(
+                    Arg::with_name("csv")
+                        .value_name("CSV_PATH")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("CSV file of RECIPIENT,AMOUNT rows, with amounts in tokens. \
+                               Recipients may be token accounts or wallet addresses, in which case \
+                               the tokens go to the associated token account"),
+                )
+                .arg(
+                    Arg::with_name("state_file")
+                        .long("state-file")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help("File logging the rows already sent, used to resume an interrupted \
+                               distribution [default: CSV_PATH with a .state suffix]"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .validator(is_valid_pubkey)
+                        .value_name("SENDER_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .long("from")
+                        .help("Specify the sending token account \
+                            [default: owner's associated token account]")
+                )
+                .arg(owner_keypair_arg_with_value_name("SENDER_TOKEN_OWNER_KEYPAIR")
+                        .help(
+                            "Specify the owner of the sending token account. \
+                            This may be a keypair file, the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("fund_recipient")
+                        .long("fund-recipient")
+                        .takes_value(false)
+                        .help("Create the associated token accounts of recipients that don't already have one")
+                )
+                .arg(multisig_signer_arg())
+                .arg(mint_decimals_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("burn")
+                .about("Burn tokens from an account")
+                .arg(
+                    Arg::with_name("source")
+                        .validator(is_valid_pubkey)
+                        .value_name("SOURCE_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token account address to burn from"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to burn, in tokens"),
+                )
+                .arg(owner_keypair_arg_with_value_name("SOURCE_TOKEN_OWNER_KEYPAIR")
+                        .help(
+                            "Specify the source token owner account. \
+                            This may be a keypair file, the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .mint_args()
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+        )
+        .subcommand(
+            SubCommand::with_name("mint")
+                .about("Mint new tokens")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token to mint"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to mint, in tokens"),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("RECIPIENT_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(3)
+                        .help("The token account address of recipient [default: associated token account for --owner]"),
+                )
+                .arg(
+                    Arg::with_name("mint_authority")
+                        .long("mint-authority")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the mint authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(mint_decimals_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        )
+        .subcommand(
+            SubCommand::with_name("set-transfer-fee")
+                .about("Set the transfer fee of a token")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token with the transfer fee"),
+                )
+                .arg(
+                    Arg::with_name("transfer_fee_basis_points")
+                        .value_name("BASIS_POINTS")
+                        .validator(is_transfer_fee_basis_points)
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Fee withheld from every transfer, in basis points of the transferred amount"),
+                )
+                .arg(
+                    Arg::with_name("transfer_fee_maximum")
+                        .value_name("TOKEN_AMOUNT")
+                        .validator(is_amount)
+                        .takes_value(true)
+                        .index(3)
+                        .required(true)
+                        .help("Maximum fee withheld from a single transfer, in tokens"),
+                )
+                .arg(
+                    Arg::with_name("transfer_fee_authority")
+                        .long("transfer-fee-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the transfer fee authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(mint_decimals_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        )
+        .subcommand(
+            SubCommand::with_name("set-interest-rate")
+                .about("Set the interest rate of an interest-bearing token")
                 .arg(
-                    Arg::with_name("source")
+                    Arg::with_name("token")
                         .validator(is_valid_pubkey)
-                        .value_name("SOURCE_TOKEN_ACCOUNT_ADDRESS")
+                        .value_name("TOKEN_ADDRESS")
                         .takes_value(true)
                         .index(1)
                         .required(true)
-                        .help("The token account address to burn from"),
+                        .help("The interest-bearing token"),
                 )
                 .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount)
-                        .value_name("TOKEN_AMOUNT")
+                    Arg::with_name("rate")
+                        .value_name("BASIS_POINTS")
+                        .validator(is_parsable::<i16>)
                         .takes_value(true)
+                        .allow_hyphen_values(true)
                         .index(2)
                         .required(true)
-                        .help("Amount to burn, in tokens"),
+                        .help("The new annual interest rate, in basis points"),
                 )
-                .arg(owner_keypair_arg_with_value_name("SOURCE_TOKEN_OWNER_KEYPAIR")
+                .arg(
+                    Arg::with_name("rate_authority")
+                        .long("rate-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
                         .help(
-                            "Specify the source token owner account. \
-                            This may be a keypair file, the ASK keyword. \
-                            Defaults to the client keypair.",
+                            "Specify the rate authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
                         ),
                 )
                 .arg(multisig_signer_arg())
-                .mint_args()
                 .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+                .offline_args(),
         )
         .subcommand(
-            SubCommand::with_name("mint")
-                .about("Mint new tokens")
+            SubCommand::with_name("withdraw-withheld-tokens")
+                .about("Withdraw transfer fees withheld in token accounts")
                 .arg(
                     Arg::with_name("token")
                         .validator(is_valid_pubkey)
@@ -1670,42 +2855,73 @@ fn main() {
                         .takes_value(true)
                         .index(1)
                         .required(true)
-                        .help("The token to mint"),
+                        .help("The token with the transfer fee"),
                 )
                 .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount)
-                        .value_name("TOKEN_AMOUNT")
+                    Arg::with_name("destination")
+                        .validator(is_valid_pubkey)
+                        .value_name("DESTINATION_TOKEN_ACCOUNT_ADDRESS")
                         .takes_value(true)
                         .index(2)
                         .required(true)
-                        .help("Amount to mint, in tokens"),
+                        .help("The token account to receive the withheld tokens"),
                 )
                 .arg(
-                    Arg::with_name("recipient")
+                    Arg::with_name("source")
                         .validator(is_valid_pubkey)
-                        .value_name("RECIPIENT_TOKEN_ACCOUNT_ADDRESS")
+                        .value_name("SOURCE_TOKEN_ACCOUNT_ADDRESS")
                         .takes_value(true)
                         .index(3)
-                        .help("The token account address of recipient [default: associated token account for --owner]"),
+                        .required_unless("include_mint")
+                        .multiple(true)
+                        .help("The token accounts to withdraw withheld tokens from"),
                 )
                 .arg(
-                    Arg::with_name("mint_authority")
-                        .long("mint-authority")
-                        .alias("owner")
+                    Arg::with_name("include_mint")
+                        .long("include-mint")
+                        .takes_value(false)
+                        .help("Also withdraw the withheld tokens harvested into the mint"),
+                )
+                .arg(
+                    Arg::with_name("withdraw_withheld_authority")
+                        .long("withdraw-withheld-authority")
                         .value_name("KEYPAIR")
                         .validator(is_valid_signer)
                         .takes_value(true)
                         .help(
-                            "Specify the mint authority keypair. \
+                            "Specify the withdraw withheld authority keypair. \
                              This may be a keypair file or the ASK keyword. \
                              Defaults to the client keypair."
                         ),
                 )
-                .arg(mint_decimals_arg())
                 .arg(multisig_signer_arg())
                 .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name("harvest-withheld-tokens")
+                .about("Move transfer fees withheld in token accounts into their mint")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token with the transfer fee"),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .validator(is_valid_pubkey)
+                        .value_name("SOURCE_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .multiple(true)
+                        .help("The token accounts to harvest withheld tokens from"),
+                )
+                .nonce_args(true)
+                .offline_args(),
         )
         .subcommand(
             SubCommand::with_name("freeze")
@@ -1868,6 +3084,40 @@ fn main() {
                 .nonce_args(true)
                 .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
         )
+        .subcommand(
+            SubCommand::with_name("enable-required-transfer-memos")
+                .about("Require memos on transfers into a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account, which must have room for the memo requirement"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name("disable-required-transfer-memos")
+                .about("Stop requiring memos on transfers into a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
         .subcommand(
             SubCommand::with_name("revoke")
                 .about("Revoke a delegate's authority")
@@ -2051,10 +3301,48 @@ fn main() {
                     .help("The address of the SPL Token multisig account to query"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("List the token movements of an account, or of every token account of an owner")
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_OR_OWNER_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Token account to list, or owner whose token accounts to list"),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .long("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .help("Only list accounts of this token"),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .validator(is_parsable::<usize>)
+                        .value_name("COUNT")
+                        .takes_value(true)
+                        .help("Only look at the most recent COUNT transactions of each account"),
+                )
+                .arg(
+                    Arg::with_name("csv")
+                        .long("csv")
+                        .takes_value(false)
+                        .help("Print the ledger as CSV, ignoring `--output`"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("gc")
                 .about("Cleanup unnecessary token accounts")
                 .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
         )
         .subcommand(
             SubCommand::with_name("sync-native")
@@ -2075,6 +3363,69 @@ fn main() {
                         .long("address")
                         .conflicts_with("owner")
                         .help("Specify the specific token account address to sync"),
+                )
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name("sign-transaction")
+                .about("Add signatures to the transactions of a transaction file")
+                .arg(
+                    Arg::with_name("transaction_file")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Transaction file written by `--export-transaction`, updated in place"),
+                )
+                .arg(
+                    Arg::with_name("keypair")
+                        .long("keypair")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .multiple(true)
+                        .required(true)
+                        .help("Keypair to sign with; may be specified multiple times"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("merge-transactions")
+                .about("Combine the signatures collected in copies of a transaction file")
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("OUTPUT_PATH")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Transaction file to write the merged transactions to"),
+                )
+                .arg(
+                    Arg::with_name("inputs")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .index(2)
+                        .multiple(true)
+                        .required(true)
+                        .help("Signed copies of the same transaction file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("submit-transaction")
+                .about("Send the fully signed transactions of a transaction file")
+                .arg(
+                    Arg::with_name("transaction_file")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Transaction file holding every required signature"),
+                )
+                .arg(
+                    Arg::with_name("no_wait")
+                        .long("no-wait")
+                        .takes_value(false)
+                        .help("Return signatures immediately after submitting the transactions, instead of waiting for confirmations"),
                 ),
         )
         .get_matches();
@@ -2098,13 +3449,22 @@ fn main() {
                 .unwrap_or(&cli_config.json_rpc_url),
         );
 
-        let (signer, fee_payer) = signer_from_path(
+        // Exported transactions are signed elsewhere, so any signer may be given by pubkey
+        let export_transaction = matches
+            .value_of("export_transaction")
+            .map(|path| path.to_string());
+        let signer_config = SignerFromPathConfig {
+            allow_null_signer: export_transaction.is_some(),
+        };
+
+        let (signer, fee_payer) = signer_from_path_with_config(
             matches,
             matches
                 .value_of("fee_payer")
                 .unwrap_or(&cli_config.keypair_path),
             "fee_payer",
             &mut wallet_manager,
+            &signer_config,
         )
         .map(|s| {
             let p = s.pubkey();
@@ -2136,13 +3496,14 @@ fn main() {
                 exit(1);
             });
         let nonce_authority = if nonce_account.is_some() {
-            let (signer, nonce_authority) = signer_from_path(
+            let (signer, nonce_authority) = signer_from_path_with_config(
                 matches,
                 matches
                     .value_of(NONCE_AUTHORITY_ARG.name)
                     .unwrap_or(&cli_config.keypair_path),
                 NONCE_AUTHORITY_ARG.name,
                 &mut wallet_manager,
+                &signer_config,
             )
             .map(|s| {
                 let p = s.pubkey();
@@ -2162,11 +3523,16 @@ fn main() {
         let blockhash_query = BlockhashQuery::new_from_matches(matches);
         let sign_only = matches.is_present(SIGN_ONLY_ARG.name);
 
-        let multisig_signers = signers_of(matches, MULTISIG_SIGNER_ARG.name, &mut wallet_manager)
-            .unwrap_or_else(|e| {
-                eprintln!("error: {}", e);
-                exit(1);
-            });
+        let multisig_signers = signers_of(
+            matches,
+            MULTISIG_SIGNER_ARG.name,
+            &mut wallet_manager,
+            &signer_config,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        });
         if let Some(mut multisig_signers) = multisig_signers {
             multisig_signers.sort_by(|(_, lp), (_, rp)| lp.cmp(rp));
             let (signers, pubkeys): (Vec<_>, Vec<_>) = multisig_signers.into_iter().unzip();
@@ -2185,6 +3551,7 @@ fn main() {
             blockhash_query,
             sign_only,
             multisigner_pubkeys,
+            export_transaction,
         }
     };
 
@@ -2196,6 +3563,17 @@ fn main() {
             let mint_authority =
                 config.pubkey_or_default(arg_matches, "mint_authority", &mut wallet_manager);
             let memo = value_t!(arg_matches, "memo", String).ok();
+            let transfer_fee = value_of::<u16>(arg_matches, "transfer_fee_basis_points").map(
+                |transfer_fee_basis_points| {
+                    (
+                        transfer_fee_basis_points,
+                        value_t_or_exit!(arg_matches, "transfer_fee_maximum", f64),
+                    )
+                },
+            );
+            let interest_rate = value_of::<i16>(arg_matches, "interest_rate");
+            let permanent_delegate =
+                pubkey_of_signer(arg_matches, "permanent_delegate", &mut wallet_manager).unwrap();
 
             let (token_signer, token) =
                 get_signer(arg_matches, "token_keypair", &mut wallet_manager)
@@ -2208,6 +3586,11 @@ fn main() {
                 token,
                 mint_authority,
                 arg_matches.is_present("enable_freeze"),
+                transfer_fee,
+                interest_rate,
+                arg_matches.is_present("non_transferable"),
+                permanent_delegate,
+                arg_matches.is_present("default_frozen"),
                 memo,
             )
         }
@@ -2260,6 +3643,10 @@ fn main() {
                 "freeze" => AuthorityType::FreezeAccount,
                 "owner" => AuthorityType::AccountOwner,
                 "close" => AuthorityType::CloseAccount,
+                "transfer-fee-config" => AuthorityType::TransferFeeConfig,
+                "withheld-withdraw" => AuthorityType::WithheldWithdraw,
+                "interest-rate" => AuthorityType::InterestRate,
+                "permanent-delegate" => AuthorityType::PermanentDelegate,
                 _ => unreachable!(),
             };
 
@@ -2302,6 +3689,7 @@ fn main() {
                 || matches.is_present("allow_unfunded_recipient");
             no_wait = matches.is_present("no_wait");
             let recipient_is_ata_owner = matches.is_present("recipient_is_ata_owner");
+            let memo = value_t!(arg_matches, "memo", String).ok();
 
             command_transfer(
                 &config,
@@ -2314,6 +3702,37 @@ fn main() {
                 fund_recipient,
                 mint_decimals,
                 recipient_is_ata_owner,
+                memo,
+            )
+        }
+        ("distribute", Some(arg_matches)) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let csv_path = arg_matches.value_of("csv").unwrap();
+            let state_path = arg_matches
+                .value_of("state_file")
+                .map(|path| path.to_string())
+                .unwrap_or_else(|| format!("{}.state", csv_path));
+            let sender = pubkey_of_signer(arg_matches, "from", &mut wallet_manager).unwrap();
+
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            let fund_recipient = arg_matches.is_present("fund_recipient");
+
+            command_distribute(
+                &config,
+                token,
+                csv_path,
+                &state_path,
+                sender,
+                owner,
+                fund_recipient,
+                mint_decimals,
+                &bulk_signers,
             )
         }
         ("burn", Some(arg_matches)) => {
@@ -2355,6 +3774,84 @@ fn main() {
                 mint_authority,
             )
         }
+        ("set-transfer-fee", Some(arg_matches)) => {
+            let (transfer_fee_authority_signer, transfer_fee_authority) = config.signer_or_default(
+                arg_matches,
+                "transfer_fee_authority",
+                &mut wallet_manager,
+            );
+            bulk_signers.push(transfer_fee_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let transfer_fee_basis_points =
+                value_t_or_exit!(arg_matches, "transfer_fee_basis_points", u16);
+            let transfer_fee_maximum = value_t_or_exit!(arg_matches, "transfer_fee_maximum", f64);
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            command_set_transfer_fee(
+                &config,
+                token,
+                transfer_fee_basis_points,
+                transfer_fee_maximum,
+                mint_decimals,
+                transfer_fee_authority,
+            )
+        }
+        ("set-interest-rate", Some(arg_matches)) => {
+            let (rate_authority_signer, rate_authority) =
+                config.signer_or_default(arg_matches, "rate_authority", &mut wallet_manager);
+            bulk_signers.push(rate_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let rate = value_t_or_exit!(arg_matches, "rate", i16);
+            command_set_interest_rate(&config, token, rate, rate_authority)
+        }
+        ("withdraw-withheld-tokens", Some(arg_matches)) => {
+            let (withdraw_withheld_authority_signer, withdraw_withheld_authority) = config
+                .signer_or_default(
+                    arg_matches,
+                    "withdraw_withheld_authority",
+                    &mut wallet_manager,
+                );
+            bulk_signers.push(withdraw_withheld_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let destination = pubkey_of_signer(arg_matches, "destination", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let sources = pubkeys_of_multiple_signers(arg_matches, "source", &mut wallet_manager)
+                .unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    exit(1);
+                })
+                .unwrap_or_default();
+            let include_mint = arg_matches.is_present("include_mint");
+            command_withdraw_withheld_tokens(
+                &config,
+                token,
+                destination,
+                sources,
+                include_mint,
+                withdraw_withheld_authority,
+            )
+        }
+        ("harvest-withheld-tokens", Some(arg_matches)) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let sources = pubkeys_of_multiple_signers(arg_matches, "source", &mut wallet_manager)
+                .unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    exit(1);
+                })
+                .unwrap();
+            command_harvest_withheld_tokens(&config, token, sources)
+        }
         ("freeze", Some(arg_matches)) => {
             let (freeze_authority_signer, freeze_authority) =
                 config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
@@ -2429,6 +3926,18 @@ fn main() {
                 mint_decimals,
             )
         }
+        ("enable-required-transfer-memos", Some(arg_matches))
+        | ("disable-required-transfer-memos", Some(arg_matches)) => {
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let enable = sub_command == "enable-required-transfer-memos";
+            command_required_transfer_memos(&config, account, owner, enable)
+        }
         ("revoke", Some(arg_matches)) => {
             let (owner_signer, owner_address) =
                 config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
@@ -2493,6 +4002,16 @@ fn main() {
                 .unwrap();
             command_multisig(&config, address)
         }
+        ("history", Some(arg_matches)) => {
+            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager).unwrap();
+            let limit = value_of::<usize>(arg_matches, "limit");
+            let csv = arg_matches.is_present("csv");
+
+            command_history(&config, address, token, limit, csv)
+        }
         ("gc", Some(arg_matches)) => {
             match config.output_format {
                 OutputFormat::Json | OutputFormat::JsonCompact => {
@@ -2519,6 +4038,34 @@ fn main() {
 
             command_sync_native(address)
         }
+        ("sign-transaction", Some(arg_matches)) => {
+            let path = arg_matches.value_of("transaction_file").unwrap();
+            let signers = arg_matches
+                .values_of("keypair")
+                .unwrap()
+                .map(|keypair_path| {
+                    signer_from_path(arg_matches, keypair_path, "keypair", &mut wallet_manager)
+                        .unwrap_or_else(|e| {
+                            eprintln!("error: {}", e);
+                            exit(1);
+                        })
+                })
+                .collect();
+
+            command_sign_transaction(&config, path, signers)
+        }
+        ("merge-transactions", Some(arg_matches)) => {
+            let output = arg_matches.value_of("output").unwrap();
+            let inputs = arg_matches.values_of("inputs").unwrap().collect();
+
+            command_merge_transactions(&config, output, inputs)
+        }
+        ("submit-transaction", Some(arg_matches)) => {
+            let path = arg_matches.value_of("transaction_file").unwrap();
+            let no_wait = arg_matches.is_present("no_wait");
+
+            command_submit_transaction(&config, path, no_wait)
+        }
         _ => unreachable!(),
     }
     .and_then(|transaction_info| {
@@ -2527,6 +4074,7 @@ fn main() {
             let signer_info = CliSignerInfo {
                 signers: bulk_signers,
             };
+            let mut exported_transactions = vec![];
 
             for instructions in instruction_batches {
                 let message = if let Some(nonce_account) = config.nonce_account.as_ref() {
@@ -2560,7 +4108,10 @@ fn main() {
                 let signers = signer_info.signers_for_message(&message);
                 let mut transaction = Transaction::new_unsigned(message);
 
-                if config.sign_only {
+                if config.export_transaction.is_some() {
+                    transaction.try_partial_sign(&signers, recent_blockhash)?;
+                    exported_transactions.push(transaction);
+                } else if config.sign_only {
                     transaction.try_partial_sign(&signers, recent_blockhash)?;
                     println!("{}", return_signers(&transaction, &config.output_format)?);
                 } else {
@@ -2578,6 +4129,11 @@ fn main() {
                     println!("{}", config.output_format.formatted_string(&signature));
                 }
             }
+
+            if let Some(path) = &config.export_transaction {
+                write_transactions(path, &exported_transactions)?;
+                print_transaction_signers(&config, &exported_transactions)?;
+            }
         }
         Ok(())
     })
@@ -21,12 +21,17 @@ pub(crate) fn println_display(config: &Config, message: String) {
 pub(crate) struct CliTokenAmount {
     #[serde(flatten)]
     pub(crate) amount: UiTokenAmount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ui_amount_with_interest: Option<String>,
 }
 
 impl QuietDisplay for CliTokenAmount {}
 impl VerboseDisplay for CliTokenAmount {
     fn write_str(&self, w: &mut dyn fmt::Write) -> fmt::Result {
         writeln!(w, "ui amount: {}", self.amount.real_number_string_trimmed())?;
+        if let Some(ui_amount_with_interest) = &self.ui_amount_with_interest {
+            writeln!(w, "ui amount with interest: {}", ui_amount_with_interest)?;
+        }
         writeln!(w, "decimals: {}", self.amount.decimals)?;
         writeln!(w, "amount: {}", self.amount.amount)
     }
@@ -34,7 +39,11 @@ impl VerboseDisplay for CliTokenAmount {
 
 impl fmt::Display for CliTokenAmount {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{}", self.amount.real_number_string_trimmed())
+        if let Some(ui_amount_with_interest) = &self.ui_amount_with_interest {
+            writeln!(f, "{}", ui_amount_with_interest)
+        } else {
+            writeln!(f, "{}", self.amount.real_number_string_trimmed())
+        }
     }
 }
 
@@ -314,6 +323,98 @@ impl fmt::Display for CliTokenAccounts {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliHistoryEntry {
+    pub(crate) signature: String,
+    pub(crate) slot: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) block_time: Option<i64>,
+    pub(crate) description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) counterparty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) debit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) credit: Option<String>,
+    pub(crate) balance: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliAccountHistory {
+    pub(crate) address: String,
+    pub(crate) mint: String,
+    pub(crate) entries: Vec<CliHistoryEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenHistory {
+    pub(crate) accounts: Vec<CliAccountHistory>,
+}
+
+impl CliTokenHistory {
+    pub(crate) fn to_csv(&self) -> String {
+        let mut csv =
+            "account,mint,slot,blockTime,signature,description,counterparty,debit,credit,balance\n"
+                .to_string();
+        for account in &self.accounts {
+            for entry in &account.entries {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{}\n",
+                    account.address,
+                    account.mint,
+                    entry.slot,
+                    entry
+                        .block_time
+                        .map(|block_time| block_time.to_string())
+                        .unwrap_or_default(),
+                    entry.signature,
+                    entry.description,
+                    entry.counterparty.as_deref().unwrap_or_default(),
+                    entry.debit.as_deref().unwrap_or_default(),
+                    entry.credit.as_deref().unwrap_or_default(),
+                    entry.balance,
+                ));
+            }
+        }
+        csv
+    }
+}
+
+impl QuietDisplay for CliTokenHistory {}
+impl VerboseDisplay for CliTokenHistory {}
+
+impl fmt::Display for CliTokenHistory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for account in &self.accounts {
+            writeln!(f)?;
+            writeln_name_value(f, "Account:", &account.address)?;
+            writeln_name_value(f, "Mint:", &account.mint)?;
+            writeln!(
+                f,
+                "{:<12}  {:<10}  {:>20}  {:>20}  {:>20}  Signature",
+                "Slot", "Type", "Debit", "Credit", "Balance"
+            )?;
+            writeln!(f, "{}", "-".repeat(160))?;
+            for entry in &account.entries {
+                writeln!(
+                    f,
+                    "{:<12}  {:<10}  {:>20}  {:>20}  {:>20}  {}",
+                    entry.slot,
+                    entry.description,
+                    entry.debit.as_deref().unwrap_or_default(),
+                    entry.credit.as_deref().unwrap_or_default(),
+                    entry.balance,
+                    entry.signature,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
 fn flattened<S: Serializer>(
     vec: &[Vec<CliTokenAccount>],
     serializer: S,
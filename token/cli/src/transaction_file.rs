@@ -0,0 +1,85 @@
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Reads the transactions stored in a transaction file, one base64-encoded transaction per
+/// line
+pub(crate) fn read_transactions(path: &str) -> Result<Vec<Transaction>, Error> {
+    let file = File::open(path)
+        .map_err(|err| format!("Unable to open transaction file {}: {}", path, err))?;
+    let mut transactions = vec![];
+    for (line_index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let transaction = base64::decode(line)
+            .ok()
+            .and_then(|data| bincode::deserialize::<Transaction>(&data).ok())
+            .ok_or_else(|| {
+                format!(
+                    "Transaction file {}, line {}: invalid transaction",
+                    path,
+                    line_index + 1
+                )
+            })?;
+        transactions.push(transaction);
+    }
+    if transactions.is_empty() {
+        return Err(format!("Transaction file {} is empty", path).into());
+    }
+    Ok(transactions)
+}
+
+/// Writes transactions to a transaction file, replacing any previous contents
+pub(crate) fn write_transactions(path: &str, transactions: &[Transaction]) -> Result<(), Error> {
+    let mut file = File::create(path)
+        .map_err(|err| format!("Unable to create transaction file {}: {}", path, err))?;
+    for transaction in transactions {
+        writeln!(file, "{}", base64::encode(bincode::serialize(transaction)?))?;
+    }
+    Ok(())
+}
+
+/// Copies into `transaction` the valid signatures of `other`, which must carry the same
+/// message
+pub(crate) fn merge_signatures(
+    transaction: &mut Transaction,
+    other: &Transaction,
+) -> Result<(), Error> {
+    if transaction.message != other.message {
+        return Err("Cannot merge signatures of different transactions".into());
+    }
+    let message_data = other.message_data();
+    for ((signature, pubkey), other_signature) in transaction
+        .signatures
+        .iter_mut()
+        .zip(other.message.account_keys.iter())
+        .zip(other.signatures.iter())
+    {
+        if *other_signature == Signature::default() {
+            continue;
+        }
+        if !other_signature.verify(pubkey.as_ref(), &message_data) {
+            return Err(format!("Invalid signature for signer {}", pubkey).into());
+        }
+        *signature = *other_signature;
+    }
+    Ok(())
+}
+
+/// Returns the signers whose signature is still missing from `transaction`
+pub(crate) fn absent_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    transaction
+        .signatures
+        .iter()
+        .zip(transaction.message.account_keys.iter())
+        .filter(|(signature, _)| **signature == Signature::default())
+        .map(|(_, pubkey)| *pubkey)
+        .collect()
+}
@@ -74,6 +74,42 @@ pub enum TokenError {
     /// Instruction does not support non-native tokens
     #[error("Instruction does not support non-native tokens")]
     NonNativeNotSupported,
+
+    // 20
+    /// Extension not found in account data
+    #[error("Extension not found in account data")]
+    ExtensionNotFound,
+    /// Extension already initialized on this account
+    #[error("Extension already initialized on this account")]
+    ExtensionAlreadyInitialized,
+    /// Transfers of tokens with extensions must provide the mint
+    #[error("Transfers of tokens with extensions must provide the mint")]
+    MintRequiredForTransfer,
+    /// Transfer fee exceeds the maximum of 10,000 basis points
+    #[error("Transfer fee exceeds the maximum of 10,000 basis points")]
+    TransferFeeExceedsMaximum,
+    /// No authority exists to perform the desired operation
+    #[error("No authority exists to perform the desired operation")]
+    NoAuthorityExists,
+
+    // 25
+    /// Account still has withheld transfer fees that must be harvested or withdrawn before
+    /// closing
+    #[error("Account still has withheld transfer fees")]
+    AccountHasWithheldTransferFees,
+    /// Tokens of this mint are non-transferable
+    #[error("Tokens of this mint are non-transferable")]
+    NonTransferable,
+    /// The destination requires transfers to be immediately preceded by a memo
+    #[error("No memo in previous instruction; required for recipient to receive a transfer")]
+    NoMemo,
+    /// A batch contains another batch
+    #[error("Batch instructions can't be nested")]
+    NestedBatch,
+    /// Transfers into accounts requiring memos can't be made through a cross-program
+    /// invocation or a batch
+    #[error("Transfers into accounts that require memos must be top-level instructions")]
+    MemoTransferNotTopLevel,
 }
 impl From<TokenError> for ProgramError {
     fn from(e: TokenError) -> Self {
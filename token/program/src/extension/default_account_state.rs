@@ -0,0 +1,66 @@
+//! Default account state mint extension
+
+use crate::{
+    extension::{Extension, ExtensionType},
+    state::AccountState,
+};
+use num_enum::TryFromPrimitive;
+use solana_program::{
+    program_error::ProgramError,
+    program_pack::{Pack, Sealed},
+};
+
+/// Default account state extension data for mints.
+///
+/// Every account of the mint starts in this state when initialized.  Starting accounts as
+/// `Frozen` requires the mint to have a freeze authority, which thaws accounts as needed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DefaultAccountState {
+    /// Default state of new accounts
+    pub state: AccountState,
+}
+impl Default for DefaultAccountState {
+    fn default() -> Self {
+        DefaultAccountState {
+            state: AccountState::Initialized,
+        }
+    }
+}
+impl Sealed for DefaultAccountState {}
+impl Pack for DefaultAccountState {
+    const LEN: usize = 1;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(DefaultAccountState {
+            state: AccountState::try_from_primitive(src[0])
+                .or(Err(ProgramError::InvalidAccountData))?,
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.state as u8;
+    }
+}
+impl Extension for DefaultAccountState {
+    const TYPE: ExtensionType = ExtensionType::DefaultAccountState;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let check = DefaultAccountState {
+            state: AccountState::Frozen,
+        };
+        let mut packed = vec![0; DefaultAccountState::get_packed_len()];
+        DefaultAccountState::pack(check, &mut packed).unwrap();
+        assert_eq!(packed, vec![2]);
+        let unpacked = DefaultAccountState::unpack_from_slice(&packed).unwrap();
+        assert_eq!(unpacked, check);
+        assert_eq!(
+            DefaultAccountState::unpack_from_slice(&[3]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
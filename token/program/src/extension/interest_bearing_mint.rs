@@ -0,0 +1,260 @@
+//! Interest-bearing mint extension
+
+use crate::{
+    extension::{Extension, ExtensionType},
+    state::{pack_coption_key, unpack_coption_key},
+};
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    clock::UnixTimestamp,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::{Pack, Sealed},
+    pubkey::Pubkey,
+};
+use std::convert::TryFrom;
+
+/// Interest rates are expressed in basis points
+const ONE_IN_BASIS_POINTS: f64 = 10_000.;
+/// Average length of a year, used to annualize interest rates
+const SECONDS_PER_YEAR: f64 = 60. * 60. * 24. * 365.24;
+
+/// Interest-bearing extension data for mints.
+///
+/// Tokens accrue interest continuously at an annual rate, expressed in basis points.  The
+/// interest only applies to UI amounts: the raw amounts held in accounts and the mint supply
+/// never change.  When the rate is updated, the average rate since initialization is stored so
+/// that previously accrued interest is preserved.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InterestBearingConfig {
+    /// Authority that can set the interest rate
+    pub rate_authority: COption<Pubkey>,
+    /// Timestamp of initialization, from which to base interest calculations
+    pub initialization_timestamp: UnixTimestamp,
+    /// Average rate from initialization until the last update, in basis points
+    pub pre_update_average_rate: i16,
+    /// Timestamp of the last update, used to calculate the total amount accrued
+    pub last_update_timestamp: UnixTimestamp,
+    /// Current rate, since the last update, in basis points
+    pub current_rate: i16,
+}
+impl InterestBearingConfig {
+    /// Creates a new config accruing interest at `rate` from `unix_timestamp`
+    pub fn new(rate_authority: COption<Pubkey>, rate: i16, unix_timestamp: UnixTimestamp) -> Self {
+        Self {
+            rate_authority,
+            initialization_timestamp: unix_timestamp,
+            pre_update_average_rate: rate,
+            last_update_timestamp: unix_timestamp,
+            current_rate: rate,
+        }
+    }
+
+    /// Updates the current rate, folding the previous rate into the average rate since
+    /// initialization.  Returns `None` if `unix_timestamp` precedes the last update.
+    pub fn update_rate(&mut self, rate: i16, unix_timestamp: UnixTimestamp) -> Option<()> {
+        let pre_update_timespan = self
+            .last_update_timestamp
+            .checked_sub(self.initialization_timestamp)?;
+        let post_update_timespan = unix_timestamp.checked_sub(self.last_update_timestamp)?;
+        if post_update_timespan < 0 {
+            return None;
+        }
+        let total_timespan = pre_update_timespan.checked_add(post_update_timespan)?;
+        if total_timespan > 0 {
+            let pre_update_weight =
+                (self.pre_update_average_rate as i128).checked_mul(pre_update_timespan as i128)?;
+            let post_update_weight =
+                (self.current_rate as i128).checked_mul(post_update_timespan as i128)?;
+            let average_rate = pre_update_weight
+                .checked_add(post_update_weight)?
+                .checked_div(total_timespan as i128)?;
+            self.pre_update_average_rate = i16::try_from(average_rate).ok()?;
+        } else {
+            self.pre_update_average_rate = rate;
+        }
+        self.last_update_timestamp = unix_timestamp;
+        self.current_rate = rate;
+        Some(())
+    }
+
+    /// Total growth factor of UI amounts from initialization until `unix_timestamp`
+    pub fn total_scale(&self, unix_timestamp: UnixTimestamp) -> Option<f64> {
+        let pre_update_timespan = self
+            .last_update_timestamp
+            .checked_sub(self.initialization_timestamp)?;
+        let post_update_timespan = unix_timestamp.checked_sub(self.last_update_timestamp)?;
+        let pre_update_exp = (self.pre_update_average_rate as f64 * pre_update_timespan as f64
+            / SECONDS_PER_YEAR
+            / ONE_IN_BASIS_POINTS)
+            .exp();
+        let post_update_exp = (self.current_rate as f64 * post_update_timespan as f64
+            / SECONDS_PER_YEAR
+            / ONE_IN_BASIS_POINTS)
+            .exp();
+        Some(pre_update_exp * post_update_exp)
+    }
+
+    /// Converts a raw amount to its UI representation, including the interest accrued until
+    /// `unix_timestamp`
+    pub fn amount_to_ui_amount(
+        &self,
+        amount: u64,
+        decimals: u8,
+        unix_timestamp: UnixTimestamp,
+    ) -> Option<f64> {
+        let scale = self.total_scale(unix_timestamp)?;
+        let ui_amount = crate::amount_to_ui_amount(amount, decimals) * scale;
+        if ui_amount.is_finite() {
+            Some(ui_amount)
+        } else {
+            None
+        }
+    }
+
+    /// Converts a UI amount, including the interest accrued until `unix_timestamp`, to its raw
+    /// representation
+    pub fn ui_amount_to_amount(
+        &self,
+        ui_amount: f64,
+        decimals: u8,
+        unix_timestamp: UnixTimestamp,
+    ) -> Option<u64> {
+        let scale = self.total_scale(unix_timestamp)?;
+        let amount = ui_amount / scale * 10_usize.pow(decimals as u32) as f64;
+        if amount.is_finite() && amount >= 0. && amount <= u64::MAX as f64 {
+            Some(amount.round() as u64)
+        } else {
+            None
+        }
+    }
+}
+impl Sealed for InterestBearingConfig {}
+impl Pack for InterestBearingConfig {
+    const LEN: usize = 56;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 56];
+        let (
+            rate_authority,
+            initialization_timestamp,
+            pre_update_average_rate,
+            last_update_timestamp,
+            current_rate,
+        ) = array_refs![src, 36, 8, 2, 8, 2];
+        Ok(InterestBearingConfig {
+            rate_authority: unpack_coption_key(rate_authority)?,
+            initialization_timestamp: i64::from_le_bytes(*initialization_timestamp),
+            pre_update_average_rate: i16::from_le_bytes(*pre_update_average_rate),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+            current_rate: i16::from_le_bytes(*current_rate),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 56];
+        let (
+            rate_authority_dst,
+            initialization_timestamp_dst,
+            pre_update_average_rate_dst,
+            last_update_timestamp_dst,
+            current_rate_dst,
+        ) = mut_array_refs![dst, 36, 8, 2, 8, 2];
+        let &InterestBearingConfig {
+            ref rate_authority,
+            initialization_timestamp,
+            pre_update_average_rate,
+            last_update_timestamp,
+            current_rate,
+        } = self;
+        pack_coption_key(rate_authority, rate_authority_dst);
+        *initialization_timestamp_dst = initialization_timestamp.to_le_bytes();
+        *pre_update_average_rate_dst = pre_update_average_rate.to_le_bytes();
+        *last_update_timestamp_dst = last_update_timestamp.to_le_bytes();
+        *current_rate_dst = current_rate.to_le_bytes();
+    }
+}
+impl Extension for InterestBearingConfig {
+    const TYPE: ExtensionType = ExtensionType::InterestBearingConfig;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIALIZATION_TIMESTAMP: UnixTimestamp = 1_000_000;
+    const ONE_YEAR: UnixTimestamp = SECONDS_PER_YEAR as UnixTimestamp;
+
+    #[test]
+    fn test_pack_unpack() {
+        let check = InterestBearingConfig {
+            rate_authority: COption::Some(Pubkey::new(&[1; 32])),
+            initialization_timestamp: 2,
+            pre_update_average_rate: -3,
+            last_update_timestamp: 4,
+            current_rate: 5,
+        };
+        let mut packed = vec![0; InterestBearingConfig::get_packed_len()];
+        InterestBearingConfig::pack(check, &mut packed).unwrap();
+        let mut expect = vec![1, 0, 0, 0];
+        expect.extend_from_slice(&[1; 32]);
+        expect.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 0]);
+        expect.extend_from_slice(&[253, 255]);
+        expect.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0]);
+        expect.extend_from_slice(&[5, 0]);
+        assert_eq!(packed, expect);
+        let unpacked = InterestBearingConfig::unpack_from_slice(&packed).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_amount_to_ui_amount() {
+        // 5% continuously compounded
+        let config = InterestBearingConfig::new(COption::None, 500, INITIALIZATION_TIMESTAMP);
+        assert_eq!(
+            config.amount_to_ui_amount(1_000_000, 6, INITIALIZATION_TIMESTAMP),
+            Some(1.)
+        );
+        let ui_amount = config
+            .amount_to_ui_amount(1_000_000, 6, INITIALIZATION_TIMESTAMP + ONE_YEAR)
+            .unwrap();
+        assert!((ui_amount - 0.05f64.exp()).abs() < 1e-6);
+        assert_eq!(
+            config.ui_amount_to_amount(ui_amount, 6, INITIALIZATION_TIMESTAMP + ONE_YEAR),
+            Some(1_000_000)
+        );
+
+        // negative rates shrink the UI amount
+        let config = InterestBearingConfig::new(COption::None, -500, INITIALIZATION_TIMESTAMP);
+        let ui_amount = config
+            .amount_to_ui_amount(1_000_000, 6, INITIALIZATION_TIMESTAMP + ONE_YEAR)
+            .unwrap();
+        assert!((ui_amount - (-0.05f64).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_update_rate() {
+        let mut config = InterestBearingConfig::new(COption::None, 100, INITIALIZATION_TIMESTAMP);
+        config
+            .update_rate(300, INITIALIZATION_TIMESTAMP + ONE_YEAR)
+            .unwrap();
+        assert_eq!(config.pre_update_average_rate, 100);
+        assert_eq!(config.current_rate, 300);
+        let ui_amount = config
+            .amount_to_ui_amount(100, 0, INITIALIZATION_TIMESTAMP + 2 * ONE_YEAR)
+            .unwrap();
+        assert!((ui_amount - 100. * 0.04f64.exp()).abs() < 1e-6);
+
+        config
+            .update_rate(0, INITIALIZATION_TIMESTAMP + 2 * ONE_YEAR)
+            .unwrap();
+        assert_eq!(config.pre_update_average_rate, 200);
+        assert_eq!(config.current_rate, 0);
+        let ui_amount = config
+            .amount_to_ui_amount(100, 0, INITIALIZATION_TIMESTAMP + 10 * ONE_YEAR)
+            .unwrap();
+        assert!((ui_amount - 100. * 0.04f64.exp()).abs() < 1e-6);
+
+        // time can't go backwards
+        assert_eq!(config.update_rate(1, INITIALIZATION_TIMESTAMP), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! Memo-required transfers account extension
+
+use crate::{
+    error::TokenError,
+    extension::{Extension, ExtensionType},
+    instruction::TokenInstruction,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    program_pack::{Pack, Sealed},
+    pubkey::Pubkey,
+    sysvar::instructions,
+};
+
+/// Memo-required transfers extension data for token accounts.
+///
+/// While incoming transfer memos are required, any transfer into the account must be a
+/// top-level instruction immediately preceded by a memo instruction in the same transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MemoTransfer {
+    /// Require transfers into this account to be accompanied by a memo
+    pub require_incoming_transfer_memos: bool,
+}
+impl Sealed for MemoTransfer {}
+impl Pack for MemoTransfer {
+    const LEN: usize = 1;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let require_incoming_transfer_memos = match src[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(MemoTransfer {
+            require_incoming_transfer_memos,
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.require_incoming_transfer_memos as u8;
+    }
+}
+impl Extension for MemoTransfer {
+    const TYPE: ExtensionType = ExtensionType::MemoTransfer;
+}
+
+/// Checks that the current instruction is a top-level transfer immediately preceded by a
+/// memo, given the instructions sysvar, which must be the last account of the transfer.
+///
+/// The instructions sysvar only lists top-level instructions, so a memo can't be tied to a
+/// transfer made through a cross-program invocation or inside a `Batch`.  Such transfers are
+/// rejected.
+pub fn check_previous_instruction_is_memo(
+    program_id: &Pubkey,
+    instructions_sysvar_info: &AccountInfo,
+) -> Result<(), ProgramError> {
+    if !instructions::check_id(instructions_sysvar_info.key) {
+        return Err(TokenError::NoMemo.into());
+    }
+    let instructions_data = instructions_sysvar_info.data.borrow();
+    let current_index = instructions::load_current_index(&instructions_data);
+    let current_instruction =
+        instructions::load_instruction_at(current_index as usize, &instructions_data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+    let is_top_level_transfer = current_instruction.program_id == *program_id
+        && matches!(
+            TokenInstruction::unpack(&current_instruction.data),
+            Ok(TokenInstruction::Transfer { .. }) | Ok(TokenInstruction::TransferChecked { .. })
+        );
+    if !is_top_level_transfer {
+        return Err(TokenError::MemoTransferNotTopLevel.into());
+    }
+
+    let previous_index = current_index.checked_sub(1).ok_or(TokenError::NoMemo)?;
+    let previous_instruction =
+        instructions::load_instruction_at(previous_index as usize, &instructions_data)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+    if previous_instruction.program_id == spl_memo::id()
+        || previous_instruction.program_id == spl_memo::v1::id()
+    {
+        Ok(())
+    } else {
+        Err(TokenError::NoMemo.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let check = MemoTransfer {
+            require_incoming_transfer_memos: true,
+        };
+        let mut packed = vec![0; MemoTransfer::get_packed_len()];
+        MemoTransfer::pack(check, &mut packed).unwrap();
+        assert_eq!(packed, vec![1]);
+        let unpacked = MemoTransfer::unpack_from_slice(&packed).unwrap();
+        assert_eq!(unpacked, check);
+        assert_eq!(
+            MemoTransfer::unpack_from_slice(&[2]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -0,0 +1,687 @@
+//! Extensions available to token mints and accounts
+//!
+//! Extended mints and accounts keep the legacy `Mint` and `Account` layouts at the start of their
+//! data. Mints are zero-padded up to `Account::LEN`, and both are followed by a one-byte
+//! [AccountType](enum.AccountType.html) and a type-length-value area holding the extensions:
+//!
+//! ```text
+//! | base state | padding (mints only) | account type | type | length | value | type | ... |
+//! ```
+//!
+//! Mints and accounts with the legacy sizes have no extensions, so existing clients and data
+//! remain valid.
+//!
+//! Unused space at the end of the extension area stays zeroed until an extension is initialized
+//! into it.  Mints must be allocated with room for all of their extensions, using
+//! [get_account_len](enum.ExtensionType.html#method.get_account_len), since those are
+//! initialized before the mint itself.  Token accounts, legacy or extended, can be grown in place
+//! with the `Reallocate` instruction to make room for account extensions such as `MemoTransfer`.
+
+use crate::{
+    error::TokenError,
+    state::{Account, Mint, Multisig},
+};
+use arrayref::{array_mut_ref, array_ref};
+use num_enum::TryFromPrimitive;
+use solana_program::{
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack},
+};
+use std::convert::TryFrom;
+
+pub mod default_account_state;
+pub mod interest_bearing_mint;
+pub mod memo_transfer;
+pub mod non_transferable;
+pub mod permanent_delegate;
+pub mod transfer_fee;
+
+use default_account_state::DefaultAccountState;
+use interest_bearing_mint::InterestBearingConfig;
+use memo_transfer::MemoTransfer;
+use non_transferable::{NonTransferable, NonTransferableAccount};
+use permanent_delegate::PermanentDelegate;
+use transfer_fee::{TransferFeeAmount, TransferFeeConfig};
+
+/// Length of the type and length fields preceding every extension value
+const TLV_HEADER_LEN: usize = 4;
+/// Index of the account type byte, directly after the base `Account` layout
+const ACCOUNT_TYPE_INDEX: usize = Account::LEN;
+/// Index of the first extension entry
+const TLV_START_INDEX: usize = ACCOUNT_TYPE_INDEX + 1;
+
+/// Type of an extended mint or account, stored directly after the base state
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, TryFromPrimitive)]
+pub enum AccountType {
+    /// Marker for 0 data
+    Uninitialized,
+    /// Mint account with additional extensions
+    Mint,
+    /// Token holding account with additional extensions
+    Account,
+}
+
+impl Default for AccountType {
+    fn default() -> Self {
+        AccountType::Uninitialized
+    }
+}
+
+/// Extensions that can be applied to mints or accounts.  Mint extensions must only be
+/// applied to mint accounts, and account extensions must only be applied to token holding
+/// accounts.
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, PartialEq, TryFromPrimitive)]
+pub enum ExtensionType {
+    /// Used as padding if the account size would otherwise be 355, same as a multisig
+    Uninitialized,
+    /// Includes transfer fee rate info and accompanying authorities to withdraw and set the fee
+    TransferFeeConfig,
+    /// Includes withheld transfer fees
+    TransferFeeAmount,
+    /// Includes an interest rate used to display accrued interest in UI amounts
+    InterestBearingConfig,
+    /// Indicates that the tokens of this mint can't be transferred
+    NonTransferable,
+    /// Marks accounts holding tokens of a non-transferable mint
+    NonTransferableAccount,
+    /// Includes a delegate that can transfer or burn tokens from any account of this mint
+    PermanentDelegate,
+    /// Indicates whether incoming transfers to this account must be accompanied by a memo
+    MemoTransfer,
+    /// Includes the state that new accounts of this mint start in
+    DefaultAccountState,
+}
+
+impl ExtensionType {
+    /// Get the packed length of the extension's value
+    pub fn get_type_len(&self) -> usize {
+        match self {
+            ExtensionType::Uninitialized => 0,
+            ExtensionType::TransferFeeConfig => TransferFeeConfig::LEN,
+            ExtensionType::TransferFeeAmount => TransferFeeAmount::LEN,
+            ExtensionType::InterestBearingConfig => InterestBearingConfig::LEN,
+            ExtensionType::NonTransferable => NonTransferable::LEN,
+            ExtensionType::NonTransferableAccount => NonTransferableAccount::LEN,
+            ExtensionType::PermanentDelegate => PermanentDelegate::LEN,
+            ExtensionType::MemoTransfer => MemoTransfer::LEN,
+            ExtensionType::DefaultAccountState => DefaultAccountState::LEN,
+        }
+    }
+
+    /// Get the associated account type
+    pub fn get_account_type(&self) -> AccountType {
+        match self {
+            ExtensionType::Uninitialized => AccountType::Uninitialized,
+            ExtensionType::TransferFeeConfig
+            | ExtensionType::InterestBearingConfig
+            | ExtensionType::NonTransferable
+            | ExtensionType::PermanentDelegate
+            | ExtensionType::DefaultAccountState => AccountType::Mint,
+            ExtensionType::TransferFeeAmount
+            | ExtensionType::NonTransferableAccount
+            | ExtensionType::MemoTransfer => AccountType::Account,
+        }
+    }
+
+    /// Get the data length required for the base state `S` followed by the given extensions
+    pub fn get_account_len<S: BaseState>(extension_types: &[Self]) -> usize {
+        if extension_types.is_empty() {
+            return S::LEN;
+        }
+        let extensions_len = extension_types
+            .iter()
+            .map(|extension_type| TLV_HEADER_LEN + extension_type.get_type_len())
+            .sum::<usize>();
+        let account_len = TLV_START_INDEX + extensions_len;
+        if account_len == Multisig::LEN {
+            // pad so that the owner check in `validate_owner` can never mistake an extended
+            // account for a multisig
+            account_len + TLV_HEADER_LEN
+        } else {
+            account_len
+        }
+    }
+
+    /// Get the account extensions required by a mint with the given extensions
+    pub fn get_required_init_account_extensions(mint_extension_types: &[Self]) -> Vec<Self> {
+        let mut account_extension_types = vec![];
+        for extension_type in mint_extension_types {
+            match extension_type {
+                ExtensionType::TransferFeeConfig => {
+                    account_extension_types.push(ExtensionType::TransferFeeAmount);
+                }
+                ExtensionType::NonTransferable => {
+                    account_extension_types.push(ExtensionType::NonTransferableAccount);
+                }
+                _ => {}
+            }
+        }
+        account_extension_types
+    }
+}
+
+/// Base state of a mint or account, which may be followed by extensions
+pub trait BaseState: Pack + IsInitialized {
+    /// Account type written after the base state of extended accounts
+    const ACCOUNT_TYPE: AccountType;
+}
+impl BaseState for Mint {
+    const ACCOUNT_TYPE: AccountType = AccountType::Mint;
+}
+impl BaseState for Account {
+    const ACCOUNT_TYPE: AccountType = AccountType::Account;
+}
+
+/// Value stored in the extension area of a mint or account
+pub trait Extension: Pack {
+    /// Associated extension type
+    const TYPE: ExtensionType;
+}
+
+/// Read-only view of a mint or account along with its extensions
+#[derive(Debug, PartialEq)]
+pub struct StateWithExtensions<'data, S: BaseState> {
+    /// Unpacked base state
+    pub base: S,
+    tlv_data: &'data [u8],
+}
+impl<'data, S: BaseState> StateWithExtensions<'data, S> {
+    /// Unpacks an initialized base state, along with any extensions
+    pub fn unpack(input: &'data [u8]) -> Result<Self, ProgramError> {
+        check_account_len::<S>(input)?;
+        let (base_data, rest) = input.split_at(S::LEN);
+        let (_account_type, tlv_data) = split_extension_data::<S>(rest, true)?;
+        let base = S::unpack(base_data)?;
+        Ok(Self { base, tlv_data })
+    }
+
+    /// Unpacks the extension of type `V`, if present
+    pub fn get_extension<V: Extension>(&self) -> Result<V, ProgramError> {
+        get_extension::<S, V>(self.tlv_data)
+    }
+
+    /// Lists the types of all initialized extensions
+    pub fn get_extension_types(&self) -> Result<Vec<ExtensionType>, ProgramError> {
+        get_extension_types(self.tlv_data)
+    }
+}
+
+/// Mutable view of a mint or account along with its extensions
+#[derive(Debug, PartialEq)]
+pub struct StateWithExtensionsMut<'data, S: BaseState> {
+    /// Unpacked base state
+    pub base: S,
+    base_data: &'data mut [u8],
+    account_type: &'data mut [u8],
+    tlv_data: &'data mut [u8],
+}
+impl<'data, S: BaseState> StateWithExtensionsMut<'data, S> {
+    /// Unpacks an initialized base state, along with any extensions
+    pub fn unpack(input: &'data mut [u8]) -> Result<Self, ProgramError> {
+        Self::_unpack(input, true)
+    }
+
+    /// Unpacks a base state without checking that it is initialized, for use by the
+    /// initialization instructions
+    pub fn unpack_unchecked(input: &'data mut [u8]) -> Result<Self, ProgramError> {
+        Self::_unpack(input, false)
+    }
+
+    fn _unpack(input: &'data mut [u8], check_initialized: bool) -> Result<Self, ProgramError> {
+        check_account_len::<S>(input)?;
+        let (base_data, rest) = input.split_at_mut(S::LEN);
+        let (account_type, tlv_data) = split_extension_data_mut::<S>(rest, check_initialized)?;
+        let base = if check_initialized {
+            S::unpack(base_data)?
+        } else {
+            S::unpack_unchecked(base_data)?
+        };
+        Ok(Self {
+            base,
+            base_data,
+            account_type,
+            tlv_data,
+        })
+    }
+
+    /// Packs the base state back into the account data, leaving the extensions untouched
+    pub fn pack_base(&mut self) {
+        S::pack_into_slice(&self.base, self.base_data);
+    }
+
+    /// Writes the account type of extended accounts, a no-op for legacy layouts
+    pub fn init_account_type(&mut self) {
+        if let Some(account_type) = self.account_type.first_mut() {
+            *account_type = S::ACCOUNT_TYPE as u8;
+        }
+    }
+
+    /// Unpacks the extension of type `V`, if present
+    pub fn get_extension<V: Extension>(&self) -> Result<V, ProgramError> {
+        get_extension::<S, V>(self.tlv_data)
+    }
+
+    /// Lists the types of all initialized extensions
+    pub fn get_extension_types(&self) -> Result<Vec<ExtensionType>, ProgramError> {
+        get_extension_types(self.tlv_data)
+    }
+
+    /// Writes a new extension into the first unused entry, failing if the extension is
+    /// already present or there is not enough room left in the account
+    pub fn init_extension<V: Extension>(&mut self, extension: &V) -> Result<(), ProgramError> {
+        check_extension_type::<S, V>()?;
+        let start = match find_extension(self.tlv_data, V::TYPE)? {
+            ExtensionLookup::Found(_) => {
+                return Err(TokenError::ExtensionAlreadyInitialized.into());
+            }
+            ExtensionLookup::Free(start) => start,
+        };
+        let value_start = start + TLV_HEADER_LEN;
+        let value_end = value_start + V::LEN;
+        if value_end > self.tlv_data.len() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let header = array_mut_ref![self.tlv_data, start, TLV_HEADER_LEN];
+        header[..2].copy_from_slice(&(V::TYPE as u16).to_le_bytes());
+        header[2..].copy_from_slice(&(V::LEN as u16).to_le_bytes());
+        extension.pack_into_slice(&mut self.tlv_data[value_start..value_end]);
+        self.init_account_type();
+        Ok(())
+    }
+
+    /// Overwrites an existing extension
+    pub fn set_extension<V: Extension>(&mut self, extension: &V) -> Result<(), ProgramError> {
+        check_extension_type::<S, V>()?;
+        match find_extension(self.tlv_data, V::TYPE)? {
+            ExtensionLookup::Found(start) => {
+                let value_start = start + TLV_HEADER_LEN;
+                extension.pack_into_slice(&mut self.tlv_data[value_start..value_start + V::LEN]);
+                Ok(())
+            }
+            ExtensionLookup::Free(_) => Err(TokenError::ExtensionNotFound.into()),
+        }
+    }
+}
+
+/// Determines whether the given data holds a mint or an account, accounting for the legacy
+/// layouts which have no account type byte
+pub fn get_account_type(input: &[u8]) -> Result<AccountType, ProgramError> {
+    match input.len() {
+        Mint::LEN => Ok(AccountType::Mint),
+        Account::LEN => Ok(AccountType::Account),
+        Multisig::LEN => Err(ProgramError::InvalidAccountData),
+        len if len > ACCOUNT_TYPE_INDEX => AccountType::try_from(input[ACCOUNT_TYPE_INDEX])
+            .map_err(|_| ProgramError::InvalidAccountData),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+fn check_account_len<S: BaseState>(input: &[u8]) -> Result<(), ProgramError> {
+    if input.len() == S::LEN || (input.len() > ACCOUNT_TYPE_INDEX && input.len() != Multisig::LEN) {
+        Ok(())
+    } else {
+        Err(ProgramError::InvalidAccountData)
+    }
+}
+
+fn check_extension_type<S: BaseState, V: Extension>() -> Result<(), ProgramError> {
+    if V::TYPE.get_account_type() == S::ACCOUNT_TYPE {
+        Ok(())
+    } else {
+        Err(ProgramError::InvalidAccountData)
+    }
+}
+
+/// Splits the data following the base state into the account type and the extension area,
+/// checking the mint padding and the account type
+fn split_extension_data<S: BaseState>(
+    rest: &[u8],
+    check_initialized: bool,
+) -> Result<(&[u8], &[u8]), ProgramError> {
+    if rest.is_empty() {
+        return Ok((&[], &[]));
+    }
+    let account_type_index = ACCOUNT_TYPE_INDEX - S::LEN;
+    let (padding, rest) = rest.split_at(account_type_index);
+    let (account_type, tlv_data) = rest.split_at(1);
+    check_padding_and_account_type::<S>(padding, account_type[0], check_initialized)?;
+    Ok((account_type, tlv_data))
+}
+
+fn split_extension_data_mut<S: BaseState>(
+    rest: &mut [u8],
+    check_initialized: bool,
+) -> Result<(&mut [u8], &mut [u8]), ProgramError> {
+    if rest.is_empty() {
+        return Ok((&mut [], &mut []));
+    }
+    let account_type_index = ACCOUNT_TYPE_INDEX - S::LEN;
+    let (padding, rest) = rest.split_at_mut(account_type_index);
+    let (account_type, tlv_data) = rest.split_at_mut(1);
+    check_padding_and_account_type::<S>(padding, account_type[0], check_initialized)?;
+    Ok((account_type, tlv_data))
+}
+
+fn check_padding_and_account_type<S: BaseState>(
+    padding: &[u8],
+    account_type: u8,
+    check_initialized: bool,
+) -> Result<(), ProgramError> {
+    if padding.iter().any(|&byte| byte != 0) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let account_type =
+        AccountType::try_from(account_type).map_err(|_| ProgramError::InvalidAccountData)?;
+    if account_type == S::ACCOUNT_TYPE {
+        Ok(())
+    } else if account_type == AccountType::Uninitialized {
+        if check_initialized {
+            Err(ProgramError::UninitializedAccount)
+        } else {
+            Ok(())
+        }
+    } else {
+        Err(ProgramError::InvalidAccountData)
+    }
+}
+
+enum ExtensionLookup {
+    /// Start of the matching entry
+    Found(usize),
+    /// Start of the first unused entry
+    Free(usize),
+}
+
+fn find_extension(
+    tlv_data: &[u8],
+    extension_type: ExtensionType,
+) -> Result<ExtensionLookup, ProgramError> {
+    let mut start = 0;
+    while start + TLV_HEADER_LEN <= tlv_data.len() {
+        let header = array_ref![tlv_data, start, TLV_HEADER_LEN];
+        let entry_type = u16::from_le_bytes([header[0], header[1]]);
+        let entry_type =
+            ExtensionType::try_from(entry_type).map_err(|_| ProgramError::InvalidAccountData)?;
+        if entry_type == ExtensionType::Uninitialized {
+            break;
+        }
+        if entry_type == extension_type {
+            return Ok(ExtensionLookup::Found(start));
+        }
+        let entry_len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        start = start
+            .checked_add(TLV_HEADER_LEN + entry_len)
+            .ok_or(ProgramError::InvalidAccountData)?;
+    }
+    Ok(ExtensionLookup::Free(start))
+}
+
+fn get_extension<S: BaseState, V: Extension>(tlv_data: &[u8]) -> Result<V, ProgramError> {
+    check_extension_type::<S, V>()?;
+    match find_extension(tlv_data, V::TYPE)? {
+        ExtensionLookup::Found(start) => {
+            let value_start = start + TLV_HEADER_LEN;
+            let value = tlv_data
+                .get(value_start..value_start + V::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?;
+            V::unpack_from_slice(value)
+        }
+        ExtensionLookup::Free(_) => Err(TokenError::ExtensionNotFound.into()),
+    }
+}
+
+fn get_extension_types(tlv_data: &[u8]) -> Result<Vec<ExtensionType>, ProgramError> {
+    let mut extension_types = vec![];
+    let mut start = 0;
+    while start + TLV_HEADER_LEN <= tlv_data.len() {
+        let header = array_ref![tlv_data, start, TLV_HEADER_LEN];
+        let entry_type = u16::from_le_bytes([header[0], header[1]]);
+        let entry_type =
+            ExtensionType::try_from(entry_type).map_err(|_| ProgramError::InvalidAccountData)?;
+        if entry_type == ExtensionType::Uninitialized {
+            break;
+        }
+        extension_types.push(entry_type);
+        let entry_len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        start = start
+            .checked_add(TLV_HEADER_LEN + entry_len)
+            .ok_or(ProgramError::InvalidAccountData)?;
+    }
+    Ok(extension_types)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::{program_option::COption, pubkey::Pubkey};
+
+    fn test_mint() -> Mint {
+        Mint {
+            mint_authority: COption::Some(Pubkey::new(&[1; 32])),
+            supply: 42,
+            decimals: 7,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+    }
+
+    fn test_transfer_fee_config() -> TransferFeeConfig {
+        TransferFeeConfig {
+            transfer_fee_config_authority: COption::Some(Pubkey::new(&[2; 32])),
+            withdraw_withheld_authority: COption::None,
+            transfer_fee_basis_points: 10,
+            maximum_fee: 5_000,
+            withheld_amount: 0,
+        }
+    }
+
+    #[test]
+    fn test_get_account_len() {
+        assert_eq!(ExtensionType::get_account_len::<Mint>(&[]), Mint::LEN);
+        assert_eq!(ExtensionType::get_account_len::<Account>(&[]), Account::LEN);
+        assert_eq!(
+            ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]),
+            Account::LEN + 1 + TLV_HEADER_LEN + TransferFeeConfig::LEN
+        );
+        assert_eq!(
+            ExtensionType::get_account_len::<Account>(&[ExtensionType::TransferFeeAmount]),
+            Account::LEN + 1 + TLV_HEADER_LEN + TransferFeeAmount::LEN
+        );
+        assert_eq!(
+            ExtensionType::get_required_init_account_extensions(&[
+                ExtensionType::TransferFeeConfig
+            ]),
+            vec![ExtensionType::TransferFeeAmount]
+        );
+    }
+
+    #[test]
+    fn test_legacy_layouts() {
+        let mut buffer = vec![0; Mint::LEN];
+        Mint::pack(test_mint(), &mut buffer).unwrap();
+        let state = StateWithExtensions::<Mint>::unpack(&buffer).unwrap();
+        assert_eq!(state.base, test_mint());
+        assert_eq!(state.get_extension_types().unwrap(), vec![]);
+        assert_eq!(
+            state.get_extension::<TransferFeeConfig>(),
+            Err(TokenError::ExtensionNotFound.into())
+        );
+        assert_eq!(get_account_type(&buffer).unwrap(), AccountType::Mint);
+
+        // legacy accounts have no room for extensions
+        let mut state = StateWithExtensionsMut::<Mint>::unpack(&mut buffer).unwrap();
+        assert_eq!(
+            state.init_extension(&test_transfer_fee_config()),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // multisig-sized and truncated data are rejected
+        let buffer = vec![0; Multisig::LEN];
+        assert_eq!(
+            StateWithExtensions::<Mint>::unpack(&buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+        let buffer = vec![0; Mint::LEN + 1];
+        assert_eq!(
+            StateWithExtensions::<Mint>::unpack(&buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_mint_with_extensions() {
+        let len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]);
+        let mut buffer = vec![0; len];
+
+        // uninitialized mints can only be unpacked unchecked
+        assert_eq!(
+            StateWithExtensionsMut::<Mint>::unpack(&mut buffer),
+            Err(ProgramError::UninitializedAccount)
+        );
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut buffer).unwrap();
+        assert_eq!(
+            state.set_extension(&test_transfer_fee_config()),
+            Err(TokenError::ExtensionNotFound.into())
+        );
+        state.init_extension(&test_transfer_fee_config()).unwrap();
+        assert_eq!(
+            state.init_extension(&test_transfer_fee_config()),
+            Err(TokenError::ExtensionAlreadyInitialized.into())
+        );
+        // account extensions can't be written to mints
+        assert_eq!(
+            state.init_extension(&TransferFeeAmount { withheld_amount: 1 }),
+            Err(ProgramError::InvalidAccountData)
+        );
+        state.base = test_mint();
+        state.pack_base();
+
+        let mut expect = vec![0; Mint::LEN];
+        Mint::pack(test_mint(), &mut expect).unwrap();
+        expect.extend_from_slice(&[0; Account::LEN - Mint::LEN]);
+        expect.push(AccountType::Mint as u8);
+        expect.extend_from_slice(&(ExtensionType::TransferFeeConfig as u16).to_le_bytes());
+        expect.extend_from_slice(&(TransferFeeConfig::LEN as u16).to_le_bytes());
+        let mut value = vec![0; TransferFeeConfig::LEN];
+        test_transfer_fee_config().pack_into_slice(&mut value);
+        expect.extend_from_slice(&value);
+        assert_eq!(buffer, expect);
+        assert_eq!(get_account_type(&buffer).unwrap(), AccountType::Mint);
+
+        let state = StateWithExtensions::<Mint>::unpack(&buffer).unwrap();
+        assert_eq!(state.base, test_mint());
+        assert_eq!(
+            state.get_extension::<TransferFeeConfig>().unwrap(),
+            test_transfer_fee_config()
+        );
+        assert_eq!(
+            state.get_extension_types().unwrap(),
+            vec![ExtensionType::TransferFeeConfig]
+        );
+
+        // an extended mint is not an account
+        assert_eq!(
+            StateWithExtensions::<Account>::unpack(&buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // mint padding must stay zeroed
+        let mut bad_buffer = buffer.clone();
+        bad_buffer[Mint::LEN] = 1;
+        assert_eq!(
+            StateWithExtensions::<Mint>::unpack(&bad_buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // update in place
+        let mut state = StateWithExtensionsMut::<Mint>::unpack(&mut buffer).unwrap();
+        let mut config = state.get_extension::<TransferFeeConfig>().unwrap();
+        config.maximum_fee = 1;
+        state.set_extension(&config).unwrap();
+        let state = StateWithExtensions::<Mint>::unpack(&buffer).unwrap();
+        assert_eq!(state.get_extension::<TransferFeeConfig>().unwrap(), config);
+    }
+
+    #[test]
+    fn test_account_with_several_extensions() {
+        let len = ExtensionType::get_account_len::<Account>(&[
+            ExtensionType::TransferFeeAmount,
+            ExtensionType::MemoTransfer,
+        ]);
+        assert_eq!(
+            len,
+            Account::LEN
+                + 1
+                + TLV_HEADER_LEN
+                + TransferFeeAmount::LEN
+                + TLV_HEADER_LEN
+                + MemoTransfer::LEN
+        );
+        let mut buffer = vec![0; len];
+        Account::pack(Account::default(), &mut buffer[..Account::LEN]).unwrap();
+        let mut state = StateWithExtensionsMut::<Account>::unpack_unchecked(&mut buffer).unwrap();
+        state
+            .init_extension(&TransferFeeAmount { withheld_amount: 7 })
+            .unwrap();
+        state
+            .init_extension(&MemoTransfer {
+                require_incoming_transfer_memos: true,
+            })
+            .unwrap();
+        assert_eq!(
+            state.init_extension(&NonTransferableAccount),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            state.get_extension_types().unwrap(),
+            vec![
+                ExtensionType::TransferFeeAmount,
+                ExtensionType::MemoTransfer
+            ]
+        );
+
+        let mut expect = vec![0; Account::LEN];
+        Account::pack(Account::default(), &mut expect).unwrap();
+        expect.push(AccountType::Account as u8);
+        expect.extend_from_slice(&(ExtensionType::TransferFeeAmount as u16).to_le_bytes());
+        expect.extend_from_slice(&(TransferFeeAmount::LEN as u16).to_le_bytes());
+        expect.extend_from_slice(&7u64.to_le_bytes());
+        expect.extend_from_slice(&(ExtensionType::MemoTransfer as u16).to_le_bytes());
+        expect.extend_from_slice(&(MemoTransfer::LEN as u16).to_le_bytes());
+        expect.push(1);
+        assert_eq!(buffer, expect);
+
+        // later extensions are found past earlier ones, and updated in place
+        let mut state = StateWithExtensionsMut::<Account>::unpack_unchecked(&mut buffer).unwrap();
+        state
+            .set_extension(&MemoTransfer {
+                require_incoming_transfer_memos: false,
+            })
+            .unwrap();
+        assert_eq!(
+            state.get_extension::<TransferFeeAmount>().unwrap(),
+            TransferFeeAmount { withheld_amount: 7 }
+        );
+        assert_eq!(
+            state.get_extension::<MemoTransfer>().unwrap(),
+            MemoTransfer {
+                require_incoming_transfer_memos: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_multisig_len_rejected() {
+        let mut buffer = vec![0; Multisig::LEN];
+        assert_eq!(
+            StateWithExtensionsMut::<Account>::unpack_unchecked(&mut buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            get_account_type(&buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Non-transferable mint extension
+
+use crate::extension::{Extension, ExtensionType};
+use solana_program::{
+    program_error::ProgramError,
+    program_pack::{Pack, Sealed},
+};
+
+/// Indicates that the tokens of a mint can never be transferred between accounts.  Tokens can
+/// still be minted and burned, and empty accounts closed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NonTransferable;
+impl Sealed for NonTransferable {}
+impl Pack for NonTransferable {
+    const LEN: usize = 0;
+    fn unpack_from_slice(_src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(NonTransferable)
+    }
+    fn pack_into_slice(&self, _dst: &mut [u8]) {}
+}
+impl Extension for NonTransferable {
+    const TYPE: ExtensionType = ExtensionType::NonTransferable;
+}
+
+/// Marks accounts of a non-transferable mint, so that plain `Transfer` instructions can be
+/// rejected without the mint
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NonTransferableAccount;
+impl Sealed for NonTransferableAccount {}
+impl Pack for NonTransferableAccount {
+    const LEN: usize = 0;
+    fn unpack_from_slice(_src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(NonTransferableAccount)
+    }
+    fn pack_into_slice(&self, _dst: &mut [u8]) {}
+}
+impl Extension for NonTransferableAccount {
+    const TYPE: ExtensionType = ExtensionType::NonTransferableAccount;
+}
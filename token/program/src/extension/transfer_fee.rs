@@ -0,0 +1,188 @@
+//! Transfer fee extension
+
+use crate::{
+    extension::{Extension, ExtensionType},
+    state::{pack_coption_key, unpack_coption_key},
+};
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::{Pack, Sealed},
+    pubkey::Pubkey,
+};
+use std::{cmp, convert::TryFrom};
+
+/// Maximum possible fee in basis points is 100%, aka 10_000 basis points
+pub const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+const ONE_IN_BASIS_POINTS: u128 = MAX_FEE_BASIS_POINTS as u128;
+
+/// Transfer fee extension data for mints.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferFeeConfig {
+    /// Optional authority to set the fee
+    pub transfer_fee_config_authority: COption<Pubkey>,
+    /// Optional authority to withdraw withheld fees from token accounts
+    pub withdraw_withheld_authority: COption<Pubkey>,
+    /// Amount of a transfer withheld as a fee, expressed as basis points of the transfer amount
+    pub transfer_fee_basis_points: u16,
+    /// Maximum fee withheld from a single transfer, in raw token amount
+    pub maximum_fee: u64,
+    /// Withheld fees harvested from token accounts, to be withdrawn by the withdraw withheld
+    /// authority
+    pub withheld_amount: u64,
+}
+impl TransferFeeConfig {
+    /// Calculates the fee withheld from a transfer of `amount`, rounding up so that small
+    /// transfers can't avoid the fee.  Returns `None` on overflow.
+    pub fn calculate_fee(&self, amount: u64) -> Option<u64> {
+        if self.transfer_fee_basis_points == 0 || amount == 0 {
+            return Some(0);
+        }
+        let numerator = (amount as u128).checked_mul(self.transfer_fee_basis_points as u128)?;
+        let raw_fee = numerator
+            .checked_add(ONE_IN_BASIS_POINTS)?
+            .checked_sub(1)?
+            .checked_div(ONE_IN_BASIS_POINTS)?;
+        let raw_fee = u64::try_from(raw_fee).ok()?;
+        Some(cmp::min(raw_fee, self.maximum_fee))
+    }
+}
+impl Sealed for TransferFeeConfig {}
+impl Pack for TransferFeeConfig {
+    const LEN: usize = 90;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 90];
+        let (
+            transfer_fee_config_authority,
+            withdraw_withheld_authority,
+            transfer_fee_basis_points,
+            maximum_fee,
+            withheld_amount,
+        ) = array_refs![src, 36, 36, 2, 8, 8];
+        Ok(TransferFeeConfig {
+            transfer_fee_config_authority: unpack_coption_key(transfer_fee_config_authority)?,
+            withdraw_withheld_authority: unpack_coption_key(withdraw_withheld_authority)?,
+            transfer_fee_basis_points: u16::from_le_bytes(*transfer_fee_basis_points),
+            maximum_fee: u64::from_le_bytes(*maximum_fee),
+            withheld_amount: u64::from_le_bytes(*withheld_amount),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 90];
+        let (
+            transfer_fee_config_authority_dst,
+            withdraw_withheld_authority_dst,
+            transfer_fee_basis_points_dst,
+            maximum_fee_dst,
+            withheld_amount_dst,
+        ) = mut_array_refs![dst, 36, 36, 2, 8, 8];
+        let &TransferFeeConfig {
+            ref transfer_fee_config_authority,
+            ref withdraw_withheld_authority,
+            transfer_fee_basis_points,
+            maximum_fee,
+            withheld_amount,
+        } = self;
+        pack_coption_key(
+            transfer_fee_config_authority,
+            transfer_fee_config_authority_dst,
+        );
+        pack_coption_key(withdraw_withheld_authority, withdraw_withheld_authority_dst);
+        *transfer_fee_basis_points_dst = transfer_fee_basis_points.to_le_bytes();
+        *maximum_fee_dst = maximum_fee.to_le_bytes();
+        *withheld_amount_dst = withheld_amount.to_le_bytes();
+    }
+}
+impl Extension for TransferFeeConfig {
+    const TYPE: ExtensionType = ExtensionType::TransferFeeConfig;
+}
+
+/// Transfer fee extension data for accounts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferFeeAmount {
+    /// Amount withheld during transfers, to be harvested to the mint or withdrawn by the
+    /// withdraw withheld authority
+    pub withheld_amount: u64,
+}
+impl Sealed for TransferFeeAmount {}
+impl Pack for TransferFeeAmount {
+    const LEN: usize = 8;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 8];
+        Ok(TransferFeeAmount {
+            withheld_amount: u64::from_le_bytes(*src),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 8];
+        *dst = self.withheld_amount.to_le_bytes();
+    }
+}
+impl Extension for TransferFeeAmount {
+    const TYPE: ExtensionType = ExtensionType::TransferFeeAmount;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let check = TransferFeeConfig {
+            transfer_fee_config_authority: COption::Some(Pubkey::new(&[1; 32])),
+            withdraw_withheld_authority: COption::None,
+            transfer_fee_basis_points: 258,
+            maximum_fee: 3,
+            withheld_amount: 4,
+        };
+        let mut packed = vec![0; TransferFeeConfig::get_packed_len()];
+        TransferFeeConfig::pack(check, &mut packed).unwrap();
+        let mut expect = vec![1, 0, 0, 0];
+        expect.extend_from_slice(&[1; 32]);
+        expect.extend_from_slice(&[0; 36]);
+        expect.extend_from_slice(&[2, 1]);
+        expect.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0]);
+        expect.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(packed, expect);
+        let unpacked = TransferFeeConfig::unpack_from_slice(&packed).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TransferFeeAmount { withheld_amount: 4 };
+        let mut packed = vec![0; TransferFeeAmount::get_packed_len()];
+        TransferFeeAmount::pack(check, &mut packed).unwrap();
+        assert_eq!(packed, vec![4, 0, 0, 0, 0, 0, 0, 0]);
+        let unpacked = TransferFeeAmount::unpack_from_slice(&packed).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_calculate_fee() {
+        let mut config = TransferFeeConfig {
+            transfer_fee_basis_points: 0,
+            maximum_fee: u64::MAX,
+            ..TransferFeeConfig::default()
+        };
+        assert_eq!(config.calculate_fee(u64::MAX), Some(0));
+
+        // 1% fee
+        config.transfer_fee_basis_points = 100;
+        assert_eq!(config.calculate_fee(0), Some(0));
+        assert_eq!(config.calculate_fee(100), Some(1));
+        // rounds up
+        assert_eq!(config.calculate_fee(1), Some(1));
+        assert_eq!(config.calculate_fee(101), Some(2));
+        assert_eq!(config.calculate_fee(u64::MAX), Some(u64::MAX / 100 + 1));
+
+        // capped by the maximum fee
+        config.maximum_fee = 5;
+        assert_eq!(config.calculate_fee(10_000), Some(5));
+
+        // 100% fee
+        config.transfer_fee_basis_points = MAX_FEE_BASIS_POINTS;
+        config.maximum_fee = u64::MAX;
+        assert_eq!(config.calculate_fee(u64::MAX), Some(u64::MAX));
+    }
+}
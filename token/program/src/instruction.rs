@@ -1,14 +1,17 @@
 //! Instruction types
 
-use crate::{check_program_account, error::TokenError};
+use crate::{
+    check_program_account, error::TokenError, extension::ExtensionType, state::AccountState,
+};
+use num_enum::TryFromPrimitive;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     program_option::COption,
     pubkey::Pubkey,
-    sysvar,
+    system_program, sysvar,
 };
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::mem::size_of;
 
 /// Minimum number of multisignature signers (min N)
@@ -102,6 +105,9 @@ pub enum TokenInstruction {
     ///   1. `[writable]` The destination account.
     ///   2. `[]` The source account's multisignature owner/delegate.
     ///   3. ..3+M `[signer]` M signer accounts.
+    ///
+    ///   If the destination requires incoming transfer memos, the instructions sysvar must be
+    ///   appended as the last account, after any signer accounts.
     Transfer {
         /// The amount of tokens to transfer.
         amount: u64,
@@ -265,6 +271,9 @@ pub enum TokenInstruction {
     ///   2. `[writable]` The destination account.
     ///   3. `[]` The source account's multisignature owner/delegate.
     ///   4. ..4+M `[signer]` M signer accounts.
+    ///
+    ///   If the destination requires incoming transfer memos, the instructions sysvar must be
+    ///   appended as the last account, after any signer accounts.
     TransferChecked {
         /// The amount of tokens to transfer.
         amount: u64,
@@ -409,7 +418,248 @@ pub enum TokenInstruction {
         /// The freeze authority/multisignature of the mint.
         freeze_authority: COption<Pubkey>,
     },
+    /// Initialize the transfer fee on a new mint.  The mint account must be large enough to
+    /// hold the `TransferFeeConfig` extension.
+    ///
+    /// Fails if the mint has already been initialized, so must be called before
+    /// `InitializeMint`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeTransferFeeConfig {
+        /// Pubkey that may update the fees
+        transfer_fee_config_authority: COption<Pubkey>,
+        /// Withdraw instructions must be signed by this key
+        withdraw_withheld_authority: COption<Pubkey>,
+        /// Amount of transfer collected as fees, expressed as basis points of the
+        /// transfer amount
+        transfer_fee_basis_points: u16,
+        /// Maximum fee assessed on transfers
+        maximum_fee: u64,
+    },
+    /// Set the transfer fee.  Only supported for mints that include the `TransferFeeConfig`
+    /// extension.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[signer]` The mint's fee account owner.
+    ///
+    ///   * Multisignature authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[]` The mint's multisignature fee account owner.
+    ///   2. ..2+M `[signer]` M signer accounts.
+    SetTransferFee {
+        /// Amount of transfer collected as fees, expressed as basis points of the
+        /// transfer amount
+        transfer_fee_basis_points: u16,
+        /// Maximum fee assessed on transfers
+        maximum_fee: u64,
+    },
+    /// Transfer all withheld tokens to an account.  Signed by the mint's withdraw withheld
+    /// tokens authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner/delegate
+    ///   0. `[]` The token mint.
+    ///   1. `[writable]` The fee receiver account.  Must include the `TransferFeeAmount`
+    ///      extension associated with the provided mint.
+    ///   2. `[signer]` The mint's `withdraw_withheld_authority`.
+    ///   3. ..3+N `[writable]` The source accounts to withdraw from.
+    ///
+    ///   * Multisignature owner/delegate
+    ///   0. `[]` The token mint.
+    ///   1. `[writable]` The destination account.
+    ///   2. `[]` The mint's multisig `withdraw_withheld_authority`.
+    ///   3. ..3+M `[signer]` M signer accounts.
+    ///   3+M. ..3+M+N `[writable]` The source accounts to withdraw from.
+    WithdrawWithheldTokensFromAccounts {
+        /// Number of token accounts harvested
+        num_token_accounts: u8,
+    },
+    /// Initialize a new mint that accrues interest on its UI amounts.  The mint account must
+    /// be large enough to hold the `InterestBearingConfig` extension.
+    ///
+    /// Fails if the mint has already been initialized, so must be called before
+    /// `InitializeMint`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeInterestBearingMint {
+        /// The public key for the account that can update the rate
+        rate_authority: COption<Pubkey>,
+        /// The initial interest rate, in basis points
+        rate: i16,
+    },
+    /// Update the interest rate.  Only supported for mints that include the
+    /// `InterestBearingConfig` extension.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[signer]` The mint's rate authority.
+    ///
+    ///   * Multisignature authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[]` The mint's multisignature rate authority.
+    ///   2. ..2+M `[signer]` M signer accounts.
+    UpdateInterestRate {
+        /// The new interest rate, in basis points
+        rate: i16,
+    },
+    /// Initialize a new mint whose tokens can never be transferred, for example to represent
+    /// credentials.  Tokens can still be minted and burned, and empty accounts closed.  The
+    /// mint account must be large enough to hold the `NonTransferable` extension.
+    ///
+    /// Fails if the mint has already been initialized, so must be called before
+    /// `InitializeMint`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeNonTransferableMint,
+    /// Initialize the permanent delegate of a new mint.  The permanent delegate may transfer or
+    /// burn tokens from any account of the mint without the owner's approval, and must use
+    /// `TransferChecked` and `Burn` or `BurnChecked` to do so.  The mint account must be large
+    /// enough to hold the `PermanentDelegate` extension.
+    ///
+    /// Fails if the mint has already been initialized, so must be called before
+    /// `InitializeMint`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializePermanentDelegate {
+        /// Authority that may transfer or burn any tokens of the mint
+        delegate: Pubkey,
+    },
+    /// Require memos for transfers into this account.  The account must be large enough to
+    /// hold the `MemoTransfer` extension, which `Reallocate` makes room for.
+    ///
+    /// While memos are required, `Transfer` and `TransferChecked` into the account fail unless
+    /// they are immediately preceded by a memo instruction, and the instructions sysvar is
+    /// appended as their last account.  Transfers into the account through a cross-program
+    /// invocation or inside a `Batch` always fail, since their memo can't be checked.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The account to update.
+    ///   1. `[signer]` The account's owner.
+    ///
+    ///   * Multisignature owner
+    ///   0. `[writable]` The account to update.
+    ///   1. `[]` The account's multisignature owner.
+    ///   2. ..2+M `[signer]` M signer accounts.
+    EnableRequiredTransferMemos,
+    /// Stop requiring memos for transfers into this account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The account to update.
+    ///   1. `[signer]` The account's owner.
+    ///
+    ///   * Multisignature owner
+    ///   0. `[writable]` The account to update.
+    ///   1. `[]` The account's multisignature owner.
+    ///   2. ..2+M `[signer]` M signer accounts.
+    DisableRequiredTransferMemos,
+    /// Initialize the state that every new account of a mint starts in.  The mint account
+    /// must be large enough to hold the `DefaultAccountState` extension.
+    ///
+    /// Accounts that start `Frozen` must be thawed by the freeze authority before they can be
+    /// used, so `InitializeMint` fails if the mint has no freeze authority.
+    ///
+    /// Fails if the mint has already been initialized, so must be called before
+    /// `InitializeMint`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeDefaultAccountState {
+        /// Default state of new accounts
+        state: AccountState,
+    },
+    /// Grow a token account in place to make room for the given account extensions, for
+    /// example to require memos on an existing legacy account.  Extensions already present are
+    /// kept, and nothing is done if the account is already large enough.
+    ///
+    /// The payer funds any additional lamports the account needs to remain rent-exempt at its
+    /// new size, through the system program.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The account to reallocate.
+    ///   1. `[writable, signer]` The payer of the additional rent.
+    ///   2. `[]` The system program.
+    ///   3. `[signer]` The account's owner.
+    ///
+    ///   * Multisignature owner
+    ///   0. `[writable]` The account to reallocate.
+    ///   1. `[writable, signer]` The payer of the additional rent.
+    ///   2. `[]` The system program.
+    ///   3. `[]` The account's multisignature owner.
+    ///   4. ..4+M `[signer]` M signer accounts.
+    Reallocate {
+        /// The account extensions to make room for
+        extension_types: Vec<ExtensionType>,
+    },
+    /// Process several instructions in a single call, for example to initialize, fund and
+    /// close many accounts at once.  Each sub-instruction consumes the given number of
+    /// accounts, in order, from the accounts of the batch.  Batches can't be nested.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. ..N The accounts expected by every sub-instruction, in order.
+    Batch {
+        /// The sub-instructions to process
+        instructions: Vec<BatchedInstruction>,
+    },
+    /// Move the withheld tokens of token accounts into their mint, so that the accounts can be
+    /// closed even if the mint has no withdraw withheld tokens authority.  Anyone may harvest.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The token mint.  Must include the `TransferFeeConfig` extension.
+    ///   1. ..1+N `[writable]` The source accounts to harvest from.
+    HarvestWithheldTokensToMint,
+    /// Transfer all withheld tokens harvested into the mint to an account.  Signed by the
+    /// mint's withdraw withheld tokens authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner/delegate
+    ///   0. `[writable]` The token mint.  Must include the `TransferFeeConfig` extension.
+    ///   1. `[writable]` The fee receiver account.  Must include the `TransferFeeAmount`
+    ///      extension associated with the provided mint.
+    ///   2. `[signer]` The mint's `withdraw_withheld_authority`.
+    ///
+    ///   * Multisignature owner/delegate
+    ///   0. `[writable]` The token mint.
+    ///   1. `[writable]` The destination account.
+    ///   2. `[]` The mint's multisig `withdraw_withheld_authority`.
+    ///   3. ..3+M `[signer]` M signer accounts.
+    WithdrawWithheldTokensFromMint,
+}
+
+/// A sub-instruction of a [Batch](enum.TokenInstruction.html#variant.Batch), along with the
+/// number of accounts it consumes
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchedInstruction {
+    /// Number of accounts consumed by the sub-instruction
+    pub num_accounts: u8,
+    /// The sub-instruction
+    pub instruction: TokenInstruction,
 }
+
 impl TokenInstruction {
     /// Unpacks a byte buffer into a [TokenInstruction](enum.TokenInstruction.html).
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
@@ -529,6 +779,92 @@ impl TokenInstruction {
                     decimals,
                 }
             }
+            21 => {
+                let (transfer_fee_config_authority, rest) = Self::unpack_pubkey_option(rest)?;
+                let (withdraw_withheld_authority, rest) = Self::unpack_pubkey_option(rest)?;
+                let (transfer_fee_basis_points, rest) = Self::unpack_u16(rest)?;
+                let (maximum_fee, _rest) = Self::unpack_u64(rest)?;
+                Self::InitializeTransferFeeConfig {
+                    transfer_fee_config_authority,
+                    withdraw_withheld_authority,
+                    transfer_fee_basis_points,
+                    maximum_fee,
+                }
+            }
+            22 => {
+                let (transfer_fee_basis_points, rest) = Self::unpack_u16(rest)?;
+                let (maximum_fee, _rest) = Self::unpack_u64(rest)?;
+                Self::SetTransferFee {
+                    transfer_fee_basis_points,
+                    maximum_fee,
+                }
+            }
+            23 => {
+                let &num_token_accounts = rest.get(0).ok_or(InvalidInstruction)?;
+                Self::WithdrawWithheldTokensFromAccounts { num_token_accounts }
+            }
+            24 => {
+                let (rate_authority, rest) = Self::unpack_pubkey_option(rest)?;
+                let (rate, _rest) = Self::unpack_i16(rest)?;
+                Self::InitializeInterestBearingMint {
+                    rate_authority,
+                    rate,
+                }
+            }
+            25 => {
+                let (rate, _rest) = Self::unpack_i16(rest)?;
+                Self::UpdateInterestRate { rate }
+            }
+            26 => Self::InitializeNonTransferableMint,
+            27 => {
+                let (delegate, _rest) = Self::unpack_pubkey(rest)?;
+                Self::InitializePermanentDelegate { delegate }
+            }
+            28 => Self::EnableRequiredTransferMemos,
+            29 => Self::DisableRequiredTransferMemos,
+            30 => {
+                let &state = rest.get(0).ok_or(InvalidInstruction)?;
+                let state = AccountState::try_from_primitive(state).or(Err(InvalidInstruction))?;
+                Self::InitializeDefaultAccountState { state }
+            }
+            31 => {
+                if rest.len() % 2 != 0 {
+                    return Err(InvalidInstruction.into());
+                }
+                let extension_types = rest
+                    .chunks(2)
+                    .map(|chunk| {
+                        ExtensionType::try_from(u16::from_le_bytes([chunk[0], chunk[1]]))
+                            .or(Err(InvalidInstruction))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Self::Reallocate { extension_types }
+            }
+            32 => {
+                let (&num_instructions, mut rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                let mut instructions = Vec::with_capacity(num_instructions as usize);
+                for _ in 0..num_instructions {
+                    let (&num_accounts, remaining) =
+                        rest.split_first().ok_or(InvalidInstruction)?;
+                    let (data_len, remaining) = Self::unpack_u16(remaining)?;
+                    if remaining.len() < data_len as usize {
+                        return Err(InvalidInstruction.into());
+                    }
+                    let (data, remaining) = remaining.split_at(data_len as usize);
+                    let instruction = Self::unpack(data)?;
+                    if let Self::Batch { .. } = instruction {
+                        return Err(TokenError::NestedBatch.into());
+                    }
+                    instructions.push(BatchedInstruction {
+                        num_accounts,
+                        instruction,
+                    });
+                    rest = remaining;
+                }
+                Self::Batch { instructions }
+            }
+            33 => Self::HarvestWithheldTokensToMint,
+            34 => Self::WithdrawWithheldTokensFromMint,
             _ => return Err(TokenError::InvalidInstruction.into()),
         })
     }
@@ -625,6 +961,81 @@ impl TokenInstruction {
                 buf.extend_from_slice(mint_authority.as_ref());
                 Self::pack_pubkey_option(freeze_authority, &mut buf);
             }
+            &Self::InitializeTransferFeeConfig {
+                ref transfer_fee_config_authority,
+                ref withdraw_withheld_authority,
+                transfer_fee_basis_points,
+                maximum_fee,
+            } => {
+                buf.push(21);
+                Self::pack_pubkey_option(transfer_fee_config_authority, &mut buf);
+                Self::pack_pubkey_option(withdraw_withheld_authority, &mut buf);
+                buf.extend_from_slice(&transfer_fee_basis_points.to_le_bytes());
+                buf.extend_from_slice(&maximum_fee.to_le_bytes());
+            }
+            &Self::SetTransferFee {
+                transfer_fee_basis_points,
+                maximum_fee,
+            } => {
+                buf.push(22);
+                buf.extend_from_slice(&transfer_fee_basis_points.to_le_bytes());
+                buf.extend_from_slice(&maximum_fee.to_le_bytes());
+            }
+            &Self::WithdrawWithheldTokensFromAccounts { num_token_accounts } => {
+                buf.push(23);
+                buf.push(num_token_accounts);
+            }
+            &Self::InitializeInterestBearingMint {
+                ref rate_authority,
+                rate,
+            } => {
+                buf.push(24);
+                Self::pack_pubkey_option(rate_authority, &mut buf);
+                buf.extend_from_slice(&rate.to_le_bytes());
+            }
+            &Self::UpdateInterestRate { rate } => {
+                buf.push(25);
+                buf.extend_from_slice(&rate.to_le_bytes());
+            }
+            &Self::InitializeNonTransferableMint => {
+                buf.push(26);
+            }
+            &Self::InitializePermanentDelegate { delegate } => {
+                buf.push(27);
+                buf.extend_from_slice(delegate.as_ref());
+            }
+            &Self::EnableRequiredTransferMemos => {
+                buf.push(28);
+            }
+            &Self::DisableRequiredTransferMemos => {
+                buf.push(29);
+            }
+            &Self::InitializeDefaultAccountState { state } => {
+                buf.push(30);
+                buf.push(state as u8);
+            }
+            Self::Reallocate { extension_types } => {
+                buf.push(31);
+                for extension_type in extension_types {
+                    buf.extend_from_slice(&(*extension_type as u16).to_le_bytes());
+                }
+            }
+            Self::Batch { instructions } => {
+                buf.push(32);
+                buf.push(instructions.len() as u8);
+                for batched_instruction in instructions {
+                    let data = batched_instruction.instruction.pack();
+                    buf.push(batched_instruction.num_accounts);
+                    buf.extend_from_slice(&(data.len() as u16).to_le_bytes());
+                    buf.extend_from_slice(&data);
+                }
+            }
+            &Self::HarvestWithheldTokensToMint => {
+                buf.push(33);
+            }
+            &Self::WithdrawWithheldTokensFromMint => {
+                buf.push(34);
+            }
         };
         buf
     }
@@ -639,6 +1050,33 @@ impl TokenInstruction {
         }
     }
 
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        let value = input
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(TokenError::InvalidInstruction)?;
+        Ok((value, &input[2..]))
+    }
+
+    fn unpack_i16(input: &[u8]) -> Result<(i16, &[u8]), ProgramError> {
+        let value = input
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i16::from_le_bytes)
+            .ok_or(TokenError::InvalidInstruction)?;
+        Ok((value, &input[2..]))
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(TokenError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
     fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
         match input.split_first() {
             Option::Some((&0, rest)) => Ok((COption::None, rest)),
@@ -674,6 +1112,14 @@ pub enum AuthorityType {
     AccountOwner,
     /// Authority to close a token account
     CloseAccount,
+    /// Authority to set the transfer fee
+    TransferFeeConfig,
+    /// Authority to withdraw withheld tokens from accounts
+    WithheldWithdraw,
+    /// Authority to update the interest rate
+    InterestRate,
+    /// Authority to transfer or burn any tokens of the mint
+    PermanentDelegate,
 }
 
 impl AuthorityType {
@@ -683,6 +1129,10 @@ impl AuthorityType {
             AuthorityType::FreezeAccount => 1,
             AuthorityType::AccountOwner => 2,
             AuthorityType::CloseAccount => 3,
+            AuthorityType::TransferFeeConfig => 4,
+            AuthorityType::WithheldWithdraw => 5,
+            AuthorityType::InterestRate => 6,
+            AuthorityType::PermanentDelegate => 7,
         }
     }
 
@@ -692,6 +1142,10 @@ impl AuthorityType {
             1 => Ok(AuthorityType::FreezeAccount),
             2 => Ok(AuthorityType::AccountOwner),
             3 => Ok(AuthorityType::CloseAccount),
+            4 => Ok(AuthorityType::TransferFeeConfig),
+            5 => Ok(AuthorityType::WithheldWithdraw),
+            6 => Ok(AuthorityType::InterestRate),
+            7 => Ok(AuthorityType::PermanentDelegate),
             _ => Err(TokenError::InvalidInstruction.into()),
         }
     }
@@ -1298,6 +1752,372 @@ pub fn sync_native(
     })
 }
 
+/// Creates an `InitializeTransferFeeConfig` instruction.
+pub fn initialize_transfer_fee_config(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    transfer_fee_config_authority: Option<&Pubkey>,
+    withdraw_withheld_authority: Option<&Pubkey>,
+    transfer_fee_basis_points: u16,
+    maximum_fee: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeTransferFeeConfig {
+        transfer_fee_config_authority: transfer_fee_config_authority.cloned().into(),
+        withdraw_withheld_authority: withdraw_withheld_authority.cloned().into(),
+        transfer_fee_basis_points,
+        maximum_fee,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates a `SetTransferFee` instruction.
+pub fn set_transfer_fee(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    transfer_fee_basis_points: u16,
+    maximum_fee: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::SetTransferFee {
+        transfer_fee_basis_points,
+        maximum_fee,
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `WithdrawWithheldTokensFromAccounts` instruction.
+pub fn withdraw_withheld_tokens_from_accounts(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    source_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let num_token_accounts =
+        u8::try_from(source_pubkeys.len()).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let data = TokenInstruction::WithdrawWithheldTokensFromAccounts { num_token_accounts }.pack();
+
+    let mut accounts = Vec::with_capacity(3 + signer_pubkeys.len() + source_pubkeys.len());
+    accounts.push(AccountMeta::new_readonly(*mint_pubkey, false));
+    accounts.push(AccountMeta::new(*destination_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+    for source_pubkey in source_pubkeys.iter() {
+        accounts.push(AccountMeta::new(**source_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitializeInterestBearingMint` instruction.
+pub fn initialize_interest_bearing_mint(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    rate_authority: Option<&Pubkey>,
+    rate: i16,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeInterestBearingMint {
+        rate_authority: rate_authority.cloned().into(),
+        rate,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `UpdateInterestRate` instruction.
+pub fn update_interest_rate(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    rate_authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    rate: i16,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::UpdateInterestRate { rate }.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *rate_authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitializeNonTransferableMint` instruction.
+pub fn initialize_non_transferable_mint(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeNonTransferableMint.pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `InitializePermanentDelegate` instruction.
+pub fn initialize_permanent_delegate(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    delegate: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializePermanentDelegate {
+        delegate: *delegate,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `EnableRequiredTransferMemos` instruction.
+pub fn enable_required_transfer_memos(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::EnableRequiredTransferMemos.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*account_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `DisableRequiredTransferMemos` instruction.
+pub fn disable_required_transfer_memos(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::DisableRequiredTransferMemos.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*account_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitializeDefaultAccountState` instruction.
+pub fn initialize_default_account_state(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    state: &AccountState,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeDefaultAccountState { state: *state }.pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates a `Reallocate` instruction.
+pub fn reallocate(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    payer: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    extension_types: &[ExtensionType],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::Reallocate {
+        extension_types: extension_types.to_vec(),
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(4 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*account_pubkey, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `HarvestWithheldTokensToMint` instruction.
+pub fn harvest_withheld_tokens_to_mint(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    source_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::HarvestWithheldTokensToMint.pack();
+
+    let mut accounts = Vec::with_capacity(1 + source_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    for source_pubkey in source_pubkeys.iter() {
+        accounts.push(AccountMeta::new(**source_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `WithdrawWithheldTokensFromMint` instruction.
+pub fn withdraw_withheld_tokens_from_mint(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::WithdrawWithheldTokensFromMint.pack();
+
+    let mut accounts = Vec::with_capacity(3 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new(*destination_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Batch` instruction out of token program instructions, such as those created by
+/// the other functions in this module.
+pub fn batch(
+    token_program_id: &Pubkey,
+    instructions: &[Instruction],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    if instructions.len() > u8::MAX as usize {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut accounts = vec![];
+    let mut batched_instructions = Vec::with_capacity(instructions.len());
+    for instruction in instructions {
+        check_program_account(&instruction.program_id)?;
+        let num_accounts =
+            u8::try_from(instruction.accounts.len()).map_err(|_| ProgramError::InvalidArgument)?;
+        batched_instructions.push(BatchedInstruction {
+            num_accounts,
+            instruction: TokenInstruction::unpack(&instruction.data)?,
+        });
+        accounts.extend_from_slice(&instruction.accounts);
+    }
+    let data = TokenInstruction::Batch {
+        instructions: batched_instructions,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
 /// Utility function that checks index is between MIN_SIGNERS and MAX_SIGNERS
 pub fn is_valid_signer_index(index: usize) -> bool {
     (MIN_SIGNERS..=MAX_SIGNERS).contains(&index)
@@ -1518,5 +2338,160 @@ mod test {
         assert_eq!(packed, expect);
         let unpacked = TokenInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeTransferFeeConfig {
+            transfer_fee_config_authority: COption::Some(Pubkey::new(&[4u8; 32])),
+            withdraw_withheld_authority: COption::None,
+            transfer_fee_basis_points: 111,
+            maximum_fee: u64::MAX,
+        };
+        let packed = check.pack();
+        let mut expect = vec![21u8, 1];
+        expect.extend_from_slice(&[4u8; 32]);
+        expect.extend_from_slice(&[0]);
+        expect.extend_from_slice(&111u16.to_le_bytes());
+        expect.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::SetTransferFee {
+            transfer_fee_basis_points: 1,
+            maximum_fee: 2,
+        };
+        let packed = check.pack();
+        let expect = vec![22u8, 1, 0, 2, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::WithdrawWithheldTokensFromAccounts {
+            num_token_accounts: 3,
+        };
+        let packed = check.pack();
+        let expect = vec![23u8, 3];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeInterestBearingMint {
+            rate_authority: COption::Some(Pubkey::new(&[5u8; 32])),
+            rate: -2,
+        };
+        let packed = check.pack();
+        let mut expect = vec![24u8, 1];
+        expect.extend_from_slice(&[5u8; 32]);
+        expect.extend_from_slice(&[254, 255]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::UpdateInterestRate { rate: 300 };
+        let packed = check.pack();
+        let expect = vec![25u8, 44, 1];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeNonTransferableMint;
+        let packed = check.pack();
+        let expect = vec![26u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializePermanentDelegate {
+            delegate: Pubkey::new(&[6u8; 32]),
+        };
+        let packed = check.pack();
+        let mut expect = vec![27u8];
+        expect.extend_from_slice(&[6u8; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::EnableRequiredTransferMemos;
+        let packed = check.pack();
+        let expect = vec![28u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::DisableRequiredTransferMemos;
+        let packed = check.pack();
+        let expect = vec![29u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeDefaultAccountState {
+            state: AccountState::Frozen,
+        };
+        let packed = check.pack();
+        let expect = vec![30u8, 2];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+        assert!(TokenInstruction::unpack(&[30u8, 3]).is_err());
+
+        let check = TokenInstruction::Reallocate {
+            extension_types: vec![
+                ExtensionType::MemoTransfer,
+                ExtensionType::TransferFeeAmount,
+            ],
+        };
+        let packed = check.pack();
+        let expect = vec![31u8, 7, 0, 2, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+        assert!(TokenInstruction::unpack(&[31u8, 7]).is_err());
+        assert!(TokenInstruction::unpack(&[31u8, 255, 0]).is_err());
+
+        let check = TokenInstruction::HarvestWithheldTokensToMint;
+        let packed = check.pack();
+        let expect = vec![33u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::WithdrawWithheldTokensFromMint;
+        let packed = check.pack();
+        let expect = vec![34u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::Batch {
+            instructions: vec![
+                BatchedInstruction {
+                    num_accounts: 3,
+                    instruction: TokenInstruction::Transfer { amount: 1 },
+                },
+                BatchedInstruction {
+                    num_accounts: 3,
+                    instruction: TokenInstruction::CloseAccount,
+                },
+            ],
+        };
+        let packed = check.pack();
+        let mut expect = vec![32u8, 2, 3, 9, 0, 3, 1, 0, 0, 0, 0, 0, 0, 0];
+        expect.extend_from_slice(&[3, 1, 0, 9]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        // batches can't be nested or truncated
+        let nested = TokenInstruction::Batch {
+            instructions: vec![BatchedInstruction {
+                num_accounts: 0,
+                instruction: check,
+            }],
+        };
+        assert_eq!(
+            TokenInstruction::unpack(&nested.pack()),
+            Err(TokenError::NestedBatch.into())
+        );
+        assert!(TokenInstruction::unpack(&expect[..expect.len() - 1]).is_err());
     }
 }
@@ -2,7 +2,19 @@
 
 use crate::{
     error::TokenError,
-    instruction::{is_valid_signer_index, AuthorityType, TokenInstruction, MAX_SIGNERS},
+    extension::{
+        default_account_state::DefaultAccountState,
+        get_account_type,
+        interest_bearing_mint::InterestBearingConfig,
+        memo_transfer::{check_previous_instruction_is_memo, MemoTransfer},
+        non_transferable::{NonTransferable, NonTransferableAccount},
+        permanent_delegate::PermanentDelegate,
+        transfer_fee::{TransferFeeAmount, TransferFeeConfig, MAX_FEE_BASIS_POINTS},
+        AccountType, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+    },
+    instruction::{
+        is_valid_signer_index, AuthorityType, BatchedInstruction, TokenInstruction, MAX_SIGNERS,
+    },
     state::{Account, AccountState, Mint, Multisig},
 };
 use num_traits::FromPrimitive;
@@ -11,11 +23,13 @@ use solana_program::{
     decode_error::DecodeError,
     entrypoint::ProgramResult,
     msg,
+    program::invoke,
     program_error::{PrintProgramError, ProgramError},
     program_option::COption,
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
-    sysvar::{rent::Rent, Sysvar},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 
 /// Program state handler.
@@ -37,8 +51,9 @@ impl Processor {
             Rent::get()?
         };
 
-        let mut mint = Mint::unpack_unchecked(&mint_info.data.borrow())?;
-        if mint.is_initialized {
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut mint_data)?;
+        if mint.base.is_initialized {
             return Err(TokenError::AlreadyInUse.into());
         }
 
@@ -46,12 +61,20 @@ impl Processor {
             return Err(TokenError::NotRentExempt.into());
         }
 
-        mint.mint_authority = COption::Some(mint_authority);
-        mint.decimals = decimals;
-        mint.is_initialized = true;
-        mint.freeze_authority = freeze_authority;
+        if let Ok(default_account_state) = mint.get_extension::<DefaultAccountState>() {
+            // accounts that start frozen could never be thawed
+            if default_account_state.state == AccountState::Frozen && freeze_authority.is_none() {
+                return Err(TokenError::MintCannotFreeze.into());
+            }
+        }
 
-        Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+        mint.base.mint_authority = COption::Some(mint_authority);
+        mint.base.decimals = decimals;
+        mint.base.is_initialized = true;
+        mint.base.freeze_authority = freeze_authority;
+
+        mint.pack_base();
+        mint.init_account_type();
 
         Ok(())
     }
@@ -96,8 +119,9 @@ impl Processor {
             Rent::get()?
         };
 
-        let mut account = Account::unpack_unchecked(&new_account_info.data.borrow())?;
-        if account.is_initialized() {
+        let mut account_data = new_account_info.data.borrow_mut();
+        let mut account = StateWithExtensionsMut::<Account>::unpack_unchecked(&mut account_data)?;
+        if account.base.is_initialized() {
             return Err(TokenError::AlreadyInUse.into());
         }
 
@@ -105,29 +129,52 @@ impl Processor {
             return Err(TokenError::NotRentExempt.into());
         }
 
-        if *mint_info.key != crate::native_mint::id() {
-            let _ = Mint::unpack(&mint_info.data.borrow_mut())
+        let (required_extensions, starting_state) = if *mint_info.key != crate::native_mint::id() {
+            let mint_data = mint_info.data.borrow();
+            let mint = StateWithExtensions::<Mint>::unpack(&mint_data)
                 .map_err(|_| Into::<ProgramError>::into(TokenError::InvalidMint))?;
+            let starting_state = mint
+                .get_extension::<DefaultAccountState>()
+                .map(|default_account_state| default_account_state.state)
+                .unwrap_or(AccountState::Initialized);
+            (
+                ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?),
+                starting_state,
+            )
+        } else {
+            (vec![], AccountState::Initialized)
+        };
+        for extension_type in required_extensions {
+            match extension_type {
+                ExtensionType::TransferFeeAmount => {
+                    account.init_extension(&TransferFeeAmount::default())?;
+                }
+                ExtensionType::NonTransferableAccount => {
+                    account.init_extension(&NonTransferableAccount)?;
+                }
+                _ => {}
+            }
         }
 
-        account.mint = *mint_info.key;
-        account.owner = *owner;
-        account.delegate = COption::None;
-        account.delegated_amount = 0;
-        account.state = AccountState::Initialized;
+        account.base.mint = *mint_info.key;
+        account.base.owner = *owner;
+        account.base.delegate = COption::None;
+        account.base.delegated_amount = 0;
+        account.base.state = starting_state;
         if *mint_info.key == crate::native_mint::id() {
             let rent_exempt_reserve = rent.minimum_balance(new_account_info_data_len);
-            account.is_native = COption::Some(rent_exempt_reserve);
-            account.amount = new_account_info
+            account.base.is_native = COption::Some(rent_exempt_reserve);
+            account.base.amount = new_account_info
                 .lamports()
                 .checked_sub(rent_exempt_reserve)
                 .ok_or(TokenError::Overflow)?;
         } else {
-            account.is_native = COption::None;
-            account.amount = 0;
+            account.base.is_native = COption::None;
+            account.base.amount = 0;
         };
 
-        Account::pack(account, &mut new_account_info.data.borrow_mut())?;
+        account.pack_base();
+        account.init_account_type();
 
         Ok(())
     }
@@ -219,8 +266,24 @@ impl Processor {
         let dest_account_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
-        let mut dest_account = Account::unpack(&dest_account_info.data.borrow())?;
+        let (mut source_account, source_has_fee_extension) = {
+            let source_data = source_account_info.data.borrow();
+            let source = StateWithExtensions::<Account>::unpack(&source_data)?;
+            if source.get_extension::<NonTransferableAccount>().is_ok() {
+                return Err(TokenError::NonTransferable.into());
+            }
+            let has_fee_extension = source.get_extension::<TransferFeeAmount>().is_ok();
+            (source.base, has_fee_extension)
+        };
+        let (mut dest_account, dest_requires_memo) = {
+            let dest_data = dest_account_info.data.borrow();
+            let dest = StateWithExtensions::<Account>::unpack(&dest_data)?;
+            let requires_memo = dest
+                .get_extension::<MemoTransfer>()
+                .map(|memo_transfer| memo_transfer.require_incoming_transfer_memos)
+                .unwrap_or(false);
+            (dest.base, requires_memo)
+        };
 
         if source_account.is_frozen() || dest_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
@@ -232,27 +295,60 @@ impl Processor {
             return Err(TokenError::MintMismatch.into());
         }
 
-        if let Some((mint_info, expected_decimals)) = expected_mint_info {
+        let (fee, permanent_delegate) = if let Some((mint_info, expected_decimals)) =
+            expected_mint_info
+        {
             if source_account.mint != *mint_info.key {
                 return Err(TokenError::MintMismatch.into());
             }
 
-            let mint = Mint::unpack(&mint_info.data.borrow_mut())?;
-            if expected_decimals != mint.decimals {
+            let mint_data = mint_info.data.borrow();
+            let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+            if expected_decimals != mint.base.decimals {
                 return Err(TokenError::MintDecimalsMismatch.into());
             }
-        }
+            if mint.get_extension::<NonTransferable>().is_ok() {
+                return Err(TokenError::NonTransferable.into());
+            }
+
+            let fee = if let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() {
+                transfer_fee_config
+                    .calculate_fee(amount)
+                    .ok_or(TokenError::Overflow)?
+            } else {
+                0
+            };
+            let permanent_delegate: Option<Pubkey> = mint
+                .get_extension::<PermanentDelegate>()
+                .ok()
+                .and_then(|permanent_delegate| permanent_delegate.delegate.into());
+            (fee, permanent_delegate)
+        } else if source_has_fee_extension {
+            // the fee can only be calculated from the mint
+            return Err(TokenError::MintRequiredForTransfer.into());
+        } else {
+            (0, None)
+        };
 
         let self_transfer = source_account_info.key == dest_account_info.key;
 
+        // when memos are required, the instructions sysvar follows any signer accounts
+        let (signer_infos, instructions_sysvar_info) = if dest_requires_memo {
+            let (instructions_sysvar_info, signer_infos) = account_info_iter
+                .as_slice()
+                .split_last()
+                .ok_or(TokenError::NoMemo)?;
+            (signer_infos, Some(instructions_sysvar_info))
+        } else {
+            (account_info_iter.as_slice(), None)
+        };
+
         match source_account.delegate {
+            _ if permanent_delegate.as_ref() == Some(authority_info.key) => {
+                Self::validate_owner(program_id, authority_info.key, authority_info, signer_infos)?
+            }
             COption::Some(ref delegate) if authority_info.key == delegate => {
-                Self::validate_owner(
-                    program_id,
-                    delegate,
-                    authority_info,
-                    account_info_iter.as_slice(),
-                )?;
+                Self::validate_owner(program_id, delegate, authority_info, signer_infos)?;
                 if source_account.delegated_amount < amount {
                     return Err(TokenError::InsufficientFunds.into());
                 }
@@ -270,10 +366,14 @@ impl Processor {
                 program_id,
                 &source_account.owner,
                 authority_info,
-                account_info_iter.as_slice(),
+                signer_infos,
             )?,
         };
 
+        if let Some(instructions_sysvar_info) = instructions_sysvar_info {
+            check_previous_instruction_is_memo(program_id, instructions_sysvar_info)?;
+        }
+
         // This check MUST occur just before the amounts are manipulated
         // to ensure self-transfers are fully validated
         if self_transfer {
@@ -284,9 +384,10 @@ impl Processor {
             .amount
             .checked_sub(amount)
             .ok_or(TokenError::Overflow)?;
+        let credited_amount = amount.checked_sub(fee).ok_or(TokenError::Overflow)?;
         dest_account.amount = dest_account
             .amount
-            .checked_add(amount)
+            .checked_add(credited_amount)
             .ok_or(TokenError::Overflow)?;
 
         if source_account.is_native() {
@@ -301,8 +402,20 @@ impl Processor {
                 .ok_or(TokenError::Overflow)?;
         }
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
-        Account::pack(dest_account, &mut dest_account_info.data.borrow_mut())?;
+        Account::pack_into_slice(&source_account, &mut source_account_info.data.borrow_mut());
+
+        let mut dest_data = dest_account_info.data.borrow_mut();
+        let mut dest = StateWithExtensionsMut::<Account>::unpack(&mut dest_data)?;
+        dest.base = dest_account;
+        dest.pack_base();
+        if fee > 0 {
+            let mut transfer_fee_amount = dest.get_extension::<TransferFeeAmount>()?;
+            transfer_fee_amount.withheld_amount = transfer_fee_amount
+                .withheld_amount
+                .checked_add(fee)
+                .ok_or(TokenError::Overflow)?;
+            dest.set_extension(&transfer_fee_amount)?;
+        }
 
         Ok(())
     }
@@ -326,7 +439,8 @@ impl Processor {
         let delegate_info = next_account_info(account_info_iter)?;
         let owner_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account =
+            StateWithExtensions::<Account>::unpack(&source_account_info.data.borrow())?.base;
 
         if source_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
@@ -337,7 +451,7 @@ impl Processor {
                 return Err(TokenError::MintMismatch.into());
             }
 
-            let mint = Mint::unpack(&mint_info.data.borrow_mut())?;
+            let mint = StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?.base;
             if expected_decimals != mint.decimals {
                 return Err(TokenError::MintDecimalsMismatch.into());
             }
@@ -353,7 +467,7 @@ impl Processor {
         source_account.delegate = COption::Some(*delegate_info.key);
         source_account.delegated_amount = amount;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_into_slice(&source_account, &mut source_account_info.data.borrow_mut());
 
         Ok(())
     }
@@ -363,7 +477,8 @@ impl Processor {
         let account_info_iter = &mut accounts.iter();
         let source_account_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account =
+            StateWithExtensions::<Account>::unpack(&source_account_info.data.borrow())?.base;
 
         let owner_info = next_account_info(account_info_iter)?;
 
@@ -381,7 +496,7 @@ impl Processor {
         source_account.delegate = COption::None;
         source_account.delegated_amount = 0;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_into_slice(&source_account, &mut source_account_info.data.borrow_mut());
 
         Ok(())
     }
@@ -397,8 +512,11 @@ impl Processor {
         let account_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        if account_info.data_len() == Account::get_packed_len() {
-            let mut account = Account::unpack(&account_info.data.borrow())?;
+        let account_type = get_account_type(&account_info.data.borrow())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        if account_type == AccountType::Account {
+            let mut account =
+                StateWithExtensions::<Account>::unpack(&account_info.data.borrow())?.base;
 
             if account.is_frozen() {
                 return Err(TokenError::AccountFrozen.into());
@@ -440,9 +558,9 @@ impl Processor {
                     return Err(TokenError::AuthorityTypeNotSupported.into());
                 }
             }
-            Account::pack(account, &mut account_info.data.borrow_mut())?;
-        } else if account_info.data_len() == Mint::get_packed_len() {
-            let mut mint = Mint::unpack(&account_info.data.borrow())?;
+            Account::pack_into_slice(&account, &mut account_info.data.borrow_mut());
+        } else if account_type == AccountType::Mint {
+            let mut mint = StateWithExtensions::<Mint>::unpack(&account_info.data.borrow())?.base;
             match authority_type {
                 AuthorityType::MintTokens => {
                     // Once a mint's supply is fixed, it cannot be undone by setting a new
@@ -470,13 +588,88 @@ impl Processor {
                         authority_info,
                         account_info_iter.as_slice(),
                     )?;
+                    if new_authority.is_none() {
+                        // accounts that start frozen could never be thawed
+                        let default_account_state =
+                            StateWithExtensions::<Mint>::unpack(&account_info.data.borrow())?
+                                .get_extension::<DefaultAccountState>();
+                        if let Ok(DefaultAccountState {
+                            state: AccountState::Frozen,
+                        }) = default_account_state
+                        {
+                            return Err(TokenError::InvalidState.into());
+                        }
+                    }
                     mint.freeze_authority = new_authority;
                 }
+                AuthorityType::TransferFeeConfig | AuthorityType::WithheldWithdraw => {
+                    let mut transfer_fee_config =
+                        StateWithExtensions::<Mint>::unpack(&account_info.data.borrow())?
+                            .get_extension::<TransferFeeConfig>()?;
+                    let authority = if authority_type == AuthorityType::TransferFeeConfig {
+                        &mut transfer_fee_config.transfer_fee_config_authority
+                    } else {
+                        &mut transfer_fee_config.withdraw_withheld_authority
+                    };
+                    // Once an authority is disabled, it cannot be re-enabled
+                    let current_authority = authority
+                        .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+                    Self::validate_owner(
+                        program_id,
+                        &current_authority,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+                    *authority = new_authority;
+
+                    let mut mint_data = account_info.data.borrow_mut();
+                    StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?
+                        .set_extension(&transfer_fee_config)?;
+                }
+                AuthorityType::InterestRate => {
+                    let mut interest_bearing_config =
+                        StateWithExtensions::<Mint>::unpack(&account_info.data.borrow())?
+                            .get_extension::<InterestBearingConfig>()?;
+                    let rate_authority =
+                        interest_bearing_config
+                            .rate_authority
+                            .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+                    Self::validate_owner(
+                        program_id,
+                        &rate_authority,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+                    interest_bearing_config.rate_authority = new_authority;
+
+                    let mut mint_data = account_info.data.borrow_mut();
+                    StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?
+                        .set_extension(&interest_bearing_config)?;
+                }
+                AuthorityType::PermanentDelegate => {
+                    let mut permanent_delegate =
+                        StateWithExtensions::<Mint>::unpack(&account_info.data.borrow())?
+                            .get_extension::<PermanentDelegate>()?;
+                    let delegate = permanent_delegate
+                        .delegate
+                        .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+                    Self::validate_owner(
+                        program_id,
+                        &delegate,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+                    permanent_delegate.delegate = new_authority;
+
+                    let mut mint_data = account_info.data.borrow_mut();
+                    StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?
+                        .set_extension(&permanent_delegate)?;
+                }
                 _ => {
                     return Err(TokenError::AuthorityTypeNotSupported.into());
                 }
             }
-            Mint::pack(mint, &mut account_info.data.borrow_mut())?;
+            Mint::pack_into_slice(&mint, &mut account_info.data.borrow_mut());
         } else {
             return Err(ProgramError::InvalidArgument);
         }
@@ -496,7 +689,8 @@ impl Processor {
         let dest_account_info = next_account_info(account_info_iter)?;
         let owner_info = next_account_info(account_info_iter)?;
 
-        let mut dest_account = Account::unpack(&dest_account_info.data.borrow())?;
+        let mut dest_account =
+            StateWithExtensions::<Account>::unpack(&dest_account_info.data.borrow())?.base;
         if dest_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
         }
@@ -508,7 +702,7 @@ impl Processor {
             return Err(TokenError::MintMismatch.into());
         }
 
-        let mut mint = Mint::unpack(&mint_info.data.borrow())?;
+        let mut mint = StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?.base;
         if let Some(expected_decimals) = expected_decimals {
             if expected_decimals != mint.decimals {
                 return Err(TokenError::MintDecimalsMismatch.into());
@@ -535,8 +729,8 @@ impl Processor {
             .checked_add(amount)
             .ok_or(TokenError::Overflow)?;
 
-        Account::pack(dest_account, &mut dest_account_info.data.borrow_mut())?;
-        Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+        Account::pack_into_slice(&dest_account, &mut dest_account_info.data.borrow_mut());
+        Mint::pack_into_slice(&mint, &mut mint_info.data.borrow_mut());
 
         Ok(())
     }
@@ -554,8 +748,17 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
-        let mut mint = Mint::unpack(&mint_info.data.borrow())?;
+        let mut source_account =
+            StateWithExtensions::<Account>::unpack(&source_account_info.data.borrow())?.base;
+        let (mut mint, permanent_delegate) = {
+            let mint_data = mint_info.data.borrow();
+            let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+            let permanent_delegate: Option<Pubkey> = mint
+                .get_extension::<PermanentDelegate>()
+                .ok()
+                .and_then(|permanent_delegate| permanent_delegate.delegate.into());
+            (mint.base, permanent_delegate)
+        };
 
         if source_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
@@ -577,6 +780,12 @@ impl Processor {
         }
 
         match source_account.delegate {
+            _ if permanent_delegate.as_ref() == Some(authority_info.key) => Self::validate_owner(
+                program_id,
+                authority_info.key,
+                authority_info,
+                account_info_iter.as_slice(),
+            )?,
             COption::Some(ref delegate) if authority_info.key == delegate => {
                 Self::validate_owner(
                     program_id,
@@ -613,8 +822,8 @@ impl Processor {
             .checked_sub(amount)
             .ok_or(TokenError::Overflow)?;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
-        Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+        Account::pack_into_slice(&source_account, &mut source_account_info.data.borrow_mut());
+        Mint::pack_into_slice(&mint, &mut mint_info.data.borrow_mut());
 
         Ok(())
     }
@@ -626,7 +835,16 @@ impl Processor {
         let dest_account_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account = {
+            let source_data = source_account_info.data.borrow();
+            let source = StateWithExtensions::<Account>::unpack(&source_data)?;
+            if let Ok(transfer_fee_amount) = source.get_extension::<TransferFeeAmount>() {
+                if transfer_fee_amount.withheld_amount != 0 {
+                    return Err(TokenError::AccountHasWithheldTransferFees.into());
+                }
+            }
+            source.base
+        };
         if !source_account.is_native() && source_account.amount != 0 {
             return Err(TokenError::NonNativeHasBalance.into());
         }
@@ -649,7 +867,7 @@ impl Processor {
         **source_account_info.lamports.borrow_mut() = 0;
         source_account.amount = 0;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_into_slice(&source_account, &mut source_account_info.data.borrow_mut());
 
         Ok(())
     }
@@ -666,7 +884,8 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account =
+            StateWithExtensions::<Account>::unpack(&source_account_info.data.borrow())?.base;
         if freeze && source_account.is_frozen() || !freeze && !source_account.is_frozen() {
             return Err(TokenError::InvalidState.into());
         }
@@ -677,7 +896,7 @@ impl Processor {
             return Err(TokenError::MintMismatch.into());
         }
 
-        let mint = Mint::unpack(&mint_info.data.borrow_mut())?;
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?.base;
         match mint.freeze_authority {
             COption::Some(authority) => Self::validate_owner(
                 program_id,
@@ -694,7 +913,7 @@ impl Processor {
             AccountState::Initialized
         };
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_into_slice(&source_account, &mut source_account_info.data.borrow_mut());
 
         Ok(())
     }
@@ -707,7 +926,8 @@ impl Processor {
         if native_account_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        let mut native_account = Account::unpack(&native_account_info.data.borrow())?;
+        let mut native_account =
+            StateWithExtensions::<Account>::unpack(&native_account_info.data.borrow())?.base;
 
         if let COption::Some(rent_exempt_reserve) = native_account.is_native {
             let new_amount = native_account_info
@@ -722,519 +942,2540 @@ impl Processor {
             return Err(TokenError::NonNativeNotSupported.into());
         }
 
-        Account::pack(native_account, &mut native_account_info.data.borrow_mut())?;
+        Account::pack_into_slice(&native_account, &mut native_account_info.data.borrow_mut());
         Ok(())
     }
 
-    /// Processes an [Instruction](enum.Instruction.html).
-    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
-        let instruction = TokenInstruction::unpack(input)?;
+    /// Processes an [InitializeTransferFeeConfig](enum.TokenInstruction.html) instruction.
+    pub fn process_initialize_transfer_fee_config(
+        accounts: &[AccountInfo],
+        transfer_fee_config_authority: COption<Pubkey>,
+        withdraw_withheld_authority: COption<Pubkey>,
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
 
-        match instruction {
-            TokenInstruction::InitializeMint {
-                decimals,
-                mint_authority,
-                freeze_authority,
-            } => {
-                msg!("Instruction: InitializeMint");
-                Self::process_initialize_mint(accounts, decimals, mint_authority, freeze_authority)
-            }
-            TokenInstruction::InitializeMint2 {
-                decimals,
-                mint_authority,
-                freeze_authority,
-            } => {
-                msg!("Instruction: InitializeMint2");
-                Self::process_initialize_mint2(accounts, decimals, mint_authority, freeze_authority)
-            }
-            TokenInstruction::InitializeAccount => {
-                msg!("Instruction: InitializeAccount");
-                Self::process_initialize_account(accounts)
-            }
-            TokenInstruction::InitializeAccount2 { owner } => {
-                msg!("Instruction: InitializeAccount2");
-                Self::process_initialize_account2(accounts, owner)
-            }
-            TokenInstruction::InitializeAccount3 { owner } => {
-                msg!("Instruction: InitializeAccount3");
-                Self::process_initialize_account3(accounts, owner)
-            }
-            TokenInstruction::InitializeMultisig { m } => {
-                msg!("Instruction: InitializeMultisig");
-                Self::process_initialize_multisig(accounts, m)
-            }
-            TokenInstruction::InitializeMultisig2 { m } => {
-                msg!("Instruction: InitializeMultisig2");
-                Self::process_initialize_multisig2(accounts, m)
-            }
-            TokenInstruction::Transfer { amount } => {
-                msg!("Instruction: Transfer");
-                Self::process_transfer(program_id, accounts, amount, None)
-            }
-            TokenInstruction::Approve { amount } => {
-                msg!("Instruction: Approve");
-                Self::process_approve(program_id, accounts, amount, None)
-            }
-            TokenInstruction::Revoke => {
-                msg!("Instruction: Revoke");
-                Self::process_revoke(program_id, accounts)
-            }
-            TokenInstruction::SetAuthority {
-                authority_type,
-                new_authority,
-            } => {
-                msg!("Instruction: SetAuthority");
-                Self::process_set_authority(program_id, accounts, authority_type, new_authority)
-            }
-            TokenInstruction::MintTo { amount } => {
-                msg!("Instruction: MintTo");
-                Self::process_mint_to(program_id, accounts, amount, None)
-            }
-            TokenInstruction::Burn { amount } => {
-                msg!("Instruction: Burn");
-                Self::process_burn(program_id, accounts, amount, None)
-            }
-            TokenInstruction::CloseAccount => {
-                msg!("Instruction: CloseAccount");
-                Self::process_close_account(program_id, accounts)
-            }
-            TokenInstruction::FreezeAccount => {
-                msg!("Instruction: FreezeAccount");
-                Self::process_toggle_freeze_account(program_id, accounts, true)
-            }
-            TokenInstruction::ThawAccount => {
-                msg!("Instruction: ThawAccount");
-                Self::process_toggle_freeze_account(program_id, accounts, false)
-            }
-            TokenInstruction::TransferChecked { amount, decimals } => {
-                msg!("Instruction: TransferChecked");
-                Self::process_transfer(program_id, accounts, amount, Some(decimals))
-            }
-            TokenInstruction::ApproveChecked { amount, decimals } => {
-                msg!("Instruction: ApproveChecked");
-                Self::process_approve(program_id, accounts, amount, Some(decimals))
-            }
-            TokenInstruction::MintToChecked { amount, decimals } => {
-                msg!("Instruction: MintToChecked");
-                Self::process_mint_to(program_id, accounts, amount, Some(decimals))
-            }
-            TokenInstruction::BurnChecked { amount, decimals } => {
-                msg!("Instruction: BurnChecked");
-                Self::process_burn(program_id, accounts, amount, Some(decimals))
-            }
-            TokenInstruction::SyncNative => {
-                msg!("Instruction: SyncNative");
-                Self::process_sync_native(program_id, accounts)
-            }
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut mint_data)?;
+        if mint.base.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+        if transfer_fee_basis_points > MAX_FEE_BASIS_POINTS {
+            return Err(TokenError::TransferFeeExceedsMaximum.into());
         }
+
+        mint.init_extension(&TransferFeeConfig {
+            transfer_fee_config_authority,
+            withdraw_withheld_authority,
+            transfer_fee_basis_points,
+            maximum_fee,
+            withheld_amount: 0,
+        })?;
+
+        Ok(())
     }
 
-    /// Validates owner(s) are present
-    pub fn validate_owner(
+    /// Processes a [SetTransferFee](enum.TokenInstruction.html) instruction.
+    pub fn process_set_transfer_fee(
         program_id: &Pubkey,
-        expected_owner: &Pubkey,
-        owner_account_info: &AccountInfo,
-        signers: &[AccountInfo],
+        accounts: &[AccountInfo],
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
     ) -> ProgramResult {
-        if expected_owner != owner_account_info.key {
-            return Err(TokenError::OwnerMismatch.into());
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let mut transfer_fee_config =
+            StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?
+                .get_extension::<TransferFeeConfig>()?;
+        let authority = transfer_fee_config
+            .transfer_fee_config_authority
+            .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+        Self::validate_owner(
+            program_id,
+            &authority,
+            authority_info,
+            account_info_iter.as_slice(),
+        )?;
+
+        if transfer_fee_basis_points > MAX_FEE_BASIS_POINTS {
+            return Err(TokenError::TransferFeeExceedsMaximum.into());
         }
-        if program_id == owner_account_info.owner
-            && owner_account_info.data_len() == Multisig::get_packed_len()
-        {
-            let multisig = Multisig::unpack(&owner_account_info.data.borrow())?;
-            let mut num_signers = 0;
-            let mut matched = [false; MAX_SIGNERS];
-            for signer in signers.iter() {
-                for (position, key) in multisig.signers[0..multisig.n as usize].iter().enumerate() {
-                    if key == signer.key && !matched[position] {
-                        if !signer.is_signer {
-                            return Err(ProgramError::MissingRequiredSignature);
-                        }
-                        matched[position] = true;
-                        num_signers += 1;
-                    }
-                }
-            }
-            if num_signers < multisig.m {
-                return Err(ProgramError::MissingRequiredSignature);
+        transfer_fee_config.transfer_fee_basis_points = transfer_fee_basis_points;
+        transfer_fee_config.maximum_fee = maximum_fee;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?
+            .set_extension(&transfer_fee_config)?;
+
+        Ok(())
+    }
+
+    /// Processes a [WithdrawWithheldTokensFromAccounts](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_withdraw_withheld_tokens_from_accounts(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        num_token_accounts: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let dest_account_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let remaining_infos = account_info_iter.as_slice();
+        let num_signers = remaining_infos
+            .len()
+            .checked_sub(num_token_accounts as usize)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let (signer_infos, source_account_infos) = remaining_infos.split_at(num_signers);
+
+        let transfer_fee_config = StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?
+            .get_extension::<TransferFeeConfig>()?;
+        let authority = transfer_fee_config
+            .withdraw_withheld_authority
+            .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+        Self::validate_owner(program_id, &authority, authority_info, signer_infos)?;
+
+        let dest_account =
+            StateWithExtensions::<Account>::unpack(&dest_account_info.data.borrow())?.base;
+        if dest_account.mint != *mint_info.key {
+            return Err(TokenError::MintMismatch.into());
+        }
+        if dest_account.is_frozen() {
+            return Err(TokenError::AccountFrozen.into());
+        }
+
+        let mut withdrawn_amount = 0u64;
+        for source_account_info in source_account_infos {
+            let mut source_data = source_account_info.data.borrow_mut();
+            let mut source = StateWithExtensionsMut::<Account>::unpack(&mut source_data)?;
+            if source.base.mint != *mint_info.key {
+                return Err(TokenError::MintMismatch.into());
             }
-            return Ok(());
-        } else if !owner_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+            let mut transfer_fee_amount = source.get_extension::<TransferFeeAmount>()?;
+            withdrawn_amount = withdrawn_amount
+                .checked_add(transfer_fee_amount.withheld_amount)
+                .ok_or(TokenError::Overflow)?;
+            transfer_fee_amount.withheld_amount = 0;
+            source.set_extension(&transfer_fee_amount)?;
         }
+
+        // the destination may also be one of the sources, so only update it once all of their
+        // withheld amounts have been cleared
+        let mut dest_data = dest_account_info.data.borrow_mut();
+        let mut dest = StateWithExtensionsMut::<Account>::unpack(&mut dest_data)?;
+        dest.base.amount = dest
+            .base
+            .amount
+            .checked_add(withdrawn_amount)
+            .ok_or(TokenError::Overflow)?;
+        dest.pack_base();
+
         Ok(())
     }
-}
 
-impl PrintProgramError for TokenError {
-    fn print<E>(&self)
-    where
-        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
-    {
-        match self {
-            TokenError::NotRentExempt => msg!("Error: Lamport balance below rent-exempt threshold"),
-            TokenError::InsufficientFunds => msg!("Error: insufficient funds"),
-            TokenError::InvalidMint => msg!("Error: Invalid Mint"),
-            TokenError::MintMismatch => msg!("Error: Account not associated with this Mint"),
-            TokenError::OwnerMismatch => msg!("Error: owner does not match"),
-            TokenError::FixedSupply => msg!("Error: the total supply of this token is fixed"),
-            TokenError::AlreadyInUse => msg!("Error: account or token already in use"),
-            TokenError::InvalidNumberOfProvidedSigners => {
-                msg!("Error: Invalid number of provided signers")
-            }
-            TokenError::InvalidNumberOfRequiredSigners => {
-                msg!("Error: Invalid number of required signers")
-            }
-            TokenError::UninitializedState => msg!("Error: State is uninitialized"),
-            TokenError::NativeNotSupported => {
-                msg!("Error: Instruction does not support native tokens")
-            }
-            TokenError::NonNativeHasBalance => {
-                msg!("Error: Non-native account can only be closed if its balance is zero")
-            }
-            TokenError::InvalidInstruction => msg!("Error: Invalid instruction"),
-            TokenError::InvalidState => msg!("Error: Invalid account state for operation"),
-            TokenError::Overflow => msg!("Error: Operation overflowed"),
-            TokenError::AuthorityTypeNotSupported => {
-                msg!("Error: Account does not support specified authority type")
-            }
-            TokenError::MintCannotFreeze => msg!("Error: This token mint cannot freeze accounts"),
-            TokenError::AccountFrozen => msg!("Error: Account is frozen"),
-            TokenError::MintDecimalsMismatch => {
-                msg!("Error: decimals different from the Mint decimals")
-            }
-            TokenError::NonNativeNotSupported => {
-                msg!("Error: Instruction does not support non-native tokens")
+    /// Processes a [HarvestWithheldTokensToMint](enum.TokenInstruction.html) instruction.
+    pub fn process_harvest_withheld_tokens_to_mint(accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let source_account_infos = account_info_iter.as_slice();
+        if source_account_infos
+            .iter()
+            .any(|source_account_info| source_account_info.key == mint_info.key)
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?;
+        let mut transfer_fee_config = mint.get_extension::<TransferFeeConfig>()?;
+
+        for source_account_info in source_account_infos {
+            let mut source_data = source_account_info.data.borrow_mut();
+            let mut source = StateWithExtensionsMut::<Account>::unpack(&mut source_data)?;
+            if source.base.mint != *mint_info.key {
+                return Err(TokenError::MintMismatch.into());
             }
+            let mut transfer_fee_amount = source.get_extension::<TransferFeeAmount>()?;
+            transfer_fee_config.withheld_amount = transfer_fee_config
+                .withheld_amount
+                .checked_add(transfer_fee_amount.withheld_amount)
+                .ok_or(TokenError::Overflow)?;
+            transfer_fee_amount.withheld_amount = 0;
+            source.set_extension(&transfer_fee_amount)?;
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::instruction::*;
-    use solana_program::{
-        account_info::IntoAccountInfo, clock::Epoch, instruction::Instruction, program_error,
-        sysvar::rent,
-    };
-    use solana_sdk::account::{
-        create_account_for_test, create_is_signer_account_infos, Account as SolanaAccount,
-    };
+        mint.set_extension(&transfer_fee_config)?;
 
-    struct SyscallStubs {}
-    impl solana_sdk::program_stubs::SyscallStubs for SyscallStubs {
-        fn sol_log(&self, _message: &str) {}
+        Ok(())
+    }
 
-        fn sol_invoke_signed(
-            &self,
-            _instruction: &Instruction,
-            _account_infos: &[AccountInfo],
-            _signers_seeds: &[&[&[u8]]],
-        ) -> ProgramResult {
-            Err(ProgramError::Custom(42)) // Not supported
-        }
+    /// Processes a [WithdrawWithheldTokensFromMint](enum.TokenInstruction.html) instruction.
+    pub fn process_withdraw_withheld_tokens_from_mint(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let dest_account_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
 
-        fn sol_get_clock_sysvar(&self, _var_addr: *mut u8) -> u64 {
-            program_error::UNSUPPORTED_SYSVAR
-        }
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?;
+        let mut transfer_fee_config = mint.get_extension::<TransferFeeConfig>()?;
+        let authority = transfer_fee_config
+            .withdraw_withheld_authority
+            .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+        Self::validate_owner(
+            program_id,
+            &authority,
+            authority_info,
+            account_info_iter.as_slice(),
+        )?;
 
-        fn sol_get_epoch_schedule_sysvar(&self, _var_addr: *mut u8) -> u64 {
-            program_error::UNSUPPORTED_SYSVAR
+        let mut dest_data = dest_account_info.data.borrow_mut();
+        let mut dest = StateWithExtensionsMut::<Account>::unpack(&mut dest_data)?;
+        if dest.base.mint != *mint_info.key {
+            return Err(TokenError::MintMismatch.into());
         }
-
-        #[allow(deprecated)]
-        fn sol_get_fees_sysvar(&self, _var_addr: *mut u8) -> u64 {
-            program_error::UNSUPPORTED_SYSVAR
+        if dest.base.is_frozen() {
+            return Err(TokenError::AccountFrozen.into());
         }
+        dest.base.amount = dest
+            .base
+            .amount
+            .checked_add(transfer_fee_config.withheld_amount)
+            .ok_or(TokenError::Overflow)?;
+        dest.pack_base();
 
-        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
-            unsafe {
-                *(var_addr as *mut _ as *mut Rent) = Rent::default();
-            }
-            solana_program::entrypoint::SUCCESS
-        }
+        transfer_fee_config.withheld_amount = 0;
+        mint.set_extension(&transfer_fee_config)?;
+
+        Ok(())
     }
 
-    fn do_process_instruction(
-        instruction: Instruction,
-        accounts: Vec<&mut SolanaAccount>,
+    /// Processes an [InitializeInterestBearingMint](enum.TokenInstruction.html) instruction.
+    pub fn process_initialize_interest_bearing_mint(
+        accounts: &[AccountInfo],
+        rate_authority: COption<Pubkey>,
+        rate: i16,
     ) -> ProgramResult {
-        {
-            use std::sync::Once;
-            static ONCE: Once = Once::new();
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
 
-            ONCE.call_once(|| {
-                solana_sdk::program_stubs::set_syscall_stubs(Box::new(SyscallStubs {}));
-            });
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut mint_data)?;
+        if mint.base.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
         }
 
-        let mut meta = instruction
-            .accounts
-            .iter()
-            .zip(accounts)
-            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
-            .collect::<Vec<_>>();
+        let unix_timestamp = Clock::get()?.unix_timestamp;
+        mint.init_extension(&InterestBearingConfig::new(
+            rate_authority,
+            rate,
+            unix_timestamp,
+        ))?;
 
-        let account_infos = create_is_signer_account_infos(&mut meta);
-        Processor::process(&instruction.program_id, &account_infos, &instruction.data)
+        Ok(())
     }
 
-    fn do_process_instruction_dups(
-        instruction: Instruction,
-        account_infos: Vec<AccountInfo>,
+    /// Processes an [UpdateInterestRate](enum.TokenInstruction.html) instruction.
+    pub fn process_update_interest_rate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        rate: i16,
     ) -> ProgramResult {
-        Processor::process(&instruction.program_id, &account_infos, &instruction.data)
-    }
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
 
-    fn return_token_error_as_program_error() -> ProgramError {
-        TokenError::MintMismatch.into()
+        let mut interest_bearing_config =
+            StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?
+                .get_extension::<InterestBearingConfig>()?;
+        let rate_authority = interest_bearing_config
+            .rate_authority
+            .ok_or(Into::<ProgramError>::into(TokenError::NoAuthorityExists))?;
+        Self::validate_owner(
+            program_id,
+            &rate_authority,
+            authority_info,
+            account_info_iter.as_slice(),
+        )?;
+
+        let unix_timestamp = Clock::get()?.unix_timestamp;
+        interest_bearing_config
+            .update_rate(rate, unix_timestamp)
+            .ok_or(TokenError::Overflow)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        StateWithExtensionsMut::<Mint>::unpack(&mut mint_data)?
+            .set_extension(&interest_bearing_config)?;
+
+        Ok(())
     }
 
-    fn rent_sysvar() -> SolanaAccount {
-        create_account_for_test(&Rent::default())
+    /// Processes an [InitializeNonTransferableMint](enum.TokenInstruction.html) instruction.
+    pub fn process_initialize_non_transferable_mint(accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut mint_data)?;
+        if mint.base.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        mint.init_extension(&NonTransferable)
     }
 
-    fn mint_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(Mint::get_packed_len())
+    /// Processes an [InitializePermanentDelegate](enum.TokenInstruction.html) instruction.
+    pub fn process_initialize_permanent_delegate(
+        accounts: &[AccountInfo],
+        delegate: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut mint_data)?;
+        if mint.base.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        mint.init_extension(&PermanentDelegate {
+            delegate: COption::Some(delegate),
+        })
     }
 
-    fn account_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(Account::get_packed_len())
+    /// Processes an [InitializeDefaultAccountState](enum.TokenInstruction.html) instruction.
+    pub fn process_initialize_default_account_state(
+        accounts: &[AccountInfo],
+        state: AccountState,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_unchecked(&mut mint_data)?;
+        if mint.base.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+        if state == AccountState::Uninitialized {
+            return Err(TokenError::InvalidState.into());
+        }
+
+        mint.init_extension(&DefaultAccountState { state })
     }
 
-    fn multisig_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(Multisig::get_packed_len())
+    /// Processes a [Batch](enum.TokenInstruction.html) instruction.
+    pub fn process_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        instructions: Vec<BatchedInstruction>,
+    ) -> ProgramResult {
+        let mut remaining_accounts = accounts;
+        for batched_instruction in instructions {
+            let num_accounts = batched_instruction.num_accounts as usize;
+            if remaining_accounts.len() < num_accounts {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+            if let TokenInstruction::Batch { .. } = batched_instruction.instruction {
+                return Err(TokenError::NestedBatch.into());
+            }
+            let (instruction_accounts, rest) = remaining_accounts.split_at(num_accounts);
+            Self::process_instruction(
+                program_id,
+                instruction_accounts,
+                batched_instruction.instruction,
+            )?;
+            remaining_accounts = rest;
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_print_error() {
-        let error = return_token_error_as_program_error();
-        error.print::<TokenError>();
+    fn _process_required_transfer_memos(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        require_incoming_transfer_memos: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let token_account_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+
+        let owner = StateWithExtensions::<Account>::unpack(&token_account_info.data.borrow())?
+            .base
+            .owner;
+        Self::validate_owner(program_id, &owner, owner_info, account_info_iter.as_slice())?;
+
+        let mut account_data = token_account_info.data.borrow_mut();
+        let mut account = StateWithExtensionsMut::<Account>::unpack(&mut account_data)?;
+        let memo_transfer = MemoTransfer {
+            require_incoming_transfer_memos,
+        };
+        if account.get_extension::<MemoTransfer>().is_ok() {
+            account.set_extension(&memo_transfer)
+        } else if require_incoming_transfer_memos {
+            account.init_extension(&memo_transfer)
+        } else {
+            Ok(())
+        }
     }
 
-    #[test]
-    #[should_panic(expected = "Custom(3)")]
-    fn test_error_unwrap() {
-        Err::<(), ProgramError>(return_token_error_as_program_error()).unwrap();
+    /// Processes an [EnableRequiredTransferMemos](enum.TokenInstruction.html) instruction.
+    pub fn process_enable_required_transfer_memos(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::_process_required_transfer_memos(program_id, accounts, true)
     }
 
-    #[test]
-    fn test_unique_account_sizes() {
-        assert_ne!(Mint::get_packed_len(), 0);
-        assert_ne!(Mint::get_packed_len(), Account::get_packed_len());
-        assert_ne!(Mint::get_packed_len(), Multisig::get_packed_len());
-        assert_ne!(Account::get_packed_len(), 0);
-        assert_ne!(Account::get_packed_len(), Multisig::get_packed_len());
-        assert_ne!(Multisig::get_packed_len(), 0);
+    /// Processes a [DisableRequiredTransferMemos](enum.TokenInstruction.html) instruction.
+    pub fn process_disable_required_transfer_memos(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::_process_required_transfer_memos(program_id, accounts, false)
     }
 
-    #[test]
-    fn test_pack_unpack() {
-        // Mint
-        let check = Mint {
-            mint_authority: COption::Some(Pubkey::new(&[1; 32])),
-            supply: 42,
-            decimals: 7,
-            is_initialized: true,
-            freeze_authority: COption::Some(Pubkey::new(&[2; 32])),
-        };
-        let mut packed = vec![0; Mint::get_packed_len() + 1];
-        assert_eq!(
-            Err(ProgramError::InvalidAccountData),
-            Mint::pack(check, &mut packed)
-        );
-        let mut packed = vec![0; Mint::get_packed_len() - 1];
-        assert_eq!(
-            Err(ProgramError::InvalidAccountData),
-            Mint::pack(check, &mut packed)
-        );
-        let mut packed = vec![0; Mint::get_packed_len()];
-        Mint::pack(check, &mut packed).unwrap();
-        let expect = vec![
-            1, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-            1, 1, 1, 1, 1, 1, 1, 42, 0, 0, 0, 0, 0, 0, 0, 7, 1, 1, 0, 0, 0, 2, 2, 2, 2, 2, 2, 2, 2,
-            2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
-        ];
-        assert_eq!(packed, expect);
-        let unpacked = Mint::unpack(&packed).unwrap();
-        assert_eq!(unpacked, check);
+    /// Processes a [Reallocate](enum.TokenInstruction.html) instruction.
+    pub fn process_reallocate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_extension_types: Vec<ExtensionType>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let token_account_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
 
-        // Account
-        let check = Account {
-            mint: Pubkey::new(&[1; 32]),
-            owner: Pubkey::new(&[2; 32]),
-            amount: 3,
-            delegate: COption::Some(Pubkey::new(&[4; 32])),
-            state: AccountState::Frozen,
-            is_native: COption::Some(5),
-            delegated_amount: 6,
-            close_authority: COption::Some(Pubkey::new(&[7; 32])),
+        let mut extension_types = {
+            let account_data = token_account_info.data.borrow();
+            let account = StateWithExtensions::<Account>::unpack(&account_data)?;
+            Self::validate_owner(
+                program_id,
+                &account.base.owner,
+                owner_info,
+                account_info_iter.as_slice(),
+            )?;
+            account.get_extension_types()?
         };
-        let mut packed = vec![0; Account::get_packed_len() + 1];
-        assert_eq!(
+
+        for extension_type in new_extension_types {
+            if extension_type.get_account_type() != AccountType::Account {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            if !extension_types.contains(&extension_type) {
+                extension_types.push(extension_type);
+            }
+        }
+        let account_len = ExtensionType::get_account_len::<Account>(&extension_types);
+        if token_account_info.data_len() >= account_len {
+            return Ok(());
+        }
+        token_account_info.realloc(account_len, false)?;
+
+        let minimum_balance = Rent::get()?.minimum_balance(account_len);
+        let additional_lamports = minimum_balance.saturating_sub(token_account_info.lamports());
+        if additional_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    token_account_info.key,
+                    additional_lamports,
+                ),
+                &[
+                    payer_info.clone(),
+                    token_account_info.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+
+        // legacy accounts gain an account type, marking them as extended
+        let mut account_data = token_account_info.data.borrow_mut();
+        StateWithExtensionsMut::<Account>::unpack_unchecked(&mut account_data)?.init_account_type();
+        Ok(())
+    }
+
+    /// Processes an [Instruction](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = TokenInstruction::unpack(input)?;
+        Self::process_instruction(program_id, accounts, instruction)
+    }
+
+    /// Processes an unpacked [TokenInstruction](enum.TokenInstruction.html).
+    pub fn process_instruction(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        instruction: TokenInstruction,
+    ) -> ProgramResult {
+        match instruction {
+            TokenInstruction::InitializeMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => {
+                msg!("Instruction: InitializeMint");
+                Self::process_initialize_mint(accounts, decimals, mint_authority, freeze_authority)
+            }
+            TokenInstruction::InitializeMint2 {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => {
+                msg!("Instruction: InitializeMint2");
+                Self::process_initialize_mint2(accounts, decimals, mint_authority, freeze_authority)
+            }
+            TokenInstruction::InitializeAccount => {
+                msg!("Instruction: InitializeAccount");
+                Self::process_initialize_account(accounts)
+            }
+            TokenInstruction::InitializeAccount2 { owner } => {
+                msg!("Instruction: InitializeAccount2");
+                Self::process_initialize_account2(accounts, owner)
+            }
+            TokenInstruction::InitializeAccount3 { owner } => {
+                msg!("Instruction: InitializeAccount3");
+                Self::process_initialize_account3(accounts, owner)
+            }
+            TokenInstruction::InitializeMultisig { m } => {
+                msg!("Instruction: InitializeMultisig");
+                Self::process_initialize_multisig(accounts, m)
+            }
+            TokenInstruction::InitializeMultisig2 { m } => {
+                msg!("Instruction: InitializeMultisig2");
+                Self::process_initialize_multisig2(accounts, m)
+            }
+            TokenInstruction::Transfer { amount } => {
+                msg!("Instruction: Transfer");
+                Self::process_transfer(program_id, accounts, amount, None)
+            }
+            TokenInstruction::Approve { amount } => {
+                msg!("Instruction: Approve");
+                Self::process_approve(program_id, accounts, amount, None)
+            }
+            TokenInstruction::Revoke => {
+                msg!("Instruction: Revoke");
+                Self::process_revoke(program_id, accounts)
+            }
+            TokenInstruction::SetAuthority {
+                authority_type,
+                new_authority,
+            } => {
+                msg!("Instruction: SetAuthority");
+                Self::process_set_authority(program_id, accounts, authority_type, new_authority)
+            }
+            TokenInstruction::MintTo { amount } => {
+                msg!("Instruction: MintTo");
+                Self::process_mint_to(program_id, accounts, amount, None)
+            }
+            TokenInstruction::Burn { amount } => {
+                msg!("Instruction: Burn");
+                Self::process_burn(program_id, accounts, amount, None)
+            }
+            TokenInstruction::CloseAccount => {
+                msg!("Instruction: CloseAccount");
+                Self::process_close_account(program_id, accounts)
+            }
+            TokenInstruction::FreezeAccount => {
+                msg!("Instruction: FreezeAccount");
+                Self::process_toggle_freeze_account(program_id, accounts, true)
+            }
+            TokenInstruction::ThawAccount => {
+                msg!("Instruction: ThawAccount");
+                Self::process_toggle_freeze_account(program_id, accounts, false)
+            }
+            TokenInstruction::TransferChecked { amount, decimals } => {
+                msg!("Instruction: TransferChecked");
+                Self::process_transfer(program_id, accounts, amount, Some(decimals))
+            }
+            TokenInstruction::ApproveChecked { amount, decimals } => {
+                msg!("Instruction: ApproveChecked");
+                Self::process_approve(program_id, accounts, amount, Some(decimals))
+            }
+            TokenInstruction::MintToChecked { amount, decimals } => {
+                msg!("Instruction: MintToChecked");
+                Self::process_mint_to(program_id, accounts, amount, Some(decimals))
+            }
+            TokenInstruction::BurnChecked { amount, decimals } => {
+                msg!("Instruction: BurnChecked");
+                Self::process_burn(program_id, accounts, amount, Some(decimals))
+            }
+            TokenInstruction::SyncNative => {
+                msg!("Instruction: SyncNative");
+                Self::process_sync_native(program_id, accounts)
+            }
+            TokenInstruction::InitializeTransferFeeConfig {
+                transfer_fee_config_authority,
+                withdraw_withheld_authority,
+                transfer_fee_basis_points,
+                maximum_fee,
+            } => {
+                msg!("Instruction: InitializeTransferFeeConfig");
+                Self::process_initialize_transfer_fee_config(
+                    accounts,
+                    transfer_fee_config_authority,
+                    withdraw_withheld_authority,
+                    transfer_fee_basis_points,
+                    maximum_fee,
+                )
+            }
+            TokenInstruction::SetTransferFee {
+                transfer_fee_basis_points,
+                maximum_fee,
+            } => {
+                msg!("Instruction: SetTransferFee");
+                Self::process_set_transfer_fee(
+                    program_id,
+                    accounts,
+                    transfer_fee_basis_points,
+                    maximum_fee,
+                )
+            }
+            TokenInstruction::WithdrawWithheldTokensFromAccounts { num_token_accounts } => {
+                msg!("Instruction: WithdrawWithheldTokensFromAccounts");
+                Self::process_withdraw_withheld_tokens_from_accounts(
+                    program_id,
+                    accounts,
+                    num_token_accounts,
+                )
+            }
+            TokenInstruction::InitializeInterestBearingMint {
+                rate_authority,
+                rate,
+            } => {
+                msg!("Instruction: InitializeInterestBearingMint");
+                Self::process_initialize_interest_bearing_mint(accounts, rate_authority, rate)
+            }
+            TokenInstruction::UpdateInterestRate { rate } => {
+                msg!("Instruction: UpdateInterestRate");
+                Self::process_update_interest_rate(program_id, accounts, rate)
+            }
+            TokenInstruction::InitializeNonTransferableMint => {
+                msg!("Instruction: InitializeNonTransferableMint");
+                Self::process_initialize_non_transferable_mint(accounts)
+            }
+            TokenInstruction::InitializePermanentDelegate { delegate } => {
+                msg!("Instruction: InitializePermanentDelegate");
+                Self::process_initialize_permanent_delegate(accounts, delegate)
+            }
+            TokenInstruction::EnableRequiredTransferMemos => {
+                msg!("Instruction: EnableRequiredTransferMemos");
+                Self::process_enable_required_transfer_memos(program_id, accounts)
+            }
+            TokenInstruction::DisableRequiredTransferMemos => {
+                msg!("Instruction: DisableRequiredTransferMemos");
+                Self::process_disable_required_transfer_memos(program_id, accounts)
+            }
+            TokenInstruction::InitializeDefaultAccountState { state } => {
+                msg!("Instruction: InitializeDefaultAccountState");
+                Self::process_initialize_default_account_state(accounts, state)
+            }
+            TokenInstruction::Reallocate { extension_types } => {
+                msg!("Instruction: Reallocate");
+                Self::process_reallocate(program_id, accounts, extension_types)
+            }
+            TokenInstruction::Batch { instructions } => {
+                msg!("Instruction: Batch");
+                Self::process_batch(program_id, accounts, instructions)
+            }
+            TokenInstruction::HarvestWithheldTokensToMint => {
+                msg!("Instruction: HarvestWithheldTokensToMint");
+                Self::process_harvest_withheld_tokens_to_mint(accounts)
+            }
+            TokenInstruction::WithdrawWithheldTokensFromMint => {
+                msg!("Instruction: WithdrawWithheldTokensFromMint");
+                Self::process_withdraw_withheld_tokens_from_mint(program_id, accounts)
+            }
+        }
+    }
+
+    /// Validates owner(s) are present
+    pub fn validate_owner(
+        program_id: &Pubkey,
+        expected_owner: &Pubkey,
+        owner_account_info: &AccountInfo,
+        signers: &[AccountInfo],
+    ) -> ProgramResult {
+        if expected_owner != owner_account_info.key {
+            return Err(TokenError::OwnerMismatch.into());
+        }
+        if program_id == owner_account_info.owner
+            && owner_account_info.data_len() == Multisig::get_packed_len()
+        {
+            let multisig = Multisig::unpack(&owner_account_info.data.borrow())?;
+            let mut num_signers = 0;
+            let mut matched = [false; MAX_SIGNERS];
+            for signer in signers.iter() {
+                for (position, key) in multisig.signers[0..multisig.n as usize].iter().enumerate() {
+                    if key == signer.key && !matched[position] {
+                        if !signer.is_signer {
+                            return Err(ProgramError::MissingRequiredSignature);
+                        }
+                        matched[position] = true;
+                        num_signers += 1;
+                    }
+                }
+            }
+            if num_signers < multisig.m {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            return Ok(());
+        } else if !owner_account_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Ok(())
+    }
+}
+
+impl PrintProgramError for TokenError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        match self {
+            TokenError::NotRentExempt => msg!("Error: Lamport balance below rent-exempt threshold"),
+            TokenError::InsufficientFunds => msg!("Error: insufficient funds"),
+            TokenError::InvalidMint => msg!("Error: Invalid Mint"),
+            TokenError::MintMismatch => msg!("Error: Account not associated with this Mint"),
+            TokenError::OwnerMismatch => msg!("Error: owner does not match"),
+            TokenError::FixedSupply => msg!("Error: the total supply of this token is fixed"),
+            TokenError::AlreadyInUse => msg!("Error: account or token already in use"),
+            TokenError::InvalidNumberOfProvidedSigners => {
+                msg!("Error: Invalid number of provided signers")
+            }
+            TokenError::InvalidNumberOfRequiredSigners => {
+                msg!("Error: Invalid number of required signers")
+            }
+            TokenError::UninitializedState => msg!("Error: State is uninitialized"),
+            TokenError::NativeNotSupported => {
+                msg!("Error: Instruction does not support native tokens")
+            }
+            TokenError::NonNativeHasBalance => {
+                msg!("Error: Non-native account can only be closed if its balance is zero")
+            }
+            TokenError::InvalidInstruction => msg!("Error: Invalid instruction"),
+            TokenError::InvalidState => msg!("Error: Invalid account state for operation"),
+            TokenError::Overflow => msg!("Error: Operation overflowed"),
+            TokenError::AuthorityTypeNotSupported => {
+                msg!("Error: Account does not support specified authority type")
+            }
+            TokenError::MintCannotFreeze => msg!("Error: This token mint cannot freeze accounts"),
+            TokenError::AccountFrozen => msg!("Error: Account is frozen"),
+            TokenError::MintDecimalsMismatch => {
+                msg!("Error: decimals different from the Mint decimals")
+            }
+            TokenError::NonNativeNotSupported => {
+                msg!("Error: Instruction does not support non-native tokens")
+            }
+            TokenError::ExtensionNotFound => msg!("Error: Extension not found in account data"),
+            TokenError::ExtensionAlreadyInitialized => {
+                msg!("Error: Extension already initialized on this account")
+            }
+            TokenError::MintRequiredForTransfer => {
+                msg!("Error: Transfers of tokens with extensions must provide the mint")
+            }
+            TokenError::TransferFeeExceedsMaximum => {
+                msg!("Error: Transfer fee exceeds the maximum of 10,000 basis points")
+            }
+            TokenError::NoAuthorityExists => {
+                msg!("Error: No authority exists to perform the desired operation")
+            }
+            TokenError::AccountHasWithheldTransferFees => {
+                msg!("Error: Account still has withheld transfer fees")
+            }
+            TokenError::NonTransferable => msg!("Error: Tokens of this mint are non-transferable"),
+            TokenError::NoMemo => {
+                msg!("Error: No memo in previous instruction; required for recipient to receive a transfer")
+            }
+            TokenError::NestedBatch => msg!("Error: Batch instructions can't be nested"),
+            TokenError::MemoTransferNotTopLevel => {
+                msg!("Error: Transfers into accounts that require memos must be top-level instructions")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::*;
+    use solana_program::{
+        account_info::IntoAccountInfo,
+        clock::Epoch,
+        entrypoint::MAX_PERMITTED_DATA_INCREASE,
+        instruction::{AccountMeta, Instruction},
+        program_error,
+        system_instruction::SystemInstruction,
+        system_program,
+        sysvar::rent,
+    };
+    use solana_sdk::{
+        account::{
+            create_account_for_test, create_is_signer_account_infos, Account as SolanaAccount,
+        },
+        program_utils::limited_deserialize,
+    };
+
+    struct SyscallStubs {}
+    impl solana_sdk::program_stubs::SyscallStubs for SyscallStubs {
+        fn sol_log(&self, _message: &str) {}
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            // only system transfers are supported, to fund reallocations
+            match limited_deserialize(&instruction.data) {
+                Ok(SystemInstruction::Transfer { lamports })
+                    if instruction.program_id == system_program::id() =>
+                {
+                    let find_account_info = |index: usize| {
+                        account_infos
+                            .iter()
+                            .find(|account_info| {
+                                *account_info.key == instruction.accounts[index].pubkey
+                            })
+                            .unwrap()
+                    };
+                    **find_account_info(0).try_borrow_mut_lamports()? -= lamports;
+                    **find_account_info(1).try_borrow_mut_lamports()? += lamports;
+                    Ok(())
+                }
+                _ => Err(ProgramError::Custom(42)), // Not supported
+            }
+        }
+
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe {
+                *(var_addr as *mut _ as *mut Clock) = Clock::default();
+            }
+            solana_program::entrypoint::SUCCESS
+        }
+
+        fn sol_get_epoch_schedule_sysvar(&self, _var_addr: *mut u8) -> u64 {
+            program_error::UNSUPPORTED_SYSVAR
+        }
+
+        #[allow(deprecated)]
+        fn sol_get_fees_sysvar(&self, _var_addr: *mut u8) -> u64 {
+            program_error::UNSUPPORTED_SYSVAR
+        }
+
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe {
+                *(var_addr as *mut _ as *mut Rent) = Rent::default();
+            }
+            solana_program::entrypoint::SUCCESS
+        }
+    }
+
+    fn do_process_instruction(
+        instruction: Instruction,
+        accounts: Vec<&mut SolanaAccount>,
+    ) -> ProgramResult {
+        {
+            use std::sync::Once;
+            static ONCE: Once = Once::new();
+
+            ONCE.call_once(|| {
+                solana_sdk::program_stubs::set_syscall_stubs(Box::new(SyscallStubs {}));
+            });
+        }
+
+        let mut meta = instruction
+            .accounts
+            .iter()
+            .zip(accounts)
+            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
+            .collect::<Vec<_>>();
+
+        let account_infos = create_is_signer_account_infos(&mut meta);
+        Processor::process(&instruction.program_id, &account_infos, &instruction.data)
+    }
+
+    /// Key of an account laid out the way the runtime serializes it for programs, preceded by
+    /// the account's original data length
+    #[repr(C)]
+    struct SerializedKey {
+        original_data_len: u32,
+        key: Pubkey,
+    }
+
+    /// Like `do_process_instruction`, but with the first account laid out the way the runtime
+    /// serializes it for programs, so that it can be grown with `AccountInfo::realloc`
+    fn do_process_instruction_with_realloc(
+        instruction: Instruction,
+        mut accounts: Vec<&mut SolanaAccount>,
+    ) -> ProgramResult {
+        let account = accounts.remove(0);
+        let data_len = account.data.len();
+        let serialized_key = SerializedKey {
+            original_data_len: data_len as u32,
+            key: instruction.accounts[0].pubkey,
+        };
+        // the data is preceded by its current length and followed by room to grow, and its
+        // length is written as a `u64` so the buffer must be aligned accordingly
+        let mut words = vec![0u64; 2 + (data_len + MAX_PERMITTED_DATA_INCREASE) / 8];
+        let buffer = unsafe {
+            std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 8)
+        };
+        buffer[..8].copy_from_slice(&(data_len as u64).to_le_bytes());
+        buffer[8..8 + data_len].copy_from_slice(&account.data);
+        let mut lamports = account.lamports;
+        let account_info = AccountInfo::new(
+            &serialized_key.key,
+            instruction.accounts[0].is_signer,
+            instruction.accounts[0].is_writable,
+            &mut lamports,
+            &mut buffer[8..8 + data_len],
+            &account.owner,
+            false,
+            Epoch::default(),
+        );
+
+        let mut meta = instruction.accounts[1..]
+            .iter()
+            .zip(accounts)
+            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
+            .collect::<Vec<_>>();
+        let mut account_infos = vec![account_info.clone()];
+        account_infos.extend(create_is_signer_account_infos(&mut meta));
+        let result = Processor::process(&instruction.program_id, &account_infos, &instruction.data);
+
+        account.lamports = account_info.lamports();
+        account.data = account_info.data.borrow().to_vec();
+        result
+    }
+
+    fn do_process_instruction_dups(
+        instruction: Instruction,
+        account_infos: Vec<AccountInfo>,
+    ) -> ProgramResult {
+        Processor::process(&instruction.program_id, &account_infos, &instruction.data)
+    }
+
+    fn return_token_error_as_program_error() -> ProgramError {
+        TokenError::MintMismatch.into()
+    }
+
+    fn rent_sysvar() -> SolanaAccount {
+        create_account_for_test(&Rent::default())
+    }
+
+    fn mint_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(Mint::get_packed_len())
+    }
+
+    fn account_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(Account::get_packed_len())
+    }
+
+    fn multisig_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(Multisig::get_packed_len())
+    }
+
+    fn instructions_sysvar(instructions: &[Instruction], current_index: u16) -> SolanaAccount {
+        let mut data = (instructions.len() as u16).to_le_bytes().to_vec();
+        let mut serialized_instructions = vec![];
+        for instruction in instructions {
+            let offset = 2 + 2 * instructions.len() + serialized_instructions.len();
+            data.extend_from_slice(&(offset as u16).to_le_bytes());
+            serialized_instructions
+                .extend_from_slice(&(instruction.accounts.len() as u16).to_le_bytes());
+            for account_meta in &instruction.accounts {
+                serialized_instructions
+                    .push(account_meta.is_signer as u8 | (account_meta.is_writable as u8) << 1);
+                serialized_instructions.extend_from_slice(account_meta.pubkey.as_ref());
+            }
+            serialized_instructions.extend_from_slice(instruction.program_id.as_ref());
+            serialized_instructions
+                .extend_from_slice(&(instruction.data.len() as u16).to_le_bytes());
+            serialized_instructions.extend_from_slice(&instruction.data);
+        }
+        data.extend_from_slice(&serialized_instructions);
+        data.extend_from_slice(&current_index.to_le_bytes());
+        SolanaAccount {
+            lamports: 1,
+            data,
+            owner: solana_program::sysvar::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_print_error() {
+        let error = return_token_error_as_program_error();
+        error.print::<TokenError>();
+    }
+
+    #[test]
+    #[should_panic(expected = "Custom(3)")]
+    fn test_error_unwrap() {
+        Err::<(), ProgramError>(return_token_error_as_program_error()).unwrap();
+    }
+
+    #[test]
+    fn test_unique_account_sizes() {
+        assert_ne!(Mint::get_packed_len(), 0);
+        assert_ne!(Mint::get_packed_len(), Account::get_packed_len());
+        assert_ne!(Mint::get_packed_len(), Multisig::get_packed_len());
+        assert_ne!(Account::get_packed_len(), 0);
+        assert_ne!(Account::get_packed_len(), Multisig::get_packed_len());
+        assert_ne!(Multisig::get_packed_len(), 0);
+    }
+
+    #[test]
+    fn test_pack_unpack() {
+        // Mint
+        let check = Mint {
+            mint_authority: COption::Some(Pubkey::new(&[1; 32])),
+            supply: 42,
+            decimals: 7,
+            is_initialized: true,
+            freeze_authority: COption::Some(Pubkey::new(&[2; 32])),
+        };
+        let mut packed = vec![0; Mint::get_packed_len() + 1];
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            Mint::pack(check, &mut packed)
+        );
+        let mut packed = vec![0; Mint::get_packed_len() - 1];
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            Mint::pack(check, &mut packed)
+        );
+        let mut packed = vec![0; Mint::get_packed_len()];
+        Mint::pack(check, &mut packed).unwrap();
+        let expect = vec![
+            1, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1, 42, 0, 0, 0, 0, 0, 0, 0, 7, 1, 1, 0, 0, 0, 2, 2, 2, 2, 2, 2, 2, 2,
+            2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+        ];
+        assert_eq!(packed, expect);
+        let unpacked = Mint::unpack(&packed).unwrap();
+        assert_eq!(unpacked, check);
+
+        // Account
+        let check = Account {
+            mint: Pubkey::new(&[1; 32]),
+            owner: Pubkey::new(&[2; 32]),
+            amount: 3,
+            delegate: COption::Some(Pubkey::new(&[4; 32])),
+            state: AccountState::Frozen,
+            is_native: COption::Some(5),
+            delegated_amount: 6,
+            close_authority: COption::Some(Pubkey::new(&[7; 32])),
+        };
+        let mut packed = vec![0; Account::get_packed_len() + 1];
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            Account::pack(check, &mut packed)
+        );
+        let mut packed = vec![0; Account::get_packed_len() - 1];
+        assert_eq!(
             Err(ProgramError::InvalidAccountData),
             Account::pack(check, &mut packed)
         );
-        let mut packed = vec![0; Account::get_packed_len() - 1];
+        let mut packed = vec![0; Account::get_packed_len()];
+        Account::pack(check, &mut packed).unwrap();
+        let expect = vec![
+            1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+            2, 2, 2, 2, 2, 2, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+            4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 2, 1, 0, 0, 0, 5, 0, 0,
+            0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        ];
+        assert_eq!(packed, expect);
+        let unpacked = Account::unpack(&packed).unwrap();
+        assert_eq!(unpacked, check);
+
+        // Multisig
+        let check = Multisig {
+            m: 1,
+            n: 2,
+            is_initialized: true,
+            signers: [Pubkey::new(&[3; 32]); MAX_SIGNERS],
+        };
+        let mut packed = vec![0; Multisig::get_packed_len() + 1];
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            Multisig::pack(check, &mut packed)
+        );
+        let mut packed = vec![0; Multisig::get_packed_len() - 1];
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            Multisig::pack(check, &mut packed)
+        );
+        let mut packed = vec![0; Multisig::get_packed_len()];
+        Multisig::pack(check, &mut packed).unwrap();
+        let expect = vec![
+            1, 2, 1, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+            3, 3, 3, 3, 3, 3, 3,
+        ];
+        assert_eq!(packed, expect);
+        let unpacked = Multisig::unpack(&packed).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_initialize_mint() {
+        let program_id = crate::id();
+        let owner_key = Pubkey::new_unique();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account = SolanaAccount::new(42, Mint::get_packed_len(), &program_id);
+        let mint2_key = Pubkey::new_unique();
+        let mut mint2_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mut rent_sysvar = rent_sysvar();
+
+        // mint is not rent exempt
+        assert_eq!(
+            Err(TokenError::NotRentExempt.into()),
+            do_process_instruction(
+                initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+                vec![&mut mint_account, &mut rent_sysvar]
+            )
+        );
+
+        mint_account.lamports = mint_minimum_balance();
+
+        // create new mint
+        do_process_instruction(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        // create twice
+        assert_eq!(
+            Err(TokenError::AlreadyInUse.into()),
+            do_process_instruction(
+                initialize_mint(&program_id, &mint_key, &owner_key, None, 2,).unwrap(),
+                vec![&mut mint_account, &mut rent_sysvar]
+            )
+        );
+
+        // create another mint that can freeze
+        do_process_instruction(
+            initialize_mint(&program_id, &mint2_key, &owner_key, Some(&owner_key), 2).unwrap(),
+            vec![&mut mint2_account, &mut rent_sysvar],
+        )
+        .unwrap();
+        let mint = Mint::unpack_unchecked(&mint2_account.data).unwrap();
+        assert_eq!(mint.freeze_authority, COption::Some(owner_key));
+    }
+
+    #[test]
+    fn test_initialize_mint2() {
+        let program_id = crate::id();
+        let owner_key = Pubkey::new_unique();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account = SolanaAccount::new(42, Mint::get_packed_len(), &program_id);
+        let mint2_key = Pubkey::new_unique();
+        let mut mint2_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+
+        // mint is not rent exempt
+        assert_eq!(
+            Err(TokenError::NotRentExempt.into()),
+            do_process_instruction(
+                initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+                vec![&mut mint_account]
+            )
+        );
+
+        mint_account.lamports = mint_minimum_balance();
+
+        // create new mint
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+
+        // create twice
+        assert_eq!(
+            Err(TokenError::AlreadyInUse.into()),
+            do_process_instruction(
+                initialize_mint2(&program_id, &mint_key, &owner_key, None, 2,).unwrap(),
+                vec![&mut mint_account]
+            )
+        );
+
+        // create another mint that can freeze
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint2_key, &owner_key, Some(&owner_key), 2).unwrap(),
+            vec![&mut mint2_account],
+        )
+        .unwrap();
+        let mint = Mint::unpack_unchecked(&mint2_account.data).unwrap();
+        assert_eq!(mint.freeze_authority, COption::Some(owner_key));
+    }
+
+    #[test]
+    fn test_initialize_mint_account() {
+        let program_id = crate::id();
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(42, Account::get_packed_len(), &program_id);
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mut rent_sysvar = rent_sysvar();
+
+        // account is not rent exempt
+        assert_eq!(
+            Err(TokenError::NotRentExempt.into()),
+            do_process_instruction(
+                initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut owner_account,
+                    &mut rent_sysvar
+                ],
+            )
+        );
+
+        account_account.lamports = account_minimum_balance();
+
+        // mint is not valid (not initialized)
+        assert_eq!(
+            Err(TokenError::InvalidMint.into()),
+            do_process_instruction(
+                initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut owner_account,
+                    &mut rent_sysvar
+                ],
+            )
+        );
+
+        // create mint
+        do_process_instruction(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        // create account
+        do_process_instruction(
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create twice
+        assert_eq!(
+            Err(TokenError::AlreadyInUse.into()),
+            do_process_instruction(
+                initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut owner_account,
+                    &mut rent_sysvar
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn test_transfer_dups() {
+        let program_id = crate::id();
+        let account1_key = Pubkey::new_unique();
+        let mut account1_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let mut account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let mut account2_info: AccountInfo = (&account2_key, false, &mut account2_account).into();
+        let account3_key = Pubkey::new_unique();
+        let mut account3_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account3_info: AccountInfo = (&account3_key, false, &mut account3_account).into();
+        let account4_key = Pubkey::new_unique();
+        let mut account4_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account4_info: AccountInfo = (&account4_key, true, &mut account4_account).into();
+        let multisig_key = Pubkey::new_unique();
+        let mut multisig_account = SolanaAccount::new(
+            multisig_minimum_balance(),
+            Multisig::get_packed_len(),
+            &program_id,
+        );
+        let multisig_info: AccountInfo = (&multisig_key, true, &mut multisig_account).into();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner_info: AccountInfo = (&owner_key, true, &mut owner_account).into();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mint_info: AccountInfo = (&mint_key, false, &mut mint_account).into();
+        let rent_key = rent::id();
+        let mut rent_sysvar = rent_sysvar();
+        let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
+
+        // create mint
+        do_process_instruction_dups(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![mint_info.clone(), rent_info.clone()],
+        )
+        .unwrap();
+
+        // create account
+        do_process_instruction_dups(
+            initialize_account(&program_id, &account1_key, &mint_key, &account1_key).unwrap(),
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account1_info.clone(),
+                rent_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction_dups(
+            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                account2_info.clone(),
+                mint_info.clone(),
+                owner_info.clone(),
+                rent_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // mint to account
+        do_process_instruction_dups(
+            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
+
+        // source-owner transfer
+        do_process_instruction_dups(
+            transfer(
+                &program_id,
+                &account1_key,
+                &account2_key,
+                &account1_key,
+                &[],
+                500,
+            )
+            .unwrap(),
+            vec![
+                account1_info.clone(),
+                account2_info.clone(),
+                account1_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // source-owner TransferChecked
+        do_process_instruction_dups(
+            transfer_checked(
+                &program_id,
+                &account1_key,
+                &mint_key,
+                &account2_key,
+                &account1_key,
+                &[],
+                500,
+                2,
+            )
+            .unwrap(),
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account2_info.clone(),
+                account1_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // source-delegate transfer
+        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
+        account.amount = 1000;
+        account.delegated_amount = 1000;
+        account.delegate = COption::Some(account1_key);
+        account.owner = owner_key;
+        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
+
+        do_process_instruction_dups(
+            transfer(
+                &program_id,
+                &account1_key,
+                &account2_key,
+                &account1_key,
+                &[],
+                500,
+            )
+            .unwrap(),
+            vec![
+                account1_info.clone(),
+                account2_info.clone(),
+                account1_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // source-delegate TransferChecked
+        do_process_instruction_dups(
+            transfer_checked(
+                &program_id,
+                &account1_key,
+                &mint_key,
+                &account2_key,
+                &account1_key,
+                &[],
+                500,
+                2,
+            )
+            .unwrap(),
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account2_info.clone(),
+                account1_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // test destination-owner transfer
+        do_process_instruction_dups(
+            initialize_account(&program_id, &account3_key, &mint_key, &account2_key).unwrap(),
+            vec![
+                account3_info.clone(),
+                mint_info.clone(),
+                account2_info.clone(),
+                rent_info.clone(),
+            ],
+        )
+        .unwrap();
+        do_process_instruction_dups(
+            mint_to(&program_id, &mint_key, &account3_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account3_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
+
+        account1_info.is_signer = false;
+        account2_info.is_signer = true;
+        do_process_instruction_dups(
+            transfer(
+                &program_id,
+                &account3_key,
+                &account2_key,
+                &account2_key,
+                &[],
+                500,
+            )
+            .unwrap(),
+            vec![
+                account3_info.clone(),
+                account2_info.clone(),
+                account2_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // destination-owner TransferChecked
+        do_process_instruction_dups(
+            transfer_checked(
+                &program_id,
+                &account3_key,
+                &mint_key,
+                &account2_key,
+                &account2_key,
+                &[],
+                500,
+                2,
+            )
+            .unwrap(),
+            vec![
+                account3_info.clone(),
+                mint_info.clone(),
+                account2_info.clone(),
+                account2_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // test source-multisig signer
+        do_process_instruction_dups(
+            initialize_multisig(&program_id, &multisig_key, &[&account4_key], 1).unwrap(),
+            vec![
+                multisig_info.clone(),
+                rent_info.clone(),
+                account4_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        do_process_instruction_dups(
+            initialize_account(&program_id, &account4_key, &mint_key, &multisig_key).unwrap(),
+            vec![
+                account4_info.clone(),
+                mint_info.clone(),
+                multisig_info.clone(),
+                rent_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        do_process_instruction_dups(
+            mint_to(&program_id, &mint_key, &account4_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account4_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
+
+        // source-multisig-signer transfer
+        do_process_instruction_dups(
+            transfer(
+                &program_id,
+                &account4_key,
+                &account2_key,
+                &multisig_key,
+                &[&account4_key],
+                500,
+            )
+            .unwrap(),
+            vec![
+                account4_info.clone(),
+                account2_info.clone(),
+                multisig_info.clone(),
+                account4_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // source-multisig-signer TransferChecked
+        do_process_instruction_dups(
+            transfer_checked(
+                &program_id,
+                &account4_key,
+                &mint_key,
+                &account2_key,
+                &multisig_key,
+                &[&account4_key],
+                500,
+                2,
+            )
+            .unwrap(),
+            vec![
+                account4_info.clone(),
+                mint_info.clone(),
+                account2_info.clone(),
+                multisig_info.clone(),
+                account4_info.clone(),
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_transfer() {
+        let program_id = crate::id();
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account3_key = Pubkey::new_unique();
+        let mut account3_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let delegate_key = Pubkey::new_unique();
+        let mut delegate_account = SolanaAccount::default();
+        let mismatch_key = Pubkey::new_unique();
+        let mut mismatch_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mint2_key = Pubkey::new_unique();
+        let mut rent_sysvar = rent_sysvar();
+
+        // create mint
+        do_process_instruction(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        // create account
+        do_process_instruction(
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account2_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account3_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create mismatch account
+        do_process_instruction(
+            initialize_account(&program_id, &mismatch_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut mismatch_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+        let mut account = Account::unpack_unchecked(&mismatch_account.data).unwrap();
+        account.mint = mint2_key;
+        Account::pack(account, &mut mismatch_account.data).unwrap();
+
+        // mint to account
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+
+        // missing signer
+        let mut instruction = transfer(
+            &program_id,
+            &account_key,
+            &account2_key,
+            &owner_key,
+            &[],
+            1000,
+        )
+        .unwrap();
+        instruction.accounts[2].is_signer = false;
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            do_process_instruction(
+                instruction,
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+
+        // mismatch mint
+        assert_eq!(
+            Err(TokenError::MintMismatch.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &mismatch_key,
+                    &owner_key,
+                    &[],
+                    1000
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut mismatch_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+
+        // missing owner
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &owner2_key,
+                    &[],
+                    1000
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut owner2_account,
+                ],
+            )
+        );
+
+        // transfer
+        do_process_instruction(
+            transfer(
+                &program_id,
+                &account_key,
+                &account2_key,
+                &owner_key,
+                &[],
+                1000,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut account2_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // insufficient funds
+        assert_eq!(
+            Err(TokenError::InsufficientFunds.into()),
+            do_process_instruction(
+                transfer(&program_id, &account_key, &account2_key, &owner_key, &[], 1).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+
+        // transfer half back
+        do_process_instruction(
+            transfer(
+                &program_id,
+                &account2_key,
+                &account_key,
+                &owner_key,
+                &[],
+                500,
+            )
+            .unwrap(),
+            vec![
+                &mut account2_account,
+                &mut account_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // incorrect decimals
+        assert_eq!(
+            Err(TokenError::MintDecimalsMismatch.into()),
+            do_process_instruction(
+                transfer_checked(
+                    &program_id,
+                    &account2_key,
+                    &mint_key,
+                    &account_key,
+                    &owner_key,
+                    &[],
+                    1,
+                    10 // <-- incorrect decimals
+                )
+                .unwrap(),
+                vec![
+                    &mut account2_account,
+                    &mut mint_account,
+                    &mut account_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+
+        // incorrect mint
+        assert_eq!(
+            Err(TokenError::MintMismatch.into()),
+            do_process_instruction(
+                transfer_checked(
+                    &program_id,
+                    &account2_key,
+                    &account3_key, // <-- incorrect mint
+                    &account_key,
+                    &owner_key,
+                    &[],
+                    1,
+                    2
+                )
+                .unwrap(),
+                vec![
+                    &mut account2_account,
+                    &mut account3_account, // <-- incorrect mint
+                    &mut account_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+        // transfer rest with explicit decimals
+        do_process_instruction(
+            transfer_checked(
+                &program_id,
+                &account2_key,
+                &mint_key,
+                &account_key,
+                &owner_key,
+                &[],
+                500,
+                2,
+            )
+            .unwrap(),
+            vec![
+                &mut account2_account,
+                &mut mint_account,
+                &mut account_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // insufficient funds
+        assert_eq!(
+            Err(TokenError::InsufficientFunds.into()),
+            do_process_instruction(
+                transfer(&program_id, &account2_key, &account_key, &owner_key, &[], 1).unwrap(),
+                vec![
+                    &mut account2_account,
+                    &mut account_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+
+        // approve delegate
+        do_process_instruction(
+            approve(
+                &program_id,
+                &account_key,
+                &delegate_key,
+                &owner_key,
+                &[],
+                100,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut delegate_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // transfer via delegate
+        do_process_instruction(
+            transfer(
+                &program_id,
+                &account_key,
+                &account2_key,
+                &delegate_key,
+                &[],
+                100,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut account2_account,
+                &mut delegate_account,
+            ],
+        )
+        .unwrap();
+
+        // insufficient funds approved via delegate
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &delegate_key,
+                    &[],
+                    100
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut delegate_account,
+                ],
+            )
+        );
+
+        // transfer rest
+        do_process_instruction(
+            transfer(
+                &program_id,
+                &account_key,
+                &account2_key,
+                &owner_key,
+                &[],
+                900,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut account2_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // approve delegate
+        do_process_instruction(
+            approve(
+                &program_id,
+                &account_key,
+                &delegate_key,
+                &owner_key,
+                &[],
+                100,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut delegate_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // insufficient funds in source account via delegate
+        assert_eq!(
+            Err(TokenError::InsufficientFunds.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &delegate_key,
+                    &[],
+                    100
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut delegate_account,
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn test_self_transfer() {
+        let program_id = crate::id();
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account3_key = Pubkey::new_unique();
+        let mut account3_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let delegate_key = Pubkey::new_unique();
+        let mut delegate_account = SolanaAccount::default();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mut rent_sysvar = rent_sysvar();
+
+        // create mint
+        do_process_instruction(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        // create account
+        do_process_instruction(
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account2_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account3_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // mint to account
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+
+        let account_info = (&account_key, false, &mut account_account).into_account_info();
+        let account3_info = (&account3_key, false, &mut account3_account).into_account_info();
+        let delegate_info = (&delegate_key, true, &mut delegate_account).into_account_info();
+        let owner_info = (&owner_key, true, &mut owner_account).into_account_info();
+        let owner2_info = (&owner2_key, true, &mut owner2_account).into_account_info();
+        let mint_info = (&mint_key, false, &mut mint_account).into_account_info();
+
+        // transfer
+        let instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1000,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+        // no balance change...
+        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
+        assert_eq!(account.amount, 1000);
+
+        // transfer checked
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1000,
+            2,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+        // no balance change...
+        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
+        assert_eq!(account.amount, 1000);
+
+        // missing signer
+        let mut owner_no_sign_info = owner_info.clone();
+        let mut instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            owner_no_sign_info.key,
+            &[],
+            1000,
+        )
+        .unwrap();
+        instruction.accounts[2].is_signer = false;
+        owner_no_sign_info.is_signer = false;
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    owner_no_sign_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+
+        // missing signer checked
+        let mut instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            owner_no_sign_info.key,
+            &[],
+            1000,
+            2,
+        )
+        .unwrap();
+        instruction.accounts[3].is_signer = false;
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    owner_no_sign_info,
+                ],
+                &instruction.data,
+            )
+        );
+
+        // missing owner
+        let instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            owner2_info.key,
+            &[],
+            1000,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    owner2_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+
+        // missing owner checked
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            owner2_info.key,
+            &[],
+            1000,
+            2,
+        )
+        .unwrap();
         assert_eq!(
-            Err(ProgramError::InvalidAccountData),
-            Account::pack(check, &mut packed)
+            Err(TokenError::OwnerMismatch.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    owner2_info.clone(),
+                ],
+                &instruction.data,
+            )
         );
-        let mut packed = vec![0; Account::get_packed_len()];
-        Account::pack(check, &mut packed).unwrap();
-        let expect = vec![
-            1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-            1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
-            2, 2, 2, 2, 2, 2, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
-            4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 2, 1, 0, 0, 0, 5, 0, 0,
-            0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
-            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
-        ];
-        assert_eq!(packed, expect);
-        let unpacked = Account::unpack(&packed).unwrap();
-        assert_eq!(unpacked, check);
 
-        // Multisig
-        let check = Multisig {
-            m: 1,
-            n: 2,
-            is_initialized: true,
-            signers: [Pubkey::new(&[3; 32]); MAX_SIGNERS],
-        };
-        let mut packed = vec![0; Multisig::get_packed_len() + 1];
+        // insufficient funds
+        let instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1001,
+        )
+        .unwrap();
         assert_eq!(
-            Err(ProgramError::InvalidAccountData),
-            Multisig::pack(check, &mut packed)
+            Err(TokenError::InsufficientFunds.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
         );
-        let mut packed = vec![0; Multisig::get_packed_len() - 1];
+
+        // insufficient funds checked
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1001,
+            2,
+        )
+        .unwrap();
         assert_eq!(
-            Err(ProgramError::InvalidAccountData),
-            Multisig::pack(check, &mut packed)
+            Err(TokenError::InsufficientFunds.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
         );
-        let mut packed = vec![0; Multisig::get_packed_len()];
-        Multisig::pack(check, &mut packed).unwrap();
-        let expect = vec![
-            1, 2, 1, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
-            3, 3, 3, 3, 3, 3, 3,
-        ];
-        assert_eq!(packed, expect);
-        let unpacked = Multisig::unpack(&packed).unwrap();
-        assert_eq!(unpacked, check);
-    }
 
-    #[test]
-    fn test_initialize_mint() {
-        let program_id = crate::id();
-        let owner_key = Pubkey::new_unique();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account = SolanaAccount::new(42, Mint::get_packed_len(), &program_id);
-        let mint2_key = Pubkey::new_unique();
-        let mut mint2_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mut rent_sysvar = rent_sysvar();
+        // incorrect decimals
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1,
+            10, // <-- incorrect decimals
+        )
+        .unwrap();
+        assert_eq!(
+            Err(TokenError::MintDecimalsMismatch.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
 
-        // mint is not rent exempt
+        // incorrect mint
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            account3_info.key, // <-- incorrect mint
+            account_info.key,
+            owner_info.key,
+            &[],
+            1,
+            2,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(TokenError::MintMismatch.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account3_info.clone(), // <-- incorrect mint
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+
+        // approve delegate
+        let instruction = approve(
+            &program_id,
+            account_info.key,
+            delegate_info.key,
+            owner_info.key,
+            &[],
+            100,
+        )
+        .unwrap();
+        Processor::process(
+            &instruction.program_id,
+            &[
+                account_info.clone(),
+                delegate_info.clone(),
+                owner_info.clone(),
+            ],
+            &instruction.data,
+        )
+        .unwrap();
+
+        // delegate transfer
+        let instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            delegate_info.key,
+            &[],
+            100,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    delegate_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+        // no balance change...
+        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
+        assert_eq!(account.amount, 1000);
+        assert_eq!(account.delegated_amount, 100);
+
+        // delegate transfer checked
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            delegate_info.key,
+            &[],
+            100,
+            2,
+        )
+        .unwrap();
         assert_eq!(
-            Err(TokenError::NotRentExempt.into()),
-            do_process_instruction(
-                initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-                vec![&mut mint_account, &mut rent_sysvar]
+            Ok(()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    delegate_info.clone(),
+                ],
+                &instruction.data,
             )
         );
+        // no balance change...
+        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
+        assert_eq!(account.amount, 1000);
+        assert_eq!(account.delegated_amount, 100);
 
-        mint_account.lamports = mint_minimum_balance();
-
-        // create new mint
-        do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+        // delegate insufficient funds
+        let instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            delegate_info.key,
+            &[],
+            101,
         )
         .unwrap();
-
-        // create twice
         assert_eq!(
-            Err(TokenError::AlreadyInUse.into()),
-            do_process_instruction(
-                initialize_mint(&program_id, &mint_key, &owner_key, None, 2,).unwrap(),
-                vec![&mut mint_account, &mut rent_sysvar]
+            Err(TokenError::InsufficientFunds.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    delegate_info.clone(),
+                ],
+                &instruction.data,
             )
         );
 
-        // create another mint that can freeze
-        do_process_instruction(
-            initialize_mint(&program_id, &mint2_key, &owner_key, Some(&owner_key), 2).unwrap(),
-            vec![&mut mint2_account, &mut rent_sysvar],
+        // delegate insufficient funds checked
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            delegate_info.key,
+            &[],
+            101,
+            2,
         )
         .unwrap();
-        let mint = Mint::unpack_unchecked(&mint2_account.data).unwrap();
-        assert_eq!(mint.freeze_authority, COption::Some(owner_key));
-    }
-
-    #[test]
-    fn test_initialize_mint2() {
-        let program_id = crate::id();
-        let owner_key = Pubkey::new_unique();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account = SolanaAccount::new(42, Mint::get_packed_len(), &program_id);
-        let mint2_key = Pubkey::new_unique();
-        let mut mint2_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-
-        // mint is not rent exempt
         assert_eq!(
-            Err(TokenError::NotRentExempt.into()),
-            do_process_instruction(
-                initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-                vec![&mut mint_account]
+            Err(TokenError::InsufficientFunds.into()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    delegate_info.clone(),
+                ],
+                &instruction.data,
             )
         );
 
-        mint_account.lamports = mint_minimum_balance();
-
-        // create new mint
-        do_process_instruction(
-            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account],
+        // owner transfer with delegate assigned
+        let instruction = transfer(
+            &program_id,
+            account_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1000,
         )
         .unwrap();
-
-        // create twice
         assert_eq!(
-            Err(TokenError::AlreadyInUse.into()),
-            do_process_instruction(
-                initialize_mint2(&program_id, &mint_key, &owner_key, None, 2,).unwrap(),
-                vec![&mut mint_account]
+            Ok(()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
             )
         );
+        // no balance change...
+        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
+        assert_eq!(account.amount, 1000);
 
-        // create another mint that can freeze
-        do_process_instruction(
-            initialize_mint2(&program_id, &mint2_key, &owner_key, Some(&owner_key), 2).unwrap(),
-            vec![&mut mint2_account],
+        // owner transfer with delegate assigned checked
+        let instruction = transfer_checked(
+            &program_id,
+            account_info.key,
+            mint_info.key,
+            account_info.key,
+            owner_info.key,
+            &[],
+            1000,
+            2,
         )
         .unwrap();
-        let mint = Mint::unpack_unchecked(&mint2_account.data).unwrap();
-        assert_eq!(mint.freeze_authority, COption::Some(owner_key));
+        assert_eq!(
+            Ok(()),
+            Processor::process(
+                &instruction.program_id,
+                &[
+                    account_info.clone(),
+                    mint_info.clone(),
+                    account_info.clone(),
+                    owner_info.clone(),
+                ],
+                &instruction.data,
+            )
+        );
+        // no balance change...
+        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
+        assert_eq!(account.amount, 1000);
     }
 
     #[test]
-    fn test_initialize_mint_account() {
+    fn test_mintable_token_with_zero_supply() {
         let program_id = crate::id();
         let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(42, Account::get_packed_len(), &program_id);
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let mint_key = Pubkey::new_unique();
@@ -1242,42 +3483,24 @@ mod tests {
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let mut rent_sysvar = rent_sysvar();
 
-        // account is not rent exempt
-        assert_eq!(
-            Err(TokenError::NotRentExempt.into()),
-            do_process_instruction(
-                initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut mint_account,
-                    &mut owner_account,
-                    &mut rent_sysvar
-                ],
-            )
-        );
-
-        account_account.lamports = account_minimum_balance();
-
-        // mint is not valid (not initialized)
-        assert_eq!(
-            Err(TokenError::InvalidMint.into()),
-            do_process_instruction(
-                initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut mint_account,
-                    &mut owner_account,
-                    &mut rent_sysvar
-                ],
-            )
-        );
-
-        // create mint
+        // create mint-able token with zero supply
+        let decimals = 2;
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            initialize_mint(&program_id, &mint_key, &owner_key, None, decimals).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
+        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+        assert_eq!(
+            mint,
+            Mint {
+                mint_authority: COption::Some(owner_key),
+                supply: 0,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            }
+        );
 
         // create account
         do_process_instruction(
@@ -1290,24 +3513,61 @@ mod tests {
             ],
         )
         .unwrap();
-
-        // create twice
-        assert_eq!(
-            Err(TokenError::AlreadyInUse.into()),
-            do_process_instruction(
-                initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut mint_account,
-                    &mut owner_account,
-                    &mut rent_sysvar
-                ],
-            )
-        );
+
+        // mint to
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 42).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+        let _ = Mint::unpack(&mint_account.data).unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 42);
+
+        // mint to 2, with incorrect decimals
+        assert_eq!(
+            Err(TokenError::MintDecimalsMismatch.into()),
+            do_process_instruction(
+                mint_to_checked(
+                    &program_id,
+                    &mint_key,
+                    &account_key,
+                    &owner_key,
+                    &[],
+                    42,
+                    decimals + 1
+                )
+                .unwrap(),
+                vec![&mut mint_account, &mut account_account, &mut owner_account],
+            )
+        );
+
+        let _ = Mint::unpack(&mint_account.data).unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 42);
+
+        // mint to 2
+        do_process_instruction(
+            mint_to_checked(
+                &program_id,
+                &mint_key,
+                &account_key,
+                &owner_key,
+                &[],
+                42,
+                decimals,
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+        let _ = Mint::unpack(&mint_account.data).unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 84);
     }
 
     #[test]
-    fn test_transfer_dups() {
+    fn test_approve_dups() {
         let program_id = crate::id();
         let account1_key = Pubkey::new_unique();
         let mut account1_account = SolanaAccount::new(
@@ -1315,28 +3575,21 @@ mod tests {
             Account::get_packed_len(),
             &program_id,
         );
-        let mut account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
+        let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
         let account2_key = Pubkey::new_unique();
         let mut account2_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let mut account2_info: AccountInfo = (&account2_key, false, &mut account2_account).into();
+        let account2_info: AccountInfo = (&account2_key, false, &mut account2_account).into();
         let account3_key = Pubkey::new_unique();
         let mut account3_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let account3_info: AccountInfo = (&account3_key, false, &mut account3_account).into();
-        let account4_key = Pubkey::new_unique();
-        let mut account4_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account4_info: AccountInfo = (&account4_key, true, &mut account4_account).into();
+        let account3_info: AccountInfo = (&account3_key, true, &mut account3_account).into();
         let multisig_key = Pubkey::new_unique();
         let mut multisig_account = SolanaAccount::new(
             multisig_minimum_balance(),
@@ -1393,9 +3646,9 @@ mod tests {
         )
         .unwrap();
 
-        // source-owner transfer
+        // source-owner approve
         do_process_instruction_dups(
-            transfer(
+            approve(
                 &program_id,
                 &account1_key,
                 &account2_key,
@@ -1412,9 +3665,9 @@ mod tests {
         )
         .unwrap();
 
-        // source-owner TransferChecked
+        // source-owner approve_checked
         do_process_instruction_dups(
-            transfer_checked(
+            approve_checked(
                 &program_id,
                 &account1_key,
                 &mint_key,
@@ -1434,100 +3687,70 @@ mod tests {
         )
         .unwrap();
 
-        // source-delegate transfer
-        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.amount = 1000;
-        account.delegated_amount = 1000;
-        account.delegate = COption::Some(account1_key);
-        account.owner = owner_key;
-        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
-
+        // source-owner revoke
         do_process_instruction_dups(
-            transfer(
-                &program_id,
-                &account1_key,
-                &account2_key,
-                &account1_key,
-                &[],
-                500,
-            )
-            .unwrap(),
-            vec![
-                account1_info.clone(),
-                account2_info.clone(),
-                account1_info.clone(),
-            ],
+            revoke(&program_id, &account1_key, &account1_key, &[]).unwrap(),
+            vec![account1_info.clone(), account1_info.clone()],
         )
         .unwrap();
 
-        // source-delegate TransferChecked
+        // test source-multisig signer
         do_process_instruction_dups(
-            transfer_checked(
-                &program_id,
-                &account1_key,
-                &mint_key,
-                &account2_key,
-                &account1_key,
-                &[],
-                500,
-                2,
-            )
-            .unwrap(),
+            initialize_multisig(&program_id, &multisig_key, &[&account3_key], 1).unwrap(),
             vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account2_info.clone(),
-                account1_info.clone(),
+                multisig_info.clone(),
+                rent_info.clone(),
+                account3_info.clone(),
             ],
         )
         .unwrap();
 
-        // test destination-owner transfer
         do_process_instruction_dups(
-            initialize_account(&program_id, &account3_key, &mint_key, &account2_key).unwrap(),
+            initialize_account(&program_id, &account3_key, &mint_key, &multisig_key).unwrap(),
             vec![
                 account3_info.clone(),
                 mint_info.clone(),
-                account2_info.clone(),
+                multisig_info.clone(),
                 rent_info.clone(),
             ],
         )
         .unwrap();
+
         do_process_instruction_dups(
             mint_to(&program_id, &mint_key, &account3_key, &owner_key, &[], 1000).unwrap(),
             vec![mint_info.clone(), account3_info.clone(), owner_info.clone()],
         )
         .unwrap();
 
-        account1_info.is_signer = false;
-        account2_info.is_signer = true;
+        // source-multisig-signer approve
         do_process_instruction_dups(
-            transfer(
+            approve(
                 &program_id,
                 &account3_key,
                 &account2_key,
-                &account2_key,
-                &[],
+                &multisig_key,
+                &[&account3_key],
                 500,
             )
             .unwrap(),
             vec![
                 account3_info.clone(),
                 account2_info.clone(),
-                account2_info.clone(),
+                multisig_info.clone(),
+                account3_info.clone(),
             ],
         )
         .unwrap();
 
-        // destination-owner TransferChecked
+        // source-multisig-signer approve_checked
         do_process_instruction_dups(
-            transfer_checked(
+            approve_checked(
                 &program_id,
                 &account3_key,
                 &mint_key,
                 &account2_key,
-                &account2_key,
-                &[],
+                &multisig_key,
+                &[&account3_key],
                 500,
                 2,
             )
@@ -1536,85 +3759,26 @@ mod tests {
                 account3_info.clone(),
                 mint_info.clone(),
                 account2_info.clone(),
-                account2_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // test source-multisig signer
-        do_process_instruction_dups(
-            initialize_multisig(&program_id, &multisig_key, &[&account4_key], 1).unwrap(),
-            vec![
-                multisig_info.clone(),
-                rent_info.clone(),
-                account4_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        do_process_instruction_dups(
-            initialize_account(&program_id, &account4_key, &mint_key, &multisig_key).unwrap(),
-            vec![
-                account4_info.clone(),
-                mint_info.clone(),
-                multisig_info.clone(),
-                rent_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account4_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account4_info.clone(), owner_info.clone()],
-        )
-        .unwrap();
-
-        // source-multisig-signer transfer
-        do_process_instruction_dups(
-            transfer(
-                &program_id,
-                &account4_key,
-                &account2_key,
-                &multisig_key,
-                &[&account4_key],
-                500,
-            )
-            .unwrap(),
-            vec![
-                account4_info.clone(),
-                account2_info.clone(),
                 multisig_info.clone(),
-                account4_info.clone(),
+                account3_info.clone(),
             ],
         )
         .unwrap();
 
-        // source-multisig-signer TransferChecked
+        // source-owner multisig-signer
         do_process_instruction_dups(
-            transfer_checked(
-                &program_id,
-                &account4_key,
-                &mint_key,
-                &account2_key,
-                &multisig_key,
-                &[&account4_key],
-                500,
-                2,
-            )
-            .unwrap(),
+            revoke(&program_id, &account3_key, &multisig_key, &[&account3_key]).unwrap(),
             vec![
-                account4_info.clone(),
-                mint_info.clone(),
-                account2_info.clone(),
+                account3_info.clone(),
                 multisig_info.clone(),
-                account4_info.clone(),
+                account3_info.clone(),
             ],
         )
         .unwrap();
     }
 
     #[test]
-    fn test_transfer() {
+    fn test_approve() {
         let program_id = crate::id();
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
@@ -1628,20 +3792,8 @@ mod tests {
             Account::get_packed_len(),
             &program_id,
         );
-        let account3_key = Pubkey::new_unique();
-        let mut account3_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
         let delegate_key = Pubkey::new_unique();
         let mut delegate_account = SolanaAccount::default();
-        let mismatch_key = Pubkey::new_unique();
-        let mut mismatch_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner2_key = Pubkey::new_unique();
@@ -1649,7 +3801,6 @@ mod tests {
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint2_key = Pubkey::new_unique();
         let mut rent_sysvar = rent_sysvar();
 
         // create mint
@@ -1683,33 +3834,6 @@ mod tests {
         )
         .unwrap();
 
-        // create another account
-        do_process_instruction(
-            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account3_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-
-        // create mismatch account
-        do_process_instruction(
-            initialize_account(&program_id, &mismatch_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut mismatch_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-        let mut account = Account::unpack_unchecked(&mismatch_account.data).unwrap();
-        account.mint = mint2_key;
-        Account::pack(account, &mut mismatch_account.data).unwrap();
-
         // mint to account
         do_process_instruction(
             mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
@@ -1718,324 +3842,251 @@ mod tests {
         .unwrap();
 
         // missing signer
-        let mut instruction = transfer(
+        let mut instruction = approve(
             &program_id,
             &account_key,
-            &account2_key,
+            &delegate_key,
             &owner_key,
             &[],
-            1000,
+            100,
         )
         .unwrap();
         instruction.accounts[2].is_signer = false;
         assert_eq!(
             Err(ProgramError::MissingRequiredSignature),
-            do_process_instruction(
-                instruction,
-                vec![
-                    &mut account_account,
-                    &mut account2_account,
-                    &mut owner_account,
-                ],
-            )
-        );
-
-        // mismatch mint
-        assert_eq!(
-            Err(TokenError::MintMismatch.into()),
-            do_process_instruction(
-                transfer(
-                    &program_id,
-                    &account_key,
-                    &mismatch_key,
-                    &owner_key,
-                    &[],
-                    1000
-                )
-                .unwrap(),
+            do_process_instruction(
+                instruction,
                 vec![
                     &mut account_account,
-                    &mut mismatch_account,
+                    &mut delegate_account,
                     &mut owner_account,
                 ],
             )
         );
 
-        // missing owner
+        // no owner
         assert_eq!(
             Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                transfer(
+                approve(
                     &program_id,
                     &account_key,
-                    &account2_key,
+                    &delegate_key,
                     &owner2_key,
                     &[],
-                    1000
+                    100
                 )
                 .unwrap(),
                 vec![
                     &mut account_account,
-                    &mut account2_account,
+                    &mut delegate_account,
                     &mut owner2_account,
                 ],
             )
         );
 
-        // transfer
-        do_process_instruction(
-            transfer(
-                &program_id,
-                &account_key,
-                &account2_key,
-                &owner_key,
-                &[],
-                1000,
-            )
-            .unwrap(),
-            vec![
-                &mut account_account,
-                &mut account2_account,
-                &mut owner_account,
-            ],
-        )
-        .unwrap();
-
-        // insufficient funds
-        assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            do_process_instruction(
-                transfer(&program_id, &account_key, &account2_key, &owner_key, &[], 1).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account2_account,
-                    &mut owner_account,
-                ],
-            )
-        );
-
-        // transfer half back
+        // approve delegate
         do_process_instruction(
-            transfer(
+            approve(
                 &program_id,
-                &account2_key,
                 &account_key,
+                &delegate_key,
                 &owner_key,
                 &[],
-                500,
+                100,
             )
             .unwrap(),
             vec![
-                &mut account2_account,
                 &mut account_account,
+                &mut delegate_account,
                 &mut owner_account,
             ],
         )
         .unwrap();
 
-        // incorrect decimals
+        // approve delegate 2, with incorrect decimals
         assert_eq!(
             Err(TokenError::MintDecimalsMismatch.into()),
             do_process_instruction(
-                transfer_checked(
+                approve_checked(
                     &program_id,
-                    &account2_key,
-                    &mint_key,
                     &account_key,
+                    &mint_key,
+                    &delegate_key,
                     &owner_key,
                     &[],
-                    1,
-                    10 // <-- incorrect decimals
+                    100,
+                    0 // <-- incorrect decimals
                 )
                 .unwrap(),
                 vec![
-                    &mut account2_account,
-                    &mut mint_account,
                     &mut account_account,
+                    &mut mint_account,
+                    &mut delegate_account,
                     &mut owner_account,
                 ],
             )
         );
 
-        // incorrect mint
+        // approve delegate 2, with incorrect mint
         assert_eq!(
             Err(TokenError::MintMismatch.into()),
             do_process_instruction(
-                transfer_checked(
+                approve_checked(
                     &program_id,
-                    &account2_key,
-                    &account3_key, // <-- incorrect mint
                     &account_key,
+                    &account2_key, // <-- bad mint
+                    &delegate_key,
                     &owner_key,
                     &[],
-                    1,
-                    2
+                    100,
+                    0
                 )
                 .unwrap(),
                 vec![
-                    &mut account2_account,
-                    &mut account3_account, // <-- incorrect mint
                     &mut account_account,
+                    &mut account2_account, // <-- bad mint
+                    &mut delegate_account,
                     &mut owner_account,
                 ],
             )
         );
-        // transfer rest with explicit decimals
+
+        // approve delegate 2
         do_process_instruction(
-            transfer_checked(
+            approve_checked(
                 &program_id,
-                &account2_key,
-                &mint_key,
                 &account_key,
+                &mint_key,
+                &delegate_key,
                 &owner_key,
                 &[],
-                500,
+                100,
                 2,
             )
             .unwrap(),
             vec![
-                &mut account2_account,
-                &mut mint_account,
                 &mut account_account,
+                &mut mint_account,
+                &mut delegate_account,
                 &mut owner_account,
             ],
         )
         .unwrap();
 
-        // insufficient funds
-        assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            do_process_instruction(
-                transfer(&program_id, &account2_key, &account_key, &owner_key, &[], 1).unwrap(),
-                vec![
-                    &mut account2_account,
-                    &mut account_account,
-                    &mut owner_account,
-                ],
-            )
+        // revoke delegate
+        do_process_instruction(
+            revoke(&program_id, &account_key, &owner_key, &[]).unwrap(),
+            vec![&mut account_account, &mut owner_account],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_set_authority_dups() {
+        let program_id = crate::id();
+        let account1_key = Pubkey::new_unique();
+        let mut account1_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
         );
+        let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
+        let owner_key = Pubkey::new_unique();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mint_info: AccountInfo = (&mint_key, true, &mut mint_account).into();
+        let rent_key = rent::id();
+        let mut rent_sysvar = rent_sysvar();
+        let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
 
-        // approve delegate
-        do_process_instruction(
-            approve(
-                &program_id,
-                &account_key,
-                &delegate_key,
-                &owner_key,
-                &[],
-                100,
-            )
-            .unwrap(),
+        // create mint
+        do_process_instruction_dups(
+            initialize_mint(&program_id, &mint_key, &mint_key, Some(&mint_key), 2).unwrap(),
+            vec![mint_info.clone(), rent_info.clone()],
+        )
+        .unwrap();
+
+        // create account
+        do_process_instruction_dups(
+            initialize_account(&program_id, &account1_key, &mint_key, &account1_key).unwrap(),
             vec![
-                &mut account_account,
-                &mut delegate_account,
-                &mut owner_account,
+                account1_info.clone(),
+                mint_info.clone(),
+                account1_info.clone(),
+                rent_info.clone(),
             ],
         )
         .unwrap();
 
-        // transfer via delegate
-        do_process_instruction(
-            transfer(
+        // set mint_authority when currently self
+        do_process_instruction_dups(
+            set_authority(
                 &program_id,
-                &account_key,
-                &account2_key,
-                &delegate_key,
+                &mint_key,
+                Some(&owner_key),
+                AuthorityType::MintTokens,
+                &mint_key,
                 &[],
-                100,
             )
             .unwrap(),
-            vec![
-                &mut account_account,
-                &mut account2_account,
-                &mut delegate_account,
-            ],
+            vec![mint_info.clone(), mint_info.clone()],
         )
         .unwrap();
 
-        // insufficient funds approved via delegate
-        assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
-            do_process_instruction(
-                transfer(
-                    &program_id,
-                    &account_key,
-                    &account2_key,
-                    &delegate_key,
-                    &[],
-                    100
-                )
-                .unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account2_account,
-                    &mut delegate_account,
-                ],
+        // set freeze_authority when currently self
+        do_process_instruction_dups(
+            set_authority(
+                &program_id,
+                &mint_key,
+                Some(&owner_key),
+                AuthorityType::FreezeAccount,
+                &mint_key,
+                &[],
             )
-        );
+            .unwrap(),
+            vec![mint_info.clone(), mint_info.clone()],
+        )
+        .unwrap();
 
-        // transfer rest
-        do_process_instruction(
-            transfer(
+        // set account owner when currently self
+        do_process_instruction_dups(
+            set_authority(
                 &program_id,
-                &account_key,
-                &account2_key,
-                &owner_key,
+                &account1_key,
+                Some(&owner_key),
+                AuthorityType::AccountOwner,
+                &account1_key,
                 &[],
-                900,
             )
             .unwrap(),
-            vec![
-                &mut account_account,
-                &mut account2_account,
-                &mut owner_account,
-            ],
+            vec![account1_info.clone(), account1_info.clone()],
         )
         .unwrap();
 
-        // approve delegate
-        do_process_instruction(
-            approve(
+        // set close_authority when currently self
+        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
+        account.close_authority = COption::Some(account1_key);
+        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
+
+        do_process_instruction_dups(
+            set_authority(
                 &program_id,
-                &account_key,
-                &delegate_key,
-                &owner_key,
+                &account1_key,
+                Some(&owner_key),
+                AuthorityType::CloseAccount,
+                &account1_key,
                 &[],
-                100,
             )
             .unwrap(),
-            vec![
-                &mut account_account,
-                &mut delegate_account,
-                &mut owner_account,
-            ],
+            vec![account1_info.clone(), account1_info.clone()],
         )
         .unwrap();
-
-        // insufficient funds in source account via delegate
-        assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            do_process_instruction(
-                transfer(
-                    &program_id,
-                    &account_key,
-                    &account2_key,
-                    &delegate_key,
-                    &[],
-                    100
-                )
-                .unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account2_account,
-                    &mut delegate_account,
-                ],
-            )
-        );
     }
 
     #[test]
-    fn test_self_transfer() {
+    fn test_set_authority() {
         let program_id = crate::id();
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
@@ -2049,622 +4100,375 @@ mod tests {
             Account::get_packed_len(),
             &program_id,
         );
-        let account3_key = Pubkey::new_unique();
-        let mut account3_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let delegate_key = Pubkey::new_unique();
-        let mut delegate_account = SolanaAccount::default();
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner2_key = Pubkey::new_unique();
         let mut owner2_account = SolanaAccount::default();
+        let owner3_key = Pubkey::new_unique();
+        let mut owner3_account = SolanaAccount::default();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mint2_key = Pubkey::new_unique();
+        let mut mint2_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let mut rent_sysvar = rent_sysvar();
 
-        // create mint
+        // create new mint with owner
         do_process_instruction(
             initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
 
-        // create account
-        do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-
-        // create another account
-        do_process_instruction(
-            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account2_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-
-        // create another account
-        do_process_instruction(
-            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account3_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-
-        // mint to account
+        // create mint with owner and freeze_authority
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
-            vec![&mut mint_account, &mut account_account, &mut owner_account],
-        )
-        .unwrap();
-
-        let account_info = (&account_key, false, &mut account_account).into_account_info();
-        let account3_info = (&account3_key, false, &mut account3_account).into_account_info();
-        let delegate_info = (&delegate_key, true, &mut delegate_account).into_account_info();
-        let owner_info = (&owner_key, true, &mut owner_account).into_account_info();
-        let owner2_info = (&owner2_key, true, &mut owner2_account).into_account_info();
-        let mint_info = (&mint_key, false, &mut mint_account).into_account_info();
-
-        // transfer
-        let instruction = transfer(
-            &program_id,
-            account_info.key,
-            account_info.key,
-            owner_info.key,
-            &[],
-            1000,
-        )
-        .unwrap();
-        assert_eq!(
-            Ok(()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
-            )
-        );
-        // no balance change...
-        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
-        assert_eq!(account.amount, 1000);
-
-        // transfer checked
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            owner_info.key,
-            &[],
-            1000,
-            2,
-        )
-        .unwrap();
-        assert_eq!(
-            Ok(()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
-            )
-        );
-        // no balance change...
-        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
-        assert_eq!(account.amount, 1000);
-
-        // missing signer
-        let mut owner_no_sign_info = owner_info.clone();
-        let mut instruction = transfer(
-            &program_id,
-            account_info.key,
-            account_info.key,
-            owner_no_sign_info.key,
-            &[],
-            1000,
-        )
-        .unwrap();
-        instruction.accounts[2].is_signer = false;
-        owner_no_sign_info.is_signer = false;
-        assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    owner_no_sign_info.clone(),
-                ],
-                &instruction.data,
-            )
-        );
-
-        // missing signer checked
-        let mut instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            owner_no_sign_info.key,
-            &[],
-            1000,
-            2,
-        )
-        .unwrap();
-        instruction.accounts[3].is_signer = false;
-        assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    owner_no_sign_info,
-                ],
-                &instruction.data,
-            )
-        );
-
-        // missing owner
-        let instruction = transfer(
-            &program_id,
-            account_info.key,
-            account_info.key,
-            owner2_info.key,
-            &[],
-            1000,
+            initialize_mint(&program_id, &mint2_key, &owner_key, Some(&owner_key), 2).unwrap(),
+            vec![&mut mint2_account, &mut rent_sysvar],
         )
         .unwrap();
-        assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    owner2_info.clone(),
-                ],
-                &instruction.data,
-            )
-        );
 
-        // missing owner checked
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            owner2_info.key,
-            &[],
-            1000,
-            2,
-        )
-        .unwrap();
+        // invalid account
         assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    owner2_info.clone(),
-                ],
-                &instruction.data,
+            Err(ProgramError::UninitializedAccount),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &account_key,
+                    Some(&owner2_key),
+                    AuthorityType::AccountOwner,
+                    &owner_key,
+                    &[]
+                )
+                .unwrap(),
+                vec![&mut account_account, &mut owner_account],
             )
         );
 
-        // insufficient funds
-        let instruction = transfer(
-            &program_id,
-            account_info.key,
-            account_info.key,
-            owner_info.key,
-            &[],
-            1001,
+        // create account
+        do_process_instruction(
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
         )
         .unwrap();
-        assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
-            )
-        );
 
-        // insufficient funds checked
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            owner_info.key,
-            &[],
-            1001,
-            2,
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account2_key, &mint2_key, &owner_key).unwrap(),
+            vec![
+                &mut account2_account,
+                &mut mint2_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
         )
         .unwrap();
+
+        // missing owner
         assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &account_key,
+                    Some(&owner_key),
+                    AuthorityType::AccountOwner,
+                    &owner2_key,
+                    &[]
+                )
+                .unwrap(),
+                vec![&mut account_account, &mut owner2_account],
             )
         );
 
-        // incorrect decimals
-        let instruction = transfer_checked(
+        // owner did not sign
+        let mut instruction = set_authority(
             &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            owner_info.key,
+            &account_key,
+            Some(&owner2_key),
+            AuthorityType::AccountOwner,
+            &owner_key,
             &[],
-            1,
-            10, // <-- incorrect decimals
         )
         .unwrap();
+        instruction.accounts[1].is_signer = false;
         assert_eq!(
-            Err(TokenError::MintDecimalsMismatch.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
+            Err(ProgramError::MissingRequiredSignature),
+            do_process_instruction(instruction, vec![&mut account_account, &mut owner_account,],)
+        );
+
+        // wrong authority type
+        assert_eq!(
+            Err(TokenError::AuthorityTypeNotSupported.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &account_key,
+                    Some(&owner2_key),
+                    AuthorityType::FreezeAccount,
+                    &owner_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut account_account, &mut owner_account],
             )
         );
 
-        // incorrect mint
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            account3_info.key, // <-- incorrect mint
-            account_info.key,
-            owner_info.key,
-            &[],
-            1,
-            2,
-        )
-        .unwrap();
+        // account owner may not be set to None
         assert_eq!(
-            Err(TokenError::MintMismatch.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account3_info.clone(), // <-- incorrect mint
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
+            Err(TokenError::InvalidInstruction.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &account_key,
+                    None,
+                    AuthorityType::AccountOwner,
+                    &owner_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut account_account, &mut owner_account],
             )
         );
 
-        // approve delegate
-        let instruction = approve(
-            &program_id,
-            account_info.key,
-            delegate_info.key,
-            owner_info.key,
-            &[],
-            100,
-        )
-        .unwrap();
-        Processor::process(
-            &instruction.program_id,
-            &[
-                account_info.clone(),
-                delegate_info.clone(),
-                owner_info.clone(),
+        // set delegate
+        do_process_instruction(
+            approve(
+                &program_id,
+                &account_key,
+                &owner2_key,
+                &owner_key,
+                &[],
+                u64::MAX,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut owner2_account,
+                &mut owner_account,
             ],
-            &instruction.data,
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.delegate, COption::Some(owner2_key));
+        assert_eq!(account.delegated_amount, u64::MAX);
 
-        // delegate transfer
-        let instruction = transfer(
-            &program_id,
-            account_info.key,
-            account_info.key,
-            delegate_info.key,
-            &[],
-            100,
-        )
-        .unwrap();
-        assert_eq!(
-            Ok(()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    delegate_info.clone(),
-                ],
-                &instruction.data,
+        // set owner
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &account_key,
+                Some(&owner3_key),
+                AuthorityType::AccountOwner,
+                &owner_key,
+                &[],
             )
-        );
-        // no balance change...
-        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
-        assert_eq!(account.amount, 1000);
-        assert_eq!(account.delegated_amount, 100);
-
-        // delegate transfer checked
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            delegate_info.key,
-            &[],
-            100,
-            2,
+            .unwrap(),
+            vec![&mut account_account, &mut owner_account],
         )
         .unwrap();
-        assert_eq!(
-            Ok(()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    delegate_info.clone(),
-                ],
-                &instruction.data,
-            )
-        );
-        // no balance change...
-        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
-        assert_eq!(account.amount, 1000);
-        assert_eq!(account.delegated_amount, 100);
 
-        // delegate insufficient funds
-        let instruction = transfer(
-            &program_id,
-            account_info.key,
-            account_info.key,
-            delegate_info.key,
-            &[],
-            101,
+        // check delegate cleared
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.delegate, COption::None);
+        assert_eq!(account.delegated_amount, 0);
+
+        // set owner without existing delegate
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &account_key,
+                Some(&owner2_key),
+                AuthorityType::AccountOwner,
+                &owner3_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut account_account, &mut owner3_account],
         )
         .unwrap();
-        assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    delegate_info.clone(),
-                ],
-                &instruction.data,
+
+        // set close_authority
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &account_key,
+                Some(&owner2_key),
+                AuthorityType::CloseAccount,
+                &owner2_key,
+                &[],
             )
-        );
+            .unwrap(),
+            vec![&mut account_account, &mut owner2_account],
+        )
+        .unwrap();
 
-        // delegate insufficient funds checked
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            delegate_info.key,
-            &[],
-            101,
-            2,
+        // close_authority may be set to None
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &account_key,
+                None,
+                AuthorityType::CloseAccount,
+                &owner2_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut account_account, &mut owner2_account],
         )
         .unwrap();
+
+        // wrong owner
         assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    delegate_info.clone(),
-                ],
-                &instruction.data,
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &mint_key,
+                    Some(&owner3_key),
+                    AuthorityType::MintTokens,
+                    &owner2_key,
+                    &[]
+                )
+                .unwrap(),
+                vec![&mut mint_account, &mut owner2_account],
             )
         );
 
-        // owner transfer with delegate assigned
-        let instruction = transfer(
+        // owner did not sign
+        let mut instruction = set_authority(
             &program_id,
-            account_info.key,
-            account_info.key,
-            owner_info.key,
+            &mint_key,
+            Some(&owner2_key),
+            AuthorityType::MintTokens,
+            &owner_key,
             &[],
-            1000,
         )
         .unwrap();
+        instruction.accounts[1].is_signer = false;
         assert_eq!(
-            Ok(()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
-            )
+            Err(ProgramError::MissingRequiredSignature),
+            do_process_instruction(instruction, vec![&mut mint_account, &mut owner_account],)
         );
-        // no balance change...
-        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
-        assert_eq!(account.amount, 1000);
 
-        // owner transfer with delegate assigned checked
-        let instruction = transfer_checked(
-            &program_id,
-            account_info.key,
-            mint_info.key,
-            account_info.key,
-            owner_info.key,
-            &[],
-            1000,
-            2,
-        )
-        .unwrap();
+        // cannot freeze
         assert_eq!(
-            Ok(()),
-            Processor::process(
-                &instruction.program_id,
-                &[
-                    account_info.clone(),
-                    mint_info.clone(),
-                    account_info.clone(),
-                    owner_info.clone(),
-                ],
-                &instruction.data,
+            Err(TokenError::MintCannotFreeze.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &mint_key,
+                    Some(&owner2_key),
+                    AuthorityType::FreezeAccount,
+                    &owner_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut mint_account, &mut owner_account],
             )
         );
-        // no balance change...
-        let account = Account::unpack_unchecked(&account_info.try_borrow_data().unwrap()).unwrap();
-        assert_eq!(account.amount, 1000);
-    }
 
-    #[test]
-    fn test_mintable_token_with_zero_supply() {
-        let program_id = crate::id();
-        let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mut rent_sysvar = rent_sysvar();
+        // set owner
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &mint_key,
+                Some(&owner2_key),
+                AuthorityType::MintTokens,
+                &owner_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut owner_account],
+        )
+        .unwrap();
 
-        // create mint-able token with zero supply
-        let decimals = 2;
+        // set owner to None
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, decimals).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            set_authority(
+                &program_id,
+                &mint_key,
+                None,
+                AuthorityType::MintTokens,
+                &owner2_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut owner2_account],
         )
         .unwrap();
-        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+
+        // test unsetting mint_authority is one-way operation
         assert_eq!(
-            mint,
-            Mint {
-                mint_authority: COption::Some(owner_key),
-                supply: 0,
-                decimals,
-                is_initialized: true,
-                freeze_authority: COption::None,
-            }
+            Err(TokenError::FixedSupply.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &mint2_key,
+                    Some(&owner2_key),
+                    AuthorityType::MintTokens,
+                    &owner_key,
+                    &[]
+                )
+                .unwrap(),
+                vec![&mut mint_account, &mut owner_account],
+            )
         );
 
-        // create account
+        // set freeze_authority
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
+            set_authority(
+                &program_id,
+                &mint2_key,
+                Some(&owner2_key),
+                AuthorityType::FreezeAccount,
+                &owner_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint2_account, &mut owner_account],
         )
         .unwrap();
 
-        // mint to
+        // test unsetting freeze_authority is one-way operation
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 42).unwrap(),
-            vec![&mut mint_account, &mut account_account, &mut owner_account],
+            set_authority(
+                &program_id,
+                &mint2_key,
+                None,
+                AuthorityType::FreezeAccount,
+                &owner2_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint2_account, &mut owner2_account],
         )
         .unwrap();
-        let _ = Mint::unpack(&mint_account.data).unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 42);
 
-        // mint to 2, with incorrect decimals
         assert_eq!(
-            Err(TokenError::MintDecimalsMismatch.into()),
+            Err(TokenError::MintCannotFreeze.into()),
             do_process_instruction(
-                mint_to_checked(
+                set_authority(
                     &program_id,
-                    &mint_key,
-                    &account_key,
+                    &mint2_key,
+                    Some(&owner2_key),
+                    AuthorityType::FreezeAccount,
                     &owner_key,
                     &[],
-                    42,
-                    decimals + 1
                 )
                 .unwrap(),
-                vec![&mut mint_account, &mut account_account, &mut owner_account],
+                vec![&mut mint2_account, &mut owner2_account],
             )
         );
-
-        let _ = Mint::unpack(&mint_account.data).unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 42);
-
-        // mint to 2
-        do_process_instruction(
-            mint_to_checked(
-                &program_id,
-                &mint_key,
-                &account_key,
-                &owner_key,
-                &[],
-                42,
-                decimals,
-            )
-            .unwrap(),
-            vec![&mut mint_account, &mut account_account, &mut owner_account],
-        )
-        .unwrap();
-        let _ = Mint::unpack(&mint_account.data).unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 84);
     }
 
     #[test]
-    fn test_approve_dups() {
+    fn test_mint_to_dups() {
         let program_id = crate::id();
         let account1_key = Pubkey::new_unique();
         let mut account1_account = SolanaAccount::new(
@@ -2673,209 +4477,94 @@ mod tests {
             &program_id,
         );
         let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account2_info: AccountInfo = (&account2_key, false, &mut account2_account).into();
-        let account3_key = Pubkey::new_unique();
-        let mut account3_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account3_info: AccountInfo = (&account3_key, true, &mut account3_account).into();
-        let multisig_key = Pubkey::new_unique();
-        let mut multisig_account = SolanaAccount::new(
-            multisig_minimum_balance(),
-            Multisig::get_packed_len(),
-            &program_id,
-        );
-        let multisig_info: AccountInfo = (&multisig_key, true, &mut multisig_account).into();
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner_info: AccountInfo = (&owner_key, true, &mut owner_account).into();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint_info: AccountInfo = (&mint_key, false, &mut mint_account).into();
+        let mint_info: AccountInfo = (&mint_key, true, &mut mint_account).into();
         let rent_key = rent::id();
         let mut rent_sysvar = rent_sysvar();
         let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
 
         // create mint
         do_process_instruction_dups(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            initialize_mint(&program_id, &mint_key, &mint_key, None, 2).unwrap(),
             vec![mint_info.clone(), rent_info.clone()],
         )
         .unwrap();
 
         // create account
         do_process_instruction_dups(
-            initialize_account(&program_id, &account1_key, &mint_key, &account1_key).unwrap(),
+            initialize_account(&program_id, &account1_key, &mint_key, &owner_key).unwrap(),
             vec![
                 account1_info.clone(),
                 mint_info.clone(),
-                account1_info.clone(),
+                owner_info.clone(),
                 rent_info.clone(),
             ],
         )
         .unwrap();
 
-        // create another account
+        // mint_to when mint_authority is self
         do_process_instruction_dups(
-            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                account2_info.clone(),
-                mint_info.clone(),
-                owner_info.clone(),
-                rent_info.clone(),
-            ],
+            mint_to(&program_id, &mint_key, &account1_key, &mint_key, &[], 42).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), mint_info.clone()],
         )
         .unwrap();
 
-        // mint to account
+        // mint_to_checked when mint_authority is self
         do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
+            mint_to_checked(&program_id, &mint_key, &account1_key, &mint_key, &[], 42, 2).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), mint_info.clone()],
         )
         .unwrap();
 
-        // source-owner approve
+        // mint_to when mint_authority is account owner
+        let mut mint = Mint::unpack_unchecked(&mint_info.data.borrow()).unwrap();
+        mint.mint_authority = COption::Some(account1_key);
+        Mint::pack(mint, &mut mint_info.data.borrow_mut()).unwrap();
         do_process_instruction_dups(
-            approve(
+            mint_to(
                 &program_id,
+                &mint_key,
                 &account1_key,
-                &account2_key,
                 &account1_key,
                 &[],
-                500,
+                42,
             )
             .unwrap(),
             vec![
+                mint_info.clone(),
                 account1_info.clone(),
-                account2_info.clone(),
                 account1_info.clone(),
             ],
         )
         .unwrap();
 
-        // source-owner approve_checked
+        // mint_to_checked when mint_authority is account owner
         do_process_instruction_dups(
-            approve_checked(
+            mint_to(
                 &program_id,
-                &account1_key,
                 &mint_key,
-                &account2_key,
+                &account1_key,
                 &account1_key,
                 &[],
-                500,
-                2,
+                42,
             )
             .unwrap(),
             vec![
-                account1_info.clone(),
                 mint_info.clone(),
-                account2_info.clone(),
                 account1_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // source-owner revoke
-        do_process_instruction_dups(
-            revoke(&program_id, &account1_key, &account1_key, &[]).unwrap(),
-            vec![account1_info.clone(), account1_info.clone()],
-        )
-        .unwrap();
-
-        // test source-multisig signer
-        do_process_instruction_dups(
-            initialize_multisig(&program_id, &multisig_key, &[&account3_key], 1).unwrap(),
-            vec![
-                multisig_info.clone(),
-                rent_info.clone(),
-                account3_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        do_process_instruction_dups(
-            initialize_account(&program_id, &account3_key, &mint_key, &multisig_key).unwrap(),
-            vec![
-                account3_info.clone(),
-                mint_info.clone(),
-                multisig_info.clone(),
-                rent_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account3_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account3_info.clone(), owner_info.clone()],
-        )
-        .unwrap();
-
-        // source-multisig-signer approve
-        do_process_instruction_dups(
-            approve(
-                &program_id,
-                &account3_key,
-                &account2_key,
-                &multisig_key,
-                &[&account3_key],
-                500,
-            )
-            .unwrap(),
-            vec![
-                account3_info.clone(),
-                account2_info.clone(),
-                multisig_info.clone(),
-                account3_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // source-multisig-signer approve_checked
-        do_process_instruction_dups(
-            approve_checked(
-                &program_id,
-                &account3_key,
-                &mint_key,
-                &account2_key,
-                &multisig_key,
-                &[&account3_key],
-                500,
-                2,
-            )
-            .unwrap(),
-            vec![
-                account3_info.clone(),
-                mint_info.clone(),
-                account2_info.clone(),
-                multisig_info.clone(),
-                account3_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // source-owner multisig-signer
-        do_process_instruction_dups(
-            revoke(&program_id, &account3_key, &multisig_key, &[&account3_key]).unwrap(),
-            vec![
-                account3_info.clone(),
-                multisig_info.clone(),
-                account3_info.clone(),
+                account1_info.clone(),
             ],
         )
         .unwrap();
     }
 
     #[test]
-    fn test_approve() {
+    fn test_mint_to() {
         let program_id = crate::id();
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
@@ -2889,8 +4578,18 @@ mod tests {
             Account::get_packed_len(),
             &program_id,
         );
-        let delegate_key = Pubkey::new_unique();
-        let mut delegate_account = SolanaAccount::default();
+        let account3_key = Pubkey::new_unique();
+        let mut account3_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let mismatch_key = Pubkey::new_unique();
+        let mut mismatch_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner2_key = Pubkey::new_unique();
@@ -2898,9 +4597,16 @@ mod tests {
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mint2_key = Pubkey::new_unique();
+        let uninitialized_key = Pubkey::new_unique();
+        let mut uninitialized_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
         let mut rent_sysvar = rent_sysvar();
 
-        // create mint
+        // create new mint with owner
         do_process_instruction(
             initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
@@ -2923,164 +4629,145 @@ mod tests {
         do_process_instruction(
             initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
             vec![
-                &mut account2_account,
+                &mut account2_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account3_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create mismatch account
+        do_process_instruction(
+            initialize_account(&program_id, &mismatch_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut mismatch_account,
                 &mut mint_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
+        let mut account = Account::unpack_unchecked(&mismatch_account.data).unwrap();
+        account.mint = mint2_key;
+        Account::pack(account, &mut mismatch_account.data).unwrap();
 
-        // mint to account
+        // mint to
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 42).unwrap(),
             vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        // missing signer
-        let mut instruction = approve(
-            &program_id,
-            &account_key,
-            &delegate_key,
-            &owner_key,
-            &[],
-            100,
+        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+        assert_eq!(mint.supply, 42);
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 42);
+
+        // mint to another account to test supply accumulation
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account2_key, &owner_key, &[], 42).unwrap(),
+            vec![&mut mint_account, &mut account2_account, &mut owner_account],
         )
         .unwrap();
+
+        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+        assert_eq!(mint.supply, 84);
+        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        assert_eq!(account.amount, 42);
+
+        // missing signer
+        let mut instruction =
+            mint_to(&program_id, &mint_key, &account2_key, &owner_key, &[], 42).unwrap();
         instruction.accounts[2].is_signer = false;
         assert_eq!(
             Err(ProgramError::MissingRequiredSignature),
             do_process_instruction(
                 instruction,
-                vec![
-                    &mut account_account,
-                    &mut delegate_account,
-                    &mut owner_account,
-                ],
+                vec![&mut mint_account, &mut account2_account, &mut owner_account],
             )
         );
 
-        // no owner
+        // mismatch account
         assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
+            Err(TokenError::MintMismatch.into()),
             do_process_instruction(
-                approve(
-                    &program_id,
-                    &account_key,
-                    &delegate_key,
-                    &owner2_key,
-                    &[],
-                    100
-                )
-                .unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut delegate_account,
-                    &mut owner2_account,
-                ],
+                mint_to(&program_id, &mint_key, &mismatch_key, &owner_key, &[], 42).unwrap(),
+                vec![&mut mint_account, &mut mismatch_account, &mut owner_account],
             )
         );
 
-        // approve delegate
-        do_process_instruction(
-            approve(
-                &program_id,
-                &account_key,
-                &delegate_key,
-                &owner_key,
-                &[],
-                100,
-            )
-            .unwrap(),
-            vec![
-                &mut account_account,
-                &mut delegate_account,
-                &mut owner_account,
-            ],
-        )
-        .unwrap();
-
-        // approve delegate 2, with incorrect decimals
+        // missing owner
         assert_eq!(
-            Err(TokenError::MintDecimalsMismatch.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                approve_checked(
-                    &program_id,
-                    &account_key,
-                    &mint_key,
-                    &delegate_key,
-                    &owner_key,
-                    &[],
-                    100,
-                    0 // <-- incorrect decimals
-                )
-                .unwrap(),
+                mint_to(&program_id, &mint_key, &account2_key, &owner2_key, &[], 42).unwrap(),
                 vec![
-                    &mut account_account,
                     &mut mint_account,
-                    &mut delegate_account,
-                    &mut owner_account,
+                    &mut account2_account,
+                    &mut owner2_account,
                 ],
             )
         );
 
-        // approve delegate 2, with incorrect mint
+        // uninitialized destination account
         assert_eq!(
-            Err(TokenError::MintMismatch.into()),
+            Err(ProgramError::UninitializedAccount),
             do_process_instruction(
-                approve_checked(
+                mint_to(
                     &program_id,
-                    &account_key,
-                    &account2_key, // <-- bad mint
-                    &delegate_key,
+                    &mint_key,
+                    &uninitialized_key,
                     &owner_key,
                     &[],
-                    100,
-                    0
+                    42
                 )
                 .unwrap(),
                 vec![
-                    &mut account_account,
-                    &mut account2_account, // <-- bad mint
-                    &mut delegate_account,
+                    &mut mint_account,
+                    &mut uninitialized_account,
                     &mut owner_account,
                 ],
             )
         );
 
-        // approve delegate 2
+        // unset mint_authority and test minting fails
         do_process_instruction(
-            approve_checked(
+            set_authority(
                 &program_id,
-                &account_key,
                 &mint_key,
-                &delegate_key,
+                None,
+                AuthorityType::MintTokens,
                 &owner_key,
                 &[],
-                100,
-                2,
             )
             .unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut delegate_account,
-                &mut owner_account,
-            ],
-        )
-        .unwrap();
-
-        // revoke delegate
-        do_process_instruction(
-            revoke(&program_id, &account_key, &owner_key, &[]).unwrap(),
-            vec![&mut account_account, &mut owner_account],
+            vec![&mut mint_account, &mut owner_account],
         )
         .unwrap();
+        assert_eq!(
+            Err(TokenError::FixedSupply.into()),
+            do_process_instruction(
+                mint_to(&program_id, &mint_key, &account2_key, &owner_key, &[], 42).unwrap(),
+                vec![&mut mint_account, &mut account2_account, &mut owner_account],
+            )
+        );
     }
 
     #[test]
-    fn test_set_authority_dups() {
+    fn test_burn_dups() {
         let program_id = crate::id();
         let account1_key = Pubkey::new_unique();
         let mut account1_account = SolanaAccount::new(
@@ -3090,6 +4777,8 @@ mod tests {
         );
         let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
         let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner_info: AccountInfo = (&owner_key, true, &mut owner_account).into();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
@@ -3100,7 +4789,7 @@ mod tests {
 
         // create mint
         do_process_instruction_dups(
-            initialize_mint(&program_id, &mint_key, &mint_key, Some(&mint_key), 2).unwrap(),
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
             vec![mint_info.clone(), rent_info.clone()],
         )
         .unwrap();
@@ -3117,73 +4806,168 @@ mod tests {
         )
         .unwrap();
 
-        // set mint_authority when currently self
+        // mint to account
         do_process_instruction_dups(
-            set_authority(
+            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
+
+        // source-owner burn
+        do_process_instruction_dups(
+            burn(
                 &program_id,
                 &mint_key,
-                Some(&owner_key),
-                AuthorityType::MintTokens,
+                &account1_key,
+                &account1_key,
+                &[],
+                500,
+            )
+            .unwrap(),
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account1_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // source-owner burn_checked
+        do_process_instruction_dups(
+            burn_checked(
+                &program_id,
+                &account1_key,
                 &mint_key,
+                &account1_key,
                 &[],
+                500,
+                2,
             )
             .unwrap(),
-            vec![mint_info.clone(), mint_info.clone()],
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account1_info.clone(),
+            ],
         )
         .unwrap();
 
-        // set freeze_authority when currently self
+        // mint-owner burn
         do_process_instruction_dups(
-            set_authority(
+            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
+        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
+        account.owner = mint_key;
+        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
+        do_process_instruction_dups(
+            burn(&program_id, &account1_key, &mint_key, &mint_key, &[], 500).unwrap(),
+            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
+        )
+        .unwrap();
+
+        // mint-owner burn_checked
+        do_process_instruction_dups(
+            burn_checked(
                 &program_id,
+                &account1_key,
                 &mint_key,
-                Some(&owner_key),
-                AuthorityType::FreezeAccount,
                 &mint_key,
                 &[],
+                500,
+                2,
             )
             .unwrap(),
-            vec![mint_info.clone(), mint_info.clone()],
+            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
         )
         .unwrap();
 
-        // set account owner when currently self
+        // source-delegate burn
+        do_process_instruction_dups(
+            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
+        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
+        account.delegated_amount = 1000;
+        account.delegate = COption::Some(account1_key);
+        account.owner = owner_key;
+        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
+        do_process_instruction_dups(
+            burn(
+                &program_id,
+                &account1_key,
+                &mint_key,
+                &account1_key,
+                &[],
+                500,
+            )
+            .unwrap(),
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account1_info.clone(),
+            ],
+        )
+        .unwrap();
+
+        // source-delegate burn_checked
         do_process_instruction_dups(
-            set_authority(
+            burn_checked(
                 &program_id,
                 &account1_key,
-                Some(&owner_key),
-                AuthorityType::AccountOwner,
+                &mint_key,
                 &account1_key,
                 &[],
+                500,
+                2,
             )
             .unwrap(),
-            vec![account1_info.clone(), account1_info.clone()],
+            vec![
+                account1_info.clone(),
+                mint_info.clone(),
+                account1_info.clone(),
+            ],
         )
         .unwrap();
 
-        // set close_authority when currently self
+        // mint-delegate burn
+        do_process_instruction_dups(
+            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
+            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
+        )
+        .unwrap();
         let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.close_authority = COption::Some(account1_key);
+        account.delegated_amount = 1000;
+        account.delegate = COption::Some(mint_key);
+        account.owner = owner_key;
         Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
+        do_process_instruction_dups(
+            burn(&program_id, &account1_key, &mint_key, &mint_key, &[], 500).unwrap(),
+            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
+        )
+        .unwrap();
 
+        // mint-delegate burn_checked
         do_process_instruction_dups(
-            set_authority(
+            burn_checked(
                 &program_id,
                 &account1_key,
-                Some(&owner_key),
-                AuthorityType::CloseAccount,
-                &account1_key,
+                &mint_key,
+                &mint_key,
                 &[],
+                500,
+                2,
             )
             .unwrap(),
-            vec![account1_info.clone(), account1_info.clone()],
+            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
         )
         .unwrap();
     }
 
     #[test]
-    fn test_set_authority() {
+    fn test_burn() {
         let program_id = crate::id();
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
@@ -3197,51 +4981,37 @@ mod tests {
             Account::get_packed_len(),
             &program_id,
         );
+        let account3_key = Pubkey::new_unique();
+        let mut account3_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let delegate_key = Pubkey::new_unique();
+        let mut delegate_account = SolanaAccount::default();
+        let mismatch_key = Pubkey::new_unique();
+        let mut mismatch_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner2_key = Pubkey::new_unique();
         let mut owner2_account = SolanaAccount::default();
-        let owner3_key = Pubkey::new_unique();
-        let mut owner3_account = SolanaAccount::default();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let mint2_key = Pubkey::new_unique();
-        let mut mint2_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let mut rent_sysvar = rent_sysvar();
 
-        // create new mint with owner
+        // create new mint
         do_process_instruction(
             initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
 
-        // create mint with owner and freeze_authority
-        do_process_instruction(
-            initialize_mint(&program_id, &mint2_key, &owner_key, Some(&owner_key), 2).unwrap(),
-            vec![&mut mint2_account, &mut rent_sysvar],
-        )
-        .unwrap();
-
-        // invalid account
-        assert_eq!(
-            Err(ProgramError::UninitializedAccount),
-            do_process_instruction(
-                set_authority(
-                    &program_id,
-                    &account_key,
-                    Some(&owner2_key),
-                    AuthorityType::AccountOwner,
-                    &owner_key,
-                    &[]
-                )
-                .unwrap(),
-                vec![&mut account_account, &mut owner_account],
-            )
-        );
-
         // create account
         do_process_instruction(
             initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
@@ -3256,615 +5026,789 @@ mod tests {
 
         // create another account
         do_process_instruction(
-            initialize_account(&program_id, &account2_key, &mint2_key, &owner_key).unwrap(),
+            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
             vec![
                 &mut account2_account,
-                &mut mint2_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create another account
+        do_process_instruction(
+            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account3_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // create mismatch account
+        do_process_instruction(
+            initialize_account(&program_id, &mismatch_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut mismatch_account,
+                &mut mint_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
 
+        // mint to account
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+
+        // mint to mismatch account and change mint key
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &mismatch_key, &owner_key, &[], 1000).unwrap(),
+            vec![&mut mint_account, &mut mismatch_account, &mut owner_account],
+        )
+        .unwrap();
+        let mut account = Account::unpack_unchecked(&mismatch_account.data).unwrap();
+        account.mint = mint2_key;
+        Account::pack(account, &mut mismatch_account.data).unwrap();
+
+        // missing signer
+        let mut instruction =
+            burn(&program_id, &account_key, &mint_key, &delegate_key, &[], 42).unwrap();
+        instruction.accounts[1].is_signer = false;
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                instruction,
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut delegate_account
+                ],
+            )
+        );
+
         // missing owner
         assert_eq!(
             Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                set_authority(
+                burn(&program_id, &account_key, &mint_key, &owner2_key, &[], 42).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+            )
+        );
+
+        // mint mismatch
+        assert_eq!(
+            Err(TokenError::MintMismatch.into()),
+            do_process_instruction(
+                burn(&program_id, &mismatch_key, &mint_key, &owner_key, &[], 42).unwrap(),
+                vec![&mut mismatch_account, &mut mint_account, &mut owner_account],
+            )
+        );
+
+        // burn
+        do_process_instruction(
+            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 21).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
+        )
+        .unwrap();
+
+        // burn_checked, with incorrect decimals
+        assert_eq!(
+            Err(TokenError::MintDecimalsMismatch.into()),
+            do_process_instruction(
+                burn_checked(&program_id, &account_key, &mint_key, &owner_key, &[], 21, 3).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner_account],
+            )
+        );
+
+        // burn_checked
+        do_process_instruction(
+            burn_checked(&program_id, &account_key, &mint_key, &owner_key, &[], 21, 2).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
+        )
+        .unwrap();
+
+        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+        assert_eq!(mint.supply, 2000 - 42);
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 1000 - 42);
+
+        // insufficient funds
+        assert_eq!(
+            Err(TokenError::InsufficientFunds.into()),
+            do_process_instruction(
+                burn(
                     &program_id,
                     &account_key,
-                    Some(&owner_key),
-                    AuthorityType::AccountOwner,
-                    &owner2_key,
-                    &[]
+                    &mint_key,
+                    &owner_key,
+                    &[],
+                    100_000_000
                 )
                 .unwrap(),
-                vec![&mut account_account, &mut owner2_account],
+                vec![&mut account_account, &mut mint_account, &mut owner_account],
             )
         );
 
-        // owner did not sign
-        let mut instruction = set_authority(
-            &program_id,
-            &account_key,
-            Some(&owner2_key),
-            AuthorityType::AccountOwner,
-            &owner_key,
-            &[],
+        // approve delegate
+        do_process_instruction(
+            approve(
+                &program_id,
+                &account_key,
+                &delegate_key,
+                &owner_key,
+                &[],
+                84,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut delegate_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+
+        // not a delegate of source account
+        assert_eq!(
+            Err(TokenError::InsufficientFunds.into()),
+            do_process_instruction(
+                burn(
+                    &program_id,
+                    &account_key,
+                    &mint_key,
+                    &owner_key,
+                    &[],
+                    100_000_000
+                )
+                .unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner_account],
+            )
+        );
+
+        // burn via delegate
+        do_process_instruction(
+            burn(&program_id, &account_key, &mint_key, &delegate_key, &[], 84).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut delegate_account,
+            ],
         )
         .unwrap();
-        instruction.accounts[1].is_signer = false;
-        assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            do_process_instruction(instruction, vec![&mut account_account, &mut owner_account,],)
-        );
 
-        // wrong authority type
+        // match
+        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+        assert_eq!(mint.supply, 2000 - 42 - 84);
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 1000 - 42 - 84);
+
+        // insufficient funds approved via delegate
         assert_eq!(
-            Err(TokenError::AuthorityTypeNotSupported.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                set_authority(
+                burn(
                     &program_id,
                     &account_key,
-                    Some(&owner2_key),
-                    AuthorityType::FreezeAccount,
-                    &owner_key,
+                    &mint_key,
+                    &delegate_key,
                     &[],
+                    100
                 )
                 .unwrap(),
-                vec![&mut account_account, &mut owner_account],
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut delegate_account
+                ],
             )
         );
+    }
 
-        // account owner may not be set to None
+    #[test]
+    fn test_multisig() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let account_key = Pubkey::new_unique();
+        let mut account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let multisig_key = Pubkey::new_unique();
+        let mut multisig_account = SolanaAccount::new(42, Multisig::get_packed_len(), &program_id);
+        let multisig_delegate_key = Pubkey::new_unique();
+        let mut multisig_delegate_account = SolanaAccount::new(
+            multisig_minimum_balance(),
+            Multisig::get_packed_len(),
+            &program_id,
+        );
+        let signer_keys = vec![Pubkey::new_unique(); MAX_SIGNERS];
+        let signer_key_refs: Vec<&Pubkey> = signer_keys.iter().collect();
+        let mut signer_accounts = vec![SolanaAccount::new(0, 0, &program_id); MAX_SIGNERS];
+        let mut rent_sysvar = rent_sysvar();
+
+        // multisig is not rent exempt
+        let account_info_iter = &mut signer_accounts.iter_mut();
         assert_eq!(
-            Err(TokenError::InvalidInstruction.into()),
+            Err(TokenError::NotRentExempt.into()),
             do_process_instruction(
-                set_authority(
-                    &program_id,
-                    &account_key,
-                    None,
-                    AuthorityType::AccountOwner,
-                    &owner_key,
-                    &[],
-                )
-                .unwrap(),
-                vec![&mut account_account, &mut owner_account],
+                initialize_multisig(&program_id, &multisig_key, &[&signer_keys[0]], 1).unwrap(),
+                vec![
+                    &mut multisig_account,
+                    &mut rent_sysvar,
+                    &mut account_info_iter.next().unwrap(),
+                ],
             )
         );
 
-        // set delegate
+        multisig_account.lamports = multisig_minimum_balance();
+        let mut multisig_account2 = multisig_account.clone();
+
+        // single signer
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            approve(
-                &program_id,
-                &account_key,
-                &owner2_key,
-                &owner_key,
-                &[],
-                u64::MAX,
-            )
-            .unwrap(),
+            initialize_multisig(&program_id, &multisig_key, &[&signer_keys[0]], 1).unwrap(),
             vec![
-                &mut account_account,
-                &mut owner2_account,
-                &mut owner_account,
+                &mut multisig_account,
+                &mut rent_sysvar,
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.delegate, COption::Some(owner2_key));
-        assert_eq!(account.delegated_amount, u64::MAX);
 
-        // set owner
+        // single signer using `initialize_multisig2`
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            set_authority(
-                &program_id,
-                &account_key,
-                Some(&owner3_key),
-                AuthorityType::AccountOwner,
-                &owner_key,
-                &[],
-            )
-            .unwrap(),
-            vec![&mut account_account, &mut owner_account],
+            initialize_multisig2(&program_id, &multisig_key, &[&signer_keys[0]], 1).unwrap(),
+            vec![
+                &mut multisig_account2,
+                &mut account_info_iter.next().unwrap(),
+            ],
         )
         .unwrap();
 
-        // check delegate cleared
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.delegate, COption::None);
-        assert_eq!(account.delegated_amount, 0);
-
-        // set owner without existing delegate
+        // multiple signer
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            set_authority(
+            initialize_multisig(
                 &program_id,
-                &account_key,
-                Some(&owner2_key),
-                AuthorityType::AccountOwner,
-                &owner3_key,
-                &[],
+                &multisig_delegate_key,
+                &signer_key_refs,
+                MAX_SIGNERS as u8,
             )
             .unwrap(),
-            vec![&mut account_account, &mut owner3_account],
+            vec![
+                &mut multisig_delegate_account,
+                &mut rent_sysvar,
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+            ],
         )
         .unwrap();
 
-        // set close_authority
+        // create new mint with multisig owner
         do_process_instruction(
-            set_authority(
-                &program_id,
-                &account_key,
-                Some(&owner2_key),
-                AuthorityType::CloseAccount,
-                &owner2_key,
-                &[],
-            )
-            .unwrap(),
-            vec![&mut account_account, &mut owner2_account],
+            initialize_mint(&program_id, &mint_key, &multisig_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
 
-        // close_authority may be set to None
+        // create account with multisig owner
         do_process_instruction(
-            set_authority(
-                &program_id,
-                &account_key,
-                None,
-                AuthorityType::CloseAccount,
-                &owner2_key,
-                &[],
-            )
-            .unwrap(),
-            vec![&mut account_account, &mut owner2_account],
-        )
-        .unwrap();
-
-        // wrong owner
-        assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
-            do_process_instruction(
-                set_authority(
-                    &program_id,
-                    &mint_key,
-                    Some(&owner3_key),
-                    AuthorityType::MintTokens,
-                    &owner2_key,
-                    &[]
-                )
-                .unwrap(),
-                vec![&mut mint_account, &mut owner2_account],
-            )
-        );
-
-        // owner did not sign
-        let mut instruction = set_authority(
-            &program_id,
-            &mint_key,
-            Some(&owner2_key),
-            AuthorityType::MintTokens,
-            &owner_key,
-            &[],
+            initialize_account(&program_id, &account_key, &mint_key, &multisig_key).unwrap(),
+            vec![
+                &mut account,
+                &mut mint_account,
+                &mut multisig_account,
+                &mut rent_sysvar,
+            ],
         )
         .unwrap();
-        instruction.accounts[1].is_signer = false;
-        assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            do_process_instruction(instruction, vec![&mut mint_account, &mut owner_account],)
-        );
-
-        // cannot freeze
-        assert_eq!(
-            Err(TokenError::MintCannotFreeze.into()),
-            do_process_instruction(
-                set_authority(
-                    &program_id,
-                    &mint_key,
-                    Some(&owner2_key),
-                    AuthorityType::FreezeAccount,
-                    &owner_key,
-                    &[],
-                )
-                .unwrap(),
-                vec![&mut mint_account, &mut owner_account],
-            )
-        );
 
-        // set owner
+        // create another account with multisig owner
         do_process_instruction(
-            set_authority(
+            initialize_account(
                 &program_id,
-                &mint_key,
-                Some(&owner2_key),
-                AuthorityType::MintTokens,
-                &owner_key,
-                &[],
+                &account2_key,
+                &mint_key,
+                &multisig_delegate_key,
             )
             .unwrap(),
-            vec![&mut mint_account, &mut owner_account],
+            vec![
+                &mut account2_account,
+                &mut mint_account,
+                &mut multisig_account,
+                &mut rent_sysvar,
+            ],
         )
         .unwrap();
 
-        // set owner to None
+        // mint to account
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            set_authority(
+            mint_to(
                 &program_id,
                 &mint_key,
-                None,
-                AuthorityType::MintTokens,
-                &owner2_key,
-                &[],
+                &account_key,
+                &multisig_key,
+                &[&signer_keys[0]],
+                1000,
             )
             .unwrap(),
-            vec![&mut mint_account, &mut owner2_account],
+            vec![
+                &mut mint_account,
+                &mut account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
+            ],
         )
         .unwrap();
 
-        // test unsetting mint_authority is one-way operation
-        assert_eq!(
-            Err(TokenError::FixedSupply.into()),
-            do_process_instruction(
-                set_authority(
-                    &program_id,
-                    &mint2_key,
-                    Some(&owner2_key),
-                    AuthorityType::MintTokens,
-                    &owner_key,
-                    &[]
-                )
-                .unwrap(),
-                vec![&mut mint_account, &mut owner_account],
-            )
-        );
-
-        // set freeze_authority
+        // approve
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            set_authority(
+            approve(
                 &program_id,
-                &mint2_key,
-                Some(&owner2_key),
-                AuthorityType::FreezeAccount,
-                &owner_key,
-                &[],
+                &account_key,
+                &multisig_delegate_key,
+                &multisig_key,
+                &[&signer_keys[0]],
+                100,
             )
             .unwrap(),
-            vec![&mut mint2_account, &mut owner_account],
+            vec![
+                &mut account,
+                &mut multisig_delegate_account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
+            ],
         )
         .unwrap();
 
-        // test unsetting freeze_authority is one-way operation
+        // transfer
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            set_authority(
+            transfer(
                 &program_id,
-                &mint2_key,
-                None,
-                AuthorityType::FreezeAccount,
-                &owner2_key,
-                &[],
+                &account_key,
+                &account2_key,
+                &multisig_key,
+                &[&signer_keys[0]],
+                42,
             )
             .unwrap(),
-            vec![&mut mint2_account, &mut owner2_account],
+            vec![
+                &mut account,
+                &mut account2_account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
+            ],
         )
         .unwrap();
 
-        assert_eq!(
-            Err(TokenError::MintCannotFreeze.into()),
-            do_process_instruction(
-                set_authority(
-                    &program_id,
-                    &mint2_key,
-                    Some(&owner2_key),
-                    AuthorityType::FreezeAccount,
-                    &owner_key,
-                    &[],
-                )
-                .unwrap(),
-                vec![&mut mint2_account, &mut owner2_account],
+        // transfer via delegate
+        let account_info_iter = &mut signer_accounts.iter_mut();
+        do_process_instruction(
+            transfer(
+                &program_id,
+                &account_key,
+                &account2_key,
+                &multisig_delegate_key,
+                &signer_key_refs,
+                42,
             )
-        );
-    }
-
-    #[test]
-    fn test_mint_to_dups() {
-        let program_id = crate::id();
-        let account1_key = Pubkey::new_unique();
-        let mut account1_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
-        let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let owner_info: AccountInfo = (&owner_key, true, &mut owner_account).into();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint_info: AccountInfo = (&mint_key, true, &mut mint_account).into();
-        let rent_key = rent::id();
-        let mut rent_sysvar = rent_sysvar();
-        let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
-
-        // create mint
-        do_process_instruction_dups(
-            initialize_mint(&program_id, &mint_key, &mint_key, None, 2).unwrap(),
-            vec![mint_info.clone(), rent_info.clone()],
-        )
-        .unwrap();
-
-        // create account
-        do_process_instruction_dups(
-            initialize_account(&program_id, &account1_key, &mint_key, &owner_key).unwrap(),
+            .unwrap(),
             vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                owner_info.clone(),
-                rent_info.clone(),
+                &mut account,
+                &mut account2_account,
+                &mut multisig_delegate_account,
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
 
-        // mint_to when mint_authority is self
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account1_key, &mint_key, &[], 42).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), mint_info.clone()],
-        )
-        .unwrap();
-
-        // mint_to_checked when mint_authority is self
-        do_process_instruction_dups(
-            mint_to_checked(&program_id, &mint_key, &account1_key, &mint_key, &[], 42, 2).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), mint_info.clone()],
-        )
-        .unwrap();
-
-        // mint_to when mint_authority is account owner
-        let mut mint = Mint::unpack_unchecked(&mint_info.data.borrow()).unwrap();
-        mint.mint_authority = COption::Some(account1_key);
-        Mint::pack(mint, &mut mint_info.data.borrow_mut()).unwrap();
-        do_process_instruction_dups(
+        // mint to
+        let account_info_iter = &mut signer_accounts.iter_mut();
+        do_process_instruction(
             mint_to(
                 &program_id,
                 &mint_key,
-                &account1_key,
-                &account1_key,
-                &[],
+                &account2_key,
+                &multisig_key,
+                &[&signer_keys[0]],
                 42,
             )
             .unwrap(),
             vec![
-                mint_info.clone(),
-                account1_info.clone(),
-                account1_info.clone(),
+                &mut mint_account,
+                &mut account2_account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
 
-        // mint_to_checked when mint_authority is account owner
-        do_process_instruction_dups(
-            mint_to(
+        // burn
+        let account_info_iter = &mut signer_accounts.iter_mut();
+        do_process_instruction(
+            burn(
                 &program_id,
+                &account_key,
                 &mint_key,
-                &account1_key,
-                &account1_key,
-                &[],
+                &multisig_key,
+                &[&signer_keys[0]],
                 42,
             )
             .unwrap(),
             vec![
-                mint_info.clone(),
-                account1_info.clone(),
-                account1_info.clone(),
+                &mut account,
+                &mut mint_account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
-    }
 
-    #[test]
-    fn test_mint_to() {
-        let program_id = crate::id();
-        let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
+        // burn via delegate
+        let account_info_iter = &mut signer_accounts.iter_mut();
+        do_process_instruction(
+            burn(
+                &program_id,
+                &account_key,
+                &mint_key,
+                &multisig_delegate_key,
+                &signer_key_refs,
+                42,
+            )
+            .unwrap(),
+            vec![
+                &mut account,
+                &mut mint_account,
+                &mut multisig_delegate_account,
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+                &mut account_info_iter.next().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        // freeze account
         let account3_key = Pubkey::new_unique();
         let mut account3_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let mismatch_key = Pubkey::new_unique();
-        let mut mismatch_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let owner2_key = Pubkey::new_unique();
-        let mut owner2_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let mint2_key = Pubkey::new_unique();
-        let uninitialized_key = Pubkey::new_unique();
-        let mut uninitialized_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let mut rent_sysvar = rent_sysvar();
-
-        // create new mint with owner
+        let mut mint2_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            initialize_mint(
+                &program_id,
+                &mint2_key,
+                &multisig_key,
+                Some(&multisig_key),
+                2,
+            )
+            .unwrap(),
+            vec![&mut mint2_account, &mut rent_sysvar],
         )
         .unwrap();
-
-        // create account
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            initialize_account(&program_id, &account3_key, &mint2_key, &owner_key).unwrap(),
             vec![
-                &mut account_account,
-                &mut mint_account,
+                &mut account3_account,
+                &mut mint2_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
-
-        // create another account
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            mint_to(
+                &program_id,
+                &mint2_key,
+                &account3_key,
+                &multisig_key,
+                &[&signer_keys[0]],
+                1000,
+            )
+            .unwrap(),
             vec![
-                &mut account2_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
+                &mut mint2_account,
+                &mut account3_account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
-
-        // create another account
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
+            freeze_account(
+                &program_id,
+                &account3_key,
+                &mint2_key,
+                &multisig_key,
+                &[&signer_keys[0]],
+            )
+            .unwrap(),
             vec![
                 &mut account3_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
+                &mut mint2_account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
 
-        // create mismatch account
+        // do SetAuthority on mint
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            initialize_account(&program_id, &mismatch_key, &mint_key, &owner_key).unwrap(),
+            set_authority(
+                &program_id,
+                &mint_key,
+                Some(&owner_key),
+                AuthorityType::MintTokens,
+                &multisig_key,
+                &[&signer_keys[0]],
+            )
+            .unwrap(),
             vec![
-                &mut mismatch_account,
                 &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
             ],
         )
         .unwrap();
-        let mut account = Account::unpack_unchecked(&mismatch_account.data).unwrap();
-        account.mint = mint2_key;
-        Account::pack(account, &mut mismatch_account.data).unwrap();
 
-        // mint to
+        // do SetAuthority on account
+        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 42).unwrap(),
-            vec![&mut mint_account, &mut account_account, &mut owner_account],
+            set_authority(
+                &program_id,
+                &account_key,
+                Some(&owner_key),
+                AuthorityType::AccountOwner,
+                &multisig_key,
+                &[&signer_keys[0]],
+            )
+            .unwrap(),
+            vec![
+                &mut account,
+                &mut multisig_account,
+                &mut account_info_iter.next().unwrap(),
+            ],
         )
         .unwrap();
+    }
 
-        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
-        assert_eq!(mint.supply, 42);
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 42);
+    #[test]
+    fn test_validate_owner() {
+        let program_id = crate::id();
+        let owner_key = Pubkey::new_unique();
+        let mut signer_keys = [Pubkey::default(); MAX_SIGNERS];
+        for signer_key in signer_keys.iter_mut().take(MAX_SIGNERS) {
+            *signer_key = Pubkey::new_unique();
+        }
+        let mut signer_lamports = 0;
+        let mut signer_data = vec![];
+        let mut signers = vec![
+            AccountInfo::new(
+                &owner_key,
+                true,
+                false,
+                &mut signer_lamports,
+                &mut signer_data,
+                &program_id,
+                false,
+                Epoch::default(),
+            );
+            MAX_SIGNERS + 1
+        ];
+        for (signer, key) in signers.iter_mut().zip(&signer_keys) {
+            signer.key = key;
+        }
+        let mut lamports = 0;
+        let mut data = vec![0; Multisig::get_packed_len()];
+        let mut multisig = Multisig::unpack_unchecked(&data).unwrap();
+        multisig.m = MAX_SIGNERS as u8;
+        multisig.n = MAX_SIGNERS as u8;
+        multisig.signers = signer_keys;
+        multisig.is_initialized = true;
+        Multisig::pack(multisig, &mut data).unwrap();
+        let owner_account_info = AccountInfo::new(
+            &owner_key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            Epoch::default(),
+        );
 
-        // mint to another account to test supply accumulation
-        do_process_instruction(
-            mint_to(&program_id, &mint_key, &account2_key, &owner_key, &[], 42).unwrap(),
-            vec![&mut mint_account, &mut account2_account, &mut owner_account],
-        )
-        .unwrap();
+        // full 11 of 11
+        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers).unwrap();
 
-        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
-        assert_eq!(mint.supply, 84);
-        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        assert_eq!(account.amount, 42);
+        // 1 of 11
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 1;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
+        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers).unwrap();
 
-        // missing signer
-        let mut instruction =
-            mint_to(&program_id, &mint_key, &account2_key, &owner_key, &[], 42).unwrap();
-        instruction.accounts[2].is_signer = false;
+        // 2:1
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 2;
+            multisig.n = 1;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
         assert_eq!(
             Err(ProgramError::MissingRequiredSignature),
-            do_process_instruction(
-                instruction,
-                vec![&mut mint_account, &mut account2_account, &mut owner_account],
-            )
+            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers)
         );
 
-        // mismatch account
-        assert_eq!(
-            Err(TokenError::MintMismatch.into()),
-            do_process_instruction(
-                mint_to(&program_id, &mint_key, &mismatch_key, &owner_key, &[], 42).unwrap(),
-                vec![&mut mint_account, &mut mismatch_account, &mut owner_account],
-            )
-        );
+        // 0:11
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 0;
+            multisig.n = 11;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
+        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers).unwrap();
 
-        // missing owner
-        assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
-            do_process_instruction(
-                mint_to(&program_id, &mint_key, &account2_key, &owner2_key, &[], 42).unwrap(),
-                vec![
-                    &mut mint_account,
-                    &mut account2_account,
-                    &mut owner2_account,
-                ],
-            )
+        // 2:11 but 0 provided
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 2;
+            multisig.n = 11;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &[])
         );
-
-        // uninitialized destination account
+        // 2:11 but 1 provided
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 2;
+            multisig.n = 11;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
         assert_eq!(
-            Err(ProgramError::UninitializedAccount),
-            do_process_instruction(
-                mint_to(
-                    &program_id,
-                    &mint_key,
-                    &uninitialized_key,
-                    &owner_key,
-                    &[],
-                    42
-                )
-                .unwrap(),
-                vec![
-                    &mut mint_account,
-                    &mut uninitialized_account,
-                    &mut owner_account,
-                ],
-            )
+            Err(ProgramError::MissingRequiredSignature),
+            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers[0..1])
         );
 
-        // unset mint_authority and test minting fails
-        do_process_instruction(
-            set_authority(
-                &program_id,
-                &mint_key,
-                None,
-                AuthorityType::MintTokens,
-                &owner_key,
-                &[],
-            )
-            .unwrap(),
-            vec![&mut mint_account, &mut owner_account],
-        )
-        .unwrap();
+        // 2:11, 2 from middle provided
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 2;
+            multisig.n = 11;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
+        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers[5..7])
+            .unwrap();
+
+        // 11:11, one is not a signer
+        {
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 11;
+            multisig.n = 11;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+        }
+        signers[5].is_signer = false;
         assert_eq!(
-            Err(TokenError::FixedSupply.into()),
-            do_process_instruction(
-                mint_to(&program_id, &mint_key, &account2_key, &owner_key, &[], 42).unwrap(),
-                vec![&mut mint_account, &mut account2_account, &mut owner_account],
-            )
+            Err(ProgramError::MissingRequiredSignature),
+            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers)
         );
+        signers[5].is_signer = true;
+
+        // 11:11, single signer signs multiple times
+        {
+            let mut signer_lamports = 0;
+            let mut signer_data = vec![];
+            let signers = vec![
+                AccountInfo::new(
+                    &signer_keys[5],
+                    true,
+                    false,
+                    &mut signer_lamports,
+                    &mut signer_data,
+                    &program_id,
+                    false,
+                    Epoch::default(),
+                );
+                MAX_SIGNERS + 1
+            ];
+            let mut multisig =
+                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
+            multisig.m = 11;
+            multisig.n = 11;
+            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
+            assert_eq!(
+                Err(ProgramError::MissingRequiredSignature),
+                Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers)
+            );
+        }
     }
 
     #[test]
-    fn test_burn_dups() {
+    fn test_close_account_dups() {
         let program_id = crate::id();
         let account1_key = Pubkey::new_unique();
         let mut account1_account = SolanaAccount::new(
@@ -3873,13 +5817,18 @@ mod tests {
             &program_id,
         );
         let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_info: AccountInfo = (&account2_key, true, &mut account2_account).into();
         let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let owner_info: AccountInfo = (&owner_key, true, &mut owner_account).into();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint_info: AccountInfo = (&mint_key, true, &mut mint_account).into();
+        let mint_info: AccountInfo = (&mint_key, false, &mut mint_account).into();
         let rent_key = rent::id();
         let mut rent_sysvar = rent_sysvar();
         let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
@@ -3903,169 +5852,53 @@ mod tests {
         )
         .unwrap();
 
-        // mint to account
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
-        )
-        .unwrap();
-
-        // source-owner burn
-        do_process_instruction_dups(
-            burn(
-                &program_id,
-                &mint_key,
-                &account1_key,
-                &account1_key,
-                &[],
-                500,
-            )
-            .unwrap(),
-            vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account1_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // source-owner burn_checked
+        // source-owner close
         do_process_instruction_dups(
-            burn_checked(
+            close_account(
                 &program_id,
                 &account1_key,
-                &mint_key,
+                &account2_key,
                 &account1_key,
                 &[],
-                500,
-                2,
             )
             .unwrap(),
             vec![
                 account1_info.clone(),
-                mint_info.clone(),
+                account2_info.clone(),
                 account1_info.clone(),
             ],
         )
         .unwrap();
 
-        // mint-owner burn
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
-        )
-        .unwrap();
-        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.owner = mint_key;
-        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
-        do_process_instruction_dups(
-            burn(&program_id, &account1_key, &mint_key, &mint_key, &[], 500).unwrap(),
-            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
-        )
-        .unwrap();
-
-        // mint-owner burn_checked
-        do_process_instruction_dups(
-            burn_checked(
-                &program_id,
-                &account1_key,
-                &mint_key,
-                &mint_key,
-                &[],
-                500,
-                2,
-            )
-            .unwrap(),
-            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
-        )
-        .unwrap();
-
-        // source-delegate burn
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
-        )
-        .unwrap();
+        // source-close-authority close
         let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.delegated_amount = 1000;
-        account.delegate = COption::Some(account1_key);
+        account.close_authority = COption::Some(account1_key);
         account.owner = owner_key;
         Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
         do_process_instruction_dups(
-            burn(
-                &program_id,
-                &account1_key,
-                &mint_key,
-                &account1_key,
-                &[],
-                500,
-            )
-            .unwrap(),
-            vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account1_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // source-delegate burn_checked
-        do_process_instruction_dups(
-            burn_checked(
+            close_account(
                 &program_id,
                 &account1_key,
-                &mint_key,
-                &account1_key,
-                &[],
-                500,
-                2,
-            )
-            .unwrap(),
-            vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account1_info.clone(),
-            ],
-        )
-        .unwrap();
-
-        // mint-delegate burn
-        do_process_instruction_dups(
-            mint_to(&program_id, &mint_key, &account1_key, &owner_key, &[], 1000).unwrap(),
-            vec![mint_info.clone(), account1_info.clone(), owner_info.clone()],
-        )
-        .unwrap();
-        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.delegated_amount = 1000;
-        account.delegate = COption::Some(mint_key);
-        account.owner = owner_key;
-        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
-        do_process_instruction_dups(
-            burn(&program_id, &account1_key, &mint_key, &mint_key, &[], 500).unwrap(),
-            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
-        )
-        .unwrap();
-
-        // mint-delegate burn_checked
-        do_process_instruction_dups(
-            burn_checked(
-                &program_id,
+                &account2_key,
                 &account1_key,
-                &mint_key,
-                &mint_key,
                 &[],
-                500,
-                2,
             )
             .unwrap(),
-            vec![account1_info.clone(), mint_info.clone(), mint_info.clone()],
+            vec![
+                account1_info.clone(),
+                account2_info.clone(),
+                account1_info.clone(),
+            ],
         )
         .unwrap();
     }
 
     #[test]
-    fn test_burn() {
+    fn test_close_account() {
         let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
             account_minimum_balance(),
@@ -4074,7 +5907,7 @@ mod tests {
         );
         let account2_key = Pubkey::new_unique();
         let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
+            account_minimum_balance() + 42,
             Account::get_packed_len(),
             &program_id,
         );
@@ -4084,32 +5917,31 @@ mod tests {
             Account::get_packed_len(),
             &program_id,
         );
-        let delegate_key = Pubkey::new_unique();
-        let mut delegate_account = SolanaAccount::default();
-        let mismatch_key = Pubkey::new_unique();
-        let mut mismatch_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner2_key = Pubkey::new_unique();
         let mut owner2_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint2_key = Pubkey::new_unique();
         let mut rent_sysvar = rent_sysvar();
 
-        // create new mint
+        // uninitialized
+        assert_eq!(
+            Err(ProgramError::UninitializedAccount),
+            do_process_instruction(
+                close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account3_account,
+                    &mut owner2_account,
+                ],
+            )
+        );
+
+        // initialize and mint to non-native account
         do_process_instruction(
             initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
-
-        // create account
         do_process_instruction(
             initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
             vec![
@@ -4120,792 +5952,757 @@ mod tests {
             ],
         )
         .unwrap();
-
-        // create another account
-        do_process_instruction(
-            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account2_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-
-        // create another account
         do_process_instruction(
-            initialize_account(&program_id, &account3_key, &mint_key, &owner_key).unwrap(),
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 42).unwrap(),
             vec![
-                &mut account3_account,
                 &mut mint_account,
+                &mut account_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 42);
 
-        // create mismatch account
+        // initialize native account
         do_process_instruction(
-            initialize_account(&program_id, &mismatch_key, &mint_key, &owner_key).unwrap(),
+            initialize_account(
+                &program_id,
+                &account2_key,
+                &crate::native_mint::id(),
+                &owner_key,
+            )
+            .unwrap(),
             vec![
-                &mut mismatch_account,
+                &mut account2_account,
                 &mut mint_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account.amount, 42);
 
-        // mint to account
-        do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
-            vec![&mut mint_account, &mut account_account, &mut owner_account],
-        )
-        .unwrap();
+        // close non-native account with balance
+        assert_eq!(
+            Err(TokenError::NonNativeHasBalance.into()),
+            do_process_instruction(
+                close_account(&program_id, &account_key, &account3_key, &owner_key, &[]).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account3_account,
+                    &mut owner_account,
+                ],
+            )
+        );
+        assert_eq!(account_account.lamports, account_minimum_balance());
 
-        // mint to mismatch account and change mint key
+        // empty account
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &mismatch_key, &owner_key, &[], 1000).unwrap(),
-            vec![&mut mint_account, &mut mismatch_account, &mut owner_account],
+            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 42).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
         )
         .unwrap();
-        let mut account = Account::unpack_unchecked(&mismatch_account.data).unwrap();
-        account.mint = mint2_key;
-        Account::pack(account, &mut mismatch_account.data).unwrap();
 
-        // missing signer
-        let mut instruction =
-            burn(&program_id, &account_key, &mint_key, &delegate_key, &[], 42).unwrap();
-        instruction.accounts[1].is_signer = false;
+        // wrong owner
         assert_eq!(
             Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                instruction,
+                close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
                 vec![
                     &mut account_account,
-                    &mut mint_account,
-                    &mut delegate_account
+                    &mut account3_account,
+                    &mut owner2_account,
                 ],
             )
         );
 
-        // missing owner
-        assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
-            do_process_instruction(
-                burn(&program_id, &account_key, &mint_key, &owner2_key, &[], 42).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner2_account],
-            )
-        );
+        // close account
+        do_process_instruction(
+            close_account(&program_id, &account_key, &account3_key, &owner_key, &[]).unwrap(),
+            vec![
+                &mut account_account,
+                &mut account3_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+        assert_eq!(account_account.lamports, 0);
+        assert_eq!(account3_account.lamports, 2 * account_minimum_balance());
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 0);
 
-        // mint mismatch
-        assert_eq!(
-            Err(TokenError::MintMismatch.into()),
-            do_process_instruction(
-                burn(&program_id, &mismatch_key, &mint_key, &owner_key, &[], 42).unwrap(),
-                vec![&mut mismatch_account, &mut mint_account, &mut owner_account],
-            )
+        // fund and initialize new non-native account to test close authority
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
         );
+        do_process_instruction(
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+        account_account.lamports = 2;
 
-        // burn
         do_process_instruction(
-            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 21).unwrap(),
-            vec![&mut account_account, &mut mint_account, &mut owner_account],
+            set_authority(
+                &program_id,
+                &account_key,
+                Some(&owner2_key),
+                AuthorityType::CloseAccount,
+                &owner_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        // burn_checked, with incorrect decimals
+        // account owner cannot authorize close if close_authority is set
         assert_eq!(
-            Err(TokenError::MintDecimalsMismatch.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                burn_checked(&program_id, &account_key, &mint_key, &owner_key, &[], 21, 3).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner_account],
+                close_account(&program_id, &account_key, &account3_key, &owner_key, &[]).unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account3_account,
+                    &mut owner_account,
+                ],
             )
         );
 
-        // burn_checked
+        // close non-native account with close_authority
         do_process_instruction(
-            burn_checked(&program_id, &account_key, &mint_key, &owner_key, &[], 21, 2).unwrap(),
-            vec![&mut account_account, &mut mint_account, &mut owner_account],
+            close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
+            vec![
+                &mut account_account,
+                &mut account3_account,
+                &mut owner2_account,
+            ],
         )
         .unwrap();
-
-        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
-        assert_eq!(mint.supply, 2000 - 42);
+        assert_eq!(account_account.lamports, 0);
+        assert_eq!(account3_account.lamports, 2 * account_minimum_balance() + 2);
         let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 1000 - 42);
+        assert_eq!(account.amount, 0);
 
-        // insufficient funds
+        // close native account
+        do_process_instruction(
+            close_account(&program_id, &account2_key, &account3_key, &owner_key, &[]).unwrap(),
+            vec![
+                &mut account2_account,
+                &mut account3_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account_account.lamports, 0);
+        assert_eq!(account.amount, 0);
         assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
-            do_process_instruction(
-                burn(
-                    &program_id,
-                    &account_key,
-                    &mint_key,
-                    &owner_key,
-                    &[],
-                    100_000_000
-                )
-                .unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner_account],
-            )
+            account3_account.lamports,
+            3 * account_minimum_balance() + 2 + 42
+        );
+    }
+
+    #[test]
+    fn test_native_token() {
+        let program_id = crate::id();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance() + 40,
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
         );
+        let account3_key = Pubkey::new_unique();
+        let mut account3_account = SolanaAccount::new(account_minimum_balance(), 0, &program_id);
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::default();
+        let owner3_key = Pubkey::new_unique();
+        let mut rent_sysvar = rent_sysvar();
 
-        // approve delegate
+        // initialize native account
         do_process_instruction(
-            approve(
+            initialize_account(
                 &program_id,
                 &account_key,
-                &delegate_key,
+                &crate::native_mint::id(),
                 &owner_key,
-                &[],
-                84,
             )
             .unwrap(),
             vec![
                 &mut account_account,
-                &mut delegate_account,
+                &mut mint_account,
                 &mut owner_account,
+                &mut rent_sysvar,
             ],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account.amount, 40);
 
-        // not a delegate of source account
+        // initialize native account
+        do_process_instruction(
+            initialize_account(
+                &program_id,
+                &account2_key,
+                &crate::native_mint::id(),
+                &owner_key,
+            )
+            .unwrap(),
+            vec![
+                &mut account2_account,
+                &mut mint_account,
+                &mut owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account.amount, 0);
+
+        // mint_to unsupported
         assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
+            Err(TokenError::NativeNotSupported.into()),
             do_process_instruction(
-                burn(
+                mint_to(
                     &program_id,
+                    &crate::native_mint::id(),
                     &account_key,
-                    &mint_key,
                     &owner_key,
                     &[],
-                    100_000_000
+                    42
                 )
                 .unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner_account],
+                vec![&mut mint_account, &mut account_account, &mut owner_account],
             )
         );
 
-        // burn via delegate
+        // burn unsupported
+        let bogus_mint_key = Pubkey::new_unique();
+        let mut bogus_mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         do_process_instruction(
-            burn(&program_id, &account_key, &mint_key, &delegate_key, &[], 84).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut delegate_account,
-            ],
+            initialize_mint(&program_id, &bogus_mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut bogus_mint_account, &mut rent_sysvar],
         )
         .unwrap();
 
-        // match
-        let mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
-        assert_eq!(mint.supply, 2000 - 42 - 84);
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 1000 - 42 - 84);
-
-        // insufficient funds approved via delegate
         assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
+            Err(TokenError::NativeNotSupported.into()),
             do_process_instruction(
                 burn(
                     &program_id,
                     &account_key,
-                    &mint_key,
-                    &delegate_key,
+                    &bogus_mint_key,
+                    &owner_key,
                     &[],
-                    100
+                    42
                 )
                 .unwrap(),
                 vec![
                     &mut account_account,
-                    &mut mint_account,
-                    &mut delegate_account
+                    &mut bogus_mint_account,
+                    &mut owner_account
                 ],
             )
         );
-    }
-
-    #[test]
-    fn test_multisig() {
-        let program_id = crate::id();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let account_key = Pubkey::new_unique();
-        let mut account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let multisig_key = Pubkey::new_unique();
-        let mut multisig_account = SolanaAccount::new(42, Multisig::get_packed_len(), &program_id);
-        let multisig_delegate_key = Pubkey::new_unique();
-        let mut multisig_delegate_account = SolanaAccount::new(
-            multisig_minimum_balance(),
-            Multisig::get_packed_len(),
-            &program_id,
-        );
-        let signer_keys = vec![Pubkey::new_unique(); MAX_SIGNERS];
-        let signer_key_refs: Vec<&Pubkey> = signer_keys.iter().collect();
-        let mut signer_accounts = vec![SolanaAccount::new(0, 0, &program_id); MAX_SIGNERS];
-        let mut rent_sysvar = rent_sysvar();
 
-        // multisig is not rent exempt
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // ensure can't transfer below rent-exempt reserve
         assert_eq!(
-            Err(TokenError::NotRentExempt.into()),
+            Err(TokenError::InsufficientFunds.into()),
             do_process_instruction(
-                initialize_multisig(&program_id, &multisig_key, &[&signer_keys[0]], 1).unwrap(),
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &owner_key,
+                    &[],
+                    50,
+                )
+                .unwrap(),
                 vec![
-                    &mut multisig_account,
-                    &mut rent_sysvar,
-                    &mut account_info_iter.next().unwrap(),
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut owner_account,
                 ],
             )
         );
 
-        multisig_account.lamports = multisig_minimum_balance();
-        let mut multisig_account2 = multisig_account.clone();
-
-        // single signer
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // transfer between native accounts
         do_process_instruction(
-            initialize_multisig(&program_id, &multisig_key, &[&signer_keys[0]], 1).unwrap(),
+            transfer(
+                &program_id,
+                &account_key,
+                &account2_key,
+                &owner_key,
+                &[],
+                40,
+            )
+            .unwrap(),
             vec![
-                &mut multisig_account,
-                &mut rent_sysvar,
-                &mut account_info_iter.next().unwrap(),
+                &mut account_account,
+                &mut account2_account,
+                &mut owner_account,
             ],
         )
         .unwrap();
+        assert_eq!(account_account.lamports, account_minimum_balance());
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account.amount, 0);
+        assert_eq!(account2_account.lamports, account_minimum_balance() + 40);
+        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account.amount, 40);
 
-        // single signer using `initialize_multisig2`
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // set close authority
         do_process_instruction(
-            initialize_multisig2(&program_id, &multisig_key, &[&signer_keys[0]], 1).unwrap(),
-            vec![
-                &mut multisig_account2,
-                &mut account_info_iter.next().unwrap(),
-            ],
+            set_authority(
+                &program_id,
+                &account_key,
+                Some(&owner3_key),
+                AuthorityType::CloseAccount,
+                &owner_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut account_account, &mut owner_account],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.close_authority, COption::Some(owner3_key));
 
-        // multiple signer
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // set new account owner
         do_process_instruction(
-            initialize_multisig(
+            set_authority(
                 &program_id,
-                &multisig_delegate_key,
-                &signer_key_refs,
-                MAX_SIGNERS as u8,
+                &account_key,
+                Some(&owner2_key),
+                AuthorityType::AccountOwner,
+                &owner_key,
+                &[],
             )
             .unwrap(),
+            vec![&mut account_account, &mut owner_account],
+        )
+        .unwrap();
+
+        // close authority cleared
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.close_authority, COption::None);
+
+        // close native account
+        do_process_instruction(
+            close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
             vec![
-                &mut multisig_delegate_account,
-                &mut rent_sysvar,
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
+                &mut account_account,
+                &mut account3_account,
+                &mut owner2_account,
             ],
         )
         .unwrap();
+        assert_eq!(account_account.lamports, 0);
+        assert_eq!(account3_account.lamports, 2 * account_minimum_balance());
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert!(account.is_native());
+        assert_eq!(account.amount, 0);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let program_id = crate::id();
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::default();
+        let mint_owner_key = Pubkey::new_unique();
+        let mut mint_owner_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mut rent_sysvar = rent_sysvar();
 
-        // create new mint with multisig owner
+        // create new mint with owner
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &multisig_key, None, 2).unwrap(),
+            initialize_mint(&program_id, &mint_key, &mint_owner_key, None, 2).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
 
-        // create account with multisig owner
+        // create an account
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &multisig_key).unwrap(),
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
             vec![
-                &mut account,
+                &mut account_account,
                 &mut mint_account,
-                &mut multisig_account,
+                &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
 
-        // create another account with multisig owner
+        // create another account
         do_process_instruction(
-            initialize_account(
-                &program_id,
-                &account2_key,
-                &mint_key,
-                &multisig_delegate_key,
-            )
-            .unwrap(),
+            initialize_account(&program_id, &account2_key, &mint_key, &owner2_key).unwrap(),
             vec![
                 &mut account2_account,
                 &mut mint_account,
-                &mut multisig_account,
+                &mut owner2_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
 
-        // mint to account
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // mint the max to an account
         do_process_instruction(
             mint_to(
                 &program_id,
                 &mint_key,
                 &account_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-                1000,
+                &mint_owner_key,
+                &[],
+                u64::MAX,
             )
             .unwrap(),
             vec![
                 &mut mint_account,
-                &mut account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
+                &mut account_account,
+                &mut mint_owner_account,
             ],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, u64::MAX);
 
-        // approve
-        let account_info_iter = &mut signer_accounts.iter_mut();
-        do_process_instruction(
-            approve(
-                &program_id,
-                &account_key,
-                &multisig_delegate_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-                100,
+        // attempt to mint one more to account
+        assert_eq!(
+            Err(TokenError::Overflow.into()),
+            do_process_instruction(
+                mint_to(
+                    &program_id,
+                    &mint_key,
+                    &account_key,
+                    &mint_owner_key,
+                    &[],
+                    1,
+                )
+                .unwrap(),
+                vec![
+                    &mut mint_account,
+                    &mut account_account,
+                    &mut mint_owner_account,
+                ],
             )
-            .unwrap(),
-            vec![
-                &mut account,
-                &mut multisig_delegate_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
-            ],
-        )
-        .unwrap();
+        );
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, u64::MAX);
 
-        // transfer
-        let account_info_iter = &mut signer_accounts.iter_mut();
-        do_process_instruction(
-            transfer(
-                &program_id,
-                &account_key,
-                &account2_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-                42,
+        // atttempt to mint one more to the other account
+        assert_eq!(
+            Err(TokenError::Overflow.into()),
+            do_process_instruction(
+                mint_to(
+                    &program_id,
+                    &mint_key,
+                    &account2_key,
+                    &mint_owner_key,
+                    &[],
+                    1,
+                )
+                .unwrap(),
+                vec![
+                    &mut mint_account,
+                    &mut account2_account,
+                    &mut mint_owner_account,
+                ],
             )
-            .unwrap(),
-            vec![
-                &mut account,
-                &mut account2_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
-            ],
-        )
-        .unwrap();
+        );
 
-        // transfer via delegate
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // burn some of the supply
         do_process_instruction(
-            transfer(
-                &program_id,
-                &account_key,
-                &account2_key,
-                &multisig_delegate_key,
-                &signer_key_refs,
-                42,
-            )
-            .unwrap(),
-            vec![
-                &mut account,
-                &mut account2_account,
-                &mut multisig_delegate_account,
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-            ],
+            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 100).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, u64::MAX - 100);
 
-        // mint to
-        let account_info_iter = &mut signer_accounts.iter_mut();
         do_process_instruction(
             mint_to(
                 &program_id,
                 &mint_key,
-                &account2_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-                42,
-            )
-            .unwrap(),
-            vec![
-                &mut mint_account,
-                &mut account2_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
-            ],
-        )
-        .unwrap();
-
-        // burn
-        let account_info_iter = &mut signer_accounts.iter_mut();
-        do_process_instruction(
-            burn(
-                &program_id,
                 &account_key,
-                &mint_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-                42,
+                &mint_owner_key,
+                &[],
+                100,
             )
             .unwrap(),
             vec![
-                &mut account,
                 &mut mint_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
+                &mut account_account,
+                &mut mint_owner_account,
             ],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, u64::MAX);
 
-        // burn via delegate
-        let account_info_iter = &mut signer_accounts.iter_mut();
-        do_process_instruction(
-            burn(
-                &program_id,
-                &account_key,
-                &mint_key,
-                &multisig_delegate_key,
-                &signer_key_refs,
-                42,
+        // manipulate account balance to attempt overflow transfer
+        let mut account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        account.amount = 1;
+        Account::pack(account, &mut account2_account.data).unwrap();
+
+        assert_eq!(
+            Err(TokenError::Overflow.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account2_key,
+                    &account_key,
+                    &owner2_key,
+                    &[],
+                    1,
+                )
+                .unwrap(),
+                vec![
+                    &mut account2_account,
+                    &mut account_account,
+                    &mut owner2_account,
+                ],
             )
-            .unwrap(),
-            vec![
-                &mut account,
-                &mut mint_account,
-                &mut multisig_delegate_account,
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-                &mut account_info_iter.next().unwrap(),
-            ],
-        )
-        .unwrap();
+        );
+    }
 
-        // freeze account
-        let account3_key = Pubkey::new_unique();
-        let mut account3_account = SolanaAccount::new(
+    #[test]
+    fn test_frozen() {
+        let program_id = crate::id();
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let mint2_key = Pubkey::new_unique();
-        let mut mint2_account =
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mut rent_sysvar = rent_sysvar();
+
+        // create new mint and fund first account
         do_process_instruction(
-            initialize_mint(
-                &program_id,
-                &mint2_key,
-                &multisig_key,
-                Some(&multisig_key),
-                2,
-            )
-            .unwrap(),
-            vec![&mut mint2_account, &mut rent_sysvar],
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
+
+        // create account
         do_process_instruction(
-            initialize_account(&program_id, &account3_key, &mint2_key, &owner_key).unwrap(),
+            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
             vec![
-                &mut account3_account,
-                &mut mint2_account,
+                &mut account_account,
+                &mut mint_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
-        let account_info_iter = &mut signer_accounts.iter_mut();
-        do_process_instruction(
-            mint_to(
-                &program_id,
-                &mint2_key,
-                &account3_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-                1000,
-            )
-            .unwrap(),
-            vec![
-                &mut mint2_account,
-                &mut account3_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
-            ],
-        )
-        .unwrap();
-        let account_info_iter = &mut signer_accounts.iter_mut();
-        do_process_instruction(
-            freeze_account(
-                &program_id,
-                &account3_key,
-                &mint2_key,
-                &multisig_key,
-                &[&signer_keys[0]],
-            )
-            .unwrap(),
-            vec![
-                &mut account3_account,
-                &mut mint2_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
-            ],
-        )
-        .unwrap();
 
-        // do SetAuthority on mint
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // create another account
         do_process_instruction(
-            set_authority(
-                &program_id,
-                &mint_key,
-                Some(&owner_key),
-                AuthorityType::MintTokens,
-                &multisig_key,
-                &[&signer_keys[0]],
-            )
-            .unwrap(),
+            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
             vec![
+                &mut account2_account,
                 &mut mint_account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
+                &mut owner_account,
+                &mut rent_sysvar,
             ],
         )
         .unwrap();
 
-        // do SetAuthority on account
-        let account_info_iter = &mut signer_accounts.iter_mut();
+        // fund first account
         do_process_instruction(
-            set_authority(
-                &program_id,
-                &account_key,
-                Some(&owner_key),
-                AuthorityType::AccountOwner,
-                &multisig_key,
-                &[&signer_keys[0]],
-            )
-            .unwrap(),
-            vec![
-                &mut account,
-                &mut multisig_account,
-                &mut account_info_iter.next().unwrap(),
-            ],
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
-    }
 
-    #[test]
-    fn test_validate_owner() {
-        let program_id = crate::id();
-        let owner_key = Pubkey::new_unique();
-        let mut signer_keys = [Pubkey::default(); MAX_SIGNERS];
-        for signer_key in signer_keys.iter_mut().take(MAX_SIGNERS) {
-            *signer_key = Pubkey::new_unique();
-        }
-        let mut signer_lamports = 0;
-        let mut signer_data = vec![];
-        let mut signers = vec![
-            AccountInfo::new(
-                &owner_key,
-                true,
-                false,
-                &mut signer_lamports,
-                &mut signer_data,
-                &program_id,
-                false,
-                Epoch::default(),
-            );
-            MAX_SIGNERS + 1
-        ];
-        for (signer, key) in signers.iter_mut().zip(&signer_keys) {
-            signer.key = key;
-        }
-        let mut lamports = 0;
-        let mut data = vec![0; Multisig::get_packed_len()];
-        let mut multisig = Multisig::unpack_unchecked(&data).unwrap();
-        multisig.m = MAX_SIGNERS as u8;
-        multisig.n = MAX_SIGNERS as u8;
-        multisig.signers = signer_keys;
-        multisig.is_initialized = true;
-        Multisig::pack(multisig, &mut data).unwrap();
-        let owner_account_info = AccountInfo::new(
-            &owner_key,
-            false,
-            false,
-            &mut lamports,
-            &mut data,
-            &program_id,
-            false,
-            Epoch::default(),
+        // no transfer if either account is frozen
+        let mut account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        account.state = AccountState::Frozen;
+        Account::pack(account, &mut account2_account.data).unwrap();
+        assert_eq!(
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &owner_key,
+                    &[],
+                    500,
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                ],
+            )
         );
 
-        // full 11 of 11
-        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers).unwrap();
-
-        // 1 of 11
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 1;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
-        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers).unwrap();
-
-        // 2:1
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 2;
-            multisig.n = 1;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
+        let mut account = Account::unpack_unchecked(&account_account.data).unwrap();
+        account.state = AccountState::Initialized;
+        Account::pack(account, &mut account_account.data).unwrap();
+        let mut account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        account.state = AccountState::Frozen;
+        Account::pack(account, &mut account2_account.data).unwrap();
         assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers)
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &owner_key,
+                    &[],
+                    500,
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                ],
+            )
         );
 
-        // 0:11
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 0;
-            multisig.n = 11;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
-        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers).unwrap();
-
-        // 2:11 but 0 provided
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 2;
-            multisig.n = 11;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
+        // no approve if account is frozen
+        let mut account = Account::unpack_unchecked(&account_account.data).unwrap();
+        account.state = AccountState::Frozen;
+        Account::pack(account, &mut account_account.data).unwrap();
+        let delegate_key = Pubkey::new_unique();
+        let mut delegate_account = SolanaAccount::default();
         assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &[])
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                approve(
+                    &program_id,
+                    &account_key,
+                    &delegate_key,
+                    &owner_key,
+                    &[],
+                    100
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut delegate_account,
+                    &mut owner_account,
+                ],
+            )
         );
-        // 2:11 but 1 provided
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 2;
-            multisig.n = 11;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
+
+        // no revoke if account is frozen
+        let mut account = Account::unpack_unchecked(&account_account.data).unwrap();
+        account.delegate = COption::Some(delegate_key);
+        account.delegated_amount = 100;
+        Account::pack(account, &mut account_account.data).unwrap();
         assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers[0..1])
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                revoke(&program_id, &account_key, &owner_key, &[]).unwrap(),
+                vec![&mut account_account, &mut owner_account],
+            )
         );
 
-        // 2:11, 2 from middle provided
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 2;
-            multisig.n = 11;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
-        Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers[5..7])
-            .unwrap();
+        // no set authority if account is frozen
+        let new_owner_key = Pubkey::new_unique();
+        assert_eq!(
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &account_key,
+                    Some(&new_owner_key),
+                    AuthorityType::AccountOwner,
+                    &owner_key,
+                    &[]
+                )
+                .unwrap(),
+                vec![&mut account_account, &mut owner_account,],
+            )
+        );
 
-        // 11:11, one is not a signer
-        {
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 11;
-            multisig.n = 11;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-        }
-        signers[5].is_signer = false;
+        // no mint_to if destination account is frozen
         assert_eq!(
-            Err(ProgramError::MissingRequiredSignature),
-            Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers)
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 100).unwrap(),
+                vec![&mut mint_account, &mut account_account, &mut owner_account,],
+            )
         );
-        signers[5].is_signer = true;
 
-        // 11:11, single signer signs multiple times
-        {
-            let mut signer_lamports = 0;
-            let mut signer_data = vec![];
-            let signers = vec![
-                AccountInfo::new(
-                    &signer_keys[5],
-                    true,
-                    false,
-                    &mut signer_lamports,
-                    &mut signer_data,
-                    &program_id,
-                    false,
-                    Epoch::default(),
-                );
-                MAX_SIGNERS + 1
-            ];
-            let mut multisig =
-                Multisig::unpack_unchecked(&owner_account_info.data.borrow()).unwrap();
-            multisig.m = 11;
-            multisig.n = 11;
-            Multisig::pack(multisig, &mut owner_account_info.data.borrow_mut()).unwrap();
-            assert_eq!(
-                Err(ProgramError::MissingRequiredSignature),
-                Processor::validate_owner(&program_id, &owner_key, &owner_account_info, &signers)
-            );
-        }
+        // no burn if account is frozen
+        assert_eq!(
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                burn(&program_id, &account_key, &mint_key, &owner_key, &[], 100).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner_account],
+            )
+        );
     }
 
     #[test]
-    fn test_close_account_dups() {
+    fn test_freeze_thaw_dups() {
         let program_id = crate::id();
         let account1_key = Pubkey::new_unique();
         let mut account1_account = SolanaAccount::new(
@@ -4914,25 +6711,18 @@ mod tests {
             &program_id,
         );
         let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account2_info: AccountInfo = (&account2_key, true, &mut account2_account).into();
         let owner_key = Pubkey::new_unique();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint_info: AccountInfo = (&mint_key, false, &mut mint_account).into();
+        let mint_info: AccountInfo = (&mint_key, true, &mut mint_account).into();
         let rent_key = rent::id();
         let mut rent_sysvar = rent_sysvar();
         let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
 
         // create mint
         do_process_instruction_dups(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            initialize_mint(&program_id, &mint_key, &owner_key, Some(&account1_key), 2).unwrap(),
             vec![mint_info.clone(), rent_info.clone()],
         )
         .unwrap();
@@ -4949,41 +6739,26 @@ mod tests {
         )
         .unwrap();
 
-        // source-owner close
+        // freeze where mint freeze_authority is account
         do_process_instruction_dups(
-            close_account(
-                &program_id,
-                &account1_key,
-                &account2_key,
-                &account1_key,
-                &[],
-            )
-            .unwrap(),
+            freeze_account(&program_id, &account1_key, &mint_key, &account1_key, &[]).unwrap(),
             vec![
                 account1_info.clone(),
-                account2_info.clone(),
+                mint_info.clone(),
                 account1_info.clone(),
             ],
         )
         .unwrap();
 
-        // source-close-authority close
+        // thaw where mint freeze_authority is account
         let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.close_authority = COption::Some(account1_key);
-        account.owner = owner_key;
+        account.state = AccountState::Frozen;
         Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
         do_process_instruction_dups(
-            close_account(
-                &program_id,
-                &account1_key,
-                &account2_key,
-                &account1_key,
-                &[],
-            )
-            .unwrap(),
+            thaw_account(&program_id, &account1_key, &mint_key, &account1_key, &[]).unwrap(),
             vec![
                 account1_info.clone(),
-                account2_info.clone(),
+                mint_info.clone(),
                 account1_info.clone(),
             ],
         )
@@ -4991,24 +6766,132 @@ mod tests {
     }
 
     #[test]
-    fn test_close_account() {
+    fn test_freeze_account() {
+        let program_id = crate::id();
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account_owner_key = Pubkey::new_unique();
+        let mut account_owner_account = SolanaAccount::default();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mut rent_sysvar = rent_sysvar();
+
+        // create new mint with owner different from account owner
+        do_process_instruction(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        // create account
+        do_process_instruction(
+            initialize_account(&program_id, &account_key, &mint_key, &account_owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut account_owner_account,
+                &mut rent_sysvar,
+            ],
+        )
+        .unwrap();
+
+        // mint to account
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+
+        // mint cannot freeze
+        assert_eq!(
+            Err(TokenError::MintCannotFreeze.into()),
+            do_process_instruction(
+                freeze_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner_account],
+            )
+        );
+
+        // missing freeze_authority
+        let mut mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
+        mint.freeze_authority = COption::Some(owner_key);
+        Mint::pack(mint, &mut mint_account.data).unwrap();
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                freeze_account(&program_id, &account_key, &mint_key, &owner2_key, &[]).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+            )
+        );
+
+        // check explicit thaw
+        assert_eq!(
+            Err(TokenError::InvalidState.into()),
+            do_process_instruction(
+                thaw_account(&program_id, &account_key, &mint_key, &owner2_key, &[]).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+            )
+        );
+
+        // freeze
+        do_process_instruction(
+            freeze_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
+        )
+        .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.state, AccountState::Frozen);
+
+        // check explicit freeze
+        assert_eq!(
+            Err(TokenError::InvalidState.into()),
+            do_process_instruction(
+                freeze_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner_account],
+            )
+        );
+
+        // check thaw authority
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                thaw_account(&program_id, &account_key, &mint_key, &owner2_key, &[]).unwrap(),
+                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+            )
+        );
+
+        // thaw
+        do_process_instruction(
+            thaw_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
+        )
+        .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.state, AccountState::Initialized);
+    }
+
+    #[test]
+    fn test_initialize_account2_and_3() {
         let program_id = crate::id();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let account2_key = Pubkey::new_unique();
         let mut account2_account = SolanaAccount::new(
-            account_minimum_balance() + 42,
+            account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let account3_key = Pubkey::new_unique();
         let mut account3_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
@@ -5016,29 +6899,18 @@ mod tests {
         );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
-        let owner2_key = Pubkey::new_unique();
-        let mut owner2_account = SolanaAccount::default();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         let mut rent_sysvar = rent_sysvar();
 
-        // uninitialized
-        assert_eq!(
-            Err(ProgramError::UninitializedAccount),
-            do_process_instruction(
-                close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account3_account,
-                    &mut owner2_account,
-                ],
-            )
-        );
-
-        // initialize and mint to non-native account
+        // create mint
         do_process_instruction(
             initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
             vec![&mut mint_account, &mut rent_sysvar],
         )
         .unwrap();
+
         do_process_instruction(
             initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
             vec![
@@ -5049,635 +6921,911 @@ mod tests {
             ],
         )
         .unwrap();
+
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 42).unwrap(),
+            initialize_account2(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account2_account, &mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        assert_eq!(account_account, account2_account);
+
+        do_process_instruction(
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account3_account, &mut mint_account],
+        )
+        .unwrap();
+
+        assert_eq!(account_account, account3_account);
+    }
+
+    #[test]
+    fn test_sync_native() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let native_account_key = Pubkey::new_unique();
+        let lamports = 40;
+        let mut native_account = SolanaAccount::new(
+            account_minimum_balance() + lamports,
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let non_native_account_key = Pubkey::new_unique();
+        let mut non_native_account = SolanaAccount::new(
+            account_minimum_balance() + 50,
+            Account::get_packed_len(),
+            &program_id,
+        );
+
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let mut rent_sysvar = rent_sysvar();
+
+        // initialize non-native mint
+        do_process_instruction(
+            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar],
+        )
+        .unwrap();
+
+        // initialize non-native account
+        do_process_instruction(
+            initialize_account(&program_id, &non_native_account_key, &mint_key, &owner_key)
+                .unwrap(),
             vec![
+                &mut non_native_account,
                 &mut mint_account,
-                &mut account_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 42);
 
-        // initialize native account
+        let account = Account::unpack_unchecked(&non_native_account.data).unwrap();
+        assert!(!account.is_native());
+        assert_eq!(account.amount, 0);
+
+        // fail sync non-native
+        assert_eq!(
+            Err(TokenError::NonNativeNotSupported.into()),
+            do_process_instruction(
+                sync_native(&program_id, &non_native_account_key,).unwrap(),
+                vec![&mut non_native_account],
+            )
+        );
+
+        // fail sync uninitialized
+        assert_eq!(
+            Err(ProgramError::UninitializedAccount),
+            do_process_instruction(
+                sync_native(&program_id, &native_account_key,).unwrap(),
+                vec![&mut native_account],
+            )
+        );
+
+        // wrap native account
         do_process_instruction(
             initialize_account(
                 &program_id,
-                &account2_key,
+                &native_account_key,
                 &crate::native_mint::id(),
                 &owner_key,
             )
             .unwrap(),
             vec![
-                &mut account2_account,
+                &mut native_account,
                 &mut mint_account,
                 &mut owner_account,
                 &mut rent_sysvar,
             ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
+        let account = Account::unpack_unchecked(&native_account.data).unwrap();
         assert!(account.is_native());
-        assert_eq!(account.amount, 42);
+        assert_eq!(account.amount, lamports);
 
-        // close non-native account with balance
-        assert_eq!(
-            Err(TokenError::NonNativeHasBalance.into()),
-            do_process_instruction(
-                close_account(&program_id, &account_key, &account3_key, &owner_key, &[]).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account3_account,
-                    &mut owner_account,
-                ],
-            )
-        );
-        assert_eq!(account_account.lamports, account_minimum_balance());
+        // sync, no change
+        do_process_instruction(
+            sync_native(&program_id, &native_account_key).unwrap(),
+            vec![&mut native_account],
+        )
+        .unwrap();
+        let account = Account::unpack_unchecked(&native_account.data).unwrap();
+        assert_eq!(account.amount, lamports);
 
-        // empty account
+        // transfer sol
+        let new_lamports = lamports + 50;
+        native_account.lamports = account_minimum_balance() + new_lamports;
+
+        // success sync
         do_process_instruction(
-            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 42).unwrap(),
-            vec![&mut account_account, &mut mint_account, &mut owner_account],
+            sync_native(&program_id, &native_account_key).unwrap(),
+            vec![&mut native_account],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&native_account.data).unwrap();
+        assert_eq!(account.amount, new_lamports);
 
-        // wrong owner
+        // reduce sol
+        native_account.lamports -= 1;
+
+        // fail sync
         assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
+            Err(TokenError::InvalidState.into()),
             do_process_instruction(
-                close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account3_account,
-                    &mut owner2_account,
-                ],
+                sync_native(&program_id, &native_account_key,).unwrap(),
+                vec![&mut native_account],
             )
         );
+    }
 
-        // close account
-        do_process_instruction(
-            close_account(&program_id, &account_key, &account3_key, &owner_key, &[]).unwrap(),
-            vec![
-                &mut account_account,
-                &mut account3_account,
-                &mut owner_account,
-            ],
-        )
-        .unwrap();
-        assert_eq!(account_account.lamports, 0);
-        assert_eq!(account3_account.lamports, 2 * account_minimum_balance());
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 0);
-
-        // fund and initialize new non-native account to test close authority
-        let account_key = Pubkey::new_unique();
+    #[test]
+    fn test_transfer_fee() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mint_len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]);
+        let mut mint_account = SolanaAccount::new(
+            Rent::default().minimum_balance(mint_len),
+            mint_len,
+            &program_id,
+        );
+        let account_len =
+            ExtensionType::get_account_len::<Account>(&[ExtensionType::TransferFeeAmount]);
         let mut account_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
+            Rent::default().minimum_balance(account_len),
+            account_len,
             &program_id,
         );
-        let owner2_key = Pubkey::new_unique();
-        let mut owner2_account = SolanaAccount::new(
+        let account_key = Pubkey::new_unique();
+        let mut account2_account = account_account.clone();
+        let account2_key = Pubkey::new_unique();
+        let mut legacy_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
+        let legacy_key = Pubkey::new_unique();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let dest_key = Pubkey::new_unique();
+        let mut dest_account = SolanaAccount::default();
+        let fee_authority_key = Pubkey::new_unique();
+        let mut fee_authority_account = SolanaAccount::default();
+        let withdraw_authority_key = Pubkey::new_unique();
+        let mut withdraw_authority_account = SolanaAccount::default();
+
+        // fee above 100% is rejected
+        assert_eq!(
+            Err(TokenError::TransferFeeExceedsMaximum.into()),
+            do_process_instruction(
+                initialize_transfer_fee_config(
+                    &program_id,
+                    &mint_key,
+                    Some(&fee_authority_key),
+                    Some(&withdraw_authority_key),
+                    MAX_FEE_BASIS_POINTS + 1,
+                    u64::MAX,
+                )
+                .unwrap(),
+                vec![&mut mint_account],
+            )
+        );
+
+        // 1% fee, capped at 5 tokens
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
+            initialize_transfer_fee_config(
+                &program_id,
+                &mint_key,
+                Some(&fee_authority_key),
+                Some(&withdraw_authority_key),
+                100,
+                5,
+            )
+            .unwrap(),
+            vec![&mut mint_account],
         )
         .unwrap();
-        account_account.lamports = 2;
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+
+        // config must precede mint initialization
+        assert_eq!(
+            Err(TokenError::AlreadyInUse.into()),
+            do_process_instruction(
+                initialize_transfer_fee_config(&program_id, &mint_key, None, None, 1, 1).unwrap(),
+                vec![&mut mint_account],
+            )
+        );
 
+        // accounts without room for the withheld amount are rejected
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            do_process_instruction(
+                initialize_account3(&program_id, &legacy_key, &mint_key, &owner_key).unwrap(),
+                vec![&mut legacy_account, &mut mint_account],
+            )
+        );
         do_process_instruction(
-            set_authority(
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account2_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            mint_to(
                 &program_id,
+                &mint_key,
                 &account_key,
-                Some(&owner2_key),
-                AuthorityType::CloseAccount,
                 &owner_key,
                 &[],
+                10_000,
             )
             .unwrap(),
-            vec![&mut account_account, &mut owner_account],
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        // account owner cannot authorize close if close_authority is set
+        // fees can only be calculated with the mint
         assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
+            Err(TokenError::MintRequiredForTransfer.into()),
             do_process_instruction(
-                close_account(&program_id, &account_key, &account3_key, &owner_key, &[]).unwrap(),
+                transfer(
+                    &program_id,
+                    &account_key,
+                    &account2_key,
+                    &owner_key,
+                    &[],
+                    100
+                )
+                .unwrap(),
                 vec![
                     &mut account_account,
-                    &mut account3_account,
+                    &mut account2_account,
                     &mut owner_account,
                 ],
             )
         );
 
-        // close non-native account with close_authority
+        // fee rounds up
         do_process_instruction(
-            close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
+            transfer_checked(
+                &program_id,
+                &account_key,
+                &mint_key,
+                &account2_key,
+                &owner_key,
+                &[],
+                101,
+                2,
+            )
+            .unwrap(),
             vec![
                 &mut account_account,
-                &mut account3_account,
-                &mut owner2_account,
-            ],
-        )
-        .unwrap();
-        assert_eq!(account_account.lamports, 0);
-        assert_eq!(account3_account.lamports, 2 * account_minimum_balance() + 2);
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, 0);
-
-        // close native account
-        do_process_instruction(
-            close_account(&program_id, &account2_key, &account3_key, &owner_key, &[]).unwrap(),
-            vec![
+                &mut mint_account,
                 &mut account2_account,
-                &mut account3_account,
                 &mut owner_account,
             ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account_account.lamports, 0);
-        assert_eq!(account.amount, 0);
-        assert_eq!(
-            account3_account.lamports,
-            3 * account_minimum_balance() + 2 + 42
-        );
-    }
-
-    #[test]
-    fn test_native_token() {
-        let program_id = crate::id();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(
-            account_minimum_balance() + 40,
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account3_key = Pubkey::new_unique();
-        let mut account3_account = SolanaAccount::new(account_minimum_balance(), 0, &program_id);
-        let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let owner2_key = Pubkey::new_unique();
-        let mut owner2_account = SolanaAccount::default();
-        let owner3_key = Pubkey::new_unique();
-        let mut rent_sysvar = rent_sysvar();
-
-        // initialize native account
+        // fee is capped
         do_process_instruction(
-            initialize_account(
+            transfer_checked(
                 &program_id,
                 &account_key,
-                &crate::native_mint::id(),
+                &mint_key,
+                &account2_key,
                 &owner_key,
+                &[],
+                1_000,
+                2,
             )
             .unwrap(),
             vec![
                 &mut account_account,
                 &mut mint_account,
+                &mut account2_account,
                 &mut owner_account,
-                &mut rent_sysvar,
             ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account.amount, 40);
 
-        // initialize native account
+        let account = StateWithExtensions::<Account>::unpack(&account_account.data).unwrap();
+        assert_eq!(account.base.amount, 10_000 - 1_101);
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(account2.base.amount, 1_101 - 2 - 5);
+        assert_eq!(
+            account2
+                .get_extension::<TransferFeeAmount>()
+                .unwrap()
+                .withheld_amount,
+            7
+        );
+
+        // withheld fees must be withdrawn before closing
         do_process_instruction(
-            initialize_account(
+            burn(
                 &program_id,
                 &account2_key,
-                &crate::native_mint::id(),
+                &mint_key,
                 &owner_key,
+                &[],
+                1_094,
             )
             .unwrap(),
-            vec![
-                &mut account2_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
+            vec![&mut account2_account, &mut mint_account, &mut owner_account],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account.amount, 0);
+        assert_eq!(
+            Err(TokenError::AccountHasWithheldTransferFees.into()),
+            do_process_instruction(
+                close_account(&program_id, &account2_key, &dest_key, &owner_key, &[]).unwrap(),
+                vec![&mut account2_account, &mut dest_account, &mut owner_account,],
+            )
+        );
 
-        // mint_to unsupported
+        // only the withdraw authority can harvest
         assert_eq!(
-            Err(TokenError::NativeNotSupported.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                mint_to(
+                withdraw_withheld_tokens_from_accounts(
                     &program_id,
-                    &crate::native_mint::id(),
+                    &mint_key,
                     &account_key,
-                    &owner_key,
+                    &fee_authority_key,
                     &[],
-                    42
+                    &[&account2_key],
                 )
                 .unwrap(),
-                vec![&mut mint_account, &mut account_account, &mut owner_account],
+                vec![
+                    &mut mint_account,
+                    &mut account_account,
+                    &mut fee_authority_account,
+                    &mut account2_account,
+                ],
             )
         );
-
-        // burn unsupported
-        let bogus_mint_key = Pubkey::new_unique();
-        let mut bogus_mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
         do_process_instruction(
-            initialize_mint(&program_id, &bogus_mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut bogus_mint_account, &mut rent_sysvar],
+            withdraw_withheld_tokens_from_accounts(
+                &program_id,
+                &mint_key,
+                &account_key,
+                &withdraw_authority_key,
+                &[],
+                &[&account2_key],
+            )
+            .unwrap(),
+            vec![
+                &mut mint_account,
+                &mut account_account,
+                &mut withdraw_authority_account,
+                &mut account2_account,
+            ],
+        )
+        .unwrap();
+        let account = StateWithExtensions::<Account>::unpack(&account_account.data).unwrap();
+        assert_eq!(account.base.amount, 10_000 - 1_101 + 7);
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(
+            account2
+                .get_extension::<TransferFeeAmount>()
+                .unwrap()
+                .withheld_amount,
+            0
+        );
+        do_process_instruction(
+            close_account(&program_id, &account2_key, &dest_key, &owner_key, &[]).unwrap(),
+            vec![&mut account2_account, &mut dest_account, &mut owner_account],
         )
         .unwrap();
 
+        // update the fee
         assert_eq!(
-            Err(TokenError::NativeNotSupported.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                burn(
+                set_transfer_fee(&program_id, &mint_key, &owner_key, &[], 10, 10).unwrap(),
+                vec![&mut mint_account, &mut owner_account],
+            )
+        );
+        assert_eq!(
+            Err(TokenError::TransferFeeExceedsMaximum.into()),
+            do_process_instruction(
+                set_transfer_fee(
                     &program_id,
-                    &account_key,
-                    &bogus_mint_key,
-                    &owner_key,
+                    &mint_key,
+                    &fee_authority_key,
                     &[],
-                    42
+                    MAX_FEE_BASIS_POINTS + 1,
+                    10
                 )
                 .unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut bogus_mint_account,
-                    &mut owner_account
-                ],
+                vec![&mut mint_account, &mut fee_authority_account],
             )
         );
+        do_process_instruction(
+            set_transfer_fee(&program_id, &mint_key, &fee_authority_key, &[], 10, 10).unwrap(),
+            vec![&mut mint_account, &mut fee_authority_account],
+        )
+        .unwrap();
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        let transfer_fee_config = mint.get_extension::<TransferFeeConfig>().unwrap();
+        assert_eq!(transfer_fee_config.transfer_fee_basis_points, 10);
+        assert_eq!(transfer_fee_config.maximum_fee, 10);
 
-        // ensure can't transfer below rent-exempt reserve
+        // remove the fee authority, fixing the fee
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &mint_key,
+                None,
+                AuthorityType::TransferFeeConfig,
+                &fee_authority_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut fee_authority_account],
+        )
+        .unwrap();
         assert_eq!(
-            Err(TokenError::InsufficientFunds.into()),
+            Err(TokenError::NoAuthorityExists.into()),
             do_process_instruction(
-                transfer(
-                    &program_id,
-                    &account_key,
-                    &account2_key,
-                    &owner_key,
-                    &[],
-                    50,
-                )
-                .unwrap(),
-                vec![
-                    &mut account_account,
-                    &mut account2_account,
-                    &mut owner_account,
-                ],
+                set_transfer_fee(&program_id, &mint_key, &fee_authority_key, &[], 0, 0).unwrap(),
+                vec![&mut mint_account, &mut fee_authority_account],
             )
         );
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        assert_eq!(mint.base.supply, 10_000 - 1_094);
+        assert_eq!(
+            mint.get_extension::<TransferFeeConfig>()
+                .unwrap()
+                .transfer_fee_config_authority,
+            COption::None
+        );
+    }
 
-        // transfer between native accounts
+    #[test]
+    fn test_harvest_withheld_tokens_to_mint() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mint_len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]);
+        let mut mint_account = SolanaAccount::new(
+            Rent::default().minimum_balance(mint_len),
+            mint_len,
+            &program_id,
+        );
+        let account_len =
+            ExtensionType::get_account_len::<Account>(&[ExtensionType::TransferFeeAmount]);
+        let mut account_account = SolanaAccount::new(
+            Rent::default().minimum_balance(account_len),
+            account_len,
+            &program_id,
+        );
+        let account_key = Pubkey::new_unique();
+        let mut account2_account = account_account.clone();
+        let account2_key = Pubkey::new_unique();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let dest_key = Pubkey::new_unique();
+        let mut dest_account = SolanaAccount::default();
+        let withdraw_authority_key = Pubkey::new_unique();
+        let mut withdraw_authority_account = SolanaAccount::default();
+
+        // 1% fee, capped at 5 tokens
         do_process_instruction(
-            transfer(
+            initialize_transfer_fee_config(
+                &program_id,
+                &mint_key,
+                None,
+                Some(&withdraw_authority_key),
+                100,
+                5,
+            )
+            .unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account2_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            mint_to(
                 &program_id,
+                &mint_key,
                 &account_key,
-                &account2_key,
                 &owner_key,
                 &[],
-                40,
+                10_000,
             )
             .unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+        let transfer_instruction = transfer_checked(
+            &program_id,
+            &account_key,
+            &mint_key,
+            &account2_key,
+            &owner_key,
+            &[],
+            1_000,
+            2,
+        )
+        .unwrap();
+        do_process_instruction(
+            transfer_instruction.clone(),
             vec![
                 &mut account_account,
+                &mut mint_account,
                 &mut account2_account,
                 &mut owner_account,
             ],
         )
         .unwrap();
-        assert_eq!(account_account.lamports, account_minimum_balance());
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account.amount, 0);
-        assert_eq!(account2_account.lamports, account_minimum_balance() + 40);
-        let account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account.amount, 40);
 
-        // set close authority
+        // anyone can harvest into the mint
         do_process_instruction(
-            set_authority(
-                &program_id,
-                &account_key,
-                Some(&owner3_key),
-                AuthorityType::CloseAccount,
-                &owner_key,
-                &[],
-            )
-            .unwrap(),
-            vec![&mut account_account, &mut owner_account],
+            harvest_withheld_tokens_to_mint(&program_id, &mint_key, &[&account2_key]).unwrap(),
+            vec![&mut mint_account, &mut account2_account],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.close_authority, COption::Some(owner3_key));
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(
+            account2
+                .get_extension::<TransferFeeAmount>()
+                .unwrap()
+                .withheld_amount,
+            0
+        );
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        assert_eq!(
+            mint.get_extension::<TransferFeeConfig>()
+                .unwrap()
+                .withheld_amount,
+            5
+        );
 
-        // set new account owner
+        // the mint can't harvest from itself
+        assert_eq!(
+            Err(ProgramError::InvalidArgument),
+            do_process_instruction(
+                harvest_withheld_tokens_to_mint(&program_id, &mint_key, &[&mint_key]).unwrap(),
+                vec![&mut mint_account.clone(), &mut mint_account],
+            )
+        );
+
+        // only the withdraw authority can withdraw from the mint
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                withdraw_withheld_tokens_from_mint(
+                    &program_id,
+                    &mint_key,
+                    &account_key,
+                    &owner_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut mint_account, &mut account_account, &mut owner_account],
+            )
+        );
         do_process_instruction(
-            set_authority(
+            withdraw_withheld_tokens_from_mint(
                 &program_id,
+                &mint_key,
                 &account_key,
-                Some(&owner2_key),
-                AuthorityType::AccountOwner,
-                &owner_key,
+                &withdraw_authority_key,
                 &[],
             )
             .unwrap(),
-            vec![&mut account_account, &mut owner_account],
-        )
-        .unwrap();
-
-        // close authority cleared
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.close_authority, COption::None);
-
-        // close native account
-        do_process_instruction(
-            close_account(&program_id, &account_key, &account3_key, &owner2_key, &[]).unwrap(),
             vec![
+                &mut mint_account,
                 &mut account_account,
-                &mut account3_account,
-                &mut owner2_account,
+                &mut withdraw_authority_account,
             ],
         )
         .unwrap();
-        assert_eq!(account_account.lamports, 0);
-        assert_eq!(account3_account.lamports, 2 * account_minimum_balance());
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account.amount, 0);
-    }
-
-    #[test]
-    fn test_overflow() {
-        let program_id = crate::id();
-        let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
+        let account = StateWithExtensions::<Account>::unpack(&account_account.data).unwrap();
+        assert_eq!(account.base.amount, 10_000 - 1_000 + 5);
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        assert_eq!(
+            mint.get_extension::<TransferFeeConfig>()
+                .unwrap()
+                .withheld_amount,
+            0
         );
-        let owner_key = Pubkey::new_unique();
-        let mut owner_account = SolanaAccount::default();
-        let owner2_key = Pubkey::new_unique();
-        let mut owner2_account = SolanaAccount::default();
-        let mint_owner_key = Pubkey::new_unique();
-        let mut mint_owner_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mut rent_sysvar = rent_sysvar();
 
-        // create new mint with owner
+        // without a withdraw authority, withheld fees can't be withdrawn
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &mint_owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            set_authority(
+                &program_id,
+                &mint_key,
+                None,
+                AuthorityType::WithheldWithdraw,
+                &withdraw_authority_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut withdraw_authority_account],
         )
         .unwrap();
-
-        // create an account
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            transfer_instruction,
             vec![
                 &mut account_account,
                 &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
-        )
-        .unwrap();
-
-        // create another account
-        do_process_instruction(
-            initialize_account(&program_id, &account2_key, &mint_key, &owner2_key).unwrap(),
-            vec![
                 &mut account2_account,
-                &mut mint_account,
-                &mut owner2_account,
-                &mut rent_sysvar,
+                &mut owner_account,
             ],
         )
         .unwrap();
-
-        // mint the max to an account
         do_process_instruction(
-            mint_to(
+            burn(
                 &program_id,
+                &account2_key,
                 &mint_key,
-                &account_key,
-                &mint_owner_key,
+                &owner_key,
                 &[],
-                u64::MAX,
+                2 * (1_000 - 5),
             )
             .unwrap(),
-            vec![
-                &mut mint_account,
-                &mut account_account,
-                &mut mint_owner_account,
-            ],
+            vec![&mut account2_account, &mut mint_account, &mut owner_account],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, u64::MAX);
-
-        // attempt to mint one more to account
         assert_eq!(
-            Err(TokenError::Overflow.into()),
+            Err(TokenError::NoAuthorityExists.into()),
             do_process_instruction(
-                mint_to(
+                withdraw_withheld_tokens_from_accounts(
                     &program_id,
                     &mint_key,
                     &account_key,
-                    &mint_owner_key,
+                    &withdraw_authority_key,
                     &[],
-                    1,
+                    &[&account2_key],
                 )
                 .unwrap(),
                 vec![
                     &mut mint_account,
                     &mut account_account,
-                    &mut mint_owner_account,
+                    &mut withdraw_authority_account,
+                    &mut account2_account,
                 ],
             )
         );
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, u64::MAX);
+        assert_eq!(
+            Err(TokenError::AccountHasWithheldTransferFees.into()),
+            do_process_instruction(
+                close_account(&program_id, &account2_key, &dest_key, &owner_key, &[]).unwrap(),
+                vec![&mut account2_account, &mut dest_account, &mut owner_account],
+            )
+        );
 
-        // atttempt to mint one more to the other account
+        // but harvesting them into the mint lets the account close
+        do_process_instruction(
+            harvest_withheld_tokens_to_mint(&program_id, &mint_key, &[&account2_key]).unwrap(),
+            vec![&mut mint_account, &mut account2_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            close_account(&program_id, &account2_key, &dest_key, &owner_key, &[]).unwrap(),
+            vec![&mut account2_account, &mut dest_account, &mut owner_account],
+        )
+        .unwrap();
         assert_eq!(
-            Err(TokenError::Overflow.into()),
+            dest_account.lamports,
+            Rent::default().minimum_balance(account_len)
+        );
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        assert_eq!(
+            mint.get_extension::<TransferFeeConfig>()
+                .unwrap()
+                .withheld_amount,
+            5
+        );
+        assert_eq!(
+            Err(TokenError::NoAuthorityExists.into()),
             do_process_instruction(
-                mint_to(
+                withdraw_withheld_tokens_from_mint(
                     &program_id,
                     &mint_key,
-                    &account2_key,
-                    &mint_owner_key,
+                    &account_key,
+                    &withdraw_authority_key,
                     &[],
-                    1,
                 )
                 .unwrap(),
                 vec![
                     &mut mint_account,
-                    &mut account2_account,
-                    &mut mint_owner_account,
+                    &mut account_account,
+                    &mut withdraw_authority_account,
                 ],
             )
         );
+    }
 
-        // burn some of the supply
-        do_process_instruction(
-            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 100).unwrap(),
-            vec![&mut account_account, &mut mint_account, &mut owner_account],
-        )
-        .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, u64::MAX - 100);
+    #[test]
+    fn test_interest_bearing_mint() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mint_len =
+            ExtensionType::get_account_len::<Mint>(&[ExtensionType::InterestBearingConfig]);
+        let mut mint_account = SolanaAccount::new(
+            Rent::default().minimum_balance(mint_len),
+            mint_len,
+            &program_id,
+        );
+        let mut legacy_mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let rate_authority_key = Pubkey::new_unique();
+        let mut rate_authority_account = SolanaAccount::default();
+
+        // legacy mints have no room for the config
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            do_process_instruction(
+                initialize_interest_bearing_mint(
+                    &program_id,
+                    &mint_key,
+                    Some(&rate_authority_key),
+                    500
+                )
+                .unwrap(),
+                vec![&mut legacy_mint_account],
+            )
+        );
 
         do_process_instruction(
-            mint_to(
+            initialize_interest_bearing_mint(
                 &program_id,
                 &mint_key,
-                &account_key,
-                &mint_owner_key,
-                &[],
-                100,
+                Some(&rate_authority_key),
+                500,
             )
             .unwrap(),
-            vec![
-                &mut mint_account,
-                &mut account_account,
-                &mut mint_owner_account,
-            ],
+            vec![&mut mint_account],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.amount, u64::MAX);
-
-        // manipulate account balance to attempt overflow transfer
-        let mut account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        account.amount = 1;
-        Account::pack(account, &mut account2_account.data).unwrap();
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        assert_eq!(
+            mint.get_extension::<InterestBearingConfig>().unwrap(),
+            InterestBearingConfig::new(COption::Some(rate_authority_key), 500, 0)
+        );
 
+        // only the rate authority can update the rate
         assert_eq!(
-            Err(TokenError::Overflow.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                transfer(
-                    &program_id,
-                    &account2_key,
-                    &account_key,
-                    &owner2_key,
-                    &[],
-                    1,
-                )
-                .unwrap(),
-                vec![
-                    &mut account2_account,
-                    &mut account_account,
-                    &mut owner2_account,
-                ],
+                update_interest_rate(&program_id, &mint_key, &owner_key, &[], 100).unwrap(),
+                vec![&mut mint_account, &mut owner_account],
+            )
+        );
+        do_process_instruction(
+            update_interest_rate(&program_id, &mint_key, &rate_authority_key, &[], 100).unwrap(),
+            vec![&mut mint_account, &mut rate_authority_account],
+        )
+        .unwrap();
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        let interest_bearing_config = mint.get_extension::<InterestBearingConfig>().unwrap();
+        assert_eq!(interest_bearing_config.current_rate, 100);
+        assert_eq!(interest_bearing_config.pre_update_average_rate, 100);
+
+        // disable rate updates
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &mint_key,
+                None,
+                AuthorityType::InterestRate,
+                &rate_authority_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut rate_authority_account],
+        )
+        .unwrap();
+        assert_eq!(
+            Err(TokenError::NoAuthorityExists.into()),
+            do_process_instruction(
+                update_interest_rate(&program_id, &mint_key, &rate_authority_key, &[], 0).unwrap(),
+                vec![&mut mint_account, &mut rate_authority_account],
             )
         );
     }
 
     #[test]
-    fn test_frozen() {
+    fn test_non_transferable_mint() {
         let program_id = crate::id();
-        let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
+        let mint_key = Pubkey::new_unique();
+        let mint_len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::NonTransferable]);
+        let mut mint_account = SolanaAccount::new(
+            Rent::default().minimum_balance(mint_len),
+            mint_len,
             &program_id,
         );
-        let account2_key = Pubkey::new_unique();
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
+        let account_len =
+            ExtensionType::get_account_len::<Account>(&[ExtensionType::NonTransferableAccount]);
+        let mut account_account = SolanaAccount::new(
+            Rent::default().minimum_balance(account_len),
+            account_len,
             &program_id,
         );
+        let account_key = Pubkey::new_unique();
+        let mut account2_account = account_account.clone();
+        let account2_key = Pubkey::new_unique();
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mut rent_sysvar = rent_sysvar();
+        let dest_key = Pubkey::new_unique();
+        let mut dest_account = SolanaAccount::default();
 
-        // create new mint and fund first account
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            initialize_non_transferable_mint(&program_id, &mint_key).unwrap(),
+            vec![&mut mint_account],
         )
         .unwrap();
-
-        // create account
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
         )
         .unwrap();
+        assert_eq!(
+            Err(TokenError::AlreadyInUse.into()),
+            do_process_instruction(
+                initialize_non_transferable_mint(&program_id, &mint_key).unwrap(),
+                vec![&mut mint_account],
+            )
+        );
 
-        // create another account
         do_process_instruction(
-            initialize_account(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
-            vec![
-                &mut account2_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account2_account, &mut mint_account],
         )
         .unwrap();
+        let account = StateWithExtensions::<Account>::unpack(&account_account.data).unwrap();
+        assert_eq!(
+            account.get_extension_types().unwrap(),
+            vec![ExtensionType::NonTransferableAccount]
+        );
 
-        // fund first account
+        // minting still works
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 100).unwrap(),
             vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        // no transfer if either account is frozen
-        let mut account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        account.state = AccountState::Frozen;
-        Account::pack(account, &mut account2_account.data).unwrap();
+        // transfers fail, with or without the mint
         assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
+            Err(TokenError::NonTransferable.into()),
             do_process_instruction(
                 transfer(
                     &program_id,
@@ -5685,7 +7833,7 @@ mod tests {
                     &account2_key,
                     &owner_key,
                     &[],
-                    500,
+                    10
                 )
                 .unwrap(),
                 vec![
@@ -5695,466 +7843,892 @@ mod tests {
                 ],
             )
         );
-
-        let mut account = Account::unpack_unchecked(&account_account.data).unwrap();
-        account.state = AccountState::Initialized;
-        Account::pack(account, &mut account_account.data).unwrap();
-        let mut account = Account::unpack_unchecked(&account2_account.data).unwrap();
-        account.state = AccountState::Frozen;
-        Account::pack(account, &mut account2_account.data).unwrap();
         assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
+            Err(TokenError::NonTransferable.into()),
             do_process_instruction(
-                transfer(
+                transfer_checked(
                     &program_id,
                     &account_key,
+                    &mint_key,
                     &account2_key,
                     &owner_key,
                     &[],
-                    500,
+                    10,
+                    2,
                 )
                 .unwrap(),
                 vec![
                     &mut account_account,
+                    &mut mint_account,
                     &mut account2_account,
                     &mut owner_account,
                 ],
             )
         );
 
-        // no approve if account is frozen
-        let mut account = Account::unpack_unchecked(&account_account.data).unwrap();
-        account.state = AccountState::Frozen;
-        Account::pack(account, &mut account_account.data).unwrap();
+        // burning and closing still work
+        do_process_instruction(
+            burn(&program_id, &account_key, &mint_key, &owner_key, &[], 100).unwrap(),
+            vec![&mut account_account, &mut mint_account, &mut owner_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            close_account(&program_id, &account_key, &dest_key, &owner_key, &[]).unwrap(),
+            vec![&mut account_account, &mut dest_account, &mut owner_account],
+        )
+        .unwrap();
+        assert_eq!(
+            dest_account.lamports,
+            Rent::default().minimum_balance(account_len)
+        );
+    }
+
+    #[test]
+    fn test_permanent_delegate() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mint_len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::PermanentDelegate]);
+        let mut mint_account = SolanaAccount::new(
+            Rent::default().minimum_balance(mint_len),
+            mint_len,
+            &program_id,
+        );
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = account_account.clone();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
         let delegate_key = Pubkey::new_unique();
         let mut delegate_account = SolanaAccount::default();
+        let new_delegate_key = Pubkey::new_unique();
+        let mut new_delegate_account = SolanaAccount::default();
+
+        do_process_instruction(
+            initialize_permanent_delegate(&program_id, &mint_key, &delegate_key).unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account2_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1_000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
+        )
+        .unwrap();
+
+        // the permanent delegate must provide the mint
         assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                approve(
+                transfer(
                     &program_id,
                     &account_key,
+                    &account2_key,
                     &delegate_key,
-                    &owner_key,
                     &[],
                     100
                 )
                 .unwrap(),
                 vec![
                     &mut account_account,
+                    &mut account2_account,
                     &mut delegate_account,
-                    &mut owner_account,
                 ],
             )
         );
-
-        // no revoke if account is frozen
-        let mut account = Account::unpack_unchecked(&account_account.data).unwrap();
-        account.delegate = COption::Some(delegate_key);
-        account.delegated_amount = 100;
-        Account::pack(account, &mut account_account.data).unwrap();
-        assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
-            do_process_instruction(
-                revoke(&program_id, &account_key, &owner_key, &[]).unwrap(),
-                vec![&mut account_account, &mut owner_account],
+        do_process_instruction(
+            transfer_checked(
+                &program_id,
+                &account_key,
+                &mint_key,
+                &account2_key,
+                &delegate_key,
+                &[],
+                100,
+                2,
             )
-        );
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut account2_account,
+                &mut delegate_account,
+            ],
+        )
+        .unwrap();
+        do_process_instruction(
+            burn(
+                &program_id,
+                &account_key,
+                &mint_key,
+                &delegate_key,
+                &[],
+                100,
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut delegate_account,
+            ],
+        )
+        .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.amount, 800);
+        assert_eq!(account.delegate, COption::None);
+        let account2 = Account::unpack_unchecked(&account2_account.data).unwrap();
+        assert_eq!(account2.amount, 100);
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        assert_eq!(mint.base.supply, 900);
 
-        // no set authority if account is frozen
-        let new_owner_key = Pubkey::new_unique();
+        // only the permanent delegate can reassign itself
         assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
+            Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
                 set_authority(
                     &program_id,
-                    &account_key,
-                    Some(&new_owner_key),
-                    AuthorityType::AccountOwner,
+                    &mint_key,
+                    Some(&new_delegate_key),
+                    AuthorityType::PermanentDelegate,
                     &owner_key,
-                    &[]
+                    &[],
                 )
                 .unwrap(),
-                vec![&mut account_account, &mut owner_account,],
-            )
-        );
-
-        // no mint_to if destination account is frozen
-        assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
-            do_process_instruction(
-                mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 100).unwrap(),
-                vec![&mut mint_account, &mut account_account, &mut owner_account,],
-            )
-        );
-
-        // no burn if account is frozen
-        assert_eq!(
-            Err(TokenError::AccountFrozen.into()),
-            do_process_instruction(
-                burn(&program_id, &account_key, &mint_key, &owner_key, &[], 100).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner_account],
+                vec![&mut mint_account, &mut owner_account],
             )
         );
-    }
-
-    #[test]
-    fn test_freeze_thaw_dups() {
-        let program_id = crate::id();
-        let account1_key = Pubkey::new_unique();
-        let mut account1_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let account1_info: AccountInfo = (&account1_key, true, &mut account1_account).into();
-        let owner_key = Pubkey::new_unique();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mint_info: AccountInfo = (&mint_key, true, &mut mint_account).into();
-        let rent_key = rent::id();
-        let mut rent_sysvar = rent_sysvar();
-        let rent_info: AccountInfo = (&rent_key, false, &mut rent_sysvar).into();
-
-        // create mint
-        do_process_instruction_dups(
-            initialize_mint(&program_id, &mint_key, &owner_key, Some(&account1_key), 2).unwrap(),
-            vec![mint_info.clone(), rent_info.clone()],
-        )
-        .unwrap();
-
-        // create account
-        do_process_instruction_dups(
-            initialize_account(&program_id, &account1_key, &mint_key, &account1_key).unwrap(),
-            vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account1_info.clone(),
-                rent_info.clone(),
-            ],
+        do_process_instruction(
+            set_authority(
+                &program_id,
+                &mint_key,
+                Some(&new_delegate_key),
+                AuthorityType::PermanentDelegate,
+                &delegate_key,
+                &[],
+            )
+            .unwrap(),
+            vec![&mut mint_account, &mut delegate_account],
         )
         .unwrap();
-
-        // freeze where mint freeze_authority is account
-        do_process_instruction_dups(
-            freeze_account(&program_id, &account1_key, &mint_key, &account1_key, &[]).unwrap(),
+        assert_eq!(
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction(
+                burn(
+                    &program_id,
+                    &account_key,
+                    &mint_key,
+                    &delegate_key,
+                    &[],
+                    100
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut delegate_account
+                ],
+            )
+        );
+        do_process_instruction(
+            burn_checked(
+                &program_id,
+                &account_key,
+                &mint_key,
+                &new_delegate_key,
+                &[],
+                100,
+                2,
+            )
+            .unwrap(),
             vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account1_info.clone(),
+                &mut account_account,
+                &mut mint_account,
+                &mut new_delegate_account,
             ],
         )
         .unwrap();
 
-        // thaw where mint freeze_authority is account
-        let mut account = Account::unpack_unchecked(&account1_info.data.borrow()).unwrap();
-        account.state = AccountState::Frozen;
-        Account::pack(account, &mut account1_info.data.borrow_mut()).unwrap();
-        do_process_instruction_dups(
-            thaw_account(&program_id, &account1_key, &mint_key, &account1_key, &[]).unwrap(),
-            vec![
-                account1_info.clone(),
-                mint_info.clone(),
-                account1_info.clone(),
-            ],
+        // mints without the extension don't support the authority
+        let mut legacy_mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        do_process_instruction(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut legacy_mint_account],
         )
         .unwrap();
+        assert_eq!(
+            Err(TokenError::ExtensionNotFound.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &mint_key,
+                    None,
+                    AuthorityType::PermanentDelegate,
+                    &owner_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut legacy_mint_account, &mut owner_account],
+            )
+        );
     }
 
     #[test]
-    fn test_freeze_account() {
+    fn test_required_transfer_memos() {
         let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let account_len = ExtensionType::get_account_len::<Account>(&[ExtensionType::MemoTransfer]);
         let account_key = Pubkey::new_unique();
         let mut account_account = SolanaAccount::new(
+            Rent::default().minimum_balance(account_len),
+            account_len,
+            &program_id,
+        );
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = account_account.clone();
+        let legacy_key = Pubkey::new_unique();
+        let mut legacy_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-        let account_owner_key = Pubkey::new_unique();
-        let mut account_owner_account = SolanaAccount::default();
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
         let owner2_key = Pubkey::new_unique();
         let mut owner2_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mut rent_sysvar = rent_sysvar();
 
-        // create new mint with owner different from account owner
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
         )
         .unwrap();
-
-        // create account
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &account_owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                &mut mint_account,
-                &mut account_owner_account,
-                &mut rent_sysvar,
-            ],
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
         )
         .unwrap();
-
-        // mint to account
         do_process_instruction(
-            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1000).unwrap(),
+            initialize_account3(&program_id, &account2_key, &mint_key, &owner2_key).unwrap(),
+            vec![&mut account2_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account3(&program_id, &legacy_key, &mint_key, &owner2_key).unwrap(),
+            vec![&mut legacy_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1_000).unwrap(),
             vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        // mint cannot freeze
+        // legacy accounts have no room for the extension
         assert_eq!(
-            Err(TokenError::MintCannotFreeze.into()),
+            Err(ProgramError::InvalidAccountData),
             do_process_instruction(
-                freeze_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner_account],
+                enable_required_transfer_memos(&program_id, &legacy_key, &owner2_key, &[]).unwrap(),
+                vec![&mut legacy_account, &mut owner2_account],
             )
         );
 
-        // missing freeze_authority
-        let mut mint = Mint::unpack_unchecked(&mint_account.data).unwrap();
-        mint.freeze_authority = COption::Some(owner_key);
-        Mint::pack(mint, &mut mint_account.data).unwrap();
+        // only the owner can require memos
         assert_eq!(
             Err(TokenError::OwnerMismatch.into()),
             do_process_instruction(
-                freeze_account(&program_id, &account_key, &mint_key, &owner2_key, &[]).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+                enable_required_transfer_memos(&program_id, &account2_key, &owner_key, &[])
+                    .unwrap(),
+                vec![&mut account2_account, &mut owner_account],
             )
         );
+        do_process_instruction(
+            enable_required_transfer_memos(&program_id, &account2_key, &owner2_key, &[]).unwrap(),
+            vec![&mut account2_account, &mut owner2_account],
+        )
+        .unwrap();
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(
+            account2.get_extension::<MemoTransfer>().unwrap(),
+            MemoTransfer {
+                require_incoming_transfer_memos: true
+            }
+        );
 
-        // check explicit thaw
+        // transfers without the instructions sysvar fail
+        let transfer_instruction = transfer_checked(
+            &program_id,
+            &account_key,
+            &mint_key,
+            &account2_key,
+            &owner_key,
+            &[],
+            100,
+            2,
+        )
+        .unwrap();
         assert_eq!(
-            Err(TokenError::InvalidState.into()),
+            Err(TokenError::NoMemo.into()),
             do_process_instruction(
-                thaw_account(&program_id, &account_key, &mint_key, &owner2_key, &[]).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+                transfer_instruction.clone(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                ],
             )
         );
 
-        // freeze
+        // transfers not preceded by a memo fail
+        let mut transfer_with_sysvar = transfer_instruction.clone();
+        transfer_with_sysvar
+            .accounts
+            .push(AccountMeta::new_readonly(
+                solana_program::sysvar::instructions::id(),
+                false,
+            ));
+        let memo_instruction = spl_memo::build_memo(b"deposit 42", &[]);
+        let mut instructions_account =
+            instructions_sysvar(&[transfer_with_sysvar.clone(), memo_instruction.clone()], 0);
+        assert_eq!(
+            Err(TokenError::NoMemo.into()),
+            do_process_instruction(
+                transfer_with_sysvar.clone(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                    &mut instructions_account,
+                ],
+            )
+        );
+
+        let mut instructions_account =
+            instructions_sysvar(&[memo_instruction.clone(), transfer_with_sysvar.clone()], 1);
         do_process_instruction(
-            freeze_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
-            vec![&mut account_account, &mut mint_account, &mut owner_account],
+            transfer_with_sysvar.clone(),
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut account2_account,
+                &mut owner_account,
+                &mut instructions_account,
+            ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.state, AccountState::Frozen);
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(account2.base.amount, 100);
 
-        // check explicit freeze
+        // the memo can't be tied to transfers made through a cross-program invocation
+        let cpi_instruction = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let mut instructions_account =
+            instructions_sysvar(&[memo_instruction.clone(), cpi_instruction], 1);
         assert_eq!(
-            Err(TokenError::InvalidState.into()),
+            Err(TokenError::MemoTransferNotTopLevel.into()),
             do_process_instruction(
-                freeze_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner_account],
+                transfer_with_sysvar.clone(),
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                    &mut instructions_account,
+                ],
             )
         );
 
-        // check thaw authority
+        // or inside a batch
+        let batch_instruction = batch(&program_id, &[transfer_with_sysvar]).unwrap();
+        let mut instructions_account =
+            instructions_sysvar(&[memo_instruction, batch_instruction.clone()], 1);
         assert_eq!(
-            Err(TokenError::OwnerMismatch.into()),
+            Err(TokenError::MemoTransferNotTopLevel.into()),
             do_process_instruction(
-                thaw_account(&program_id, &account_key, &mint_key, &owner2_key, &[]).unwrap(),
-                vec![&mut account_account, &mut mint_account, &mut owner2_account],
+                batch_instruction,
+                vec![
+                    &mut account_account,
+                    &mut mint_account,
+                    &mut account2_account,
+                    &mut owner_account,
+                    &mut instructions_account,
+                ],
             )
         );
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(account2.base.amount, 100);
 
-        // thaw
+        // memos are no longer required once disabled
         do_process_instruction(
-            thaw_account(&program_id, &account_key, &mint_key, &owner_key, &[]).unwrap(),
-            vec![&mut account_account, &mut mint_account, &mut owner_account],
+            disable_required_transfer_memos(&program_id, &account2_key, &owner2_key, &[]).unwrap(),
+            vec![&mut account2_account, &mut owner2_account],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&account_account.data).unwrap();
-        assert_eq!(account.state, AccountState::Initialized);
+        do_process_instruction(
+            transfer_instruction,
+            vec![
+                &mut account_account,
+                &mut mint_account,
+                &mut account2_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+        let account2 = StateWithExtensions::<Account>::unpack(&account2_account.data).unwrap();
+        assert_eq!(account2.base.amount, 200);
     }
 
     #[test]
-    fn test_initialize_account2_and_3() {
+    fn test_default_account_state() {
         let program_id = crate::id();
-        let account_key = Pubkey::new_unique();
-        let mut account_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let mut account2_account = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
+        let mint_key = Pubkey::new_unique();
+        let mint_len =
+            ExtensionType::get_account_len::<Mint>(&[ExtensionType::DefaultAccountState]);
+        let mut mint_account = SolanaAccount::new(
+            Rent::default().minimum_balance(mint_len),
+            mint_len,
             &program_id,
         );
-        let mut account3_account = SolanaAccount::new(
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
             account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
-        let mint_key = Pubkey::new_unique();
-        let mut mint_account =
-            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let mut rent_sysvar = rent_sysvar();
+        let freeze_authority_key = Pubkey::new_unique();
+        let mut freeze_authority_account = SolanaAccount::default();
 
-        // create mint
+        // new accounts can't start uninitialized
+        assert_eq!(
+            Err(TokenError::InvalidState.into()),
+            do_process_instruction(
+                initialize_default_account_state(
+                    &program_id,
+                    &mint_key,
+                    &AccountState::Uninitialized
+                )
+                .unwrap(),
+                vec![&mut mint_account],
+            )
+        );
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            initialize_default_account_state(&program_id, &mint_key, &AccountState::Frozen)
+                .unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+
+        // frozen accounts need a freeze authority to thaw them
+        assert_eq!(
+            Err(TokenError::MintCannotFreeze.into()),
+            do_process_instruction(
+                initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+                vec![&mut mint_account],
+            )
+        );
+        do_process_instruction(
+            initialize_mint2(
+                &program_id,
+                &mint_key,
+                &owner_key,
+                Some(&freeze_authority_key),
+                2,
+            )
+            .unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+
+        do_process_instruction(
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
         )
         .unwrap();
+        let account = Account::unpack_unchecked(&account_account.data).unwrap();
+        assert_eq!(account.state, AccountState::Frozen);
+        assert_eq!(
+            Err(TokenError::AccountFrozen.into()),
+            do_process_instruction(
+                mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 100).unwrap(),
+                vec![&mut mint_account, &mut account_account, &mut owner_account],
+            )
+        );
 
         do_process_instruction(
-            initialize_account(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            thaw_account(
+                &program_id,
+                &account_key,
+                &mint_key,
+                &freeze_authority_key,
+                &[],
+            )
+            .unwrap(),
             vec![
                 &mut account_account,
                 &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
+                &mut freeze_authority_account,
             ],
         )
         .unwrap();
-
-        do_process_instruction(
-            initialize_account2(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-            vec![&mut account2_account, &mut mint_account, &mut rent_sysvar],
-        )
-        .unwrap();
-
-        assert_eq!(account_account, account2_account);
-
         do_process_instruction(
-            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
-            vec![&mut account3_account, &mut mint_account],
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 100).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        assert_eq!(account_account, account3_account);
+        // the freeze authority can't be disabled while accounts start frozen
+        assert_eq!(
+            Err(TokenError::InvalidState.into()),
+            do_process_instruction(
+                set_authority(
+                    &program_id,
+                    &mint_key,
+                    None,
+                    AuthorityType::FreezeAccount,
+                    &freeze_authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut mint_account, &mut freeze_authority_account],
+            )
+        );
     }
 
     #[test]
-    fn test_sync_native() {
+    fn test_reallocate() {
         let program_id = crate::id();
         let mint_key = Pubkey::new_unique();
         let mut mint_account =
             SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
-        let native_account_key = Pubkey::new_unique();
-        let lamports = 40;
-        let mut native_account = SolanaAccount::new(
-            account_minimum_balance() + lamports,
-            Account::get_packed_len(),
-            &program_id,
-        );
-        let non_native_account_key = Pubkey::new_unique();
-        let mut non_native_account = SolanaAccount::new(
-            account_minimum_balance() + 50,
+        let account_key = Pubkey::new_unique();
+        let mut account_account = SolanaAccount::new(
+            account_minimum_balance(),
             Account::get_packed_len(),
             &program_id,
         );
-
         let owner_key = Pubkey::new_unique();
         let mut owner_account = SolanaAccount::default();
-        let mut rent_sysvar = rent_sysvar();
+        let owner2_key = Pubkey::new_unique();
+        let mut owner2_account = SolanaAccount::default();
+        let payer_key = Pubkey::new_unique();
+        let mut payer_account = SolanaAccount::new(1_000_000_000, 0, &system_program::id());
+        let mut system_program_account = SolanaAccount::default();
 
-        // initialize non-native mint
         do_process_instruction(
-            initialize_mint(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar],
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![&mut mint_account],
         )
         .unwrap();
-
-        // initialize non-native account
         do_process_instruction(
-            initialize_account(&program_id, &non_native_account_key, &mint_key, &owner_key)
-                .unwrap(),
-            vec![
-                &mut non_native_account,
-                &mut mint_account,
-                &mut owner_account,
-                &mut rent_sysvar,
-            ],
+            initialize_account3(&program_id, &account_key, &mint_key, &owner_key).unwrap(),
+            vec![&mut account_account, &mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            mint_to(&program_id, &mint_key, &account_key, &owner_key, &[], 1_000).unwrap(),
+            vec![&mut mint_account, &mut account_account, &mut owner_account],
         )
         .unwrap();
 
-        let account = Account::unpack_unchecked(&non_native_account.data).unwrap();
-        assert!(!account.is_native());
-        assert_eq!(account.amount, 0);
-
-        // fail sync non-native
+        // only account extensions can be added
         assert_eq!(
-            Err(TokenError::NonNativeNotSupported.into()),
-            do_process_instruction(
-                sync_native(&program_id, &non_native_account_key,).unwrap(),
-                vec![&mut non_native_account],
+            Err(ProgramError::InvalidInstructionData),
+            do_process_instruction_with_realloc(
+                reallocate(
+                    &program_id,
+                    &account_key,
+                    &payer_key,
+                    &owner_key,
+                    &[],
+                    &[ExtensionType::TransferFeeConfig],
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut payer_account,
+                    &mut system_program_account,
+                    &mut owner_account,
+                ],
             )
         );
 
-        // fail sync uninitialized
+        // only the owner can reallocate
         assert_eq!(
-            Err(ProgramError::UninitializedAccount),
-            do_process_instruction(
-                sync_native(&program_id, &native_account_key,).unwrap(),
-                vec![&mut native_account],
+            Err(TokenError::OwnerMismatch.into()),
+            do_process_instruction_with_realloc(
+                reallocate(
+                    &program_id,
+                    &account_key,
+                    &payer_key,
+                    &owner2_key,
+                    &[],
+                    &[ExtensionType::MemoTransfer],
+                )
+                .unwrap(),
+                vec![
+                    &mut account_account,
+                    &mut payer_account,
+                    &mut system_program_account,
+                    &mut owner2_account,
+                ],
+            )
+        );
+        let mut instruction = reallocate(
+            &program_id,
+            &account_key,
+            &payer_key,
+            &owner_key,
+            &[],
+            &[ExtensionType::MemoTransfer],
+        )
+        .unwrap();
+        instruction.accounts[3].is_signer = false;
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            do_process_instruction_with_realloc(
+                instruction,
+                vec![
+                    &mut account_account,
+                    &mut payer_account,
+                    &mut system_program_account,
+                    &mut owner_account,
+                ],
             )
         );
+        assert_eq!(account_account.data.len(), Account::LEN);
 
-        // wrap native account
-        do_process_instruction(
-            initialize_account(
+        // the legacy account grows in place, with the payer topping up its rent
+        let account_len = ExtensionType::get_account_len::<Account>(&[ExtensionType::MemoTransfer]);
+        let payer_lamports = payer_account.lamports;
+        do_process_instruction_with_realloc(
+            reallocate(
                 &program_id,
-                &native_account_key,
-                &crate::native_mint::id(),
+                &account_key,
+                &payer_key,
                 &owner_key,
+                &[],
+                &[ExtensionType::MemoTransfer, ExtensionType::MemoTransfer],
             )
             .unwrap(),
             vec![
-                &mut native_account,
-                &mut mint_account,
+                &mut account_account,
+                &mut payer_account,
+                &mut system_program_account,
                 &mut owner_account,
-                &mut rent_sysvar,
             ],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&native_account.data).unwrap();
-        assert!(account.is_native());
-        assert_eq!(account.amount, lamports);
+        assert_eq!(account_account.data.len(), account_len);
+        assert_eq!(
+            account_account.lamports,
+            Rent::default().minimum_balance(account_len)
+        );
+        assert_eq!(
+            payer_account.lamports,
+            payer_lamports - (account_account.lamports - account_minimum_balance())
+        );
+        assert_eq!(
+            get_account_type(&account_account.data).unwrap(),
+            AccountType::Account
+        );
+        let account = StateWithExtensions::<Account>::unpack(&account_account.data).unwrap();
+        assert_eq!(account.base.amount, 1_000);
+        assert_eq!(account.get_extension_types().unwrap(), vec![]);
 
-        // sync, no change
+        // asking again for room that is already there does nothing
+        let payer_lamports = payer_account.lamports;
+        do_process_instruction_with_realloc(
+            reallocate(
+                &program_id,
+                &account_key,
+                &payer_key,
+                &owner_key,
+                &[],
+                &[ExtensionType::MemoTransfer],
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut payer_account,
+                &mut system_program_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+        assert_eq!(account_account.data.len(), account_len);
+        assert_eq!(payer_account.lamports, payer_lamports);
+
+        // the new room holds the extension
         do_process_instruction(
-            sync_native(&program_id, &native_account_key).unwrap(),
-            vec![&mut native_account],
+            enable_required_transfer_memos(&program_id, &account_key, &owner_key, &[]).unwrap(),
+            vec![&mut account_account, &mut owner_account],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&native_account.data).unwrap();
-        assert_eq!(account.amount, lamports);
 
-        // transfer sol
-        let new_lamports = lamports + 50;
-        native_account.lamports = account_minimum_balance() + new_lamports;
+        // existing extensions are kept when making room for more
+        do_process_instruction_with_realloc(
+            reallocate(
+                &program_id,
+                &account_key,
+                &payer_key,
+                &owner_key,
+                &[],
+                &[ExtensionType::TransferFeeAmount],
+            )
+            .unwrap(),
+            vec![
+                &mut account_account,
+                &mut payer_account,
+                &mut system_program_account,
+                &mut owner_account,
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            account_account.data.len(),
+            ExtensionType::get_account_len::<Account>(&[
+                ExtensionType::MemoTransfer,
+                ExtensionType::TransferFeeAmount
+            ])
+        );
+        let account = StateWithExtensions::<Account>::unpack(&account_account.data).unwrap();
+        assert_eq!(
+            account.get_extension::<MemoTransfer>().unwrap(),
+            MemoTransfer {
+                require_incoming_transfer_memos: true
+            }
+        );
+        assert_eq!(account.base.amount, 1_000);
+    }
 
-        // success sync
-        do_process_instruction(
-            sync_native(&program_id, &native_account_key).unwrap(),
-            vec![&mut native_account],
+    #[test]
+    fn test_batch() {
+        let program_id = crate::id();
+        let mint_key = Pubkey::new_unique();
+        let mut mint_account =
+            SolanaAccount::new(mint_minimum_balance(), Mint::get_packed_len(), &program_id);
+        let mint_info: AccountInfo = (&mint_key, false, &mut mint_account).into();
+        let account1_key = Pubkey::new_unique();
+        let mut account1_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account1_info: AccountInfo = (&account1_key, false, &mut account1_account).into();
+        let account2_key = Pubkey::new_unique();
+        let mut account2_account = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &program_id,
+        );
+        let account2_info: AccountInfo = (&account2_key, false, &mut account2_account).into();
+        let owner_key = Pubkey::new_unique();
+        let mut owner_account = SolanaAccount::default();
+        let owner_info: AccountInfo = (&owner_key, true, &mut owner_account).into();
+        let dest_key = Pubkey::new_unique();
+        let mut dest_account = SolanaAccount::default();
+        let dest_info: AccountInfo = (&dest_key, false, &mut dest_account).into();
+
+        do_process_instruction_dups(
+            initialize_mint2(&program_id, &mint_key, &owner_key, None, 2).unwrap(),
+            vec![mint_info.clone()],
         )
         .unwrap();
-        let account = Account::unpack_unchecked(&native_account.data).unwrap();
-        assert_eq!(account.amount, new_lamports);
 
-        // reduce sol
-        native_account.lamports -= 1;
+        // create, fund and close accounts in one instruction
+        let instruction = batch(
+            &program_id,
+            &[
+                initialize_account3(&program_id, &account1_key, &mint_key, &owner_key).unwrap(),
+                initialize_account3(&program_id, &account2_key, &mint_key, &owner_key).unwrap(),
+                mint_to(
+                    &program_id,
+                    &mint_key,
+                    &account1_key,
+                    &owner_key,
+                    &[],
+                    1_000,
+                )
+                .unwrap(),
+                transfer(
+                    &program_id,
+                    &account1_key,
+                    &account2_key,
+                    &owner_key,
+                    &[],
+                    1_000,
+                )
+                .unwrap(),
+                close_account(&program_id, &account1_key, &dest_key, &owner_key, &[]).unwrap(),
+            ],
+        )
+        .unwrap();
+        let account_infos = vec![
+            account1_info.clone(),
+            mint_info.clone(),
+            account2_info.clone(),
+            mint_info.clone(),
+            mint_info.clone(),
+            account1_info.clone(),
+            owner_info.clone(),
+            account1_info.clone(),
+            account2_info.clone(),
+            owner_info.clone(),
+            account1_info.clone(),
+            dest_info.clone(),
+            owner_info.clone(),
+        ];
+        assert_eq!(instruction.accounts.len(), account_infos.len());
 
-        // fail sync
+        // every sub-instruction needs its accounts
         assert_eq!(
-            Err(TokenError::InvalidState.into()),
-            do_process_instruction(
-                sync_native(&program_id, &native_account_key,).unwrap(),
-                vec![&mut native_account],
-            )
+            Err(ProgramError::NotEnoughAccountKeys),
+            do_process_instruction_dups(instruction.clone(), vec![account1_info.clone()])
         );
+
+        // batches can't contain batches
+        assert_eq!(
+            Err(TokenError::NestedBatch.into()),
+            Processor::process_batch(
+                &program_id,
+                &[],
+                vec![BatchedInstruction {
+                    num_accounts: 0,
+                    instruction: TokenInstruction::Batch {
+                        instructions: vec![],
+                    },
+                }],
+            )
+        );
+
+        do_process_instruction_dups(instruction, account_infos).unwrap();
+        assert_eq!(account1_info.lamports(), 0);
+        assert_eq!(dest_info.lamports(), account_minimum_balance());
+        let account2 = Account::unpack(&account2_info.data.borrow()).unwrap();
+        assert_eq!(account2.amount, 1_000);
+        let mint = Mint::unpack(&mint_info.data.borrow()).unwrap();
+        assert_eq!(mint.supply, 1_000);
     }
 }